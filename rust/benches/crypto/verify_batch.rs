@@ -0,0 +1,48 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use xaynet_core::crypto::{verify_batch, PublicSigningKey, Signature, SigningKeyPair};
+
+// The size of the signed data of a sum message.
+const DATA_LENGTH: usize = 200;
+
+fn make_signatures(len: usize) -> Vec<(PublicSigningKey, Vec<u8>, Signature)> {
+    (0..len)
+        .map(|_| {
+            let keys = SigningKeyPair::generate();
+            let data = vec![0xff; DATA_LENGTH];
+            let signature = keys.secret.sign_detached(&data);
+            (keys.public, data, signature)
+        })
+        .collect()
+}
+
+fn verify(crit: &mut Criterion) {
+    sodiumoxide::init().unwrap();
+    let mut crit = crit.benchmark_group("verify message signatures");
+
+    for &len in &[1, 16, 64] {
+        let signatures = make_signatures(len);
+        let items = signatures
+            .iter()
+            .map(|(pk, data, signature)| (*pk, data.as_slice(), *signature))
+            .collect::<Vec<_>>();
+        crit.bench_with_input(
+            BenchmarkId::new("individual", len),
+            &items,
+            |bench, items| {
+                bench.iter(|| {
+                    items
+                        .iter()
+                        .map(|(pk, data, signature)| pk.verify_detached(signature, data))
+                        .collect::<Vec<_>>()
+                })
+            },
+        );
+        crit.bench_with_input(BenchmarkId::new("batch", len), &items, |bench, items| {
+            bench.iter(|| verify_batch(black_box(items)))
+        });
+    }
+}
+
+criterion_group!(bench_verify_batch, verify);
+criterion_main!(bench_verify_batch);
@@ -0,0 +1,41 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use xaynet_core::{
+    mask::{FromPrimitives, Masker, Model, Scalar},
+    message::{compression, ToBytes},
+    testutils::messages::mask::mask_config,
+};
+
+// Get a serialized masked model of 1MB of f32 weights
+#[allow(non_snake_case)]
+fn masked_model_1MB() -> Vec<u8> {
+    let model = Model::from_primitives(vec![0.5_f32; 1 << 18].into_iter()).unwrap();
+    let (_, masked_model) = Masker::new(mask_config().0.into()).mask(Scalar::unit(), &model);
+    let mut bytes = vec![0; masked_model.buffer_length()];
+    masked_model.to_bytes(&mut bytes);
+    bytes
+}
+
+#[allow(non_snake_case)]
+fn compress_1MB(crit: &mut Criterion) {
+    let bytes = masked_model_1MB();
+    let compressed = compression::compress(&bytes);
+
+    let mut crit = crit.benchmark_group("compress 1MB masked model");
+    crit.throughput(Throughput::Bytes(bytes.len() as u64));
+
+    crit.bench_function("compress 1MB masked model", |bench| {
+        bench.iter(|| compression::compress(black_box(&bytes)))
+    });
+
+    crit.bench_function("decompress 1MB masked model", |bench| {
+        bench.iter(|| compression::decompress(black_box(&compressed), bytes.len()))
+    });
+}
+
+criterion_group!(
+    name = bench_compression;
+    config = Criterion::default();
+    targets = compress_1MB,
+);
+criterion_main!(bench_compression);
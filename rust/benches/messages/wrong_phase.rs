@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+use xaynet_core::{
+    crypto::{EncryptKeyPair, SigningKeyPair},
+    message::{Message, MessageBuffer, Tag},
+    testutils::messages as helpers,
+};
+
+// number of wrong-phase messages in a batch
+const BATCH: usize = 10_000;
+
+/// Simulate a batch of update and sum2 messages sent by misbehaving
+/// participants during the sum phase. Each message is returned as it
+/// arrives at the coordinator: encrypted, along with the tag of its
+/// envelope.
+fn wrong_phase_messages(coordinator_keys: &EncryptKeyPair) -> Vec<(Tag, Vec<u8>)> {
+    let participant_keys = SigningKeyPair::generate();
+    let (update, _) = helpers::message(helpers::update::payload);
+    let (sum2, _) = helpers::message(helpers::sum2::payload);
+    [update, sum2]
+        .iter()
+        .cycle()
+        .take(BATCH)
+        .map(|message| {
+            let message = Message {
+                signature: None,
+                participant_pk: participant_keys.public,
+                coordinator_pk: coordinator_keys.public,
+                ..message.clone()
+            };
+            let mut buf = vec![0; message.buffer_length()];
+            message.to_bytes(&mut buf, &participant_keys.secret);
+            (message.tag, coordinator_keys.public.encrypt(&buf))
+        })
+        .collect()
+}
+
+fn is_sum(tag: u8) -> bool {
+    tag == u8::from(Tag::Sum)
+}
+
+fn discard_wrong_phase(crit: &mut Criterion) {
+    sodiumoxide::init().unwrap();
+    let coordinator_keys = EncryptKeyPair::generate();
+    let messages = wrong_phase_messages(&coordinator_keys);
+
+    let mut crit = crit.benchmark_group("discard 10k wrong-phase messages");
+    crit.sample_size(10);
+
+    // no filter: the messages are rejected by the state machine,
+    // once they are decrypted, verified and parsed
+    crit.bench_function("unfiltered", |bench| {
+        bench.iter_batched(
+            || messages.clone(),
+            |messages| {
+                for (_, enc) in messages {
+                    let raw = coordinator_keys
+                        .secret
+                        .decrypt(&enc, &coordinator_keys.public);
+                    let raw = raw.unwrap();
+                    MessageBuffer::new(&raw).unwrap().check_signature().unwrap();
+                    let message = Message::from_byte_slice(&raw).unwrap();
+                    black_box(message.tag == Tag::Sum);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    // legacy messages: the tag of the decrypted payload is checked
+    // before the signature is verified
+    crit.bench_function("payload tag", |bench| {
+        bench.iter_batched(
+            || messages.clone(),
+            |messages| {
+                for (_, enc) in messages {
+                    let raw = coordinator_keys
+                        .secret
+                        .decrypt(&enc, &coordinator_keys.public);
+                    let raw = raw.unwrap();
+                    black_box(is_sum(MessageBuffer::new(&raw).unwrap().tag()));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    // the tag of the envelope is checked before the message is
+    // decrypted
+    crit.bench_function("envelope tag", |bench| {
+        bench.iter_batched(
+            || messages.clone(),
+            |messages| {
+                for (tag, enc) in messages {
+                    black_box(is_sum(tag.into()));
+                    drop(enc);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(
+    name = bench_wrong_phase;
+    config = Criterion::default().measurement_time(Duration::new(15, 0));
+    targets = discard_wrong_phase,
+);
+criterion_main!(bench_wrong_phase);
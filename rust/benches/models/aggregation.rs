@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use xaynet_core::{
+    crypto::ByteObject,
+    mask::{
+        Aggregation,
+        BoundType,
+        DataType,
+        GroupType,
+        MaskConfig,
+        MaskConfigPair,
+        MaskObject,
+        MaskSeed,
+        ModelType,
+    },
+};
+
+fn make_config() -> MaskConfigPair {
+    MaskConfig {
+        group_type: GroupType::Prime,
+        data_type: DataType::F32,
+        bound_type: BoundType::B0,
+        model_type: ModelType::M3,
+    }
+    .into()
+}
+
+// This mirrors how the coordinator aggregates the masks of the sum participants: an
+// aggregation which already holds a mask is aggregated with another one.
+fn make_aggregation(len: usize) -> (Aggregation, MaskObject) {
+    let config = make_config();
+    let mut aggregation = Aggregation::new(config, len);
+    aggregation.aggregate(MaskSeed::generate().derive_mask(len, config));
+    let object = MaskSeed::generate().derive_mask(len, config);
+    (aggregation, object)
+}
+
+fn aggregation(crit: &mut Criterion) {
+    sodiumoxide::init().unwrap();
+    let mut crit = crit.benchmark_group("aggregate a masked model");
+    crit.sample_size(10)
+        .measurement_time(Duration::from_secs(10));
+
+    for &len in &[10_000, 100_000, 1_000_000] {
+        let (aggregation, object) = make_aggregation(len);
+        crit.bench_with_input(
+            BenchmarkId::new("sequential", len),
+            &(aggregation.clone(), object.clone()),
+            |bench, input| {
+                bench.iter_batched(
+                    || input.clone(),
+                    |(mut aggregation, object)| aggregation.aggregate(object),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+        crit.bench_with_input(
+            BenchmarkId::new("parallel", len),
+            &(aggregation, object),
+            |bench, input| {
+                bench.iter_batched(
+                    || input.clone(),
+                    |(mut aggregation, object)| aggregation.parallel_aggregate(object),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+}
+
+criterion_group!(bench_aggregation, aggregation);
+criterion_main!(bench_aggregation);
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use xaynet_core::{
+    crypto::ByteObject,
+    mask::{
+        BoundType,
+        DataType,
+        FromPrimitives,
+        GroupType,
+        MaskConfig,
+        MaskConfigPair,
+        MaskSeed,
+        Masker,
+        Model,
+        ModelType,
+        Scalar,
+    },
+};
+
+fn make_config() -> MaskConfigPair {
+    MaskConfig {
+        group_type: GroupType::Prime,
+        data_type: DataType::F32,
+        bound_type: BoundType::B0,
+        model_type: ModelType::M3,
+    }
+    .into()
+}
+
+fn make_model(len: usize) -> Model {
+    Model::from_primitives((0..len).map(|i| (i % 100) as f32 / 100.)).unwrap()
+}
+
+fn masking(crit: &mut Criterion) {
+    sodiumoxide::init().unwrap();
+    let mut crit = crit.benchmark_group("mask a model");
+    crit.sample_size(10)
+        .measurement_time(Duration::from_secs(10));
+
+    let config = make_config();
+    let seed = MaskSeed::generate();
+    let scalar = Scalar::new(1_u8, 2_u8);
+    for &len in &[10_000, 100_000, 1_000_000] {
+        let model = make_model(len);
+        crit.bench_with_input(
+            BenchmarkId::new("sequential", len),
+            &model,
+            |bench, model| {
+                bench.iter(|| Masker::with_seed(config, seed.clone()).mask(scalar.clone(), model))
+            },
+        );
+        crit.bench_with_input(BenchmarkId::new("parallel", len), &model, |bench, model| {
+            bench.iter(|| {
+                Masker::with_seed(config, seed.clone()).parallel_mask(scalar.clone(), model)
+            })
+        });
+    }
+}
+
+criterion_group!(bench_masking, masking);
+criterion_main!(bench_masking);
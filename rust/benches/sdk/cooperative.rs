@@ -0,0 +1,209 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use tokio::runtime::Runtime;
+
+use xaynet_core::{
+    common::{PhaseStats, RoundMetadata, RoundParameters, RoundSeed},
+    crypto::{ByteObject, EncryptKeyPair, SigningKeyPair},
+    mask::{BoundType, DataType, FromPrimitives, GroupType, MaskConfig, Model, ModelType},
+    message::Tag,
+    SumDict,
+    SumParticipantPublicKey,
+    UpdateSeedDict,
+};
+use xaynet_sdk::{
+    settings::{PetSettings, DEFAULT_YIELD_INTERVAL},
+    ModelStore,
+    Notify,
+    StateMachine,
+    TransitionOutcome,
+    XaynetClient,
+};
+
+const SUM_PARTICIPANTS: usize = 1_000;
+const MODEL_LENGTH: usize = 10_000;
+
+/// A coordinator which selects every participant for the update task and which serves the
+/// same sum dictionary in every round.
+#[derive(Clone)]
+struct Coordinator {
+    round_params: RoundParameters,
+    sum_dict: SumDict,
+    /// Whether the update message was sent.
+    updated: Arc<AtomicBool>,
+}
+
+impl Coordinator {
+    fn new() -> Self {
+        let config = MaskConfig {
+            group_type: GroupType::Prime,
+            data_type: DataType::F32,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        };
+        let round_params = RoundParameters {
+            pk: EncryptKeyPair::generate().public,
+            sum: 0.,
+            update: 1.,
+            seed: RoundSeed::generate(),
+            mask_config: config.into(),
+            model_length: MODEL_LENGTH,
+            plan_id: None,
+            max_sample_count: None,
+        };
+        let sum_dict = (0..SUM_PARTICIPANTS)
+            .map(|_| {
+                (
+                    SigningKeyPair::generate().public,
+                    EncryptKeyPair::generate().public,
+                )
+            })
+            .collect();
+        Self {
+            round_params,
+            sum_dict,
+            updated: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+#[async_trait]
+impl XaynetClient for Coordinator {
+    type Error = io::Error;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        Ok(self.round_params.clone())
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        Err(io::ErrorKind::NotFound.into())
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        Err(io::ErrorKind::NotFound.into())
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        Ok(Some(self.sum_dict.clone()))
+    }
+
+    async fn get_seeds(
+        &mut self,
+        _pk: SumParticipantPublicKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_model_by_id(&mut self, _id: &str) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn send_message(&mut self, tag: Tag, _msg: Vec<u8>) -> Result<(), Self::Error> {
+        if tag == Tag::Update {
+            self.updated.store(true, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+}
+
+/// A store whose local model is always ready.
+struct Store(Arc<Model>);
+
+#[async_trait]
+impl ModelStore for Store {
+    type Error = io::Error;
+    type Model = Arc<Model>;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+struct Notifier;
+
+impl Notify for Notifier {}
+
+/// Runs the state machine of a participant through a round, from fetching the round
+/// parameters to sending the update message. The participant masks its model and encrypts
+/// its mask seed for every sum participant, yielding back to the executor every
+/// `yield_interval` sum participants.
+async fn run_update_task(mut state_machine: StateMachine, updated: Arc<AtomicBool>) {
+    while !updated.load(Ordering::SeqCst) {
+        state_machine = match state_machine.transition().await {
+            TransitionOutcome::Pending(state_machine) => state_machine,
+            TransitionOutcome::Complete(state_machine) => state_machine,
+        };
+    }
+}
+
+fn update_task(crit: &mut Criterion) {
+    sodiumoxide::init().unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let coordinator = Coordinator::new();
+    let model = Model::from_primitives(vec![0.5_f32; MODEL_LENGTH].into_iter()).unwrap();
+    let model = Arc::new(model);
+
+    let mut crit = crit.benchmark_group(format!(
+        "run the update task with {} sum participants and a model of {} weights",
+        SUM_PARTICIPANTS, MODEL_LENGTH,
+    ));
+    bench_yield_interval(&mut crit, &runtime, &coordinator, &model, 0);
+    bench_yield_interval(
+        &mut crit,
+        &runtime,
+        &coordinator,
+        &model,
+        DEFAULT_YIELD_INTERVAL,
+    );
+}
+
+fn bench_yield_interval(
+    crit: &mut criterion::BenchmarkGroup<criterion::measurement::WallTime>,
+    runtime: &Runtime,
+    coordinator: &Coordinator,
+    model: &Arc<Model>,
+    yield_interval: usize,
+) {
+    crit.bench_function(format!("yield interval {}", yield_interval), |bench| {
+        bench.iter_batched(
+            || {
+                let mut settings = PetSettings::new(SigningKeyPair::generate());
+                settings.yield_interval = yield_interval;
+                let coordinator = Coordinator {
+                    updated: Arc::new(AtomicBool::new(false)),
+                    ..coordinator.clone()
+                };
+                let updated = coordinator.updated.clone();
+                let state_machine =
+                    StateMachine::new(settings, coordinator, Store(model.clone()), Notifier);
+                (state_machine, updated)
+            },
+            |(state_machine, updated)| {
+                runtime.block_on(run_update_task(black_box(state_machine), updated))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(
+    name = bench_cooperative;
+    config = Criterion::default().measurement_time(Duration::new(10, 0));
+    targets = update_task
+);
+criterion_main!(bench_cooperative);
@@ -12,6 +12,8 @@ use xaynet_core::{
 use xaynet_sdk::{
     client::{Client, ClientError},
     settings::PetSettings,
+    Backoff,
+    BackoffConfig,
 };
 
 mod participant;
@@ -67,6 +69,15 @@ fn build_http_client(settings: &settings::Opt) -> reqwest::Client {
     builder.build().unwrap()
 }
 
+fn build_backoff(settings: &settings::Opt) -> Backoff {
+    Backoff::new(BackoffConfig {
+        initial_interval: Duration::from_secs(settings.period),
+        max_interval: Duration::from_secs(settings.max_period.unwrap_or(settings.period)),
+        multiplier: settings.multiplier,
+        jitter: settings.jitter,
+    })
+}
+
 fn spawn_participant(
     id: u32,
     settings: &settings::Opt,
@@ -76,6 +87,7 @@ fn spawn_participant(
     let http_client = build_http_client(settings);
     let client = Client::new(http_client, &settings.url).unwrap();
 
+    let backoff = build_backoff(settings);
     let (participant, agent) = participant::Participant::new(config, client, model);
     tokio::spawn(async move {
         participant
@@ -85,7 +97,7 @@ fn spawn_participant(
     });
     tokio::spawn(async move {
         agent
-            .run(Duration::from_secs(1))
+            .run(backoff)
             .instrument(error_span!("agent", id = id))
             .await;
     });
@@ -1,27 +1,24 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use async_trait::async_trait;
-use tokio::{sync::mpsc, time::sleep};
-use tracing::{info, warn};
+use futures::StreamExt;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
 
 use xaynet_core::mask::Model;
 use xaynet_sdk::{
     client::Client,
     settings::PetSettings,
+    Backoff,
+    Event,
+    EventStream,
+    EventStreamConfig,
     ModelStore,
-    Notify,
     StateMachine,
     TransitionOutcome,
     XaynetClient,
 };
 
-enum Event {
-    Update,
-    Sum,
-    NewRound,
-    Idle,
-}
-
 pub struct Participant {
     // FIXME: XaynetClient requires the client to be mutable. This may
     // make it easier to implement clients, but as a result we can't
@@ -29,34 +26,23 @@ pub struct Participant {
     // same client with all the participants. Maybe XaynetClient
     // should have methods that take &self?
     xaynet_client: Client<reqwest::Client>,
-    notifications: mpsc::Receiver<Event>,
+    notifications: EventStream,
 }
 
 pub struct Agent(StateMachine);
 
 impl Agent {
-    fn new<X, M, N>(settings: PetSettings, xaynet_client: X, model_store: M, notify: N) -> Self
-    where
-        X: XaynetClient + Send + 'static,
-        M: ModelStore + Send + 'static,
-        N: Notify + Send + 'static,
-    {
-        Agent(StateMachine::new(
-            settings,
-            xaynet_client,
-            model_store,
-            notify,
-        ))
-    }
-
-    pub async fn run(mut self, tick: Duration) {
+    pub async fn run(mut self, mut backoff: Backoff) {
         loop {
             self = match self.0.transition().await {
                 TransitionOutcome::Pending(state_machine) => {
-                    sleep(tick).await;
+                    sleep(backoff.next_interval()).await;
+                    Self(state_machine)
+                }
+                TransitionOutcome::Complete(state_machine) => {
+                    backoff.reset();
                     Self(state_machine)
                 }
-                TransitionOutcome::Complete(state_machine) => Self(state_machine),
             };
         }
     }
@@ -68,20 +54,23 @@ impl Participant {
         xaynet_client: Client<reqwest::Client>,
         model: Arc<Model>,
     ) -> (Self, Agent) {
-        let (tx, rx) = mpsc::channel::<Event>(10);
-        let notifier = Notifier(tx);
-        let agent = Agent::new(settings, xaynet_client.clone(), LocalModel(model), notifier);
+        let (state_machine, notifications) = StateMachine::with_event_stream(
+            settings,
+            xaynet_client.clone(),
+            LocalModel(model),
+            EventStreamConfig::default(),
+        );
         let participant = Self {
             xaynet_client,
-            notifications: rx,
+            notifications,
         };
-        (participant, agent)
+        (participant, Agent(state_machine))
     }
 
     pub async fn run(mut self) {
         use Event::*;
         loop {
-            match self.notifications.recv().await {
+            match self.notifications.next().await {
                 Some(Sum) => {
                     info!("taking part in the sum task");
                 }
@@ -97,8 +86,27 @@ impl Participant {
                         warn!("failed to download latest model: {}", e);
                     }
                 }
+                Some(RoundIdChanged(round_id)) => {
+                    info!("round {} started", round_id);
+                }
+                Some(TrainingPlan(plan_id)) => {
+                    info!("the round belongs to the training plan {}", plan_id);
+                }
+                Some(QuotaExceeded) => {
+                    warn!("update participation quota exceeded, waiting for the next round");
+                }
+                Some(InsufficientTime) => {
+                    warn!("not enough time remaining in the phase, waiting for the next round");
+                }
+                Some(EphemeralKeysMismatch) => {
+                    error!("ephemeral keys don't match the sent public key, waiting for the next round");
+                }
+                Some(ModelUnchanged) => {
+                    info!("the global model didn't change since the previous round");
+                }
+                Some(LoadModel) | Some(Sum2MaskReady) | Some(AwaitingConsent(_)) => {}
                 None => {
-                    warn!("notifications channel closed, terminating");
+                    warn!("notifications stream ended, terminating");
                     return;
                 }
             }
@@ -106,34 +114,6 @@ impl Participant {
     }
 }
 
-struct Notifier(mpsc::Sender<Event>);
-
-impl Notify for Notifier {
-    fn new_round(&mut self) {
-        if let Err(e) = self.0.try_send(Event::NewRound) {
-            warn!("failed to notify participant: {}", e);
-        }
-    }
-
-    fn sum(&mut self) {
-        if let Err(e) = self.0.try_send(Event::Sum) {
-            warn!("failed to notify participant: {}", e);
-        }
-    }
-
-    fn update(&mut self) {
-        if let Err(e) = self.0.try_send(Event::Update) {
-            warn!("failed to notify participant: {}", e);
-        }
-    }
-
-    fn idle(&mut self) {
-        if let Err(e) = self.0.try_send(Event::Idle) {
-            warn!("failed to notify participant: {}", e);
-        }
-    }
-}
-
 pub struct LocalModel(Arc<Model>);
 
 #[async_trait]
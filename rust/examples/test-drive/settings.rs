@@ -22,6 +22,22 @@ pub struct Opt {
     )]
     pub period: u64,
 
+    #[structopt(
+        long,
+        help = "The maximum time period at which to poll for service data while the clients are waiting, in seconds [default: the period]"
+    )]
+    pub max_period: Option<u64>,
+
+    #[structopt(
+        default_value = "1",
+        long,
+        help = "The factor by which the polling period grows while the clients are waiting"
+    )]
+    pub multiplier: f64,
+
+    #[structopt(long, help = "Randomize the polling periods of the clients")]
+    pub jitter: bool,
+
     #[structopt(default_value = "10", short, help = "The number of clients")]
     pub nb_client: u32,
 
@@ -0,0 +1,567 @@
+//! An end-to-end test of a complete round between a coordinator and participants of the SDK.
+//!
+//! The coordinator derives its round keys from a fixed seed and every participant has fixed
+//! signing keys and derives its randomness from a fixed seed, hence a round is reproducible
+//! byte for byte. The participants talk to the REST API of the coordinator over HTTP, and the
+//! bodies of all their requests and responses are recorded. Since the participants poll the
+//! coordinator, the number of requests depends on the timing and the full wire transcripts
+//! can't be compared. They are written to `target/golden/round/` for inspection, while the
+//! following stable artifacts are compared against the golden files in `golden/round/`:
+//! - the hash of the update message payload of each update participant,
+//! - the global model of the round,
+//! - the sequence of notifications of each participant during the round.
+//!
+//! Any change of the protocol or of the wire format makes the test fail. If the change is
+//! intentional, the golden files are regenerated with:
+//!
+//! ```text
+//! XAYNET_UPDATE_GOLDEN=1 cargo test -p tests --test golden_round
+//! ```
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{
+    task::JoinHandle,
+    time::{sleep, timeout},
+};
+
+use xaynet_core::{
+    common::RoundParameters,
+    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, Sha256, SigningKeyPair, SigningKeySeed},
+    mask::{BoundType, DataType, FromPrimitives, GroupType, Model, ModelType},
+    message::{Message, Tag, ToBytes},
+};
+use xaynet_sdk::{
+    client::{Client, ClientError, XaynetHttpClient},
+    settings::{DeterministicSeed, PetSettings as ParticipantSettings},
+    ModelStore,
+    Notify,
+    StateMachine as Participant,
+    TransitionOutcome,
+};
+use xaynet_server::{
+    rest::serve,
+    services,
+    settings::{
+        ApiSettings,
+        MaskSettings,
+        ModelSettings,
+        PetSettings,
+        PetSettingsCount,
+        PetSettingsSum,
+        PetSettingsSum2,
+        PetSettingsTime,
+        PetSettingsUpdate,
+    },
+    state_machine::{
+        events::{EventSubscriber, ModelUpdate},
+        initializer::StateMachineInitializer,
+        requests::RequestSender,
+        StateMachine,
+    },
+    storage::{
+        coordinator_storage::in_memory,
+        model_storage::noop::NoOp,
+        trust_anchor::noop::NoOp as NoTrustAnchor,
+        Store,
+    },
+};
+
+/// The environment variable which regenerates the golden files.
+const UPDATE_GOLDEN: &str = "XAYNET_UPDATE_GOLDEN";
+
+/// The number of participants.
+const PARTICIPANTS: u8 = 6;
+
+/// The length of the models.
+const MODEL_LENGTH: usize = 4;
+
+/// The seed of the round keys of the coordinator.
+const COORDINATOR_SEED: [u8; 32] = [0xc0; 32];
+
+/// The interval at which the participants poll the coordinator.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The maximum duration of the round.
+const ROUND_TIMEOUT: Duration = Duration::from_secs(120);
+
+type CoordinatorStore = Store<in_memory::Client, NoOp, NoTrustAnchor>;
+type Coordinator = StateMachine<CoordinatorStore>;
+
+/// The number of participants selected for each task.
+#[derive(Debug, Clone, Copy)]
+struct Selection {
+    sum: u64,
+    update: u64,
+}
+
+/// The PET settings of the coordinator. Each phase ends as soon as all the selected
+/// participants sent their message, such that no message is ever discarded.
+fn pet_settings(selection: Selection) -> PetSettings {
+    let count = |count| PetSettingsCount {
+        min: count,
+        max: count,
+    };
+    let time = PetSettingsTime { min: 0, max: 60 };
+    PetSettings {
+        sum: PetSettingsSum {
+            prob: 0.4,
+            count: count(selection.sum),
+            time,
+        },
+        update: PetSettingsUpdate {
+            prob: 1.,
+            count: count(selection.update),
+            time,
+            quota: None,
+            max_sample_count: None,
+        },
+        sum2: PetSettingsSum2 {
+            count: count(selection.sum),
+            time,
+            mask_tolerance: None,
+        },
+    }
+}
+
+/// Initializes a coordinator with deterministic round keys and runs its idle phase, such that
+/// the parameters of the first round are published.
+async fn init_coordinator(
+    selection: Selection,
+) -> (
+    Coordinator,
+    RequestSender,
+    EventSubscriber,
+    CoordinatorStore,
+) {
+    let store = Store::new(in_memory::Client::new(), NoOp);
+    let (state_machine, requests_tx, event_subscriber) = StateMachineInitializer::new(
+        pet_settings(selection),
+        MaskSettings {
+            group_type: GroupType::Prime,
+            data_type: DataType::F32,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        },
+        ModelSettings {
+            length: MODEL_LENGTH,
+            update_statistics: false,
+            export_round_archive: false,
+        },
+        None,
+        store.clone(),
+    )
+    .with_deterministic_keys(EncryptKeySeed::from_slice_unchecked(&COORDINATOR_SEED))
+    .init()
+    .await
+    .unwrap();
+    let state_machine = state_machine.next().await.unwrap();
+    (state_machine, requests_tx, event_subscriber, store)
+}
+
+/// The signing keys of the `id`-th participant.
+fn participant_keys(id: u8) -> SigningKeyPair {
+    SigningKeyPair::derive_from_seed(&SigningKeySeed::from_slice_unchecked(&[id; 32]))
+}
+
+/// Counts the participants selected for each task, as the participants decide it.
+fn select(params: &RoundParameters) -> Selection {
+    let mut selection = Selection { sum: 0, update: 0 };
+    for id in 0..PARTICIPANTS {
+        let sk = participant_keys(id).secret;
+        let sign = |task: &[u8]| sk.sign_detached(&[params.seed.as_slice(), task].concat());
+        if sign(b"sum").is_eligible(params.sum) {
+            selection.sum += 1;
+        } else if sign(b"update").is_eligible(params.update) {
+            selection.update += 1;
+        }
+    }
+    selection
+}
+
+/// A request of a participant and the response of the coordinator.
+struct Exchange {
+    method: &'static str,
+    url: String,
+    request: Vec<u8>,
+    response: Result<Option<Vec<u8>>, String>,
+}
+
+/// The wire transcript of a participant.
+#[derive(Clone, Default)]
+struct Wire(Arc<Mutex<Vec<Exchange>>>);
+
+impl Wire {
+    fn record(&self, exchange: Exchange) {
+        self.0.lock().unwrap().push(exchange);
+    }
+
+    /// The bodies of the messages with the given tag which the coordinator accepted.
+    fn messages(&self, tag: Tag) -> Vec<Vec<u8>> {
+        let query = format!("/message?tag={}", u8::from(tag));
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|exchange| exchange.url.ends_with(&query) && exchange.response.is_ok())
+            .map(|exchange| exchange.request.clone())
+            .collect()
+    }
+
+    fn dump(&self) -> String {
+        let mut dump = String::new();
+        for exchange in self.0.lock().unwrap().iter() {
+            let response = match exchange.response {
+                Ok(Some(ref body)) => format!("ok {}", hex::encode(body)),
+                Ok(None) => "ok".to_string(),
+                Err(ref err) => format!("error {}", err),
+            };
+            // UNWRAP_SAFE: writing to a string doesn't fail
+            writeln!(
+                dump,
+                "{} {} {}\n  -> {}",
+                exchange.method,
+                exchange.url,
+                hex::encode(&exchange.request),
+                response,
+            )
+            .unwrap();
+        }
+        dump
+    }
+}
+
+/// An HTTP client which records the wire transcript of a participant.
+struct WireRecorder {
+    client: reqwest::Client,
+    wire: Wire,
+}
+
+#[async_trait]
+impl XaynetHttpClient for WireRecorder {
+    type Error = reqwest::Error;
+    type GetResponse = Vec<u8>;
+
+    async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        let response = XaynetHttpClient::get(&mut self.client, url)
+            .await
+            .map(|body| body.map(|body| body.to_vec()));
+        self.wire.record(Exchange {
+            method: "GET",
+            url: url.to_string(),
+            request: Vec::new(),
+            response: response
+                .as_ref()
+                .map(Clone::clone)
+                .map_err(ToString::to_string),
+        });
+        response
+    }
+
+    async fn post(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        let response = XaynetHttpClient::post(&mut self.client, url, body.clone()).await;
+        self.wire.record(Exchange {
+            method: "POST",
+            url: url.to_string(),
+            request: body,
+            response: response.as_ref().map(|_| None).map_err(ToString::to_string),
+        });
+        response
+    }
+}
+
+/// The notifications of a participant.
+#[derive(Clone, Default)]
+struct Notifications(Arc<Mutex<Vec<&'static str>>>);
+
+impl Notifications {
+    fn push(&self, notification: &'static str) {
+        self.0.lock().unwrap().push(notification);
+    }
+
+    /// The notifications of the round, once the participant completed it and went idle.
+    fn completed(&self) -> Option<Vec<&'static str>> {
+        let notifications = self.0.lock().unwrap();
+        let completed =
+            notifications.contains(&"new_round") && notifications.last() == Some(&"idle");
+        completed.then(|| notifications.clone())
+    }
+}
+
+impl Notify for Notifications {
+    fn new_round(&mut self) {
+        self.push("new_round");
+    }
+    fn sum(&mut self) {
+        self.push("sum");
+    }
+    fn update(&mut self) {
+        self.push("update");
+    }
+    fn idle(&mut self) {
+        self.push("idle");
+    }
+    fn load_model(&mut self) {
+        self.push("load_model");
+    }
+    fn sum2_mask_ready(&mut self) {
+        self.push("sum2_mask_ready");
+    }
+    fn quota_exceeded(&mut self) {
+        self.push("quota_exceeded");
+    }
+    fn awaiting_consent(&mut self, _request: xaynet_sdk::ConsentRequest) {
+        self.push("awaiting_consent");
+    }
+    fn insufficient_time(&mut self) {
+        self.push("insufficient_time");
+    }
+}
+
+/// The constant model of a participant.
+struct LocalModel(Arc<Model>);
+
+#[async_trait]
+impl ModelStore for LocalModel {
+    type Model = Arc<Model>;
+    type Error = std::convert::Infallible;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// A participant of the round and what it recorded.
+struct Recorded {
+    handle: JoinHandle<()>,
+    wire: Wire,
+    notifications: Notifications,
+}
+
+/// Spawns the `id`-th participant, whose weights are all `(id + 1) / PARTICIPANTS`.
+fn spawn_participant(id: u8, url: &str) -> Recorded {
+    let wire = Wire::default();
+    let notifications = Notifications::default();
+    let http_client = WireRecorder {
+        client: reqwest::Client::new(),
+        wire: wire.clone(),
+    };
+    let client = Client::new(http_client, url).unwrap();
+
+    let mut settings = ParticipantSettings::new(participant_keys(id));
+    settings.deterministic_seed = Some(DeterministicSeed([id; 32]));
+    let weight = f32::from(id + 1) / f32::from(PARTICIPANTS);
+    let model = Model::from_primitives(vec![weight; MODEL_LENGTH].into_iter()).unwrap();
+    let mut participant = Participant::new(
+        settings,
+        client,
+        LocalModel(Arc::new(model)),
+        notifications.clone(),
+    );
+    let handle = tokio::spawn(async move {
+        loop {
+            participant = match participant.transition().await {
+                TransitionOutcome::Pending(participant) => {
+                    sleep(POLL_INTERVAL).await;
+                    participant
+                }
+                TransitionOutcome::Complete(participant) => participant,
+            };
+        }
+    });
+    Recorded {
+        handle,
+        wire,
+        notifications,
+    }
+}
+
+/// Hashes the payload of an update message.
+fn hash_update_payload(message: &[u8], keys: &EncryptKeyPair) -> String {
+    let message = keys.secret.decrypt(message, &keys.public).unwrap();
+    let message = Message::from_byte_slice(&message).unwrap();
+    assert!(!message.is_multipart, "the update message is split");
+    let mut payload = vec![0; message.payload.buffer_length()];
+    message.payload.to_bytes(&mut payload);
+    hex::encode(Sha256::hash(&payload).as_slice())
+}
+
+/// Compares the `actual` artifact with the golden file `name`, or overwrites the golden file if
+/// the goldens are regenerated.
+fn check_golden(name: &str, actual: &str, transcripts: &Path) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("golden")
+        .join("round")
+        .join(name);
+    if env::var_os(UPDATE_GOLDEN).is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "failed to read the golden file {} ({}), generate it with {}=1",
+            path.display(),
+            err,
+            UPDATE_GOLDEN,
+        )
+    });
+    assert!(
+        expected == actual,
+        "the {} drifted from the golden file {}\n\
+         --- expected\n{}\n--- actual\n{}\n\
+         the wire transcripts are in {}. If the change is intentional, regenerate the golden \
+         files with {}=1",
+        name,
+        path.display(),
+        expected,
+        actual,
+        transcripts.display(),
+        UPDATE_GOLDEN,
+    );
+}
+
+/// The directory of the wire transcripts.
+fn transcripts_dir() -> PathBuf {
+    let target = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("..")
+                .join("target")
+        });
+    target.join("golden").join("round")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_golden_round() {
+    // a dry run of the idle phase tells how many participants the coordinator has to wait for
+    let (_, _, event_subscriber, _) = init_coordinator(Selection { sum: 1, update: 1 }).await;
+    let selection = select(&event_subscriber.params_listener().get_latest().event);
+    assert!(
+        selection.sum > 0 && selection.update > 0,
+        "the round can't complete with {:?}",
+        selection,
+    );
+
+    let (state_machine, requests_tx, event_subscriber, store) = init_coordinator(selection).await;
+    let keys = event_subscriber.keys_listener().get_latest().event;
+    let address = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let api_settings = ApiSettings {
+        bind_address: address,
+        debug_rejections: true,
+        non_production: true,
+        http2: false,
+        keep_alive_timeout: None,
+        max_concurrent_streams: None,
+        gzip: false,
+        gzip_threshold: 1024,
+        public_stats_rate_limit: 100,
+        events: false,
+    };
+    let fetcher = services::fetchers::fetcher(&event_subscriber, store);
+    let message_handler =
+        services::messages::PetMessageHandler::new(&event_subscriber, requests_tx);
+    let server = tokio::spawn(serve(
+        api_settings,
+        fetcher,
+        message_handler,
+        event_subscriber.clone(),
+        None,
+    ));
+    // the coordinator stops once the round completed, otherwise the participants would race
+    // the start of the next round
+    let coordinator = tokio::spawn(async move {
+        let mut state_machine = state_machine;
+        while let Some(next) = state_machine.next().await {
+            if let StateMachine::Idle(_) = next {
+                break;
+            }
+            state_machine = next;
+        }
+    });
+
+    let url = format!("http://{}", address);
+    let participants = (0..PARTICIPANTS)
+        .map(|id| spawn_participant(id, &url))
+        .collect::<Vec<_>>();
+
+    let mut model_listener = event_subscriber.model_listener();
+    let round = async {
+        let global_model = loop {
+            model_listener.changed().await.unwrap();
+            if let ModelUpdate::New(model) = model_listener.get_latest().event {
+                break model;
+            }
+        };
+        let notifications = loop {
+            let notifications = participants
+                .iter()
+                .map(|participant| participant.notifications.completed())
+                .collect::<Option<Vec<_>>>();
+            match notifications {
+                Some(notifications) => break notifications,
+                None => sleep(POLL_INTERVAL).await,
+            }
+        };
+        (global_model, notifications)
+    };
+    let result = timeout(ROUND_TIMEOUT, round).await;
+    for participant in participants.iter() {
+        participant.handle.abort();
+    }
+    server.abort();
+    coordinator.abort();
+
+    let transcripts = transcripts_dir();
+    fs::create_dir_all(&transcripts).unwrap();
+    for (id, participant) in participants.iter().enumerate() {
+        let path = transcripts.join(format!("participant-{}.txt", id));
+        fs::write(path, participant.wire.dump()).unwrap();
+    }
+    let (global_model, notifications) = result.unwrap_or_else(|_| {
+        panic!(
+            "the round didn't complete in time, the wire transcripts are in {}",
+            transcripts.display(),
+        )
+    });
+
+    let mut updates = String::new();
+    for (id, participant) in participants.iter().enumerate() {
+        for message in participant.wire.messages(Tag::Update) {
+            let hash = hash_update_payload(&message, &keys);
+            writeln!(updates, "participant {}: {}", id, hash).unwrap();
+        }
+    }
+    check_golden("update_payloads.txt", &updates, &transcripts);
+
+    let global_model = hex::encode(bincode::serialize(global_model.as_ref()).unwrap());
+    check_golden(
+        "global_model.txt",
+        &format!("{}\n", global_model),
+        &transcripts,
+    );
+
+    let mut sequences = String::new();
+    for (id, notifications) in notifications.iter().enumerate() {
+        writeln!(
+            sequences,
+            "participant {}: {}",
+            id,
+            notifications.join(", ")
+        )
+        .unwrap();
+    }
+    check_golden("notifications.txt", &sequences, &transcripts);
+}
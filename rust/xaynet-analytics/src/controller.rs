@@ -4,7 +4,7 @@ use anyhow::{anyhow, Error, Result};
 use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 
 use crate::{
-    data_combination::data_combiner::DataCombiner,
+    data_combination::{data_combiner::DataCombiner, data_points::data_point::DataPoint},
     database::{
         analytics_event::{
             adapter::AnalyticsEventAdapter,
@@ -33,7 +33,7 @@ use crate::{
 /// * `combiner` - `DataCombiner` component responsible for calculating `DataPoints` based on `AnalyticsEvents` and `ScreenRoutes`.
 /// * `sender` - `Sender` component responsible for preparing the message to be sent to the coordinator for aggregation.
 /// * `send_frequency_hours` - `Duration` in hours representing periods within which we want to send data to the coordinator only once.
-struct AnalyticsController {
+pub struct AnalyticsController {
     db: IsarDb,
     is_charging: bool,
     is_connected_to_wifi: bool,
@@ -43,8 +43,6 @@ struct AnalyticsController {
     send_frequency_hours: Duration,
 }
 
-// TODO: remove allow dead code when AnalyticsController is integrated with FFI layer: https://xainag.atlassian.net/browse/XN-1415
-#[allow(dead_code)]
 impl AnalyticsController {
     const MAX_SEND_FREQUENCY_HOURS: u8 = 24;
 
@@ -109,6 +107,15 @@ impl AnalyticsController {
         }
     }
 
+    /// Retrieve all `AnalyticsEvents` and `ScreenRoutes` from the db and let the `DataCombiner` calculate the
+    /// `DataPoints` for the periods ending at `end_period`.
+    pub fn compute_data_points(&self, end_period: DateTime<Utc>) -> Result<Vec<DataPoint>, Error> {
+        let events = AnalyticsEvent::get_all(&self.db, &CollectionNames::ANALYTICS_EVENTS)?;
+        let screen_routes = ScreenRoute::get_all(&self.db, &CollectionNames::SCREEN_ROUTES)?;
+        self.combiner
+            .init_data_points(end_period, &events, &screen_routes)
+    }
+
     #[cfg(test)]
     fn db(&self) -> &IsarDb {
         &self.db
@@ -208,11 +215,9 @@ impl AnalyticsController {
     /// The `DataCombiner` will init all `DataPoints` and pack them in a `Vec<DataPoint>`, which will be the input to the `Sender`.
     /// After that, save the new time_data_sent inside `ControllerData`, and cache it in `self.last_time_data_sent`
     fn send_data(&mut self) -> Result<(), Error> {
-        let events = AnalyticsEvent::get_all(&self.db, &CollectionNames::ANALYTICS_EVENTS)?;
-        let screen_routes = ScreenRoute::get_all(&self.db, &CollectionNames::SCREEN_ROUTES)?;
         let time_data_sent = Utc::now();
         self.sender
-            .send(self.combiner.init_data_points(&events, &screen_routes)?)
+            .send(self.compute_data_points(time_data_sent)?)
             .and_then(|_| {
                 ControllerData::new(time_data_sent)
                     .save(&self.db, &CollectionNames::CONTROLLER_DATA)
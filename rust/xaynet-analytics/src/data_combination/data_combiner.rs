@@ -34,11 +34,10 @@ pub struct DataCombiner;
 impl<'screen> DataCombiner {
     pub fn init_data_points(
         &self,
+        end_period: DateTime<Utc>,
         events: &[AnalyticsEvent],
         screen_routes: &[ScreenRoute],
     ) -> Result<Vec<DataPoint>, Error> {
-        let end_period = Utc::now();
-
         let one_day_period_metadata =
             DataPointMetadata::new(Period::new(PeriodUnit::Days, 1), end_period);
         let was_active_each_period_metadatas = vec![
@@ -63,10 +63,17 @@ pub enum DataPoint {
     WasActivePastNDays(CalcWasActivePastNDays),
 }
 
-#[allow(dead_code)]
-// TODO: will be called when preparing the data to be sent to the coordinator
 impl DataPoint {
-    fn calculate(&self) -> Vec<u32> {
+    pub fn metadata(&self) -> DataPointMetadata {
+        match self {
+            DataPoint::ScreenActiveTime(data) => data.metadata(),
+            DataPoint::ScreenEnterCount(data) => data.metadata(),
+            DataPoint::WasActiveEachPastPeriod(data) => data.metadata(),
+            DataPoint::WasActivePastNDays(data) => data.metadata(),
+        }
+    }
+
+    pub fn calculate(&self) -> Vec<u32> {
         match self {
             DataPoint::ScreenActiveTime(data) => data.calculate(),
             DataPoint::ScreenEnterCount(data) => data.calculate(),
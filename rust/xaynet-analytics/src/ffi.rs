@@ -0,0 +1,634 @@
+//! C API of the library, used by the mobile frameworks to record `AnalyticsEvents` and to retrieve the
+//! aggregated `DataPoints`.
+//!
+//! The functions follow the conventions of the `xaynet-mobile` FFI: the `AnalyticsController` is handed out
+//! as an opaque pointer, and all the functions that take pointers check them for NULL and return an error
+//! code instead of a `Result`.
+
+#![allow(unused_unsafe)]
+
+use chrono::{DateTime, TimeZone, Utc};
+use std::{
+    convert::TryFrom,
+    os::raw::{c_int, c_uchar, c_uint},
+    slice,
+};
+
+pub use ffi_support::FfiStr;
+
+use crate::{
+    controller::AnalyticsController,
+    data_combination::data_points::data_point::{DataPoint, PeriodUnit},
+    database::analytics_event::data_model::AnalyticsEventType,
+};
+
+/// Return value upon success
+pub const OK: c_int = 0;
+/// NULL pointer argument
+pub const ERR_NULLPTR: c_int = 1;
+/// Invalid event type: the value is not one of the `ANALYTICS_EVENT_*` constants
+pub const ERR_EVENT_TYPE: c_int = 2;
+/// Invalid event name: the name is NULL or not valid UTF-8
+pub const ERR_EVENT_NAME: c_int = 3;
+/// Invalid screen route: the screen route is not valid UTF-8
+pub const ERR_SCREEN_ROUTE: c_int = 4;
+/// Invalid timestamp: the timestamp is out of range
+pub const ERR_TIMESTAMP: c_int = 5;
+/// Failed to read from or write to the storage
+pub const ERR_STORAGE: c_int = 6;
+/// Failed to get the aggregation results: the buffer is too small
+pub const ERR_COMPUTE_LEN: c_int = 7;
+
+/// Event type of Flutter's `AppLifeCyclesEvents` (or the equivalent in other frameworks)
+pub const ANALYTICS_EVENT_APP_EVENT: c_int = 0;
+/// Event type of a known error logged by the developers
+pub const ANALYTICS_EVENT_APP_ERROR: c_int = 1;
+/// Event type of the user entering a specific screen
+pub const ANALYTICS_EVENT_SCREEN_ENTER: c_int = 2;
+/// Event type of a custom event logged by the developers
+pub const ANALYTICS_EVENT_USER_ACTION: c_int = 3;
+
+/// Kind of a serialized `ScreenActiveTime` data point
+pub const DATA_POINT_SCREEN_ACTIVE_TIME: c_uchar = 0;
+/// Kind of a serialized `ScreenEnterCount` data point
+pub const DATA_POINT_SCREEN_ENTER_COUNT: c_uchar = 1;
+/// Kind of a serialized `WasActiveEachPastPeriod` data point
+pub const DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD: c_uchar = 2;
+/// Kind of a serialized `WasActivePastNDays` data point
+pub const DATA_POINT_WAS_ACTIVE_PAST_N_DAYS: c_uchar = 3;
+
+/// Unit of a serialized period of days
+pub const PERIOD_UNIT_DAYS: c_uchar = 0;
+/// Unit of a serialized period of weeks
+pub const PERIOD_UNIT_WEEKS: c_uchar = 1;
+/// Unit of a serialized period of months
+pub const PERIOD_UNIT_MONTHS: c_uchar = 2;
+
+/// Create a new analytics controller, which stores its data in the directory at `path`.
+/// The directory must exist.
+///
+/// # Return value
+///
+/// - a NULL pointer if `path` is NULL or not valid UTF-8, or if the storage can't be
+///   opened
+/// - a valid pointer to an `AnalyticsController` otherwise. It must be destroyed with
+///   [`xaynet_analytics_destroy()`].
+///
+/// # Safety
+///
+/// `path` must be a valid NULL terminated string, or a NULL pointer.
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_analytics_new(path: FfiStr) -> *mut AnalyticsController {
+    let path = match path.as_opt_str() {
+        Some(path) => path,
+        None => return std::ptr::null_mut(),
+    };
+
+    match AnalyticsController::init(path.to_string(), false, false, None) {
+        Ok(controller) => Box::into_raw(Box::new(controller)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Destroy the analytics controller created by [`xaynet_analytics_new()`] and close its
+/// storage.
+///
+/// # Return value
+///
+/// - [`OK`] on success
+/// - [`ERR_NULLPTR`] if `controller` is NULL
+/// - [`ERR_STORAGE`] if the storage couldn't be closed. The controller is destroyed
+///   nonetheless.
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. After destroying the `AnalyticsController`, the pointer becomes invalid and must
+///    not be used.
+/// 3. This function should only be called on a pointer that has been created by
+///    [`xaynet_analytics_new()`].
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_analytics_destroy(controller: *mut AnalyticsController) -> c_int {
+    if controller.is_null() {
+        return ERR_NULLPTR;
+    }
+    match unsafe { Box::from_raw(controller) }.dispose() {
+        Ok(()) => OK,
+        Err(_) => ERR_STORAGE,
+    }
+}
+
+/// Record an event of the given type.
+///
+/// # Arguments
+///
+/// - `event_type` must be one of the `ANALYTICS_EVENT_*` constants
+/// - `name` is the name of the event
+/// - `timestamp` is the time at which the event happened, in milliseconds since the Unix
+///   epoch
+/// - `screen_route` is the name of the screen on which the event happened. It may be
+///   NULL if the event doesn't relate to a specific screen.
+///
+/// # Return value
+///
+/// - [`OK`] if the event is recorded
+/// - [`ERR_NULLPTR`] if `controller` is NULL
+/// - [`ERR_EVENT_TYPE`] if `event_type` is invalid
+/// - [`ERR_EVENT_NAME`] if `name` is NULL or not valid UTF-8
+/// - [`ERR_SCREEN_ROUTE`] if `screen_route` is not valid UTF-8
+/// - [`ERR_TIMESTAMP`] if `timestamp` is out of range
+/// - [`ERR_STORAGE`] if the event couldn't be saved
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. `name` and `screen_route` must be valid NULL terminated strings, or NULL
+///    pointers.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_analytics_record_event(
+    controller: *const AnalyticsController,
+    event_type: c_int,
+    name: FfiStr,
+    timestamp: i64,
+    screen_route: FfiStr,
+) -> c_int {
+    let controller = match unsafe { controller.as_ref() } {
+        Some(controller) => controller,
+        None => return ERR_NULLPTR,
+    };
+    let event_type = match AnalyticsEventType::try_from(event_type) {
+        Ok(event_type) => event_type,
+        Err(_) => return ERR_EVENT_TYPE,
+    };
+    let name = match name.as_opt_str() {
+        Some(name) => name,
+        None => return ERR_EVENT_NAME,
+    };
+    let screen_route_name = screen_route.as_opt_str();
+    // `as_opt_str()` also returns `None` for invalid UTF-8, unlike `into_opt_string()`
+    if screen_route_name.is_none() && screen_route.into_opt_string().is_some() {
+        return ERR_SCREEN_ROUTE;
+    }
+    let timestamp = match from_timestamp_millis(timestamp) {
+        Some(timestamp) => timestamp,
+        None => return ERR_TIMESTAMP,
+    };
+
+    match controller.save_analytics_event(name, event_type, timestamp, screen_route_name) {
+        Ok(()) => OK,
+        Err(_) => ERR_STORAGE,
+    }
+}
+
+/// Compute the data points over the periods ending at `window_end`, and copy the
+/// serialized results into `buffer`.
+///
+/// `window_end` is a timestamp in milliseconds since the Unix epoch. Only the events
+/// recorded before the midnight (UTC) preceding `window_end` are taken into account.
+///
+/// When calling this function, `len` must point to the length of `buffer`. On return,
+/// it points to the length of the serialized results. To get the length of the results
+/// without copying them, `len` can point to `0`, in which case `buffer` may be NULL.
+///
+/// # Serialization format
+///
+/// All the integers are little endian. The results start with the number of data
+/// points, followed by the data points:
+///
+/// | field           | type       | description                                  |
+/// |-----------------|------------|----------------------------------------------|
+/// | count           | `uint32_t` | number of data points                        |
+/// | data points     |            | `count` times the data point fields below    |
+///
+/// Each data point is serialized as:
+///
+/// | field           | type       | description                                  |
+/// |-----------------|------------|----------------------------------------------|
+/// | kind            | `uint8_t`  | one of the `DATA_POINT_*` constants          |
+/// | period unit     | `uint8_t`  | one of the `PERIOD_UNIT_*` constants         |
+/// | period length   | `uint32_t` | number of period units                       |
+/// | period end      | `int64_t`  | end of the period, in ms since the Unix epoch|
+/// | values count    | `uint32_t` | number of values                             |
+/// | values          | `uint32_t` | `values count` times the calculated values   |
+///
+/// The `DATA_POINT_SCREEN_ACTIVE_TIME` data points come first, one per recorded screen
+/// route in the order in which the routes were first seen, followed by one for all the
+/// screens. Then come the `DATA_POINT_SCREEN_ENTER_COUNT` data points, one per screen
+/// route in the same order, followed by the `DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD`
+/// and `DATA_POINT_WAS_ACTIVE_PAST_N_DAYS` data points.
+///
+/// # Return value
+///
+/// - [`OK`] if the results are copied into `buffer`
+/// - [`ERR_NULLPTR`] if `controller` or `len` is NULL, or if `buffer` is NULL while
+///   `len` doesn't point to `0`
+/// - [`ERR_TIMESTAMP`] if `window_end` is out of range
+/// - [`ERR_STORAGE`] if the events couldn't be read
+/// - [`ERR_COMPUTE_LEN`] if `buffer` is too small for the results
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointers are NULL
+///    *or* all of the following is true:
+///    - The pointers must be properly [aligned].
+///    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. `buffer` must be valid for writes of `len` bytes.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_analytics_compute(
+    controller: *const AnalyticsController,
+    window_end: i64,
+    buffer: *mut c_uchar,
+    len: *mut c_uint,
+) -> c_int {
+    let controller = match unsafe { controller.as_ref() } {
+        Some(controller) => controller,
+        None => return ERR_NULLPTR,
+    };
+    let len = match unsafe { len.as_mut() } {
+        Some(len) => len,
+        None => return ERR_NULLPTR,
+    };
+    if buffer.is_null() && *len != 0 {
+        return ERR_NULLPTR;
+    }
+    let window_end = match from_timestamp_millis(window_end) {
+        Some(window_end) => window_end,
+        None => return ERR_TIMESTAMP,
+    };
+
+    let results = match controller.compute_data_points(window_end) {
+        Ok(data_points) => serialize_data_points(&data_points),
+        Err(_) => return ERR_STORAGE,
+    };
+    let capacity = *len as usize;
+    *len = results.len() as c_uint;
+    if capacity < results.len() {
+        return ERR_COMPUTE_LEN;
+    }
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, results.len()) };
+    buffer.copy_from_slice(&results);
+    OK
+}
+
+fn from_timestamp_millis(timestamp: i64) -> Option<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(timestamp).single()
+}
+
+/// Serialize the `DataPoints` in the format documented in [`xaynet_analytics_compute()`].
+fn serialize_data_points(data_points: &[DataPoint]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(data_points.len() as u32).to_le_bytes());
+    for data_point in data_points {
+        let kind = match data_point {
+            DataPoint::ScreenActiveTime(_) => DATA_POINT_SCREEN_ACTIVE_TIME,
+            DataPoint::ScreenEnterCount(_) => DATA_POINT_SCREEN_ENTER_COUNT,
+            DataPoint::WasActiveEachPastPeriod(_) => DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD,
+            DataPoint::WasActivePastNDays(_) => DATA_POINT_WAS_ACTIVE_PAST_N_DAYS,
+        };
+        let metadata = data_point.metadata();
+        let period_unit = match metadata.period.unit {
+            PeriodUnit::Days => PERIOD_UNIT_DAYS,
+            PeriodUnit::Weeks => PERIOD_UNIT_WEEKS,
+            PeriodUnit::Months => PERIOD_UNIT_MONTHS,
+        };
+        let values = data_point.calculate();
+
+        bytes.push(kind);
+        bytes.push(period_unit);
+        bytes.extend_from_slice(&metadata.period.n.to_le_bytes());
+        bytes.extend_from_slice(&metadata.end.timestamp_millis().to_le_bytes());
+        bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::Duration;
+    use std::{convert::TryInto, env, ffi::CString, fs, path::PathBuf, ptr};
+
+    use crate::data_combination::data_points::data_point::{
+        CalcScreenEnterCount,
+        DataPointMetadata,
+        Period,
+    };
+
+    /// A deserialized data point: `(kind, period unit, period length, period end, values)`.
+    type Decoded = (u8, u8, u32, i64, Vec<u32>);
+
+    fn get_path(test_name: &str) -> PathBuf {
+        let temp_dir = env::temp_dir();
+        temp_dir.join(test_name)
+    }
+
+    fn get_controller(test_name: &str) -> *mut AnalyticsController {
+        let path_buf = get_path(test_name);
+        if !path_buf.exists() {
+            fs::create_dir(&path_buf).unwrap();
+        }
+        let path = CString::new(path_buf.to_str().unwrap()).unwrap();
+        let controller = unsafe { xaynet_analytics_new(FfiStr::from_cstr(&path)) };
+        assert!(!controller.is_null());
+        controller
+    }
+
+    fn cleanup(controller: *mut AnalyticsController, test_name: &str) {
+        assert_eq!(unsafe { xaynet_analytics_destroy(controller) }, OK);
+        fs::remove_dir_all(get_path(test_name)).unwrap();
+    }
+
+    fn timestamp(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn record_event(
+        controller: *const AnalyticsController,
+        event_type: c_int,
+        timestamp: DateTime<Utc>,
+        screen_route: Option<&str>,
+    ) -> c_int {
+        let name = CString::new("event").unwrap();
+        let screen_route = screen_route.map(|route| CString::new(route).unwrap());
+        let screen_route = match screen_route.as_ref() {
+            Some(route) => FfiStr::from_cstr(route),
+            None => unsafe { FfiStr::from_raw(ptr::null()) },
+        };
+        unsafe {
+            xaynet_analytics_record_event(
+                controller,
+                event_type,
+                FfiStr::from_cstr(&name),
+                timestamp.timestamp_millis(),
+                screen_route,
+            )
+        }
+    }
+
+    fn compute(controller: *const AnalyticsController, window_end: DateTime<Utc>) -> Vec<u8> {
+        let window_end = window_end.timestamp_millis();
+        let mut len: c_uint = 0;
+        let code =
+            unsafe { xaynet_analytics_compute(controller, window_end, ptr::null_mut(), &mut len) };
+        assert_eq!(code, ERR_COMPUTE_LEN);
+
+        let mut buffer = vec![0; len as usize];
+        let code = unsafe {
+            xaynet_analytics_compute(controller, window_end, buffer.as_mut_ptr(), &mut len)
+        };
+        assert_eq!(code, OK);
+        assert_eq!(len as usize, buffer.len());
+        buffer
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<Decoded> {
+        fn take<'a>(bytes: &mut &'a [u8], n: usize) -> &'a [u8] {
+            let (head, tail) = bytes.split_at(n);
+            *bytes = tail;
+            head
+        }
+        fn take_u32(bytes: &mut &[u8]) -> u32 {
+            u32::from_le_bytes(take(bytes, 4).try_into().unwrap())
+        }
+
+        let mut bytes = bytes;
+        let count = take_u32(&mut bytes);
+        let data_points = (0..count)
+            .map(|_| {
+                let kind = take(&mut bytes, 1)[0];
+                let period_unit = take(&mut bytes, 1)[0];
+                let period_n = take_u32(&mut bytes);
+                let end = i64::from_le_bytes(take(&mut bytes, 8).try_into().unwrap());
+                let values_count = take_u32(&mut bytes);
+                let values = (0..values_count).map(|_| take_u32(&mut bytes)).collect();
+                (kind, period_unit, period_n, end, values)
+            })
+            .collect();
+        assert!(bytes.is_empty());
+        data_points
+    }
+
+    #[test]
+    fn test_event_types() {
+        let event_types = vec![
+            (ANALYTICS_EVENT_APP_EVENT, AnalyticsEventType::AppEvent),
+            (ANALYTICS_EVENT_APP_ERROR, AnalyticsEventType::AppError),
+            (ANALYTICS_EVENT_SCREEN_ENTER, AnalyticsEventType::ScreenEnter),
+            (ANALYTICS_EVENT_USER_ACTION, AnalyticsEventType::UserAction),
+        ];
+        for (constant, event_type) in event_types {
+            assert_eq!(AnalyticsEventType::try_from(constant).unwrap(), event_type);
+        }
+    }
+
+    #[test]
+    fn test_serialize_data_points() {
+        let end = timestamp("2021-01-02T12:00:00+00:00");
+        let metadata = DataPointMetadata::new(Period::new(PeriodUnit::Weeks, 3), end);
+        let data_point = DataPoint::ScreenEnterCount(CalcScreenEnterCount::new(metadata, vec![]));
+
+        let mut expected = vec![1, 0, 0, 0, DATA_POINT_SCREEN_ENTER_COUNT, PERIOD_UNIT_WEEKS];
+        expected.extend_from_slice(&[3, 0, 0, 0]);
+        expected.extend_from_slice(&end.timestamp_millis().to_le_bytes());
+        expected.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(serialize_data_points(&[data_point]), expected);
+    }
+
+    #[test]
+    fn test_record_and_compute() {
+        let test_name = "test_ffi_record_and_compute";
+        let controller = get_controller(test_name);
+        let window_end = timestamp("2021-01-02T12:00:00+00:00");
+        let yesterday = timestamp("2021-01-01T10:00:00+00:00");
+
+        assert_eq!(
+            record_event(
+                controller,
+                ANALYTICS_EVENT_SCREEN_ENTER,
+                yesterday,
+                Some("home")
+            ),
+            OK
+        );
+        assert_eq!(
+            record_event(
+                controller,
+                ANALYTICS_EVENT_APP_EVENT,
+                yesterday + Duration::hours(1),
+                None
+            ),
+            OK
+        );
+
+        let results = compute(controller, window_end);
+        let expected = unsafe { &*controller }
+            .compute_data_points(window_end)
+            .unwrap();
+        assert_eq!(results, serialize_data_points(&expected));
+
+        let data_points = decode(&results);
+        let kinds: Vec<u8> = data_points.iter().map(|data_point| data_point.0).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DATA_POINT_SCREEN_ACTIVE_TIME,
+                DATA_POINT_SCREEN_ACTIVE_TIME,
+                DATA_POINT_SCREEN_ENTER_COUNT,
+                DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD,
+                DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD,
+                DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD,
+                DATA_POINT_WAS_ACTIVE_PAST_N_DAYS,
+                DATA_POINT_WAS_ACTIVE_PAST_N_DAYS,
+                DATA_POINT_WAS_ACTIVE_PAST_N_DAYS,
+            ]
+        );
+        // the "home" screen has been entered once on the day before the end of the window
+        assert_eq!(
+            data_points[2],
+            (
+                DATA_POINT_SCREEN_ENTER_COUNT,
+                PERIOD_UNIT_DAYS,
+                1,
+                window_end.timestamp_millis(),
+                vec![1]
+            )
+        );
+
+        cleanup(controller, test_name);
+    }
+
+    #[test]
+    fn test_compute_buffer_too_small() {
+        let test_name = "test_ffi_compute_buffer_too_small";
+        let controller = get_controller(test_name);
+        let window_end = timestamp("2021-01-02T12:00:00+00:00").timestamp_millis();
+
+        let mut len: c_uint = 0;
+        let code =
+            unsafe { xaynet_analytics_compute(controller, window_end, ptr::null_mut(), &mut len) };
+        assert_eq!(code, ERR_COMPUTE_LEN);
+        let required = len;
+        assert!(required > 0);
+
+        // the buffer must be left untouched
+        let mut buffer = vec![0xff; required as usize - 1];
+        len = buffer.len() as c_uint;
+        let code = unsafe {
+            xaynet_analytics_compute(controller, window_end, buffer.as_mut_ptr(), &mut len)
+        };
+        assert_eq!(code, ERR_COMPUTE_LEN);
+        assert_eq!(len, required);
+        assert!(buffer.iter().all(|byte| *byte == 0xff));
+
+        cleanup(controller, test_name);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let test_name = "test_ffi_invalid_arguments";
+        let controller = get_controller(test_name);
+        let now = timestamp("2021-01-02T12:00:00+00:00").timestamp_millis();
+        let name = CString::new("event").unwrap();
+        let invalid_utf8 = CString::new(vec![0xc3, 0x28]).unwrap();
+        let null = || unsafe { FfiStr::from_raw(ptr::null()) };
+
+        unsafe {
+            let name = || FfiStr::from_cstr(&name);
+            assert_eq!(
+                xaynet_analytics_record_event(controller, 42, name(), now, null()),
+                ERR_EVENT_TYPE
+            );
+            assert_eq!(
+                xaynet_analytics_record_event(
+                    controller,
+                    ANALYTICS_EVENT_USER_ACTION,
+                    null(),
+                    now,
+                    null()
+                ),
+                ERR_EVENT_NAME
+            );
+            assert_eq!(
+                xaynet_analytics_record_event(
+                    controller,
+                    ANALYTICS_EVENT_SCREEN_ENTER,
+                    name(),
+                    now,
+                    FfiStr::from_cstr(&invalid_utf8)
+                ),
+                ERR_SCREEN_ROUTE
+            );
+            assert_eq!(
+                xaynet_analytics_record_event(
+                    controller,
+                    ANALYTICS_EVENT_APP_ERROR,
+                    name(),
+                    i64::MAX,
+                    null()
+                ),
+                ERR_TIMESTAMP
+            );
+
+            // a NULL buffer is only allowed to query the length of the results
+            let mut len: c_uint = 1;
+            assert_eq!(
+                xaynet_analytics_compute(controller, now, ptr::null_mut(), &mut len),
+                ERR_NULLPTR
+            );
+            assert_eq!(
+                xaynet_analytics_compute(controller, now, ptr::null_mut(), ptr::null_mut()),
+                ERR_NULLPTR
+            );
+        }
+
+        cleanup(controller, test_name);
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        let name = CString::new("event").unwrap();
+        let mut len: c_uint = 0;
+        unsafe {
+            assert!(xaynet_analytics_new(FfiStr::from_raw(ptr::null())).is_null());
+            assert_eq!(xaynet_analytics_destroy(ptr::null_mut()), ERR_NULLPTR);
+            assert_eq!(
+                xaynet_analytics_record_event(
+                    ptr::null(),
+                    ANALYTICS_EVENT_APP_EVENT,
+                    FfiStr::from_cstr(&name),
+                    0,
+                    FfiStr::from_raw(ptr::null())
+                ),
+                ERR_NULLPTR
+            );
+            assert_eq!(
+                xaynet_analytics_compute(ptr::null(), 0, ptr::null_mut(), &mut len),
+                ERR_NULLPTR
+            );
+        }
+    }
+}
@@ -15,4 +15,6 @@ pub mod data_combination;
 #[cfg(not(tarpaulin))]
 pub mod database;
 #[cfg(not(tarpaulin))]
+pub mod ffi;
+#[cfg(not(tarpaulin))]
 pub mod sender;
@@ -0,0 +1,159 @@
+#include <assert.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "minunit.h"
+#include "xaynet_analytics_ffi.h"
+
+// 2021-01-01T10:00:00Z
+#define YESTERDAY 1609495200000
+// 2021-01-02T12:00:00Z
+#define WINDOW_END 1609588800000
+
+static uint32_t read_u32(const unsigned char *buffer, unsigned int *offset) {
+  uint32_t value = 0;
+  for (int i = 3; i >= 0; i--) {
+    value = (value << 8) | buffer[*offset + i];
+  }
+  *offset += 4;
+  return value;
+}
+
+static int64_t read_i64(const unsigned char *buffer, unsigned int *offset) {
+  uint64_t value = 0;
+  for (int i = 7; i >= 0; i--) {
+    value = (value << 8) | buffer[*offset + i];
+  }
+  *offset += 8;
+  return (int64_t)value;
+}
+
+AnalyticsController *new_controller(char *path) {
+  assert(mkdtemp(path) != NULL);
+  AnalyticsController *controller = xaynet_analytics_new(path);
+  assert(controller != NULL);
+  return controller;
+}
+
+void destroy_controller(AnalyticsController *controller, const char *path) {
+  assert(xaynet_analytics_destroy(controller) == OK);
+  char command[64];
+  snprintf(command, sizeof(command), "rm -rf %s", path);
+  assert(system(command) == 0);
+}
+
+static char *test_null_pointers() {
+  unsigned int len = 0;
+  mu_assert("new with NULL path should fail", xaynet_analytics_new(NULL) == NULL);
+  mu_assert("destroy NULL should fail", xaynet_analytics_destroy(NULL) == ERR_NULLPTR);
+  mu_assert("record with NULL controller should fail",
+            xaynet_analytics_record_event(NULL, ANALYTICS_EVENT_APP_EVENT, "event",
+                                          YESTERDAY, NULL) == ERR_NULLPTR);
+  mu_assert("compute with NULL controller should fail",
+            xaynet_analytics_compute(NULL, WINDOW_END, NULL, &len) == ERR_NULLPTR);
+  return 0;
+}
+
+static char *test_record_and_compute() {
+  char path[] = "/tmp/xaynet_analytics_XXXXXX";
+  AnalyticsController *controller = new_controller(path);
+
+  int err = xaynet_analytics_record_event(controller, ANALYTICS_EVENT_SCREEN_ENTER, "event",
+                                          YESTERDAY, "home");
+  mu_assert("failed to record screen enter event", !err);
+  err = xaynet_analytics_record_event(controller, ANALYTICS_EVENT_APP_EVENT, "event",
+                                      YESTERDAY + 3600000, NULL);
+  mu_assert("failed to record app event", !err);
+  err = xaynet_analytics_record_event(controller, 42, "event", YESTERDAY, NULL);
+  mu_assert("recording an invalid event type should fail", err == ERR_EVENT_TYPE);
+
+  // query the length of the results
+  unsigned int len = 0;
+  err = xaynet_analytics_compute(controller, WINDOW_END, NULL, &len);
+  mu_assert("expected the buffer to be too small", err == ERR_COMPUTE_LEN);
+  mu_assert("expected non empty results", len > 0);
+
+  unsigned char *buffer = (unsigned char *)malloc(len);
+  unsigned int buffer_len = len;
+  err = xaynet_analytics_compute(controller, WINDOW_END, buffer, &len);
+  mu_assert("failed to compute the results", !err);
+  mu_assert("unexpected results length", len == buffer_len);
+
+  // 2 screen active times (home and all screens), 1 screen enter count (home), 3 was
+  // active each past period and 3 was active past n days data points
+  unsigned int offset = 0;
+  mu_assert("unexpected number of data points", read_u32(buffer, &offset) == 9);
+  for (int i = 0; i < 9; i++) {
+    unsigned char kind = buffer[offset++];
+    unsigned char period_unit = buffer[offset++];
+    uint32_t period_n = read_u32(buffer, &offset);
+    int64_t end = read_i64(buffer, &offset);
+    uint32_t values_count = read_u32(buffer, &offset);
+    mu_assert("unexpected period end", end == WINDOW_END);
+    if (i == 2) {
+      mu_assert("expected a screen enter count", kind == DATA_POINT_SCREEN_ENTER_COUNT);
+      mu_assert("unexpected period unit", period_unit == PERIOD_UNIT_DAYS);
+      mu_assert("unexpected period length", period_n == 1);
+      mu_assert("unexpected number of values", values_count == 1);
+      mu_assert("the home screen was entered once", read_u32(buffer, &offset) == 1);
+    } else {
+      offset += 4 * values_count;
+    }
+  }
+  mu_assert("unexpected trailing bytes", offset == len);
+
+  free(buffer);
+  destroy_controller(controller, path);
+  return 0;
+}
+
+static char *test_compute_buffer_too_small() {
+  char path[] = "/tmp/xaynet_analytics_XXXXXX";
+  AnalyticsController *controller = new_controller(path);
+
+  unsigned int len = 0;
+  int err = xaynet_analytics_compute(controller, WINDOW_END, NULL, &len);
+  mu_assert("expected the buffer to be too small", err == ERR_COMPUTE_LEN);
+  unsigned int required = len;
+
+  unsigned char *buffer = (unsigned char *)malloc(required);
+  memset(buffer, 0xff, required);
+  len = required - 1;
+  err = xaynet_analytics_compute(controller, WINDOW_END, buffer, &len);
+  mu_assert("expected the buffer to be too small", err == ERR_COMPUTE_LEN);
+  mu_assert("expected the required length", len == required);
+  for (unsigned int i = 0; i < required; i++) {
+    mu_assert("the buffer must be left untouched", buffer[i] == 0xff);
+  }
+
+  // a NULL buffer is only allowed to query the length of the results
+  err = xaynet_analytics_compute(controller, WINDOW_END, NULL, &len);
+  mu_assert("compute with NULL buffer should fail", err == ERR_NULLPTR);
+
+  free(buffer);
+  destroy_controller(controller, path);
+  return 0;
+}
+
+static char *all_tests() {
+  mu_run_test(test_null_pointers);
+  mu_run_test(test_record_and_compute);
+  mu_run_test(test_compute_buffer_too_small);
+  return 0;
+}
+
+int tests_run = 0;
+
+int main(int argc, char **argv) {
+  char *result = all_tests();
+  if (result != 0) {
+    fprintf(stderr, RED "ERROR: %s\n" RESET, result);
+  } else {
+    printf(GREEN "ALL TESTS PASSED\n" RESET);
+  }
+  printf("Tests run: %d\n", tests_run);
+
+  return result != 0;
+}
@@ -0,0 +1,19 @@
+#define RESET   "\033[0m"
+#define BLACK   "\033[30m"      /* Black */
+#define RED     "\033[31m"      /* Red */
+#define GREEN   "\033[32m"      /* Green */
+#define mu_assert(message, test) \
+    do                           \
+    {                            \
+        if (!(test))             \
+            return message;      \
+    } while (0)
+#define mu_run_test(test)       \
+    do                          \
+    {                           \
+        char *message = test(); \
+        tests_run++;            \
+        if (message)            \
+            return message;     \
+    } while (0)
+extern int tests_run;
@@ -0,0 +1,319 @@
+/* Generated with cbindgen:0.17.0 */
+
+/* Warning, this file is autogenerated by cbindgen. Don't modify this manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Return value upon success
+ */
+#define OK 0
+
+/**
+ * NULL pointer argument
+ */
+#define ERR_NULLPTR 1
+
+/**
+ * Invalid event type: the value is not one of the `ANALYTICS_EVENT_*` constants
+ */
+#define ERR_EVENT_TYPE 2
+
+/**
+ * Invalid event name: the name is NULL or not valid UTF-8
+ */
+#define ERR_EVENT_NAME 3
+
+/**
+ * Invalid screen route: the screen route is not valid UTF-8
+ */
+#define ERR_SCREEN_ROUTE 4
+
+/**
+ * Invalid timestamp: the timestamp is out of range
+ */
+#define ERR_TIMESTAMP 5
+
+/**
+ * Failed to read from or write to the storage
+ */
+#define ERR_STORAGE 6
+
+/**
+ * Failed to get the aggregation results: the buffer is too small
+ */
+#define ERR_COMPUTE_LEN 7
+
+/**
+ * Event type of Flutter's `AppLifeCyclesEvents` (or the equivalent in other frameworks)
+ */
+#define ANALYTICS_EVENT_APP_EVENT 0
+
+/**
+ * Event type of a known error logged by the developers
+ */
+#define ANALYTICS_EVENT_APP_ERROR 1
+
+/**
+ * Event type of the user entering a specific screen
+ */
+#define ANALYTICS_EVENT_SCREEN_ENTER 2
+
+/**
+ * Event type of a custom event logged by the developers
+ */
+#define ANALYTICS_EVENT_USER_ACTION 3
+
+/**
+ * Kind of a serialized `ScreenActiveTime` data point
+ */
+#define DATA_POINT_SCREEN_ACTIVE_TIME 0
+
+/**
+ * Kind of a serialized `ScreenEnterCount` data point
+ */
+#define DATA_POINT_SCREEN_ENTER_COUNT 1
+
+/**
+ * Kind of a serialized `WasActiveEachPastPeriod` data point
+ */
+#define DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD 2
+
+/**
+ * Kind of a serialized `WasActivePastNDays` data point
+ */
+#define DATA_POINT_WAS_ACTIVE_PAST_N_DAYS 3
+
+/**
+ * Unit of a serialized period of days
+ */
+#define PERIOD_UNIT_DAYS 0
+
+/**
+ * Unit of a serialized period of weeks
+ */
+#define PERIOD_UNIT_WEEKS 1
+
+/**
+ * Unit of a serialized period of months
+ */
+#define PERIOD_UNIT_MONTHS 2
+
+/**
+ * The `AnalyticsController` is the core component of the library. It exposes public functions to the FFI wrapper, and it’s responsible for:
+ * - Instantiating the other necessary components (`DataCombiner`, `Sender` and `IsarDb`)
+ * - Receiving incoming data recorded by the mobile framework (via FFI of course) and saving them to the db via `IsarDb`.
+ * - Checking if the library needs to send data to the XayNet coordinator via `Sender`.
+ * - Holding some simple state (`self.is_charging`, `self.is_connected_to_wifi`) so that it knows whether it’s appropriate to send data to XayNet.
+ *
+ * ## Arguments
+ *
+ * * `db` - Singleton instance of `IsarDb`, used to operate with the database.
+ * * `is_charging` - Boolean flag representing whether the phone is currently charging or not.
+ * * `is_connected_to_wifi` - Boolean flag representing whether the phone is currently connected to the wifi or not.
+ * * `last_time_data_sent` - Timestamp representing when analytics data was last sent to the coordinator. If `None`, data was never sent before.
+ * * `combiner` - `DataCombiner` component responsible for calculating `DataPoints` based on `AnalyticsEvents` and `ScreenRoutes`.
+ * * `sender` - `Sender` component responsible for preparing the message to be sent to the coordinator for aggregation.
+ * * `send_frequency_hours` - `Duration` in hours representing periods within which we want to send data to the coordinator only once.
+ */
+typedef struct AnalyticsController AnalyticsController;
+
+/**
+ * `FfiStr<'a>` is a safe (`#[repr(transparent)]`) wrapper around a
+ * nul-terminated `*const c_char` (e.g. a C string). Conceptually, it is
+ * similar to [`std::ffi::CStr`], except that it may be used in the signatures
+ * of extern "C" functions.
+ *
+ * Functions accepting strings should use this instead of accepting a C string
+ * directly. This allows us to write those functions using safe code without
+ * allowing safe Rust to cause memory unsafety.
+ *
+ * A single function for constructing these from Rust ([`FfiStr::from_raw`])
+ * has been provided. Most of the time, this should not be necessary, and users
+ * should accept `FfiStr` in the parameter list directly.
+ *
+ * ## Caveats
+ *
+ * An effort has been made to make this struct hard to misuse, however it is
+ * still possible, if the `'static` lifetime is manually specified in the
+ * struct. E.g.
+ *
+ * ```rust,no_run
+ * # use ffi_support::FfiStr;
+ * // NEVER DO THIS
+ * #[no_mangle]
+ * extern "C" fn never_do_this(s: FfiStr<'static>) {
+ *     // save `s` somewhere, and access it after this
+ *     // function returns.
+ * }
+ * ```
+ *
+ * Instead, one of the following patterns should be used:
+ *
+ * ```
+ * # use ffi_support::FfiStr;
+ * #[no_mangle]
+ * extern "C" fn valid_use_1(s: FfiStr<'_>) {
+ *     // Use of `s` after this function returns is impossible
+ * }
+ * // Alternative:
+ * #[no_mangle]
+ * extern "C" fn valid_use_2(s: FfiStr) {
+ *     // Use of `s` after this function returns is impossible
+ * }
+ * ```
+ */
+typedef const char *FfiStr;
+
+/**
+ * Create a new analytics controller, which stores its data in the directory at `path`.
+ * The directory must exist.
+ *
+ * # Return value
+ *
+ * - a NULL pointer if `path` is NULL or not valid UTF-8, or if the storage can't be
+ *   opened
+ * - a valid pointer to an `AnalyticsController` otherwise. It must be destroyed with
+ *   [`xaynet_analytics_destroy()`].
+ *
+ * # Safety
+ *
+ * `path` must be a valid NULL terminated string, or a NULL pointer.
+ */
+struct AnalyticsController *xaynet_analytics_new(FfiStr path);
+
+/**
+ * Destroy the analytics controller created by [`xaynet_analytics_new()`] and close its
+ * storage.
+ *
+ * # Return value
+ *
+ * - [`OK`] on success
+ * - [`ERR_NULLPTR`] if `controller` is NULL
+ * - [`ERR_STORAGE`] if the storage couldn't be closed. The controller is destroyed
+ *   nonetheless.
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. After destroying the `AnalyticsController`, the pointer becomes invalid and must
+ *    not be used.
+ * 3. This function should only be called on a pointer that has been created by
+ *    [`xaynet_analytics_new()`].
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+int xaynet_analytics_destroy(struct AnalyticsController *controller);
+
+/**
+ * Record an event of the given type.
+ *
+ * # Arguments
+ *
+ * - `event_type` must be one of the `ANALYTICS_EVENT_*` constants
+ * - `name` is the name of the event
+ * - `timestamp` is the time at which the event happened, in milliseconds since the Unix
+ *   epoch
+ * - `screen_route` is the name of the screen on which the event happened. It may be
+ *   NULL if the event doesn't relate to a specific screen.
+ *
+ * # Return value
+ *
+ * - [`OK`] if the event is recorded
+ * - [`ERR_NULLPTR`] if `controller` is NULL
+ * - [`ERR_EVENT_TYPE`] if `event_type` is invalid
+ * - [`ERR_EVENT_NAME`] if `name` is NULL or not valid UTF-8
+ * - [`ERR_SCREEN_ROUTE`] if `screen_route` is not valid UTF-8
+ * - [`ERR_TIMESTAMP`] if `timestamp` is out of range
+ * - [`ERR_STORAGE`] if the event couldn't be saved
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. `name` and `screen_route` must be valid NULL terminated strings, or NULL
+ *    pointers.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+int xaynet_analytics_record_event(const struct AnalyticsController *controller,
+                                  int event_type,
+                                  FfiStr name,
+                                  int64_t timestamp,
+                                  FfiStr screen_route);
+
+/**
+ * Compute the data points over the periods ending at `window_end`, and copy the
+ * serialized results into `buffer`.
+ *
+ * `window_end` is a timestamp in milliseconds since the Unix epoch. Only the events
+ * recorded before the midnight (UTC) preceding `window_end` are taken into account.
+ *
+ * When calling this function, `len` must point to the length of `buffer`. On return,
+ * it points to the length of the serialized results. To get the length of the results
+ * without copying them, `len` can point to `0`, in which case `buffer` may be NULL.
+ *
+ * # Serialization format
+ *
+ * All the integers are little endian. The results start with the number of data
+ * points, followed by the data points:
+ *
+ * | field           | type       | description                                  |
+ * |-----------------|------------|----------------------------------------------|
+ * | count           | `uint32_t` | number of data points                        |
+ * | data points     |            | `count` times the data point fields below    |
+ *
+ * Each data point is serialized as:
+ *
+ * | field           | type       | description                                  |
+ * |-----------------|------------|----------------------------------------------|
+ * | kind            | `uint8_t`  | one of the `DATA_POINT_*` constants          |
+ * | period unit     | `uint8_t`  | one of the `PERIOD_UNIT_*` constants         |
+ * | period length   | `uint32_t` | number of period units                       |
+ * | period end      | `int64_t`  | end of the period, in ms since the Unix epoch|
+ * | values count    | `uint32_t` | number of values                             |
+ * | values          | `uint32_t` | `values count` times the calculated values   |
+ *
+ * The `DATA_POINT_SCREEN_ACTIVE_TIME` data points come first, one per recorded screen
+ * route in the order in which the routes were first seen, followed by one for all the
+ * screens. Then come the `DATA_POINT_SCREEN_ENTER_COUNT` data points, one per screen
+ * route in the same order, followed by the `DATA_POINT_WAS_ACTIVE_EACH_PAST_PERIOD`
+ * and `DATA_POINT_WAS_ACTIVE_PAST_N_DAYS` data points.
+ *
+ * # Return value
+ *
+ * - [`OK`] if the results are copied into `buffer`
+ * - [`ERR_NULLPTR`] if `controller` or `len` is NULL, or if `buffer` is NULL while
+ *   `len` doesn't point to `0`
+ * - [`ERR_TIMESTAMP`] if `window_end` is out of range
+ * - [`ERR_STORAGE`] if the events couldn't be read
+ * - [`ERR_COMPUTE_LEN`] if `buffer` is too small for the results
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointers are NULL
+ *    *or* all of the following is true:
+ *    - The pointers must be properly [aligned].
+ *    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. `buffer` must be valid for writes of `len` bytes.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+int xaynet_analytics_compute(const struct AnalyticsController *controller,
+                             int64_t window_end,
+                             unsigned char *buffer,
+                             unsigned int *len);
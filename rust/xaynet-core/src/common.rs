@@ -1,7 +1,14 @@
-use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, num::NonZeroU64};
+
+use derive_more::Display;
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
 use sodiumoxide::{self, crypto::box_};
 
-use crate::{crypto::ByteObject, mask::MaskConfigPair, CoordinatorPublicKey};
+use crate::{
+    crypto::{encrypt::PublicEncryptKey, ByteObject},
+    mask::{seed::EncryptedMaskSeed, MaskConfigPair},
+    CoordinatorPublicKey,
+};
 
 /// The round parameters.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -18,6 +25,198 @@ pub struct RoundParameters {
     pub mask_config: MaskConfigPair,
     /// The length of the model.
     pub model_length: usize,
+    /// The id of the training plan the round belongs to, if the coordinator runs training
+    /// plans.
+    pub plan_id: Option<String>,
+    /// The sample count by which the update participants normalize the number of samples
+    /// their local models were trained on, if the coordinator sets one. Larger counts are
+    /// capped to it.
+    pub max_sample_count: Option<NonZeroU64>,
+}
+
+/// The name of a phase of the PET protocol.
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PhaseName {
+    #[display(fmt = "Idle")]
+    Idle,
+    #[display(fmt = "Sum")]
+    Sum,
+    #[display(fmt = "Update")]
+    Update,
+    #[display(fmt = "Sum2")]
+    Sum2,
+    #[display(fmt = "Unmask")]
+    Unmask,
+    #[display(fmt = "Failure")]
+    Failure,
+    #[display(fmt = "Shutdown")]
+    Shutdown,
+}
+
+/// The configured duration of a phase.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PhaseDuration {
+    /// The minimal amount of time (in seconds) reserved for processing messages.
+    pub min: u64,
+    /// The maximal amount of time (in seconds) permitted for processing messages.
+    pub max: u64,
+}
+
+/// The metadata of the current round.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoundMetadata {
+    /// The id of the round.
+    pub round_id: u64,
+    /// The current phase of the round.
+    pub phase: PhaseName,
+    /// The start of the current phase (in seconds since the UNIX epoch).
+    pub phase_start: u64,
+    /// The duration of the current phase, if the phase processes messages.
+    pub phase_duration: Option<PhaseDuration>,
+    /// Whether the coordinator accepts compressed multipart messages.
+    pub compression: bool,
+    /// Whether the global model changed since the previous round. If not, participants
+    /// that already downloaded the global model don't need to download it again. This is
+    /// the last field, so that the metadata stays readable by participants that don't
+    /// know it.
+    pub changed_since_previous: bool,
+}
+
+impl RoundMetadata {
+    /// Gets the time (in seconds since the UNIX epoch) from which the current phase may end.
+    pub fn phase_min_deadline(&self) -> Option<u64> {
+        self.phase_duration
+            .map(|duration| self.phase_start.saturating_add(duration.min))
+    }
+
+    /// Gets the time (in seconds since the UNIX epoch) at which the current phase ends at the
+    /// latest.
+    pub fn phase_max_deadline(&self) -> Option<u64> {
+        self.phase_duration
+            .map(|duration| self.phase_start.saturating_add(duration.max))
+    }
+}
+
+/// The configured number of messages of a phase.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PhaseCount {
+    /// The minimal number of messages required to end the phase.
+    pub min: u64,
+    /// The maximal number of messages accepted in the phase.
+    pub max: u64,
+}
+
+/// The number of messages the coordinator handled so far in the current phase.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PhaseStats {
+    /// The id of the round.
+    pub round_id: u64,
+    /// The current phase of the round.
+    pub phase: PhaseName,
+    /// The configured number of messages of the current phase, if the phase processes
+    /// messages.
+    pub count: Option<PhaseCount>,
+    /// The number of messages accepted in the current phase.
+    pub accepted: u64,
+    /// The number of messages rejected in the current phase, because they were invalid.
+    pub rejected: u64,
+    /// The number of messages discarded in the current phase, because the phase already
+    /// accepted the maximal number of messages.
+    pub discarded: u64,
+}
+
+impl PhaseStats {
+    /// Gets the number of messages that must still be accepted before the current phase
+    /// may end, if the phase processes messages.
+    pub fn missing(&self) -> Option<u64> {
+        self.count
+            .map(|count| count.min.saturating_sub(self.accepted))
+    }
+}
+
+/// Statistics about the training which are safe to show to the end users of the apps.
+///
+/// They don't reveal anything about single participants: the number of participants is
+/// only given approximately and not at all if too few participants took part.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PublicStats {
+    /// The id of the current round.
+    pub round_id: u64,
+    /// The number of rounds that completed with a new global model.
+    pub completed_rounds: u64,
+    /// The approximate number of participants that contributed to the latest global model,
+    /// if it is large enough to be published.
+    pub participants: Option<u64>,
+    /// The time at which the latest global model was published (in seconds since the UNIX
+    /// epoch), if any.
+    pub last_model_update: Option<u64>,
+}
+
+/// An event of a round which the coordinator pushes to the participants, so that they don't
+/// need to poll for it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RoundEvent {
+    /// The parameters of a new round are available.
+    NewRound { round_id: u64 },
+    /// The sum dictionary of the round is available.
+    SumDict { round_id: u64 },
+    /// The seed dictionary of the round is available.
+    SeedDict { round_id: u64 },
+    /// The global model of the round is published.
+    GlobalModel { round_id: u64 },
+    /// A phase of the round started.
+    Phase(PhaseEvent),
+}
+
+/// The start of a phase, as pushed by the coordinator. It is JSON encoded like
+/// `{"version":1,"event":"phase","round_id":3,"phase_name":"Sum","timestamp":1600000000}`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PhaseEvent {
+    /// The id of the round.
+    pub round_id: u64,
+    /// The phase which started.
+    pub phase_name: PhaseName,
+    /// The start of the phase (in seconds since the UNIX epoch).
+    pub timestamp: u64,
+}
+
+impl From<RoundMetadata> for PhaseEvent {
+    fn from(metadata: RoundMetadata) -> Self {
+        Self {
+            round_id: metadata.round_id,
+            phase_name: metadata.phase,
+            timestamp: metadata.phase_start,
+        }
+    }
+}
+
+/// A frame of the stream of [`RoundEvent`]s, which is JSON encoded like
+/// `{"version":1,"event":"new_round","round_id":3}`.
+///
+/// The frame carries the version of its format, so that the format can evolve. Frames of
+/// unknown versions or events can't be decoded, but they still tell that something
+/// happened in the round.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoundEventFrame {
+    /// The version of the format of the frame.
+    pub version: u8,
+    /// The event.
+    #[serde(flatten)]
+    pub event: RoundEvent,
+}
+
+impl RoundEventFrame {
+    /// The current version of the format of the frames.
+    pub const VERSION: u8 = 1;
+
+    /// Creates a frame of the current version.
+    pub fn new(event: RoundEvent) -> Self {
+        Self {
+            version: Self::VERSION,
+            event,
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -45,3 +244,232 @@ impl ByteObject for RoundSeed {
         self.0.as_ref()
     }
 }
+
+/// A wrapper to serialize a dictionary in its canonical form.
+///
+/// The dictionaries of the protocol ([`SumDict`], [`LocalSeedDict`], [`SeedDict`] and
+/// [`UpdateSeedDict`]) are [`HashMap`]s, whose iteration order differs from one
+/// instance to another. In the canonical form, the entries of a dictionary are sorted
+/// by the bytes of their keys, and so are the entries of the nested dictionaries. Hence
+/// the same dictionary is always serialized to the same bytes.
+///
+/// The canonical form only orders the entries and doesn't change the serialization
+/// format: it is deserialized as a regular [`HashMap`], which accepts the entries in
+/// any order.
+///
+/// ```
+/// # use xaynet_core::{common::Canonical, SumDict};
+/// # let dict = SumDict::new();
+/// let bytes = bincode::serialize(&Canonical(&dict)).unwrap();
+/// assert_eq!(bincode::deserialize::<SumDict>(&bytes).unwrap(), dict);
+/// ```
+///
+/// [`SumDict`]: crate::SumDict
+/// [`LocalSeedDict`]: crate::LocalSeedDict
+/// [`SeedDict`]: crate::SeedDict
+/// [`UpdateSeedDict`]: crate::UpdateSeedDict
+#[derive(Debug, Clone, Copy)]
+pub struct Canonical<'a, T>(pub &'a T);
+
+impl<T: CanonicalSerialize> Serialize for Canonical<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_canonical(serializer)
+    }
+}
+
+/// A value which can be serialized in a canonical form.
+pub trait CanonicalSerialize {
+    /// Serializes the value in its canonical form.
+    fn serialize_canonical<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+impl CanonicalSerialize for PublicEncryptKey {
+    fn serialize_canonical<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+}
+
+impl CanonicalSerialize for EncryptedMaskSeed {
+    fn serialize_canonical<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+}
+
+impl<K, V> CanonicalSerialize for HashMap<K, V>
+where
+    K: ByteObject + Serialize,
+    V: CanonicalSerialize,
+{
+    fn serialize_canonical<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in sorted_entries(self) {
+            map.serialize_entry(key, &Canonical(value))?;
+        }
+        map.end()
+    }
+}
+
+/// Gets the entries of a dictionary, sorted by the bytes of their keys.
+pub(crate) fn sorted_entries<K: ByteObject, V>(dict: &HashMap<K, V>) -> Vec<(&K, &V)> {
+    let mut entries = dict.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by(|(k1, _), (k2, _)| k1.as_slice().cmp(k2.as_slice()));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::RandomState;
+
+    use super::*;
+    use crate::{
+        crypto::{EncryptKeyPair, SigningKeyPair},
+        SeedDict,
+        SumDict,
+        UpdateSeedDict,
+    };
+
+    /// Creates two equal dictionaries with differently seeded hashers and insertion
+    /// orders, hence most likely with different iteration orders.
+    fn dicts<K, V>(entries: Vec<(K, V)>) -> (HashMap<K, V>, HashMap<K, V>)
+    where
+        K: Clone + Eq + std::hash::Hash,
+        V: Clone,
+    {
+        let mut first = HashMap::with_hasher(RandomState::new());
+        let mut second = HashMap::with_hasher(RandomState::new());
+        for (key, value) in entries.iter().cloned() {
+            first.insert(key, value);
+        }
+        for (key, value) in entries.into_iter().rev() {
+            second.insert(key, value);
+        }
+        (first, second)
+    }
+
+    fn sum_dict_entries() -> Vec<(
+        crate::SumParticipantPublicKey,
+        crate::SumParticipantEphemeralPublicKey,
+    )> {
+        (0..32)
+            .map(|_| {
+                (
+                    SigningKeyPair::generate().public,
+                    EncryptKeyPair::generate().public,
+                )
+            })
+            .collect()
+    }
+
+    fn update_seed_dict() -> UpdateSeedDict {
+        (0..8)
+            .map(|i| {
+                (
+                    SigningKeyPair::generate().public,
+                    EncryptedMaskSeed::from(vec![i; EncryptedMaskSeed::LENGTH]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_canonical_sum_dict() {
+        let (first, second): (SumDict, SumDict) = dicts(sum_dict_entries());
+        assert_eq!(first, second);
+
+        let bytes = bincode::serialize(&Canonical(&first)).unwrap();
+        assert_eq!(bytes, bincode::serialize(&Canonical(&second)).unwrap());
+        assert_eq!(bincode::deserialize::<SumDict>(&bytes).unwrap(), first);
+    }
+
+    #[test]
+    fn test_canonical_seed_dict() {
+        let entries = (0..8)
+            .map(|_| (SigningKeyPair::generate().public, update_seed_dict()))
+            .collect::<Vec<_>>();
+        let (first, second): (SeedDict, SeedDict) = dicts(entries);
+
+        let bytes = bincode::serialize(&Canonical(&first)).unwrap();
+        assert_eq!(bytes, bincode::serialize(&Canonical(&second)).unwrap());
+        assert_eq!(bincode::deserialize::<SeedDict>(&bytes).unwrap(), first);
+    }
+
+    #[test]
+    fn test_unordered_dict_compatibility() {
+        // dictionaries serialized without the canonical form can still be deserialized
+        let (dict, _): (SumDict, SumDict) = dicts(sum_dict_entries());
+        let bytes = bincode::serialize(&dict).unwrap();
+        assert_eq!(bincode::deserialize::<SumDict>(&bytes).unwrap(), dict);
+        // and the canonical form only differs in the order of the entries
+        assert_eq!(
+            bytes.len(),
+            bincode::serialize(&Canonical(&dict)).unwrap().len()
+        );
+    }
+
+    #[test]
+    fn test_round_metadata_deadlines() {
+        let mut metadata = RoundMetadata {
+            round_id: 1,
+            phase: PhaseName::Sum,
+            phase_start: 100,
+            phase_duration: Some(PhaseDuration { min: 10, max: 60 }),
+            compression: false,
+            changed_since_previous: true,
+        };
+        assert_eq!(metadata.phase_min_deadline(), Some(110));
+        assert_eq!(metadata.phase_max_deadline(), Some(160));
+
+        metadata.phase = PhaseName::Idle;
+        metadata.phase_duration = None;
+        assert_eq!(metadata.phase_min_deadline(), None);
+        assert_eq!(metadata.phase_max_deadline(), None);
+    }
+
+    #[test]
+    fn test_round_metadata_compatibility() {
+        // the metadata as known by participants that don't compress their messages
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct OldRoundMetadata {
+            round_id: u64,
+            phase: PhaseName,
+            phase_start: u64,
+            phase_duration: Option<PhaseDuration>,
+        }
+
+        let metadata = RoundMetadata {
+            round_id: 1,
+            phase: PhaseName::Update,
+            phase_start: 100,
+            phase_duration: Some(PhaseDuration { min: 10, max: 60 }),
+            compression: true,
+            changed_since_previous: false,
+        };
+        let bytes = bincode::serialize(&metadata).unwrap();
+        assert_eq!(
+            bincode::deserialize::<OldRoundMetadata>(&bytes).unwrap(),
+            OldRoundMetadata {
+                round_id: 1,
+                phase: PhaseName::Update,
+                phase_start: 100,
+                phase_duration: Some(PhaseDuration { min: 10, max: 60 }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_phase_stats_missing() {
+        let mut stats = PhaseStats {
+            round_id: 1,
+            phase: PhaseName::Update,
+            count: Some(PhaseCount { min: 40, max: 100 }),
+            accepted: 3,
+            rejected: 1,
+            discarded: 0,
+        };
+        assert_eq!(stats.missing(), Some(37));
+        stats.accepted = 41;
+        assert_eq!(stats.missing(), Some(0));
+        stats.count = None;
+        assert_eq!(stats.missing(), None);
+    }
+}
@@ -7,7 +7,7 @@
 
 use derive_more::{AsMut, AsRef, From};
 use serde::{Deserialize, Serialize};
-use sodiumoxide::crypto::{box_, sealedbox};
+use sodiumoxide::crypto::{box_, generichash, sealedbox};
 
 use super::ByteObject;
 
@@ -88,6 +88,28 @@ impl PublicEncryptKey {
     pub fn encrypt(&self, m: &[u8]) -> Vec<u8> {
         sealedbox::seal(m, self.as_ref())
     }
+
+    /// Encrypts a message `m` with this public key like [`encrypt()`], but with an ephemeral key
+    /// pair derived from the given `seed` instead of a random one.
+    ///
+    /// The ciphertext is decrypted like any other, but it is reproducible: anyone who knows the
+    /// seed can decrypt it. This is meant for reproducible tests only.
+    ///
+    /// [`encrypt()`]: PublicEncryptKey::encrypt
+    pub fn encrypt_with_seed(&self, m: &[u8], seed: &EncryptKeySeed) -> Vec<u8> {
+        // a sealed box is the ephemeral public key followed by the box of the message, whose
+        // nonce is the hash of the ephemeral and the recipient public keys
+        let (ephm_pk, ephm_sk) = seed.derive_encrypt_key_pair();
+        // safe unwraps: the nonce length is a valid hash length
+        let mut hasher = generichash::State::new(Some(box_::NONCEBYTES), None).unwrap();
+        hasher.update(ephm_pk.as_slice()).unwrap();
+        hasher.update(self.as_slice()).unwrap();
+        let nonce = box_::Nonce::from_slice(hasher.finalize().unwrap().as_ref()).unwrap();
+
+        let mut c = ephm_pk.as_slice().to_vec();
+        c.extend(box_::seal(m, &nonce, self.as_ref(), ephm_sk.as_ref()));
+        c
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -37,7 +37,14 @@ pub use self::{
     encrypt::{EncryptKeyPair, EncryptKeySeed, PublicEncryptKey, SecretEncryptKey, SEALBYTES},
     hash::Sha256,
     prng::generate_integer,
-    sign::{PublicSigningKey, SecretSigningKey, Signature, SigningKeyPair, SigningKeySeed},
+    sign::{
+        verify_batch,
+        PublicSigningKey,
+        SecretSigningKey,
+        Signature,
+        SigningKeyPair,
+        SigningKeySeed,
+    },
 };
 
 /// An interface for slicing into cryptographic byte objects.
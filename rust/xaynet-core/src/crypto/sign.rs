@@ -12,6 +12,8 @@ use num::{
     bigint::{BigUint, ToBigInt},
     rational::Ratio,
 };
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use sodiumoxide::crypto::{hash::sha256, sign};
 
@@ -72,6 +74,21 @@ impl PublicSigningKey {
     }
 }
 
+/// Verifies a batch of detached signatures.
+///
+/// Returns for each `(pk, m, s)` item, in the same order, whether `s` is a valid signature of
+/// the message `m` for the public key `pk`. The results are identical to those of
+/// [`PublicSigningKey::verify_detached()`] for each item. libsodium doesn't support batch
+/// verification, hence the signatures are verified one by one, in parallel if the `rayon`
+/// feature is enabled.
+pub fn verify_batch(items: &[(PublicSigningKey, &[u8], Signature)]) -> Vec<bool> {
+    #[cfg(feature = "rayon")]
+    let items = items.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let items = items.iter();
+    items.map(|(pk, m, s)| pk.verify_detached(s, m)).collect()
+}
+
 impl ByteObject for PublicSigningKey {
     const LENGTH: usize = sign::PUBLICKEYBYTES;
 
@@ -255,4 +272,30 @@ mod tests {
         ]);
         assert!(!sig.is_eligible(0.5_f64));
     }
+
+    #[test]
+    fn test_verify_batch() {
+        let messages = (0..16_u8).map(|i| vec![i; 32]).collect::<Vec<_>>();
+        let mut items = messages
+            .iter()
+            .map(|m| {
+                let keys = SigningKeyPair::generate();
+                (keys.public, m.as_slice(), keys.secret.sign_detached(m))
+            })
+            .collect::<Vec<_>>();
+        assert!(verify_batch(&[]).is_empty());
+        assert_eq!(verify_batch(&items), vec![true; 16]);
+
+        // exactly one signature is invalid
+        items[7].2 = items[8].2;
+        let mut expected = vec![true; 16];
+        expected[7] = false;
+        assert_eq!(verify_batch(&items), expected);
+        for (pk, m, s) in &items {
+            assert_eq!(
+                verify_batch(&[(*pk, *m, *s)]),
+                vec![pk.verify_detached(s, m)]
+            );
+        }
+    }
 }
@@ -72,6 +72,14 @@ pub enum DataType {
     I32 = 2,
     /// Numbers of type i64.
     I64 = 3,
+    /// Numbers of type u8.
+    U8 = 4,
+    /// Numbers of type i16.
+    I16 = 5,
+    /// Numbers of type f16 (half precision).
+    ///
+    /// The conversions between models and `half::f16` primitives require the `f16` feature.
+    F16 = 6,
 }
 
 impl TryFrom<u8> for DataType {
@@ -83,6 +91,9 @@ impl TryFrom<u8> for DataType {
             1 => Ok(DataType::F64),
             2 => Ok(DataType::I32),
             3 => Ok(DataType::I64),
+            4 => Ok(DataType::U8),
+            5 => Ok(DataType::I16),
+            6 => Ok(DataType::F16),
             _ => Err(InvalidMaskConfigError::DataType),
         }
     }
@@ -178,7 +189,7 @@ impl MaskConfig {
     ///
     /// # Panics
     /// Panics if the bytes per number can't be represented as usize.
-    pub(crate) fn bytes_per_number(&self) -> usize {
+    pub fn bytes_per_number(&self) -> usize {
         let max_number = self.order() - BigUint::from(1_u8);
         let bpn = (max_number.bits() + 7) / 8;
 
@@ -195,7 +206,7 @@ impl MaskConfig {
     /// Gets the additional shift value for masking/unmasking.
     pub fn add_shift(&self) -> Ratio<BigInt> {
         use BoundType::{Bmax, B0, B2, B4, B6};
-        use DataType::{F32, F64, I32, I64};
+        use DataType::{F16, F32, F64, I16, I32, I64, U8};
 
         match self.bound_type {
             B0 => Ratio::from_integer(BigInt::from(1)),
@@ -208,6 +219,10 @@ impl MaskConfig {
                 F64 => Ratio::from_float(f64::MAX).unwrap(),
                 I32 => Ratio::from_integer(-BigInt::from(i32::MIN)),
                 I64 => Ratio::from_integer(-BigInt::from(i64::MIN)),
+                U8 => Ratio::from_integer(BigInt::from(u8::MAX)),
+                I16 => Ratio::from_integer(-BigInt::from(i16::MIN)),
+                // the largest finite f16
+                F16 => Ratio::from_integer(BigInt::from(65_504)),
             },
         }
     }
@@ -215,7 +230,7 @@ impl MaskConfig {
     /// Gets the exponential shift value for masking/unmasking.
     pub fn exp_shift(&self) -> BigInt {
         use BoundType::{Bmax, B0, B2, B4, B6};
-        use DataType::{F32, F64, I32, I64};
+        use DataType::{F16, F32, F64, I16, I32, I64, U8};
 
         match self.data_type {
             F32 => match self.bound_type {
@@ -226,17 +241,32 @@ impl MaskConfig {
                 B0 | B2 | B4 | B6 => BigInt::from(10).pow(20_u8),
                 Bmax => BigInt::from(10).pow(324_u16),
             },
-            I32 | I64 => BigInt::from(10).pow(10_u8),
+            F16 => match self.bound_type {
+                B0 | B2 | B4 | B6 => BigInt::from(10).pow(5_u8),
+                Bmax => BigInt::from(10).pow(8_u8),
+            },
+            I32 | I64 | U8 | I16 => BigInt::from(10).pow(10_u8),
         }
     }
 
     /// Gets the finite group order value for masking/unmasking.
     pub fn order(&self) -> BigUint {
         use BoundType::{Bmax, B0, B2, B4, B6};
-        use DataType::{F32, F64, I32, I64};
+        use DataType::{F16, F32, F64, I16, I32, I64, U8};
         use GroupType::{Integer, Power2, Prime};
         use ModelType::{M12, M3, M6, M9};
 
+        // the bounded weights of all integer types have the same shifts, hence the same orders
+        if let U8 | I16 = self.data_type {
+            if self.bound_type != Bmax {
+                return MaskConfig {
+                    data_type: I32,
+                    ..*self
+                }
+                .order();
+            }
+        }
+
         let order_str = match self.group_type {
             Integer => match self.data_type {
                 F32 => match self.bound_type {
@@ -367,6 +397,51 @@ impl MaskConfig {
                         M12 => "184_467_440_737_095_516_150_000_000_000_000_000_000_001",
                     }
                 }
+                // the bound type of U8 and I16 is `Bmax`, see above
+                U8 => match self.model_type {
+                    M3 => "5_100_000_000_000_001",
+                    M6 => "5_100_000_000_000_000_001",
+                    M9 => "5_100_000_000_000_000_000_001",
+                    M12 => "5_100_000_000_000_000_000_000_001",
+                }
+                I16 => match self.model_type {
+                    M3 => "655_350_000_000_000_001",
+                    M6 => "655_350_000_000_000_000_001",
+                    M9 => "655_350_000_000_000_000_000_001",
+                    M12 => "655_350_000_000_000_000_000_000_001",
+                }
+                F16 => match self.bound_type {
+                    B0 => match self.model_type {
+                        M3 => "200_000_001",
+                        M6 => "200_000_000_001",
+                        M9 => "200_000_000_000_001",
+                        M12 => "200_000_000_000_000_001",
+                    }
+                    B2 => match self.model_type {
+                        M3 => "20_000_000_001",
+                        M6 => "20_000_000_000_001",
+                        M9 => "20_000_000_000_000_001",
+                        M12 => "20_000_000_000_000_000_001",
+                    }
+                    B4 => match self.model_type {
+                        M3 => "2_000_000_000_001",
+                        M6 => "2_000_000_000_000_001",
+                        M9 => "2_000_000_000_000_000_001",
+                        M12 => "2_000_000_000_000_000_000_001",
+                    }
+                    B6 => match self.model_type {
+                        M3 => "200_000_000_000_001",
+                        M6 => "200_000_000_000_000_001",
+                        M9 => "200_000_000_000_000_000_001",
+                        M12 => "200_000_000_000_000_000_000_001",
+                    }
+                    Bmax => match self.model_type {
+                        M3 => "13_100_800_000_000_001",
+                        M6 => "13_100_800_000_000_000_001",
+                        M9 => "13_100_800_000_000_000_000_001",
+                        M12 => "13_100_800_000_000_000_000_000_001",
+                    }
+                }
             }
             Prime => match self.data_type {
                 F32 => match self.bound_type {
@@ -497,6 +572,51 @@ impl MaskConfig {
                         M12 => "184_467_440_737_095_516_150_000_000_000_000_000_000_089",
                     }
                 }
+                // the bound type of U8 and I16 is `Bmax`, see above
+                U8 => match self.model_type {
+                    M3 => "5_100_000_000_000_007",
+                    M6 => "5_100_000_000_000_000_091",
+                    M9 => "5_100_000_000_000_000_000_013",
+                    M12 => "5_100_000_000_000_000_000_000_013",
+                }
+                I16 => match self.model_type {
+                    M3 => "655_350_000_000_000_103",
+                    M6 => "655_350_000_000_000_000_029",
+                    M9 => "655_350_000_000_000_000_000_089",
+                    M12 => "655_350_000_000_000_000_000_000_037",
+                }
+                F16 => match self.bound_type {
+                    B0 => match self.model_type {
+                        M3 => "200_000_033",
+                        M6 => "200_000_000_041",
+                        M9 => "200_000_000_000_027",
+                        M12 => "200_000_000_000_000_003",
+                    }
+                    B2 => match self.model_type {
+                        M3 => "20_000_000_089",
+                        M6 => "20_000_000_000_021",
+                        M9 => "20_000_000_000_000_003",
+                        M12 => "20_000_000_000_000_000_011",
+                    }
+                    B4 => match self.model_type {
+                        M3 => "2_000_000_000_003",
+                        M6 => "2_000_000_000_000_021",
+                        M9 => "2_000_000_000_000_000_057",
+                        M12 => "2_000_000_000_000_000_000_069",
+                    }
+                    B6 => match self.model_type {
+                        M3 => "200_000_000_000_027",
+                        M6 => "200_000_000_000_000_003",
+                        M9 => "200_000_000_000_000_000_089",
+                        M12 => "200_000_000_000_000_000_000_069",
+                    }
+                    Bmax => match self.model_type {
+                        M3 => "13_100_800_000_000_189",
+                        M6 => "13_100_800_000_000_000_007",
+                        M9 => "13_100_800_000_000_000_000_003",
+                        M12 => "13_100_800_000_000_000_000_000_019",
+                    }
+                }
             },
             Power2 => match self.data_type {
                 F32 => match self.bound_type {
@@ -627,6 +747,51 @@ impl MaskConfig {
                         M12 => "348_449_143_727_040_986_586_495_598_010_130_648_530_944",
                     }
                 }
+                // the bound type of U8 and I16 is `Bmax`, see above
+                U8 => match self.model_type {
+                    M3 => "9_007_199_254_740_992",
+                    M6 => "9_223_372_036_854_775_808",
+                    M9 => "9_444_732_965_739_290_427_392",
+                    M12 => "9_671_406_556_917_033_397_649_408",
+                }
+                I16 => match self.model_type {
+                    M3 => "1_152_921_504_606_846_976",
+                    M6 => "1_180_591_620_717_411_303_424",
+                    M9 => "1_208_925_819_614_629_174_706_176",
+                    M12 => "1_237_940_039_285_380_274_899_124_224",
+                }
+                F16 => match self.bound_type {
+                    B0 => match self.model_type {
+                        M3 => "268_435_456",
+                        M6 => "274_877_906_944",
+                        M9 => "281_474_976_710_656",
+                        M12 => "288_230_376_151_711_744",
+                    }
+                    B2 => match self.model_type {
+                        M3 => "34_359_738_368",
+                        M6 => "35_184_372_088_832",
+                        M9 => "36_028_797_018_963_968",
+                        M12 => "36_893_488_147_419_103_232",
+                    }
+                    B4 => match self.model_type {
+                        M3 => "2_199_023_255_552",
+                        M6 => "2_251_799_813_685_248",
+                        M9 => "2_305_843_009_213_693_952",
+                        M12 => "2_361_183_241_434_822_606_848",
+                    }
+                    B6 => match self.model_type {
+                        M3 => "281_474_976_710_656",
+                        M6 => "288_230_376_151_711_744",
+                        M9 => "295_147_905_179_352_825_856",
+                        M12 => "302_231_454_903_657_293_676_544",
+                    }
+                    Bmax => match self.model_type {
+                        M3 => "18_014_398_509_481_984",
+                        M6 => "18_446_744_073_709_551_616",
+                        M9 => "18_889_465_931_478_580_854_784",
+                        M12 => "19_342_813_113_834_066_795_298_816",
+                    }
+                }
             }
         };
         // safe unwrap: string and radix are valid
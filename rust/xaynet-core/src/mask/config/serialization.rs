@@ -221,4 +221,19 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn serialize_f16() {
+        let config = MaskConfig {
+            group_type: GroupType::Power2,
+            data_type: DataType::F16,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        };
+
+        let mut buf = vec![0xff; 4];
+        config.to_bytes(&mut buf);
+        assert_eq!(buf, vec![2, 6, 0, 3]);
+        assert_eq!(MaskConfig::from_byte_slice(&buf).unwrap(), config);
+    }
 }
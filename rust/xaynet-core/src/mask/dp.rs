@@ -0,0 +1,289 @@
+//! Differential privacy of the masked models.
+//!
+//! See [`Masker::new_with_dp()`] for details.
+//!
+//! [`Masker::new_with_dp()`]: crate::mask::Masker::new_with_dp
+
+use num::{bigint::BigInt, clamp, rational::Ratio, traits::ToPrimitive};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rand_distr::{Distribution, Exp, Normal};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::mask::model::Model;
+
+/// The mechanism that adds the noise to the weights of a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DpMechanism {
+    /// Gaussian noise, calibrated to the L2 sensitivity of the model. It gives
+    /// (ε, δ)-differential privacy for `0 < ε < 1` and `0 < δ < 1`.
+    Gaussian,
+    /// Laplace noise, calibrated to the L1 sensitivity of the model. It gives
+    /// ε-differential privacy, the δ is ignored.
+    Laplace,
+}
+
+/// The differential privacy guarantee of a participant, which is given to the whole model
+/// that it masks.
+///
+/// The weights of the model are clipped to `[-sensitivity, sensitivity]`, hence replacing
+/// the model of a participant by any other one changes each weight by at most
+/// `2 * sensitivity`. The noise of the mechanism is calibrated to this bound and to the
+/// number of weights of the model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DpConfig {
+    /// The privacy budget ε.
+    pub epsilon: f64,
+    /// The probability δ that the privacy budget is exceeded.
+    pub delta: f64,
+    /// The bound of the absolute values of the weights.
+    pub sensitivity: f64,
+    /// The noise mechanism.
+    pub mechanism: DpMechanism,
+}
+
+/// Invalid [`DpConfig`] values.
+#[derive(Debug, Error)]
+pub enum InvalidDpConfig {
+    #[error("the sensitivity must be positive and finite")]
+    Sensitivity,
+    #[error("the epsilon must be positive and finite, and less than one for Gaussian noise")]
+    Epsilon,
+    #[error("the delta must be between zero and one for Gaussian noise")]
+    Delta,
+}
+
+impl DpConfig {
+    /// Checks the values of the configuration.
+    ///
+    /// # Errors
+    /// Fails if the sensitivity is not positive and finite, if the epsilon is not positive and
+    /// finite (and less than one for Gaussian noise), or if the delta of Gaussian noise is
+    /// not between zero and one.
+    pub fn validate(&self) -> Result<(), InvalidDpConfig> {
+        if !(self.sensitivity.is_finite() && self.sensitivity > 0.0) {
+            return Err(InvalidDpConfig::Sensitivity);
+        }
+        if !(self.epsilon.is_finite() && self.epsilon > 0.0) {
+            return Err(InvalidDpConfig::Epsilon);
+        }
+        if let DpMechanism::Gaussian = self.mechanism {
+            if self.epsilon >= 1.0 {
+                return Err(InvalidDpConfig::Epsilon);
+            }
+            if !(self.delta > 0.0 && self.delta < 1.0) {
+                return Err(InvalidDpConfig::Delta);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the standard deviation of the noise added to each weight of a model of
+    /// `model_len` weights.
+    pub fn noise_std(&self, model_len: usize) -> f64 {
+        let len = model_len.max(1) as f64;
+        match self.mechanism {
+            DpMechanism::Gaussian => {
+                let l2_sensitivity = 2.0 * self.sensitivity * len.sqrt();
+                l2_sensitivity * (2.0 * (1.25 / self.delta).ln()).sqrt() / self.epsilon
+            }
+            DpMechanism::Laplace => {
+                let l1_sensitivity = 2.0 * self.sensitivity * len;
+                std::f64::consts::SQRT_2 * l1_sensitivity / self.epsilon
+            }
+        }
+    }
+}
+
+/// The differential privacy of a masked model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DpReport {
+    /// The privacy budget ε that is spent. It is less than the configured one if the weights
+    /// are clipped to tighter bounds than the sensitivity, because the masking configuration
+    /// can't represent larger scaled weights.
+    pub actual_epsilon: f64,
+    /// The standard deviation of the noise added to each weight.
+    pub noise_std: f64,
+}
+
+/// The differential privacy settings of a masker, along with the PRNG of the noise.
+///
+/// The noise must not be derived from the mask seed, which the sum participants learn.
+pub(crate) struct DpNoise {
+    config: DpConfig,
+    rng: ChaCha20Rng,
+}
+
+impl DpNoise {
+    pub(crate) fn new(config: DpConfig) -> Self {
+        Self {
+            config,
+            rng: ChaCha20Rng::from_entropy(),
+        }
+    }
+
+    pub(crate) fn with_rng(config: DpConfig, rng: ChaCha20Rng) -> Self {
+        Self { config, rng }
+    }
+
+    /// Calibrates the noise for a model of `model_len` weights. The weights are clipped to
+    /// the sensitivity, but at most to `max_weight`, beyond which the masking clamps them.
+    pub(crate) fn calibrate(
+        self,
+        model_len: usize,
+        max_weight: Option<Ratio<BigInt>>,
+    ) -> DpMasking {
+        let Self { config, rng } = self;
+        // UNWRAP_SAFE: the sensitivity is positive and finite
+        let sensitivity = Ratio::from_float(config.sensitivity).unwrap();
+        let bound = match max_weight {
+            Some(max_weight) if max_weight < sensitivity => max_weight,
+            _ => sensitivity,
+        };
+        let clip_ratio = bound.to_f64().unwrap_or(config.sensitivity) / config.sensitivity;
+        let noise_std = config.noise_std(model_len);
+        let noise = match config.mechanism {
+            // UNWRAP_SAFE: the standard deviations are positive and finite
+            DpMechanism::Gaussian => Noise::Gaussian(Normal::new(0.0, noise_std).unwrap()),
+            DpMechanism::Laplace => {
+                Noise::Laplace(Exp::new(std::f64::consts::SQRT_2 / noise_std).unwrap())
+            }
+        };
+        DpMasking {
+            lower_bound: -&bound,
+            upper_bound: bound,
+            noise,
+            rng,
+            report: DpReport {
+                actual_epsilon: config.epsilon * clip_ratio,
+                noise_std,
+            },
+        }
+    }
+}
+
+enum Noise {
+    Gaussian(Normal<f64>),
+    /// A Laplace distribution, which is sampled as the difference of two exponential
+    /// distributions.
+    Laplace(Exp<f64>),
+}
+
+impl Noise {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        match self {
+            Self::Gaussian(normal) => normal.sample(rng),
+            Self::Laplace(exp) => exp.sample(rng) - exp.sample(rng),
+        }
+    }
+}
+
+/// Clips the weights of a model and adds the noise to them, before the model is masked.
+pub(crate) struct DpMasking {
+    lower_bound: Ratio<BigInt>,
+    upper_bound: Ratio<BigInt>,
+    noise: Noise,
+    rng: ChaCha20Rng,
+    report: DpReport,
+}
+
+impl DpMasking {
+    /// Clips the weights of the `chunk` and adds the noise to them.
+    pub(crate) fn privatize(&mut self, chunk: &Model) -> Model {
+        let Self {
+            lower_bound,
+            upper_bound,
+            noise,
+            rng,
+            ..
+        } = self;
+        chunk
+            .iter()
+            .map(|weight| {
+                let clipped = clamp(weight, &*lower_bound, &*upper_bound);
+                // UNWRAP_SAFE: the noise is finite
+                clipped + Ratio::from_float(noise.sample(rng)).unwrap()
+            })
+            .collect()
+    }
+
+    pub(crate) fn report(&self) -> DpReport {
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let config = DpConfig {
+            epsilon: 0.5,
+            delta: 1e-5,
+            sensitivity: 1.,
+            mechanism: DpMechanism::Gaussian,
+        };
+        assert!(config.validate().is_ok());
+        assert!(matches!(
+            DpConfig {
+                sensitivity: f64::INFINITY,
+                ..config
+            }
+            .validate(),
+            Err(InvalidDpConfig::Sensitivity),
+        ));
+        assert!(matches!(
+            DpConfig {
+                epsilon: 1.,
+                ..config
+            }
+            .validate(),
+            Err(InvalidDpConfig::Epsilon),
+        ));
+        assert!(matches!(
+            DpConfig {
+                delta: 0.,
+                ..config
+            }
+            .validate(),
+            Err(InvalidDpConfig::Delta),
+        ));
+
+        // the laplace mechanism allows any positive epsilon and ignores the delta
+        let config = DpConfig {
+            epsilon: 2.,
+            delta: 0.,
+            mechanism: DpMechanism::Laplace,
+            ..config
+        };
+        assert!(config.validate().is_ok());
+        assert!(matches!(
+            DpConfig {
+                epsilon: 0.,
+                ..config
+            }
+            .validate(),
+            Err(InvalidDpConfig::Epsilon),
+        ));
+    }
+
+    #[test]
+    fn test_noise_std() {
+        let config = DpConfig {
+            epsilon: 0.5,
+            delta: 1.25 * (-0.5_f64).exp(),
+            sensitivity: 1.,
+            mechanism: DpMechanism::Gaussian,
+        };
+        // the l2 sensitivity of 4 weights is 4
+        assert!((config.noise_std(4) - 8.).abs() < 1e-9);
+        let config = DpConfig {
+            mechanism: DpMechanism::Laplace,
+            ..config
+        };
+        // the l1 sensitivity of 4 weights is 8
+        assert!((config.noise_std(4) - 16. * std::f64::consts::SQRT_2).abs() < 1e-9);
+    }
+}
@@ -6,21 +6,27 @@
 
 use std::iter::{self, Iterator};
 
+#[cfg(feature = "differential-privacy")]
+use num::Zero;
 use num::{
     bigint::{BigInt, BigUint, ToBigInt},
     clamp,
     rational::Ratio,
-    traits::clamp_max,
 };
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+#[cfg(feature = "differential-privacy")]
+use crate::mask::dp::{DpConfig, DpMasking, DpNoise, DpReport, InvalidDpConfig};
 use crate::{
     crypto::{prng::generate_integer, ByteObject},
     mask::{
-        config::MaskConfigPair,
-        model::Model,
+        config::{MaskConfig, MaskConfigPair},
+        model::{Model, ModelShape, ModelShapeError},
         object::{MaskObject, MaskUnit, MaskVect},
         scalar::Scalar,
         seed::MaskSeed,
@@ -69,12 +75,15 @@ pub enum AggregationError {
     ScalarMismatch,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// An aggregator for masks and masked models.
 pub struct Aggregation {
     nb_models: usize,
     object: MaskObject,
     object_size: usize,
+    /// The shape of the aggregated models, which is metadata only and never serialized.
+    #[serde(skip)]
+    shape: Option<ModelShape>,
 }
 
 impl From<MaskObject> for Aggregation {
@@ -83,6 +92,7 @@ impl From<MaskObject> for Aggregation {
             nb_models: 1,
             object_size: object.vect.data.len(),
             object,
+            shape: None,
         }
     }
 }
@@ -101,6 +111,7 @@ impl Aggregation {
             nb_models: 0,
             object: MaskObject::empty(config, object_size),
             object_size,
+            shape: None,
         }
     }
 
@@ -109,6 +120,11 @@ impl Aggregation {
         self.object_size
     }
 
+    /// Gets the number of aggregated masks or masked models.
+    pub fn nb_models(&self) -> usize {
+        self.nb_models
+    }
+
     /// Gets the masking configurations of the aggregator.
     pub fn config(&self) -> MaskConfigPair {
         MaskConfigPair {
@@ -117,6 +133,29 @@ impl Aggregation {
         }
     }
 
+    /// Gets the shape of the aggregated models, if known.
+    pub fn shape(&self) -> Option<&ModelShape> {
+        self.shape.as_ref()
+    }
+
+    /// Sets the shape of the aggregated models, which is attached to the unmasked model.
+    ///
+    /// The models are aggregated independently of their shapes, hence only the first shape is
+    /// kept and any later one is ignored.
+    ///
+    /// # Errors
+    /// Fails if the number of weights of the shape doesn't match the length of the aggregated
+    /// mask object.
+    pub fn set_shape(&mut self, shape: ModelShape) -> Result<(), ModelShapeError> {
+        if shape.nb_weights() != Some(self.object_size) {
+            return Err(ModelShapeError(self.object_size));
+        }
+        if self.shape.is_none() {
+            self.shape = Some(shape);
+        }
+        Ok(())
+    }
+
     /// Validates if unmasking of the aggregated masked model with the given `mask` may be
     /// safely performed.
     ///
@@ -185,7 +224,11 @@ impl Aggregation {
     ///
     /// if [`validate_unmasking()`] returns `true`.
     ///
+    /// The shape of the aggregated models is attached to the unmasked model, if known (see
+    /// [`set_shape()`]).
+    ///
     /// [`validate_unmasking()`]: Aggregation::validate_unmasking
+    /// [`set_shape()`]: Aggregation::set_shape
     /// [`mask()`]: Masker::mask
     pub fn unmask(self, mask_obj: MaskObject) -> Model {
         let MaskObject { vect, unit } = self.object;
@@ -206,7 +249,7 @@ impl Aggregation {
         let scaled_add_shift_n = config_n.add_shift() * BigInt::from(self.nb_models);
         let exp_shift_n = config_n.exp_shift();
         let order_n = config_n.order();
-        masked_n
+        let model = masked_n
             .into_iter()
             .zip(mask_n)
             .map(|(masked, mask)| {
@@ -227,7 +270,15 @@ impl Aggregation {
                 // scaling correction
                 unmasked / &scalar_sum
             })
-            .collect()
+            .collect::<Model>();
+
+        match self.shape {
+            // UNWRAP_SAFE: the shape matches the length of the model
+            Some(shape) if shape.nb_weights() == Some(model.len()) => {
+                model.with_shape(shape).unwrap()
+            }
+            _ => model,
+        }
     }
 
     /// Validates if aggregation of the aggregated mask object with the given `object` may be safely
@@ -314,12 +365,57 @@ impl Aggregation {
 
         self.nb_models += 1;
     }
+
+    /// Aggregates the aggregated mask object with the given `object`, in parallel.
+    ///
+    /// This is equivalent to [`aggregate()`], except that the vectors of the mask objects are
+    /// split in as many chunks as there are threads in the current rayon thread pool, which are
+    /// aggregated in parallel. This pays off for large models only, since the chunks are
+    /// distributed among the threads on every call.
+    ///
+    /// As for [`aggregate()`], it should be checked that [`validate_aggregation()`] succeeds
+    /// before calling this.
+    ///
+    /// [`aggregate()`]: Aggregation::aggregate
+    /// [`validate_aggregation()`]: Aggregation::validate_aggregation
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn parallel_aggregate(&mut self, object: MaskObject) {
+        if self.nb_models == 0 {
+            self.object = object;
+            self.nb_models = 1;
+            return;
+        }
+
+        let order_n = self.object.vect.config.order();
+        // one chunk per thread, rounded up such that no weights are left over
+        let chunk_size = 1 + self.object_size.saturating_sub(1) / rayon::current_num_threads();
+        self.object
+            .vect
+            .data
+            .par_chunks_mut(chunk_size)
+            .zip(object.vect.data.par_chunks(chunk_size))
+            .for_each(|(aggregated, chunk)| {
+                for (i, j) in aggregated.iter_mut().zip(chunk) {
+                    *i = (&*i + j) % &order_n
+                }
+            });
+
+        let order_1 = self.object.unit.config.order();
+        let a = &mut self.object.unit.data;
+        let b = object.unit.data;
+        *a = (&*a + b) % &order_1;
+
+        self.nb_models += 1;
+    }
 }
 
 /// A masker for models.
 pub struct Masker {
     config: MaskConfigPair,
     seed: MaskSeed,
+    #[cfg(feature = "differential-privacy")]
+    dp: Option<DpNoise>,
 }
 
 impl Masker {
@@ -328,12 +424,71 @@ impl Masker {
         Self {
             config,
             seed: MaskSeed::generate(),
+            #[cfg(feature = "differential-privacy")]
+            dp: None,
         }
     }
 
     /// Creates a new masker with the given masking `config`uration and `seed`.
     pub fn with_seed(config: MaskConfigPair, seed: MaskSeed) -> Self {
-        Self { config, seed }
+        Self {
+            config,
+            seed,
+            #[cfg(feature = "differential-privacy")]
+            dp: None,
+        }
+    }
+
+    /// Creates a new masker with the given masking `config`uration with a randomly generated seed,
+    /// which makes the masked models differentially private.
+    ///
+    /// Before a model is masked, its weights are clipped to `[-sensitivity, sensitivity]` and
+    /// noise of the configured mechanism is added to each of them. The noise is drawn from its
+    /// own PRNG seeded from the OS entropy, and not from the mask seed, since the sum
+    /// participants learn the mask seed. The aggregated noise of many participants averages
+    /// out in the global model, while it hides the contribution of any single participant.
+    ///
+    /// If the scaled weights can't exceed the bounds of the masking configuration, the weights
+    /// are clipped to tighter bounds, which spends less of the privacy budget than configured.
+    /// The actually spent budget is reported by [`mask_with_dp_report()`].
+    ///
+    /// # Errors
+    /// Fails if the differential privacy configuration is invalid (see [`DpConfig::validate()`]).
+    ///
+    /// [`mask_with_dp_report()`]: Masker::mask_with_dp_report
+    #[cfg(feature = "differential-privacy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "differential-privacy")))]
+    pub fn new_with_dp(config: MaskConfigPair, dp: DpConfig) -> Result<Self, InvalidDpConfig> {
+        dp.validate()?;
+        Ok(Self {
+            config,
+            seed: MaskSeed::generate(),
+            dp: Some(DpNoise::new(dp)),
+        })
+    }
+
+    /// Creates a new masker with the given masking `config`uration and `seed`, which makes
+    /// the masked models differentially private like [`new_with_dp()`]. The noise is drawn
+    /// from a PRNG seeded with `noise_seed`, which must be kept as secret as the model.
+    ///
+    /// # Errors
+    /// Fails if the differential privacy configuration is invalid (see [`DpConfig::validate()`]).
+    ///
+    /// [`new_with_dp()`]: Masker::new_with_dp
+    #[cfg(feature = "differential-privacy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "differential-privacy")))]
+    pub fn with_seed_and_dp(
+        config: MaskConfigPair,
+        seed: MaskSeed,
+        dp: DpConfig,
+        noise_seed: [u8; 32],
+    ) -> Result<Self, InvalidDpConfig> {
+        dp.validate()?;
+        Ok(Self {
+            config,
+            seed,
+            dp: Some(DpNoise::with_rng(dp, ChaCha20Rng::from_seed(noise_seed))),
+        })
     }
 }
 
@@ -343,6 +498,7 @@ impl Masker {
     ///
     /// The masking proceeds in the following steps:
     /// - Clamp the scalar and the weights according to the masking configuration.
+    /// - Round the scalar to the precision of the masking configuration.
     /// - Scale the weights by the scalar.
     /// - Shift the weights into the non-negative reals.
     /// - Shift the weights into the non-negative integers.
@@ -356,72 +512,233 @@ impl Masker {
     ///
     /// [`unmask()`]: Aggregation::unmask
     pub fn mask(self, scalar: Scalar, model: &Model) -> (MaskSeed, MaskObject) {
-        let (random_int, mut random_ints) = self.random_ints();
-        let Self { config, seed } = self;
+        let mut masker = self.into_chunked(scalar, model.len());
+        masker.mask_chunk(model);
+        masker.finish()
+    }
+
+    /// Masks the given `model` like [`mask()`], and reports the differential privacy of the
+    /// masked model, if the masker was created with [`new_with_dp()`].
+    ///
+    /// [`mask()`]: Masker::mask
+    /// [`new_with_dp()`]: Masker::new_with_dp
+    #[cfg(feature = "differential-privacy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "differential-privacy")))]
+    pub fn mask_with_dp_report(
+        self,
+        scalar: Scalar,
+        model: &Model,
+    ) -> (MaskSeed, MaskObject, Option<DpReport>) {
+        let mut masker = self.into_chunked(scalar, model.len());
+        masker.mask_chunk(model);
+        let report = masker.dp_report();
+        let (seed, masked_model) = masker.finish();
+        (seed, masked_model, report)
+    }
+
+    /// Masks the given `model` wrt the masking configuration in parallel.
+    ///
+    /// This is equivalent to [`mask()`], i.e. it gives exactly the same masked model for the same
+    /// seed, such that it is unmasked in the same way. See
+    /// [`ChunkedMasker::parallel_mask_chunk()`] for details.
+    ///
+    /// [`mask()`]: Masker::mask
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn parallel_mask(self, scalar: Scalar, model: &Model) -> (MaskSeed, MaskObject) {
+        let mut masker = self.into_chunked(scalar, model.len());
+        masker.parallel_mask_chunk(model);
+        masker.finish()
+    }
+
+    /// Turns the masker into a [`ChunkedMasker`], which masks a model chunk by chunk such
+    /// that the whole model never has to be loaded at once. The masked model has room
+    /// for `model_len` weights.
+    ///
+    /// The scalar is masked right away. Masking all the chunks of a model in order gives
+    /// the same result as masking the whole model with [`mask()`].
+    ///
+    /// [`mask()`]: Masker::mask
+    pub fn into_chunked(self, scalar: Scalar, model_len: usize) -> ChunkedMasker {
+        let Self { config, seed, .. } = self;
         let MaskConfigPair {
             vect: config_n,
             unit: config_1,
         } = config;
+        let mut prng = ChaCha20Rng::from_seed(seed.as_array());
+        // the scalar is masked with the first random integer, as expected by the mask
+        // derived from the seed
+        let random_int = generate_integer(&mut prng, &config_1.order());
 
-        // clamp the scalar
+        // clamp the scalar and round it to the precision of its encoding, such that the weights
+        // are scaled by exactly the scalar which is unmasked eventually
         let add_shift_1 = config_1.add_shift();
-        let scalar_ratio = scalar.into();
-        let scalar_clamped = clamp_max(&scalar_ratio, &add_shift_1);
-
-        let exp_shift_n = config_n.exp_shift();
-        let add_shift_n = config_n.add_shift();
-        let order_n = config_n.order();
-        let higher_bound = &add_shift_n;
-        let lower_bound = -&add_shift_n;
-
-        // mask the (scaled) weights
-        let masked_weights = model
-            .iter()
-            .zip(&mut random_ints)
-            .map(|(weight, rand_int)| {
-                let scaled = scalar_clamped * weight;
-                let scaled_clamped = clamp(&scaled, &lower_bound, higher_bound);
-                // PANIC_SAFE: shifted weight is guaranteed to be non-negative
-                let shifted = ((scaled_clamped + &add_shift_n) * &exp_shift_n)
-                    .to_integer()
-                    .to_biguint()
-                    .unwrap();
-                (shifted + rand_int) % &order_n
-            })
-            .collect();
-        let masked_model = MaskVect::new_unchecked(config_n, masked_weights);
+        let scalar_rounded = scalar.masked(&config_1).to_ratio();
 
         // mask the scalar
         // PANIC_SAFE: shifted scalar is guaranteed to be non-negative
-        let shifted = ((scalar_clamped + &add_shift_1) * config_1.exp_shift())
+        let shifted = ((&scalar_rounded + &add_shift_1) * config_1.exp_shift())
             .to_integer()
             .to_biguint()
             .unwrap();
         let masked = (shifted + random_int) % config_1.order();
         let masked_scalar = MaskUnit::new_unchecked(config_1, masked);
 
-        (seed, MaskObject::new_unchecked(masked_model, masked_scalar))
+        // the noise is calibrated to the whole model, and the weights are clipped to the bounds
+        // of the masking configuration at most, which then bound the sensitivity
+        #[cfg(feature = "differential-privacy")]
+        let dp = self.dp.map(|dp| {
+            let max_weight = if scalar_rounded.is_zero() {
+                None
+            } else {
+                Some(config_n.add_shift() / &scalar_rounded)
+            };
+            dp.calibrate(model_len, max_weight)
+        });
+
+        ChunkedMasker {
+            config: config_n,
+            seed,
+            prng,
+            scalar: scalar_rounded,
+            masked_scalar,
+            masked_weights: Vec::with_capacity(model_len),
+            #[cfg(feature = "differential-privacy")]
+            dp,
+        }
     }
+}
 
-    /// Randomly generates integers wrt the masking configurations.
+/// A masker for models that are masked chunk by chunk (see [`Masker::into_chunked()`]).
+pub struct ChunkedMasker {
+    config: MaskConfig,
+    seed: MaskSeed,
+    /// The PRNG from which the random integers of the next weights are generated.
+    prng: ChaCha20Rng,
+    /// The scalar rounded to the precision of its encoding.
+    scalar: Ratio<BigInt>,
+    masked_scalar: MaskUnit,
+    masked_weights: Vec<BigUint>,
+    /// The noise added to the weights before they are masked, if any.
+    #[cfg(feature = "differential-privacy")]
+    dp: Option<DpMasking>,
+}
+
+impl ChunkedMasker {
+    /// Masks the next `chunk` of the model, following the weights masked so far.
+    pub fn mask_chunk(&mut self, chunk: &Model) {
+        #[cfg(feature = "differential-privacy")]
+        let privatized = self.dp.as_mut().map(|dp| dp.privatize(chunk));
+        #[cfg(feature = "differential-privacy")]
+        let chunk = privatized.as_ref().unwrap_or(chunk);
+        let weight_masker = WeightMasker::new(&self.config, &self.scalar);
+        let prng = &mut self.prng;
+        let random_ints = iter::from_fn(|| Some(generate_integer(prng, &weight_masker.order)));
+        let masked_chunk = chunk
+            .iter()
+            .zip(random_ints)
+            .map(|(weight, rand_int)| weight_masker.mask(weight, rand_int));
+        self.masked_weights.extend(masked_chunk);
+    }
+
+    /// Masks the next `chunk` of the model in parallel, following the weights masked so far.
     ///
-    /// The first is generated wrt the scalar configuration, while the rest are
-    /// wrt the vector configuration and returned as an iterator.
-    fn random_ints(&self) -> (BigUint, impl Iterator<Item = BigUint>) {
-        let order_n = self.config.vect.order();
-        let order_1 = self.config.unit.order();
-        let mut prng = ChaCha20Rng::from_seed(self.seed.as_array());
-        let int = generate_integer(&mut prng, &order_1);
-        let ints = iter::from_fn(move || Some(generate_integer(&mut prng, &order_n)));
-        (int, ints)
+    /// This is equivalent to [`mask_chunk()`], i.e. it gives exactly the same masked weights.
+    /// The random integers are still generated sequentially from the PRNG, which is cheap, while
+    /// the weights are scaled, shifted and masked in parallel in the current rayon thread pool.
+    /// This pays off for large chunks only.
+    ///
+    /// [`mask_chunk()`]: ChunkedMasker::mask_chunk
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+    pub fn parallel_mask_chunk(&mut self, chunk: &Model) {
+        #[cfg(feature = "differential-privacy")]
+        let privatized = self.dp.as_mut().map(|dp| dp.privatize(chunk));
+        #[cfg(feature = "differential-privacy")]
+        let chunk = privatized.as_ref().unwrap_or(chunk);
+        let weight_masker = WeightMasker::new(&self.config, &self.scalar);
+        let prng = &mut self.prng;
+        let random_ints: Vec<BigUint> =
+            iter::repeat_with(|| generate_integer(prng, &weight_masker.order))
+                .take(chunk.len())
+                .collect();
+        let masked_chunk = chunk
+            .iter()
+            .as_slice()
+            .par_iter()
+            .zip(random_ints)
+            .map(|(weight, rand_int)| weight_masker.mask(weight, rand_int));
+        self.masked_weights.par_extend(masked_chunk);
+    }
+
+    /// Returns the number of weights masked so far.
+    pub fn len(&self) -> usize {
+        self.masked_weights.len()
+    }
+
+    /// Checks whether no weight has been masked so far.
+    pub fn is_empty(&self) -> bool {
+        self.masked_weights.is_empty()
+    }
+
+    /// Returns the differential privacy of the masked model, if the masker was created with
+    /// [`Masker::new_with_dp()`].
+    #[cfg(feature = "differential-privacy")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "differential-privacy")))]
+    pub fn dp_report(&self) -> Option<DpReport> {
+        self.dp.as_ref().map(DpMasking::report)
+    }
+
+    /// Returns the mask seed and the masked model, made of all the chunks masked so far.
+    pub fn finish(self) -> (MaskSeed, MaskObject) {
+        let masked_model = MaskVect::new_unchecked(self.config, self.masked_weights);
+        (
+            self.seed,
+            MaskObject::new_unchecked(masked_model, self.masked_scalar),
+        )
+    }
+}
+
+/// Masks the weights of a model with the shifts of a masking configuration and a scalar.
+struct WeightMasker<'a> {
+    scalar: &'a Ratio<BigInt>,
+    exp_shift: BigInt,
+    add_shift: Ratio<BigInt>,
+    lower_bound: Ratio<BigInt>,
+    order: BigUint,
+}
+
+impl<'a> WeightMasker<'a> {
+    fn new(config: &MaskConfig, scalar: &'a Ratio<BigInt>) -> Self {
+        let add_shift = config.add_shift();
+        Self {
+            scalar,
+            exp_shift: config.exp_shift(),
+            lower_bound: -&add_shift,
+            add_shift,
+            order: config.order(),
+        }
+    }
+
+    /// Masks the `weight` with the random integer `rand_int`.
+    fn mask(&self, weight: &Ratio<BigInt>, rand_int: BigUint) -> BigUint {
+        let scaled = self.scalar * weight;
+        let scaled_clamped = clamp(&scaled, &self.lower_bound, &self.add_shift);
+        // PANIC_SAFE: shifted weight is guaranteed to be non-negative
+        let shifted = ((scaled_clamped + &self.add_shift) * &self.exp_shift)
+            .to_integer()
+            .to_biguint()
+            .unwrap();
+        (shifted + rand_int) % &self.order
     }
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
-    use std::iter;
+    use std::{convert::TryFrom, iter};
 
-    use num::traits::Signed;
+    use num::traits::{clamp_max, Signed};
     use rand::{
         distributions::{Distribution, Uniform},
         SeedableRng,
@@ -432,13 +749,13 @@ mod tests {
     use crate::mask::{
         config::{
             BoundType::{Bmax, B0, B2, B4, B6},
-            DataType::{F32, F64, I32, I64},
+            DataType::{F16, F32, F64, I16, I32, I64, U8},
             GroupType::{Integer, Power2, Prime},
             MaskConfig,
             ModelType::M3,
         },
-        model::FromPrimitives,
-        scalar::FromPrimitive,
+        model::{FromPrimitives, IntoPrimitives},
+        scalar::{FromPrimitive, Rounding},
     };
 
     /// Generate tests for masking and unmasking of a single model:
@@ -589,6 +906,21 @@ mod tests {
     test_masking!(pow_i64_b6, Power2, i64, 1_000_000, 10);
     test_masking!(pow_i64_bmax, Power2, i64, 10);
 
+    test_masking!(int_i16_b0, Integer, i16, 1, 10);
+    test_masking!(int_i16_b2, Integer, i16, 100, 10);
+    test_masking!(int_i16_b4, Integer, i16, 10_000, 10);
+    test_masking!(int_i16_bmax, Integer, i16, 10);
+
+    test_masking!(prime_i16_b0, Prime, i16, 1, 10);
+    test_masking!(prime_i16_b2, Prime, i16, 100, 10);
+    test_masking!(prime_i16_b4, Prime, i16, 10_000, 10);
+    test_masking!(prime_i16_bmax, Prime, i16, 10);
+
+    test_masking!(pow_i16_b0, Power2, i16, 1, 10);
+    test_masking!(pow_i16_b2, Power2, i16, 100, 10);
+    test_masking!(pow_i16_b4, Power2, i16, 10_000, 10);
+    test_masking!(pow_i16_bmax, Power2, i16, 10);
+
     /// Generate tests for masking and unmasking of a single model:
     /// - generate random scalar from a uniform distribution with a seeded PRNG
     /// - scale a model of unit weights and mask it
@@ -834,6 +1166,191 @@ mod tests {
     test_aggregation!(pow_i64_b6, Power2, I64, B6, 10, 5);
     test_aggregation!(pow_i64_bmax, Power2, I64, Bmax, 10, 5);
 
+    test_aggregation!(int_u8_bmax, Integer, U8, Bmax, 10, 5);
+
+    test_aggregation!(prime_u8_bmax, Prime, U8, Bmax, 10, 5);
+
+    test_aggregation!(pow_u8_bmax, Power2, U8, Bmax, 10, 5);
+
+    test_aggregation!(int_i16_b0, Integer, I16, B0, 10, 5);
+    test_aggregation!(int_i16_b2, Integer, I16, B2, 10, 5);
+    test_aggregation!(int_i16_b4, Integer, I16, B4, 10, 5);
+    test_aggregation!(int_i16_bmax, Integer, I16, Bmax, 10, 5);
+
+    test_aggregation!(prime_i16_b0, Prime, I16, B0, 10, 5);
+    test_aggregation!(prime_i16_b2, Prime, I16, B2, 10, 5);
+    test_aggregation!(prime_i16_b4, Prime, I16, B4, 10, 5);
+    test_aggregation!(prime_i16_bmax, Prime, I16, Bmax, 10, 5);
+
+    test_aggregation!(pow_i16_b0, Power2, I16, B0, 10, 5);
+    test_aggregation!(pow_i16_b2, Power2, I16, B2, 10, 5);
+    test_aggregation!(pow_i16_b4, Power2, I16, B4, 10, 5);
+    test_aggregation!(pow_i16_bmax, Power2, I16, Bmax, 10, 5);
+
+    test_aggregation!(int_f16_b0, Integer, F16, B0, 10, 5);
+    test_aggregation!(int_f16_b2, Integer, F16, B2, 10, 5);
+    test_aggregation!(int_f16_b4, Integer, F16, B4, 10, 5);
+    test_aggregation!(int_f16_b6, Integer, F16, B6, 10, 5);
+    test_aggregation!(int_f16_bmax, Integer, F16, Bmax, 10, 5);
+
+    test_aggregation!(prime_f16_b0, Prime, F16, B0, 10, 5);
+    test_aggregation!(prime_f16_b2, Prime, F16, B2, 10, 5);
+    test_aggregation!(prime_f16_b4, Prime, F16, B4, 10, 5);
+    test_aggregation!(prime_f16_b6, Prime, F16, B6, 10, 5);
+    test_aggregation!(prime_f16_bmax, Prime, F16, Bmax, 10, 5);
+
+    test_aggregation!(pow_f16_b0, Power2, F16, B0, 10, 5);
+    test_aggregation!(pow_f16_b2, Power2, F16, B2, 10, 5);
+    test_aggregation!(pow_f16_b4, Power2, F16, B4, 10, 5);
+    test_aggregation!(pow_f16_b6, Power2, F16, B6, 10, 5);
+    test_aggregation!(pow_f16_bmax, Power2, F16, Bmax, 10, 5);
+
+    #[test]
+    fn test_chunked_masking() {
+        let config: MaskConfigPair = MaskConfig {
+            group_type: Prime,
+            data_type: F32,
+            bound_type: B0,
+            model_type: M3,
+        }
+        .into();
+        let weights = (0..10).map(|i| i as f32 / 10.).collect::<Vec<_>>();
+        let model = Model::from_primitives(weights.iter().copied()).unwrap();
+        let scalar = Scalar::from_f64_checked(0.5, Rounding::NearestEven).unwrap();
+        let seed = MaskSeed::generate();
+
+        let mut masker = Masker::with_seed(config, seed.clone()).into_chunked(scalar.clone(), 10);
+        assert!(masker.is_empty());
+        for chunk in weights.chunks(3) {
+            masker.mask_chunk(&Model::from_primitives(chunk.iter().copied()).unwrap());
+        }
+        assert_eq!(masker.len(), 10);
+        let (chunked_seed, chunked) = masker.finish();
+        let (whole_seed, whole) = Masker::with_seed(config, seed).mask(scalar, &model);
+        assert_eq!(chunked_seed, whole_seed);
+        assert_eq!(chunked, whole);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_aggregation() {
+        let config = MaskConfig {
+            group_type: Prime,
+            data_type: F32,
+            bound_type: B0,
+            model_type: M3,
+        };
+        let order = config.order();
+        let mut prng = ChaCha20Rng::from_seed(MaskSeed::generate().as_array());
+        let masked_models = iter::repeat_with(|| {
+            let integer = generate_integer(&mut prng, &order);
+            let integers = iter::repeat_with(|| generate_integer(&mut prng, &order))
+                .take(1_001)
+                .collect::<Vec<_>>();
+            MaskObject::new(config.into(), integers, integer).unwrap()
+        })
+        .take(5)
+        .collect::<Vec<_>>();
+
+        let mut sequential = Aggregation::new(config.into(), 1_001);
+        let mut parallel = Aggregation::new(config.into(), 1_001);
+        for masked_model in masked_models {
+            assert!(parallel.validate_aggregation(&masked_model).is_ok());
+            sequential.aggregate(masked_model.clone());
+            parallel.parallel_aggregate(masked_model);
+        }
+        assert_eq!(parallel.nb_models, 5);
+        assert_eq!(parallel.object, sequential.object);
+        assert!(parallel.object.is_valid());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_masking() {
+        let config: MaskConfigPair = MaskConfig {
+            group_type: Prime,
+            data_type: F32,
+            bound_type: B0,
+            model_type: M3,
+        }
+        .into();
+        let mut prng = ChaCha20Rng::from_seed(MaskSeed::generate().as_array());
+        let weights = Uniform::new_inclusive(-1_f32, 1_f32)
+            .sample_iter(&mut prng)
+            .take(1_001)
+            .collect::<Vec<_>>();
+        let model = Model::from_primitives(weights.iter().copied()).unwrap();
+        let scalar = Scalar::new(1, 3_u8);
+        let seed = MaskSeed::generate();
+
+        let (sequential_seed, sequential) =
+            Masker::with_seed(config, seed.clone()).mask(scalar.clone(), &model);
+        let (parallel_seed, parallel) =
+            Masker::with_seed(config, seed.clone()).parallel_mask(scalar.clone(), &model);
+        assert_eq!(parallel_seed, sequential_seed);
+        assert_eq!(parallel, sequential);
+
+        // sequential and parallel chunks can be mixed
+        let mut masker = Masker::with_seed(config, seed).into_chunked(scalar, 1_001);
+        for (i, chunk) in weights.chunks(300).enumerate() {
+            let chunk = Model::from_primitives(chunk.iter().copied()).unwrap();
+            if i % 2 == 0 {
+                masker.parallel_mask_chunk(&chunk);
+            } else {
+                masker.mask_chunk(&chunk);
+            }
+        }
+        assert_eq!(masker.finish().1, sequential);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_masking_and_aggregation() {
+        let config = MaskConfig {
+            group_type: Prime,
+            data_type: F32,
+            bound_type: B0,
+            model_type: M3,
+        };
+        let mut prng = ChaCha20Rng::from_seed(MaskSeed::generate().as_array());
+        let models = iter::repeat_with(|| {
+            Model::from_primitives(
+                Uniform::new_inclusive(-1_f32, 1_f32)
+                    .sample_iter(&mut prng)
+                    .take(1_001),
+            )
+            .unwrap()
+        })
+        .take(5)
+        .collect::<Vec<_>>();
+
+        let scalar = Scalar::new(1, 5_u8);
+        let mut aggregated_masked_model = Aggregation::new(config.into(), 1_001);
+        let mut aggregated_mask = Aggregation::new(config.into(), 1_001);
+        for model in &models {
+            let (mask_seed, masked_model) =
+                Masker::new(config.into()).parallel_mask(scalar.clone(), model);
+            aggregated_masked_model.parallel_aggregate(masked_model);
+            aggregated_mask.parallel_aggregate(mask_seed.derive_mask(1_001, config.into()));
+        }
+
+        let mask = aggregated_mask.into();
+        assert!(aggregated_masked_model.validate_unmasking(&mask).is_ok());
+        let unmasked_model = aggregated_masked_model.unmask(mask);
+        let scalar = scalar.to_ratio();
+        let tolerance =
+            Ratio::from_integer(BigInt::from(5)) / Ratio::from_integer(config.exp_shift());
+        for (i, unmasked_weight) in unmasked_model.iter().enumerate() {
+            let averaged_weight = models
+                .iter()
+                .map(|model| &scalar * &model[i])
+                .fold(Ratio::from_integer(BigInt::from(0)), |sum, weight| {
+                    sum + weight
+                });
+            assert!((averaged_weight - unmasked_weight).abs() <= tolerance);
+        }
+    }
+
     /// Generate tests for masking, aggregation and unmasking of multiple models:
     /// - generate random weights from a uniform distribution with a seeded PRNG
     /// - create a model from the weights, mask and aggregate it to the aggregated masked models
@@ -1013,6 +1530,21 @@ mod tests {
     test_masking_and_aggregation!(pow_i64_b6, Power2, i64, 1_000_000, 10, 5);
     test_masking_and_aggregation!(pow_i64_bmax, Power2, i64, 10, 5);
 
+    test_masking_and_aggregation!(int_i16_b0, Integer, i16, 1, 10, 5);
+    test_masking_and_aggregation!(int_i16_b2, Integer, i16, 100, 10, 5);
+    test_masking_and_aggregation!(int_i16_b4, Integer, i16, 10_000, 10, 5);
+    test_masking_and_aggregation!(int_i16_bmax, Integer, i16, 10, 5);
+
+    test_masking_and_aggregation!(prime_i16_b0, Prime, i16, 1, 10, 5);
+    test_masking_and_aggregation!(prime_i16_b2, Prime, i16, 100, 10, 5);
+    test_masking_and_aggregation!(prime_i16_b4, Prime, i16, 10_000, 10, 5);
+    test_masking_and_aggregation!(prime_i16_bmax, Prime, i16, 10, 5);
+
+    test_masking_and_aggregation!(pow_i16_b0, Power2, i16, 1, 10, 5);
+    test_masking_and_aggregation!(pow_i16_b2, Power2, i16, 100, 10, 5);
+    test_masking_and_aggregation!(pow_i16_b4, Power2, i16, 10_000, 10, 5);
+    test_masking_and_aggregation!(pow_i16_bmax, Power2, i16, 10, 5);
+
     /// Generate tests for masking, aggregation and unmasking of multiple models:
     /// - generate random scalars from a uniform distribution with a seeded PRNG
     /// - scale a model of unit weights, mask and aggregate it to the aggregated masked models
@@ -1145,4 +1677,318 @@ mod tests {
     test_masking_and_aggregation_scalar!(pow_f64_b4, Power2, f64, 10_000, 10, 2);
     test_masking_and_aggregation_scalar!(pow_f64_b6, Power2, f64, 1_000_000, 10, 2);
     test_masking_and_aggregation_scalar!(pow_f64_bmax, Power2, f64, 10, 2);
+
+    #[test]
+    fn test_masking_u8() {
+        // quantized models are recovered without loss of precision
+        let weights = (0..=u8::MAX).collect::<Vec<_>>();
+        let model = Model::from_primitives(weights.iter().copied()).unwrap();
+        for &group_type in &[Integer, Prime, Power2] {
+            let config = MaskConfig {
+                group_type,
+                data_type: U8,
+                bound_type: Bmax,
+                model_type: M3,
+            };
+            let (mask_seed, masked_model) = Masker::new(config.into()).mask(Scalar::unit(), &model);
+            assert!(masked_model.is_valid());
+
+            let mask = mask_seed.derive_mask(model.len(), config.into());
+            let unmasked_model = Aggregation::from(masked_model).unmask(mask);
+            assert_eq!(unmasked_model, model);
+            let unmasked_weights = unmasked_model
+                .into_primitives()
+                .collect::<Result<Vec<u8>, _>>()
+                .unwrap();
+            assert_eq!(unmasked_weights, weights);
+        }
+    }
+
+    #[test]
+    fn test_aggregation_shape() {
+        let config = MaskConfig {
+            group_type: Prime,
+            data_type: U8,
+            bound_type: Bmax,
+            model_type: M3,
+        };
+        let shape = ModelShape::from(vec![vec![2, 3]]);
+        let model = Model::from_primitives(0..6_u8)
+            .unwrap()
+            .with_shape(shape.clone())
+            .unwrap();
+        let (mask_seed, masked_model) = Masker::new(config.into()).mask(Scalar::unit(), &model);
+
+        let mut aggregation = Aggregation::new(config.into(), model.len());
+        assert!(aggregation.shape().is_none());
+        assert_eq!(
+            aggregation.set_shape(ModelShape::from(vec![vec![5]])),
+            Err(ModelShapeError(6))
+        );
+        aggregation.set_shape(shape.clone()).unwrap();
+        // only the first shape is kept
+        aggregation.set_shape(vec![vec![6]].into()).unwrap();
+        assert_eq!(aggregation.shape(), Some(&shape));
+
+        aggregation.aggregate(masked_model);
+        let mask = mask_seed.derive_mask(model.len(), config.into());
+        let unmasked_model = aggregation.unmask(mask);
+        assert_eq!(unmasked_model.shape(), Some(&shape));
+        assert_eq!(unmasked_model, model);
+    }
+
+    #[cfg(feature = "f16")]
+    #[test]
+    fn test_masking_and_aggregation_f16() {
+        // the (averaged) weights of random half precision models are recovered within the decimal
+        // places guaranteed by the masking configuration
+        use half::f16;
+
+        let vect_len = 100;
+        let mut prng = ChaCha20Rng::from_seed(MaskSeed::generate().as_array());
+        for &group_type in &[Integer, Prime, Power2] {
+            for &bound_type in &[B0, B2, B4, B6, Bmax] {
+                let config = MaskConfig {
+                    group_type,
+                    data_type: F16,
+                    bound_type,
+                    model_type: M3,
+                };
+                let bound = match bound_type {
+                    B0 => 1_f32,
+                    B2 => 100_f32,
+                    B4 => 10_000_f32,
+                    B6 | Bmax => f32::from(f16::MAX),
+                };
+                for &model_count in &[1_usize, 5] {
+                    let mut averaged_model =
+                        Model::from_primitives(iter::repeat(0).take(vect_len)).unwrap();
+                    let mut aggregated_masked_model = Aggregation::new(config.into(), vect_len);
+                    let mut aggregated_mask = Aggregation::new(config.into(), vect_len);
+                    let scalar = Scalar::new(1, model_count);
+                    let scalar_ratio = &scalar.to_ratio();
+                    for _ in 0..model_count {
+                        let weights = Uniform::new_inclusive(-bound, bound)
+                            .sample_iter(&mut prng)
+                            .map(f16::from_f32)
+                            .take(vect_len);
+                        let model = Model::from_primitives(weights).unwrap();
+                        averaged_model.iter_mut().zip(model.iter()).for_each(
+                            |(averaged_weight, weight)| {
+                                *averaged_weight += scalar_ratio * weight;
+                            },
+                        );
+
+                        let (mask_seed, masked_model) =
+                            Masker::new(config.into()).mask(scalar.clone(), &model);
+                        assert!(masked_model.is_valid());
+                        aggregated_mask.aggregate(mask_seed.derive_mask(vect_len, config.into()));
+                        aggregated_masked_model.aggregate(masked_model);
+                    }
+
+                    let unmasked_model = aggregated_masked_model.unmask(aggregated_mask.into());
+                    let tolerance = Ratio::from_integer(BigInt::from(model_count))
+                        / Ratio::from_integer(config.exp_shift());
+                    assert!(averaged_model.iter().zip(unmasked_model.iter()).all(
+                        |(averaged_weight, unmasked_weight)| {
+                            (averaged_weight - unmasked_weight).abs() <= tolerance
+                        }
+                    ));
+                    assert!(unmasked_model
+                        .into_primitives()
+                        .all(|weight: Result<f16, _>| weight.is_ok()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_masking_scalar_agreement() {
+        // the scalar unmasked by the coordinator must be exactly the scalar the participant
+        // scaled its model with, otherwise the aggregated model is biased
+        let configs = vec![
+            MaskConfig {
+                group_type: Prime,
+                data_type: F32,
+                bound_type: B0,
+                model_type: M3,
+            },
+            MaskConfig {
+                group_type: Integer,
+                data_type: F64,
+                bound_type: B6,
+                model_type: M3,
+            },
+            MaskConfig {
+                group_type: Power2,
+                data_type: I64,
+                bound_type: B2,
+                model_type: M3,
+            },
+            MaskConfig {
+                group_type: Prime,
+                data_type: F64,
+                bound_type: Bmax,
+                model_type: M3,
+            },
+        ];
+        let values = [
+            1_f64 / 3_f64,
+            2_f64 / 3_f64,
+            1e-10,
+            1e-12,
+            1_f64 - 1e-11,
+            100_f64 - 1e-9,
+            1e6 - 1e-5,
+            1e6 + 1e-5,
+        ];
+        let model = Model::from_primitives(vec![1, 1, 1].into_iter()).unwrap();
+
+        for config in configs {
+            let add_shift = config.add_shift();
+            let exp_shift = config.exp_shift();
+            let order = config.order();
+            // scalars converted from floats are exact wrt every masking configuration
+            let converted = values.iter().flat_map(|value| {
+                let nearest = Scalar::from_f64_checked(*value, Rounding::NearestEven);
+                let toward_zero = Scalar::from_f64_checked(*value, Rounding::TowardZero);
+                vec![(nearest.unwrap(), true), (toward_zero.unwrap(), true)]
+            });
+            let scalars = converted.chain(iter::once((Scalar::new(1_u8, 3_u8), false)));
+
+            for (scalar, is_converted) in scalars {
+                let (mask_seed, masked_model) =
+                    Masker::new(config.into()).mask(scalar.clone(), &model);
+                let mask = mask_seed.derive_mask(model.len(), config.into());
+
+                // unmask the scalar as the coordinator does
+                let n = (masked_model.unit.data + &order - mask.unit.data) % &order;
+                let ratio = Ratio::from_integer(n.to_bigint().unwrap());
+                let unmasked = ratio / &exp_shift - &add_shift;
+
+                let clamped = clamp_max(scalar.to_ratio(), add_shift.clone());
+                if is_converted && clamped == scalar.to_ratio() {
+                    assert_eq!(unmasked, clamped);
+                }
+                let grid = exp_shift.to_biguint().unwrap();
+                let expected = Scalar::try_from(clamped)
+                    .unwrap()
+                    .quantize(&grid, Rounding::NearestEven)
+                    .to_ratio();
+                assert_eq!(unmasked, expected);
+                assert_eq!(unmasked, scalar.masked(&config).to_ratio());
+            }
+        }
+    }
+
+    #[cfg(feature = "differential-privacy")]
+    #[test]
+    fn test_dp_masking_mean() {
+        use crate::mask::dp::DpMechanism::{Gaussian, Laplace};
+
+        let config: MaskConfigPair = MaskConfig {
+            group_type: Prime,
+            data_type: F64,
+            bound_type: B2,
+            model_type: M3,
+        }
+        .into();
+        let (nb_models, model_len) = (50, 10);
+        let scalar = Scalar::new(1_u8, nb_models as u8);
+
+        // the noise of many participants averages out in the unmasked mean of their models
+        for mechanism in [Gaussian, Laplace].iter().copied() {
+            for epsilon in [0.1, 0.5, 0.9].iter().copied() {
+                for seed in 0..4 {
+                    let dp = DpConfig {
+                        epsilon,
+                        delta: 1e-5,
+                        sensitivity: 1.,
+                        mechanism,
+                    };
+                    let mut prng = ChaCha20Rng::seed_from_u64(seed);
+                    let mut clipped_sum = vec![0_f64; model_len];
+                    let mut models = Aggregation::new(config, model_len);
+                    let mut masks = Aggregation::new(config, model_len);
+                    for _ in 0..nb_models {
+                        let weights = Uniform::new_inclusive(-2_f64, 2_f64)
+                            .sample_iter(&mut prng)
+                            .take(model_len)
+                            .collect::<Vec<_>>();
+                        for (sum, weight) in clipped_sum.iter_mut().zip(&weights) {
+                            *sum += weight.clamp(-1., 1.);
+                        }
+                        let model = Model::from_primitives(weights.into_iter()).unwrap();
+                        let noise_prng = ChaCha20Rng::from_seed(MaskSeed::generate().as_array());
+                        let masker = Masker {
+                            config,
+                            seed: MaskSeed::generate(),
+                            dp: Some(DpNoise::with_rng(dp, noise_prng)),
+                        };
+                        let (mask_seed, masked_model, report) =
+                            masker.mask_with_dp_report(scalar.clone(), &model);
+                        assert_eq!(report.unwrap().actual_epsilon, epsilon);
+                        models.aggregate(masked_model);
+                        masks.aggregate(mask_seed.derive_mask(model_len, config));
+                    }
+
+                    assert!(models.validate_unmasking(&masks.clone().into()).is_ok());
+                    let mean = models.unmask(masks.into());
+                    let tolerance = 5. * dp.noise_std(model_len) / (nb_models as f64).sqrt();
+                    assert!(mean.into_primitives_unchecked().zip(clipped_sum).all(
+                        |(weight, sum): (f64, f64)| {
+                            (weight - sum / nb_models as f64).abs() <= tolerance
+                        }
+                    ));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "differential-privacy")]
+    #[test]
+    fn test_dp_report() {
+        use crate::mask::dp::DpMechanism::Laplace;
+
+        let config: MaskConfigPair = MaskConfig {
+            group_type: Prime,
+            data_type: F32,
+            bound_type: B0,
+            model_type: M3,
+        }
+        .into();
+        let model = Model::from_primitives(vec![0_f32; 4].into_iter()).unwrap();
+        let (_, _, report) = Masker::new(config).mask_with_dp_report(Scalar::unit(), &model);
+        assert!(report.is_none());
+
+        // the masking configuration bounds the weights by one, which is half the sensitivity
+        let dp = DpConfig {
+            epsilon: 2.,
+            delta: 0.,
+            sensitivity: 2.,
+            mechanism: Laplace,
+        };
+        let masker = Masker::new_with_dp(config, dp).unwrap();
+        let (mask_seed, masked_model, report) = masker.mask_with_dp_report(Scalar::unit(), &model);
+        let report = report.unwrap();
+        assert_eq!(report.actual_epsilon, 1.);
+        assert_eq!(report.noise_std, dp.noise_std(4));
+        let mask = mask_seed.derive_mask(4, config);
+        assert_ne!(Aggregation::from(masked_model).unmask(mask), model);
+
+        // the same seeds give the same noise
+        let mask_seed = MaskSeed::generate();
+        let seeded = || Masker::with_seed_and_dp(config, mask_seed.clone(), dp, [1; 32]).unwrap();
+        assert_eq!(
+            seeded().mask(Scalar::unit(), &model),
+            seeded().mask(Scalar::unit(), &model)
+        );
+
+        let invalid = DpConfig {
+            sensitivity: 0.,
+            ..dp
+        };
+        assert!(Masker::new_with_dp(config, invalid).is_err());
+        assert!(Masker::with_seed_and_dp(config, mask_seed, invalid, [1; 32]).is_err());
+    }
 }
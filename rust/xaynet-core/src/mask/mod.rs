@@ -6,8 +6,9 @@
 //! to any particular primitive data type, but it can be created from those and converted back into
 //! them.
 //!
-//! Currently, the primitive data types [`f32`], [`f64`], [`i32`] and [`i64`] are supported and
-//! this might be extended in the future.
+//! Currently, the primitive data types [`f32`], [`f64`], [`i32`], [`i64`], [`u8`] and [`i16`] are
+//! supported and this might be extended in the future. With the `f16` feature, half precision
+//! `half::f16` weights are supported as well.
 //!
 //! ```
 //! # use xaynet_core::mask::{FromPrimitives, IntoPrimitives, Model};
@@ -19,6 +20,9 @@
 //! );
 //! ```
 //!
+//! With the `ndarray` feature, models can also be converted from and into arrays of any
+//! dimensionality (see [`Model::from_ndarray()`] and [`Model::into_ndarray()`]).
+//!
 //! # Masking configurations
 //! The masking, aggregation and unmasking of models requires certain information about the models
 //! to guarantee that no information is lost during the process, which is configured via the
@@ -47,10 +51,11 @@
 //! during the masking, aggregation and unmasking process, which are:
 //! - F32: 10 decimal places for bounded model weights and 45 decimal places for unbounded.
 //! - F64: 20 decimal places for bounded model weights and 324 decimal places for unbounded.
-//! - I32 and I64: 10 decimal places (required for scaled aggregation).
+//! - F16: 5 decimal places for bounded model weights and 8 decimal places for unbounded.
+//! - I32, I64, U8 and I16: 10 decimal places (required for scaled aggregation).
 //!
-//! Currently the primitive data types [`f32`], [`f64`], [`i32`] and [`i64`] are supported via the
-//! data type variants.
+//! Currently the primitive data types [`f32`], [`f64`], [`i32`], [`i64`], [`u8`], [`i16`] and
+//! `half::f16` are supported via the data type variants.
 //!
 //! ## Bound type
 //! The [`BoundType`] describes the absolute bounds on all model weights. The smaller the bounds of
@@ -111,6 +116,10 @@
 //! let local_mask_2 = local_mask_seed_2.derive_mask(number_weights, config.into());
 //! ```
 //!
+//! With the `differential-privacy` feature, a masker created with [`Masker::new_with_dp()`]
+//! clips the weights and adds noise to them before they are masked, such that the masked model
+//! is differentially private.
+//!
 //! ## Aggregation
 //! Masked models can be aggregated via an [`Aggregation`]. Masks themselves can be aggregated via
 //! an [`Aggregation`] as well. An aggregated masked model can only be unmasked by the aggregation
@@ -182,12 +191,19 @@
 //! ```
 
 pub(crate) mod config;
+#[cfg(feature = "differential-privacy")]
+pub(crate) mod dp;
 pub(crate) mod masking;
 pub(crate) mod model;
 pub(crate) mod object;
 pub(crate) mod scalar;
 pub(crate) mod seed;
 
+#[cfg(feature = "differential-privacy")]
+#[cfg_attr(docsrs, doc(cfg(feature = "differential-privacy")))]
+pub use self::dp::{DpConfig, DpMechanism, DpReport, InvalidDpConfig};
+#[cfg(feature = "ndarray")]
+pub use self::model::NdarrayCastError;
 pub use self::{
     config::{
         serialization::MaskConfigBuffer,
@@ -199,8 +215,21 @@ pub use self::{
         MaskConfigPair,
         ModelType,
     },
-    masking::{Aggregation, AggregationError, Masker, UnmaskingError},
-    model::{FromPrimitives, IntoPrimitives, Model, ModelCastError, PrimitiveCastError},
+    masking::{Aggregation, AggregationError, ChunkedMasker, Masker, UnmaskingError},
+    model::{
+        FromPrimitives,
+        IntoPrimitives,
+        InvalidSparseModelError,
+        Model,
+        ModelCastError,
+        ModelShape,
+        ModelShapeError,
+        ParseModelShapeError,
+        PrimitiveCastError,
+        PrimitiveType,
+        QuantileError,
+        SparseModel,
+    },
     object::{
         serialization::vect::MaskVectBuffer,
         InvalidMaskObjectError,
@@ -208,6 +237,8 @@ pub use self::{
         MaskUnit,
         MaskVect,
     },
-    scalar::{FromPrimitive, IntoPrimitive, Scalar, ScalarCastError},
+    scalar::{Rounding, Scalar, ScalarCastError},
     seed::{EncryptedMaskSeed, MaskSeed},
 };
+#[allow(deprecated)]
+pub use self::scalar::{FromPrimitive, IntoPrimitive};
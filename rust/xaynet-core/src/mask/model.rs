@@ -5,24 +5,84 @@
 //! [mask module]: crate::mask
 
 use std::{
-    fmt::Debug,
+    fmt::{self, Debug},
     iter::{FromIterator, IntoIterator},
+    ops::{Index, IndexMut},
     slice::{Iter, IterMut},
+    str::FromStr,
 };
 
-use derive_more::{Display, From, Index, IndexMut, Into};
+use derive_more::{Display, From, Into};
+#[cfg(feature = "f16")]
+use half::f16;
+#[cfg(feature = "ndarray")]
+use ndarray::{Array, ArrayBase, ArrayD, Data, Dimension, IxDyn, ShapeBuilder, ShapeError};
 use num::{
     bigint::BigInt,
     clamp,
     rational::Ratio,
-    traits::{float::FloatCore, identities::Zero, ToPrimitive},
+    traits::{float::FloatCore, identities::Zero, Signed, ToPrimitive},
 };
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
-#[derive(Debug, Clone, PartialEq, Hash, From, Index, IndexMut, Into, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Hash)]
 /// A numerical representation of a machine learning model.
-pub struct Model(Vec<Ratio<BigInt>>);
+///
+/// The weights/parameters of a model are flat. A model may additionally carry the shapes of its
+/// layers (see [`Model::with_shape()`]), which is metadata only: the shape is neither masked nor
+/// serialized, hence the masked models in the PET messages and the serialized models consist of
+/// the weights alone.
+pub struct Model {
+    weights: Vec<Ratio<BigInt>>,
+    shape: Option<ModelShape>,
+}
+
+impl From<Vec<Ratio<BigInt>>> for Model {
+    fn from(weights: Vec<Ratio<BigInt>>) -> Self {
+        Self {
+            weights,
+            shape: None,
+        }
+    }
+}
+
+impl From<Model> for Vec<Ratio<BigInt>> {
+    fn from(model: Model) -> Self {
+        model.weights
+    }
+}
+
+impl Index<usize> for Model {
+    type Output = Ratio<BigInt>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.weights[index]
+    }
+}
+
+impl IndexMut<usize> for Model {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.weights[index]
+    }
+}
+
+impl Serialize for Model {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // the shape is skipped to keep the serialization of the weights unchanged
+        serializer.serialize_newtype_struct("Model", &self.weights)
+    }
+}
+
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Model")]
+        struct Weights(Vec<Ratio<BigInt>>);
+
+        Weights::deserialize(deserializer).map(|Weights(weights)| weights.into())
+    }
+}
 
 impl std::convert::AsRef<Model> for Model {
     fn as_ref(&self) -> &Model {
@@ -34,24 +94,290 @@ impl std::convert::AsRef<Model> for Model {
 impl Model {
     /// Gets the number of weights/parameters of this model.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.weights.len()
     }
 
     /// Creates an iterator that yields references to the weights/parameters of this model.
     pub fn iter(&self) -> Iter<Ratio<BigInt>> {
-        self.0.iter()
+        self.weights.iter()
     }
 
     /// Creates an iterator that yields mutable references to the weights/parameters of this model.
     pub fn iter_mut(&mut self) -> IterMut<Ratio<BigInt>> {
-        self.0.iter_mut()
+        self.weights.iter_mut()
+    }
+
+    /// Computes the requested quantiles of the weights/parameters of this model.
+    ///
+    /// The quantiles are linearly interpolated between the closest ranks, such that the `0.0`
+    /// quantile is the smallest weight, the `0.5` quantile is the median and the `1.0` quantile
+    /// is the largest weight. Weights which are not representable as [`f64`] are clamped to its
+    /// finite range.
+    ///
+    /// # Errors
+    /// Fails if the model is empty or if any of the quantiles is not within `[0, 1]`.
+    pub fn quantiles(&self, qs: &[f64]) -> Result<Vec<f64>, QuantileError> {
+        if self.weights.is_empty() {
+            return Err(QuantileError::EmptyModel);
+        }
+        if let Some(q) = qs.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+            return Err(QuantileError::OutOfRange(*q));
+        }
+
+        let mut sorted = self.weights.iter().collect::<Vec<_>>();
+        sorted.sort_unstable();
+        let max_rank = (sorted.len() - 1) as f64;
+        let quantiles = qs
+            .iter()
+            .map(|q| {
+                let rank = q * max_rank;
+                let (lower, upper) = (rank.floor(), rank.ceil());
+                let lower_weight = ratio_to_float_clamped(sorted[lower as usize]);
+                if lower == upper {
+                    lower_weight
+                } else {
+                    let upper_weight = ratio_to_float_clamped(sorted[upper as usize]);
+                    lower_weight + (upper_weight - lower_weight) * (rank - lower)
+                }
+            })
+            .collect();
+        Ok(quantiles)
+    }
+
+    /// Gets the shapes of the layers of this model, if known.
+    pub fn shape(&self) -> Option<&ModelShape> {
+        self.shape.as_ref()
+    }
+
+    /// Attaches the shapes of its layers to this model.
+    ///
+    /// # Errors
+    /// Fails if the number of weights of the shape doesn't match the length of the model.
+    pub fn with_shape(mut self, shape: ModelShape) -> Result<Self, ModelShapeError> {
+        if shape.nb_weights() != Some(self.len()) {
+            return Err(ModelShapeError(self.len()));
+        }
+        self.shape = Some(shape);
+        Ok(self)
+    }
+}
+
+/// The shapes of the layers of a model.
+///
+/// The weights/parameters of the layers are concatenated in the order of the layers, each layer
+/// in its logical (row-major) order. For example, a dense layer with 3 inputs and 2 outputs
+/// followed by its biases has the shape `[[3, 2], [2]]`. A layer with an empty shape is a scalar.
+///
+/// The textual representation of a shape, as used by the REST API of the coordinator, is a
+/// nested list like `[[3,2],[2]]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, From, Into, Serialize, Deserialize)]
+pub struct ModelShape(Vec<Vec<usize>>);
+
+impl ModelShape {
+    /// Gets the shapes of the layers.
+    pub fn layers(&self) -> &[Vec<usize>] {
+        &self.0
+    }
+
+    /// Gets the number of weights/parameters of all layers.
+    ///
+    /// Returns `None` if the number overflows.
+    pub fn nb_weights(&self) -> Option<usize> {
+        self.0.iter().try_fold(0_usize, |nb_weights, layer| {
+            let layer_weights = layer
+                .iter()
+                .try_fold(1_usize, |product, dim| product.checked_mul(*dim))?;
+            nb_weights.checked_add(layer_weights)
+        })
+    }
+}
+
+impl fmt::Display for ModelShape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, layer) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            f.write_str("[")?;
+            for (j, dim) in layer.iter().enumerate() {
+                if j > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}", dim)?;
+            }
+            f.write_str("]")?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl FromStr for ModelShape {
+    type Err = ParseModelShapeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseModelShapeError(s.to_string());
+        let mut rest = s
+            .trim()
+            .strip_prefix('[')
+            .and_then(|s| s.strip_suffix(']'))
+            .ok_or_else(error)?
+            .trim();
+        let mut layers = Vec::new();
+        while !rest.is_empty() {
+            let layer = rest.strip_prefix('[').ok_or_else(error)?;
+            let end = layer.find(']').ok_or_else(error)?;
+            let dims = layer[..end].trim();
+            let dims = if dims.is_empty() {
+                Vec::new()
+            } else {
+                dims.split(',')
+                    .map(|dim| dim.trim().parse::<usize>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error())?
+            };
+            layers.push(dims);
+
+            rest = layer[end + 1..].trim_start();
+            if let Some(next) = rest.strip_prefix(',') {
+                rest = next.trim_start();
+                if rest.is_empty() {
+                    return Err(error());
+                }
+            } else if !rest.is_empty() {
+                return Err(error());
+            }
+        }
+        Ok(Self(layers))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Hash)]
+/// A sparse representation of a model.
+///
+/// Only the weights/parameters at the given indices are stored. The other weights of the dense
+/// model are zero (see [`SparseModel::into_dense()`]) or the ones of a base model, like the last
+/// global model (see [`SparseModel::apply_to()`]).
+///
+/// This is a representation for the applications only. The PET protocol masks and aggregates
+/// dense models, hence a sparse model must be converted into a dense model before it is masked:
+/// masking only the stored weights would reveal the indices of the updated weights to the
+/// coordinator, and the aggregated masks of the sum participants couldn't unmask the aggregated
+/// sparse models anyways.
+pub struct SparseModel {
+    indices: Vec<usize>,
+    values: Model,
+    dense_len: usize,
+}
+
+impl SparseModel {
+    /// Creates a sparse model of `dense_len` weights, whose weight at `indices[i]` is
+    /// `values[i]`.
+    ///
+    /// # Errors
+    /// Fails if the number of indices and values differ, or if an index is out of range or
+    /// duplicated.
+    pub fn new(
+        indices: Vec<usize>,
+        values: Model,
+        dense_len: usize,
+    ) -> Result<Self, InvalidSparseModelError> {
+        if indices.len() != values.len() {
+            return Err(InvalidSparseModelError::LengthMismatch {
+                indices: indices.len(),
+                values: values.len(),
+            });
+        }
+        let mut is_set = vec![false; dense_len];
+        for &index in indices.iter() {
+            match is_set.get_mut(index) {
+                None => {
+                    return Err(InvalidSparseModelError::IndexOutOfRange {
+                        index,
+                        len: dense_len,
+                    })
+                }
+                Some(true) => return Err(InvalidSparseModelError::DuplicateIndex(index)),
+                Some(is_set) => *is_set = true,
+            }
+        }
+        Ok(Self {
+            indices,
+            values,
+            dense_len,
+        })
+    }
+
+    /// Gets the indices of the stored weights/parameters.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    /// Gets the stored weights/parameters, in the order of their indices.
+    pub fn values(&self) -> &Model {
+        &self.values
+    }
+
+    /// Gets the number of weights/parameters of the dense model.
+    pub fn dense_len(&self) -> usize {
+        self.dense_len
+    }
+
+    /// Converts the sparse model into a dense model, whose weights/parameters which are not
+    /// stored are zero.
+    pub fn into_dense(self) -> Model {
+        let base = Model::from(vec![Ratio::zero(); self.dense_len]);
+        // UNWRAP_SAFE: the base model has the length of the dense model
+        self.apply_to(base).unwrap()
+    }
+
+    /// Replaces the weights/parameters of the `base` model at the indices of this sparse model.
+    ///
+    /// The shape of the `base` model is kept, if any.
+    ///
+    /// # Errors
+    /// Fails if the `base` model doesn't have the length of the dense model.
+    pub fn apply_to(self, mut base: Model) -> Result<Model, InvalidSparseModelError> {
+        if base.len() != self.dense_len {
+            return Err(InvalidSparseModelError::BaseLength {
+                expected: self.dense_len,
+                found: base.len(),
+            });
+        }
+        for (index, value) in self.indices.into_iter().zip(self.values) {
+            base.weights[index] = value;
+        }
+        Ok(base)
+    }
+}
+
+impl From<Model> for SparseModel {
+    /// Creates a sparse model which stores the non-zero weights/parameters of the `model`.
+    fn from(model: Model) -> Self {
+        let dense_len = model.len();
+        let (indices, values) = model
+            .into_iter()
+            .enumerate()
+            .filter(|(_, weight)| !weight.is_zero())
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        Self {
+            indices,
+            values: values.into(),
+            dense_len,
+        }
+    }
+}
+
+impl From<SparseModel> for Model {
+    fn from(sparse: SparseModel) -> Self {
+        sparse.into_dense()
     }
 }
 
 impl FromIterator<Ratio<BigInt>> for Model {
     fn from_iter<I: IntoIterator<Item = Ratio<BigInt>>>(iter: I) -> Self {
         let data: Vec<Ratio<BigInt>> = iter.into_iter().collect();
-        Model(data)
+        Model::from(data)
     }
 }
 
@@ -60,17 +386,163 @@ impl IntoIterator for Model {
     type IntoIter = std::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.into_iter()
+        self.weights.into_iter()
+    }
+}
+
+#[cfg(feature = "ndarray")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+impl Model {
+    /// Creates a model from an array of primitive values of any dimensionality.
+    ///
+    /// The array is flattened in its logical (row-major) order.
+    ///
+    /// # Errors
+    /// Fails if a primitive value can't be converted into a numerical value due to not being
+    /// finite.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ndarray::{array, Array2, Ix2};
+    /// # use xaynet_core::mask::Model;
+    /// // the weight matrix of a dense layer with 3 inputs and 2 outputs
+    /// let weights: Array2<f32> = array![[0.5, -0.25], [1.0, 0.0], [-1.5, 2.0]];
+    /// let model = Model::from_ndarray(&weights).unwrap();
+    /// assert_eq!(model.len(), 6);
+    ///
+    /// let array = model.into_ndarray::<f32, Ix2, _>((3, 2)).unwrap();
+    /// assert_eq!(array, weights);
+    /// ```
+    pub fn from_ndarray<P, S, D>(array: &ArrayBase<S, D>) -> Result<Self, PrimitiveCastError<P>>
+    where
+        P: Copy + Debug,
+        S: Data<Elem = P>,
+        D: Dimension,
+        Self: FromPrimitives<P>,
+    {
+        match array.as_slice() {
+            Some(slice) => Self::from_primitives(slice.iter().copied()),
+            // the array is not contiguous in memory or not in standard order
+            None => Self::from_primitives(array.iter().copied()),
+        }
+    }
+
+    /// Converts the model into an array of primitive values with the given shape.
+    ///
+    /// The weights fill the array in its logical (row-major) order, unless the shape is given
+    /// in column-major order (see [`ShapeBuilder`]).
+    ///
+    /// # Errors
+    /// Fails if a numerical value can't be converted into a primitive value or if the number of
+    /// elements of the shape doesn't match the length of the model.
+    pub fn into_ndarray<P, D, Sh>(self, shape: Sh) -> Result<Array<P, D>, NdarrayCastError>
+    where
+        P: 'static,
+        D: Dimension,
+        Sh: ShapeBuilder<Dim = D>,
+        Self: IntoPrimitives<P>,
+    {
+        let primitives = self.into_primitives().collect::<Result<Vec<P>, _>>()?;
+        Ok(Array::from_shape_vec(shape, primitives)?)
+    }
+
+    /// Creates a model from the arrays of primitive values of its layers.
+    ///
+    /// The arrays are flattened in their logical (row-major) order and concatenated, and their
+    /// shapes are attached to the model (see [`Model::shape()`]).
+    ///
+    /// # Errors
+    /// Fails if a primitive value can't be converted into a numerical value due to not being
+    /// finite.
+    ///
+    /// # Examples
+    /// ```
+    /// # use ndarray::{array, ArrayD};
+    /// # use xaynet_core::mask::Model;
+    /// // the weights and the biases of a dense layer with 3 inputs and 2 outputs
+    /// let layers: Vec<ArrayD<f64>> = vec![
+    ///     array![[0.5, -0.25], [1.0, 0.0], [-1.5, 2.0]].into_dyn(),
+    ///     array![0.1, -0.1].into_dyn(),
+    /// ];
+    /// let model = Model::from_ndarrays(&layers).unwrap();
+    /// assert_eq!(model.len(), 8);
+    /// assert_eq!(model.shape().unwrap().to_string(), "[[3,2],[2]]");
+    ///
+    /// assert_eq!(model.into_ndarrays::<f64>().unwrap(), layers);
+    /// ```
+    pub fn from_ndarrays<P, S, D>(arrays: &[ArrayBase<S, D>]) -> Result<Self, PrimitiveCastError<P>>
+    where
+        P: Copy + Debug,
+        S: Data<Elem = P>,
+        D: Dimension,
+        Self: FromPrimitives<P>,
+    {
+        let mut weights = Vec::new();
+        let mut layers = Vec::with_capacity(arrays.len());
+        for array in arrays {
+            weights.extend(Self::from_ndarray(array)?.weights);
+            layers.push(array.shape().to_vec());
+        }
+        Ok(Self {
+            weights,
+            shape: Some(ModelShape(layers)),
+        })
+    }
+
+    /// Converts the model into the arrays of primitive values of its layers.
+    ///
+    /// The arrays have the shapes of the layers (see [`Model::shape()`]). A model without a shape
+    /// is converted into a single one-dimensional array.
+    ///
+    /// # Errors
+    /// Fails if a numerical value can't be converted into a primitive value.
+    pub fn into_ndarrays<P>(mut self) -> Result<Vec<ArrayD<P>>, NdarrayCastError>
+    where
+        P: 'static,
+        Self: IntoPrimitives<P>,
+    {
+        let layers = match self.shape.take() {
+            Some(ModelShape(layers)) => layers,
+            None => vec![vec![self.len()]],
+        };
+        let mut primitives = self.into_primitives();
+        layers
+            .into_iter()
+            .map(|layer| {
+                let len = layer.iter().product();
+                let layer_primitives = primitives
+                    .by_ref()
+                    .take(len)
+                    .collect::<Result<Vec<P>, _>>()?;
+                Ok(Array::from_shape_vec(IxDyn(&layer), layer_primitives)?)
+            })
+            .collect()
     }
 }
 
-#[derive(Debug, Display)]
-/// A primitive data type as a target for model conversion.
-pub(crate) enum PrimitiveType {
+#[cfg(feature = "ndarray")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+#[derive(Error, Debug)]
+/// Errors related to model conversion into arrays.
+pub enum NdarrayCastError {
+    #[error(transparent)]
+    /// A weight can't be converted into the primitive type.
+    Cast(#[from] ModelCastError),
+    #[error("Could not shape the model into an array: {0}")]
+    /// The shape doesn't match the length of the model.
+    Shape(#[from] ShapeError),
+}
+
+#[derive(Clone, Copy, Debug, Display)]
+/// A primitive data type as a target for model and scalar conversion.
+pub enum PrimitiveType {
     F32,
     F64,
     I32,
     I64,
+    U8,
+    I16,
+    F16,
 }
 
 #[derive(Error, Debug)]
@@ -86,11 +558,49 @@ pub struct ModelCastError {
 /// Errors related to weight conversion from primitives.
 pub struct PrimitiveCastError<P: Debug>(pub(crate) P);
 
+#[derive(Clone, Copy, Error, Debug, PartialEq, Eq)]
+#[error("The model shape does not match the {0} weights of the model")]
+/// Errors related to attaching a shape to a model.
+pub struct ModelShapeError(pub(crate) usize);
+
+#[derive(Clone, Copy, Error, Debug, PartialEq, Eq)]
+/// Errors related to invalid sparse models.
+pub enum InvalidSparseModelError {
+    #[error("The sparse model has {indices} indices but {values} values")]
+    /// The number of indices and values differ.
+    LengthMismatch { indices: usize, values: usize },
+    #[error("Weight index {index} is out of range for a model of {len} weights")]
+    /// An index is out of range of the dense model.
+    IndexOutOfRange { index: usize, len: usize },
+    #[error("Weight index {0} is duplicated")]
+    /// An index is duplicated.
+    DuplicateIndex(usize),
+    #[error("The base model has {found} weights instead of {expected}")]
+    /// The base model doesn't have the length of the dense model.
+    BaseLength { expected: usize, found: usize },
+}
+
+#[derive(Clone, Error, Debug, PartialEq, Eq)]
+#[error("Could not parse model shape {0:?}")]
+/// Errors related to parsing a model shape.
+pub struct ParseModelShapeError(String);
+
+#[derive(Clone, Copy, Error, Debug, PartialEq)]
+/// Errors related to the computation of model quantiles.
+pub enum QuantileError {
+    #[error("Could not compute quantiles of an empty model")]
+    /// The model has no weights.
+    EmptyModel,
+    #[error("Quantile {0} is not within [0, 1]")]
+    /// A requested quantile is out of range.
+    OutOfRange(f64),
+}
+
 /// An interface to convert a collection of numerical values into an iterator of primitive values.
 ///
 /// This trait is used to convert a [`Model`], which has its own internal representation of the
-/// weights, into primitive types ([`f32`], [`f64`], [`i32`], [`i64`]). The opposite trait is
-/// [`FromPrimitives`].
+/// weights, into primitive types ([`f32`], [`f64`], [`i32`], [`i64`], [`u8`], [`i16`] and, with
+/// the `f16` feature, `half::f16`). The opposite trait is [`FromPrimitives`].
 pub trait IntoPrimitives<P: 'static>: Sized {
     /// Creates an iterator from numerical values that yields converted primitive values.
     ///
@@ -118,9 +628,9 @@ pub trait IntoPrimitives<P: 'static>: Sized {
 
 /// An interface to convert a collection of primitive values into an iterator of numerical values.
 ///
-/// This trait is used to convert primitive types ([`f32`], [`f64`], [`i32`], [`i64`]) into a
-/// [`Model`], which has its own internal representation of the weights. The opposite trait is
-/// [`IntoPrimitives`].
+/// This trait is used to convert primitive types ([`f32`], [`f64`], [`i32`], [`i64`], [`u8`],
+/// [`i16`] and, with the `f16` feature, `half::f16`) into a [`Model`], which has its own internal
+/// representation of the weights. The opposite trait is [`IntoPrimitives`].
 pub trait FromPrimitives<P: Debug>: Sized {
     /// Creates an iterator from primitive values that yields converted numerical values.
     ///
@@ -138,7 +648,7 @@ pub trait FromPrimitives<P: Debug>: Sized {
 
 impl IntoPrimitives<i32> for Model {
     fn into_primitives(self) -> Box<dyn Iterator<Item = Result<i32, ModelCastError>>> {
-        Box::new(self.0.into_iter().map(|i| {
+        Box::new(self.weights.into_iter().map(|i| {
             i.to_integer().to_i32().ok_or(ModelCastError {
                 weight: i,
                 target: PrimitiveType::I32,
@@ -147,7 +657,7 @@ impl IntoPrimitives<i32> for Model {
     }
 
     fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<i32, ModelCastError>>> {
-        let vec = self.0.clone();
+        let vec = self.weights.clone();
         Box::new(vec.into_iter().map(|i| {
             i.to_integer().to_i32().ok_or(ModelCastError {
                 weight: i,
@@ -169,7 +679,7 @@ impl FromPrimitives<i32> for Model {
 
 impl IntoPrimitives<i64> for Model {
     fn into_primitives(self) -> Box<dyn Iterator<Item = Result<i64, ModelCastError>>> {
-        Box::new(self.0.into_iter().map(|i| {
+        Box::new(self.weights.into_iter().map(|i| {
             i.to_integer().to_i64().ok_or(ModelCastError {
                 weight: i,
                 target: PrimitiveType::I64,
@@ -178,7 +688,7 @@ impl IntoPrimitives<i64> for Model {
     }
 
     fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<i64, ModelCastError>>> {
-        let vec = self.0.clone();
+        let vec = self.weights.clone();
         Box::new(vec.into_iter().map(|i| {
             i.to_integer().to_i64().ok_or(ModelCastError {
                 weight: i,
@@ -198,9 +708,71 @@ impl FromPrimitives<i64> for Model {
     }
 }
 
+impl IntoPrimitives<u8> for Model {
+    fn into_primitives(self) -> Box<dyn Iterator<Item = Result<u8, ModelCastError>>> {
+        Box::new(self.weights.into_iter().map(|i| {
+            i.to_integer().to_u8().ok_or(ModelCastError {
+                weight: i,
+                target: PrimitiveType::U8,
+            })
+        }))
+    }
+
+    fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<u8, ModelCastError>>> {
+        let vec = self.weights.clone();
+        Box::new(vec.into_iter().map(|i| {
+            i.to_integer().to_u8().ok_or(ModelCastError {
+                weight: i,
+                target: PrimitiveType::U8,
+            })
+        }))
+    }
+}
+
+impl FromPrimitives<u8> for Model {
+    fn from_primitives<I: Iterator<Item = u8>>(iter: I) -> Result<Self, PrimitiveCastError<u8>> {
+        Ok(iter.map(|p| Ratio::from_integer(BigInt::from(p))).collect())
+    }
+
+    fn from_primitives_bounded<I: Iterator<Item = u8>>(iter: I) -> Self {
+        Self::from_primitives(iter).unwrap()
+    }
+}
+
+impl IntoPrimitives<i16> for Model {
+    fn into_primitives(self) -> Box<dyn Iterator<Item = Result<i16, ModelCastError>>> {
+        Box::new(self.weights.into_iter().map(|i| {
+            i.to_integer().to_i16().ok_or(ModelCastError {
+                weight: i,
+                target: PrimitiveType::I16,
+            })
+        }))
+    }
+
+    fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<i16, ModelCastError>>> {
+        let vec = self.weights.clone();
+        Box::new(vec.into_iter().map(|i| {
+            i.to_integer().to_i16().ok_or(ModelCastError {
+                weight: i,
+                target: PrimitiveType::I16,
+            })
+        }))
+    }
+}
+
+impl FromPrimitives<i16> for Model {
+    fn from_primitives<I: Iterator<Item = i16>>(iter: I) -> Result<Self, PrimitiveCastError<i16>> {
+        Ok(iter.map(|p| Ratio::from_integer(BigInt::from(p))).collect())
+    }
+
+    fn from_primitives_bounded<I: Iterator<Item = i16>>(iter: I) -> Self {
+        Self::from_primitives(iter).unwrap()
+    }
+}
+
 impl IntoPrimitives<f32> for Model {
     fn into_primitives(self) -> Box<dyn Iterator<Item = Result<f32, ModelCastError>>> {
-        let iter = self.0.into_iter().map(|r| {
+        let iter = self.weights.into_iter().map(|r| {
             ratio_to_float::<f32>(&r).ok_or(ModelCastError {
                 weight: r,
                 target: PrimitiveType::F32,
@@ -210,7 +782,7 @@ impl IntoPrimitives<f32> for Model {
     }
 
     fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<f32, ModelCastError>>> {
-        let vec = self.0.clone();
+        let vec = self.weights.clone();
         let iter = vec.into_iter().map(|r| {
             ratio_to_float::<f32>(&r).ok_or(ModelCastError {
                 weight: r,
@@ -234,7 +806,7 @@ impl FromPrimitives<f32> for Model {
 
 impl IntoPrimitives<f64> for Model {
     fn into_primitives(self) -> Box<dyn Iterator<Item = Result<f64, ModelCastError>>> {
-        let iter = self.0.into_iter().map(|r| {
+        let iter = self.weights.into_iter().map(|r| {
             ratio_to_float::<f64>(&r).ok_or(ModelCastError {
                 weight: r,
                 target: PrimitiveType::F64,
@@ -244,7 +816,7 @@ impl IntoPrimitives<f64> for Model {
     }
 
     fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<f64, ModelCastError>>> {
-        let vec = self.0.clone();
+        let vec = self.weights.clone();
         let iter = vec.into_iter().map(|r| {
             ratio_to_float::<f64>(&r).ok_or(ModelCastError {
                 weight: r,
@@ -266,6 +838,48 @@ impl FromPrimitives<f64> for Model {
     }
 }
 
+#[cfg(feature = "f16")]
+#[cfg_attr(docsrs, doc(cfg(feature = "f16")))]
+impl IntoPrimitives<f16> for Model {
+    fn into_primitives(self) -> Box<dyn Iterator<Item = Result<f16, ModelCastError>>> {
+        let iter = self.weights.into_iter().map(|r| {
+            ratio_to_f16(&r).ok_or(ModelCastError {
+                weight: r,
+                target: PrimitiveType::F16,
+            })
+        });
+        Box::new(iter)
+    }
+
+    fn to_primitives(&self) -> Box<dyn Iterator<Item = Result<f16, ModelCastError>>> {
+        let vec = self.weights.clone();
+        let iter = vec.into_iter().map(|r| {
+            ratio_to_f16(&r).ok_or(ModelCastError {
+                weight: r,
+                target: PrimitiveType::F16,
+            })
+        });
+        Box::new(iter)
+    }
+}
+
+#[cfg(feature = "f16")]
+#[cfg_attr(docsrs, doc(cfg(feature = "f16")))]
+impl FromPrimitives<f16> for Model {
+    fn from_primitives<I: Iterator<Item = f16>>(iter: I) -> Result<Self, PrimitiveCastError<f16>> {
+        // the conversion into f32 is lossless
+        iter.map(|f| Ratio::from_float(f32::from(f)).ok_or(PrimitiveCastError(f)))
+            .collect()
+    }
+
+    fn from_primitives_bounded<I: Iterator<Item = f16>>(iter: I) -> Self {
+        // infinities are clamped to the finite range of f16 instead of f32, NaN is kept
+        let (min, max) = (f32::from(f16::MIN), f32::from(f16::MAX));
+        iter.map(|f| float_to_ratio_bounded(clamp(f32::from(f), min, max)))
+            .collect()
+    }
+}
+
 /// Converts a numerical value into a primitive floating point value.
 ///
 /// # Errors
@@ -297,6 +911,33 @@ pub(crate) fn ratio_to_float<F: FloatCore>(ratio: &Ratio<BigInt>) -> Option<F> {
     }
 }
 
+/// Converts a numerical value into a primitive half precision floating point value.
+///
+/// # Errors
+/// Fails if the numerical value is not representable in [`f16`].
+#[cfg(feature = "f16")]
+fn ratio_to_f16(ratio: &Ratio<BigInt>) -> Option<f16> {
+    let max_value = Ratio::from_float(f32::from(f16::MAX)).unwrap();
+    if ratio.abs() > max_value {
+        return None;
+    }
+    // rounding twice via f64 yields the same f16 as rounding once
+    ratio_to_float::<f64>(ratio).map(f16::from_f64)
+}
+
+/// Converts the numerical value into a [`f64`].
+///
+/// Maps values beyond the finite range of [`f64`] to its max/min.
+fn ratio_to_float_clamped(ratio: &Ratio<BigInt>) -> f64 {
+    ratio_to_float::<f64>(ratio).unwrap_or_else(|| {
+        if ratio.is_positive() {
+            f64::MAX
+        } else {
+            f64::MIN
+        }
+    })
+}
+
 /// Converts the primitive floating point value into a numerical value.
 ///
 /// Maps positive/negative infinity to max/min of the primitive data type and NaN to zero.
@@ -317,6 +958,183 @@ mod tests {
 
     type R = Ratio<BigInt>;
 
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_model_ndarray() {
+        use ndarray::{Array, Array3, Ix1, Ix3};
+
+        let expected_array = Array::from_iter((0..24).map(|i| i as f64 - 12.))
+            .into_shape((2, 3, 4))
+            .unwrap();
+        let model = Model::from_ndarray(&expected_array).unwrap();
+        assert_eq!(model.len(), 24);
+        let actual_array: Array3<f64> = model.clone().into_ndarray((2, 3, 4)).unwrap();
+        assert_eq!(actual_array, expected_array);
+
+        // a non-contiguous view is flattened in its logical order
+        let transposed = expected_array.t();
+        let model_t = Model::from_ndarray(&transposed).unwrap();
+        let actual_array = model_t.into_ndarray::<f64, Ix3, _>((4, 3, 2)).unwrap();
+        assert_eq!(actual_array, transposed);
+
+        assert!(matches!(
+            model.clone().into_ndarray::<f64, Ix1, _>(25),
+            Err(NdarrayCastError::Shape(_))
+        ));
+        assert!(matches!(
+            model.into_ndarray::<u8, Ix1, _>(24),
+            Err(NdarrayCastError::Cast(_))
+        ));
+        assert!(Model::from_ndarray(&Array::from(vec![f32::NAN])).is_err());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_model_ndarrays() {
+        use ndarray::{Array, ArrayD};
+
+        let layers: Vec<ArrayD<f32>> = vec![
+            Array::from_iter((0..6).map(|i| i as f32))
+                .into_shape(vec![2, 3])
+                .unwrap(),
+            Array::from_elem(vec![], 6.),
+            Array::from_iter((7..11).map(|i| i as f32))
+                .into_shape(vec![4])
+                .unwrap(),
+        ];
+        let model = Model::from_ndarrays(&layers).unwrap();
+        assert_eq!(model.len(), 11);
+        assert_eq!(
+            model.shape(),
+            Some(&ModelShape::from(vec![vec![2, 3], vec![], vec![4]]))
+        );
+        let expected_weights = Model::from_primitives((0..11).map(|i| i as f32)).unwrap();
+        assert!(model.iter().eq(expected_weights.iter()));
+        assert_eq!(model.into_ndarrays::<f32>().unwrap(), layers);
+
+        // a model without a shape is a single layer
+        let arrays = expected_weights.into_ndarrays::<f32>().unwrap();
+        assert_eq!(
+            arrays,
+            vec![Array::from_iter((0..11).map(|i| i as f32)).into_dyn()]
+        );
+
+        assert!(matches!(
+            Model::from_ndarrays(&[Array::from_elem(vec![2], -1_f32)])
+                .unwrap()
+                .into_ndarrays::<u8>(),
+            Err(NdarrayCastError::Cast(_))
+        ));
+    }
+
+    #[test]
+    fn test_model_shape() {
+        let shape = ModelShape::from(vec![vec![3, 2], vec![2], vec![]]);
+        assert_eq!(shape.nb_weights(), Some(9));
+        assert_eq!(shape.to_string(), "[[3,2],[2],[]]");
+        assert_eq!("[[3,2],[2],[]]".parse::<ModelShape>().unwrap(), shape);
+        assert_eq!(
+            " [ [3, 2] , [2],[ ] ] ".parse::<ModelShape>().unwrap(),
+            shape
+        );
+        assert_eq!(
+            "[]".parse::<ModelShape>().unwrap(),
+            ModelShape::from(vec![])
+        );
+        for invalid in &[
+            "",
+            "[",
+            "[[3,2]",
+            "[[3,2],]",
+            "[[3,2][2]]",
+            "[[3,-2]]",
+            "[3,2]",
+        ] {
+            assert!(invalid.parse::<ModelShape>().is_err(), "{}", invalid);
+        }
+        let overflowing = ModelShape::from(vec![vec![usize::MAX, 2]]);
+        assert_eq!(overflowing.nb_weights(), None);
+
+        let model = Model::from(vec![R::zero(); 9]);
+        assert!(model.shape().is_none());
+        let model = model.with_shape(shape.clone()).unwrap();
+        assert_eq!(model.shape(), Some(&shape));
+        assert_eq!(
+            Model::from(vec![R::zero(); 8])
+                .with_shape(shape)
+                .unwrap_err(),
+            ModelShapeError(8)
+        );
+        assert!(Model::from(vec![R::zero(); 8])
+            .with_shape(overflowing)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sparse_model() {
+        let int = |i: i32| R::from_integer(BigInt::from(i));
+        let model = Model::from(vec![int(0), int(3), int(0), int(0), int(-1)]);
+
+        let sparse = SparseModel::from(model.clone());
+        assert_eq!(sparse.indices(), &[1, 4]);
+        assert_eq!(sparse.values(), &Model::from(vec![int(3), int(-1)]));
+        assert_eq!(sparse.dense_len(), 5);
+        assert_eq!(Model::from(sparse), model);
+
+        let sparse = SparseModel::new(vec![3, 0], Model::from(vec![int(7), int(8)]), 5).unwrap();
+        let base = Model::from(vec![int(1); 5])
+            .with_shape(vec![vec![5]].into())
+            .unwrap();
+        let updated = sparse.clone().apply_to(base.clone()).unwrap();
+        assert!(updated.iter().eq(&[int(8), int(1), int(1), int(7), int(1)]));
+        assert_eq!(updated.shape(), base.shape());
+        assert_eq!(
+            sparse.clone().into_dense(),
+            Model::from(vec![int(8), int(0), int(0), int(7), int(0)])
+        );
+        assert_eq!(
+            sparse.apply_to(Model::from(vec![int(1); 4])),
+            Err(InvalidSparseModelError::BaseLength {
+                expected: 5,
+                found: 4
+            })
+        );
+
+        let values = || Model::from(vec![int(1), int(2)]);
+        assert_eq!(
+            SparseModel::new(vec![1], values(), 5),
+            Err(InvalidSparseModelError::LengthMismatch {
+                indices: 1,
+                values: 2
+            })
+        );
+        assert_eq!(
+            SparseModel::new(vec![1, 5], values(), 5),
+            Err(InvalidSparseModelError::IndexOutOfRange { index: 5, len: 5 })
+        );
+        assert_eq!(
+            SparseModel::new(vec![2, 2], values(), 5),
+            Err(InvalidSparseModelError::DuplicateIndex(2))
+        );
+    }
+
+    #[test]
+    fn test_model_shape_not_serialized() {
+        let model = Model::from(vec![R::zero(), R::from_integer(BigInt::from(1))]);
+        let shaped = model
+            .clone()
+            .with_shape(ModelShape::from(vec![vec![2]]))
+            .unwrap();
+        let serialized = bincode::serialize(&shaped).unwrap();
+        assert_eq!(serialized, bincode::serialize(&model).unwrap());
+        assert_eq!(
+            bincode::serialize(&model).unwrap(),
+            bincode::serialize(&vec![R::zero(), R::from_integer(BigInt::from(1))]).unwrap()
+        );
+        let deserialized: Model = bincode::deserialize(&serialized).unwrap();
+        assert_eq!(deserialized, model);
+    }
+
     #[test]
     fn test_model_f32() {
         let expected_primitives = vec![-1_f32, 0_f32, 1_f32];
@@ -403,6 +1221,49 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "f16")]
+    #[test]
+    fn test_model_f16() {
+        let expected_primitives = vec![f16::from_f32(-1.5), f16::ZERO, f16::MAX];
+        let expected_model = Model::from(vec![
+            R::from_float(-1.5_f32).unwrap(),
+            R::zero(),
+            R::from_integer(BigInt::from(65_504)),
+        ]);
+
+        let actual_model = Model::from_primitives(expected_primitives.iter().cloned()).unwrap();
+        assert_eq!(actual_model, expected_model);
+
+        let actual_model = Model::from_primitives_bounded(expected_primitives.iter().cloned());
+        assert_eq!(actual_model, expected_model);
+
+        let actual_primitives: Vec<f16> = expected_model.into_primitives_unchecked().collect();
+        assert_eq!(actual_primitives, expected_primitives);
+
+        // beyond the finite range of f16
+        let model = Model::from(vec![R::from_integer(BigInt::from(65_505))]);
+        assert!(model
+            .into_primitives()
+            .all(|weight: Result<f16, _>| weight.is_err()));
+    }
+
+    #[cfg(feature = "f16")]
+    #[test]
+    fn test_model_f16_from_weird_primitives() {
+        let max = R::from_integer(BigInt::from(65_504));
+        for (weird, bounded) in [
+            (f16::INFINITY, max.clone()),
+            (f16::NEG_INFINITY, -max),
+            (f16::NAN, R::zero()),
+        ] {
+            assert!(Model::from_primitives(iter::once(weird)).is_err());
+            assert_eq!(
+                Model::from_primitives_bounded(iter::once(weird)),
+                vec![bounded].into()
+            );
+        }
+    }
+
     #[test]
     fn test_model_i32() {
         let expected_primitives = vec![-1_i32, 0_i32, 1_i32];
@@ -462,4 +1323,64 @@ mod tests {
         let ratio = &f64_max * BigInt::from(10_usize) / (f64_max * BigInt::from(100_usize));
         assert_eq!(ratio_to_float::<f64>(&ratio).unwrap(), 0.1_f64);
     }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_quantiles() {
+        // uniform distribution of the integers 1..=101 in reverse order
+        let model = Model::from_primitives((1..=101_i32).rev()).unwrap();
+        let quantiles = model
+            .quantiles(&[0.0, 0.1, 0.25, 0.5, 0.75, 0.9, 1.0])
+            .unwrap();
+        assert_eq!(quantiles, vec![1.0, 11.0, 26.0, 51.0, 76.0, 91.0, 101.0]);
+
+        // interpolation between the closest ranks
+        let model =
+            Model::from_primitives(vec![4_f32, -1_f32, 2_f32, 0.5_f32].into_iter()).unwrap();
+        let quantiles = model.quantiles(&[0.0, 0.5, 0.75, 1.0]).unwrap();
+        assert_eq!(quantiles, vec![-1.0, 1.25, 2.5, 4.0]);
+
+        // no quantiles requested
+        assert!(model.quantiles(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_quantiles_single_weight() {
+        let model = Model::from_primitives(iter::once(7_i32)).unwrap();
+        assert_eq!(model.quantiles(&[0.0, 0.5, 1.0]).unwrap(), vec![7.0; 3]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_quantiles_clamped() {
+        let f64_max = R::from_float(f64::max_value()).unwrap();
+        let model = Model::from(vec![
+            -&f64_max * BigInt::from(2),
+            R::zero(),
+            f64_max * BigInt::from(2),
+        ]);
+        let quantiles = model.quantiles(&[0.0, 0.5, 1.0]).unwrap();
+        assert_eq!(quantiles, vec![f64::MIN, 0.0, f64::MAX]);
+    }
+
+    #[test]
+    fn test_quantiles_errors() {
+        let model = Model::from(Vec::new());
+        assert_eq!(model.quantiles(&[0.5]), Err(QuantileError::EmptyModel));
+
+        let model = Model::from_primitives(vec![1_i32, 2, 3].into_iter()).unwrap();
+        assert_eq!(
+            model.quantiles(&[0.5, 1.5]),
+            Err(QuantileError::OutOfRange(1.5)),
+        );
+        assert_eq!(
+            model.quantiles(&[-0.1]),
+            Err(QuantileError::OutOfRange(-0.1)),
+        );
+        assert!(matches!(
+            model.quantiles(&[f64::NAN]),
+            Err(QuantileError::OutOfRange(q)) if q.is_nan(),
+        ));
+    }
 }
@@ -5,6 +5,7 @@
 //! [mask module]: crate::mask
 
 use crate::mask::{
+    config::MaskConfig,
     model::{ratio_to_float, PrimitiveType},
     PrimitiveCastError,
 };
@@ -15,12 +16,14 @@ use num::{
     traits::{float::FloatCore, ToPrimitive},
     BigInt,
     BigUint,
+    Integer,
     One,
     Unsigned,
     Zero,
 };
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp::Ordering,
     convert::{TryFrom, TryInto},
     fmt::Debug,
 };
@@ -30,6 +33,34 @@ use thiserror::Error;
 /// A numerical representation of a machine learning scalar.
 pub struct Scalar(Ratio<BigUint>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A rounding mode for conversions between [`Scalar`]s and primitive floats.
+pub enum Rounding {
+    /// Rounds to the nearest representable value. Ties are rounded to the value with an even
+    /// last digit, which avoids a systematic bias when many rounded values are summed up.
+    NearestEven,
+    /// Rounds to the nearest representable value of smaller or equal magnitude, i.e. truncates.
+    TowardZero,
+}
+
+impl Rounding {
+    /// Divides `numer` by `denom` and rounds the quotient to an integer.
+    ///
+    /// # Panics
+    /// Panics if `denom` is zero.
+    fn div(self, numer: &BigUint, denom: &BigUint) -> BigUint {
+        let (quot, rem) = numer.div_rem(denom);
+        match self {
+            Self::TowardZero => quot,
+            Self::NearestEven => match (rem << 1_usize).cmp(denom) {
+                Ordering::Less => quot,
+                Ordering::Equal if quot.is_even() => quot,
+                _ => quot + 1_u8,
+            },
+        }
+    }
+}
+
 impl From<Scalar> for Ratio<BigInt> {
     fn from(scalar: Scalar) -> Self {
         let (numer, denom) = scalar.0.into();
@@ -68,6 +99,118 @@ impl Scalar {
         Self(Ratio::one())
     }
 
+    /// The number of decimal digits a `Scalar` converted from a primitive float is quantized to.
+    ///
+    /// The masking configurations of the data types other than [`F16`] encode scalars with at
+    /// least this precision, hence a quantized scalar is masked exactly and the coordinator
+    /// unmasks the same value that the participant scaled its model with.
+    ///
+    /// [`F16`]: crate::mask::DataType::F16
+    pub const DECIMALS: u32 = 10;
+
+    /// The rounding mode in which the participants and the coordinator convert scalars.
+    ///
+    /// The masking rounds the scalars to the precision of the masking configuration in this
+    /// mode (see [`Scalar::masked()`]), hence a participant and the coordinator agree on the
+    /// unmasked scalar by construction if they convert it in this mode, too.
+    pub const ROUNDING: Rounding = Rounding::NearestEven;
+
+    /// Constructs a `Scalar` from a primitive float, quantized to [`Scalar::DECIMALS`] decimal
+    /// digits wrt the given rounding mode.
+    ///
+    /// Values smaller than the precision round to zero or to the smallest positive `Scalar`
+    /// as any other value, i.e. the round-trip error is bounded by `10^-DECIMALS` for all
+    /// values.
+    ///
+    /// # Errors
+    /// Returns an error if the value is not finite or negative.
+    pub fn from_f64_checked(value: f64, rounding: Rounding) -> Result<Self, ScalarCastError> {
+        if !value.is_finite() {
+            return Err(ScalarCastError::NotFinite(value));
+        }
+        if value.is_sign_negative() && value != 0_f64 {
+            return Err(ScalarCastError::Negative(value));
+        }
+        // safe unwrap: the value is finite
+        let ratio = Ratio::from_float(value).unwrap();
+        // safe unwrap: the value is non-negative
+        let scalar = Self::try_from(ratio).unwrap();
+        let grid = BigUint::from(10_u8).pow(Self::DECIMALS);
+        Ok(scalar.quantize(&grid, rounding))
+    }
+
+    /// Gets the scalar which the coordinator unmasks when this scalar is masked wrt the given
+    /// masking configuration, i.e. the scalar clamped to the bounds of the configuration and
+    /// rounded to its precision in the [`Scalar::ROUNDING`] mode.
+    pub fn masked(&self, config: &MaskConfig) -> Self {
+        // PANIC_SAFE: the additional and the exponent shifts are positive
+        let add_shift = Self::try_from(config.add_shift()).unwrap();
+        let exp_shift = config.exp_shift().to_biguint().unwrap();
+        let clamped = if self.0 > add_shift.0 {
+            add_shift
+        } else {
+            self.clone()
+        };
+        clamped.quantize(&exp_shift, Self::ROUNDING)
+    }
+
+    /// Checks whether the scalar is zero.
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Converts to a primitive float wrt the given rounding mode.
+    ///
+    /// The result is correctly rounded, i.e. it is the nearest float in the sense of the
+    /// rounding mode, including subnormals. Values beyond the range of `f64` saturate to
+    /// [`f64::MAX`].
+    pub fn to_f64_lossy(&self, rounding: Rounding) -> f64 {
+        const MANTISSA_BITS: i64 = 52;
+        const MIN_EXP: i64 = -1022;
+        const MAX_EXP: i64 = 1023;
+
+        let (numer, denom) = (self.0.numer(), self.0.denom());
+        if numer.is_zero() {
+            return 0_f64;
+        }
+
+        // binary exponent of the value, such that 2^exp <= numer / denom < 2^(exp + 1)
+        let mut exp = numer.bits() as i64 - denom.bits() as i64;
+        let below = if exp >= 0 {
+            numer < &(denom << exp as usize)
+        } else {
+            &(numer << (-exp) as usize) < denom
+        };
+        if below {
+            exp -= 1;
+        }
+        if exp > MAX_EXP {
+            return f64::MAX;
+        }
+
+        // binary exponent of the last mantissa digit, which is fixed for subnormals
+        let lsb = (exp - MANTISSA_BITS).max(MIN_EXP - MANTISSA_BITS);
+        let mantissa = if lsb >= 0 {
+            rounding.div(numer, &(denom << lsb as usize))
+        } else {
+            rounding.div(&(numer << (-lsb) as usize), denom)
+        };
+        // safe unwrap: the rounded mantissa has at most 54 bits
+        let float = mantissa.to_u64().unwrap() as f64 * pow2(lsb);
+        if float.is_finite() {
+            float
+        } else {
+            f64::MAX
+        }
+    }
+
+    /// Rounds to a multiple of `1 / grid` wrt the given rounding mode.
+    pub(crate) fn quantize(&self, grid: &BigUint, rounding: Rounding) -> Self {
+        let numer = self.0.numer() * grid;
+        let quantized = rounding.div(&numer, self.0.denom());
+        Self(Ratio::new(quantized, grid.clone()))
+    }
+
     /// Convenience method for conversion to a non-negative ratio of `BigInt`.
     pub(crate) fn to_ratio(&self) -> Ratio<BigInt> {
         self.clone().into()
@@ -89,12 +232,27 @@ impl Scalar {
     }
 }
 
-#[derive(Error, Debug)]
-#[error("Could not convert weight {weight} to primitive type {target}")]
-/// Errors related to scalar conversion into primitives.
-pub struct ScalarCastError {
-    weight: Ratio<BigUint>,
-    target: PrimitiveType,
+/// Gets the float `2^exp` for an exponent in the range of subnormal and normal floats.
+fn pow2(exp: i64) -> f64 {
+    if exp >= -1022 {
+        f64::from_bits(((exp + 1023) as u64) << 52)
+    } else {
+        f64::from_bits(1_u64 << (exp + 1074))
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+/// Errors related to scalar conversion from and into primitives.
+pub enum ScalarCastError {
+    #[error("Could not convert weight {weight} to primitive type {target}")]
+    IntoPrimitive {
+        weight: Ratio<BigUint>,
+        target: PrimitiveType,
+    },
+    #[error("Could not convert {0} to a scalar: the value is not finite")]
+    NotFinite(f64),
+    #[error("Could not convert {0} to a scalar: the value is negative")]
+    Negative(f64),
 }
 
 /// An interface for conversion into a primitive value.
@@ -102,6 +260,10 @@ pub struct ScalarCastError {
 /// This trait is used to convert a [`Scalar`], which has its own internal
 /// representation, into a primitive type ([`f32`], [`f64`], [`i32`], [`i64`]).
 /// The opposite trait is [`FromPrimitive`].
+///
+/// The conversion rounds implicitly, which is why it is deprecated in favor of
+/// [`Scalar::to_f64_lossy()`].
+#[deprecated(note = "use `Scalar::to_f64_lossy()`, which makes the rounding mode explicit")]
 pub trait IntoPrimitive<P>: Sized {
     /// Consumes into a converted primitive value.
     ///
@@ -130,6 +292,12 @@ pub trait IntoPrimitive<P>: Sized {
 /// This trait is used to obtain a [`Scalar`], which has its own representation,
 /// from a primitive type ([`f32`], [`f64`], [`i32`], [`i64`]). The opposite
 /// trait is [`IntoPrimitive`].
+///
+/// The conversion rounds implicitly, which is why it is deprecated in favor of
+/// [`Scalar::from_f64_checked()`] and [`Scalar::from_integer()`].
+#[deprecated(
+    note = "use `Scalar::from_f64_checked()`, which makes the rounding mode explicit, or `Scalar::from_integer()`"
+)]
 pub trait FromPrimitive<P: Debug>: Sized {
     /// Converts from a primitive value.
     ///
@@ -143,13 +311,16 @@ pub trait FromPrimitive<P: Debug>: Sized {
     fn from_primitive_bounded(prim: P) -> Self;
 }
 
+#[allow(deprecated)]
 impl IntoPrimitive<i32> for Scalar {
     fn into_primitive(self) -> Result<i32, ScalarCastError> {
         let r = self.0;
-        r.to_integer().to_i32().ok_or(ScalarCastError {
-            weight: r,
-            target: PrimitiveType::I32,
-        })
+        r.to_integer()
+            .to_i32()
+            .ok_or(ScalarCastError::IntoPrimitive {
+                weight: r,
+                target: PrimitiveType::I32,
+            })
     }
 
     fn to_primitive(&self) -> Result<i32, ScalarCastError> {
@@ -157,6 +328,7 @@ impl IntoPrimitive<i32> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl FromPrimitive<i32> for Scalar {
     fn from_primitive(prim: i32) -> Result<Self, PrimitiveCastError<i32>> {
         let i = BigUint::try_from(prim).map_err(|_| PrimitiveCastError(prim))?;
@@ -168,13 +340,16 @@ impl FromPrimitive<i32> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl IntoPrimitive<i64> for Scalar {
     fn into_primitive(self) -> Result<i64, ScalarCastError> {
         let i = self.0;
-        i.to_integer().to_i64().ok_or(ScalarCastError {
-            weight: i,
-            target: PrimitiveType::I64,
-        })
+        i.to_integer()
+            .to_i64()
+            .ok_or(ScalarCastError::IntoPrimitive {
+                weight: i,
+                target: PrimitiveType::I64,
+            })
     }
 
     fn to_primitive(&self) -> Result<i64, ScalarCastError> {
@@ -182,6 +357,7 @@ impl IntoPrimitive<i64> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl FromPrimitive<i64> for Scalar {
     fn from_primitive(prim: i64) -> Result<Self, PrimitiveCastError<i64>> {
         let i = BigUint::try_from(prim).map_err(|_| PrimitiveCastError(prim))?;
@@ -193,10 +369,11 @@ impl FromPrimitive<i64> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl IntoPrimitive<f32> for Scalar {
     fn into_primitive(self) -> Result<f32, ScalarCastError> {
         let r = self.to_ratio();
-        ratio_to_float(&r).ok_or(ScalarCastError {
+        ratio_to_float(&r).ok_or(ScalarCastError::IntoPrimitive {
             weight: self.0,
             target: PrimitiveType::F32,
         })
@@ -207,6 +384,7 @@ impl IntoPrimitive<f32> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl FromPrimitive<f32> for Scalar {
     fn from_primitive(prim: f32) -> Result<Self, PrimitiveCastError<f32>> {
         let r = Ratio::from_float(prim).ok_or(PrimitiveCastError(prim))?;
@@ -218,10 +396,11 @@ impl FromPrimitive<f32> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl IntoPrimitive<f64> for Scalar {
     fn into_primitive(self) -> Result<f64, ScalarCastError> {
         let r = self.to_ratio();
-        ratio_to_float(&r).ok_or(ScalarCastError {
+        ratio_to_float(&r).ok_or(ScalarCastError::IntoPrimitive {
             weight: self.0,
             target: PrimitiveType::F64,
         })
@@ -232,6 +411,7 @@ impl IntoPrimitive<f64> for Scalar {
     }
 }
 
+#[allow(deprecated)]
 impl FromPrimitive<f64> for Scalar {
     fn from_primitive(prim: f64) -> Result<Self, PrimitiveCastError<f64>> {
         let r = Ratio::from_float(prim).ok_or(PrimitiveCastError(prim))?;
@@ -244,8 +424,37 @@ impl FromPrimitive<f64> for Scalar {
 }
 
 #[cfg(test)]
+#[allow(deprecated)]
 mod tests {
+    use rand::{
+        distributions::{Distribution, Uniform},
+        SeedableRng,
+    };
+    use rand_chacha::ChaCha20Rng;
+
     use super::*;
+    use crate::mask::{BoundType, DataType, GroupType, ModelType};
+    use num::Signed;
+
+    /// Values which are not exactly representable on the grid of quantized scalars or which
+    /// are close to the bounds of the masking configurations.
+    const AWKWARD_VALUES: [f64; 11] = [
+        1_f64 / 3_f64,
+        2_f64 / 3_f64,
+        0.1,
+        1e-10,
+        1e-12,
+        1.6e-10,
+        1_f64 - 1e-11,
+        100_f64 - 1e-9,
+        1e6 - 1e-5,
+        f32::MAX as f64 / 2.1,
+        f64::MAX / 2.1,
+    ];
+
+    fn grid() -> Ratio<BigInt> {
+        Ratio::new(BigInt::one(), BigInt::from(10_u8).pow(Scalar::DECIMALS))
+    }
 
     #[test]
     fn test_ratio_conversion() {
@@ -386,4 +595,192 @@ mod tests {
             assert_eq!(converted_prim, prim);
         }
     }
+
+    #[test]
+    fn test_from_f64_checked_err() {
+        for rounding in [Rounding::NearestEven, Rounding::TowardZero]
+            .iter()
+            .copied()
+        {
+            assert!(matches!(
+                Scalar::from_f64_checked(f64::NAN, rounding),
+                Err(ScalarCastError::NotFinite(_))
+            ));
+            assert!(matches!(
+                Scalar::from_f64_checked(f64::INFINITY, rounding),
+                Err(ScalarCastError::NotFinite(_))
+            ));
+            assert!(matches!(
+                Scalar::from_f64_checked(-1_f64, rounding),
+                Err(ScalarCastError::Negative(_))
+            ));
+            assert_eq!(
+                Scalar::from_f64_checked(-0_f64, rounding).unwrap(),
+                Scalar::from_integer(0_u8),
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_f64_checked_rounding() {
+        let denom = 10_000_000_000_u64;
+        let pairs = vec![
+            (1_f64 / 3_f64, 3_333_333_333_u64, 3_333_333_333_u64),
+            (2_f64 / 3_f64, 6_666_666_667, 6_666_666_666),
+            (1_f64 - 1e-11, 10_000_000_000, 9_999_999_999),
+            (1e-12, 0, 0),
+            (0.6e-10, 1, 0),
+            (1.4e-10, 1, 1),
+            (1.6e-10, 2, 1),
+            (2_f64, 20_000_000_000, 20_000_000_000),
+        ];
+        for (value, nearest, toward_zero) in pairs {
+            assert_eq!(
+                Scalar::from_f64_checked(value, Rounding::NearestEven).unwrap(),
+                Scalar::new(nearest, denom),
+            );
+            assert_eq!(
+                Scalar::from_f64_checked(value, Rounding::TowardZero).unwrap(),
+                Scalar::new(toward_zero, denom),
+            );
+        }
+    }
+
+    #[test]
+    fn test_masked() {
+        let config = |data_type| MaskConfig {
+            group_type: GroupType::Prime,
+            data_type,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        };
+        let f32_config = config(DataType::F32);
+        let f16_config = config(DataType::F16);
+
+        // clamped to the bound and rounded to the precision of the configuration
+        let third = Scalar::new(1_u8, 3_u8);
+        assert_eq!(
+            third.masked(&f32_config),
+            Scalar::new(3_333_333_333_u64, 10_000_000_000)
+        );
+        assert_eq!(third.masked(&f16_config), Scalar::new(33_333_u32, 100_000));
+        assert_eq!(
+            Scalar::from_integer(2_u8).masked(&f32_config),
+            Scalar::unit()
+        );
+
+        // a converted scalar is masked exactly, unless it is below the precision
+        let converted = Scalar::from_f64_checked(1_f64 / 3_f64, Scalar::ROUNDING).unwrap();
+        assert_eq!(converted.masked(&f32_config), converted);
+        let tiny = Scalar::new(1_u64, 1_000_000_000_000);
+        assert!(tiny.masked(&f32_config).is_zero());
+        assert!(!tiny.is_zero());
+    }
+
+    #[test]
+    fn test_quantize_ties_to_even() {
+        let grid = BigUint::from(10_u8);
+        let pairs = vec![(5_u8, 0_u8), (15, 2), (25, 2), (35, 4)];
+        for (numer, nearest) in pairs {
+            let scalar = Scalar::new(numer, 100);
+            assert_eq!(
+                scalar.quantize(&grid, Rounding::NearestEven),
+                Scalar::new(nearest, 10),
+            );
+            assert_eq!(
+                scalar.quantize(&grid, Rounding::TowardZero),
+                Scalar::new(numer / 10, 10),
+            );
+        }
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_to_f64_lossy() {
+        let third = Scalar::new(1_u8, 3_u8);
+        assert_eq!(third.to_f64_lossy(Rounding::NearestEven), 1_f64 / 3_f64);
+        let toward_zero = third.to_f64_lossy(Rounding::TowardZero);
+        assert!(Ratio::from_float(toward_zero).unwrap() <= third.to_ratio());
+
+        // the smallest subnormal and rounding below it
+        let min_subnormal = Scalar(Ratio::new(BigUint::one(), BigUint::one() << 1074_usize));
+        assert_eq!(
+            min_subnormal.to_f64_lossy(Rounding::NearestEven),
+            f64::from_bits(1),
+        );
+        let below = Scalar(Ratio::new(
+            BigUint::one(),
+            BigUint::from(3_u8) << 1073_usize,
+        ));
+        assert_eq!(below.to_f64_lossy(Rounding::NearestEven), f64::from_bits(1));
+        assert_eq!(below.to_f64_lossy(Rounding::TowardZero), 0_f64);
+
+        // saturation at the largest float
+        let huge = Scalar::from_integer(BigUint::one() << 1024_usize);
+        assert_eq!(huge.to_f64_lossy(Rounding::NearestEven), f64::MAX);
+        assert_eq!(huge.to_f64_lossy(Rounding::TowardZero), f64::MAX);
+        let max = Scalar::from_float_bounded(f64::MAX);
+        assert_eq!(max.to_f64_lossy(Rounding::NearestEven), f64::MAX);
+
+        assert_eq!(
+            Scalar::from_integer(0_u8).to_f64_lossy(Rounding::NearestEven),
+            0_f64
+        );
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_to_f64_lossy_is_correctly_rounded() {
+        // the quotient of two integers below 2^53 is correctly rounded by the float division
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+        let uniform = Uniform::new_inclusive(1_u32, u32::MAX);
+        for _ in 0..10_000 {
+            let (numer, denom) = (uniform.sample(&mut prng), uniform.sample(&mut prng));
+            let scalar = Scalar::new(numer, denom);
+            let nearest = scalar.to_f64_lossy(Rounding::NearestEven);
+            assert_eq!(nearest, numer as f64 / denom as f64);
+
+            let toward_zero = scalar.to_f64_lossy(Rounding::TowardZero);
+            let toward_zero_next = f64::from_bits(toward_zero.to_bits() + 1);
+            assert!(Ratio::from_float(toward_zero).unwrap() <= scalar.to_ratio());
+            assert!(Ratio::from_float(toward_zero_next).unwrap() > scalar.to_ratio());
+        }
+    }
+
+    #[test]
+    fn test_f64_round_trip() {
+        let mut prng = ChaCha20Rng::seed_from_u64(0);
+        let uniform = Uniform::new_inclusive(1e-10, 1e6);
+        let values = (0..10_000)
+            .map(|_| uniform.sample(&mut prng))
+            .chain(AWKWARD_VALUES.iter().copied());
+        for value in values {
+            let exact = Ratio::from_float(value).unwrap();
+            let float_error = Ratio::from_float(value * f64::EPSILON).unwrap();
+
+            let nearest = Scalar::from_f64_checked(value, Rounding::NearestEven).unwrap();
+            let rounded = Ratio::from_float(nearest.to_f64_lossy(Rounding::NearestEven)).unwrap();
+            assert!((rounded - &exact).abs() <= grid() / BigInt::from(2_u8) + &float_error);
+
+            let toward_zero = Scalar::from_f64_checked(value, Rounding::TowardZero).unwrap();
+            let truncated =
+                Ratio::from_float(toward_zero.to_f64_lossy(Rounding::TowardZero)).unwrap();
+            assert!(truncated <= exact);
+            assert!(&exact - truncated <= grid() + &float_error);
+        }
+    }
+
+    #[test]
+    fn test_from_f64_checked_is_idempotent() {
+        // a quantized scalar converted back to a float converts to the very same scalar, hence
+        // a value handed on between participant and coordinator as a float doesn't drift
+        for value in AWKWARD_VALUES.iter().copied() {
+            let scalar = Scalar::from_f64_checked(value, Rounding::NearestEven).unwrap();
+            let float = scalar.to_f64_lossy(Rounding::NearestEven);
+            assert_eq!(
+                Scalar::from_f64_checked(float, Rounding::NearestEven).unwrap(),
+                scalar,
+            );
+        }
+    }
 }
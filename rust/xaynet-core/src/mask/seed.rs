@@ -14,7 +14,7 @@ use sodiumoxide::crypto::box_;
 use thiserror::Error;
 
 use crate::{
-    crypto::{encrypt::SEALBYTES, prng::generate_integer, ByteObject},
+    crypto::{encrypt::SEALBYTES, prng::generate_integer, ByteObject, EncryptKeySeed},
     mask::{
         object::{MaskObject, MaskUnit, MaskVect},
         MaskConfigPair,
@@ -57,6 +57,21 @@ impl MaskSeed {
         EncryptedMaskSeed::from_slice_unchecked(pk.encrypt(self.as_slice()).as_slice())
     }
 
+    /// Encrypts this seed with the given public key as an [`EncryptedMaskSeed`], reproducibly
+    /// from the given encryption `seed` (see [`PublicEncryptKey::encrypt_with_seed()`]).
+    ///
+    /// [`PublicEncryptKey::encrypt_with_seed()`]: crate::crypto::PublicEncryptKey::encrypt_with_seed
+    pub fn encrypt_with_seed(
+        &self,
+        pk: &SumParticipantEphemeralPublicKey,
+        seed: &EncryptKeySeed,
+    ) -> EncryptedMaskSeed {
+        // safe unwrap: length of slice is guaranteed by constants
+        EncryptedMaskSeed::from_slice_unchecked(
+            pk.encrypt_with_seed(self.as_slice(), seed).as_slice(),
+        )
+    }
+
     /// Derives a mask of given length from this seed wrt the masking configurations.
     pub fn derive_mask(&self, len: usize, config: MaskConfigPair) -> MaskObject {
         let MaskConfigPair {
@@ -186,4 +201,21 @@ mod tests {
         let decr_seed = encr_seed.decrypt(&public, &secret).unwrap();
         assert_eq!(seed, decr_seed);
     }
+
+    #[test]
+    fn test_encryption_with_seed() {
+        let seed = MaskSeed::generate();
+        let EncryptKeyPair { public, secret } = EncryptKeyPair::generate();
+        let encr_seed = seed.encrypt_with_seed(&public, &EncryptKeySeed::zeroed());
+        assert_eq!(
+            encr_seed,
+            seed.encrypt_with_seed(&public, &EncryptKeySeed::zeroed())
+        );
+        assert_ne!(
+            encr_seed,
+            seed.encrypt_with_seed(&public, &EncryptKeySeed::generate())
+        );
+        let decr_seed = encr_seed.decrypt(&public, &secret).unwrap();
+        assert_eq!(seed, decr_seed);
+    }
 }
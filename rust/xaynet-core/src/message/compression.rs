@@ -0,0 +1,88 @@
+//! Compression of the PET messages.
+//!
+//! The serialized payload of a large message can be compressed with [zstd] before it is
+//! signed and encrypted. A compressed message is always sent as a multipart message: its
+//! chunks carry the compressed payload and have the [`Flags::COMPRESSED`] flag set. The
+//! coordinator decompresses the payload once all the chunks arrived.
+//!
+//! [zstd]: https://facebook.github.io/zstd/
+//! [`Flags::COMPRESSED`]: crate::message::Flags::COMPRESSED
+
+use std::io::Read;
+
+use anyhow::{anyhow, Context};
+
+use crate::message::DecodeError;
+
+/// The zstd compression level. Masked models are made of large random integers, so higher
+/// levels cost time for little gain.
+const LEVEL: i32 = 3;
+
+/// Compresses the serialized payload of a message.
+pub fn compress(payload: &[u8]) -> Vec<u8> {
+    // PANIC_SAFE: compressing a buffer in memory only fails if zstd runs out of memory
+    zstd::bulk::compress(payload, LEVEL).expect("failed to compress the message payload")
+}
+
+/// Decompresses the payload of a message.
+///
+/// # Errors
+/// Fails if the data is not compressed with zstd or if the decompressed payload is larger
+/// than `max_len` bytes. The output buffer grows with the decompressed payload, so a large
+/// `max_len` doesn't allocate memory upfront.
+pub fn decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut payload = Vec::new();
+    zstd::stream::read::Decoder::new(data)
+        .context("failed to initialize the decompression")?
+        .take(max_len as u64 + 1)
+        .read_to_end(&mut payload)
+        .context("failed to decompress the message payload")?;
+    if payload.len() > max_len {
+        return Err(anyhow!(
+            "the decompressed payload exceeds the maximum length of {} bytes",
+            max_len
+        ));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mask::{FromPrimitives, Masker, Model, Scalar},
+        message::{FromBytes, ToBytes, Update},
+        testutils::messages as helpers,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let (update, bytes) = helpers::update::payload();
+        let compressed = compress(&bytes);
+        assert_eq!(decompress(&compressed, bytes.len()).unwrap(), bytes);
+        assert_eq!(
+            Update::from_byte_slice(&decompress(&compressed, bytes.len()).unwrap()).unwrap(),
+            update
+        );
+    }
+
+    #[test]
+    fn test_round_trip_masked_model() {
+        // a masked model of 1MB of f32 weights
+        let model = Model::from_primitives(vec![0.5_f32; 1 << 18].into_iter()).unwrap();
+        let config = helpers::mask::mask_config().0.into();
+        let (_, masked_model) = Masker::new(config).mask(Scalar::unit(), &model);
+        let mut bytes = vec![0; masked_model.buffer_length()];
+        masked_model.to_bytes(&mut bytes);
+
+        let compressed = compress(&bytes);
+        assert_eq!(decompress(&compressed, bytes.len()).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_decompress_too_large() {
+        let bytes = vec![0; 1000];
+        assert!(decompress(&compress(&bytes), 999).is_err());
+        assert!(decompress(&bytes, 1000).is_err());
+    }
+}
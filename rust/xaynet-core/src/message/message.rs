@@ -144,8 +144,8 @@ pub const HEADER_LENGTH: usize = ranges::RESERVED.end;
 ///   messages can be as big as 2^32 = 4,294,967,296 bytes.
 /// - `tag` indicates the type of message (sum, update, sum2 or
 ///   multipart message)
-/// - the `flags` field currently supports a single flag, that
-///   indicates whether this is a multipart message
+/// - the `flags` field indicates whether this is a multipart message
+///   and whether its payload is compressed (see [`Flags`])
 ///
 /// # Examples
 /// ## Reading a sum message
@@ -433,6 +433,10 @@ bitflags::bitflags! {
     pub struct Flags: u8 {
         /// Indicates whether this message is a multipart message
         const MULTIPART = 1 << 0;
+        /// Indicates whether the payload of this multipart message is
+        /// compressed. The flag is only valid together with
+        /// [`Flags::MULTIPART`].
+        const COMPRESSED = 1 << 1;
     }
 }
 
@@ -483,6 +487,9 @@ pub struct Message {
     pub coordinator_pk: PublicEncryptKey,
     /// Wether this is a multipart message
     pub is_multipart: bool,
+    /// Whether the chunks of this multipart message carry a
+    /// compressed payload.
+    pub is_compressed: bool,
     /// The type of message. This information is partially redundant
     /// with the `payload` field. So when serializing the message,
     /// this field is ignored if the payload is a [`Payload::Sum`],
@@ -506,6 +513,7 @@ impl Message {
             participant_pk,
             coordinator_pk,
             is_multipart: false,
+            is_compressed: false,
             tag: Tag::Sum,
             payload: message.into(),
         }
@@ -523,6 +531,7 @@ impl Message {
             participant_pk,
             coordinator_pk,
             is_multipart: false,
+            is_compressed: false,
             tag: Tag::Sum2,
             payload: message.into(),
         }
@@ -540,6 +549,7 @@ impl Message {
             participant_pk,
             coordinator_pk,
             is_multipart: false,
+            is_compressed: false,
             tag: Tag::Update,
             payload: message.into(),
         }
@@ -558,6 +568,7 @@ impl Message {
             participant_pk,
             coordinator_pk,
             is_multipart: true,
+            is_compressed: false,
             tag,
             payload: message.into(),
         }
@@ -577,6 +588,10 @@ impl Message {
 
         let tag = reader.tag().try_into()?;
         let is_multipart = reader.flags().contains(Flags::MULTIPART);
+        let is_compressed = reader.flags().contains(Flags::COMPRESSED);
+        if is_compressed && !is_multipart {
+            return Err(anyhow!("compressed messages must be multipart messages"));
+        }
 
         let payload = if is_multipart {
             Chunk::from_byte_slice(&reader.payload()).map(Into::into)
@@ -595,6 +610,7 @@ impl Message {
             signature: Some(signature),
             payload,
             is_multipart,
+            is_compressed,
             tag,
         })
     }
@@ -618,11 +634,9 @@ impl Message {
             .to_bytes(&mut writer.participant_pk_mut());
         self.coordinator_pk
             .to_bytes(&mut writer.coordinator_pk_mut());
-        let flags = if self.is_multipart {
-            Flags::MULTIPART
-        } else {
-            Flags::empty()
-        };
+        let mut flags = Flags::empty();
+        flags.set(Flags::MULTIPART, self.is_multipart);
+        flags.set(Flags::COMPRESSED, self.is_compressed);
         writer.set_flags(flags);
         self.payload.to_bytes(&mut writer.payload_mut());
         // Determine the tag from the payload type if
@@ -703,4 +717,27 @@ mod tests {
             .copy_from_slice(helpers::sum::payload().1.as_slice());
         assert_eq!(bytes, expected);
     }
+
+    #[test]
+    fn compressed_flag() {
+        let (mut message, mut bytes) = sum_message();
+        // only multipart messages can be compressed
+        bytes[ranges::FLAGS] = Flags::COMPRESSED.bits();
+        assert!(Message::from_byte_slice(&bytes).is_err());
+
+        let chunk = Chunk {
+            id: 0,
+            message_id: 1,
+            last: true,
+            data: vec![0xff; 10],
+        };
+        message.payload = chunk.into();
+        message.is_multipart = true;
+        message.is_compressed = true;
+        let mut bytes = vec![0; message.buffer_length()];
+        message.to_bytes(&mut bytes, &SecretSigningKey::zeroed());
+        let buffer = MessageBuffer::new(&bytes).unwrap();
+        assert_eq!(buffer.flags(), Flags::MULTIPART | Flags::COMPRESSED);
+        assert_eq!(Message::from_byte_slice(&bytes).unwrap(), message);
+    }
 }
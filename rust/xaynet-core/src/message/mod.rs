@@ -23,6 +23,9 @@
 //! - The sum signature proves the eligibility of the participant for the sum task.
 //! - The global mask is used by XayNet to unmask the aggregated global model.
 
+#[cfg(feature = "compression")]
+#[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+pub mod compression;
 #[allow(clippy::module_inception)]
 pub(crate) mod message;
 pub(crate) mod payload;
@@ -297,16 +297,8 @@ pub mod tests {
         assert_eq!(update.buffer_length(), bytes.len());
         let mut buf = vec![0xff; update.buffer_length()];
         update.to_bytes(&mut buf);
-        // The order in which the hashmap is serialized is not
-        // guaranteed, but we chose our key/values such that they are
-        // sorted.
-        //
-        // First compute the offset at which the local seed dict value
-        // starts: two signature (64 bytes), the masked model (32
-        // bytes), the length field (4 bytes), the masked scalar (10 bytes)
-        let offset = 64 * 2 + 32 + 4 + 10;
-        // Sort the end of the buffer
-        (&mut buf[offset..]).sort_unstable();
+        // The local seed dictionary is serialized in its canonical form, and the
+        // expected entries are sorted by key.
         assert_eq!(buf, bytes);
     }
 }
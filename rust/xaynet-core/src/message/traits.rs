@@ -14,6 +14,7 @@ use std::{
 use anyhow::{anyhow, Context};
 
 use crate::{
+    common::sorted_entries,
     crypto::ByteObject,
     mask::seed::EncryptedMaskSeed,
     message::{utils::ChunkableIterator, DecodeError},
@@ -276,6 +277,11 @@ impl<'a, T: AsRef<[u8]> + ?Sized> LengthValueBuffer<&'a T> {
 
 const ENTRY_LENGTH: usize = SumParticipantPublicKey::LENGTH + EncryptedMaskSeed::LENGTH;
 
+/// The local seed dictionary is serialized in its canonical form: the entries are
+/// sorted by the bytes of their keys (see [`Canonical`]). The entries are accepted in
+/// any order when deserializing.
+///
+/// [`Canonical`]: crate::common::Canonical
 impl ToBytes for LocalSeedDict {
     fn buffer_length(&self) -> usize {
         LENGTH_FIELD.end + self.len() * ENTRY_LENGTH
@@ -285,7 +291,7 @@ impl ToBytes for LocalSeedDict {
         let mut writer = Cursor::new(buffer.as_mut());
         let length = self.buffer_length() as u32;
         let _ = writer.write(&length.to_be_bytes()).unwrap();
-        for (key, value) in self {
+        for (key, value) in sorted_entries(self) {
             let _ = writer.write(key.as_slice()).unwrap();
             let _ = writer.write(value.as_ref()).unwrap();
         }
@@ -402,6 +408,47 @@ impl FromBytes for u32 {
 mod tests {
     use super::*;
 
+    fn local_seed_dict(keys: impl Iterator<Item = u8>) -> (LocalSeedDict, Vec<u8>) {
+        let mut dict = LocalSeedDict::new();
+        let mut bytes = Vec::new();
+        for key in keys {
+            let key = vec![key; SumParticipantPublicKey::LENGTH];
+            let seed = vec![0xaa; EncryptedMaskSeed::LENGTH];
+            bytes.extend(&key);
+            bytes.extend(&seed);
+            dict.insert(
+                SumParticipantPublicKey::from_slice(&key).unwrap(),
+                EncryptedMaskSeed::from(seed),
+            );
+        }
+        let length = (bytes.len() as u32 + 4).to_be_bytes();
+        bytes.splice(0..0, length.iter().cloned());
+        (dict, bytes)
+    }
+
+    #[test]
+    fn encode_local_seed_dict_canonical() {
+        let (dict, bytes) = local_seed_dict(0..16);
+        // a dictionary with a different iteration order
+        let (reversed, _) = local_seed_dict((0..16).rev());
+
+        let mut buffer = vec![0; dict.buffer_length()];
+        dict.to_bytes(&mut buffer);
+        assert_eq!(buffer, bytes);
+        reversed.to_bytes(&mut buffer);
+        assert_eq!(buffer, bytes);
+    }
+
+    #[test]
+    fn decode_local_seed_dict_unordered() {
+        let (dict, bytes) = local_seed_dict((0..16).rev());
+        assert_eq!(LocalSeedDict::from_byte_slice(&bytes).unwrap(), dict);
+        assert_eq!(
+            LocalSeedDict::from_byte_stream(&mut bytes.into_iter()).unwrap(),
+            dict
+        );
+    }
+
     #[test]
     fn decode_length_value_buffer() {
         let bytes = vec![
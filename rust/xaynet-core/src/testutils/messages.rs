@@ -60,6 +60,7 @@ where
         coordinator_pk: coordinator_pk().0,
         payload,
         is_multipart: false,
+        is_compressed: false,
         tag,
     };
 
@@ -122,7 +123,7 @@ pub mod update {
     }
 
     /// Return a local seed dictionary with two entries with its
-    /// expected serialized version
+    /// expected serialized version, in canonical form
     pub fn local_seed_dict() -> (LocalSeedDict, Vec<u8>) {
         let mut local_seed_dict = LocalSeedDict::new();
         let mut bytes = vec![];
@@ -0,0 +1,229 @@
+use std::{fs::File, io::Read, time::Duration};
+
+use thiserror::Error;
+
+#[cfg(all(feature = "hyper-client", not(feature = "reqwest-client")))]
+use xaynet_sdk::client::hyper_client_builder;
+#[cfg(feature = "reqwest-client")]
+use xaynet_sdk::client::reqwest_client_builder;
+use xaynet_sdk::client::{Client, ReloadableClient};
+
+use crate::{DataUsage, MeteredClient, Notifier};
+
+/// Error returned upon failing to instantiate a new [`xaynet_sdk::client::Client`]
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("failed to read trust anchor {0}: {1}")]
+    TrustAnchor(String, String),
+    #[error("failed to read client certificate {0}: {1}")]
+    ClientCert(String, String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl ClientError {
+    fn trust_anchor<E: std::error::Error>(path: String, e: E) -> Self {
+        Self::TrustAnchor(path, format!("{}", e))
+    }
+
+    fn client_cert<E: std::error::Error>(path: String, e: E) -> Self {
+        Self::ClientCert(path, format!("{}", e))
+    }
+
+    fn other<E: std::error::Error>(e: E) -> Self {
+        Self::Other(format!("{}", e))
+    }
+}
+
+/// The HTTP backend of the [`xaynet_sdk::client::Client`], selected by the
+/// `reqwest-client` and `hyper-client` features.
+#[cfg(feature = "reqwest-client")]
+type HttpClient = reqwest::Client;
+#[cfg(all(feature = "hyper-client", not(feature = "reqwest-client")))]
+type HttpClient = xaynet_sdk::client::HyperClient;
+#[cfg(not(any(feature = "reqwest-client", feature = "hyper-client")))]
+compile_error!("either the `reqwest-client` or the `hyper-client` feature must be enabled");
+
+/// The client the participant uses to talk to the coordinator. Its HTTP backend can be
+/// rebuilt with new TLS certificates via a [`TlsReloader`].
+pub(crate) type CoordinatorClient = Client<MeteredClient<ReloadableClient<HttpClient>>>;
+
+/// How the certificates are described in the errors when they are not read from a file.
+const IN_MEMORY: &str = "<in memory>";
+
+/// The configuration the HTTP backend is built with. The trust anchor and the client
+/// certificate are given with their paths, which are only used in the errors.
+#[derive(Clone)]
+struct HttpClientConfig {
+    pool_idle_timeout: Duration,
+    trust_anchor: Option<(String, Vec<u8>)>,
+    client_cert: Option<(String, Vec<u8>)>,
+}
+
+impl HttpClientConfig {
+    fn build(&self) -> Result<HttpClient, ClientError> {
+        build_http_client(
+            self.pool_idle_timeout,
+            self.trust_anchor.clone(),
+            self.client_cert.clone(),
+        )
+    }
+}
+
+/// Rotates the TLS certificates of a [`CoordinatorClient`] by rebuilding its HTTP backend.
+///
+/// The clones of the client, like the one owned by the participant state machine, use the
+/// new certificates for their next requests, so the participant doesn't lose its progress.
+pub(crate) struct TlsReloader {
+    config: HttpClientConfig,
+    http_client: ReloadableClient<HttpClient>,
+}
+
+impl TlsReloader {
+    /// Replace the root certificate used for TLS server authentication. The certificate
+    /// must be PEM encoded.
+    pub(crate) fn set_root_certificate(&mut self, pem: Vec<u8>) -> Result<(), ClientError> {
+        let mut config = self.config.clone();
+        config.trust_anchor = Some((IN_MEMORY.to_string(), pem));
+        self.reload(config)
+    }
+
+    /// Replace the identity used for TLS client authentication. The private key and the
+    /// certificate must be PEM encoded.
+    pub(crate) fn set_identity(&mut self, pem: Vec<u8>) -> Result<(), ClientError> {
+        let mut config = self.config.clone();
+        config.client_cert = Some((IN_MEMORY.to_string(), pem));
+        self.reload(config)
+    }
+
+    /// Rebuild the HTTP backend. If it fails, the current backend is kept.
+    fn reload(&mut self, config: HttpClientConfig) -> Result<(), ClientError> {
+        self.http_client.reload(config.build()?);
+        self.config = config;
+        Ok(())
+    }
+}
+
+/// Build a new [`xaynet_sdk::client::Client`]
+///
+/// # Args
+///
+/// - `address`: URL of the Xaynet coordinator to connect to
+/// - `data_usage`: the data usage into which the client records the data it consumes
+/// - `notifier`: notifier used to emit an event when the daily data budget is exhausted
+/// - `trust_anchor_path`: path the to root certificate for TLS server authentication. The
+///   certificate must be PEM encoded.
+/// - `client_cert_path`: path to the client certificate to use for TLS client authentication. The
+///   certificate must be PEM encoded.
+/// - `pool_idle_timeout`: how long an idle connection to the coordinator is kept open
+///
+/// The certificates of the client can be rotated afterwards with the returned
+/// [`TlsReloader`].
+pub(crate) fn new_client(
+    address: &str,
+    data_usage: DataUsage,
+    notifier: Notifier,
+    trust_anchor_path: Option<String>,
+    client_cert_path: Option<String>,
+    pool_idle_timeout: Duration,
+) -> Result<(CoordinatorClient, TlsReloader), ClientError> {
+    let trust_anchor = trust_anchor_path
+        .map(|path| {
+            read_pem(&path)
+                .map_err(|e| ClientError::trust_anchor(path.clone(), e))
+                .map(|pem| (path, pem))
+        })
+        .transpose()?;
+    let client_cert = client_cert_path
+        .map(|path| {
+            read_pem(&path)
+                .map_err(|e| ClientError::client_cert(path.clone(), e))
+                .map(|pem| (path, pem))
+        })
+        .transpose()?;
+    let config = HttpClientConfig {
+        pool_idle_timeout,
+        trust_anchor,
+        client_cert,
+    };
+    let http_client = ReloadableClient::new(config.build()?);
+    let mut metered_client = MeteredClient::new(http_client.clone(), data_usage);
+    metered_client.set_notifier(notifier);
+
+    let xaynet_client = Client::new(metered_client, address)
+        .map_err(|_| ClientError::InvalidUrl(address.to_string()))?;
+    Ok((
+        xaynet_client,
+        TlsReloader {
+            config,
+            http_client,
+        },
+    ))
+}
+
+/// Read a PEM encoded file.
+fn read_pem(path: &str) -> Result<Vec<u8>, std::io::Error> {
+    let mut buf = Vec::new();
+    File::open(path)?.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Build the HTTP backend. The trust anchor and the client certificate are given with
+/// their paths, which are only used in the errors.
+#[cfg(feature = "reqwest-client")]
+fn build_http_client(
+    pool_idle_timeout: Duration,
+    trust_anchor: Option<(String, Vec<u8>)>,
+    client_cert: Option<(String, Vec<u8>)>,
+) -> Result<HttpClient, ClientError> {
+    let builder = reqwest_client_builder(pool_idle_timeout);
+
+    let builder = if let Some((path, pem)) = trust_anchor {
+        let root_cert =
+            reqwest::Certificate::from_pem(&pem).map_err(|e| ClientError::trust_anchor(path, e))?;
+        builder.use_rustls_tls().add_root_certificate(root_cert)
+    } else {
+        builder
+    };
+
+    let builder = if let Some((path, pem)) = client_cert {
+        let identity =
+            reqwest::Identity::from_pem(&pem).map_err(|e| ClientError::client_cert(path, e))?;
+        builder.use_rustls_tls().identity(identity)
+    } else {
+        builder
+    };
+
+    builder.build().map_err(ClientError::other)
+}
+
+/// Build the HTTP backend. The trust anchor and the client certificate are given with
+/// their paths, which are only used in the errors.
+#[cfg(all(feature = "hyper-client", not(feature = "reqwest-client")))]
+fn build_http_client(
+    pool_idle_timeout: Duration,
+    trust_anchor: Option<(String, Vec<u8>)>,
+    client_cert: Option<(String, Vec<u8>)>,
+) -> Result<HttpClient, ClientError> {
+    let builder = hyper_client_builder(pool_idle_timeout);
+
+    let builder = if let Some((path, pem)) = trust_anchor {
+        builder
+            .add_root_certificate(&pem)
+            .map_err(|e| ClientError::trust_anchor(path, e))?
+    } else {
+        builder
+    };
+
+    let builder = if let Some((path, pem)) = client_cert {
+        builder
+            .identity(&pem)
+            .map_err(|e| ClientError::client_cert(path, e))?
+    } else {
+        builder
+    };
+
+    builder.build().map_err(ClientError::other)
+}
@@ -0,0 +1,419 @@
+//! Accounting of the data consumed by the participant.
+//!
+//! Mobile users on limited data plans may want to cap how much data the participant
+//! consumes. The [`DataUsage`] keeps track of the bytes sent to and received from the
+//! coordinator over a rolling one day window, and the [`MeteredClient`] wraps the HTTP
+//! client used by the participant so that no new request is started once the daily
+//! budget is exhausted.
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use xaynet_sdk::client::{ClientError, XaynetHttpClient};
+
+use crate::participant::{Event, Notifier};
+
+/// Length of the window after which the data usage is reset. The window is measured on
+/// the monotonic clock, so it is not affected by jumps of the device clock.
+const WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug)]
+struct Inner {
+    /// Maximum number of bytes that can be consumed in a window. `None` means that the
+    /// data usage is not limited.
+    budget: Option<u64>,
+    /// Number of bytes consumed in the current window.
+    used: u64,
+    /// Start of the current window.
+    window_start: Instant,
+    /// Whether the exhaustion of the budget has already been notified in the current
+    /// window.
+    notified: bool,
+}
+
+impl Inner {
+    /// Reset the usage if the current window elapsed.
+    fn refresh(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.window_start) >= WINDOW {
+            self.used = 0;
+            self.window_start = now;
+            self.notified = false;
+        }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        matches!(self.budget, Some(budget) if self.used >= budget)
+    }
+
+    fn time_until_reset(&self, now: Instant) -> Duration {
+        WINDOW - now.saturating_duration_since(self.window_start).min(WINDOW)
+    }
+}
+
+/// The data usage as saved in the participant state.
+///
+/// The monotonic clock readings are meaningless in another process, so the window is
+/// saved as the time that elapsed in it, and the time spent while the state was saved
+/// is estimated with the wall clock when it is restored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SavedDataUsage {
+    budget: Option<u64>,
+    used: u64,
+    /// Milliseconds elapsed in the window when the state was saved.
+    window_elapsed: u64,
+    /// Milliseconds since the UNIX epoch on the wall clock when the state was saved.
+    saved_at: u64,
+}
+
+impl SavedDataUsage {
+    /// Forget the clock readings of the saved data usage, so that the states saved at
+    /// different times can be compared.
+    #[cfg(test)]
+    pub(crate) fn without_clock_readings(self) -> Self {
+        Self {
+            window_elapsed: 0,
+            saved_at: 0,
+            ..self
+        }
+    }
+}
+
+/// Read the wall clock, in milliseconds since the UNIX epoch.
+fn wall_clock() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Daily data usage of a participant. Cloning a `DataUsage` is cheap and all the clones
+/// share the same counters.
+#[derive(Debug, Clone)]
+pub struct DataUsage(Arc<Mutex<Inner>>);
+
+impl DataUsage {
+    /// Create a new data usage tracker with the given daily budget, in bytes. If
+    /// `budget` is `None`, the data usage is tracked but not limited.
+    pub fn new(budget: Option<u64>) -> Self {
+        Self(Arc::new(Mutex::new(Inner {
+            budget,
+            used: 0,
+            window_start: Instant::now(),
+            notified: false,
+        })))
+    }
+
+    /// Restore the data usage saved with [`DataUsage::save()`].
+    ///
+    /// If the wall clock moved backwards since the state was saved, no time is assumed to
+    /// have elapsed, so that the usage is not reset early.
+    pub(crate) fn restore(saved: SavedDataUsage) -> Self {
+        let now = Instant::now();
+        let elapsed = Duration::from_millis(
+            saved
+                .window_elapsed
+                .saturating_add(wall_clock().saturating_sub(saved.saved_at)),
+        );
+        let (used, window_start) = match now.checked_sub(elapsed) {
+            Some(window_start) if elapsed < WINDOW => (saved.used, window_start),
+            _ => (0, now),
+        };
+        Self(Arc::new(Mutex::new(Inner {
+            budget: saved.budget,
+            used,
+            window_start,
+            notified: false,
+        })))
+    }
+
+    /// Save the data usage, so that it can be restored in another process.
+    pub(crate) fn save(&self) -> SavedDataUsage {
+        self.with_inner(|inner| SavedDataUsage {
+            budget: inner.budget,
+            used: inner.used,
+            window_elapsed: inner.window_start.elapsed().as_millis() as u64,
+            saved_at: wall_clock(),
+        })
+    }
+
+    fn with_inner<T, F: FnOnce(&mut Inner) -> T>(&self, f: F) -> T {
+        // UNWRAP_SAFE: the lock is never held across a panic
+        let mut inner = self.0.lock().unwrap();
+        inner.refresh(Instant::now());
+        f(&mut inner)
+    }
+
+    /// Set the daily budget, in bytes. If `budget` is `None`, the data usage is not
+    /// limited anymore.
+    pub fn set_budget(&self, budget: Option<u64>) {
+        self.with_inner(|inner| {
+            inner.budget = budget;
+            if !inner.is_exhausted() {
+                inner.notified = false;
+            }
+        })
+    }
+
+    /// Return the daily budget, in bytes.
+    pub fn budget(&self) -> Option<u64> {
+        self.with_inner(|inner| inner.budget)
+    }
+
+    /// Return the number of bytes consumed since the start of the current window.
+    pub fn used(&self) -> u64 {
+        self.with_inner(|inner| inner.used)
+    }
+
+    /// Check whether the daily budget is exhausted.
+    pub fn is_exhausted(&self) -> bool {
+        self.with_inner(|inner| inner.is_exhausted())
+    }
+
+    /// Return the time until the current window elapses and the data usage is reset.
+    pub fn time_until_reset(&self) -> Duration {
+        self.with_inner(|inner| inner.time_until_reset(Instant::now()))
+    }
+
+    /// Record that `bytes` have been consumed.
+    fn record(&self, bytes: usize) {
+        self.with_inner(|inner| inner.used = inner.used.saturating_add(bytes as u64))
+    }
+
+    /// Check whether a new network operation can be started. The first time this fails
+    /// in a window, the error is `true` so that the caller can notify that the budget is
+    /// exhausted.
+    fn check(&self) -> Result<(), bool> {
+        self.with_inner(|inner| {
+            if inner.is_exhausted() {
+                let first = !inner.notified;
+                inner.notified = true;
+                Err(first)
+            } else {
+                Ok(())
+            }
+        })
+    }
+}
+
+/// An HTTP client that records the data it consumes into a [`DataUsage`], and declines
+/// to start new requests once the daily budget is exhausted.
+///
+/// Only the size of the request and response bodies is accounted for.
+#[derive(Clone)]
+pub struct MeteredClient<C> {
+    inner: C,
+    usage: DataUsage,
+    notifier: Option<Arc<Mutex<Notifier>>>,
+}
+
+impl<C> MeteredClient<C> {
+    /// Wrap the given HTTP client.
+    pub fn new(inner: C, usage: DataUsage) -> Self {
+        Self {
+            inner,
+            usage,
+            notifier: None,
+        }
+    }
+
+    /// Set the notifier used to emit [`Event::DataBudgetExceeded`] and
+    /// [`Event::MessageSent`].
+    pub(crate) fn set_notifier(&mut self, notifier: Notifier) {
+        self.notifier = Some(Arc::new(Mutex::new(notifier)));
+    }
+
+    fn notify(&self, event: Event) {
+        if let Some(ref notifier) = self.notifier {
+            // UNWRAP_SAFE: the lock is never held across a panic
+            notifier.lock().unwrap().notify(event);
+        }
+    }
+
+    fn check_budget(&self) -> Result<(), ClientError> {
+        self.usage.check().map_err(|first| {
+            if first {
+                warn!("daily data budget exhausted, declining network operations");
+                self.notify(Event::DataBudgetExceeded);
+            }
+            ClientError::Other("daily data budget exhausted".to_string())
+        })
+    }
+}
+
+#[async_trait]
+impl<C> XaynetHttpClient for MeteredClient<C>
+where
+    C: XaynetHttpClient + Send,
+{
+    type Error = C::Error;
+    type GetResponse = C::GetResponse;
+
+    async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        self.check_budget()?;
+        let resp = self.inner.get(url).await?;
+        if let Some(ref body) = resp {
+            self.usage.record(body.as_ref().len());
+        }
+        Ok(resp)
+    }
+
+    async fn post(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        self.check_budget()?;
+        let bytes = body.len();
+        self.usage.record(bytes);
+        self.inner.post(url, body).await?;
+        self.notify(Event::MessageSent(bytes));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+
+    #[derive(Clone)]
+    struct DummyClient;
+
+    #[async_trait]
+    impl XaynetHttpClient for DummyClient {
+        type Error = ClientError;
+        type GetResponse = Vec<u8>;
+
+        async fn get(&mut self, _url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+            Ok(Some(vec![0; 10]))
+        }
+
+        async fn post(&mut self, _url: &str, _body: Vec<u8>) -> Result<(), ClientError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_unlimited_budget() {
+        let usage = DataUsage::new(None);
+        let mut client = MeteredClient::new(DummyClient, usage.clone());
+        for _ in 0..10 {
+            assert!(block_on(client.get("http://localhost")).is_ok());
+        }
+        assert!(block_on(client.post("http://localhost", vec![0; 5])).is_ok());
+        assert_eq!(usage.used(), 105);
+        assert!(!usage.is_exhausted());
+    }
+
+    #[test]
+    fn test_declined_after_budget_exhausted() {
+        let usage = DataUsage::new(Some(25));
+        let mut client = MeteredClient::new(DummyClient, usage.clone());
+
+        assert!(block_on(client.get("http://localhost")).is_ok());
+        assert!(block_on(client.post("http://localhost", vec![0; 10])).is_ok());
+        assert!(!usage.is_exhausted());
+        // this request overshoots the budget, but it was started before the budget was
+        // exhausted
+        assert!(block_on(client.get("http://localhost")).is_ok());
+        assert_eq!(usage.used(), 30);
+        assert!(usage.is_exhausted());
+
+        assert!(block_on(client.get("http://localhost")).is_err());
+        assert!(block_on(client.post("http://localhost", vec![0; 1])).is_err());
+        assert_eq!(usage.used(), 30);
+
+        // raising the budget allows new operations
+        usage.set_budget(Some(100));
+        assert!(block_on(client.get("http://localhost")).is_ok());
+    }
+
+    #[test]
+    fn test_budget_exceeded_notified_once() {
+        let (mut events, notifier) =
+            crate::participant::Events::new(crate::DEFAULT_MAX_PENDING_EVENTS);
+        let mut client = MeteredClient::new(DummyClient, DataUsage::new(Some(0)));
+        client.set_notifier(notifier);
+
+        assert!(block_on(client.get("http://localhost")).is_err());
+        assert!(block_on(client.get("http://localhost")).is_err());
+        assert!(matches!(events.next(), Some(Event::DataBudgetExceeded)));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_message_sent_notified() {
+        let (mut events, notifier) =
+            crate::participant::Events::new(crate::DEFAULT_MAX_PENDING_EVENTS);
+        let mut client = MeteredClient::new(DummyClient, DataUsage::new(None));
+        client.set_notifier(notifier);
+
+        assert!(block_on(client.get("http://localhost")).is_ok());
+        assert!(block_on(client.post("http://localhost", vec![0; 5])).is_ok());
+        assert!(matches!(events.next(), Some(Event::MessageSent(5))));
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn test_usage_reset_after_window() {
+        let usage = DataUsage::new(Some(10));
+        usage.record(10);
+        assert!(usage.is_exhausted());
+
+        {
+            let mut inner = usage.0.lock().unwrap();
+            let start = inner.window_start;
+            inner.refresh(start + WINDOW);
+        }
+        assert_eq!(usage.used(), 0);
+        assert!(!usage.is_exhausted());
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let usage = DataUsage::new(Some(10));
+        usage.record(10);
+        let saved = usage.save();
+
+        let restored = DataUsage::restore(saved);
+        assert_eq!(restored.budget(), Some(10));
+        assert_eq!(restored.used(), 10);
+        assert!(restored.is_exhausted());
+
+        // the wall clock moved backwards, no time is assumed to have elapsed
+        let restored = DataUsage::restore(SavedDataUsage {
+            saved_at: saved.saved_at + 60 * 60 * 1000,
+            ..saved
+        });
+        assert_eq!(restored.used(), 10);
+
+        // the window elapsed while the state was saved
+        let restored = DataUsage::restore(SavedDataUsage {
+            saved_at: saved.saved_at - WINDOW.as_millis() as u64,
+            ..saved
+        });
+        assert_eq!(restored.budget(), Some(10));
+        assert_eq!(restored.used(), 0);
+        assert!(!restored.is_exhausted());
+    }
+
+    #[test]
+    fn test_window_rollover() {
+        let start = Instant::now();
+        let mut inner = Inner {
+            budget: Some(10),
+            used: 10,
+            window_start: start,
+            notified: true,
+        };
+        inner.refresh(start + WINDOW - Duration::from_secs(1));
+        assert!(inner.is_exhausted());
+        assert_eq!(
+            inner.time_until_reset(start + WINDOW - Duration::from_secs(1)),
+            Duration::from_secs(1)
+        );
+        inner.refresh(start + WINDOW);
+        assert!(!inner.is_exhausted());
+        assert!(!inner.notified);
+    }
+}
@@ -1,5 +1,4 @@
-use crate::ffi::{ERR_NULLPTR, OK};
-use std::os::raw::c_int;
+use crate::ffi::{fail_nullptr, ReturnCode};
 use xaynet_core::mask::DataType;
 
 mod pv {
@@ -11,8 +10,8 @@ mod pv {
 ///
 /// # Return value
 ///
-/// - [`OK`] on success
-/// - [`ERR_NULLPTR`] if `local_model_config` is NULL
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `local_model_config` is NULL
 ///
 /// # Safety
 ///
@@ -32,12 +31,15 @@ mod pv {
 #[no_mangle]
 pub unsafe extern "C" fn xaynet_ffi_local_model_config_destroy(
     local_model_config: *mut LocalModelConfig,
-) -> c_int {
+) -> ReturnCode {
     if local_model_config.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr(
+            "xaynet_ffi_local_model_config_destroy",
+            "`local_model_config`",
+        );
     }
     pv::_xaynet_ffi_local_model_config_destroy(local_model_config);
-    OK
+    ReturnCode::Ok
 }
 
 #[repr(C)]
@@ -62,6 +64,7 @@ impl From<xaynet_sdk::LocalModelConfig> for LocalModelConfig {
 
 #[repr(u8)]
 /// The original primitive data type of the numerical values to be masked.
+/// cbindgen:prefix-with-name
 pub enum ModelDataType {
     /// Numbers of type f32.
     F32 = 0,
@@ -71,6 +74,12 @@ pub enum ModelDataType {
     I32 = 2,
     /// Numbers of type i64.
     I64 = 3,
+    /// Numbers of type u8.
+    U8 = 4,
+    /// Numbers of type i16.
+    I16 = 5,
+    /// Numbers of type f16, passed as the IEEE 754 binary16 bits in an `uint16_t`.
+    F16 = 6,
 }
 
 impl From<DataType> for ModelDataType {
@@ -80,6 +89,9 @@ impl From<DataType> for ModelDataType {
             DataType::F64 => ModelDataType::F64,
             DataType::I32 => ModelDataType::I32,
             DataType::I64 => ModelDataType::I64,
+            DataType::U8 => ModelDataType::U8,
+            DataType::I16 => ModelDataType::I16,
+            DataType::F16 => ModelDataType::F16,
         }
     }
 }
@@ -0,0 +1,178 @@
+use std::{
+    cell::RefCell,
+    ffi::CString,
+    fmt::Display,
+    os::raw::{c_char, c_int, c_uint},
+    slice,
+};
+
+use super::ReturnCode;
+
+thread_local! {
+    /// The message of the last error of an FFI function on the current thread.
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::default();
+}
+
+/// Record the error of the failing FFI function `function`. The message is returned by
+/// [`xaynet_ffi_last_error_message()`] until another FFI function fails on the current
+/// thread.
+pub(crate) fn set_last_error(function: &str, error: impl Display) {
+    let message = format!("{} failed: {}", function, error);
+    // UNWRAP_SAFE: the interior NUL bytes have been removed
+    let message = CString::new(message.replace('\0', "")).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Record the error of the failing FFI function `function` and return the given error
+/// code.
+pub(crate) fn fail(code: ReturnCode, function: &str, error: impl Display) -> ReturnCode {
+    set_last_error(function, error);
+    code
+}
+
+/// Record that the FFI function `function` failed because of the NULL pointer
+/// arguments `arguments`, and return [`ReturnCode::ErrNullptr`].
+pub(crate) fn fail_nullptr(function: &str, arguments: &str) -> ReturnCode {
+    fail(
+        ReturnCode::ErrNullptr,
+        function,
+        format_args!("{} is NULL", arguments),
+    )
+}
+
+/// Get the length in bytes of the message of the last error of an FFI function on the
+/// current thread, including the terminating NUL byte.
+///
+/// # Return value
+///
+/// - the length of the message
+/// - `0` if no FFI function failed on the current thread
+///
+/// # Safety
+///
+/// This function is safe to call
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_last_error_length() -> c_int {
+    LAST_ERROR.with(|last_error| match *last_error.borrow() {
+        Some(ref message) => message.as_bytes_with_nul().len() as c_int,
+        None => 0,
+    })
+}
+
+/// Copy the message of the last error of an FFI function on the current thread into
+/// `buffer`, as a NUL-terminated string. The message names the function that failed
+/// and the reason of the failure, for instance:
+///
+/// ```text
+/// xaynet_ffi_participant_new failed: invalid participant settings MissingKeys
+/// ```
+///
+/// The length of `buffer` must be at least [`xaynet_ffi_last_error_length()`]. The
+/// message is kept until another FFI function fails on the current thread, this
+/// function and [`xaynet_ffi_last_error_length()`] never fail with a message.
+///
+/// # Return value
+///
+/// - `OK` if the message is copied into `buffer`
+/// - `ERR_NULLPTR` if `buffer` is NULL
+/// - `LAST_ERROR_NONE` if no FFI function failed on the current thread
+/// - `ERR_LAST_ERROR_LEN` if `buffer` is too small for the message
+///
+/// # Safety
+///
+/// `buffer` must be valid for writes of `len` bytes.
+///
+/// # Example
+///
+/// ```c
+/// int len = xaynet_ffi_last_error_length();
+/// if (len > 0) {
+///   char *message = malloc(len);
+///   if (xaynet_ffi_last_error_message(message, len) == OK) {
+///     fprintf(stderr, "%s\n", message);
+///   }
+///   free(message);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_last_error_message(
+    buffer: *mut c_char,
+    len: c_uint,
+) -> ReturnCode {
+    if buffer.is_null() {
+        return ReturnCode::ErrNullptr;
+    }
+    LAST_ERROR.with(|last_error| match *last_error.borrow() {
+        Some(ref message) => {
+            let message = message.as_bytes_with_nul();
+            if (len as usize) < message.len() {
+                return ReturnCode::ErrLastErrorLen;
+            }
+            let buffer = unsafe { slice::from_raw_parts_mut(buffer as *mut u8, message.len()) };
+            buffer.copy_from_slice(message);
+            ReturnCode::Ok
+        }
+        None => ReturnCode::LastErrorNone,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CStr, thread};
+
+    use super::*;
+
+    fn last_error() -> Option<String> {
+        let len = unsafe { xaynet_ffi_last_error_length() };
+        if len == 0 {
+            return None;
+        }
+        let mut buffer = vec![0 as c_char; len as usize];
+        let err = unsafe { xaynet_ffi_last_error_message(buffer.as_mut_ptr(), len as c_uint) };
+        assert_eq!(err, ReturnCode::Ok);
+        let message = unsafe { CStr::from_ptr(buffer.as_ptr()) };
+        Some(message.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn test_last_error() {
+        assert!(last_error().is_none());
+        let mut buffer = [0 as c_char; 8];
+        let err = unsafe { xaynet_ffi_last_error_message(buffer.as_mut_ptr(), 8) };
+        assert_eq!(err, ReturnCode::LastErrorNone);
+
+        assert_eq!(
+            fail_nullptr("xaynet_ffi_foo", "`bar`"),
+            ReturnCode::ErrNullptr
+        );
+        assert_eq!(
+            last_error().unwrap(),
+            "xaynet_ffi_foo failed: `bar` is NULL"
+        );
+
+        // the buffer is too small for the message, or missing
+        let err = unsafe { xaynet_ffi_last_error_message(buffer.as_mut_ptr(), 8) };
+        assert_eq!(err, ReturnCode::ErrLastErrorLen);
+        let err = unsafe { xaynet_ffi_last_error_message(std::ptr::null_mut(), 64) };
+        assert_eq!(err, ReturnCode::ErrNullptr);
+
+        // querying the message doesn't overwrite it
+        assert_eq!(
+            last_error().unwrap(),
+            "xaynet_ffi_foo failed: `bar` is NULL"
+        );
+
+        // interior NUL bytes are removed
+        set_last_error("xaynet_ffi_foo", "a\0b");
+        assert_eq!(last_error().unwrap(), "xaynet_ffi_foo failed: ab");
+    }
+
+    #[test]
+    fn test_last_error_is_thread_local() {
+        set_last_error("xaynet_ffi_foo", "error");
+        thread::spawn(|| assert!(last_error().is_none()))
+            .join()
+            .unwrap();
+        assert_eq!(last_error().unwrap(), "xaynet_ffi_foo failed: error");
+    }
+}
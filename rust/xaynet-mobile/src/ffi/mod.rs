@@ -1,16 +1,31 @@
 #![allow(unused_unsafe)]
+//! C bindings for the mobile [`Participant`](crate::Participant).
+//!
+//! # Return codes
+//!
+//! The functions that can fail return a [`ReturnCode`], which the C header exposes as an
+//! enum with named variants: `OK` on success, an `ERR_*` code on failure and a `*_NONE`
+//! code when there is nothing to return. The functions that return a value instead
+//! return the negated code on failure. The numeric values of the codes are stable: a
+//! code is never renumbered nor reused, and new codes are appended. The message of the
+//! last failure is given by [`xaynet_ffi_last_error_message()`].
 
 mod participant;
 pub use participant::*;
 
+mod shared;
+pub use shared::*;
+
 mod settings;
 pub use settings::*;
 
 mod config;
 pub use config::*;
 
+mod error;
+pub use error::*;
+
 pub use ffi_support::{ByteBuffer, FfiStr};
-use std::os::raw::c_int;
 
 /// Destroy the given `ByteBuffer` and free its memory. This function must only be
 /// called on `ByteBuffer`s that have been created on the Rust side of the FFI. If you
@@ -19,8 +34,8 @@ use std::os::raw::c_int;
 ///
 /// # Return value
 ///
-/// - [`OK`] on success
-/// - [`ERR_NULLPTR`] if `buf` is NULL
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `buf` is NULL
 ///
 /// # Safety
 ///
@@ -46,12 +61,12 @@ pub unsafe extern "C" fn xaynet_ffi_byte_buffer_destroy(
     // here is no big deal since the pointer becomes invalid afterward
     // anyway.
     buf: *const ByteBuffer,
-) -> c_int {
+) -> ReturnCode {
     if buf.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr("xaynet_ffi_byte_buffer_destroy", "`buf`");
     }
     Box::from_raw(buf as *mut ByteBuffer).destroy();
-    OK
+    ReturnCode::Ok
 }
 
 /// Initialize the crypto library. This method must be called before instantiating a
@@ -60,50 +75,145 @@ pub unsafe extern "C" fn xaynet_ffi_byte_buffer_destroy(
 ///
 /// # Return value
 ///
-/// - [`OK`] if the initialization succeeded
-/// - -[`ERR_CRYPTO_INIT`] if the initialization failed
+/// - `OK` if the initialization succeeded
+/// - -`ERR_CRYPTO_INIT` if the initialization failed
 ///
 /// # Safety
 ///
 /// This function is safe to call
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_crypto_init() -> c_int {
+pub unsafe extern "C" fn xaynet_ffi_crypto_init() -> ReturnCode {
     if sodiumoxide::init().is_err() {
-        ERR_CRYPTO_INIT
+        fail(
+            ReturnCode::ErrCryptoInit,
+            "xaynet_ffi_crypto_init",
+            "failed to initialize sodiumoxide",
+        )
     } else {
-        OK
+        ReturnCode::Ok
     }
 }
 
-/// Return value upon success
-pub const OK: c_int = 0;
-/// NULL pointer argument
-pub const ERR_NULLPTR: c_int = 1;
-/// Invalid coordinator URL
-pub const ERR_INVALID_URL: c_int = 2;
-/// Invalid settings: coordinator URL is not set
-pub const ERR_SETTINGS_URL: c_int = 3;
-/// Invalid settings: signing keys are not set
-pub const ERR_SETTINGS_KEYS: c_int = 4;
-/// Invalid settings: scalar is out of bounds
-pub const ERR_SETTINGS_SCALAR: c_int = 5;
-/// Failed to set the local model: invalid model
-pub const ERR_SETMODEL_MODEL: c_int = 6;
-/// Failed to set the local model: invalid data type
-pub const ERR_SETMODEL_DATATYPE: c_int = 7;
-/// Failed to initialized the crypto library
-pub const ERR_CRYPTO_INIT: c_int = 8;
-/// Invalid secret signing key
-pub const ERR_CRYPTO_SECRET_KEY: c_int = 9;
-/// Invalid public signing key
-pub const ERR_CRYPTO_PUBLIC_KEY: c_int = 10;
-/// No global model is currently available
-pub const GLOBALMODEL_NONE: c_int = 11;
-/// Failed to get the global model: communication with the coordinator failed
-pub const ERR_GLOBALMODEL_IO: c_int = 12;
-/// Failed to get the global model: invalid data type
-pub const ERR_GLOBALMODEL_DATATYPE: c_int = 13;
-/// Failed to get the global model: invalid buffer length
-pub const ERR_GLOBALMODEL_LEN: c_int = 14;
-/// Failed to get the global model: invalid model
-pub const ERR_GLOBALMODEL_CONVERT: c_int = 15;
+/// The return codes of the C API, see the module documentation. The C header names the
+/// variants in screaming snake case, e.g. `ERR_NULLPTR` for [`ReturnCode::ErrNullptr`].
+///
+/// The discriminants are part of the C API: they must never change, and a new code gets
+/// a new variant with the next value.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReturnCode {
+    /// Return value upon success
+    Ok = 0,
+    /// NULL pointer argument
+    ErrNullptr = 1,
+    /// Invalid coordinator URL
+    ErrInvalidUrl = 2,
+    /// Invalid settings: coordinator URL is not set
+    ErrSettingsUrl = 3,
+    /// Invalid settings: signing keys are not set
+    ErrSettingsKeys = 4,
+    /// Invalid settings: scalar is out of bounds
+    ErrSettingsScalar = 5,
+    /// Failed to set the local model: invalid model
+    ErrSetmodelModel = 6,
+    /// Failed to set the local model: invalid data type
+    ErrSetmodelDatatype = 7,
+    /// Failed to initialized the crypto library
+    ErrCryptoInit = 8,
+    /// Invalid secret signing key
+    ErrCryptoSecretKey = 9,
+    /// Invalid public signing key
+    ErrCryptoPublicKey = 10,
+    /// No global model is currently available
+    GlobalmodelNone = 11,
+    /// Failed to get the global model: communication with the coordinator failed
+    ErrGlobalmodelIo = 12,
+    /// Failed to get the global model: invalid data type
+    ErrGlobalmodelDatatype = 13,
+    /// Failed to get the global model: invalid buffer length
+    ErrGlobalmodelLen = 14,
+    /// Failed to get the global model: invalid model
+    ErrGlobalmodelConvert = 15,
+    /// Invalid participant state: the state is truncated or altered
+    ErrStateCorrupt = 16,
+    /// Invalid participant state: the state can't be deserialized
+    ErrStateDeserialize = 17,
+    /// The participant doesn't await the confirmation of a global mask
+    Sum2MaskNone = 18,
+    /// Failed to get the global mask: the buffer is too small
+    ErrSum2MaskLen = 19,
+    /// The participant doesn't await the consent of the user to take part in a task
+    ConsentNone = 20,
+    /// No FFI function failed on the current thread
+    LastErrorNone = 21,
+    /// Failed to get the last error message: the buffer is too small
+    ErrLastErrorLen = 22,
+    /// Invalid participant state: the state was saved by a newer version of the library
+    ErrStateVersion = 23,
+    /// Failed to set the local model: a weight index is out of range or duplicated
+    ErrSetmodelIndices = 24,
+    /// The local model is set, but it has a different length than the models that were set
+    /// before: the cached global model and the local model that was not sent are discarded
+    ModelShapeChanged = 25,
+    /// Failed to set the local model: the coordinator expects another model length
+    ErrSetmodelLength = 26,
+    /// Failed to get a round of the participant history: the index is out of range
+    ErrHistoryIndex = 27,
+    /// Failed to get the public statistics: the communication with the coordinator failed
+    ErrPublicStatsIo = 28,
+    /// Failed to update the TLS certificates: the certificate or the private key is invalid
+    ErrTlsCertificate = 29,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_int;
+
+    use super::*;
+
+    #[test]
+    fn test_return_codes_are_stable() {
+        let codes = [
+            (ReturnCode::Ok, 0),
+            (ReturnCode::ErrNullptr, 1),
+            (ReturnCode::ErrInvalidUrl, 2),
+            (ReturnCode::ErrSettingsUrl, 3),
+            (ReturnCode::ErrSettingsKeys, 4),
+            (ReturnCode::ErrSettingsScalar, 5),
+            (ReturnCode::ErrSetmodelModel, 6),
+            (ReturnCode::ErrSetmodelDatatype, 7),
+            (ReturnCode::ErrCryptoInit, 8),
+            (ReturnCode::ErrCryptoSecretKey, 9),
+            (ReturnCode::ErrCryptoPublicKey, 10),
+            (ReturnCode::GlobalmodelNone, 11),
+            (ReturnCode::ErrGlobalmodelIo, 12),
+            (ReturnCode::ErrGlobalmodelDatatype, 13),
+            (ReturnCode::ErrGlobalmodelLen, 14),
+            (ReturnCode::ErrGlobalmodelConvert, 15),
+            (ReturnCode::ErrStateCorrupt, 16),
+            (ReturnCode::ErrStateDeserialize, 17),
+            (ReturnCode::Sum2MaskNone, 18),
+            (ReturnCode::ErrSum2MaskLen, 19),
+            (ReturnCode::ConsentNone, 20),
+            (ReturnCode::LastErrorNone, 21),
+            (ReturnCode::ErrLastErrorLen, 22),
+            (ReturnCode::ErrStateVersion, 23),
+            (ReturnCode::ErrSetmodelIndices, 24),
+            (ReturnCode::ModelShapeChanged, 25),
+            (ReturnCode::ErrSetmodelLength, 26),
+            (ReturnCode::ErrHistoryIndex, 27),
+            (ReturnCode::ErrPublicStatsIo, 28),
+            (ReturnCode::ErrTlsCertificate, 29),
+        ];
+        for (code, pinned) in codes {
+            assert_eq!(code as c_int, pinned, "{:?} was renumbered", code);
+        }
+    }
+
+    #[test]
+    fn test_public_key_length() {
+        use xaynet_core::{crypto::ByteObject, ParticipantPublicKey};
+
+        assert_eq!(PUBLIC_KEY_LENGTH as usize, ParticipantPublicKey::LENGTH);
+    }
+}
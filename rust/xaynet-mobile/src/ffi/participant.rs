@@ -3,24 +3,34 @@ use std::{
     os::raw::{c_int, c_uchar, c_uint, c_void},
     ptr,
     slice,
+    time::Duration,
 };
 
 use ffi_support::{ByteBuffer, FfiStr};
-use xaynet_core::mask::{DataType, FromPrimitives, IntoPrimitives, Model};
-
-use super::{
-    LocalModelConfig,
-    ERR_GLOBALMODEL_CONVERT,
-    ERR_GLOBALMODEL_DATATYPE,
-    ERR_GLOBALMODEL_IO,
-    ERR_GLOBALMODEL_LEN,
-    ERR_NULLPTR,
-    ERR_SETMODEL_DATATYPE,
-    ERR_SETMODEL_MODEL,
-    GLOBALMODEL_NONE,
-    OK,
+use half::f16;
+use xaynet_core::{
+    crypto::ByteObject,
+    mask::{DataType, FromPrimitives, IntoPrimitives, Model},
+};
+use xaynet_sdk::{CircuitState, ConsentTask};
+
+use super::{fail, fail_nullptr, set_last_error, LocalModelConfig, ReturnCode};
+use crate::{
+    into_primitives,
+    migrate_state,
+    participant::Progress,
+    InitError,
+    MigrateError,
+    Participant,
+    ProgressObserver,
+    Settings,
+    SparseModelError,
+    StateChange,
+    StateObserver,
+    Task,
+    WakeupReason,
+    WorkClass,
 };
-use crate::{into_primitives, Participant, Settings, Task};
 
 mod pv {
     use super::Participant;
@@ -32,8 +42,8 @@ mod pv {
 ///
 /// # Return value
 ///
-/// - [`OK`] on success
-/// - [`ERR_NULLPTR`] if `participant` is NULL
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` is NULL
 ///
 /// # Safety
 ///
@@ -50,12 +60,14 @@ mod pv {
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_participant_destroy(participant: *mut Participant) -> c_int {
+pub unsafe extern "C" fn xaynet_ffi_participant_destroy(
+    participant: *mut Participant,
+) -> ReturnCode {
     if participant.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr("xaynet_ffi_participant_destroy", "`participant`");
     }
     pv::_xaynet_ffi_participant_destroy(participant);
-    OK
+    ReturnCode::Ok
 }
 
 /// The participant is not taking part in the sum or update task
@@ -70,15 +82,240 @@ pub const PARTICIPANT_SHOULD_SET_MODEL: c_int = 1 << 3;
 pub const PARTICIPANT_MADE_PROGRESS: c_int = 1 << 4;
 /// A new global model is available
 pub const PARTICIPANT_NEW_GLOBALMODEL: c_int = 1 << 5;
+/// The participant daily data budget is exhausted
+pub const PARTICIPANT_DATA_BUDGET_EXCEEDED: c_int = 1 << 6;
+/// The coordinator repeatedly failed and the participant stopped sending requests to it
+pub const PARTICIPANT_CIRCUIT_OPEN: c_int = 1 << 7;
+/// The participant awaits the confirmation of the global mask it aggregated
+pub const PARTICIPANT_AWAITING_SUM2_CONFIRMATION: c_int = 1 << 8;
+/// The participant has been selected for a task and awaits the consent of the user
+pub const PARTICIPANT_AWAITING_CONSENT: c_int = 1 << 9;
+
+/// The participant is not taking part in the sum or update task, see
+/// [`xaynet_ffi_participant_task()`]
+pub const TASK_NONE: c_int = 0;
+/// The participant is taking part in the sum task, see [`xaynet_ffi_participant_task()`]
+pub const TASK_SUM: c_int = 1;
+/// The participant is taking part in the update task, see
+/// [`xaynet_ffi_participant_task()`]
+pub const TASK_UPDATE: c_int = 2;
+
+/// The participant state changed because the participant made progress
+pub const STATE_CHANGE_PROGRESS: c_int = 1;
+/// The participant state changed because the circuit breaker opened, became half-open
+/// or closed
+pub const STATE_CHANGE_CIRCUIT_BREAKER: c_int = 2;
+
+/// The participant entered the phase of a task, see
+/// [`xaynet_ffi_participant_set_progress_callback()`]
+pub const PROGRESS_PHASE_ENTERED: c_int = 1;
+/// The participant sent a message to the coordinator, see
+/// [`xaynet_ffi_participant_set_progress_callback()`]
+pub const PROGRESS_MESSAGE_SENT: c_int = 2;
+
+/// The participant polls the coordinator or waits for a decision of the user, see
+/// [`WakeupRecommendation`]
+pub const WORK_POLL: c_int = 0;
+/// The participant downloads data from the coordinator, see [`WakeupRecommendation`]
+pub const WORK_DOWNLOAD: c_int = 1;
+/// The participant waits for the model to train, see [`WakeupRecommendation`]
+pub const WORK_TRAIN: c_int = 2;
+/// The participant uploads a message to the coordinator, see [`WakeupRecommendation`]
+pub const WORK_UPLOAD: c_int = 3;
+/// The participant masks its model or aggregates the masks, see [`WakeupRecommendation`]
+pub const WORK_COMPUTE: c_int = 4;
+/// The participant doesn't request the coordinator, see
+/// [`xaynet_ffi_participant_pending_work()`]
+pub const WORK_NONE: c_int = 5;
+
+/// The participant waits to be selected for a task, see [`WakeupRecommendation`]
+pub const WAKEUP_IDLE: c_int = 0;
+/// The participant is taking part in a task, see [`WakeupRecommendation`]
+pub const WAKEUP_BUSY: c_int = 1;
+/// The participant waits for the model it is expected to train, see
+/// [`WakeupRecommendation`]
+pub const WAKEUP_AWAITING_MODEL: c_int = 2;
+/// The participant waits for a decision of the user, see [`WakeupRecommendation`]
+pub const WAKEUP_AWAITING_USER: c_int = 3;
+/// The participant backs off from a failing coordinator, see [`WakeupRecommendation`]
+pub const WAKEUP_CIRCUIT_OPEN: c_int = 4;
+/// The participant daily data budget is exhausted, see [`WakeupRecommendation`]
+pub const WAKEUP_DATA_BUDGET_EXCEEDED: c_int = 5;
+
+/// The length in bytes of the public signing key of a participant, see
+/// [`xaynet_ffi_participant_public_key()`]
+pub const PUBLIC_KEY_LENGTH: c_uint = 32;
+
+#[repr(C)]
+/// A recommendation of when the participant should be ticked next, see
+/// [`xaynet_ffi_participant_next_wakeup()`].
+pub struct WakeupRecommendation {
+    /// Delay in milliseconds before which ticking the participant is not expected to make
+    /// progress.
+    pub earliest_ms: u64,
+    /// Delay in milliseconds after which the participant risks missing its task. It is
+    /// never shorter than `earliest_ms`.
+    pub latest_ms: u64,
+    /// The kind of work the participant performs once woken up, one of [`WORK_POLL`],
+    /// [`WORK_DOWNLOAD`], [`WORK_TRAIN`], [`WORK_COMPUTE`] and [`WORK_UPLOAD`].
+    pub expected_work: c_int,
+    /// Why the participant should be woken up, one of the `WAKEUP_*` constants.
+    pub reason: c_int,
+}
+
+/// Get the `WORK_*` constant of a kind of work.
+fn work_class(work: WorkClass) -> c_int {
+    match work {
+        WorkClass::Poll => WORK_POLL,
+        WorkClass::Download => WORK_DOWNLOAD,
+        WorkClass::Train => WORK_TRAIN,
+        WorkClass::Upload => WORK_UPLOAD,
+        WorkClass::Compute => WORK_COMPUTE,
+    }
+}
+
+impl From<crate::WakeupRecommendation> for WakeupRecommendation {
+    fn from(recommendation: crate::WakeupRecommendation) -> Self {
+        let millis = |duration: Duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+        WakeupRecommendation {
+            earliest_ms: millis(recommendation.earliest),
+            latest_ms: millis(recommendation.latest),
+            expected_work: work_class(recommendation.expected_work),
+            reason: match recommendation.reason {
+                WakeupReason::Idle => WAKEUP_IDLE,
+                WakeupReason::Busy => WAKEUP_BUSY,
+                WakeupReason::AwaitingModel => WAKEUP_AWAITING_MODEL,
+                WakeupReason::AwaitingUser => WAKEUP_AWAITING_USER,
+                WakeupReason::CircuitOpen => WAKEUP_CIRCUIT_OPEN,
+                WakeupReason::DataBudgetExceeded => WAKEUP_DATA_BUDGET_EXCEEDED,
+            },
+        }
+    }
+}
+
+#[repr(C)]
+/// A round the participant observed and completed, see
+/// [`xaynet_ffi_participant_history_entry()`].
+pub struct RoundRecord {
+    /// The SHA-256 hash of the round seed, which identifies the round.
+    pub round_seed_hash: [u8; 32],
+    /// The task the participant was selected for in the round, one of [`TASK_NONE`],
+    /// [`TASK_SUM`] and [`TASK_UPDATE`].
+    pub task: c_int,
+    /// Whether the participant successfully sent a message to the coordinator in the
+    /// round.
+    pub message_sent: bool,
+}
+
+impl From<crate::RoundRecord> for RoundRecord {
+    fn from(record: crate::RoundRecord) -> Self {
+        RoundRecord {
+            round_seed_hash: record.round_seed_hash,
+            task: match record.task {
+                Task::None => TASK_NONE,
+                Task::Sum => TASK_SUM,
+                Task::Update => TASK_UPDATE,
+            },
+            message_sent: record.message_sent,
+        }
+    }
+}
+
+#[repr(C)]
+/// The statistics about the training which are safe to show to the user of the app, see
+/// [`xaynet_ffi_participant_public_stats()`].
+pub struct PublicStats {
+    /// The id of the current round.
+    pub round_id: u64,
+    /// The number of rounds that completed with a new global model.
+    pub completed_rounds: u64,
+    /// The approximate number of participants that contributed to the latest global
+    /// model, or `0` if too few participants contributed to publish it.
+    pub participants: u64,
+    /// The time at which the latest global model was published (in seconds since the UNIX
+    /// epoch), or `0` if no global model was published yet.
+    pub last_model_update: u64,
+}
+
+impl From<xaynet_core::common::PublicStats> for PublicStats {
+    fn from(stats: xaynet_core::common::PublicStats) -> Self {
+        PublicStats {
+            round_id: stats.round_id,
+            completed_rounds: stats.completed_rounds,
+            participants: stats.participants.unwrap_or(0),
+            last_model_update: stats.last_model_update.unwrap_or(0),
+        }
+    }
+}
+
+/// A callback invoked when the participant state changed, with the user data it was
+/// registered with and the reason of the change (see
+/// [`xaynet_ffi_participant_set_state_changed_callback()`]).
+type StateChangedCallback = unsafe extern "C" fn(user_data: *mut c_void, change: c_int);
+
+/// A [`StateObserver`] that invokes a callback from the other side of the FFI.
+struct CallbackObserver {
+    callback: StateChangedCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: a participant is used by one thread at a time. The user data of the callbacks
+// of a shared participant must be safe to use from the threads that tick it, which is
+// documented by `SharedParticipant`.
+unsafe impl Send for CallbackObserver {}
+
+impl StateObserver for CallbackObserver {
+    fn state_changed(&mut self, change: StateChange) {
+        let change = match change {
+            StateChange::Progress => STATE_CHANGE_PROGRESS,
+            StateChange::CircuitBreaker => STATE_CHANGE_CIRCUIT_BREAKER,
+        };
+        unsafe { (self.callback)(self.user_data, change) }
+    }
+}
+
+/// A callback invoked when the participant made progress through the PET protocol, with
+/// the user data it was registered with, the kind of progress and its value (see
+/// [`xaynet_ffi_participant_set_progress_callback()`]).
+type ProgressCallback = unsafe extern "C" fn(user_data: *mut c_void, progress: c_int, value: u64);
+
+/// A [`ProgressObserver`] that invokes a callback from the other side of the FFI.
+struct CallbackProgressObserver {
+    callback: ProgressCallback,
+    user_data: *mut c_void,
+}
+
+// SAFETY: a participant is used by one thread at a time. The user data of the callbacks
+// of a shared participant must be safe to use from the threads that tick it, which is
+// documented by `SharedParticipant`.
+unsafe impl Send for CallbackProgressObserver {}
+
+impl ProgressObserver for CallbackProgressObserver {
+    fn on_phase_entered(&mut self, task: Task) {
+        let task = match task {
+            Task::None => TASK_NONE,
+            Task::Sum => TASK_SUM,
+            Task::Update => TASK_UPDATE,
+        };
+        unsafe { (self.callback)(self.user_data, PROGRESS_PHASE_ENTERED, task as u64) }
+    }
+
+    fn on_message_sent(&mut self, bytes: usize) {
+        unsafe { (self.callback)(self.user_data, PROGRESS_MESSAGE_SENT, bytes as u64) }
+    }
+}
 
 /// Instantiate a new participant with the given settings. The participant must be
 /// destroyed with [`xaynet_ffi_participant_destroy`].
 ///
 /// # Return value
 ///
-/// - a NULL pointer if `settings` is NULL or if the participant creation failed
+/// - a NULL pointer if `settings` is NULL or if the participant creation failed, the
+///   reason is given by [`xaynet_ffi_last_error_message()`]
 /// - a valid pointer to a [`Participant`] otherwise
 ///
+/// [`xaynet_ffi_last_error_message()`]: crate::ffi::xaynet_ffi_last_error_message
+///
 /// # Safety
 ///
 /// When calling this method, you have to ensure that *either* the pointer is NULL *or*
@@ -97,12 +334,18 @@ pub const PARTICIPANT_NEW_GLOBALMODEL: c_int = 1 << 5;
 pub unsafe extern "C" fn xaynet_ffi_participant_new(settings: *const Settings) -> *mut Participant {
     let settings = match unsafe { settings.as_ref() } {
         Some(settings) => settings.clone(),
-        None => return std::ptr::null_mut(),
+        None => {
+            set_last_error("xaynet_ffi_participant_new", "`settings` is NULL");
+            return ptr::null_mut();
+        }
     };
 
     match Participant::new(settings) {
         Ok(participant) => Box::into_raw(Box::new(participant)),
-        Err(_) => std::ptr::null_mut(),
+        Err(err) => {
+            set_last_error("xaynet_ffi_participant_new", err);
+            ptr::null_mut()
+        }
     }
 }
 
@@ -111,7 +354,7 @@ pub unsafe extern "C" fn xaynet_ffi_participant_new(settings: *const Settings) -
 ///
 /// # Return value
 ///
-/// - [`ERR_NULLPTR`] is `participant` is NULL
+/// - `ERR_NULLPTR` is `participant` is NULL
 /// - a bitflag otherwise, with the following flags:
 ///   - [`PARTICIPANT_MADE_PROGRESS`]: if set, this flag indicates that the participant
 ///     internal state machine was able to make some progress, and that the participant
@@ -135,6 +378,33 @@ pub unsafe extern "C" fn xaynet_ffi_participant_new(settings: *const Settings) -
 ///     model, by calling [`xaynet_ffi_participant_set_model()`]
 ///   - [`PARTICIPANT_NEW_GLOBALMODEL`]: if set, the participant can fetch the new global
 ///     model, by calling [`xaynet_ffi_participant_global_model()`]
+///   - [`PARTICIPANT_DATA_BUDGET_EXCEEDED`]: if set, the participant daily data budget
+///     is exhausted and the participant declines to start new network operations until
+///     the next day (see [`xaynet_ffi_participant_data_usage()`])
+///   - [`PARTICIPANT_CIRCUIT_OPEN`]: if set, the coordinator repeatedly failed with
+///     server errors and the participant backs off: it doesn't send any request to the
+///     coordinator until the backoff elapsed
+///   - [`PARTICIPANT_AWAITING_SUM2_CONFIRMATION`]: if set, the participant aggregated the
+///     global mask and doesn't send it to the coordinator until it is confirmed with
+///     [`xaynet_ffi_participant_confirm_sum2()`] (see
+///     [`xaynet_ffi_settings_set_confirm_sum2()`])
+///   - [`PARTICIPANT_AWAITING_CONSENT`]: if set, the participant has been selected for a
+///     task and doesn't take part in it until the user consents with
+///     [`xaynet_ffi_participant_grant_consent()`] (see
+///     [`xaynet_ffi_participant_consent_request()`] and
+///     [`xaynet_ffi_settings_set_require_consent()`])
+///
+/// If the participant made progress through the PET protocol, the callback registered
+/// with [`xaynet_ffi_participant_set_progress_callback()`] is invoked before this
+/// function returns. Then, if the participant state changed, the callback registered
+/// with [`xaynet_ffi_participant_set_state_changed_callback()`] is invoked.
+///
+/// The events emitted by the participant internal state machine are processed before
+/// this function returns, so they don't accumulate between ticks. If more events than
+/// the maximum set with [`xaynet_ffi_settings_set_max_pending_events()`] are emitted in
+/// a single tick, the oldest informational ones are dropped (see
+/// [`xaynet_ffi_participant_dropped_events()`]). The events that report the progress
+/// through the tasks or request an action are never dropped.
 ///
 /// # Safety
 ///
@@ -150,15 +420,74 @@ pub unsafe extern "C" fn xaynet_ffi_participant_new(settings: *const Settings) -
 ///
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_settings_set_confirm_sum2()`]: crate::ffi::xaynet_ffi_settings_set_confirm_sum2
+/// [`xaynet_ffi_settings_set_require_consent()`]: crate::ffi::xaynet_ffi_settings_set_require_consent
+/// [`xaynet_ffi_settings_set_max_pending_events()`]: crate::ffi::xaynet_ffi_settings_set_max_pending_events
 #[no_mangle]
 pub unsafe extern "C" fn xaynet_ffi_participant_tick(participant: *mut Participant) -> c_int {
-    let participant = match unsafe { participant.as_mut() } {
-        Some(participant) => participant,
-        None => return ERR_NULLPTR,
+    let (flags, mut notifications) = match unsafe { participant.as_mut() } {
+        Some(participant) => advance(participant),
+        None => return fail_nullptr("xaynet_ffi_participant_tick", "`participant`") as c_int,
     };
 
-    participant.tick();
+    // No reference to the participant is held while the callbacks are invoked, so that
+    // the callbacks can use the participant.
+    notifications.notify();
+    if let Some(participant) = unsafe { participant.as_mut() } {
+        notifications.put_back(participant);
+    }
+    flags
+}
+
+/// The notifications of the observers of a participant that are pending at the end of
+/// a tick. The observers are taken out of the participant, so that they can be
+/// notified while the participant is used.
+#[allow(clippy::type_complexity)]
+pub(crate) struct Notifications {
+    progress: Option<(Box<dyn ProgressObserver>, Vec<Progress>, u64)>,
+    state_changes: Option<(Box<dyn StateObserver>, Vec<StateChange>, u64)>,
+}
+
+impl Notifications {
+    /// Notify the observers.
+    pub(crate) fn notify(&mut self) {
+        if let Some((ref mut observer, ref mut progress, _)) = self.progress {
+            for progress in progress.drain(..) {
+                progress.notify(observer.as_mut());
+            }
+        }
+        if let Some((ref mut observer, ref mut changes, _)) = self.state_changes {
+            for change in changes.drain(..) {
+                observer.state_changed(change);
+            }
+        }
+    }
+
+    /// Put the observers back into the participant.
+    pub(crate) fn put_back(self, participant: &mut Participant) {
+        if let Some((observer, _, version)) = self.progress {
+            participant.put_back_progress_observer(observer, version);
+        }
+        if let Some((observer, _, version)) = self.state_changes {
+            participant.put_back_state_observer(observer, version);
+        }
+    }
+}
+
+/// Advance the participant, and get the flags returned by
+/// [`xaynet_ffi_participant_tick()`] and the notifications of its observers.
+pub(crate) fn advance(participant: &mut Participant) -> (c_int, Notifications) {
+    participant.advance();
+    let flags = tick_flags(participant);
+    let notifications = Notifications {
+        progress: participant.take_progress(),
+        state_changes: participant.take_state_changes(),
+    };
+    (flags, notifications)
+}
 
+/// Get the flags returned by [`xaynet_ffi_participant_tick()`].
+fn tick_flags(participant: &Participant) -> c_int {
     let mut flags: c_int = 0;
     match participant.task() {
         Task::None => flags |= PARTICIPANT_TASK_NONE,
@@ -174,260 +503,1226 @@ pub unsafe extern "C" fn xaynet_ffi_participant_tick(participant: *mut Participa
     if participant.new_global_model() {
         flags |= PARTICIPANT_NEW_GLOBALMODEL;
     }
+    if participant.data_budget_exceeded() {
+        flags |= PARTICIPANT_DATA_BUDGET_EXCEEDED;
+    }
+    if let CircuitState::Open { .. } = participant.circuit_state() {
+        flags |= PARTICIPANT_CIRCUIT_OPEN;
+    }
+    if participant.awaiting_sum2_confirmation() {
+        flags |= PARTICIPANT_AWAITING_SUM2_CONFIRMATION;
+    }
+    if participant.consent_request().is_some() {
+        flags |= PARTICIPANT_AWAITING_CONSENT;
+    }
     flags
 }
 
-/// Serialize the participant state and return a buffer that contains the serialized
-/// participant.
+/// Get the number of rounds the participant has observed. It increases every time the
+/// coordinator starts a new round and it is preserved when the participant is saved and
+/// restored, so it can be used to decide whether the model should be trained again. It is
+/// unrelated to the round ID used internally by the coordinator.
+///
+/// # Return value
+///
+/// - the number of rounds, saturated at `UINT_MAX - 1`
+/// - `UINT_MAX` if `participant` is NULL
 ///
 /// # Safety
 ///
-/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
-///    *or* all of the following is true:
-///    - The pointer must be properly [aligned].
-///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
-///      documentation.
-/// 2. the `ByteBuffer` created by this function must be destroyed with
-///    [`xaynet_ffi_participant_destroy`]. Attempting to free the memory from the other
-///    side of the FFI is UB.
-/// 3. This function destroys the participant. Therefore, **the pointer becomes invalid
-///    and must not be used anymore**. Instead, a new participant should be created,
-///    either with [`xaynet_ffi_participant_new()`] or
-///    [`xaynet_ffi_participant_restore()`]
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
 ///
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
-///
-/// # Example
-///
-/// To save the participant into a file:
-///
-/// ```c
-///  const ByteBuffer *save_buf = xaynet_ffi_participant_save(participant);
-///  assert(save_buf);
-///
-///  char *path = "./participant.bin";
-///  FILE *f = fopen(path, "w");
-///  fwrite(save_buf->data, 1, save_buf->len, f);
-///  fclose(f);
-/// ```
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_participant_save(
-    participant: *mut Participant,
-) -> *const ByteBuffer {
-    let participant: Participant = match unsafe { participant.as_mut() } {
-        Some(ptr) => unsafe { *Box::from_raw(ptr) },
-        None => return std::ptr::null(),
-    };
-
-    Box::into_raw(Box::new(ByteBuffer::from_vec(participant.save())))
+pub unsafe extern "C" fn xaynet_ffi_participant_rounds_observed(
+    participant: *const Participant,
+) -> c_uint {
+    match unsafe { participant.as_ref() } {
+        Some(participant) => {
+            c_uint::try_from(participant.rounds_observed()).unwrap_or(c_uint::MAX - 1)
+        }
+        None => {
+            set_last_error(
+                "xaynet_ffi_participant_rounds_observed",
+                "`participant` is NULL",
+            );
+            c_uint::MAX
+        }
+    }
 }
 
-/// Restore the participant from a buffer that contained its serialized state.
+/// Copy the public signing key of the participant into `buffer`. The key identifies the
+/// participant towards the coordinator, for instance in its logs, and it is preserved
+/// when the participant is saved and restored.
 ///
 /// # Return value
 ///
-/// - a NULL pointer on failure
-/// - a pointer to the restored participant on success
+/// - `OK` if the key is copied into `buffer`
+/// - `ERR_NULLPTR` if `participant` or `buffer` is NULL
 ///
 /// # Safety
 ///
-/// When calling this method, you have to ensure that *either* the pointers are NULL
-/// *or* all of the following is true:
-/// - The pointers must be properly [aligned].
-/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
-///   documentation.
+/// 1. When calling this method, you have to ensure that *either* the pointers are NULL
+///    *or* all of the following is true:
+///    - The pointers must be properly [aligned].
+///    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. `buffer` must be valid for writes of [`PUBLIC_KEY_LENGTH`] bytes.
 ///
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
-///
-/// # Example
-///
-/// To restore a participant from a file:
-///
-/// ```c
-/// f = fopen("./participant.bin", "r");
-/// fseek(f, 0L, SEEK_END);
-/// int fsize = ftell(f);
-/// fseek(f, 0L, SEEK_SET);
-/// ByteBuffer buf = {
-///     .len = fsize,
-///     .data = (uint8_t *)malloc(fsize),
-/// };
-/// int n_read = fread(buf.data, 1, fsize, f);
-/// assert(n_read == fsize);
-/// fclose(f);
-/// Participant *restored =
-///     xaynet_ffi_participant_restore("http://localhost:8081", &buf);
-/// free(buf.data);
-/// ```
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_participant_restore(
-    url: FfiStr,
-    buffer: *const ByteBuffer,
-) -> *mut Participant {
-    let url = match url.as_opt_str() {
-        Some(url) => url,
-        None => return ptr::null_mut(),
-    };
-
-    let buffer: &ByteBuffer = match unsafe { buffer.as_ref() } {
-        Some(ptr) => ptr,
-        None => return ptr::null_mut(),
+pub unsafe extern "C" fn xaynet_ffi_participant_public_key(
+    participant: *const Participant,
+    buffer: *mut c_uchar,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_public_key", "`participant`"),
     };
-
-    if let Ok(participant) = Participant::restore(buffer.as_slice(), url) {
-        Box::into_raw(Box::new(participant))
-    } else {
-        ptr::null_mut()
+    if buffer.is_null() {
+        return fail_nullptr("xaynet_ffi_participant_public_key", "`buffer`");
     }
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, PUBLIC_KEY_LENGTH as usize) };
+    buffer.copy_from_slice(participant.public_key().as_slice());
+    ReturnCode::Ok
 }
 
-/// Set the participant's model. Usually this should be called when the value returned
-/// by [`xaynet_ffi_participant_tick()`] contains the [`PARTICIPANT_SHOULD_SET_MODEL`]
-/// flag, but it can be called anytime. The model just won't be sent to the coordinator
-/// until it's time.
+/// Generate new signing keys for the participant and copy their public key into
+/// `buffer`, so that the coordinator cannot link the participant across the rounds.
 ///
-/// - `buffer` should be a pointer to a buffer that contains the model
-/// - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
-///   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
-/// - `len` is the number of weights the model has
+/// The new keys are applied when the next round starts: until then,
+/// [`xaynet_ffi_participant_public_key()`] still returns the current key, which signs
+/// the messages of the current round. The new keys are preserved when the participant
+/// is saved and restored.
 ///
 /// # Return value
 ///
-/// - [`OK`] if the model is set successfully
-/// - [`ERR_NULLPTR`] if `participant` is NULL
-/// - [`ERR_SETMODEL_DATATYPE`] if the datatype is invalid
-/// - [`ERR_SETMODEL_MODEL`] if the model is invalid
+/// - `OK` if the keys are rotated and the new public key is copied into `buffer`
+/// - `ERR_NULLPTR` if `participant` or `buffer` is NULL
 ///
 /// # Safety
 ///
-/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+/// 1. When calling this method, you have to ensure that *either* the pointers are NULL
 ///    *or* all of the following is true:
-///    - The pointer must be properly [aligned].
-///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///    - The pointers must be properly [aligned].
+///    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
 ///      documentation.
-/// 2. If `len` or `data_type` do not match the model in `buffer`, this method will
-///    result in a buffer over-read.
+/// 2. `buffer` must be valid for writes of [`PUBLIC_KEY_LENGTH`] bytes.
 ///
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_participant_set_model(
+pub unsafe extern "C" fn xaynet_ffi_participant_rotate_keys(
     participant: *mut Participant,
-    buffer: *const c_void,
-    data_type: c_uchar,
-    len: c_uint,
-) -> c_int {
+    buffer: *mut c_uchar,
+) -> ReturnCode {
     let participant = match unsafe { participant.as_mut() } {
         Some(participant) => participant,
-        None => return ERR_NULLPTR,
+        None => return fail_nullptr("xaynet_ffi_participant_rotate_keys", "`participant`"),
     };
-
     if buffer.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr("xaynet_ffi_participant_rotate_keys", "`buffer`");
     }
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, PUBLIC_KEY_LENGTH as usize) };
+    buffer.copy_from_slice(participant.rotate_keys().as_slice());
+    ReturnCode::Ok
+}
 
-    let data_type = match DataType::try_from(data_type) {
-        Ok(data_type) => data_type,
-        Err(_) => return ERR_SETMODEL_DATATYPE,
-    };
-
-    let len = len as usize;
-    let model = match data_type {
-        DataType::F32 => {
-            let buffer = unsafe { slice::from_raw_parts(buffer as *const f32, len) };
-            // we map the error so that we get an uniform error type
-            Model::from_primitives(buffer.iter().copied()).map_err(|_| ())
-        }
-        DataType::F64 => {
-            let buffer = unsafe { slice::from_raw_parts(buffer as *const f64, len) };
-            Model::from_primitives(buffer.iter().copied()).map_err(|_| ())
-        }
-        DataType::I32 => {
-            let buffer = unsafe { slice::from_raw_parts(buffer as *const i32, len) };
-            Model::from_primitives(buffer.iter().copied()).map_err(|_| ())
+/// Replace the root certificate that the participant uses to authenticate the
+/// coordinator, with the PEM encoded certificate in `buffer`.
+///
+/// The certificate can be rotated at any time: the participant keeps its progress and
+/// uses the new certificate for its next requests. The certificates are not part of the
+/// participant state, so they must be set again after the participant is restored.
+///
+/// # Return value
+///
+/// - `OK` if the certificate is replaced
+/// - `ERR_NULLPTR` if `participant` or `buffer` is NULL
+/// - `ERR_TLS_CERTIFICATE` if the certificate is invalid. The current one is kept.
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_update_tls_root_certificate(
+    participant: *mut Participant,
+    buffer: *const ByteBuffer,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => {
+            return fail_nullptr(
+                "xaynet_ffi_participant_update_tls_root_certificate",
+                "`participant`",
+            )
         }
-        DataType::I64 => {
-            let buffer = unsafe { slice::from_raw_parts(buffer as *const i64, len) };
-            Model::from_primitives(buffer.iter().copied()).map_err(|_| ())
+    };
+    let buffer = match unsafe { buffer.as_ref() } {
+        Some(buffer) => buffer,
+        None => {
+            return fail_nullptr(
+                "xaynet_ffi_participant_update_tls_root_certificate",
+                "`buffer`",
+            )
         }
     };
-
-    if let Ok(m) = model {
-        participant.set_model(m);
-        OK
-    } else {
-        ERR_SETMODEL_MODEL
+    match participant.set_tls_root_certificate(buffer.as_slice()) {
+        Ok(()) => ReturnCode::Ok,
+        Err(err) => fail(
+            ReturnCode::ErrTlsCertificate,
+            "xaynet_ffi_participant_update_tls_root_certificate",
+            err,
+        ),
     }
 }
 
-/// Return the latest global model from the coordinator.
-///
-/// - `buffer` is the array in which the global model should be copied.
-/// - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
-///   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
-/// - `len` is the number of weights the model has
-///
-/// # Return Value
+/// Replace the identity that the participant uses to authenticate itself to the
+/// coordinator, with the PEM encoded private key and certificate chain in `buffer`.
 ///
-/// - [`OK`] if the model is set successfully
-/// - [`ERR_NULLPTR`] if `participant` or the `buffer` is NULL
-/// - [`GLOBALMODEL_NONE`] if no model exists
-/// - [`ERR_GLOBALMODEL_IO`] if the communication with the coordinator failed
-/// - [`ERR_GLOBALMODEL_DATATYPE`] if the datatype is invalid
-/// - [`ERR_GLOBALMODEL_LEN`] if the length of the buffer does not match the length of the model
-/// - [`ERR_GLOBALMODEL_CONVERT`] if the conversion of the model failed
+/// Like the root certificate (see
+/// [`xaynet_ffi_participant_update_tls_root_certificate()`]), the identity can be
+/// rotated at any time without losing the participant progress.
 ///
-/// # Note
+/// # Return value
 ///
-///   It is **not** guaranteed, that the model configuration returned by
-///   [`xaynet_ffi_participant_local_model_config`] corresponds to the configuration of
-///   the global model. This means that the global model can have a different length / data type
-///   than it is defined in model configuration. That both model configurations are the same is
-///   only guaranteed if the model config **never** changes on the coordinator side.
+/// - `OK` if the identity is replaced
+/// - `ERR_NULLPTR` if `participant` or `buffer` is NULL
+/// - `ERR_TLS_CERTIFICATE` if the private key or the certificates are invalid. The
+///   current identity is kept.
 ///
 /// # Safety
 ///
-/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
-///    *or* all of the following is true:
-///    - The pointer must be properly [aligned].
-///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
-///      documentation.
-/// 2. If `len` or `data_type` do not match the model in `buffer`, this method will
-///    result in a buffer over-read.
-///
-/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// See [`xaynet_ffi_participant_update_tls_root_certificate()`].
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_participant_global_model(
+pub unsafe extern "C" fn xaynet_ffi_participant_update_tls_identity(
     participant: *mut Participant,
-    buffer: *mut c_void,
-    data_type: c_uchar,
-    len: c_uint,
-) -> c_int {
+    buffer: *const ByteBuffer,
+) -> ReturnCode {
     let participant = match unsafe { participant.as_mut() } {
         Some(participant) => participant,
-        None => return ERR_NULLPTR,
+        None => {
+            return fail_nullptr(
+                "xaynet_ffi_participant_update_tls_identity",
+                "`participant`",
+            )
+        }
+    };
+    let buffer = match unsafe { buffer.as_ref() } {
+        Some(buffer) => buffer,
+        None => return fail_nullptr("xaynet_ffi_participant_update_tls_identity", "`buffer`"),
+    };
+    match participant.set_tls_identity(buffer.as_slice()) {
+        Ok(()) => ReturnCode::Ok,
+        Err(err) => fail(
+            ReturnCode::ErrTlsCertificate,
+            "xaynet_ffi_participant_update_tls_identity",
+            err,
+        ),
+    }
+}
+
+/// Get the task the participant has been selected for, in the current round.
+///
+/// # Return value
+///
+/// - [`TASK_NONE`] if the participant is not taking part in the sum or update task
+/// - [`TASK_SUM`] if the participant is taking part in the sum task
+/// - [`TASK_UPDATE`] if the participant is taking part in the update task
+/// - -`ERR_NULLPTR` if `participant` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_task(participant: *const Participant) -> c_int {
+    match unsafe { participant.as_ref() } {
+        Some(participant) => match participant.task() {
+            Task::None => TASK_NONE,
+            Task::Sum => TASK_SUM,
+            Task::Update => TASK_UPDATE,
+        },
+        None => -(fail_nullptr("xaynet_ffi_participant_task", "`participant`") as c_int),
+    }
+}
+
+/// Get the work that the next [`xaynet_ffi_participant_tick()`] performs, without
+/// advancing the participant. Every tick that requests the coordinator checks whether a
+/// new round started. A caller that wants to avoid network use can skip ticking while
+/// this returns [`WORK_DOWNLOAD`] or [`WORK_UPLOAD`], and one that wants to avoid heavy
+/// computations while it returns [`WORK_COMPUTE`].
+///
+/// # Return value
+///
+/// - [`WORK_POLL`] if the next tick polls the coordinator for the selection for a task
+/// - [`WORK_DOWNLOAD`] if the next tick downloads data from the coordinator
+/// - [`WORK_TRAIN`] if the next tick waits for the local model, which must be set with
+///   [`xaynet_ffi_participant_set_model()`] first. Until then, ticking only checks
+///   whether a new round started.
+/// - [`WORK_COMPUTE`] if the next tick masks the local model or aggregates the masks
+/// - [`WORK_UPLOAD`] if the next tick uploads a message to the coordinator
+/// - [`WORK_NONE`] if the next tick doesn't request the coordinator, because the circuit
+///   breaker is open or the daily data budget is exhausted
+/// - -`ERR_NULLPTR` if `participant` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_pending_work(
+    participant: *const Participant,
+) -> c_int {
+    match unsafe { participant.as_ref() } {
+        Some(participant) => match participant.pending_work() {
+            Some(work) => work_class(work),
+            None => WORK_NONE,
+        },
+        None => -(fail_nullptr("xaynet_ffi_participant_pending_work", "`participant`") as c_int),
+    }
+}
+
+/// Get the number of rounds in the history of the participant, i.e. the number of rounds
+/// the participant observed and completed, up to the maximum set with
+/// [`xaynet_ffi_settings_set_max_history_len()`]. The history is preserved when the
+/// participant is saved and restored.
+///
+/// # Return value
+///
+/// - the number of rounds in the history
+/// - `UINT_MAX` if `participant` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_settings_set_max_history_len()`]: crate::ffi::xaynet_ffi_settings_set_max_history_len
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_history_len(
+    participant: *const Participant,
+) -> c_uint {
+    match unsafe { participant.as_ref() } {
+        Some(participant) => {
+            c_uint::try_from(participant.history().len()).unwrap_or(c_uint::MAX - 1)
+        }
+        None => {
+            set_last_error(
+                "xaynet_ffi_participant_history_len",
+                "`participant` is NULL",
+            );
+            c_uint::MAX
+        }
+    }
+}
+
+/// Copy the round at position `index` in the history of the participant into `record`.
+/// The rounds are ordered from the oldest to the most recent one (see
+/// [`xaynet_ffi_participant_history_len()`]).
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` or `record` is NULL
+/// - `ERR_HISTORY_INDEX` if `index` is not smaller than the number of rounds in the
+///   history
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_history_entry(
+    participant: *const Participant,
+    index: c_uint,
+    record: *mut RoundRecord,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_history_entry", "`participant`"),
+    };
+    let record = match unsafe { record.as_mut() } {
+        Some(record) => record,
+        None => return fail_nullptr("xaynet_ffi_participant_history_entry", "`record`"),
+    };
+    let history = participant.history();
+    match history.get(index as usize) {
+        Some(&entry) => {
+            *record = entry.into();
+            ReturnCode::Ok
+        }
+        None => fail(
+            ReturnCode::ErrHistoryIndex,
+            "xaynet_ffi_participant_history_entry",
+            format_args!(
+                "index {} is out of range for a history of {} rounds",
+                index,
+                history.len()
+            ),
+        ),
+    }
+}
+
+/// Register a callback that is invoked whenever the participant state changed, with
+/// the given user data and the reason of the change:
+/// - [`STATE_CHANGE_PROGRESS`] if the participant made progress
+/// - [`STATE_CHANGE_CIRCUIT_BREAKER`] if the circuit breaker opened, became half-open
+///   or closed
+///
+/// The callback is invoked by [`xaynet_ffi_participant_tick()`], once per change, and
+/// can be used to save the participant whenever needed, instead of after every tick.
+/// Setting a model with [`xaynet_ffi_participant_set_model()`] doesn't invoke the
+/// callback, even though the model is part of the participant state saved afterwards.
+///
+/// A previously registered callback is replaced. If `callback` is NULL, the
+/// previously registered callback is removed.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` is NULL
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. `user_data` must remain valid until the callback is replaced or removed, or
+///    until the participant is destroyed.
+/// 3. The callback can use the participant, for instance to checkpoint it with
+///    [`xaynet_ffi_participant_prepare_for_shutdown()`]. However, it must not destroy
+///    the participant, so it must not call [`xaynet_ffi_participant_save()`] or
+///    [`xaynet_ffi_participant_destroy()`].
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+///
+/// # Example
+///
+/// To checkpoint the participant whenever its state changed:
+///
+/// ```c
+/// static void on_state_changed(void *user_data, int change) {
+///   Participant *participant = (Participant *)user_data;
+///   const ByteBuffer *buf = xaynet_ffi_participant_prepare_for_shutdown(participant);
+///   // write the buffer to a file
+///   xaynet_ffi_byte_buffer_destroy(buf);
+/// }
+///
+/// xaynet_ffi_participant_set_state_changed_callback(participant, on_state_changed,
+///                                                   participant);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_set_state_changed_callback(
+    participant: *mut Participant,
+    callback: Option<unsafe extern "C" fn(user_data: *mut c_void, change: c_int)>,
+    user_data: *mut c_void,
+) -> ReturnCode {
+    match unsafe { participant.as_mut() } {
+        Some(participant) => {
+            let observer = callback.map(|callback| {
+                Box::new(CallbackObserver {
+                    callback,
+                    user_data,
+                }) as Box<dyn StateObserver>
+            });
+            participant.set_state_observer(observer);
+            ReturnCode::Ok
+        }
+        None => fail_nullptr(
+            "xaynet_ffi_participant_set_state_changed_callback",
+            "`participant`",
+        ),
+    }
+}
+
+/// Register a callback that is invoked whenever the participant made progress through
+/// the PET protocol, with the given user data, the kind of progress and its value:
+/// - [`PROGRESS_PHASE_ENTERED`] if the participant entered the phase of a task. The
+///   value is [`TASK_NONE`], [`TASK_SUM`] or [`TASK_UPDATE`]. [`TASK_NONE`] means that
+///   the participant is done with its task or has not been selected for any task.
+/// - [`PROGRESS_MESSAGE_SENT`] if the participant sent a message to the coordinator. The
+///   value is the number of bytes sent. A message that is split in several parts is
+///   notified once per part.
+///
+/// The callback is invoked by [`xaynet_ffi_participant_tick()`], once per progress, in
+/// the order the progress was made. The callback is not part of the participant state,
+/// so it must be registered again after the participant is restored with
+/// [`xaynet_ffi_participant_restore()`]. The restored participant then notifies the
+/// phase it resumes in, on the next tick.
+///
+/// A previously registered callback is replaced. If `callback` is NULL, the
+/// previously registered callback is removed.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` is NULL
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. `user_data` must remain valid until the callback is replaced or removed, or
+///    until the participant is destroyed.
+/// 3. The callback can use the participant. However, it must not destroy the
+///    participant, so it must not call [`xaynet_ffi_participant_save()`] or
+///    [`xaynet_ffi_participant_destroy()`].
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_set_progress_callback(
+    participant: *mut Participant,
+    callback: Option<unsafe extern "C" fn(user_data: *mut c_void, progress: c_int, value: u64)>,
+    user_data: *mut c_void,
+) -> ReturnCode {
+    match unsafe { participant.as_mut() } {
+        Some(participant) => {
+            let observer = callback.map(|callback| {
+                Box::new(CallbackProgressObserver {
+                    callback,
+                    user_data,
+                }) as Box<dyn ProgressObserver>
+            });
+            participant.set_progress_observer(observer);
+            ReturnCode::Ok
+        }
+        None => fail_nullptr(
+            "xaynet_ffi_participant_set_progress_callback",
+            "`participant`",
+        ),
+    }
+}
+
+/// Serialize the participant state and return a buffer that contains the serialized
+/// participant.
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. the `ByteBuffer` created by this function must be destroyed with
+///    [`xaynet_ffi_participant_destroy`]. Attempting to free the memory from the other
+///    side of the FFI is UB.
+/// 3. This function destroys the participant. Therefore, **the pointer becomes invalid
+///    and must not be used anymore**. Instead, a new participant should be created,
+///    either with [`xaynet_ffi_participant_new()`] or
+///    [`xaynet_ffi_participant_restore()`]
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+///
+/// # Example
+///
+/// To save the participant into a file:
+///
+/// ```c
+///  const ByteBuffer *save_buf = xaynet_ffi_participant_save(participant);
+///  assert(save_buf);
+///
+///  char *path = "./participant.bin";
+///  FILE *f = fopen(path, "w");
+///  fwrite(save_buf->data, 1, save_buf->len, f);
+///  fclose(f);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_save(
+    participant: *mut Participant,
+) -> *const ByteBuffer {
+    let participant: Participant = match unsafe { participant.as_mut() } {
+        Some(ptr) => unsafe { *Box::from_raw(ptr) },
+        None => {
+            set_last_error("xaynet_ffi_participant_save", "`participant` is NULL");
+            return ptr::null();
+        }
+    };
+
+    Box::into_raw(Box::new(ByteBuffer::from_vec(participant.save())))
+}
+
+/// Checkpoint the participant before the app is shut down, and return a buffer that
+/// contains the serialized participant.
+///
+/// Unlike [`xaynet_ffi_participant_save()`], this function doesn't destroy the
+/// participant, which can keep being used if the app is not shut down after all. A
+/// model that was set with [`xaynet_ffi_participant_set_model()`] but not yet processed
+/// by [`xaynet_ffi_participant_tick()`] is part of the checkpoint: a participant
+/// restored from the checkpoint doesn't ask for the model again.
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. the `ByteBuffer` created by this function must be destroyed with
+///    [`xaynet_ffi_byte_buffer_destroy`]. Attempting to free the memory from the other
+///    side of the FFI is UB.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_byte_buffer_destroy`]: crate::ffi::xaynet_ffi_byte_buffer_destroy
+///
+/// # Example
+///
+/// To checkpoint the participant into a file:
+///
+/// ```c
+///  const ByteBuffer *checkpoint_buf = xaynet_ffi_participant_prepare_for_shutdown(participant);
+///  assert(checkpoint_buf);
+///
+///  char *path = "./participant.bin";
+///  FILE *f = fopen(path, "w");
+///  fwrite(checkpoint_buf->data, 1, checkpoint_buf->len, f);
+///  fclose(f);
+///  xaynet_ffi_byte_buffer_destroy(checkpoint_buf);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_prepare_for_shutdown(
+    participant: *mut Participant,
+) -> *const ByteBuffer {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => {
+            set_last_error(
+                "xaynet_ffi_participant_prepare_for_shutdown",
+                "`participant` is NULL",
+            );
+            return ptr::null();
+        }
+    };
+
+    Box::into_raw(Box::new(ByteBuffer::from_vec(
+        participant.prepare_for_shutdown(),
+    )))
+}
+
+/// Restore the participant from a buffer that contained its serialized state.
+///
+/// # Return value
+///
+/// - a NULL pointer on failure. [`xaynet_ffi_check_state()`] tells whether the
+///   failure is due to a corrupt state, and [`xaynet_ffi_last_error_message()`] gives
+///   the reason of the failure.
+/// - a pointer to the restored participant on success
+///
+/// [`xaynet_ffi_last_error_message()`]: crate::ffi::xaynet_ffi_last_error_message
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+///
+/// # Example
+///
+/// To restore a participant from a file:
+///
+/// ```c
+/// f = fopen("./participant.bin", "r");
+/// fseek(f, 0L, SEEK_END);
+/// int fsize = ftell(f);
+/// fseek(f, 0L, SEEK_SET);
+/// ByteBuffer buf = {
+///     .len = fsize,
+///     .data = (uint8_t *)malloc(fsize),
+/// };
+/// int n_read = fread(buf.data, 1, fsize, f);
+/// assert(n_read == fsize);
+/// fclose(f);
+/// Participant *restored =
+///     xaynet_ffi_participant_restore("http://localhost:8081", &buf);
+/// free(buf.data);
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_restore(
+    url: FfiStr,
+    buffer: *const ByteBuffer,
+) -> *mut Participant {
+    let url = match url.as_opt_str() {
+        Some(url) => url,
+        None => {
+            set_last_error(
+                "xaynet_ffi_participant_restore",
+                "`url` is NULL or not valid UTF-8",
+            );
+            return ptr::null_mut();
+        }
+    };
+
+    let buffer: &ByteBuffer = match unsafe { buffer.as_ref() } {
+        Some(ptr) => ptr,
+        None => {
+            set_last_error("xaynet_ffi_participant_restore", "`buffer` is NULL");
+            return ptr::null_mut();
+        }
+    };
+
+    match Participant::restore(buffer.as_slice(), url) {
+        Ok(participant) => Box::into_raw(Box::new(participant)),
+        Err(err) => {
+            set_last_error("xaynet_ffi_participant_restore", err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Check whether a participant can be restored from a buffer that contains its
+/// serialized state (see [`xaynet_ffi_participant_restore()`]).
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `buffer` is NULL
+/// - `ERR_STATE_CORRUPT` if the state is truncated or altered
+/// - `ERR_STATE_DESERIALIZE` if the state can't be deserialized
+/// - `ERR_STATE_VERSION` if the state was saved by a newer version of the library
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_check_state(buffer: *const ByteBuffer) -> ReturnCode {
+    match unsafe { buffer.as_ref() } {
+        Some(buffer) => match Participant::check_state(buffer.as_slice()) {
+            Ok(()) => ReturnCode::Ok,
+            Err(err @ InitError::Corrupt) => {
+                fail(ReturnCode::ErrStateCorrupt, "xaynet_ffi_check_state", err)
+            }
+            Err(err @ InitError::UnsupportedVersion(_)) => {
+                fail(ReturnCode::ErrStateVersion, "xaynet_ffi_check_state", err)
+            }
+            Err(err) => fail(
+                ReturnCode::ErrStateDeserialize,
+                "xaynet_ffi_check_state",
+                err,
+            ),
+        },
+        None => fail_nullptr("xaynet_ffi_check_state", "`buffer`"),
+    }
+}
+
+/// Migrate a serialized participant state that was saved by an older version of the
+/// library to the format of the current version, and write a pointer to a buffer that
+/// contains the migrated state into `migrated`.
+///
+/// [`xaynet_ffi_participant_restore()`] migrates the states it restores, so this is only
+/// needed for upgrading the states that are stored by the app.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `buffer` or `migrated` is NULL
+/// - `ERR_STATE_CORRUPT` if the state is truncated or altered
+/// - `ERR_STATE_DESERIALIZE` if the state can't be deserialized
+/// - `ERR_STATE_VERSION` if the state was saved by a newer version of the library
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointers are NULL
+///    *or* all of the following is true:
+///    - The pointers must be properly [aligned].
+///    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. the `ByteBuffer` created by this function must be destroyed with
+///    [`xaynet_ffi_byte_buffer_destroy`]. Attempting to free the memory from the other
+///    side of the FFI is UB.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_byte_buffer_destroy`]: crate::ffi::xaynet_ffi_byte_buffer_destroy
+///
+/// # Example
+///
+/// ```c
+/// const ByteBuffer *migrated = NULL;
+/// if (xaynet_ffi_migrate_state(&buf, &migrated) == OK) {
+///   // store the migrated state in place of the old one
+///   xaynet_ffi_byte_buffer_destroy(migrated);
+/// }
+/// ```
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_migrate_state(
+    buffer: *const ByteBuffer,
+    migrated: *mut *const ByteBuffer,
+) -> ReturnCode {
+    let (buffer, migrated) = match (unsafe { buffer.as_ref() }, unsafe { migrated.as_mut() }) {
+        (Some(buffer), Some(migrated)) => (buffer, migrated),
+        _ => return fail_nullptr("xaynet_ffi_migrate_state", "`buffer` or `migrated`"),
+    };
+
+    match migrate_state(buffer.as_slice()) {
+        Ok(state) => {
+            *migrated = Box::into_raw(Box::new(ByteBuffer::from_vec(state)));
+            ReturnCode::Ok
+        }
+        Err(err @ MigrateError::Corrupt) => {
+            fail(ReturnCode::ErrStateCorrupt, "xaynet_ffi_migrate_state", err)
+        }
+        Err(err @ MigrateError::Deserialization(_)) => fail(
+            ReturnCode::ErrStateDeserialize,
+            "xaynet_ffi_migrate_state",
+            err,
+        ),
+        Err(err @ MigrateError::UnsupportedVersion(_)) => {
+            fail(ReturnCode::ErrStateVersion, "xaynet_ffi_migrate_state", err)
+        }
+    }
+}
+
+/// Set the participant's model. Usually this should be called when the value returned
+/// by [`xaynet_ffi_participant_tick()`] contains the [`PARTICIPANT_SHOULD_SET_MODEL`]
+/// flag, but it can be called anytime. The model just won't be sent to the coordinator
+/// until it's time.
+///
+/// - `buffer` should be a pointer to a buffer that contains the model
+/// - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
+///   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
+/// - `len` is the number of weights the model has
+///
+/// # Return value
+///
+/// - `OK` if the model is set successfully
+/// - `MODEL_SHAPE_CHANGED` if the model is set successfully, but it has a different
+///   length than the models that were set before. The global model that was cached for
+///   the previous length and the local model that was not sent yet are discarded.
+/// - `ERR_NULLPTR` if `participant` is NULL
+/// - `ERR_SETMODEL_DATATYPE` if the datatype is invalid
+/// - `ERR_SETMODEL_MODEL` if the model is invalid
+/// - `ERR_SETMODEL_LENGTH` if the coordinator expects another model length in the
+///   current round. The model is not sent to the coordinator.
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. If `len` or `data_type` do not match the model in `buffer`, this method will
+///    result in a buffer over-read.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_set_model(
+    participant: *mut Participant,
+    buffer: *const c_void,
+    data_type: c_uchar,
+    len: c_uint,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_set_model", "`participant`"),
+    };
+
+    if buffer.is_null() {
+        return fail_nullptr("xaynet_ffi_participant_set_model", "`buffer`");
+    }
+
+    let data_type = match DataType::try_from(data_type) {
+        Ok(data_type) => data_type,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrSetmodelDatatype,
+                "xaynet_ffi_participant_set_model",
+                err,
+            )
+        }
+    };
+
+    let model = match unsafe { model_from_buffer(buffer, data_type, len as usize) } {
+        Ok(model) => model,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrSetmodelModel,
+                "xaynet_ffi_participant_set_model",
+                err,
+            )
+        }
+    };
+    match participant.set_model(model) {
+        Ok(()) if participant.model_shape_changed() => ReturnCode::ModelShapeChanged,
+        Ok(()) => ReturnCode::Ok,
+        Err(err) => fail(
+            ReturnCode::ErrSetmodelLength,
+            "xaynet_ffi_participant_set_model",
+            err,
+        ),
+    }
+}
+
+/// Set the participant's model from a sparse representation, see
+/// [`xaynet_ffi_participant_set_model()`]. This avoids copying the whole model when only
+/// some of its weights changed.
+///
+/// - `indices` should be a pointer to an array of `len` weight indices
+/// - `values` should be a pointer to an array of `len` weights: the weight at index
+///   `indices[i]` is `values[i]`
+/// - `data_type` specifies the type of the weights in `values` (see [`DataType`]). The C
+///   header file generated by this crate provides an enum corresponding to the parameters:
+///   `DataType`.
+/// - `len` is the number of weights in the sparse representation
+/// - `total_len` is the number of weights the model has
+///
+/// The weights that are not part of the sparse representation are the ones of the last
+/// global model fetched with [`xaynet_ffi_participant_global_model()`] in the current
+/// round, or zero if no global model was fetched. The global model is not fetched again.
+///
+/// # Return value
+///
+/// - `OK` if the model is set successfully
+/// - `MODEL_SHAPE_CHANGED` if the model is set successfully, but it has a different
+///   length than the models that were set before (see
+///   [`xaynet_ffi_participant_set_model()`])
+/// - `ERR_NULLPTR` if `participant`, `indices` or `values` is NULL
+/// - `ERR_SETMODEL_DATATYPE` if the datatype is invalid
+/// - `ERR_SETMODEL_MODEL` if the model is invalid, or if the last global model doesn't
+///   have `total_len` weights
+/// - `ERR_SETMODEL_INDICES` if an index is out of range or duplicated
+/// - `ERR_SETMODEL_LENGTH` if the coordinator expects another model length in the
+///   current round
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointers are NULL
+///    *or* all of the following is true:
+///    - The pointers must be properly [aligned].
+///    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. If `len` or `data_type` do not match the arrays `indices` and `values`, this method
+///    will result in a buffer over-read.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_set_sparse_model(
+    participant: *mut Participant,
+    indices: *const c_uint,
+    values: *const c_void,
+    data_type: c_uchar,
+    len: c_uint,
+    total_len: c_uint,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_set_sparse_model", "`participant`"),
+    };
+
+    if indices.is_null() || values.is_null() {
+        return fail_nullptr(
+            "xaynet_ffi_participant_set_sparse_model",
+            "`indices` or `values`",
+        );
+    }
+
+    let data_type = match DataType::try_from(data_type) {
+        Ok(data_type) => data_type,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrSetmodelDatatype,
+                "xaynet_ffi_participant_set_sparse_model",
+                err,
+            )
+        }
+    };
+
+    let len = len as usize;
+    let values = match unsafe { model_from_buffer(values, data_type, len) } {
+        Ok(values) => values,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrSetmodelModel,
+                "xaynet_ffi_participant_set_sparse_model",
+                err,
+            )
+        }
+    };
+    let indices: Vec<usize> = unsafe { slice::from_raw_parts(indices, len) }
+        .iter()
+        .map(|&index| index as usize)
+        .collect();
+
+    match participant.set_sparse_model(&indices, values, total_len as usize) {
+        Ok(()) if participant.model_shape_changed() => ReturnCode::ModelShapeChanged,
+        Ok(()) => ReturnCode::Ok,
+        Err(err @ SparseModelError::IndexOutOfRange { .. }) => fail(
+            ReturnCode::ErrSetmodelIndices,
+            "xaynet_ffi_participant_set_sparse_model",
+            err,
+        ),
+        Err(err @ SparseModelError::DuplicateIndex(_)) => fail(
+            ReturnCode::ErrSetmodelIndices,
+            "xaynet_ffi_participant_set_sparse_model",
+            err,
+        ),
+        Err(err @ SparseModelError::ModelLength(_)) => fail(
+            ReturnCode::ErrSetmodelLength,
+            "xaynet_ffi_participant_set_sparse_model",
+            err,
+        ),
+        Err(err) => fail(
+            ReturnCode::ErrSetmodelModel,
+            "xaynet_ffi_participant_set_sparse_model",
+            err,
+        ),
+    }
+}
+
+/// Convert the `len` weights of type `data_type` in `buffer` into a model.
+///
+/// # Safety
+///
+/// `buffer` must point to `len` properly aligned weights of type `data_type`.
+unsafe fn model_from_buffer(
+    buffer: *const c_void,
+    data_type: DataType,
+    len: usize,
+) -> Result<Model, String> {
+    match data_type {
+        DataType::F32 => {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const f32, len) };
+            // we map the error so that we get an uniform error type
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+        DataType::F64 => {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const f64, len) };
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+        DataType::I32 => {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const i32, len) };
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+        DataType::I64 => {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const i64, len) };
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+        DataType::U8 => {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const u8, len) };
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+        DataType::I16 => {
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const i16, len) };
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+        DataType::F16 => {
+            // `f16` has the same layout as the `uint16_t` bits
+            let buffer = unsafe { slice::from_raw_parts(buffer as *const f16, len) };
+            Model::from_primitives(buffer.iter().copied()).map_err(|err| err.to_string())
+        }
+    }
+}
+
+/// Get the number of weights of the latest global model from the coordinator, and
+/// write it into `len`. This is the length of the buffer expected by
+/// [`xaynet_ffi_participant_global_model()`].
+///
+/// The global model is cached by the participant until a new round starts, so that
+/// a subsequent call to [`xaynet_ffi_participant_global_model()`] doesn't download it
+/// again.
+///
+/// # Return Value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` or `len` is NULL
+/// - `GLOBALMODEL_NONE` if no model exists
+/// - `ERR_GLOBALMODEL_IO` if the communication with the coordinator failed
+/// - `ERR_GLOBALMODEL_LEN` if the number of weights doesn't fit in `len`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_global_model_len(
+    participant: *mut Participant,
+    len: *mut c_uint,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_global_model_len", "`participant`"),
+    };
+    let len = match unsafe { len.as_mut() } {
+        Some(len) => len,
+        None => return fail_nullptr("xaynet_ffi_participant_global_model_len", "`len`"),
+    };
+
+    let model_len = match participant.global_model_len() {
+        Ok(Some(model_len)) => model_len,
+        Ok(None) => return ReturnCode::GlobalmodelNone,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrGlobalmodelIo,
+                "xaynet_ffi_participant_global_model_len",
+                err,
+            )
+        }
+    };
+    match c_uint::try_from(model_len) {
+        Ok(model_len) => {
+            *len = model_len;
+            ReturnCode::Ok
+        }
+        Err(_) => fail(
+            ReturnCode::ErrGlobalmodelLen,
+            "xaynet_ffi_participant_global_model_len",
+            format_args!("the model has too many weights: {}", model_len),
+        ),
+    }
+}
+
+/// Return the latest global model from the coordinator.
+///
+/// - `buffer` is the array in which the global model should be copied.
+/// - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
+///   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
+/// - `len` is the number of weights the model has (see
+///   [`xaynet_ffi_participant_global_model_len()`])
+///
+/// # Return Value
+///
+/// - `OK` if the model is set successfully
+/// - `ERR_NULLPTR` if `participant` or the `buffer` is NULL
+/// - `GLOBALMODEL_NONE` if no model exists
+/// - `ERR_GLOBALMODEL_IO` if the communication with the coordinator failed
+/// - `ERR_GLOBALMODEL_DATATYPE` if the datatype is invalid
+/// - `ERR_GLOBALMODEL_LEN` if the length of the buffer does not match the length of the model
+/// - `ERR_GLOBALMODEL_CONVERT` if the conversion of the model failed
+///
+/// # Note
+///
+///   It is **not** guaranteed, that the model configuration returned by
+///   [`xaynet_ffi_participant_local_model_config`] corresponds to the configuration of
+///   the global model. This means that the global model can have a different length / data type
+///   than it is defined in model configuration. That both model configurations are the same is
+///   only guaranteed if the model config **never** changes on the coordinator side.
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointer is NULL
+///    *or* all of the following is true:
+///    - The pointer must be properly [aligned].
+///    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. If `len` or `data_type` do not match the model in `buffer`, this method will
+///    result in a buffer over-read.
+///
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_global_model(
+    participant: *mut Participant,
+    buffer: *mut c_void,
+    data_type: c_uchar,
+    len: c_uint,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_global_model", "`participant`"),
     };
 
     if buffer.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr("xaynet_ffi_participant_global_model", "`buffer`");
     }
 
     let global_model = match participant.global_model() {
         Ok(Some(model)) => model,
-        Ok(None) => return GLOBALMODEL_NONE,
-        Err(_) => return ERR_GLOBALMODEL_IO,
+        Ok(None) => return ReturnCode::GlobalmodelNone,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrGlobalmodelIo,
+                "xaynet_ffi_participant_global_model",
+                err,
+            )
+        }
     };
 
     let data_type = match DataType::try_from(data_type) {
         Ok(data_type) => data_type,
-        Err(_) => return ERR_GLOBALMODEL_DATATYPE,
+        Err(err) => {
+            return fail(
+                ReturnCode::ErrGlobalmodelDatatype,
+                "xaynet_ffi_participant_global_model",
+                err,
+            )
+        }
     };
 
     let len = len as usize;
     if len != global_model.len() {
-        return ERR_GLOBALMODEL_LEN;
+        return fail(
+            ReturnCode::ErrGlobalmodelLen,
+            "xaynet_ffi_participant_global_model",
+            format_args!(
+                "invalid buffer length {} (expected {})",
+                len,
+                global_model.len()
+            ),
+        );
     }
 
     match data_type {
@@ -435,21 +1730,29 @@ pub unsafe extern "C" fn xaynet_ffi_participant_global_model(
         DataType::F64 => into_primitives!(global_model, buffer, f64, len),
         DataType::I32 => into_primitives!(global_model, buffer, i32, len),
         DataType::I64 => into_primitives!(global_model, buffer, i64, len),
+        DataType::U8 => into_primitives!(global_model, buffer, u8, len),
+        DataType::I16 => into_primitives!(global_model, buffer, i16, len),
+        DataType::F16 => into_primitives!(global_model, buffer, f16, len),
     }
 }
 
 #[macro_export]
 macro_rules! into_primitives {
     ($global_model:expr, $buffer:expr, $data_type:ty, $len:expr) => {{
-        if let Ok(global_model) = $global_model
+        match $global_model
             .into_primitives()
             .collect::<Result<Vec<$data_type>, _>>()
         {
-            let buffer = unsafe { slice::from_raw_parts_mut($buffer as *mut $data_type, $len) };
-            buffer.copy_from_slice(global_model.as_slice());
-            OK
-        } else {
-            ERR_GLOBALMODEL_CONVERT
+            Ok(global_model) => {
+                let buffer = unsafe { slice::from_raw_parts_mut($buffer as *mut $data_type, $len) };
+                buffer.copy_from_slice(global_model.as_slice());
+                ReturnCode::Ok
+            }
+            Err(err) => fail(
+                ReturnCode::ErrGlobalmodelConvert,
+                "xaynet_ffi_participant_global_model",
+                err,
+            ),
         }
     }};
 }
@@ -472,8 +1775,542 @@ pub unsafe extern "C" fn xaynet_ffi_participant_local_model_config(
 ) -> *mut LocalModelConfig {
     let participant = match unsafe { participant.as_ref() } {
         Some(ptr) => ptr,
-        None => return std::ptr::null_mut(),
+        None => {
+            set_last_error(
+                "xaynet_ffi_participant_local_model_config",
+                "`participant` is NULL",
+            );
+            return ptr::null_mut();
+        }
     };
 
     Box::into_raw(Box::new(participant.local_model_config().into()))
 }
+
+/// Recommend when the participant should be ticked next and what kind of work it
+/// performs then, and write the recommendation into `recommendation`. This is meant for
+/// scheduling the participant in the background (see
+/// [`Participant::next_wakeup_recommendation()`]).
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` or `recommendation` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_next_wakeup(
+    participant: *const Participant,
+    recommendation: *mut WakeupRecommendation,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_next_wakeup", "`participant`"),
+    };
+    match unsafe { recommendation.as_mut() } {
+        Some(recommendation) => {
+            *recommendation = participant.next_wakeup_recommendation().into();
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_participant_next_wakeup", "`recommendation`"),
+    }
+}
+
+/// Get the statistics about the training from the coordinator, and write them into
+/// `stats`. They are safe to show to the user of the app, e.g. the approximate number of
+/// participants that contributed to the latest global model.
+///
+/// The statistics change at most once per round and the coordinator rate limits the
+/// requests, so they don't need to be fetched more often than every few minutes.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` or `stats` is NULL
+/// - `ERR_PUBLIC_STATS_IO` if the communication with the coordinator failed
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_public_stats(
+    participant: *mut Participant,
+    stats: *mut PublicStats,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_mut() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_public_stats", "`participant`"),
+    };
+    let stats = match unsafe { stats.as_mut() } {
+        Some(stats) => stats,
+        None => return fail_nullptr("xaynet_ffi_participant_public_stats", "`stats`"),
+    };
+
+    match participant.public_stats() {
+        Ok(public_stats) => {
+            *stats = public_stats.into();
+            ReturnCode::Ok
+        }
+        Err(err) => fail(
+            ReturnCode::ErrPublicStatsIo,
+            "xaynet_ffi_participant_public_stats",
+            err,
+        ),
+    }
+}
+
+/// Get the number of bytes the participant sent and received in the current day, and
+/// write it into `used`.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` or `used` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_data_usage(
+    participant: *const Participant,
+    used: *mut u64,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_data_usage", "`participant`"),
+    };
+    match unsafe { used.as_mut() } {
+        Some(used) => {
+            *used = participant.data_usage();
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_participant_data_usage", "`used`"),
+    }
+}
+
+/// Get the number of events that the participant dropped, and write it into `dropped`.
+///
+/// The participant processes the events emitted by its internal state machine during
+/// every [`xaynet_ffi_participant_tick()`], and keeps at most the number of events set
+/// with [`xaynet_ffi_settings_set_max_pending_events()`] until then. If more events are
+/// emitted in a single tick, the oldest informational ones are dropped, the events that
+/// report the progress through the tasks or request an action are never dropped. The
+/// pending events are not part of the state saved with [`xaynet_ffi_participant_save()`],
+/// so its size doesn't depend on them, but their maximum is.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` or `dropped` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_settings_set_max_pending_events()`]: crate::ffi::xaynet_ffi_settings_set_max_pending_events
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_dropped_events(
+    participant: *const Participant,
+    dropped: *mut u64,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_dropped_events", "`participant`"),
+    };
+    match unsafe { dropped.as_mut() } {
+        Some(dropped) => {
+            *dropped = participant.dropped_events();
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_participant_dropped_events", "`dropped`"),
+    }
+}
+
+/// Set the maximum number of bytes the participant may send and receive per day. If
+/// `budget` is `0`, the data usage is not limited.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` is NULL
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_set_daily_data_budget(
+    participant: *mut Participant,
+    budget: u64,
+) -> ReturnCode {
+    match unsafe { participant.as_mut() } {
+        Some(participant) => {
+            participant.set_daily_data_budget(if budget == 0 { None } else { Some(budget) });
+            ReturnCode::Ok
+        }
+        None => fail_nullptr(
+            "xaynet_ffi_participant_set_daily_data_budget",
+            "`participant`",
+        ),
+    }
+}
+
+/// Copy the serialized global mask that the participant aggregated in the sum2 phase
+/// into `buffer`, while the participant awaits its confirmation (see
+/// [`PARTICIPANT_AWAITING_SUM2_CONFIRMATION`]).
+///
+/// When calling this function, `len` must point to the length of `buffer`. On return,
+/// it points to the length of the serialized mask, unless the participant doesn't await
+/// the confirmation of a mask. To get the length of the mask without copying it, `len`
+/// can point to `0`, in which case `buffer` may be NULL.
+///
+/// # Return value
+///
+/// - `OK` if the mask is copied into `buffer`
+/// - `ERR_NULLPTR` if `participant` or `len` is NULL, or if `buffer` is NULL while
+///   `len` doesn't point to `0`
+/// - `SUM2_MASK_NONE` if the participant doesn't await the confirmation of a mask
+/// - `ERR_SUM2_MASK_LEN` if `buffer` is too small for the mask
+///
+/// # Safety
+///
+/// 1. When calling this method, you have to ensure that *either* the pointers are NULL
+///    *or* all of the following is true:
+///    - The pointers must be properly [aligned].
+///    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///      documentation.
+/// 2. `buffer` must be valid for writes of `len` bytes.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_sum2_mask(
+    participant: *const Participant,
+    buffer: *mut c_uchar,
+    len: *mut c_uint,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_sum2_mask", "`participant`"),
+    };
+    let len = match unsafe { len.as_mut() } {
+        Some(len) => len,
+        None => return fail_nullptr("xaynet_ffi_participant_sum2_mask", "`len`"),
+    };
+    if buffer.is_null() && *len != 0 {
+        return fail_nullptr("xaynet_ffi_participant_sum2_mask", "`buffer`");
+    }
+
+    let mask = match participant.sum2_mask() {
+        Some(mask) => mask,
+        None => return ReturnCode::Sum2MaskNone,
+    };
+    let capacity = *len as usize;
+    *len = mask.len() as c_uint;
+    if capacity < mask.len() {
+        return fail(
+            ReturnCode::ErrSum2MaskLen,
+            "xaynet_ffi_participant_sum2_mask",
+            format_args!(
+                "buffer of {} bytes is too small for the mask of {} bytes",
+                capacity,
+                mask.len()
+            ),
+        );
+    }
+    let buffer = unsafe { slice::from_raw_parts_mut(buffer, mask.len()) };
+    buffer.copy_from_slice(&mask);
+    ReturnCode::Ok
+}
+
+/// Confirm the global mask that the participant aggregated in the sum2 phase, so that
+/// the sum2 message is sent on the next tick.
+///
+/// # Return value
+///
+/// - `OK` if the mask is confirmed
+/// - `ERR_NULLPTR` if `participant` is NULL
+/// - `SUM2_MASK_NONE` if the participant doesn't await the confirmation of a mask
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_confirm_sum2(
+    participant: *mut Participant,
+) -> ReturnCode {
+    match unsafe { participant.as_mut() } {
+        Some(participant) => {
+            if participant.confirm_sum2() {
+                ReturnCode::Ok
+            } else {
+                ReturnCode::Sum2MaskNone
+            }
+        }
+        None => fail_nullptr("xaynet_ffi_participant_confirm_sum2", "`participant`"),
+    }
+}
+
+/// Get the request for the consent of the user to take part in the task the participant
+/// has been selected for, while the participant awaits the consent (see
+/// [`PARTICIPANT_AWAITING_CONSENT`]).
+///
+/// On success, `task` points to [`PARTICIPANT_TASK_SUM`] or [`PARTICIPANT_TASK_UPDATE`]
+/// and `upload_bytes` points to an estimate of the number of bytes the participant
+/// uploads for the task. The entries of the local seed dictionary of the update task
+/// are not part of the estimate, because they depend on the number of sum participants.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant`, `task` or `upload_bytes` is NULL
+/// - `CONSENT_NONE` if the participant doesn't await the consent of the user
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointers are NULL
+/// *or* all of the following is true:
+/// - The pointers must be properly [aligned].
+/// - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_consent_request(
+    participant: *const Participant,
+    task: *mut c_int,
+    upload_bytes: *mut u64,
+) -> ReturnCode {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return fail_nullptr("xaynet_ffi_participant_consent_request", "`participant`"),
+    };
+    let (task, upload_bytes) = match unsafe { (task.as_mut(), upload_bytes.as_mut()) } {
+        (Some(task), Some(upload_bytes)) => (task, upload_bytes),
+        _ => {
+            return fail_nullptr(
+                "xaynet_ffi_participant_consent_request",
+                "`task` or `upload_bytes`",
+            )
+        }
+    };
+
+    match participant.consent_request() {
+        Some(request) => {
+            *task = match request.task {
+                ConsentTask::Sum => PARTICIPANT_TASK_SUM,
+                ConsentTask::Update => PARTICIPANT_TASK_UPDATE,
+            };
+            *upload_bytes = request.upload_bytes;
+            ReturnCode::Ok
+        }
+        None => ReturnCode::ConsentNone,
+    }
+}
+
+/// Grant the consent of the user to take part in the task the participant has been
+/// selected for, so that the task starts on the next tick.
+///
+/// # Return value
+///
+/// - `OK` if the consent is granted
+/// - `ERR_NULLPTR` if `participant` is NULL
+/// - `CONSENT_NONE` if the participant doesn't await the consent of the user
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_grant_consent(
+    participant: *mut Participant,
+) -> ReturnCode {
+    match unsafe { participant.as_mut() } {
+        Some(participant) => {
+            if participant.grant_consent() {
+                ReturnCode::Ok
+            } else {
+                ReturnCode::ConsentNone
+            }
+        }
+        None => fail_nullptr("xaynet_ffi_participant_grant_consent", "`participant`"),
+    }
+}
+
+/// Deny the consent of the user to take part in the task the participant has been
+/// selected for, so that the task is abandoned on the next tick.
+///
+/// # Return value
+///
+/// - `OK` if the consent is denied
+/// - `ERR_NULLPTR` if `participant` is NULL
+/// - `CONSENT_NONE` if the participant doesn't await the consent of the user
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_participant_deny_consent(
+    participant: *mut Participant,
+) -> ReturnCode {
+    match unsafe { participant.as_mut() } {
+        Some(participant) => {
+            if participant.deny_consent() {
+                ReturnCode::Ok
+            } else {
+                ReturnCode::ConsentNone
+            }
+        }
+        None => fail_nullptr("xaynet_ffi_participant_deny_consent", "`participant`"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sodiumoxide::crypto::hash::sha256;
+    use xaynet_core::crypto::{SigningKeyPair, SigningKeySeed};
+
+    use super::*;
+    use crate::{
+        ffi::{xaynet_ffi_byte_buffer_destroy, PUBLIC_KEY_LENGTH},
+        StateVersion,
+    };
+
+    /// States saved by older versions of the library. The participant keys are derived
+    /// from the seed `[7; 32]`.
+    const OLDER_STATES: [&[u8]; 4] = [
+        include_bytes!("../../tests/data/state_baseline.bin"),
+        include_bytes!("../../tests/data/state_v1.bin"),
+        include_bytes!("../../tests/data/state_v2.bin"),
+        include_bytes!("../../tests/data/state_v3.bin"),
+    ];
+
+    const URL: &[u8] = b"http://localhost:1\0";
+
+    fn assert_restored(buffer: &ByteBuffer, keys: &SigningKeyPair) {
+        let url = unsafe { FfiStr::from_raw(URL.as_ptr() as *const _) };
+        let participant = unsafe { xaynet_ffi_participant_restore(url, buffer) };
+        assert!(!participant.is_null());
+
+        let mut public_key = vec![0; PUBLIC_KEY_LENGTH as usize];
+        assert_eq!(
+            unsafe { xaynet_ffi_participant_public_key(participant, public_key.as_mut_ptr()) },
+            ReturnCode::Ok,
+        );
+        assert_eq!(public_key, keys.public.as_slice());
+        assert_eq!(
+            unsafe { xaynet_ffi_participant_destroy(participant) },
+            ReturnCode::Ok
+        );
+    }
+
+    #[test]
+    fn test_restore_older_states() {
+        sodiumoxide::init().unwrap();
+        let keys = SigningKeyPair::derive_from_seed(&SigningKeySeed::from_slice(&[7; 32]).unwrap());
+
+        for state in OLDER_STATES.iter() {
+            let buffer = ByteBuffer::from_vec(state.to_vec());
+            assert_eq!(unsafe { xaynet_ffi_check_state(&buffer) }, ReturnCode::Ok);
+            assert_restored(&buffer, &keys);
+
+            let mut migrated = ptr::null();
+            assert_eq!(
+                unsafe { xaynet_ffi_migrate_state(&buffer, &mut migrated) },
+                ReturnCode::Ok
+            );
+            let migrated_buffer = unsafe { &*migrated };
+            assert_eq!(migrated_buffer.as_slice()[0], StateVersion::CURRENT as u8);
+            assert_eq!(
+                unsafe { xaynet_ffi_check_state(migrated_buffer) },
+                ReturnCode::Ok
+            );
+            assert_restored(migrated_buffer, &keys);
+
+            assert_eq!(
+                unsafe { xaynet_ffi_byte_buffer_destroy(migrated) },
+                ReturnCode::Ok
+            );
+            buffer.destroy();
+        }
+    }
+
+    #[test]
+    fn test_restore_newer_state() {
+        let mut state = vec![StateVersion::CURRENT as u8 + 1, 0, 0, 0];
+        let checksum = sha256::hash(&state);
+        state.extend_from_slice(checksum.as_ref());
+        let buffer = ByteBuffer::from_vec(state);
+
+        assert_eq!(
+            unsafe { xaynet_ffi_check_state(&buffer) },
+            ReturnCode::ErrStateVersion
+        );
+        let mut migrated = ptr::null();
+        assert_eq!(
+            unsafe { xaynet_ffi_migrate_state(&buffer, &mut migrated) },
+            ReturnCode::ErrStateVersion
+        );
+        assert!(migrated.is_null());
+
+        let url = unsafe { FfiStr::from_raw(URL.as_ptr() as *const _) };
+        assert!(unsafe { xaynet_ffi_participant_restore(url, &buffer) }.is_null());
+        buffer.destroy();
+    }
+}
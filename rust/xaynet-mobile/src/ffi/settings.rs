@@ -1,19 +1,13 @@
-use std::os::raw::{c_double, c_int};
+use std::{
+    os::raw::{c_double, c_int, c_uint},
+    time::Duration,
+};
 
 use ffi_support::{ByteBuffer, FfiStr};
 use xaynet_core::crypto::{ByteObject, PublicSigningKey, SecretSigningKey, SigningKeyPair};
 use zeroize::Zeroize;
 
-use super::{
-    ERR_CRYPTO_PUBLIC_KEY,
-    ERR_CRYPTO_SECRET_KEY,
-    ERR_INVALID_URL,
-    ERR_NULLPTR,
-    ERR_SETTINGS_KEYS,
-    ERR_SETTINGS_SCALAR,
-    ERR_SETTINGS_URL,
-    OK,
-};
+use super::{fail, fail_nullptr, ReturnCode};
 use crate::{Settings, SettingsError};
 
 mod pv {
@@ -25,8 +19,8 @@ mod pv {
 ///
 /// # Return value
 ///
-/// - [`OK`] on success
-/// - [`ERR_NULLPTR`] if `buf` is NULL
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `buf` is NULL
 ///
 /// # Safety
 ///
@@ -43,12 +37,12 @@ mod pv {
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_settings_destroy(settings: *mut Settings) -> c_int {
+pub unsafe extern "C" fn xaynet_ffi_settings_destroy(settings: *mut Settings) -> ReturnCode {
     if settings.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr("xaynet_ffi_settings_destroy", "`settings`");
     }
     pv::_xaynet_ffi_settings_destroy(settings);
-    OK
+    ReturnCode::Ok
 }
 
 /// Create new [`Settings`] and return a pointer to it.
@@ -67,8 +61,8 @@ pub unsafe extern "C" fn xaynet_ffi_settings_new() -> *mut Settings {
 ///
 /// # Return value
 ///
-/// - [`OK`] if successful
-/// - [`ERR_NULLPTR`] if `settings` is `NULL`
+/// - `OK` if successful
+/// - `ERR_NULLPTR` if `settings` is `NULL`
 ///
 /// # Safety
 ///
@@ -84,13 +78,13 @@ pub unsafe extern "C" fn xaynet_ffi_settings_new() -> *mut Settings {
 pub unsafe extern "C" fn xaynet_ffi_settings_set_scalar(
     settings: *mut Settings,
     scalar: c_double,
-) -> c_int {
+) -> ReturnCode {
     match unsafe { settings.as_mut() } {
         Some(settings) => {
             settings.set_scalar(scalar);
-            OK
+            ReturnCode::Ok
         }
-        None => ERR_NULLPTR,
+        None => fail_nullptr("xaynet_ffi_settings_set_scalar", "`settings`"),
     }
 }
 
@@ -98,9 +92,9 @@ pub unsafe extern "C" fn xaynet_ffi_settings_set_scalar(
 ///
 /// # Return value
 ///
-/// - [`OK`] if successful
-/// - [`ERR_INVALID_URL`] if `url` is not a valid string
-/// - [`ERR_NULLPTR`] if `settings` is `NULL`
+/// - `OK` if successful
+/// - `ERR_INVALID_URL` if `url` is not a valid string
+/// - `ERR_NULLPTR` if `settings` is `NULL`
 ///
 /// # Safety
 ///
@@ -116,17 +110,271 @@ pub unsafe extern "C" fn xaynet_ffi_settings_set_scalar(
 pub unsafe extern "C" fn xaynet_ffi_settings_set_url(
     settings: *mut Settings,
     url: FfiStr,
-) -> c_int {
+) -> ReturnCode {
     let url = match url.as_opt_str() {
         Some(url) => url,
-        None => return ERR_INVALID_URL,
+        None => {
+            return fail(
+                ReturnCode::ErrInvalidUrl,
+                "xaynet_ffi_settings_set_url",
+                "`url` is NULL or not valid UTF-8",
+            )
+        }
     };
     match unsafe { settings.as_mut() } {
         Some(settings) => {
             settings.set_url(url.to_string());
-            OK
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_url", "`settings`"),
+    }
+}
+
+/// Set the maximum number of bytes the participant may send and receive per day. Once
+/// the budget is exhausted, the participant declines to start new network operations
+/// until the next day. If `budget` is `0`, the data usage is not limited.
+///
+/// # Return value
+///
+/// - `OK` if successful
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_daily_data_budget(
+    settings: *mut Settings,
+    budget: u64,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            settings.set_daily_data_budget(if budget == 0 { None } else { Some(budget) });
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_daily_data_budget", "`settings`"),
+    }
+}
+
+/// Set whether the participant pauses before sending the sum2 message, until the global
+/// mask it aggregated is confirmed with [`xaynet_ffi_participant_confirm_sum2()`]. If
+/// `confirm` is `0`, the participant doesn't pause, which is the default.
+///
+/// [`xaynet_ffi_participant_confirm_sum2()`]: crate::ffi::xaynet_ffi_participant_confirm_sum2
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_confirm_sum2(
+    settings: *mut Settings,
+    confirm: c_int,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            settings.set_confirm_sum2(confirm != 0);
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_confirm_sum2", "`settings`"),
+    }
+}
+
+/// Set whether the participant pauses when it is selected for a task, until the user
+/// consents to take part in it with [`xaynet_ffi_participant_grant_consent()`] or
+/// refuses with [`xaynet_ffi_participant_deny_consent()`]. If `require` is `0`, the
+/// participant doesn't pause, which is the default.
+///
+/// [`xaynet_ffi_participant_grant_consent()`]: crate::ffi::xaynet_ffi_participant_grant_consent
+/// [`xaynet_ffi_participant_deny_consent()`]: crate::ffi::xaynet_ffi_participant_deny_consent
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_require_consent(
+    settings: *mut Settings,
+    require: c_int,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            settings.set_require_consent(require != 0);
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_require_consent", "`settings`"),
+    }
+}
+
+/// Set for how many seconds the participant waits for the consent of the user before
+/// abandoning the task. If `secs` is `0`, the participant waits until the end of the
+/// round, which is the default.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_consent_timeout(
+    settings: *mut Settings,
+    secs: u64,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            let timeout = if secs == 0 {
+                None
+            } else {
+                Some(Duration::from_secs(secs))
+            };
+            settings.set_consent_timeout(timeout);
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_consent_timeout", "`settings`"),
+    }
+}
+
+/// Set for how many seconds an idle connection to the coordinator is kept open, such that
+/// the next request doesn't have to open a new one. The default is 90 seconds.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_pool_idle_timeout(
+    settings: *mut Settings,
+    secs: u64,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            settings.set_pool_idle_timeout(Duration::from_secs(secs));
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_pool_idle_timeout", "`settings`"),
+    }
+}
+
+/// Set the maximum number of events that the participant keeps until it processes them.
+/// The participant processes the events emitted by its internal state machine during
+/// every [`xaynet_ffi_participant_tick()`]. If more events are emitted in a single tick,
+/// the oldest informational ones are dropped (see
+/// [`xaynet_ffi_participant_dropped_events()`]), the events that report the progress
+/// through the tasks or request an action are never dropped. The default is 256, and `0`
+/// is treated as `1`. The maximum is part of the saved state of the participant.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_participant_tick()`]: crate::ffi::xaynet_ffi_participant_tick
+/// [`xaynet_ffi_participant_dropped_events()`]: crate::ffi::xaynet_ffi_participant_dropped_events
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_max_pending_events(
+    settings: *mut Settings,
+    max: c_uint,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            settings.set_max_pending_events(max as usize);
+            ReturnCode::Ok
+        }
+        None => fail_nullptr("xaynet_ffi_settings_set_max_pending_events", "`settings`"),
+    }
+}
+
+/// Set the maximum number of completed rounds the participant keeps in its history (see
+/// [`xaynet_ffi_participant_history_len()`]). When the history is full, the oldest round
+/// is dropped. The default is 50, and `0` disables the history.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `settings` is `NULL`
+///
+/// # Safety
+///
+/// When calling this method, you have to ensure that *either* the pointer is NULL *or*
+/// all of the following is true:
+/// - The pointer must be properly [aligned].
+/// - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+///   documentation.
+///
+/// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+/// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+/// [`xaynet_ffi_participant_history_len()`]: crate::ffi::xaynet_ffi_participant_history_len
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_settings_set_max_history_len(
+    settings: *mut Settings,
+    max: c_uint,
+) -> ReturnCode {
+    match unsafe { settings.as_mut() } {
+        Some(settings) => {
+            settings.set_max_history_len(max as usize);
+            ReturnCode::Ok
         }
-        None => ERR_NULLPTR,
+        None => fail_nullptr("xaynet_ffi_settings_set_max_history_len", "`settings`"),
     }
 }
 
@@ -171,8 +419,8 @@ pub unsafe extern "C" fn xaynet_ffi_generate_key_pair() -> *const KeyPair {
 ///
 /// # Return value
 ///
-/// - [`ERR_NULLPTR`] is `key_pair` is NULL
-/// - [`OK`] otherwise
+/// - `ERR_NULLPTR` is `key_pair` is NULL
+/// - `OK` otherwise
 ///
 /// # Safety
 ///
@@ -185,9 +433,9 @@ pub unsafe extern "C" fn xaynet_ffi_generate_key_pair() -> *const KeyPair {
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_forget_key_pair(key_pair: *const KeyPair) -> c_int {
+pub unsafe extern "C" fn xaynet_ffi_forget_key_pair(key_pair: *const KeyPair) -> ReturnCode {
     if key_pair.is_null() {
-        return ERR_NULLPTR;
+        return fail_nullptr("xaynet_ffi_forget_key_pair", "`key_pair`");
     }
     let key_pair = unsafe { Box::from_raw(key_pair as *mut KeyPair) };
     // IMPORTANT: we need to free the ByteBuffer memory, since it does
@@ -195,17 +443,17 @@ pub unsafe extern "C" fn xaynet_ffi_forget_key_pair(key_pair: *const KeyPair) ->
     // for the secret key.
     key_pair.secret.destroy_into_vec().zeroize();
     key_pair.public.destroy_into_vec();
-    OK
+    ReturnCode::Ok
 }
 
 /// Set participant signing keys.
 ///
 /// # Return value
 ///
-/// - [`OK`] if successful
-/// - [`ERR_NULLPTR`] if `settings` or `key_pair` is `NULL`
-/// - [`ERR_CRYPTO_PUBLIC_KEY`] if the given `key_pair` contains an invalid public key
-/// - [`ERR_CRYPTO_SECRET_KEY`] if the given `key_pair` contains an invalid secret key
+/// - `OK` if successful
+/// - `ERR_NULLPTR` if `settings` or `key_pair` is `NULL`
+/// - `ERR_CRYPTO_PUBLIC_KEY` if the given `key_pair` contains an invalid public key
+/// - `ERR_CRYPTO_SECRET_KEY` if the given `key_pair` contains an invalid secret key
 ///
 /// # Safety
 ///
@@ -221,30 +469,46 @@ pub unsafe extern "C" fn xaynet_ffi_forget_key_pair(key_pair: *const KeyPair) ->
 pub unsafe extern "C" fn xaynet_ffi_settings_set_keys(
     settings: *mut Settings,
     key_pair: *const KeyPair,
-) -> c_int {
+) -> ReturnCode {
     let key_pair = match unsafe { key_pair.as_ref() } {
         Some(key_pair) => key_pair,
-        None => return ERR_NULLPTR,
+        None => return fail_nullptr("xaynet_ffi_settings_set_keys", "`key_pair`"),
     };
 
     let secret_slice = key_pair.secret.as_slice();
     if secret_slice.len() != SecretSigningKey::LENGTH {
-        return ERR_CRYPTO_SECRET_KEY;
+        return fail(
+            ReturnCode::ErrCryptoSecretKey,
+            "xaynet_ffi_settings_set_keys",
+            format_args!(
+                "invalid secret key length {} (expected {})",
+                secret_slice.len(),
+                SecretSigningKey::LENGTH
+            ),
+        );
     }
     let secret = SecretSigningKey::from_slice_unchecked(secret_slice);
 
     let public_slice = key_pair.public.as_slice();
     if public_slice.len() != PublicSigningKey::LENGTH {
-        return ERR_CRYPTO_PUBLIC_KEY;
+        return fail(
+            ReturnCode::ErrCryptoPublicKey,
+            "xaynet_ffi_settings_set_keys",
+            format_args!(
+                "invalid public key length {} (expected {})",
+                public_slice.len(),
+                PublicSigningKey::LENGTH
+            ),
+        );
     }
     let public = PublicSigningKey::from_slice_unchecked(public_slice);
 
     match unsafe { settings.as_mut() } {
         Some(settings) => {
             settings.set_keys(SigningKeyPair { public, secret });
-            OK
+            ReturnCode::Ok
         }
-        None => ERR_NULLPTR,
+        None => fail_nullptr("xaynet_ffi_settings_set_keys", "`settings`"),
     }
 }
 
@@ -253,10 +517,10 @@ pub unsafe extern "C" fn xaynet_ffi_settings_set_keys(
 ///
 /// # Return value
 ///
-/// - [`OK`] on success
-/// - [`ERR_SETTINGS_URL`] if the URL has not been set
-/// - [`ERR_SETTINGS_KEYS`] if the signing keys have not been set
-/// - [`ERR_SETTINGS_SCALAR`] if the scalar is out of bounds
+/// - `OK` on success
+/// - `ERR_SETTINGS_URL` if the URL has not been set
+/// - `ERR_SETTINGS_KEYS` if the signing keys have not been set
+/// - `ERR_SETTINGS_SCALAR` if the scalar is out of bounds
 ///
 /// # Safety
 ///
@@ -271,14 +535,19 @@ pub unsafe extern "C" fn xaynet_ffi_settings_set_keys(
 /// [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
 /// [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
 #[no_mangle]
-pub unsafe extern "C" fn xaynet_ffi_check_settings(settings: *const Settings) -> c_int {
+pub unsafe extern "C" fn xaynet_ffi_check_settings(settings: *const Settings) -> ReturnCode {
     match unsafe { settings.as_ref() } {
         Some(settings) => match settings.check() {
-            Ok(()) => OK,
-            Err(SettingsError::MissingUrl) => ERR_SETTINGS_URL,
-            Err(SettingsError::MissingKeys) => ERR_SETTINGS_KEYS,
-            Err(SettingsError::OutOfScalarRange(_)) => ERR_SETTINGS_SCALAR,
+            Ok(()) => ReturnCode::Ok,
+            Err(err) => {
+                let code = match err {
+                    SettingsError::MissingUrl => ReturnCode::ErrSettingsUrl,
+                    SettingsError::MissingKeys => ReturnCode::ErrSettingsKeys,
+                    SettingsError::OutOfScalarRange(_) => ReturnCode::ErrSettingsScalar,
+                };
+                fail(code, "xaynet_ffi_check_settings", err)
+            }
         },
-        None => ERR_NULLPTR,
+        None => fail_nullptr("xaynet_ffi_check_settings", "`settings`"),
     }
 }
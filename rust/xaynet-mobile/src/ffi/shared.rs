@@ -0,0 +1,853 @@
+use std::{
+    os::raw::{c_int, c_uchar, c_uint, c_void},
+    ptr,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use ffi_support::{ByteBuffer, FfiStr};
+
+#[cfg(doc)]
+use super::xaynet_ffi_participant_save;
+use super::{
+    advance,
+    fail_nullptr,
+    xaynet_ffi_participant_confirm_sum2,
+    xaynet_ffi_participant_consent_request,
+    xaynet_ffi_participant_data_usage,
+    xaynet_ffi_participant_deny_consent,
+    xaynet_ffi_participant_dropped_events,
+    xaynet_ffi_participant_global_model,
+    xaynet_ffi_participant_global_model_len,
+    xaynet_ffi_participant_grant_consent,
+    xaynet_ffi_participant_history_entry,
+    xaynet_ffi_participant_history_len,
+    xaynet_ffi_participant_local_model_config,
+    xaynet_ffi_participant_new,
+    xaynet_ffi_participant_next_wakeup,
+    xaynet_ffi_participant_pending_work,
+    xaynet_ffi_participant_prepare_for_shutdown,
+    xaynet_ffi_participant_public_key,
+    xaynet_ffi_participant_public_stats,
+    xaynet_ffi_participant_restore,
+    xaynet_ffi_participant_rotate_keys,
+    xaynet_ffi_participant_rounds_observed,
+    xaynet_ffi_participant_set_daily_data_budget,
+    xaynet_ffi_participant_set_model,
+    xaynet_ffi_participant_set_progress_callback,
+    xaynet_ffi_participant_set_sparse_model,
+    xaynet_ffi_participant_set_state_changed_callback,
+    xaynet_ffi_participant_sum2_mask,
+    xaynet_ffi_participant_task,
+    xaynet_ffi_participant_tick,
+    xaynet_ffi_participant_update_tls_identity,
+    xaynet_ffi_participant_update_tls_root_certificate,
+    LocalModelConfig,
+    PublicStats,
+    ReturnCode,
+    RoundRecord,
+    WakeupRecommendation,
+};
+use crate::{Participant, Settings};
+
+/// A participant that can be used from several threads.
+///
+/// The `xaynet_ffi_participant_*` functions must not be called concurrently on the same
+/// participant. A shared participant is instead used with the `xaynet_ffi_shared_*`
+/// functions, which lock the participant internally, and it is passed to them as a
+/// `const` pointer. Each `xaynet_ffi_shared_participant_*` function behaves like the
+/// `xaynet_ffi_participant_*` function of the same name, and the last error messages
+/// name the latter.
+///
+/// A shared participant is reference counted: [`xaynet_ffi_shared_participant_clone()`]
+/// creates a new reference, for instance for another thread, and each reference must be
+/// destroyed with [`xaynet_ffi_shared_participant_destroy()`].
+///
+/// The callbacks of a shared participant are invoked on the thread that ticks the
+/// participant, without holding the lock, so they can use the participant. Hence the
+/// user data of the callbacks must be safe to use from any thread that ticks the
+/// participant.
+pub struct SharedParticipant(Mutex<Participant>);
+
+impl SharedParticipant {
+    /// Lock the participant. The participant stays usable if a thread panicked while
+    /// holding the lock.
+    fn lock(&self) -> MutexGuard<'_, Participant> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Share the participant behind the given pointer.
+unsafe fn share(participant: *mut Participant) -> *const SharedParticipant {
+    if participant.is_null() {
+        return ptr::null();
+    }
+    let participant = unsafe { *Box::from_raw(participant) };
+    Arc::into_raw(Arc::new(SharedParticipant(Mutex::new(participant))))
+}
+
+/// Call `f` with a pointer to the locked participant, or with a NULL pointer if `shared`
+/// is NULL. The lock is held until `f` returns.
+unsafe fn with_participant<R>(
+    shared: *const SharedParticipant,
+    f: impl FnOnce(*mut Participant) -> R,
+) -> R {
+    match unsafe { shared.as_ref() } {
+        Some(shared) => f(&mut *shared.lock()),
+        None => f(ptr::null_mut()),
+    }
+}
+
+/// Instantiate a new shared participant with the given settings, see
+/// [`xaynet_ffi_participant_new()`]. The participant must be destroyed with
+/// [`xaynet_ffi_shared_participant_destroy()`].
+///
+/// # Return value
+///
+/// - a NULL pointer if `settings` is NULL or if the participant creation failed
+/// - a valid pointer to a [`SharedParticipant`] otherwise
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_participant_new()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_new(
+    settings: *const Settings,
+) -> *const SharedParticipant {
+    unsafe { share(xaynet_ffi_participant_new(settings)) }
+}
+
+/// Restore a shared participant from a buffer that contained its serialized state, see
+/// [`xaynet_ffi_participant_restore()`]. The participant must be destroyed with
+/// [`xaynet_ffi_shared_participant_destroy()`].
+///
+/// # Return value
+///
+/// - a NULL pointer on failure
+/// - a valid pointer to a [`SharedParticipant`] otherwise
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_participant_restore()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_restore(
+    url: FfiStr,
+    buffer: *const ByteBuffer,
+) -> *const SharedParticipant {
+    unsafe { share(xaynet_ffi_participant_restore(url, buffer)) }
+}
+
+/// Create a new reference to the given shared participant. The reference must be
+/// destroyed with [`xaynet_ffi_shared_participant_destroy()`].
+///
+/// # Return value
+///
+/// - a NULL pointer if `participant` is NULL
+/// - the new reference otherwise, which is the same pointer as `participant`
+///
+/// # Safety
+///
+/// `participant` must be NULL or a reference to a shared participant that has not been
+/// destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_clone(
+    participant: *const SharedParticipant,
+) -> *const SharedParticipant {
+    if participant.is_null() {
+        fail_nullptr("xaynet_ffi_shared_participant_clone", "`participant`");
+        return ptr::null();
+    }
+    unsafe { Arc::increment_strong_count(participant) };
+    participant
+}
+
+/// Destroy a reference to the given shared participant. The participant is destroyed
+/// with its last reference.
+///
+/// # Return value
+///
+/// - `OK` on success
+/// - `ERR_NULLPTR` if `participant` is NULL
+///
+/// # Safety
+///
+/// 1. `participant` must be NULL or a reference to a shared participant that has not
+///    been destroyed.
+/// 2. After destroying the reference, the pointer must not be used through this
+///    reference.
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_destroy(
+    participant: *const SharedParticipant,
+) -> ReturnCode {
+    if participant.is_null() {
+        return fail_nullptr("xaynet_ffi_shared_participant_destroy", "`participant`");
+    }
+    drop(unsafe { Arc::from_raw(participant) });
+    ReturnCode::Ok
+}
+
+/// Drive the shared participant internal state machine, see
+/// [`xaynet_ffi_participant_tick()`]. The callbacks are invoked on the current thread,
+/// after the participant is unlocked.
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_tick(
+    participant: *const SharedParticipant,
+) -> c_int {
+    let participant = match unsafe { participant.as_ref() } {
+        Some(participant) => participant,
+        None => return unsafe { xaynet_ffi_participant_tick(ptr::null_mut()) },
+    };
+    let (flags, mut notifications) = advance(&mut participant.lock());
+    notifications.notify();
+    notifications.put_back(&mut participant.lock());
+    flags
+}
+
+/// See [`xaynet_ffi_participant_rounds_observed()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_rounds_observed(
+    participant: *const SharedParticipant,
+) -> c_uint {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_rounds_observed(p)) }
+}
+
+/// See [`xaynet_ffi_participant_public_key()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_public_key()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_public_key(
+    participant: *const SharedParticipant,
+    buffer: *mut c_uchar,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_public_key(p, buffer)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_rotate_keys()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_rotate_keys()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_rotate_keys(
+    participant: *const SharedParticipant,
+    buffer: *mut c_uchar,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_rotate_keys(p, buffer)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_update_tls_root_certificate()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_update_tls_root_certificate()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_update_tls_root_certificate(
+    participant: *const SharedParticipant,
+    buffer: *const ByteBuffer,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_update_tls_root_certificate(p, buffer)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_update_tls_identity()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_update_tls_identity()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_update_tls_identity(
+    participant: *const SharedParticipant,
+    buffer: *const ByteBuffer,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_update_tls_identity(p, buffer)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_history_len()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_history_len(
+    participant: *const SharedParticipant,
+) -> c_uint {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_history_len(p)) }
+}
+
+/// See [`xaynet_ffi_participant_history_entry()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_history_entry()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_history_entry(
+    participant: *const SharedParticipant,
+    index: c_uint,
+    record: *mut RoundRecord,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_history_entry(p, index, record)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_task()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_task(
+    participant: *const SharedParticipant,
+) -> c_int {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_task(p)) }
+}
+
+/// See [`xaynet_ffi_participant_pending_work()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_pending_work(
+    participant: *const SharedParticipant,
+) -> c_int {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_pending_work(p)) }
+}
+
+/// See [`xaynet_ffi_participant_set_state_changed_callback()`]. The callback may be
+/// invoked on any thread that ticks the participant.
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_set_state_changed_callback()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_set_state_changed_callback(
+    participant: *const SharedParticipant,
+    callback: Option<unsafe extern "C" fn(user_data: *mut c_void, change: c_int)>,
+    user_data: *mut c_void,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_set_state_changed_callback(p, callback, user_data)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_set_progress_callback()`]. The callback may be invoked
+/// on any thread that ticks the participant.
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_set_progress_callback()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_set_progress_callback(
+    participant: *const SharedParticipant,
+    callback: Option<unsafe extern "C" fn(user_data: *mut c_void, progress: c_int, value: u64)>,
+    user_data: *mut c_void,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_set_progress_callback(p, callback, user_data)
+        })
+    }
+}
+
+/// Save the shared participant without destroying it, see
+/// [`xaynet_ffi_participant_prepare_for_shutdown()`]. Unlike
+/// [`xaynet_ffi_participant_save()`], the participant stays usable by the other
+/// references.
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`]. The `ByteBuffer` created by this
+/// function must be destroyed with [`xaynet_ffi_byte_buffer_destroy`].
+///
+/// [`xaynet_ffi_byte_buffer_destroy`]: crate::ffi::xaynet_ffi_byte_buffer_destroy
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_save(
+    participant: *const SharedParticipant,
+) -> *const ByteBuffer {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_prepare_for_shutdown(p)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_set_model()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_set_model()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_set_model(
+    participant: *const SharedParticipant,
+    buffer: *const c_void,
+    data_type: c_uchar,
+    len: c_uint,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_set_model(p, buffer, data_type, len)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_set_sparse_model()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_set_sparse_model()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_set_sparse_model(
+    participant: *const SharedParticipant,
+    indices: *const c_uint,
+    values: *const c_void,
+    data_type: c_uchar,
+    len: c_uint,
+    total_len: c_uint,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_set_sparse_model(p, indices, values, data_type, len, total_len)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_global_model_len()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_global_model_len()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_global_model_len(
+    participant: *const SharedParticipant,
+    len: *mut c_uint,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_global_model_len(p, len)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_global_model()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_global_model()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_global_model(
+    participant: *const SharedParticipant,
+    buffer: *mut c_void,
+    data_type: c_uchar,
+    len: c_uint,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_global_model(p, buffer, data_type, len)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_local_model_config()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_local_model_config()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_local_model_config(
+    participant: *const SharedParticipant,
+) -> *mut LocalModelConfig {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_local_model_config(p)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_next_wakeup()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_next_wakeup()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_next_wakeup(
+    participant: *const SharedParticipant,
+    recommendation: *mut WakeupRecommendation,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_next_wakeup(p, recommendation)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_public_stats()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_public_stats()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_public_stats(
+    participant: *const SharedParticipant,
+    stats: *mut PublicStats,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_public_stats(p, stats)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_data_usage()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_data_usage()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_data_usage(
+    participant: *const SharedParticipant,
+    used: *mut u64,
+) -> ReturnCode {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_data_usage(p, used)) }
+}
+
+/// See [`xaynet_ffi_participant_dropped_events()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_dropped_events()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_dropped_events(
+    participant: *const SharedParticipant,
+    dropped: *mut u64,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_dropped_events(p, dropped)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_set_daily_data_budget()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_set_daily_data_budget(
+    participant: *const SharedParticipant,
+    budget: u64,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_set_daily_data_budget(p, budget)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_sum2_mask()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_sum2_mask()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_sum2_mask(
+    participant: *const SharedParticipant,
+    buffer: *mut c_uchar,
+    len: *mut c_uint,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_sum2_mask(p, buffer, len)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_confirm_sum2()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_confirm_sum2(
+    participant: *const SharedParticipant,
+) -> ReturnCode {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_confirm_sum2(p)) }
+}
+
+/// See [`xaynet_ffi_participant_consent_request()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`] and
+/// [`xaynet_ffi_participant_consent_request()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_consent_request(
+    participant: *const SharedParticipant,
+    task: *mut c_int,
+    upload_bytes: *mut u64,
+) -> ReturnCode {
+    unsafe {
+        with_participant(participant, |p| {
+            xaynet_ffi_participant_consent_request(p, task, upload_bytes)
+        })
+    }
+}
+
+/// See [`xaynet_ffi_participant_grant_consent()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_grant_consent(
+    participant: *const SharedParticipant,
+) -> ReturnCode {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_grant_consent(p)) }
+}
+
+/// See [`xaynet_ffi_participant_deny_consent()`].
+///
+/// # Safety
+///
+/// See [`xaynet_ffi_shared_participant_clone()`].
+#[no_mangle]
+pub unsafe extern "C" fn xaynet_ffi_shared_participant_deny_consent(
+    participant: *const SharedParticipant,
+) -> ReturnCode {
+    unsafe { with_participant(participant, |p| xaynet_ffi_participant_deny_consent(p)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use xaynet_core::crypto::{ByteObject, SigningKeyPair};
+
+    use super::*;
+    use crate::ffi::{
+        xaynet_ffi_byte_buffer_destroy,
+        ReturnCode,
+        PARTICIPANT_TASK_NONE,
+        PUBLIC_KEY_LENGTH,
+    };
+
+    fn shared_participant() -> *const SharedParticipant {
+        sodiumoxide::init().unwrap();
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url("http://localhost:1".to_string());
+        let participant = unsafe { xaynet_ffi_shared_participant_new(&settings) };
+        assert!(!participant.is_null());
+        participant
+    }
+
+    #[test]
+    fn test_concurrent_tick_and_save() {
+        let participant = shared_participant();
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                // raw pointers are not `Send`, each thread gets its own reference
+                let participant = unsafe { xaynet_ffi_shared_participant_clone(participant) };
+                let participant = participant as usize;
+                thread::spawn(move || {
+                    let participant = participant as *const SharedParticipant;
+                    for _ in 0..5 {
+                        if i % 2 == 0 {
+                            let flags = unsafe { xaynet_ffi_shared_participant_tick(participant) };
+                            assert_ne!(flags & PARTICIPANT_TASK_NONE, 0);
+                        } else {
+                            let state = unsafe { xaynet_ffi_shared_participant_save(participant) };
+                            assert!(!state.is_null());
+                            let state_ref = unsafe { &*state };
+                            assert!(Participant::check_state(state_ref.as_slice()).is_ok());
+                            assert_eq!(
+                                unsafe { xaynet_ffi_byte_buffer_destroy(state) },
+                                ReturnCode::Ok
+                            );
+                        }
+                    }
+                    assert_eq!(
+                        unsafe { xaynet_ffi_shared_participant_destroy(participant) },
+                        ReturnCode::Ok
+                    );
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // the participant outlives the references of the threads
+        let flags = unsafe { xaynet_ffi_shared_participant_tick(participant) };
+        assert_ne!(flags & PARTICIPANT_TASK_NONE, 0);
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_destroy(participant) },
+            ReturnCode::Ok
+        );
+    }
+
+    #[test]
+    fn test_public_key() {
+        sodiumoxide::init().unwrap();
+        let keys = SigningKeyPair::generate();
+        let mut settings = Settings::new();
+        settings.set_keys(keys.clone());
+        settings.set_url("http://localhost:1".to_string());
+        let participant = unsafe { xaynet_ffi_shared_participant_new(&settings) };
+        assert!(!participant.is_null());
+
+        let mut buffer = vec![0; PUBLIC_KEY_LENGTH as usize];
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_public_key(participant, buffer.as_mut_ptr()) },
+            ReturnCode::Ok
+        );
+        assert_eq!(buffer, keys.public.as_slice());
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_public_key(participant, ptr::null_mut()) },
+            ReturnCode::ErrNullptr
+        );
+
+        // the new key is applied when the next round starts
+        let mut new_key = vec![0; PUBLIC_KEY_LENGTH as usize];
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_rotate_keys(participant, new_key.as_mut_ptr()) },
+            ReturnCode::Ok
+        );
+        assert_ne!(new_key, keys.public.as_slice());
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_public_key(participant, buffer.as_mut_ptr()) },
+            ReturnCode::Ok
+        );
+        assert_eq!(buffer, keys.public.as_slice());
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_rotate_keys(participant, ptr::null_mut()) },
+            ReturnCode::ErrNullptr
+        );
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_destroy(participant) },
+            ReturnCode::Ok
+        );
+    }
+
+    #[test]
+    fn test_update_tls_certificates() {
+        let participant = shared_participant();
+
+        let root = ByteBuffer::from_vec(include_bytes!("../../tests/data/root.pem").to_vec());
+        assert_eq!(
+            unsafe {
+                xaynet_ffi_shared_participant_update_tls_root_certificate(participant, &root)
+            },
+            ReturnCode::Ok
+        );
+        let identity = ByteBuffer::from_vec(include_bytes!("../../tests/data/client.pem").to_vec());
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_update_tls_identity(participant, &identity) },
+            ReturnCode::Ok
+        );
+        // a certificate without its private key is not an identity
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_update_tls_identity(participant, &root) },
+            ReturnCode::ErrTlsCertificate
+        );
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_update_tls_identity(participant, ptr::null()) },
+            ReturnCode::ErrNullptr
+        );
+        root.destroy();
+        identity.destroy();
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_destroy(participant) },
+            ReturnCode::Ok
+        );
+    }
+
+    #[test]
+    fn test_null_shared_participant() {
+        let null = ptr::null();
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_tick(null) },
+            ReturnCode::ErrNullptr as c_int
+        );
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_task(null) },
+            -(ReturnCode::ErrNullptr as c_int)
+        );
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_pending_work(null) },
+            -(ReturnCode::ErrNullptr as c_int)
+        );
+        let mut buffer = [0; PUBLIC_KEY_LENGTH as usize];
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_public_key(null, buffer.as_mut_ptr()) },
+            ReturnCode::ErrNullptr
+        );
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_rotate_keys(null, buffer.as_mut_ptr()) },
+            ReturnCode::ErrNullptr
+        );
+        let mut stats = PublicStats {
+            round_id: 0,
+            completed_rounds: 0,
+            participants: 0,
+            last_model_update: 0,
+        };
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_public_stats(null, &mut stats) },
+            ReturnCode::ErrNullptr
+        );
+        assert!(unsafe { xaynet_ffi_shared_participant_save(null) }.is_null());
+        assert!(unsafe { xaynet_ffi_shared_participant_clone(null) }.is_null());
+        assert_eq!(
+            unsafe { xaynet_ffi_shared_participant_destroy(null) },
+            ReturnCode::ErrNullptr
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! History of the rounds a participant observed.
+//!
+//! The participant keeps a short record of every round it observed, so that the app can
+//! show the user how often the device took part in the training. The history only holds
+//! a hash of the round seeds and the outcome of the rounds, and it is part of the saved
+//! state of the participant.
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+use xaynet_core::{common::RoundSeed, crypto::ByteObject};
+
+use crate::participant::Task;
+
+/// The default maximum number of rounds a participant keeps in its history.
+pub const DEFAULT_MAX_HISTORY_LEN: usize = 50;
+
+/// The outcome of a round for a participant (see [`Participant::history()`]).
+///
+/// [`Participant::history()`]: crate::Participant::history
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundRecord {
+    /// The SHA-256 hash of the round seed, which identifies the round.
+    pub round_seed_hash: [u8; 32],
+    /// The task the participant was selected for in the round.
+    pub task: Task,
+    /// Whether the participant successfully sent a message to the coordinator in the
+    /// round.
+    pub message_sent: bool,
+}
+
+impl RoundRecord {
+    fn new(round_seed: &RoundSeed) -> Self {
+        Self {
+            round_seed_hash: sha256::hash(round_seed.as_slice()).0,
+            task: Task::None,
+            message_sent: false,
+        }
+    }
+}
+
+/// The records of the last completed rounds, oldest first, and of the round in progress.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RoundHistory {
+    /// The maximum number of completed rounds that are kept.
+    max_len: usize,
+    /// The completed rounds.
+    records: Vec<RoundRecord>,
+    /// The round in progress, if any.
+    current: Option<RoundRecord>,
+}
+
+impl Default for RoundHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_HISTORY_LEN)
+    }
+}
+
+impl RoundHistory {
+    /// Create an empty history that keeps at most `max_len` completed rounds.
+    pub(crate) fn new(max_len: usize) -> Self {
+        Self {
+            max_len,
+            records: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// Return the completed rounds, oldest first.
+    pub(crate) fn records(&self) -> &[RoundRecord] {
+        &self.records
+    }
+
+    /// Complete the round in progress, if any, and start recording the round with the
+    /// given seed. The oldest rounds are dropped if the history is full.
+    ///
+    /// A restored participant is notified again of the round it was saved in, which is
+    /// still in progress.
+    pub(crate) fn start_round(&mut self, round_seed: &RoundSeed) {
+        let record = RoundRecord::new(round_seed);
+        if self.current.map(|current| current.round_seed_hash) == Some(record.round_seed_hash) {
+            return;
+        }
+        if let Some(record) = self.current.replace(record) {
+            self.records.push(record);
+        }
+        let excess = self.records.len().saturating_sub(self.max_len);
+        self.records.drain(..excess);
+    }
+
+    /// Record that the participant has been selected for `task` in the round in
+    /// progress.
+    pub(crate) fn select(&mut self, task: Task) {
+        if let Some(record) = self.current.as_mut() {
+            record.task = task;
+        }
+    }
+
+    /// Record that the participant sent a message in the round in progress.
+    pub(crate) fn message_sent(&mut self) {
+        if let Some(record) = self.current.as_mut() {
+            record.message_sent = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_history() {
+        let seeds = (0..4).map(|_| RoundSeed::generate()).collect::<Vec<_>>();
+        let mut history = RoundHistory::new(2);
+        // nothing is recorded before the first round starts
+        history.select(Task::Sum);
+        history.message_sent();
+
+        history.start_round(&seeds[0]);
+        assert!(history.records().is_empty());
+        history.select(Task::Update);
+        history.message_sent();
+        // the round in progress is not completed again
+        history.start_round(&seeds[0]);
+        assert!(history.records().is_empty());
+        history.start_round(&seeds[1]);
+        assert_eq!(
+            history.records(),
+            &[RoundRecord {
+                round_seed_hash: sha256::hash(seeds[0].as_slice()).0,
+                task: Task::Update,
+                message_sent: true,
+            }]
+        );
+
+        // the oldest round is dropped
+        history.select(Task::Sum);
+        history.start_round(&seeds[2]);
+        history.start_round(&seeds[3]);
+        let records = history.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].round_seed_hash,
+            RoundRecord::new(&seeds[1]).round_seed_hash
+        );
+        assert_eq!(
+            (records[0].task, records[0].message_sent),
+            (Task::Sum, false)
+        );
+        assert_eq!(records[1], RoundRecord::new(&seeds[2]));
+    }
+
+    #[test]
+    fn test_round_history_disabled() {
+        let mut history = RoundHistory::new(0);
+        history.start_round(&RoundSeed::generate());
+        history.start_round(&RoundSeed::generate());
+        assert!(history.records().is_empty());
+    }
+}
@@ -42,14 +42,37 @@ extern crate async_trait;
 #[macro_use]
 extern crate tracing;
 
+mod data_usage;
+mod history;
 mod participant;
 mod settings;
+mod wakeup;
 pub use self::{
-    participant::{Event, Events, InitError, Notifier, Participant, Task},
+    data_usage::{DataUsage, MeteredClient},
+    history::{RoundRecord, DEFAULT_MAX_HISTORY_LEN},
+    participant::{
+        migrate_state,
+        Event,
+        Events,
+        InitError,
+        MigrateError,
+        ModelLengthError,
+        Notifier,
+        Participant,
+        ProgressObserver,
+        SparseModelError,
+        StateChange,
+        StateObserver,
+        StateVersion,
+        Task,
+        DEFAULT_MAX_PENDING_EVENTS,
+    },
     settings::{Settings, SettingsError},
+    wakeup::{WakeupReason, WakeupRecommendation},
 };
+pub use xaynet_sdk::WorkClass;
 pub mod ffi;
 
-mod reqwest_client;
-pub(crate) use reqwest_client::new_client;
-pub use reqwest_client::ClientError;
+mod client;
+pub use client::ClientError;
+pub(crate) use client::{new_client, CoordinatorClient, TlsReloader};
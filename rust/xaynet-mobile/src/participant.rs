@@ -1,28 +1,43 @@
 //! Participant implementation
-use std::{convert::TryInto, sync::Arc};
+use std::{collections::VecDeque, convert::TryInto, mem::discriminant, sync::Arc, time::Duration};
 
-use futures::future::FutureExt;
+use bincode::Options;
+
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
 use thiserror::Error;
-use tokio::{
-    runtime::Runtime,
-    sync::{mpsc, Mutex},
+use tokio::{runtime::Runtime, sync::Mutex};
+use xaynet_core::{
+    common::{PublicStats, RoundMetadata, RoundParameters},
+    crypto::SigningKeyPair,
+    mask::{InvalidSparseModelError, MaskObject, Model, SparseModel},
+    message::ToBytes,
+    ParticipantPublicKey,
 };
-use xaynet_core::mask::Model;
 use xaynet_sdk::{
-    client::Client,
+    client::DEFAULT_POOL_IDLE_TIMEOUT,
+    legacy,
+    CircuitState,
+    ConsentRequest,
     LocalModelConfig,
     ModelStore,
     Notify,
     SerializableState,
     StateMachine,
     TransitionOutcome,
+    WorkClass,
     XaynetClient,
 };
 
 use crate::{
+    data_usage::{DataUsage, SavedDataUsage},
+    history::{RoundHistory, RoundRecord},
     new_client,
     settings::{Settings, SettingsError},
+    wakeup::{WakeupRecommendation, WakeupSignals},
     ClientError,
+    CoordinatorClient,
+    TlsReloader,
 };
 
 /// Event emitted by the participant internal state machine as it advances through the
@@ -39,47 +54,190 @@ pub enum Event {
     /// Event emitted when the participant should load its model. This only happens if
     /// the participant has been selected for the update task
     LoadModel,
+    /// Event emitted when the participant aggregated the global mask in the sum2 phase
+    /// and awaits its confirmation. This only happens if the participant is configured
+    /// to do so (see [`Settings::set_confirm_sum2()`])
+    Sum2MaskReady,
+    /// Event emitted when the participant declines a network operation because its daily
+    /// data budget is exhausted. It is emitted at most once a day.
+    DataBudgetExceeded,
+    /// Event emitted when the coordinator rejected an update message because the
+    /// participant exceeded its update participation quota
+    QuotaExceeded,
+    /// Event emitted when the participant has been selected for a task and awaits the
+    /// consent of the user to take part in it. This only happens if the participant is
+    /// configured to do so (see [`Settings::set_require_consent()`])
+    AwaitingConsent(ConsentRequest),
+    /// Event emitted when the participant abandoned its task because there is not enough
+    /// time remaining to complete it before the end of the phase. This only happens if
+    /// the participant is configured to do so (see [`Settings::set_deadline_margin()`])
+    InsufficientTime,
+    /// Event emitted when the participant abandoned the sum task because its ephemeral
+    /// keys don't match the ephemeral public key it sent to the coordinator, so it could
+    /// not decrypt the mask seeds of the update participants
+    EphemeralKeysMismatch,
+    /// Event emitted when the participant sent a message to the coordinator, with the
+    /// number of bytes sent. A message that is split in several parts is notified once
+    /// per part.
+    MessageSent(usize),
+    /// Event emitted when events have been dropped because more events than the
+    /// maximum number of pending events (see [`Settings::set_max_pending_events()`])
+    /// were emitted before the participant processed them. The oldest informational
+    /// events are dropped, the task events are never dropped (see
+    /// [`Event::is_informational()`]). It is emitted once per overflow, with the number
+    /// of dropped events.
+    EventsDropped { count: u64 },
+    /// Event emitted when [`Participant::round_metadata()`] observes that the
+    /// coordinator entered a new phase, with the metadata of the current round. Apps can
+    /// use the phase deadlines to schedule the calls to [`Participant::tick()`].
+    PhaseChanged(RoundMetadata),
+    /// Event emitted when [`Participant::set_model()`] is called with a model whose length
+    /// differs from the length of the models that were set before, e.g. because an app
+    /// update changed the model architecture. The cached global model and the local model
+    /// that has not been sent yet, if any, are discarded.
+    ModelShapeChanged { previous: usize, current: usize },
+    /// Event emitted when [`Participant::set_model()`] is called with a model whose length
+    /// differs from the model length announced by the coordinator for the current round.
+    /// The model is not sent to the coordinator.
+    ModelLengthMismatch { expected: usize, found: usize },
+    /// Event emitted right after [`Event::NewRound`] when the global model didn't change
+    /// since the previous round. The cached global model is kept and
+    /// [`Participant::new_global_model()`] is not set, so that the model is not
+    /// downloaded again.
+    ModelUnchanged,
+}
+
+impl Event {
+    /// Whether the event is only informational. The other events are task events, which
+    /// report the progress of the participant through its tasks or request an action of
+    /// the app, such as [`Event::Sum2MaskReady`] and [`Event::AwaitingConsent`]. Only
+    /// informational events are dropped when more events are emitted than the
+    /// participant keeps (see [`Settings::set_max_pending_events()`]).
+    pub fn is_informational(&self) -> bool {
+        match self {
+            Event::Update
+            | Event::Sum
+            | Event::Idle
+            | Event::NewRound
+            | Event::LoadModel
+            | Event::Sum2MaskReady
+            | Event::AwaitingConsent(_) => false,
+            Event::DataBudgetExceeded
+            | Event::QuotaExceeded
+            | Event::InsufficientTime
+            | Event::EphemeralKeysMismatch
+            | Event::MessageSent(_)
+            | Event::EventsDropped { .. }
+            | Event::PhaseChanged(_)
+            | Event::ModelShapeChanged { .. }
+            | Event::ModelLengthMismatch { .. }
+            | Event::ModelUnchanged => true,
+        }
+    }
+}
+
+/// Default maximum number of events that the participant keeps until it processes them.
+/// See [`Settings::set_max_pending_events()`].
+pub const DEFAULT_MAX_PENDING_EVENTS: usize = 256;
+
+/// Events emitted by the participant internal state machine that the participant has not
+/// processed yet. When the queue is full, the oldest informational event is dropped to
+/// make room for the new one. If the queue only holds task events, a new informational
+/// event is dropped, and a new task event is kept beyond the capacity of the queue.
+struct EventQueue {
+    /// Pending events, from the oldest to the newest
+    events: VecDeque<Event>,
+    /// Maximum number of pending events
+    capacity: usize,
+    /// Number of events dropped since the last [`Event::EventsDropped`] was emitted
+    unreported: u64,
+    /// Number of events dropped since the queue was created
+    dropped: u64,
+}
+
+impl EventQueue {
+    /// Count an event that was dropped because the queue was full.
+    fn count_dropped(&mut self) {
+        if self.unreported == 0 {
+            warn!(
+                "event queue full ({} events), dropping the oldest informational events",
+                self.capacity
+            );
+        }
+        self.unreported += 1;
+        self.dropped += 1;
+    }
 }
 
 /// Event sender that is passed to the participant internal state machine for emitting
 /// notification
-pub struct Notifier(mpsc::Sender<Event>);
+#[derive(Clone)]
+pub struct Notifier(Arc<std::sync::Mutex<EventQueue>>);
 impl Notifier {
-    fn notify(&mut self, event: Event) {
-        if let Err(e) = self.0.try_send(event) {
-            warn!("failed to notify participant: {}", e);
+    pub(crate) fn notify(&mut self, event: Event) {
+        // UNWRAP_SAFE: the lock is never held across a panic.
+        let mut queue = self.0.lock().unwrap();
+        if queue.events.len() >= queue.capacity {
+            // the task events are never dropped, if the queue only holds task events a new
+            // task event is kept beyond the capacity
+            match queue.events.iter().position(Event::is_informational) {
+                Some(index) => {
+                    queue.events.remove(index);
+                    queue.count_dropped();
+                }
+                None if event.is_informational() => {
+                    queue.count_dropped();
+                    return;
+                }
+                None => {}
+            }
         }
+        queue.events.push_back(event);
     }
 }
 
 /// A receiver for events emitted by the participant internal state machine
-pub struct Events(mpsc::Receiver<Event>);
+pub struct Events(Arc<std::sync::Mutex<EventQueue>>);
 
 impl Events {
-    /// Create a new event sender and receiver.
-    fn new() -> (Self, Notifier) {
-        let (tx, rx) = mpsc::channel(10);
-        (Self(rx), Notifier(tx))
+    /// Create a new event sender and receiver. At most `capacity` events are kept
+    /// until they are received.
+    pub(crate) fn new(capacity: usize) -> (Self, Notifier) {
+        let queue = Arc::new(std::sync::Mutex::new(EventQueue {
+            events: VecDeque::new(),
+            capacity: capacity.max(1),
+            unreported: 0,
+            dropped: 0,
+        }));
+        (Self(queue.clone()), Notifier(queue))
     }
 
     /// Pop the next event. If no event has been received, return `None`.
-    fn next(&mut self) -> Option<Event> {
-        // Note `try_recv` (tokio 0.2.x) or `recv().now_or_never()` (tokio 1.x)
-        // has an implementation bug where previously sent messages may not be
-        // available immediately.
-        // Related issue: https://github.com/tokio-rs/tokio/issues/3350
-        // However, that should not be an issue for us.
-        let next = self.0.recv().now_or_never()?;
-        if next.is_none() {
-            // if next is `none`, the channel is closed
-            // This can happen if:
-            //  1. the state machine crashed. In that case it's OK to crash.
-            //  2. `next` was called whereas the state machine was
-            //     dropped, which is an error. So crashing is OK as
-            //     well.
-            panic!("notifier dropped")
-        }
-        next
+    ///
+    /// If events have been dropped because the queue was full, an
+    /// [`Event::EventsDropped`] is returned before the remaining events, once per
+    /// overflow.
+    pub(crate) fn next(&mut self) -> Option<Event> {
+        // UNWRAP_SAFE: the lock is never held across a panic.
+        let mut queue = self.0.lock().unwrap();
+        if queue.unreported > 0 {
+            let count = std::mem::take(&mut queue.unreported);
+            return Some(Event::EventsDropped { count });
+        }
+        queue.events.pop_front()
+    }
+
+    /// Return the number of events that have been dropped because the queue was full.
+    pub(crate) fn dropped(&self) -> u64 {
+        // UNWRAP_SAFE: the lock is never held across a panic.
+        self.0.lock().unwrap().dropped
+    }
+
+    /// Return the maximum number of informational events that are kept until they are
+    /// received.
+    pub(crate) fn capacity(&self) -> usize {
+        // UNWRAP_SAFE: the lock is never held across a panic.
+        self.0.lock().unwrap().capacity
     }
 }
 
@@ -99,6 +257,24 @@ impl Notify for Notifier {
     fn idle(&mut self) {
         self.notify(Event::Idle)
     }
+    fn sum2_mask_ready(&mut self) {
+        self.notify(Event::Sum2MaskReady)
+    }
+    fn quota_exceeded(&mut self) {
+        self.notify(Event::QuotaExceeded)
+    }
+    fn awaiting_consent(&mut self, request: ConsentRequest) {
+        self.notify(Event::AwaitingConsent(request))
+    }
+    fn insufficient_time(&mut self) {
+        self.notify(Event::InsufficientTime)
+    }
+    fn ephemeral_keys_mismatch(&mut self) {
+        self.notify(Event::EphemeralKeysMismatch)
+    }
+    fn model_unchanged(&mut self) {
+        self.notify(Event::ModelUnchanged)
+    }
 }
 
 /// A store shared between by the participant and its internal state machine. When the
@@ -125,7 +301,7 @@ impl ModelStore for Store {
 }
 
 /// Represent the participant current task
-#[derive(Clone, Debug, Copy)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Task {
     /// The participant is taking part in the sum task
     Sum,
@@ -135,6 +311,63 @@ pub enum Task {
     None,
 }
 
+/// Reason why the persistent state of a participant changed. See [`StateObserver`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateChange {
+    /// The participant internal state machine made progress.
+    Progress,
+    /// The circuit breaker opened, became half-open or closed.
+    CircuitBreaker,
+}
+
+/// An observer of the changes of the persistent state of a [`Participant`]. It can be
+/// used to save the participant whenever needed, instead of after every call to
+/// [`Participant::tick()`].
+///
+/// Setting a model with [`Participant::set_model()`] doesn't notify the observer, even
+/// though the model is part of the state saved afterwards.
+pub trait StateObserver: Send {
+    /// Called at the end of the [`Participant::tick()`] that changed the persistent
+    /// state, once per change.
+    fn state_changed(&mut self, change: StateChange);
+}
+
+/// An observer of the progress of a [`Participant`] through the PET protocol. It can be
+/// used to report the progress of a round to the user, instead of guessing it from the
+/// flags of the participant after every call to [`Participant::tick()`].
+///
+/// The observer is not part of the persistent state of the participant, so it must be
+/// set again after the participant is restored. The restored participant then notifies
+/// the phase it resumes in, on the next tick.
+pub trait ProgressObserver: Send {
+    /// Called at the end of the [`Participant::tick()`] during which the participant
+    /// entered the phase of the given task. [`Task::None`] means that the participant is
+    /// done with its task or has not been selected for any task.
+    fn on_phase_entered(&mut self, task: Task);
+
+    /// Called at the end of the [`Participant::tick()`] during which the participant
+    /// sent a message of `bytes` bytes to the coordinator. A message that is split in
+    /// several parts is notified once per part.
+    fn on_message_sent(&mut self, bytes: usize);
+}
+
+/// Progress of the participant that the [`ProgressObserver`] has not been notified of
+/// yet.
+pub(crate) enum Progress {
+    PhaseEntered(Task),
+    MessageSent(usize),
+}
+
+impl Progress {
+    /// Notify the given observer of the progress.
+    pub(crate) fn notify(self, observer: &mut dyn ProgressObserver) {
+        match self {
+            Progress::PhaseEntered(task) => observer.on_phase_entered(task),
+            Progress::MessageSent(bytes) => observer.on_message_sent(bytes),
+        }
+    }
+}
+
 /// A participant. It embeds an internal state machine that executes the PET
 /// protocol. However, it is the caller's responsibility to drive this state machine by
 /// calling [`Participant::tick()`], and to take action when the participant state
@@ -144,13 +377,19 @@ pub struct Participant {
     state_machine: Option<StateMachine>,
     /// Receiver for the events emitted by the state machine
     events: Events,
+    /// Sender for the events emitted by the state machine
+    notifier: Notifier,
     /// Model store where the participant should load its model, when
     /// `self.should_set_model` is `true`.
     store: Store,
     /// Async runtime to execute the state machine
     runtime: Runtime,
     /// Xaynet client
-    client: Client<reqwest::Client>,
+    client: CoordinatorClient,
+    /// Rotates the TLS certificates of the Xaynet client
+    tls: TlsReloader,
+    /// Data consumed by the Xaynet client
+    data_usage: DataUsage,
     /// Whether the participant state changed after the last call to
     /// [`Participant::tick()`]
     made_progress: bool,
@@ -158,8 +397,32 @@ pub struct Participant {
     should_set_model: bool,
     /// Whether a new global model is available.
     new_global_model: bool,
+    /// The global model fetched in the current round, if any
+    global_model: Option<Model>,
+    /// The last round metadata fetched from the coordinator, if any
+    round_metadata: Option<RoundMetadata>,
+    /// The number of weights of the models the app sets, which is part of the
+    /// participant state
+    model_len: Option<usize>,
+    /// Whether the last model that was set has a different length than the previous ones
+    model_shape_changed: bool,
     /// The participant current task
     task: Task,
+    /// The rounds the participant observed, which is part of the participant state
+    history: RoundHistory,
+    /// Observer of the changes of the participant persistent state
+    state_observer: Option<Box<dyn StateObserver>>,
+    /// Incremented every time the state observer is set or removed
+    state_observer_version: u64,
+    /// Changes of the participant persistent state that the observer has not been
+    /// notified of yet
+    state_changes: Vec<StateChange>,
+    /// Observer of the progress of the participant through the PET protocol
+    progress_observer: Option<Box<dyn ProgressObserver>>,
+    /// Incremented every time the progress observer is set or removed
+    progress_observer_version: u64,
+    /// Progress of the participant that the observer has not been notified of yet
+    progress: Vec<Progress>,
 }
 
 /// Error that can occur when instantiating a new [`Participant`], either with
@@ -168,58 +431,292 @@ pub struct Participant {
 pub enum InitError {
     #[error("failed to deserialize the participant state {:?}", _0)]
     Deserialization(#[from] Box<bincode::ErrorKind>),
+    #[error("the participant state is corrupt")]
+    Corrupt,
     #[error("failed to initialize the participant runtime {:?}", _0)]
     Runtime(std::io::Error),
     #[error("failed to initialize HTTP client {:?}", _0)]
     Client(#[from] ClientError),
     #[error("invalid participant settings {:?}", _0)]
     InvalidSettings(#[from] SettingsError),
+    #[error(
+        "unsupported participant state version {} (expected at most {})",
+        _0,
+        StateVersion::CURRENT as u8
+    )]
+    UnsupportedVersion(u8),
+}
+
+impl From<MigrateError> for InitError {
+    fn from(error: MigrateError) -> Self {
+        match error {
+            MigrateError::Corrupt => InitError::Corrupt,
+            MigrateError::Deserialization(error) => InitError::Deserialization(error),
+            MigrateError::UnsupportedVersion(version) => InitError::UnsupportedVersion(version),
+        }
+    }
+}
+
+/// Error that can occur when migrating a serialized participant state with
+/// [`migrate_state()`].
+#[derive(Error, Debug)]
+pub enum MigrateError {
+    #[error("the participant state is corrupt")]
+    Corrupt,
+    #[error("failed to deserialize the participant state {:?}", _0)]
+    Deserialization(Box<bincode::ErrorKind>),
+    #[error(
+        "unsupported participant state version {} (expected at most {})",
+        _0,
+        StateVersion::CURRENT as u8
+    )]
+    UnsupportedVersion(u8),
+}
+
+/// Version of the format of a serialized participant state (see [`Participant::save()`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StateVersion {
+    /// The state is not prefixed by a version. This is the format of the states that
+    /// were saved before the format was versioned. The states saved by the first
+    /// releases don't end with a checksum either.
+    Unversioned = 0,
+    /// The state is prefixed by its version.
+    V1 = 1,
+    /// The state is prefixed by its version and by the length of the models that were
+    /// set, if any.
+    V2 = 2,
+    /// The state is prefixed by its version, by the length of the models that were set,
+    /// if any, and by the history of the rounds.
+    V3 = 3,
+    /// Like [`StateVersion::V3`], but the state of the state machine also records the
+    /// keys of the next round and the seed of the current round.
+    V4 = 4,
+    /// Like [`StateVersion::V4`], but the history of the rounds is followed by the data
+    /// usage of the participant.
+    V5 = 5,
+    /// Like [`StateVersion::V5`], but the state of the sum2 phase also records the
+    /// ephemeral public key that was sent in the sum message.
+    V6 = 6,
+    /// Like [`StateVersion::V6`], but the round parameters also record the id of the
+    /// training plan of the round.
+    V7 = 7,
+    /// Like [`StateVersion::V7`], but the round parameters also record the sample count by
+    /// which the sample counts are normalized.
+    V8 = 8,
+    /// Like [`StateVersion::V8`], but also records the maximum number of pending events.
+    V9 = 9,
+}
+
+impl StateVersion {
+    /// The version of the states saved by this build.
+    pub const CURRENT: Self = StateVersion::V9;
+}
+
+/// Error that can occur when setting a sparse model with
+/// [`Participant::set_sparse_model()`].
+#[derive(Error, Debug)]
+pub enum SparseModelError {
+    #[error("the sparse model has {} indices but {} values", indices, values)]
+    LengthMismatch { indices: usize, values: usize },
+    #[error(
+        "weight index {} is out of range for a model of {} weights",
+        index,
+        len
+    )]
+    IndexOutOfRange { index: usize, len: usize },
+    #[error("weight index {} is duplicated", _0)]
+    DuplicateIndex(usize),
+    #[error("{}", _0)]
+    ModelLength(#[from] ModelLengthError),
+    #[error(
+        "the last known global model has {} weights instead of {}",
+        found,
+        expected
+    )]
+    GlobalModelLength { expected: usize, found: usize },
+}
+
+impl From<InvalidSparseModelError> for SparseModelError {
+    fn from(error: InvalidSparseModelError) -> Self {
+        match error {
+            InvalidSparseModelError::LengthMismatch { indices, values } => {
+                Self::LengthMismatch { indices, values }
+            }
+            InvalidSparseModelError::IndexOutOfRange { index, len } => {
+                Self::IndexOutOfRange { index, len }
+            }
+            InvalidSparseModelError::DuplicateIndex(index) => Self::DuplicateIndex(index),
+            InvalidSparseModelError::BaseLength { expected, found } => {
+                Self::GlobalModelLength { expected, found }
+            }
+        }
+    }
+}
+
+/// Error that occurs when setting a model whose length differs from the model length
+/// announced by the coordinator for the current round (see [`Participant::set_model()`]).
+#[derive(Error, Debug)]
+#[error(
+    "the coordinator expects models of {} weights but the model has {}",
+    expected,
+    found
+)]
+pub struct ModelLengthError {
+    pub expected: usize,
+    pub found: usize,
 }
 
 #[derive(Error, Debug)]
 #[error("failed to fetch global model: {}", self.0)]
 pub struct GetGlobalModelError(xaynet_sdk::client::ClientError);
 
+#[derive(Error, Debug)]
+#[error("failed to fetch round metadata: {}", self.0)]
+pub struct GetRoundMetadataError(xaynet_sdk::client::ClientError);
+
+#[derive(Error, Debug)]
+#[error("failed to fetch public stats: {}", self.0)]
+pub struct GetPublicStatsError(xaynet_sdk::client::ClientError);
+
 impl Participant {
     /// Create a new participant with the given settings
     pub fn new(settings: Settings) -> Result<Self, InitError> {
+        let data_usage = DataUsage::new(settings.daily_data_budget());
+        let pool_idle_timeout = settings.pool_idle_timeout();
+        let (events, notifier) = Events::new(settings.max_pending_events());
+        let history = RoundHistory::new(settings.max_history_len());
         let (url, pet_settings) = settings.try_into()?;
-        let client = new_client(url.as_str(), None, None)?;
-        let (events, notifier) = Events::new();
+        let (client, tls) = new_client(
+            url.as_str(),
+            data_usage.clone(),
+            notifier.clone(),
+            None,
+            None,
+            pool_idle_timeout,
+        )?;
         let store = Store::new();
-        let state_machine =
-            StateMachine::new(pet_settings, client.clone(), store.clone(), notifier);
-        Self::init(state_machine, client, events, store)
+        let state_machine = StateMachine::new(
+            pet_settings,
+            client.clone(),
+            store.clone(),
+            notifier.clone(),
+        );
+        Self::init(
+            state_machine,
+            client,
+            tls,
+            data_usage,
+            events,
+            notifier,
+            store,
+            None,
+            history,
+        )
     }
 
     /// Restore a participant from it's serialized state. The coordinator client that
     /// the participant uses internally is not part of the participant state, so the
     /// `url` is used to instantiate a new one.
+    ///
+    /// The restored participant has no deadline margin until one is set with
+    /// [`Participant::set_deadline_margin()`]. The events that have not been processed
+    /// yet are not part of the participant state, but the maximum number of pending
+    /// events is (see [`Settings::set_max_pending_events()`]). A state saved by an older
+    /// build keeps at most [`DEFAULT_MAX_PENDING_EVENTS`] pending events.
+    ///
+    /// The data usage is part of the participant state, so the daily data budget still
+    /// applies to the restored participant. The time spent while the state was saved is
+    /// estimated with the wall clock, and the usage is reset if the daily window elapsed
+    /// in the meantime. A state saved by an older build has no daily data budget.
+    ///
+    /// The serialized state ends with a checksum. If the state has been truncated or
+    /// altered, [`InitError::Corrupt`] is returned. The states saved by the first
+    /// releases have no checksum, they are restored if they can be deserialized. A state
+    /// that was saved by an older build is migrated to the current format (see
+    /// [`migrate_state()`]), and [`InitError::UnsupportedVersion`] is returned if the
+    /// state was saved by a newer build.
+    ///
+    /// The state records the length of the models that were set (see
+    /// [`Participant::set_model()`]). If no model was set, e.g. because the state was
+    /// saved by an older build, the expected length is the model length of the round the
+    /// state was saved in. The state also records the history of the rounds (see
+    /// [`Participant::history()`]), which is empty for a state saved by an older build.
     pub fn restore(state: &[u8], url: &str) -> Result<Self, InitError> {
-        let state: SerializableState = bincode::deserialize(state)?;
-        let (events, notifier) = Events::new();
+        let (state, model_len, history, data_usage, max_pending_events) = deserialize_state(state)?;
+        let (events, notifier) = Events::new(max_pending_events);
         let store = Store::new();
-        let client = new_client(url, None, None)?;
-        let state_machine = StateMachine::restore(state, client.clone(), store.clone(), notifier);
-        Self::init(state_machine, client, events, store)
+        let data_usage = DataUsage::restore(data_usage);
+        let (client, tls) = new_client(
+            url,
+            data_usage.clone(),
+            notifier.clone(),
+            None,
+            None,
+            DEFAULT_POOL_IDLE_TIMEOUT,
+        )?;
+        let state_machine =
+            StateMachine::restore(state, client.clone(), store.clone(), notifier.clone());
+        let model_len = model_len.or_else(|| {
+            state_machine
+                .round_params()
+                .map(|params| params.model_length)
+        });
+        Self::init(
+            state_machine,
+            client,
+            tls,
+            data_usage,
+            events,
+            notifier,
+            store,
+            model_len,
+            history,
+        )
+    }
+
+    /// Check whether a participant can be restored from the given serialized state,
+    /// without restoring it.
+    pub fn check_state(state: &[u8]) -> Result<(), InitError> {
+        deserialize_state(state).map(|_| ())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn init(
         state_machine: StateMachine,
-        client: Client<reqwest::Client>,
+        client: CoordinatorClient,
+        tls: TlsReloader,
+        data_usage: DataUsage,
         events: Events,
+        notifier: Notifier,
         store: Store,
+        model_len: Option<usize>,
+        history: RoundHistory,
     ) -> Result<Self, InitError> {
         let mut participant = Self {
             runtime: Self::runtime()?,
             state_machine: Some(state_machine),
             events,
+            notifier,
             store,
             client,
+            tls,
+            data_usage,
             task: Task::None,
+            history,
             made_progress: true,
             should_set_model: false,
             new_global_model: false,
+            global_model: None,
+            round_metadata: None,
+            model_len,
+            model_shape_changed: false,
+            state_observer: None,
+            state_observer_version: 0,
+            state_changes: Vec::new(),
+            progress_observer: None,
+            progress_observer_version: 0,
+            progress: Vec::new(),
         };
         participant.process_events();
         Ok(participant)
@@ -233,10 +730,66 @@ impl Participant {
     }
 
     /// Serialize the participant state and return the corresponding buffer.
-    pub fn save(self) -> Vec<u8> {
+    ///
+    /// A model that was set with [`Participant::set_model()`] but not yet processed by
+    /// a tick is part of the state: a participant restored from it doesn't ask for the
+    /// model again.
+    pub fn save(mut self) -> Vec<u8> {
+        let state = self.save_state();
+        serialize_state(
+            &state,
+            self.model_len,
+            &self.history,
+            self.data_usage.save(),
+            self.events.capacity(),
+        )
+    }
+
+    /// Checkpoint the participant before the app is shut down, and return the
+    /// corresponding buffer.
+    ///
+    /// The participant internal state machine only advances within
+    /// [`Participant::tick()`], so between two ticks it is always at a step boundary
+    /// from which a restored participant can resume. Unlike [`Participant::save()`],
+    /// this method doesn't consume the participant, so that it can keep running if the
+    /// app is not shut down after all.
+    ///
+    /// Like for [`Participant::save()`], a model that was set with
+    /// [`Participant::set_model()`] but not yet processed by a tick is part of the
+    /// checkpoint.
+    pub fn prepare_for_shutdown(&mut self) -> Vec<u8> {
+        let state = self.save_state();
+        let checkpoint = serialize_state(
+            &state,
+            self.model_len,
+            &self.history,
+            self.data_usage.save(),
+            self.events.capacity(),
+        );
+        self.state_machine = Some(StateMachine::restore(
+            state,
+            self.client.clone(),
+            self.store.clone(),
+            self.notifier.clone(),
+        ));
+        self.process_events();
+        checkpoint
+    }
+
+    /// Take the state machine and save its state, with the model that is waiting in the
+    /// store, if any.
+    fn save_state(&mut self) -> SerializableState {
         // UNWRAP_SAFE: the state machine is always set.
-        let state_machine = self.state_machine.unwrap().save();
-        bincode::serialize(&state_machine).unwrap()
+        let mut state = self.state_machine.take().unwrap().save();
+        let Self {
+            ref mut runtime,
+            ref store,
+            ..
+        } = self;
+        if let Some(model) = runtime.block_on(async { store.0.lock().await.take() }) {
+            state.stage_local_model(model);
+        }
+        state
     }
 
     /// Drive the participant internal state machine.
@@ -249,7 +802,31 @@ impl Participant {
     ///   [`Participant::task()`]
     /// - whether the participant should load its model into the store by calling
     ///   [`Participant::should_set_model()`]
+    ///
+    /// If the participant made progress through the PET protocol, the
+    /// [`ProgressObserver`], if any, is notified before this method returns. Then, if the
+    /// persistent state changed, the [`StateObserver`], if any, is notified.
     pub fn tick(&mut self) {
+        self.advance();
+        if let Some((mut observer, progress, version)) = self.take_progress() {
+            for progress in progress {
+                progress.notify(observer.as_mut());
+            }
+            self.put_back_progress_observer(observer, version);
+        }
+        if let Some((mut observer, changes, version)) = self.take_state_changes() {
+            for change in changes {
+                observer.state_changed(change);
+            }
+            self.put_back_state_observer(observer, version);
+        }
+    }
+
+    /// Drive the participant internal state machine and record its progress and the
+    /// changes of the persistent state, without notifying the [`ProgressObserver`] and
+    /// the [`StateObserver`].
+    pub(crate) fn advance(&mut self) {
+        let circuit_state = discriminant(&self.circuit_state());
         // UNWRAP_SAFE: the state machine is always set.
         let state_machine = self.state_machine.take().unwrap();
         let outcome = self
@@ -266,30 +843,177 @@ impl Participant {
             }
         };
         self.process_events();
+
+        if self.made_progress {
+            self.state_changes.push(StateChange::Progress);
+        }
+        if discriminant(&self.circuit_state()) != circuit_state {
+            self.state_changes.push(StateChange::CircuitBreaker);
+        }
+    }
+
+    /// Set the observer that is notified when the persistent state of the participant
+    /// changes. If `observer` is `None`, the current observer is removed.
+    pub fn set_state_observer(&mut self, observer: Option<Box<dyn StateObserver>>) {
+        self.state_observer = observer;
+        self.state_observer_version += 1;
+    }
+
+    /// Take the state observer, the state changes it must be notified of and the
+    /// version of the observer, if any. The observer is taken out of the participant,
+    /// so that the participant can be used while notifying it.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn take_state_changes(
+        &mut self,
+    ) -> Option<(Box<dyn StateObserver>, Vec<StateChange>, u64)> {
+        let changes = std::mem::take(&mut self.state_changes);
+        if changes.is_empty() {
+            return None;
+        }
+        let version = self.state_observer_version;
+        self.state_observer
+            .take()
+            .map(|observer| (observer, changes, version))
+    }
+
+    /// Put back the state observer taken by [`Participant::take_state_changes()`],
+    /// unless the observer has been set or removed in the meantime.
+    pub(crate) fn put_back_state_observer(
+        &mut self,
+        observer: Box<dyn StateObserver>,
+        version: u64,
+    ) {
+        if self.state_observer_version == version {
+            self.state_observer = Some(observer);
+        }
+    }
+
+    /// Set the observer that is notified of the progress of the participant through the
+    /// PET protocol. If `observer` is `None`, the current observer is removed.
+    pub fn set_progress_observer(&mut self, observer: Option<Box<dyn ProgressObserver>>) {
+        self.progress_observer = observer;
+        self.progress_observer_version += 1;
+    }
+
+    /// Take the progress observer, the progress it must be notified of and the version
+    /// of the observer, if any. The observer is taken out of the participant, so that
+    /// the participant can be used while notifying it.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn take_progress(
+        &mut self,
+    ) -> Option<(Box<dyn ProgressObserver>, Vec<Progress>, u64)> {
+        let progress = std::mem::take(&mut self.progress);
+        if progress.is_empty() {
+            return None;
+        }
+        let version = self.progress_observer_version;
+        self.progress_observer
+            .take()
+            .map(|observer| (observer, progress, version))
+    }
+
+    /// Put back the progress observer taken by [`Participant::take_progress()`], unless
+    /// the observer has been set or removed in the meantime.
+    pub(crate) fn put_back_progress_observer(
+        &mut self,
+        observer: Box<dyn ProgressObserver>,
+        version: u64,
+    ) {
+        if self.progress_observer_version == version {
+            self.progress_observer = Some(observer);
+        }
     }
 
     fn process_events(&mut self) {
+        // the global model is invalidated once all the events are processed, unless the
+        // new round reports that it didn't change
+        let mut global_model_outdated = false;
         loop {
             match self.events.next() {
                 Some(Event::Idle) => {
                     self.task = Task::None;
+                    self.progress.push(Progress::PhaseEntered(Task::None));
                 }
                 Some(Event::Update) => {
                     self.task = Task::Update;
+                    self.history.select(Task::Update);
+                    self.progress.push(Progress::PhaseEntered(Task::Update));
                 }
                 Some(Event::Sum) => {
                     self.task = Task::Sum;
+                    self.history.select(Task::Sum);
+                    self.progress.push(Progress::PhaseEntered(Task::Sum));
                 }
                 Some(Event::NewRound) => {
+                    if let Some(params) = self.round_params() {
+                        self.history.start_round(&params.seed);
+                    }
                     self.should_set_model = false;
-                    self.new_global_model = true;
+                    global_model_outdated = true;
                 }
                 Some(Event::LoadModel) => {
                     self.should_set_model = true;
                 }
+                Some(Event::Sum2MaskReady) => {
+                    info!("global mask aggregated, awaiting its confirmation");
+                }
+                Some(Event::DataBudgetExceeded) => {
+                    info!("daily data budget exceeded, network operations are paused");
+                }
+                Some(Event::QuotaExceeded) => {
+                    info!("update participation quota exceeded, backing off from the update task");
+                }
+                Some(Event::AwaitingConsent(request)) => {
+                    info!(
+                        "awaiting consent for the {:?} task, estimated upload: {} bytes",
+                        request.task, request.upload_bytes
+                    );
+                }
+                Some(Event::InsufficientTime) => {
+                    info!("not enough time remaining in the phase, abandoning the task");
+                }
+                Some(Event::EphemeralKeysMismatch) => {
+                    error!("the ephemeral keys don't match the sent public key, abandoning the sum task");
+                }
+                Some(Event::MessageSent(bytes)) => {
+                    self.history.message_sent();
+                    self.progress.push(Progress::MessageSent(bytes));
+                }
+                Some(Event::EventsDropped { count }) => {
+                    warn!("{} events were dropped before they were processed", count);
+                }
+                Some(Event::PhaseChanged(metadata)) => {
+                    info!(
+                        "coordinator entered the {} phase of round {}",
+                        metadata.phase, metadata.round_id
+                    );
+                    self.round_metadata = Some(metadata);
+                }
+                Some(Event::ModelShapeChanged { previous, current }) => {
+                    info!(
+                        "the model length changed from {} to {} weights, discarding the cached models",
+                        previous, current
+                    );
+                    self.model_shape_changed = true;
+                    self.global_model = None;
+                }
+                Some(Event::ModelLengthMismatch { expected, found }) => {
+                    warn!(
+                        "the coordinator expects models of {} weights but the model has {}, the model is not sent",
+                        expected, found
+                    );
+                }
+                Some(Event::ModelUnchanged) => {
+                    info!("round completed, the global model didn't change");
+                    global_model_outdated = false;
+                }
                 None => break,
             }
         }
+        if global_model_outdated {
+            self.new_global_model = true;
+            self.global_model = None;
+        }
     }
 
     /// Check whether the participant internal state machine made progress while
@@ -305,20 +1029,328 @@ impl Participant {
         self.should_set_model
     }
 
+    /// Check whether the last model that was set with [`Participant::set_model()`] has a
+    /// different length than the models that were set before. If this method returns
+    /// `true`, the global model that was cached for the previous length has been
+    /// discarded, as well as the local model that was not sent yet, if any.
+    pub fn model_shape_changed(&self) -> bool {
+        self.model_shape_changed
+    }
+
     /// Check whether a new global model is available. If this method returns `true`, the
     /// caller can call [`Participant::global_model()`] to fetch the new global model.
     pub fn new_global_model(&self) -> bool {
         self.new_global_model
     }
 
+    /// Check whether the participant awaits the confirmation of the global mask it
+    /// aggregated in the sum2 phase. If this method returns `true`, the caller can
+    /// inspect the mask with [`Participant::sum2_mask()`] and should make sure to call
+    /// [`Participant::confirm_sum2()`] at some point.
+    pub fn awaiting_sum2_confirmation(&self) -> bool {
+        // the state machine is the only source of truth, so that the participant stops
+        // waiting as soon as it leaves the sum2 phase, whether or not the mask was confirmed
+        self.sum2_mask_object().is_some()
+    }
+
+    fn sum2_mask_object(&self) -> Option<&MaskObject> {
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_ref().unwrap();
+        state_machine.sum2_mask()
+    }
+
+    /// Return the serialized global mask that the participant aggregated in the sum2
+    /// phase, if it awaits its confirmation.
+    pub fn sum2_mask(&self) -> Option<Vec<u8>> {
+        self.sum2_mask_object().map(|mask| {
+            let mut bytes = vec![0; mask.buffer_length()];
+            mask.to_bytes(&mut bytes);
+            bytes
+        })
+    }
+
+    /// Confirm the global mask that the participant aggregated in the sum2 phase, so
+    /// that the sum2 message is sent on the next tick. Return `false` if the
+    /// participant doesn't await confirmation.
+    pub fn confirm_sum2(&mut self) -> bool {
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_mut().unwrap();
+        state_machine.confirm_sum2()
+    }
+
+    /// Return the request for the consent of the user to take part in the task the
+    /// participant has been selected for, if it awaits the consent (see
+    /// [`Settings::set_require_consent()`]). If this method returns a request, the caller
+    /// should make sure to call either [`Participant::grant_consent()`] or
+    /// [`Participant::deny_consent()`] at some point, unless a consent timeout is set.
+    pub fn consent_request(&self) -> Option<ConsentRequest> {
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_ref().unwrap();
+        state_machine.consent_request()
+    }
+
+    /// Grant the consent of the user to take part in the task the participant has been
+    /// selected for, so that the task starts on the next tick. Return `false` if the
+    /// participant doesn't await the consent.
+    pub fn grant_consent(&mut self) -> bool {
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine.as_mut().unwrap().grant_consent()
+    }
+
+    /// Deny the consent of the user to take part in the task the participant has been
+    /// selected for, so that the task is abandoned on the next tick. Return `false` if
+    /// the participant doesn't await the consent.
+    pub fn deny_consent(&mut self) -> bool {
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine.as_mut().unwrap().deny_consent()
+    }
+
     /// Return the participant current task
     pub fn task(&self) -> Task {
         self.task
     }
 
+    /// Return the rounds the participant observed and completed, oldest first. A round is
+    /// completed when the coordinator starts the next one, and at most
+    /// [`Settings::max_history_len()`] rounds are kept. The history is preserved when the
+    /// participant is saved and restored.
+    pub fn history(&self) -> &[RoundRecord] {
+        self.history.records()
+    }
+
+    /// Return the number of rounds the participant has observed. It increases every time
+    /// the coordinator starts a new round and it is preserved when the participant is
+    /// saved and restored, so it can be used to decide whether the model should be trained
+    /// again. It is unrelated to the round ID used internally by the coordinator.
+    pub fn rounds_observed(&self) -> u64 {
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine.as_ref().unwrap().round_id()
+    }
+
+    /// Return the public signing key of the participant, which identifies it towards the
+    /// coordinator. It is set with [`Settings::set_keys()`] and it is preserved when the
+    /// participant is saved and restored.
+    pub fn public_key(&self) -> ParticipantPublicKey {
+        // UNWRAP_SAFE: the state machine is always set.
+        *self.state_machine.as_ref().unwrap().public_key()
+    }
+
+    /// Generate new signing keys for the participant and return their public key, so that
+    /// the coordinator cannot link the participant across the rounds.
+    ///
+    /// The new keys are applied when the next round starts: until then, the participant
+    /// keeps identifying itself with its current keys (see [`Participant::public_key()`]),
+    /// so that the messages it sends in the current round stay valid. The new keys are
+    /// preserved when the participant is saved and restored. Rotating the keys again
+    /// before the next round starts replaces the keys that were generated previously.
+    pub fn rotate_keys(&mut self) -> ParticipantPublicKey {
+        let keys = SigningKeyPair::generate();
+        let public = keys.public;
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine.as_mut().unwrap().rotate_keys(keys);
+        public
+    }
+
+    /// Replace the root certificate that the participant uses to authenticate the
+    /// coordinator. The certificate must be PEM encoded.
+    ///
+    /// The certificate can be rotated at any time: the participant keeps its progress and
+    /// uses the new certificate for its next requests. If the certificate is invalid, an
+    /// error is returned and the current one is kept. The certificates are not part of the
+    /// participant state, so they must be set again after the participant is restored.
+    pub fn set_tls_root_certificate(&mut self, pem: &[u8]) -> Result<(), ClientError> {
+        self.tls.set_root_certificate(pem.to_vec())
+    }
+
+    /// Replace the identity that the participant uses to authenticate itself to the
+    /// coordinator, i.e. its private key and certificate chain, which must be PEM encoded.
+    ///
+    /// Like the root certificate (see [`Participant::set_tls_root_certificate()`]), the
+    /// identity can be rotated at any time without losing the participant progress.
+    pub fn set_tls_identity(&mut self, pem: &[u8]) -> Result<(), ClientError> {
+        self.tls.set_identity(pem.to_vec())
+    }
+
+    /// Return the parameters of the current round, i.e. the fractions of participants
+    /// selected for the sum and update tasks, the round seed and the coordinator public
+    /// key, or `None` if the participant hasn't observed any round yet. They are updated
+    /// on the [`Participant::tick()`] that detects a new round, and they are preserved
+    /// when the participant is saved and restored.
+    pub fn round_params(&self) -> Option<RoundParameters> {
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine.as_ref().unwrap().round_params().cloned()
+    }
+
+    /// Check whether the participant daily data budget is exhausted. As long as this
+    /// method returns `true`, the participant declines to start new network operations
+    /// and cannot make progress.
+    pub fn data_budget_exceeded(&self) -> bool {
+        self.data_usage.is_exhausted()
+    }
+
+    /// Return the number of bytes sent and received by the participant in the current
+    /// day.
+    pub fn data_usage(&self) -> u64 {
+        self.data_usage.used()
+    }
+
+    /// Return the number of events that have been dropped because the participant
+    /// internal state machine emitted more events than the maximum number of pending
+    /// events before the participant processed them (see [`Event::EventsDropped`]).
+    pub fn dropped_events(&self) -> u64 {
+        self.events.dropped()
+    }
+
+    /// Return the maximum number of bytes the participant may send and receive per day.
+    pub fn daily_data_budget(&self) -> Option<u64> {
+        self.data_usage.budget()
+    }
+
+    /// Set the maximum number of bytes the participant may send and receive per day. If
+    /// `budget` is `None`, the data usage is not limited.
+    pub fn set_daily_data_budget(&mut self, budget: Option<u64>) {
+        self.data_usage.set_budget(budget)
+    }
+
+    /// Return the safety margin the participant keeps before the end of the update and
+    /// sum2 phases (see [`Settings::set_deadline_margin()`]).
+    pub fn deadline_margin(&self) -> Option<Duration> {
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine.as_ref().unwrap().deadline_margin()
+    }
+
+    /// Set the safety margin the participant keeps before the end of the update and sum2
+    /// phases (see [`Settings::set_deadline_margin()`]). If `margin` is `None`, the
+    /// participant never abandons its task because of the deadline of the phase.
+    pub fn set_deadline_margin(&mut self, margin: Option<Duration>) {
+        // UNWRAP_SAFE: the state machine is always set.
+        self.state_machine
+            .as_mut()
+            .unwrap()
+            .set_deadline_margin(margin)
+    }
+
+    /// Return the state of the circuit breaker. While the circuit breaker is open, the
+    /// coordinator repeatedly failed and the participant doesn't send any request to it.
+    pub fn circuit_state(&self) -> CircuitState {
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_ref().unwrap();
+        state_machine.circuit_state()
+    }
+
+    /// Recommend when the participant should be ticked next and what kind of work it
+    /// performs then, for scheduling the participant in the background. The
+    /// recommendation is synthesized from the work pending in the PET protocol, the
+    /// circuit breaker, the daily data budget and the decisions awaited from the user.
+    /// Apart from the data budget, these signals are part of the participant state, so
+    /// a restored participant makes the same recommendation.
+    pub fn next_wakeup_recommendation(&self) -> WakeupRecommendation {
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_ref().unwrap();
+        let data_budget_reset = if self.data_usage.is_exhausted() {
+            Some(self.data_usage.time_until_reset())
+        } else {
+            None
+        };
+        WakeupRecommendation::new(WakeupSignals {
+            work: state_machine.pending_work(),
+            circuit_state: state_machine.circuit_state(),
+            data_budget_reset,
+            awaiting_user: self.awaiting_sum2_confirmation() || self.consent_request().is_some(),
+        })
+    }
+
+    /// Return the work that the next [`Participant::tick()`] performs, without
+    /// advancing the participant internal state machine, or `None` if the next tick
+    /// doesn't request the coordinator because the circuit breaker is open or the daily
+    /// data budget is exhausted.
+    ///
+    /// Every tick that requests the coordinator checks whether a new round started. A
+    /// caller that wants to avoid network use can skip ticking while this returns
+    /// [`WorkClass::Download`] or [`WorkClass::Upload`], and one that wants to avoid
+    /// heavy computations while it returns [`WorkClass::Compute`]. While it returns
+    /// [`WorkClass::Train`], ticking only checks whether a new round started until the
+    /// model is set with [`Participant::set_model()`].
+    pub fn pending_work(&self) -> Option<WorkClass> {
+        if self.data_budget_exceeded() {
+            return None;
+        }
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_ref().unwrap();
+        if let CircuitState::Open { .. } = state_machine.circuit_state() {
+            return None;
+        }
+        match state_machine.pending_work() {
+            // the model is set, the next tick loads and masks it
+            WorkClass::Train if !self.should_set_model => Some(WorkClass::Compute),
+            work => Some(work),
+        }
+    }
+
     /// Load the given model into the store, so that the participant internal state
     /// machine can process it.
-    pub fn set_model(&mut self, model: Model) {
+    ///
+    /// If the model has a different length than the models that were set before, the
+    /// shape of the model changed: the cached global model and the local model that has
+    /// not been sent yet, if any, are discarded and the new length is expected from now
+    /// on (see [`Participant::model_shape_changed()`]). If the model has a different
+    /// length than the one announced by the coordinator for the current round, the model
+    /// is not loaded and [`ModelLengthError`] is returned.
+    pub fn set_model(&mut self, model: Model) -> Result<(), ModelLengthError> {
+        self.expect_model_len(model.len());
+        self.store_model(model)
+    }
+
+    /// Record that the app sets models of `len` weights, and discard the models of
+    /// another length if the shape of the model changed.
+    fn expect_model_len(&mut self, len: usize) {
+        self.model_shape_changed = false;
+        let previous = match self.model_len.replace(len) {
+            Some(previous) if previous != len => previous,
+            _ => return,
+        };
+
+        let deadline_margin = self.deadline_margin();
+        let Self {
+            ref mut runtime,
+            ref store,
+            ..
+        } = self;
+        runtime.block_on(async { store.0.lock().await.take() });
+        // UNWRAP_SAFE: the state machine is always set.
+        let state = self.state_machine.take().unwrap().save();
+        let mut state_machine = StateMachine::restore(
+            state.discard_local_model(),
+            self.client.clone(),
+            self.store.clone(),
+            self.notifier.clone(),
+        );
+        state_machine.set_deadline_margin(deadline_margin);
+        self.state_machine = Some(state_machine);
+        self.notifier.notify(Event::ModelShapeChanged {
+            previous,
+            current: len,
+        });
+        self.process_events();
+    }
+
+    /// Load the given model into the store, unless the coordinator expects another
+    /// model length.
+    fn store_model(&mut self, model: Model) -> Result<(), ModelLengthError> {
+        if let Some(params) = self.round_params() {
+            if params.model_length != model.len() {
+                self.notifier.notify(Event::ModelLengthMismatch {
+                    expected: params.model_length,
+                    found: model.len(),
+                });
+                self.process_events();
+                return Err(ModelLengthError {
+                    expected: params.model_length,
+                    found: model.len(),
+                });
+            }
+        }
+
         let Self {
             ref mut runtime,
             ref store,
@@ -330,29 +1362,2017 @@ impl Participant {
             *stored_model = Some(model)
         });
         self.should_set_model = false;
+        Ok(())
+    }
+
+    /// Load a model of `len` weights given by its sparse representation into the store
+    /// (see [`Participant::set_model()`]). The weight at `indices[i]` is `values[i]`, and
+    /// the other weights are the ones of the last known global model, or zero if no global
+    /// model has been fetched in the current round.
+    ///
+    /// The last known global model is not fetched again from the coordinator. If the shape
+    /// of the model changed, the last known global model is discarded first (see
+    /// [`Participant::set_model()`]).
+    pub fn set_sparse_model(
+        &mut self,
+        indices: &[usize],
+        values: Model,
+        len: usize,
+    ) -> Result<(), SparseModelError> {
+        let sparse_model = SparseModel::new(indices.to_vec(), values, len)?;
+        // the last known global model is kept unless the shape of the model changes, so
+        // the call is validated before the participant state is changed
+        let shape_changes = matches!(self.model_len, Some(previous) if previous != len);
+        match self.global_model.as_ref() {
+            Some(global_model) if !shape_changes && global_model.len() != len => {
+                return Err(SparseModelError::GlobalModelLength {
+                    expected: len,
+                    found: global_model.len(),
+                });
+            }
+            _ => self.expect_model_len(len),
+        }
+
+        let model = match self.global_model.as_ref() {
+            // UNWRAP_SAFE: the global model has the length of the dense model
+            Some(global_model) => sparse_model.apply_to(global_model.clone()).unwrap(),
+            None => sparse_model.into_dense(),
+        };
+
+        self.store_model(model)?;
+        Ok(())
     }
 
     /// Retrieve the current global model, if available.
+    ///
+    /// The global model is only fetched from the coordinator once per round: it is
+    /// cached until the participant observes a new round.
     pub fn global_model(&mut self) -> Result<Option<Model>, GetGlobalModelError> {
+        let global_model = self.fetch_global_model()?.cloned();
+        self.new_global_model = false;
+        Ok(global_model)
+    }
+
+    /// Return the number of weights of the current global model, if available. This
+    /// can be used to allocate a buffer for [`Participant::global_model()`], which
+    /// doesn't fetch the global model again.
+    pub fn global_model_len(&mut self) -> Result<Option<usize>, GetGlobalModelError> {
+        Ok(self.fetch_global_model()?.map(Model::len))
+    }
+
+    /// Fetch the current global model, unless it has already been fetched in the
+    /// current round.
+    fn fetch_global_model(&mut self) -> Result<Option<&Model>, GetGlobalModelError> {
+        if self.global_model.is_none() {
+            let Self {
+                ref mut runtime,
+                ref mut client,
+                ..
+            } = self;
+            self.global_model = runtime
+                .block_on(async { client.get_model().await.map_err(GetGlobalModelError) })?;
+        }
+        Ok(self.global_model.as_ref())
+    }
+
+    /// Retrieve the metadata of the current round from the coordinator, i.e. the round
+    /// id, the current phase and its deadlines.
+    ///
+    /// If the coordinator entered another phase since the last call, an
+    /// [`Event::PhaseChanged`] is emitted.
+    pub fn round_metadata(&mut self) -> Result<RoundMetadata, GetRoundMetadataError> {
         let Self {
             ref mut runtime,
             ref mut client,
             ..
         } = self;
-
-        let global_model =
-            runtime.block_on(async { client.get_model().await.map_err(GetGlobalModelError) });
-        if global_model.is_ok() {
-            self.new_global_model = false;
+        let metadata = runtime.block_on(async {
+            client
+                .get_round_metadata()
+                .await
+                .map_err(GetRoundMetadataError)
+        })?;
+        if self.round_metadata != Some(metadata) {
+            self.notifier.notify(Event::PhaseChanged(metadata));
+            self.process_events();
         }
-        global_model
+        Ok(metadata)
     }
 
-    /// Return the local model configuration of the model that is expected in the
-    /// [`Participant::set_model`] method.
-    pub fn local_model_config(&self) -> LocalModelConfig {
-        // UNWRAP_SAFE: the state machine is always set.
-        let state_machine = self.state_machine.as_ref().unwrap();
+    /// Retrieve the statistics about the training which are safe to show to the user of
+    /// the app, like the approximate number of participants that contributed to the
+    /// latest global model.
+    ///
+    /// They change at most once per round and the coordinator rate limits the requests,
+    /// so they don't need to be fetched more often than every few minutes.
+    pub fn public_stats(&mut self) -> Result<PublicStats, GetPublicStatsError> {
+        let Self {
+            ref mut runtime,
+            ref mut client,
+            ..
+        } = self;
+        runtime.block_on(async { client.get_public_stats().await.map_err(GetPublicStatsError) })
+    }
+
+    /// Return the local model configuration of the model that is expected in the
+    /// [`Participant::set_model`] method.
+    pub fn local_model_config(&self) -> LocalModelConfig {
+        // UNWRAP_SAFE: the state machine is always set.
+        let state_machine = self.state_machine.as_ref().unwrap();
         state_machine.local_model_config()
     }
 }
+
+/// Migrate a serialized participant state that was saved by an older build to the
+/// format of the current build (see [`StateVersion::CURRENT`]). A state that is already
+/// in the current format is returned unchanged.
+///
+/// [`Participant::restore()`] migrates the states it restores, so this is only needed
+/// for upgrading the states that are stored by the app.
+pub fn migrate_state(bytes: &[u8]) -> Result<Vec<u8>, MigrateError> {
+    match read_state(bytes)? {
+        (StateVersion::V9, _) => Ok(bytes.to_vec()),
+        (_, state) => Ok(serialize_state(
+            &state.state,
+            state.model_len,
+            &state.history,
+            state.data_usage,
+            state.max_pending_events,
+        )),
+    }
+}
+
+/// Serialize the state of the state machine, the length of the models that were set,
+/// the history of the rounds, the data usage and the maximum number of pending events,
+/// prefixed by the version of the state and followed by its checksum.
+fn serialize_state(
+    state: &SerializableState,
+    model_len: Option<usize>,
+    history: &RoundHistory,
+    data_usage: SavedDataUsage,
+    max_pending_events: usize,
+) -> Vec<u8> {
+    let mut bytes = vec![StateVersion::CURRENT as u8];
+    bincode::serialize_into(&mut bytes, &model_len).unwrap();
+    bincode::serialize_into(&mut bytes, history).unwrap();
+    bincode::serialize_into(&mut bytes, &data_usage).unwrap();
+    bincode::serialize_into(&mut bytes, &max_pending_events).unwrap();
+    bincode::serialize_into(&mut bytes, state).unwrap();
+    let checksum = sha256::hash(&bytes);
+    bytes.extend_from_slice(checksum.as_ref());
+    bytes
+}
+
+/// Verify the checksum of a serialized state and deserialize it, with the length of the
+/// models that were set, the history of the rounds, the data usage and the maximum number
+/// of pending events.
+fn deserialize_state(bytes: &[u8]) -> Result<DecodedState, InitError> {
+    let (_, state) = read_state(bytes)?;
+    Ok((
+        state.state,
+        state.model_len,
+        state.history,
+        state.data_usage,
+        state.max_pending_events,
+    ))
+}
+
+/// Verify the checksum of a serialized state, deserialize it and migrate it to the
+/// current format.
+///
+/// The states saved by the first releases don't end with a checksum. If the checksum
+/// doesn't match, the state is deserialized in the format of the first releases, and it
+/// is corrupt if that fails too.
+fn read_state(bytes: &[u8]) -> Result<(StateVersion, StateV9), MigrateError> {
+    match verify_checksum(bytes) {
+        Ok(state) => decode_state(state),
+        Err(error) => bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .deserialize::<StateV0>(bytes)
+            .map(|state| (StateVersion::Unversioned, state.into()))
+            .map_err(|_| error),
+    }
+}
+
+/// Verify the checksum of a serialized state and return the state without its
+/// checksum.
+fn verify_checksum(bytes: &[u8]) -> Result<&[u8], MigrateError> {
+    if bytes.len() < sha256::DIGESTBYTES {
+        return Err(MigrateError::Corrupt);
+    }
+    let (state, checksum) = bytes.split_at(bytes.len() - sha256::DIGESTBYTES);
+    if sha256::hash(state).as_ref() != checksum {
+        return Err(MigrateError::Corrupt);
+    }
+    Ok(state)
+}
+
+/// A deserialized state, with the length of the models that were set, the history of the
+/// rounds and the data usage.
+type DecodedState = (
+    SerializableState,
+    Option<usize>,
+    RoundHistory,
+    SavedDataUsage,
+    usize,
+);
+
+/// A state saved by the first releases, which has neither a version nor a checksum. Its
+/// state machine has the [`legacy::v0`](xaynet_sdk::legacy::v0) layout.
+#[derive(Deserialize)]
+struct StateV0 {
+    state: legacy::v0::SerializableState,
+}
+
+/// A state in the [`StateVersion::V1`] format, without its version, or an unversioned
+/// state. The state machines of the formats before [`StateVersion::V4`] have the
+/// [`legacy::v1`](xaynet_sdk::legacy::v1) layout, and the ones of the
+/// [`StateVersion::V4`] and [`StateVersion::V5`] formats have the
+/// [`legacy::v2`](xaynet_sdk::legacy::v2) layout. The state machines of the
+/// [`StateVersion::V6`] format have the [`legacy::v3`](xaynet_sdk::legacy::v3) layout, and
+/// the ones of the [`StateVersion::V7`] format have the
+/// [`legacy::v4`](xaynet_sdk::legacy::v4) layout.
+#[derive(Deserialize)]
+struct StateV1 {
+    state: legacy::v1::SerializableState,
+}
+
+/// A state in the [`StateVersion::V2`] format, without its version.
+#[derive(Deserialize)]
+struct StateV2 {
+    model_len: Option<usize>,
+    state: legacy::v1::SerializableState,
+}
+
+/// A state in the [`StateVersion::V3`] format, without its version.
+#[derive(Deserialize)]
+struct StateV3 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    state: legacy::v1::SerializableState,
+}
+
+/// A state in the [`StateVersion::V4`] format, without its version.
+#[derive(Deserialize)]
+struct StateV4 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    state: legacy::v2::SerializableState,
+}
+
+/// A state in the [`StateVersion::V5`] format, without its version.
+#[derive(Deserialize)]
+struct StateV5 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    data_usage: SavedDataUsage,
+    state: legacy::v2::SerializableState,
+}
+
+/// A state in the [`StateVersion::V6`] format, without its version.
+#[derive(Deserialize)]
+struct StateV6 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    data_usage: SavedDataUsage,
+    state: legacy::v3::SerializableState,
+}
+
+/// A state in the [`StateVersion::V7`] format, without its version.
+#[derive(Deserialize)]
+struct StateV7 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    data_usage: SavedDataUsage,
+    state: legacy::v4::SerializableState,
+}
+
+/// A state in the [`StateVersion::V8`] format, without its version.
+#[derive(Deserialize)]
+struct StateV8 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    data_usage: SavedDataUsage,
+    state: SerializableState,
+}
+
+/// A state in the [`StateVersion::V9`] format, without its version.
+#[derive(Deserialize)]
+struct StateV9 {
+    model_len: Option<usize>,
+    history: RoundHistory,
+    data_usage: SavedDataUsage,
+    max_pending_events: usize,
+    state: SerializableState,
+}
+
+impl From<StateV0> for StateV1 {
+    fn from(state: StateV0) -> Self {
+        Self {
+            state: state.state.into(),
+        }
+    }
+}
+
+impl From<StateV1> for StateV2 {
+    fn from(state: StateV1) -> Self {
+        // the length of the models that were set was not recorded
+        Self {
+            model_len: None,
+            state: state.state,
+        }
+    }
+}
+
+impl From<StateV2> for StateV3 {
+    fn from(state: StateV2) -> Self {
+        // the history of the rounds was not recorded
+        Self {
+            model_len: state.model_len,
+            history: RoundHistory::default(),
+            state: state.state,
+        }
+    }
+}
+
+impl From<StateV3> for StateV4 {
+    fn from(state: StateV3) -> Self {
+        Self {
+            model_len: state.model_len,
+            history: state.history,
+            state: state.state.into(),
+        }
+    }
+}
+
+impl From<StateV4> for StateV5 {
+    fn from(state: StateV4) -> Self {
+        // the data usage was not recorded
+        Self {
+            model_len: state.model_len,
+            history: state.history,
+            data_usage: SavedDataUsage::default(),
+            state: state.state,
+        }
+    }
+}
+
+impl From<StateV5> for StateV6 {
+    fn from(state: StateV5) -> Self {
+        Self {
+            model_len: state.model_len,
+            history: state.history,
+            data_usage: state.data_usage,
+            state: state.state.into(),
+        }
+    }
+}
+
+impl From<StateV6> for StateV7 {
+    fn from(state: StateV6) -> Self {
+        Self {
+            model_len: state.model_len,
+            history: state.history,
+            data_usage: state.data_usage,
+            state: state.state.into(),
+        }
+    }
+}
+
+impl From<StateV7> for StateV8 {
+    fn from(state: StateV7) -> Self {
+        Self {
+            model_len: state.model_len,
+            history: state.history,
+            data_usage: state.data_usage,
+            state: state.state.into(),
+        }
+    }
+}
+
+impl From<StateV8> for StateV9 {
+    fn from(state: StateV8) -> Self {
+        // the maximum number of pending events was not recorded
+        Self {
+            model_len: state.model_len,
+            history: state.history,
+            data_usage: state.data_usage,
+            max_pending_events: DEFAULT_MAX_PENDING_EVENTS,
+            state: state.state,
+        }
+    }
+}
+
+impl From<StateV7> for StateV9 {
+    fn from(state: StateV7) -> Self {
+        StateV8::from(state).into()
+    }
+}
+
+impl From<StateV6> for StateV9 {
+    fn from(state: StateV6) -> Self {
+        StateV7::from(state).into()
+    }
+}
+
+impl From<StateV5> for StateV9 {
+    fn from(state: StateV5) -> Self {
+        StateV6::from(state).into()
+    }
+}
+
+impl From<StateV4> for StateV9 {
+    fn from(state: StateV4) -> Self {
+        StateV5::from(state).into()
+    }
+}
+
+impl From<StateV3> for StateV9 {
+    fn from(state: StateV3) -> Self {
+        StateV4::from(state).into()
+    }
+}
+
+impl From<StateV2> for StateV9 {
+    fn from(state: StateV2) -> Self {
+        StateV3::from(state).into()
+    }
+}
+
+impl From<StateV1> for StateV9 {
+    fn from(state: StateV1) -> Self {
+        StateV2::from(state).into()
+    }
+}
+
+impl From<StateV0> for StateV9 {
+    fn from(state: StateV0) -> Self {
+        StateV1::from(state).into()
+    }
+}
+
+/// Detect the version of a serialized state without its checksum, deserialize it in the
+/// format of its version and migrate it step by step to the current format.
+///
+/// An unversioned state starts with the index of its phase, so it can start with the
+/// same byte as a versioned state. The state is deserialized as a versioned state first,
+/// and as an unversioned state if that fails: deserializing one as the other fails
+/// because the bytes are shifted and trailing bytes are rejected.
+fn decode_state(state: &[u8]) -> Result<(StateVersion, StateV9), MigrateError> {
+    // same encoding as `bincode::serialize()`, except that trailing bytes are rejected
+    let options = bincode::DefaultOptions::new().with_fixint_encoding();
+    let (version, versioned) = match state.split_first() {
+        Some((&version, versioned)) => (version, versioned),
+        None => return Err(MigrateError::Corrupt),
+    };
+    let error = if version == StateVersion::V9 as u8 {
+        match options.deserialize::<StateV9>(versioned) {
+            Ok(state) => return Ok((StateVersion::V9, state)),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V8 as u8 {
+        match options.deserialize::<StateV8>(versioned) {
+            Ok(state) => return Ok((StateVersion::V8, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V7 as u8 {
+        match options.deserialize::<StateV7>(versioned) {
+            Ok(state) => return Ok((StateVersion::V7, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V6 as u8 {
+        match options.deserialize::<StateV6>(versioned) {
+            Ok(state) => return Ok((StateVersion::V6, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V5 as u8 {
+        match options.deserialize::<StateV5>(versioned) {
+            Ok(state) => return Ok((StateVersion::V5, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V4 as u8 {
+        match options.deserialize::<StateV4>(versioned) {
+            Ok(state) => return Ok((StateVersion::V4, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V3 as u8 {
+        match options.deserialize::<StateV3>(versioned) {
+            Ok(state) => return Ok((StateVersion::V3, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V2 as u8 {
+        match options.deserialize::<StateV2>(versioned) {
+            Ok(state) => return Ok((StateVersion::V2, state.into())),
+            Err(error) => error,
+        }
+    } else if version == StateVersion::V1 as u8 {
+        match options.deserialize::<StateV1>(versioned) {
+            Ok(state) => return Ok((StateVersion::V1, state.into())),
+            Err(error) => error,
+        }
+    } else {
+        match options.deserialize::<StateV1>(state) {
+            Ok(state) => return Ok((StateVersion::Unversioned, state.into())),
+            Err(_) if version > StateVersion::CURRENT as u8 => {
+                return Err(MigrateError::UnsupportedVersion(version))
+            }
+            Err(error) => return Err(MigrateError::Deserialization(error)),
+        }
+    };
+    options
+        .deserialize::<StateV1>(state)
+        .map(|state| (StateVersion::Unversioned, state.into()))
+        .map_err(|_| MigrateError::Deserialization(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex as StdMutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body,
+        Method,
+        Request,
+        Response,
+        Server,
+        StatusCode,
+    };
+    use xaynet_core::{
+        common::RoundSeed,
+        crypto::{ByteObject, EncryptKeyPair, PublicEncryptKey, SigningKeyPair, SigningKeySeed},
+        mask::{BoundType, DataType, FromPrimitives, GroupType, MaskConfig, MaskObject, ModelType},
+        message::{Message, Payload},
+        SumDict,
+    };
+
+    use xaynet_sdk::{settings::CircuitBreakerSettings, ConsentTask};
+
+    use super::*;
+
+    fn participant() -> Participant {
+        sodiumoxide::init().unwrap();
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url("http://localhost:1".to_string());
+        Participant::new(settings).unwrap()
+    }
+
+    #[test]
+    fn test_prepare_for_shutdown() {
+        let mut participant = participant();
+        // the coordinator is unreachable, the participant stays at the same step
+        participant.tick();
+        assert!(!participant.made_progress());
+
+        let checkpoint = participant.prepare_for_shutdown();
+        // the participant is still usable after the checkpoint
+        participant.tick();
+        assert!(matches!(participant.task(), Task::None));
+
+        let restored = Participant::restore(&checkpoint, "http://localhost:1")
+            .expect("failed to restore the participant from the checkpoint");
+        assert!(matches!(restored.task(), Task::None));
+        assert_same_state(&restored.save(), &checkpoint);
+    }
+
+    /// Assert that two saved states are the same, up to the clock readings they are
+    /// anchored to.
+    fn assert_same_state(left: &[u8], right: &[u8]) {
+        let (mut left_state, left_model_len, left_history, left_data_usage, left_max_events) =
+            deserialize_state(left).unwrap();
+        let (mut right_state, right_model_len, right_history, right_data_usage, right_max_events) =
+            deserialize_state(right).unwrap();
+        assert_eq!(left_model_len, right_model_len);
+        assert_eq!(left_history, right_history);
+        assert_eq!(left_max_events, right_max_events);
+        assert_eq!(
+            left_data_usage.without_clock_readings(),
+            right_data_usage.without_clock_readings()
+        );
+        left_state.clear_clock_readings();
+        right_state.clear_clock_readings();
+        assert_eq!(
+            bincode::serialize(&left_state).unwrap(),
+            bincode::serialize(&right_state).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_restore_corrupt_state() {
+        let state = participant().save();
+        assert!(deserialize_state(&state).is_ok());
+
+        for &i in &[0, state.len() / 2, state.len() - 1] {
+            let mut corrupt = state.clone();
+            corrupt[i] ^= 1;
+            assert!(matches!(
+                Participant::restore(&corrupt, "http://localhost:1"),
+                Err(InitError::Corrupt)
+            ));
+        }
+
+        let truncated = &state[..state.len() - 1];
+        assert!(matches!(
+            Participant::restore(truncated, "http://localhost:1"),
+            Err(InitError::Corrupt)
+        ));
+        assert!(matches!(
+            Participant::restore(&[], "http://localhost:1"),
+            Err(InitError::Corrupt)
+        ));
+    }
+
+    /// Prefix a bincode-serialized state with the given version, unless it is
+    /// unversioned, and append its checksum.
+    fn seal_state(version: StateVersion, state: Vec<u8>) -> Vec<u8> {
+        let mut bytes = match version {
+            StateVersion::Unversioned => Vec::new(),
+            StateVersion::V1 => vec![StateVersion::V1 as u8],
+            // the participant didn't set any model
+            StateVersion::V2 => vec![StateVersion::V2 as u8, 0],
+            // the participant didn't set any model nor observe any round
+            StateVersion::V3 | StateVersion::V4 => {
+                let mut bytes = vec![version as u8, 0];
+                bincode::serialize_into(&mut bytes, &RoundHistory::default()).unwrap();
+                bytes
+            }
+            // the participant didn't set any model nor observe any round, and has no
+            // daily data budget
+            StateVersion::V5 | StateVersion::V6 | StateVersion::V7 | StateVersion::V8 => {
+                let mut bytes = vec![version as u8, 0];
+                bincode::serialize_into(&mut bytes, &RoundHistory::default()).unwrap();
+                bincode::serialize_into(&mut bytes, &SavedDataUsage::default()).unwrap();
+                bytes
+            }
+            // the participant didn't set any model nor observe any round, has no daily
+            // data budget and keeps the default maximum number of pending events
+            StateVersion::V9 => {
+                let mut bytes = vec![version as u8, 0];
+                bincode::serialize_into(&mut bytes, &RoundHistory::default()).unwrap();
+                bincode::serialize_into(&mut bytes, &SavedDataUsage::default()).unwrap();
+                bincode::serialize_into(&mut bytes, &DEFAULT_MAX_PENDING_EVENTS).unwrap();
+                bytes
+            }
+        };
+        bytes.extend_from_slice(&state);
+        let checksum = sha256::hash(&bytes);
+        bytes.extend_from_slice(checksum.as_ref());
+        bytes
+    }
+
+    /// States saved by the first release, which have neither a version nor a checksum: a
+    /// new participant and a participant in the sum2 phase, whose signing keys are
+    /// derived from the seed `[7; 32]`.
+    const STATE_BASELINE: &[u8] = include_bytes!("../tests/data/state_baseline.bin");
+    const STATE_BASELINE_SUM2: &[u8] = include_bytes!("../tests/data/state_baseline_sum2.bin");
+
+    /// States saved by the last build of each format before [`StateVersion::V4`]: a new
+    /// participant whose signing keys are derived from the seed `[7; 32]`.
+    const STATE_UNVERSIONED: &[u8] = include_bytes!("../tests/data/state_unversioned.bin");
+    const STATE_V1: &[u8] = include_bytes!("../tests/data/state_v1.bin");
+    const STATE_V2: &[u8] = include_bytes!("../tests/data/state_v2.bin");
+    const STATE_V3: &[u8] = include_bytes!("../tests/data/state_v3.bin");
+
+    /// A state in the [`StateVersion::V3`] format of the same participant, selected for
+    /// the sum task in the third round it observed.
+    const STATE_V3_SUM: &[u8] = include_bytes!("../tests/data/state_v3_sum.bin");
+
+    /// A state in the [`StateVersion::V5`] format of the same participant in the sum2
+    /// phase, before it fetched the mask seeds.
+    const STATE_V5_SUM2: &[u8] = include_bytes!("../tests/data/state_v5_sum2.bin");
+
+    /// A state in the [`StateVersion::V6`] format of a new participant whose signing keys
+    /// are derived from the seed `[7; 32]`.
+    const STATE_V6: &[u8] = include_bytes!("../tests/data/state_v6.bin");
+
+    /// A state in the [`StateVersion::V7`] format of the same participant.
+    const STATE_V7: &[u8] = include_bytes!("../tests/data/state_v7.bin");
+
+    /// The length of the maximum number of pending events in a saved state.
+    const MAX_PENDING_EVENTS_LEN: usize = std::mem::size_of::<u64>();
+
+    /// The signing keys of the participants of the saved states.
+    fn saved_state_keys() -> SigningKeyPair {
+        sodiumoxide::init().unwrap();
+        SigningKeyPair::derive_from_seed(&SigningKeySeed::from_slice(&[7; 32]).unwrap())
+    }
+
+    /// Get the state machine of a [`StateVersion::V3`] state that didn't set any model
+    /// nor observe any round.
+    fn state_machine_v3(state: &[u8]) -> &[u8] {
+        let header = 1 + bincode::serialized_size(&(None::<usize>, RoundHistory::default()))
+            .unwrap() as usize;
+        &state[header..state.len() - sha256::DIGESTBYTES]
+    }
+
+    /// Get the state machine of a [`StateVersion::V6`] state that didn't set any model nor
+    /// observe any round.
+    fn state_machine_v6(state: &[u8]) -> &[u8] {
+        let header = 1 + bincode::serialized_size(&(
+            None::<usize>,
+            RoundHistory::default(),
+            SavedDataUsage::default(),
+        ))
+        .unwrap() as usize;
+        &state[header..state.len() - sha256::DIGESTBYTES]
+    }
+
+    #[test]
+    fn test_migrate_state() {
+        let mut participant = participant();
+        participant.tick();
+        let state = participant.save();
+        assert_eq!(state[0], StateVersion::CURRENT as u8);
+        // a current state is left untouched
+        assert_eq!(migrate_state(&state).unwrap(), state);
+
+        // the state machines of the older formats have the same layout
+        let body = state_machine_v3(STATE_V3).to_vec();
+        let current = migrate_state(STATE_V3).unwrap();
+
+        // a state saved before the format was versioned
+        let legacy = seal_state(StateVersion::Unversioned, body.clone());
+        // an awaiting state starts with the same byte as a versioned state
+        assert_eq!(legacy[0], StateVersion::V1 as u8);
+        let migrated = migrate_state(&legacy).unwrap();
+        assert_eq!(migrated, current);
+        assert_eq!(migrate_state(&migrated).unwrap(), current);
+        let restored = Participant::restore(&legacy, "http://localhost:1").unwrap();
+        assert_same_state(&restored.save(), &current);
+
+        // a state saved before the length of the models was recorded
+        let v1 = seal_state(StateVersion::V1, body.clone());
+        assert_eq!(migrate_state(&v1).unwrap(), current);
+
+        // a state saved before the history of the rounds was recorded
+        let v2 = seal_state(StateVersion::V2, body);
+        assert_eq!(migrate_state(&v2).unwrap(), current);
+        let restored = Participant::restore(&v2, "http://localhost:1").unwrap();
+        assert!(restored.history().is_empty());
+
+        // a state saved before the data usage was recorded, whose state machine has the
+        // layout of the state machines of the [`StateVersion::V6`] format
+        let body = state_machine_v6(STATE_V6).to_vec();
+        let v4 = seal_state(StateVersion::V4, body);
+        let current = deserialize_state(&migrate_state(STATE_V6).unwrap())
+            .unwrap()
+            .0;
+        let current = seal_state(StateVersion::CURRENT, bincode::serialize(&current).unwrap());
+        assert_eq!(migrate_state(&v4).unwrap(), current);
+        let restored = Participant::restore(&v4, "http://localhost:1").unwrap();
+        assert_eq!(restored.daily_data_budget(), None);
+        assert_eq!(restored.data_usage(), 0);
+
+        assert!(matches!(migrate_state(&[]), Err(MigrateError::Corrupt)));
+    }
+
+    #[test]
+    fn test_migrate_state_v3() {
+        let keys = saved_state_keys();
+        assert_eq!(STATE_V3[0], StateVersion::V3 as u8);
+        // the state machine was saved before the keys of the next round and the seed of
+        // the current round were recorded
+        assert!(bincode::deserialize::<SerializableState>(state_machine_v3(STATE_V3)).is_err());
+
+        let migrated = migrate_state(STATE_V3).unwrap();
+        assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+        assert_eq!(migrate_state(&migrated).unwrap(), migrated);
+
+        for state in &[STATE_V3, &migrated] {
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+            assert_eq!(participant.rounds_observed(), 0);
+            assert!(matches!(participant.task(), Task::None));
+            assert!(participant.history().is_empty());
+            assert_same_state(&participant.save(), &migrated);
+        }
+    }
+
+    #[test]
+    fn test_migrate_saved_states() {
+        let keys = saved_state_keys();
+        let current = migrate_state(STATE_V3).unwrap();
+        let saved = [
+            (StateVersion::Unversioned, STATE_UNVERSIONED),
+            (StateVersion::V1, STATE_V1),
+            (StateVersion::V2, STATE_V2),
+        ];
+        for &(version, state) in &saved {
+            let (decoded, _) = decode_state(verify_checksum(state).unwrap()).unwrap();
+            assert_eq!(decoded, version);
+            // the states only differ by their format and the clock readings they are
+            // anchored to
+            let migrated = migrate_state(state).unwrap();
+            assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+            assert_eq!(migrated.len(), current.len());
+            assert_eq!(migrate_state(&migrated).unwrap(), migrated);
+
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+            assert_eq!(participant.rounds_observed(), 0);
+            assert!(matches!(participant.task(), Task::None));
+            assert!(participant.history().is_empty());
+        }
+        // an awaiting state starts with the same byte as a versioned state
+        assert_eq!(STATE_UNVERSIONED[0], StateVersion::V1 as u8);
+    }
+
+    #[test]
+    fn test_restore_state_without_checksum() {
+        let keys = saved_state_keys();
+        for state in &[STATE_BASELINE, STATE_BASELINE_SUM2] {
+            assert!(verify_checksum(state).is_err());
+            let migrated = migrate_state(state).unwrap();
+            assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+            assert!(verify_checksum(&migrated).is_ok());
+            assert_eq!(migrate_state(&migrated).unwrap(), migrated);
+
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+            assert_eq!(participant.rounds_observed(), 0);
+            assert!(participant.history().is_empty());
+        }
+
+        let participant = Participant::restore(STATE_BASELINE, "http://localhost:1").unwrap();
+        assert!(matches!(participant.task(), Task::None));
+        assert!(matches!(
+            deserialize_state(STATE_BASELINE_SUM2).unwrap().0,
+            SerializableState::Sum2(_)
+        ));
+
+        // a state without checksum is still rejected if it is truncated or altered
+        let truncated = &STATE_BASELINE[..STATE_BASELINE.len() - 1];
+        assert!(matches!(
+            Participant::restore(truncated, "http://localhost:1"),
+            Err(InitError::Corrupt)
+        ));
+        let mut extended = STATE_BASELINE.to_vec();
+        extended.push(0);
+        assert!(matches!(
+            migrate_state(&extended),
+            Err(MigrateError::Corrupt)
+        ));
+    }
+
+    #[test]
+    fn test_migrate_state_v3_sum() {
+        let keys = saved_state_keys();
+        let migrated = migrate_state(STATE_V3_SUM).unwrap();
+        assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+        let data_usage = bincode::serialized_size(&SavedDataUsage::default()).unwrap() as usize;
+        // the keys of the next round and the seed of the current round are unset, the
+        // data usage and the maximum number of pending events were not recorded, and the
+        // round is not part of a training plan and has no maximal sample count
+        assert_eq!(
+            migrated.len(),
+            STATE_V3_SUM.len() + 4 + data_usage + MAX_PENDING_EVENTS_LEN
+        );
+
+        for state in &[STATE_V3_SUM, &migrated] {
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+            assert_eq!(participant.rounds_observed(), 3);
+            assert!(matches!(participant.task(), Task::Sum));
+        }
+    }
+
+    #[test]
+    fn test_migrate_state_v5_sum2() {
+        let keys = saved_state_keys();
+        assert_eq!(STATE_V5_SUM2[0], StateVersion::V5 as u8);
+        let migrated = migrate_state(STATE_V5_SUM2).unwrap();
+        assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+        assert_eq!(migrate_state(&migrated).unwrap(), migrated);
+        // the ephemeral public key that was sent in the sum message and the maximum number
+        // of pending events are recorded, and the round is not part of a training plan and
+        // has no maximal sample count
+        let ephm_pk_len = bincode::serialized_size(&PublicEncryptKey::zeroed()).unwrap() as usize;
+        assert_eq!(
+            migrated.len(),
+            STATE_V5_SUM2.len() + ephm_pk_len + MAX_PENDING_EVENTS_LEN + 2
+        );
+
+        for state in &[STATE_V5_SUM2, &migrated] {
+            match deserialize_state(state).unwrap().0 {
+                SerializableState::Sum2(state) => {
+                    assert_eq!(state.private.ephm_pk, state.private.ephm_keys.public);
+                    assert_eq!(state.shared.keys.public, keys.public);
+                }
+                _ => panic!("expected a sum2 state"),
+            }
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+        }
+    }
+
+    #[test]
+    fn test_migrate_state_v6() {
+        let keys = saved_state_keys();
+        assert_eq!(STATE_V6[0], StateVersion::V6 as u8);
+        let migrated = migrate_state(STATE_V6).unwrap();
+        assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+        assert_eq!(migrate_state(&migrated).unwrap(), migrated);
+        // the round parameters record that the round is not part of a training plan and has
+        // no maximal sample count, and the maximum number of pending events is recorded
+        assert_eq!(migrated.len(), STATE_V6.len() + 2 + MAX_PENDING_EVENTS_LEN);
+
+        for state in &[STATE_V6, &migrated] {
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+            assert_eq!(participant.rounds_observed(), 0);
+            assert!(matches!(participant.task(), Task::None));
+            assert_same_state(&participant.save(), &migrated);
+        }
+    }
+
+    #[test]
+    fn test_migrate_state_v7() {
+        let keys = saved_state_keys();
+        assert_eq!(STATE_V7[0], StateVersion::V7 as u8);
+        let migrated = migrate_state(STATE_V7).unwrap();
+        assert_eq!(migrated[0], StateVersion::CURRENT as u8);
+        assert_eq!(migrate_state(&migrated).unwrap(), migrated);
+        // the round parameters record that the round has no maximal sample count, and the
+        // maximum number of pending events is recorded
+        assert_eq!(migrated.len(), STATE_V7.len() + 1 + MAX_PENDING_EVENTS_LEN);
+
+        for state in &[STATE_V7, &migrated] {
+            let participant = Participant::restore(state, "http://localhost:1").unwrap();
+            assert_eq!(participant.public_key(), keys.public);
+            assert_eq!(participant.rounds_observed(), 0);
+            assert!(matches!(participant.task(), Task::None));
+            assert_same_state(&participant.save(), &migrated);
+        }
+    }
+
+    #[test]
+    fn test_save_and_restore_data_budget() {
+        let mut participant = participant();
+        participant.set_daily_data_budget(Some(0));
+        assert!(participant.data_budget_exceeded());
+        let state = participant.save();
+
+        let restored = Participant::restore(&state, "http://localhost:1").unwrap();
+        assert_eq!(restored.daily_data_budget(), Some(0));
+        assert!(restored.data_budget_exceeded());
+        // the restored participant declines to start new network operations
+        assert_eq!(restored.pending_work(), None);
+    }
+
+    #[test]
+    fn test_restore_unsupported_state_version() {
+        let state = participant().save();
+        let body = bincode::serialize(&deserialize_state(&state).unwrap().0).unwrap();
+        let mut future = vec![42];
+        future.extend_from_slice(&body);
+        let future = seal_state(StateVersion::Unversioned, future);
+        assert!(matches!(
+            migrate_state(&future),
+            Err(MigrateError::UnsupportedVersion(42))
+        ));
+        match Participant::restore(&future, "http://localhost:1") {
+            Err(error @ InitError::UnsupportedVersion(42)) => assert_eq!(
+                error.to_string(),
+                format!(
+                    "unsupported participant state version 42 (expected at most {})",
+                    StateVersion::CURRENT as u8
+                )
+            ),
+            _ => panic!("expected an unsupported state version"),
+        }
+
+        // a current state whose content is not a valid state
+        let invalid = seal_state(StateVersion::CURRENT, vec![0xff; 8]);
+        assert!(matches!(
+            migrate_state(&invalid),
+            Err(MigrateError::Deserialization(_))
+        ));
+    }
+
+    #[test]
+    fn test_sum2_confirmation_disabled() {
+        let mut participant = participant();
+        participant.tick();
+        assert!(!participant.awaiting_sum2_confirmation());
+        assert!(participant.sum2_mask().is_none());
+        assert!(!participant.confirm_sum2());
+    }
+
+    /// Replace the state of the internal state machine of a saved participant.
+    fn with_state(
+        saved: &[u8],
+        f: impl FnOnce(SerializableState) -> SerializableState,
+    ) -> Vec<u8> {
+        let (state, model_len, history, data_usage, max_pending_events) =
+            deserialize_state(saved).unwrap();
+        serialize_state(
+            &f(state),
+            model_len,
+            &history,
+            data_usage,
+            max_pending_events,
+        )
+    }
+
+    /// Craft the state of a participant that aggregated the given global mask in the
+    /// sum2 phase, from the state of a participant in the awaiting phase.
+    fn sum2_state(awaiting: &[u8], mask: &MaskObject) -> Vec<u8> {
+        with_state(awaiting, |state| state.into_sum2(mask.clone()))
+    }
+
+    #[test]
+    fn test_sum2_confirmation() {
+        sodiumoxide::init().unwrap();
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url("http://localhost:1".to_string());
+        settings.set_confirm_sum2(true);
+        let awaiting = Participant::new(settings).unwrap().save();
+
+        let config = MaskConfig {
+            group_type: GroupType::Prime,
+            data_type: DataType::F32,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        };
+        let mask = MaskObject::empty(config.into(), 4);
+        let mut participant =
+            Participant::restore(&sum2_state(&awaiting, &mask), "http://localhost:1").unwrap();
+
+        // the participant waits for the confirmation
+        assert!(participant.awaiting_sum2_confirmation());
+        let mut expected = vec![0; mask.buffer_length()];
+        mask.to_bytes(&mut expected);
+        assert_eq!(participant.sum2_mask(), Some(expected));
+        participant.tick();
+        assert!(!participant.made_progress());
+        assert!(participant.awaiting_sum2_confirmation());
+
+        // a restored participant still waits for the confirmation
+        let mut participant =
+            Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert!(participant.awaiting_sum2_confirmation());
+
+        // the confirmation is part of the participant state
+        assert!(participant.confirm_sum2());
+        assert!(!participant.awaiting_sum2_confirmation());
+        assert!(participant.sum2_mask().is_none());
+        assert!(!participant.confirm_sum2());
+        let participant = Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert!(!participant.awaiting_sum2_confirmation());
+        assert!(participant.sum2_mask().is_none());
+    }
+
+    #[test]
+    fn test_sum2_confirmation_new_round() {
+        sodiumoxide::init().unwrap();
+        let coordinator = MockCoordinator::start(4);
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url(coordinator.url.clone());
+        settings.set_confirm_sum2(true);
+        let awaiting = Participant::new(settings).unwrap().save();
+
+        let config = MaskConfig {
+            group_type: GroupType::Prime,
+            data_type: DataType::F32,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        };
+        let mask = MaskObject::empty(config.into(), 4);
+        let mut participant =
+            Participant::restore(&sum2_state(&awaiting, &mask), &coordinator.url).unwrap();
+        assert!(participant.awaiting_sum2_confirmation());
+
+        // the coordinator moves on to the next round before the mask is confirmed, and
+        // selects the participant for the update task
+        coordinator.new_round(4);
+        tick_until(&mut participant, Participant::should_set_model);
+        assert!(matches!(participant.task(), Task::Update));
+        assert!(!participant.awaiting_sum2_confirmation());
+        assert!(participant.sum2_mask().is_none());
+        assert!(!participant.confirm_sum2());
+        assert_ne!(
+            participant.next_wakeup_recommendation().reason,
+            crate::WakeupReason::AwaitingUser
+        );
+    }
+
+    /// Craft the state of a participant that fetched the sum dictionary in the update
+    /// phase, from the state of a participant in the awaiting phase.
+    fn update_state(awaiting: &[u8]) -> Vec<u8> {
+        with_state(awaiting, |state| state.into_update(SumDict::new()))
+    }
+
+    #[test]
+    fn test_pending_work() {
+        let participant = participant();
+        assert_eq!(participant.pending_work(), Some(WorkClass::Poll));
+
+        let state = update_state(&participant.save());
+        let mut participant = Participant::restore(&state, "http://localhost:1").unwrap();
+        assert_eq!(participant.pending_work(), Some(WorkClass::Train));
+        let len = participant.local_model_config().len;
+        let model = Model::from_primitives(vec![1_f32; len].into_iter()).unwrap();
+        participant.set_model(model).unwrap();
+        // masking the model is not a network operation
+        assert_eq!(participant.pending_work(), Some(WorkClass::Compute));
+
+        participant.set_daily_data_budget(Some(0));
+        assert_eq!(participant.pending_work(), None);
+    }
+
+    #[test]
+    fn test_save_and_restore_staged_model() {
+        let awaiting = participant().save();
+        let mut participant =
+            Participant::restore(&update_state(&awaiting), "http://localhost:1").unwrap();
+        assert!(matches!(participant.task(), Task::Update));
+        assert!(participant.should_set_model());
+
+        let len = participant.local_model_config().len;
+        let model = Model::from_primitives(vec![1_f32; len].into_iter()).unwrap();
+        participant.set_model(model.clone()).unwrap();
+        let state = participant.save();
+        match deserialize_state(&state).unwrap().0 {
+            SerializableState::Update(update) => {
+                assert_eq!(update.private.model.unwrap().as_ref(), &model)
+            }
+            _ => panic!("the participant is not in the update phase"),
+        }
+
+        // the restored participant doesn't ask for the model again
+        let mut participant = Participant::restore(&state, "http://localhost:1").unwrap();
+        assert!(!participant.should_set_model());
+        // the coordinator is unreachable, the participant stays at the same step
+        participant.tick();
+        assert!(!participant.should_set_model());
+        let checkpoint = participant.prepare_for_shutdown();
+        assert_eq!(checkpoint.len(), state.len());
+    }
+
+    #[test]
+    fn test_prepare_for_shutdown_mid_step_failure() {
+        sodiumoxide::init().unwrap();
+        let coordinator = MockCoordinator::start(4);
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url(coordinator.url.clone());
+        settings.set_circuit_breaker(CircuitBreakerSettings {
+            threshold: 1,
+            jitter: Duration::from_secs(3_600),
+            ..CircuitBreakerSettings::default()
+        });
+        let mut participant = Participant::new(settings).unwrap();
+        tick_until(&mut participant, Participant::should_set_model);
+        participant.set_model(model(4)).unwrap();
+
+        // the model is masked, but the coordinator fails while the update message is sent
+        coordinator.reject_messages(true);
+        tick_until(&mut participant, |participant| {
+            matches!(participant.circuit_state(), CircuitState::Open { .. })
+        });
+        assert!(coordinator.updates().is_empty());
+        assert!(matches!(participant.task(), Task::Update));
+        let remaining = |participant: &Participant| match participant.circuit_state() {
+            CircuitState::Open { remaining } => remaining,
+            state => panic!("unexpected circuit state {:?}", state),
+        };
+        let backoff = remaining(&participant);
+
+        // the backoff is jittered once, not by every checkpoint or restored participant
+        for _ in 0..3 {
+            let checkpoint = participant.prepare_for_shutdown();
+            assert!(remaining(&participant) <= backoff);
+            assert!(remaining(&participant) > backoff - Duration::from_secs(10));
+            participant = Participant::restore(&checkpoint, &coordinator.url).unwrap();
+            assert!(remaining(&participant) <= backoff);
+            assert!(remaining(&participant) > backoff - Duration::from_secs(10));
+        }
+
+        // the restored participant resumes sending the masked model once the backoff elapsed
+        assert!(!participant.should_set_model());
+        assert!(matches!(
+            deserialize_state(&participant.save()).unwrap().0,
+            SerializableState::SendingUpdate(_)
+        ));
+    }
+
+    #[test]
+    fn test_consent_disabled() {
+        let mut participant = participant();
+        participant.tick();
+        assert!(participant.consent_request().is_none());
+        assert!(!participant.grant_consent());
+        assert!(!participant.deny_consent());
+    }
+
+    /// Craft the state of a participant that awaits the consent of the user for the sum
+    /// task, from the state of a participant in the awaiting phase.
+    fn consent_state(awaiting: &[u8], request: ConsentRequest) -> Vec<u8> {
+        with_state(awaiting, |state| state.into_awaiting_consent(request))
+    }
+
+    #[test]
+    fn test_consent() {
+        sodiumoxide::init().unwrap();
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url("http://localhost:1".to_string());
+        settings.set_require_consent(true);
+        let awaiting = Participant::new(settings).unwrap().save();
+
+        let request = ConsentRequest {
+            task: ConsentTask::Sum,
+            upload_bytes: 1024,
+        };
+        let mut participant =
+            Participant::restore(&consent_state(&awaiting, request), "http://localhost:1").unwrap();
+
+        // the participant waits for the consent
+        assert_eq!(participant.consent_request(), Some(request));
+        participant.tick();
+        assert!(!participant.made_progress());
+        assert_eq!(participant.consent_request(), Some(request));
+
+        // a restored participant still waits for the consent
+        let mut participant =
+            Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert_eq!(participant.consent_request(), Some(request));
+
+        // the decision is part of the participant state
+        assert!(participant.deny_consent());
+        assert!(participant.consent_request().is_none());
+        assert!(!participant.grant_consent());
+        let participant = Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert!(participant.consent_request().is_none());
+    }
+
+    #[test]
+    fn test_global_model_cache() {
+        let mut participant = participant();
+        // the coordinator is unreachable
+        assert!(participant.global_model_len().is_err());
+        assert!(participant.global_model().is_err());
+
+        // a cached global model is not fetched again
+        let model = Model::from_primitives(vec![1_i32; 4].into_iter()).unwrap();
+        participant.global_model = Some(model.clone());
+        assert_eq!(participant.global_model_len().unwrap(), Some(4));
+        assert_eq!(participant.global_model().unwrap(), Some(model.clone()));
+        assert_eq!(participant.global_model().unwrap(), Some(model.clone()));
+
+        // the cache is kept if the global model didn't change in the last round, the
+        // unreachable coordinator isn't asked for it
+        participant.notifier.notify(Event::NewRound);
+        participant.notifier.notify(Event::ModelUnchanged);
+        participant.process_events();
+        assert!(!participant.new_global_model());
+        assert_eq!(participant.global_model_len().unwrap(), Some(4));
+        assert_eq!(participant.global_model().unwrap(), Some(model));
+
+        // the cache is invalidated when a new round starts
+        participant.notifier.notify(Event::NewRound);
+        participant.process_events();
+        assert!(participant.new_global_model());
+        assert!(participant.global_model_len().is_err());
+    }
+
+    #[test]
+    fn test_set_sparse_model() {
+        let mut participant = participant();
+        let stored_model = |participant: &mut Participant| {
+            let store = participant.store.0.clone();
+            participant
+                .runtime
+                .block_on(async move { store.lock().await.clone() })
+        };
+        let values = Model::from_primitives(vec![7_i32, 8].into_iter()).unwrap();
+
+        // without a global model, the other weights are zero
+        participant
+            .set_sparse_model(&[3, 0], values.clone(), 4)
+            .unwrap();
+        let expected = Model::from_primitives(vec![8_i32, 0, 0, 7].into_iter()).unwrap();
+        assert_eq!(stored_model(&mut participant), Some(expected));
+
+        // otherwise they are the weights of the last known global model
+        participant.global_model =
+            Some(Model::from_primitives(vec![1_i32; 4].into_iter()).unwrap());
+        participant
+            .set_sparse_model(&[1, 2], values.clone(), 4)
+            .unwrap();
+        let expected = Model::from_primitives(vec![1_i32, 7, 8, 1].into_iter()).unwrap();
+        assert_eq!(stored_model(&mut participant), Some(expected.clone()));
+
+        assert!(matches!(
+            participant.set_sparse_model(&[1, 4], values.clone(), 4),
+            Err(SparseModelError::IndexOutOfRange { index: 4, len: 4 })
+        ));
+        assert!(matches!(
+            participant.set_sparse_model(&[2, 2], values.clone(), 4),
+            Err(SparseModelError::DuplicateIndex(2))
+        ));
+        assert!(matches!(
+            participant.set_sparse_model(&[1], values.clone(), 4),
+            Err(SparseModelError::LengthMismatch { .. })
+        ));
+        // the model is left untouched on error
+        assert_eq!(stored_model(&mut participant), Some(expected));
+        assert!(!participant.model_shape_changed());
+
+        // the last known global model is discarded if the shape of the model changed
+        participant
+            .set_sparse_model(&[1, 2], values.clone(), 5)
+            .unwrap();
+        assert!(participant.model_shape_changed());
+        let expected = Model::from_primitives(vec![0_i32, 7, 8, 0, 0].into_iter()).unwrap();
+        assert_eq!(stored_model(&mut participant), Some(expected));
+
+        participant.global_model =
+            Some(Model::from_primitives(vec![1_i32; 4].into_iter()).unwrap());
+        assert!(matches!(
+            participant.set_sparse_model(&[1, 2], values, 5),
+            Err(SparseModelError::GlobalModelLength { .. })
+        ));
+        // the rejected call leaves the participant state untouched
+        assert!(participant.model_shape_changed());
+        let expected = Model::from_primitives(vec![0_i32, 7, 8, 0, 0].into_iter()).unwrap();
+        assert_eq!(stored_model(&mut participant), Some(expected));
+    }
+
+    #[test]
+    fn test_set_sparse_model_rejected() {
+        let mut participant = participant();
+        let values = Model::from_primitives(vec![7_i32, 8].into_iter()).unwrap();
+        participant.global_model =
+            Some(Model::from_primitives(vec![1_i32; 4].into_iter()).unwrap());
+        assert!(matches!(
+            participant.set_sparse_model(&[1, 2], values, 5),
+            Err(SparseModelError::GlobalModelLength {
+                expected: 5,
+                found: 4
+            })
+        ));
+
+        // the rejected call changed neither the expected model length nor the models
+        assert_eq!(participant.model_len, None);
+        assert!(participant.global_model.is_some());
+        let store = participant.store.0.clone();
+        assert!(participant
+            .runtime
+            .block_on(async move { store.lock().await.clone() })
+            .is_none());
+        let model = Model::from_primitives(vec![1_i32; 4].into_iter()).unwrap();
+        participant.set_model(model).unwrap();
+        assert!(!participant.model_shape_changed());
+    }
+
+    /// A coordinator which selects every participant for the update task, serves a sum
+    /// dictionary with a single sum participant and records the messages it receives.
+    struct MockCoordinator {
+        url: String,
+        keys: EncryptKeyPair,
+        params: Arc<StdMutex<RoundParameters>>,
+        messages: Arc<StdMutex<Vec<Vec<u8>>>>,
+        /// Whether the messages are rejected with a server error.
+        rejecting: Arc<AtomicBool>,
+    }
+
+    impl MockCoordinator {
+        /// Start a coordinator whose first round expects models of `model_length` weights.
+        fn start(model_length: usize) -> Self {
+            let keys = EncryptKeyPair::generate();
+            let params = Arc::new(StdMutex::new(Self::round_params(&keys, model_length)));
+            let messages = Arc::new(StdMutex::new(Vec::new()));
+            let rejecting = Arc::new(AtomicBool::new(false));
+            let mut sum_dict = SumDict::new();
+            sum_dict.insert(
+                SigningKeyPair::generate().public,
+                EncryptKeyPair::generate().public,
+            );
+            let sum_dict = Arc::new(bincode::serialize(&sum_dict).unwrap());
+
+            let (service_params, service_messages, service_rejecting) =
+                (params.clone(), messages.clone(), rejecting.clone());
+            let make_service = make_service_fn(move |_| {
+                let (params, messages, rejecting, sum_dict) = (
+                    service_params.clone(),
+                    service_messages.clone(),
+                    service_rejecting.clone(),
+                    sum_dict.clone(),
+                );
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                        let (params, messages, rejecting, sum_dict) = (
+                            params.clone(),
+                            messages.clone(),
+                            rejecting.clone(),
+                            sum_dict.clone(),
+                        );
+                        async move {
+                            let body = match (request.method(), request.uri().path()) {
+                                (&Method::GET, "/params") => {
+                                    bincode::serialize(&*params.lock().unwrap()).unwrap()
+                                }
+                                (&Method::GET, "/sums") => sum_dict.as_ref().clone(),
+                                (&Method::POST, "/message") if rejecting.load(Ordering::SeqCst) => {
+                                    return Ok::<_, Infallible>(
+                                        Response::builder()
+                                            .status(StatusCode::SERVICE_UNAVAILABLE)
+                                            .body(Body::empty())
+                                            .unwrap(),
+                                    )
+                                }
+                                (&Method::POST, "/message") => {
+                                    let message =
+                                        hyper::body::to_bytes(request.into_body()).await.unwrap();
+                                    messages.lock().unwrap().push(message.to_vec());
+                                    Vec::new()
+                                }
+                                // no global model is available
+                                _ => {
+                                    return Ok::<_, Infallible>(
+                                        Response::builder()
+                                            .status(StatusCode::NO_CONTENT)
+                                            .body(Body::empty())
+                                            .unwrap(),
+                                    )
+                                }
+                            };
+                            Ok(Response::new(Body::from(body)))
+                        }
+                    }))
+                }
+            });
+
+            // the participant blocks on its own runtime, so the coordinator runs on another
+            // thread
+            let (address_tx, address_rx) = std::sync::mpsc::channel();
+            thread::spawn(move || {
+                Participant::runtime().unwrap().block_on(async move {
+                    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+                    address_tx.send(server.local_addr()).unwrap();
+                    server.await.unwrap();
+                })
+            });
+            let url = format!("http://{}", address_rx.recv().unwrap());
+
+            Self {
+                url,
+                keys,
+                params,
+                messages,
+                rejecting,
+            }
+        }
+
+        fn round_params(keys: &EncryptKeyPair, model_length: usize) -> RoundParameters {
+            let config = MaskConfig {
+                group_type: GroupType::Prime,
+                data_type: DataType::F32,
+                bound_type: BoundType::B0,
+                model_type: ModelType::M3,
+            };
+            RoundParameters {
+                pk: keys.public,
+                sum: 0.,
+                update: 1.,
+                seed: RoundSeed::generate(),
+                mask_config: config.into(),
+                model_length,
+                plan_id: None,
+                max_sample_count: None,
+            }
+        }
+
+        /// Reject the messages with a server error, or accept them again.
+        fn reject_messages(&self, reject: bool) {
+            self.rejecting.store(reject, Ordering::SeqCst);
+        }
+
+        /// Start a new round which expects models of `model_length` weights.
+        fn new_round(&self, model_length: usize) {
+            *self.params.lock().unwrap() = Self::round_params(&self.keys, model_length);
+        }
+
+        /// The update messages received so far.
+        fn update_messages(&self) -> Vec<Message> {
+            self.messages
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|message| {
+                    let message = self
+                        .keys
+                        .secret
+                        .decrypt(message, &self.keys.public)
+                        .unwrap();
+                    Message::from_byte_slice(&message).unwrap()
+                })
+                .filter(|message| matches!(message.payload, Payload::Update(_)))
+                .collect()
+        }
+
+        /// The lengths of the masked models of the update messages received so far.
+        fn updates(&self) -> Vec<usize> {
+            self.update_messages()
+                .into_iter()
+                .filter_map(|message| match message.payload {
+                    Payload::Update(update) => Some(update.masked_model.vect.data.len()),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        /// The public keys of the participants that sent the update messages received so
+        /// far.
+        fn update_senders(&self) -> Vec<ParticipantPublicKey> {
+            self.update_messages()
+                .into_iter()
+                .map(|message| message.participant_pk)
+                .collect()
+        }
+    }
+
+    /// Tick the participant until `condition` holds.
+    fn tick_until(participant: &mut Participant, condition: impl Fn(&Participant) -> bool) {
+        for _ in 0..20 {
+            participant.tick();
+            if condition(participant) {
+                return;
+            }
+        }
+        panic!(
+            "the participant is stuck in the {:?} task",
+            participant.task()
+        );
+    }
+
+    fn model(len: usize) -> Model {
+        Model::from_primitives(vec![0.5_f32; len].into_iter()).unwrap()
+    }
+
+    #[test]
+    fn test_model_shape_changed() {
+        sodiumoxide::init().unwrap();
+        let coordinator = MockCoordinator::start(10);
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url(coordinator.url.clone());
+        let mut participant = Participant::new(settings).unwrap();
+
+        // the old build of the app sets a model of 10 weights, which is staged in the state
+        // it saves
+        tick_until(&mut participant, Participant::should_set_model);
+        participant.set_model(model(10)).unwrap();
+        assert!(!participant.model_shape_changed());
+        // like the states saved before `StateVersion::V2`, the state doesn't record the
+        // length of the model
+        let state = bincode::serialize(&deserialize_state(&participant.save()).unwrap().0).unwrap();
+        let state = seal_state(StateVersion::CURRENT, state);
+
+        // the app update changed the model architecture, and the coordinator started a round
+        // with the new model length
+        coordinator.new_round(12);
+        let mut participant = Participant::restore(&state, &coordinator.url).unwrap();
+        assert!(!participant.should_set_model());
+        participant.global_model = Some(model(10));
+        // the participant didn't observe the new round yet
+        assert!(matches!(
+            participant.set_model(model(12)),
+            Err(ModelLengthError {
+                expected: 10,
+                found: 12
+            })
+        ));
+        assert!(participant.model_shape_changed());
+        assert!(participant.global_model.is_none());
+        // the staged model is discarded, the participant asks for the model again
+        assert!(participant.should_set_model());
+
+        // the new model length is part of the participant state
+        let mut participant = Participant::restore(&participant.save(), &coordinator.url).unwrap();
+        tick_until(&mut participant, |participant| {
+            participant.should_set_model()
+                && participant.round_params().map(|params| params.model_length) == Some(12)
+        });
+        participant.set_model(model(12)).unwrap();
+        assert!(!participant.model_shape_changed());
+        tick_until(&mut participant, |participant| {
+            matches!(participant.task(), Task::None)
+        });
+        assert_eq!(coordinator.updates(), vec![12]);
+    }
+
+    #[test]
+    fn test_rotate_keys() {
+        sodiumoxide::init().unwrap();
+        let coordinator = MockCoordinator::start(10);
+        let keys = SigningKeyPair::generate();
+        let mut settings = Settings::new();
+        settings.set_keys(keys.clone());
+        settings.set_url(coordinator.url.clone());
+        let mut participant = Participant::new(settings).unwrap();
+
+        // the keys are rotated in the middle of the update task
+        tick_until(&mut participant, Participant::should_set_model);
+        let new_key = participant.rotate_keys();
+        assert_ne!(new_key, keys.public);
+        assert_eq!(participant.public_key(), keys.public);
+
+        // the old keys are used until the round completes, also across a restore
+        let mut participant = Participant::restore(&participant.save(), &coordinator.url).unwrap();
+        participant.set_model(model(10)).unwrap();
+        tick_until(&mut participant, |participant| {
+            matches!(participant.task(), Task::None)
+        });
+        assert_eq!(participant.public_key(), keys.public);
+        assert_eq!(coordinator.update_senders(), vec![keys.public]);
+
+        // the new keys are applied when the next round starts
+        coordinator.new_round(10);
+        tick_until(&mut participant, Participant::should_set_model);
+        assert_eq!(participant.public_key(), new_key);
+        participant.set_model(model(10)).unwrap();
+        tick_until(&mut participant, |participant| {
+            matches!(participant.task(), Task::None)
+        });
+        assert_eq!(coordinator.update_senders(), vec![keys.public, new_key]);
+    }
+
+    #[test]
+    fn test_rotate_tls_certificates() {
+        sodiumoxide::init().unwrap();
+        let coordinator = MockCoordinator::start(10);
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url(coordinator.url.clone());
+        let mut participant = Participant::new(settings).unwrap();
+
+        // the certificates are rotated in the middle of the update task
+        tick_until(&mut participant, Participant::should_set_model);
+        participant
+            .set_tls_root_certificate(include_bytes!("../tests/data/root.pem"))
+            .unwrap();
+        participant
+            .set_tls_identity(include_bytes!("../tests/data/client.pem"))
+            .unwrap();
+        // an identity without a private key is rejected
+        assert!(matches!(
+            participant.set_tls_identity(include_bytes!("../tests/data/root.pem")),
+            Err(ClientError::ClientCert(_, _))
+        ));
+
+        // the participant completes its task with the new HTTP client
+        participant.set_model(model(10)).unwrap();
+        tick_until(&mut participant, |participant| {
+            matches!(participant.task(), Task::None)
+        });
+        assert_eq!(coordinator.updates(), vec![10]);
+    }
+
+    #[test]
+    fn test_history() {
+        sodiumoxide::init().unwrap();
+        let coordinator = MockCoordinator::start(10);
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url(coordinator.url.clone());
+        settings.set_max_history_len(1);
+        let mut participant = Participant::new(settings).unwrap();
+        let seed_hash = |participant: &Participant| {
+            sha256::hash(participant.round_params().unwrap().seed.as_slice()).0
+        };
+
+        // the participant sends an update in the first round
+        tick_until(&mut participant, Participant::should_set_model);
+        let first = seed_hash(&participant);
+        participant.set_model(model(10)).unwrap();
+        tick_until(&mut participant, |participant| {
+            matches!(participant.task(), Task::None)
+        });
+        assert_eq!(coordinator.updates(), vec![10]);
+        // the round is completed when the next one starts
+        assert!(participant.history().is_empty());
+
+        // the round in progress is part of the participant state
+        let mut participant = Participant::restore(&participant.save(), &coordinator.url).unwrap();
+        coordinator.new_round(10);
+        tick_until(&mut participant, Participant::should_set_model);
+        let second = seed_hash(&participant);
+        let record = RoundRecord {
+            round_seed_hash: first,
+            task: Task::Update,
+            message_sent: true,
+        };
+        assert_eq!(participant.history(), &[record]);
+
+        // the participant doesn't send its update in the second round, which replaces the
+        // first one in the history
+        coordinator.new_round(10);
+        tick_until(&mut participant, |participant| {
+            participant.history()[0].round_seed_hash == second
+        });
+        let record = RoundRecord {
+            round_seed_hash: second,
+            task: Task::Update,
+            message_sent: false,
+        };
+        assert_eq!(participant.history(), &[record]);
+        let participant = Participant::restore(&participant.save(), &coordinator.url).unwrap();
+        assert_eq!(participant.history(), &[record]);
+    }
+
+    #[test]
+    fn test_next_wakeup_recommendation() {
+        use crate::{WakeupReason, WorkClass};
+
+        let participant = participant();
+        let recommendation = participant.next_wakeup_recommendation();
+        assert_eq!(recommendation.expected_work, WorkClass::Poll);
+        assert_eq!(recommendation.reason, WakeupReason::Idle);
+        assert!(recommendation.earliest <= recommendation.latest);
+        let participant = Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert_eq!(participant.next_wakeup_recommendation(), recommendation);
+
+        // a participant that awaits the consent of the user makes the same
+        // recommendation once restored
+        let request = ConsentRequest {
+            task: ConsentTask::Update,
+            upload_bytes: 1024,
+        };
+        let state = consent_state(&participant.save(), request);
+        let participant = Participant::restore(&state, "http://localhost:1").unwrap();
+        let recommendation = participant.next_wakeup_recommendation();
+        assert_eq!(recommendation.expected_work, WorkClass::Poll);
+        assert_eq!(recommendation.reason, WakeupReason::AwaitingUser);
+        let participant = Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert_eq!(participant.next_wakeup_recommendation(), recommendation);
+
+        // the exhaustion of the data budget postpones the wake-up until the usage is reset
+        let mut participant = participant;
+        participant.set_daily_data_budget(Some(0));
+        let recommendation = participant.next_wakeup_recommendation();
+        assert_eq!(recommendation.reason, WakeupReason::DataBudgetExceeded);
+        assert!(recommendation.earliest <= recommendation.latest);
+        assert!(recommendation.earliest > Duration::from_secs(23 * 60 * 60));
+    }
+
+    /// Craft the state of a participant that observed `rounds` rounds, from the state
+    /// of a participant in the awaiting phase.
+    fn rounds_observed_state(awaiting: &[u8], rounds: u64) -> Vec<u8> {
+        with_state(awaiting, |mut state| {
+            state.set_round_id(rounds);
+            state
+        })
+    }
+
+    #[test]
+    fn test_rounds_observed() {
+        let mut participant = participant();
+        assert_eq!(participant.rounds_observed(), 0);
+        participant.tick();
+        assert_eq!(participant.rounds_observed(), 0);
+        // the coordinator is unreachable, no round has been observed
+        assert!(participant.round_params().is_none());
+
+        let state = rounds_observed_state(&participant.save(), 41);
+        let mut participant = Participant::restore(&state, "http://localhost:1").unwrap();
+        assert_eq!(participant.rounds_observed(), 41);
+
+        // the number of rounds never decreases across save and restore cycles
+        for _ in 0..3 {
+            participant.tick();
+            let rounds = participant.rounds_observed();
+            assert!(rounds >= 41);
+            let checkpoint = participant.prepare_for_shutdown();
+            participant = Participant::restore(&checkpoint, "http://localhost:1").unwrap();
+            assert_eq!(participant.rounds_observed(), rounds);
+            participant = Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+            assert_eq!(participant.rounds_observed(), rounds);
+        }
+    }
+
+    #[test]
+    fn test_public_key() {
+        sodiumoxide::init().unwrap();
+        let keys = SigningKeyPair::generate();
+        let mut settings = Settings::new();
+        settings.set_keys(keys.clone());
+        settings.set_url("http://localhost:1".to_string());
+        let mut participant = Participant::new(settings).unwrap();
+        assert_eq!(participant.public_key(), keys.public);
+
+        participant.tick();
+        let checkpoint = participant.prepare_for_shutdown();
+        let participant = Participant::restore(&checkpoint, "http://localhost:1").unwrap();
+        assert_eq!(participant.public_key(), keys.public);
+        let participant = Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        assert_eq!(participant.public_key(), keys.public);
+    }
+
+    struct Recorder(Arc<StdMutex<Vec<StateChange>>>);
+
+    impl StateObserver for Recorder {
+        fn state_changed(&mut self, change: StateChange) {
+            self.0.lock().unwrap().push(change);
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Recorded {
+        PhaseEntered(Task),
+        MessageSent(usize),
+    }
+
+    struct ProgressRecorder(Arc<StdMutex<Vec<Recorded>>>);
+
+    impl ProgressObserver for ProgressRecorder {
+        fn on_phase_entered(&mut self, task: Task) {
+            self.0.lock().unwrap().push(Recorded::PhaseEntered(task));
+        }
+
+        fn on_message_sent(&mut self, bytes: usize) {
+            self.0.lock().unwrap().push(Recorded::MessageSent(bytes));
+        }
+    }
+
+    #[test]
+    fn test_progress_observer() {
+        let mut participant = participant();
+        let progress = Arc::new(StdMutex::new(Vec::new()));
+        participant.set_progress_observer(Some(Box::new(ProgressRecorder(progress.clone()))));
+
+        // the participant entered the awaiting phase when it was created
+        participant.tick();
+        assert_eq!(
+            std::mem::take(&mut *progress.lock().unwrap()),
+            vec![Recorded::PhaseEntered(Task::None)],
+        );
+        participant.tick();
+        assert!(progress.lock().unwrap().is_empty());
+
+        participant.notifier.notify(Event::MessageSent(42));
+        participant.notifier.notify(Event::Sum);
+        participant.tick();
+        assert_eq!(
+            std::mem::take(&mut *progress.lock().unwrap()),
+            vec![Recorded::MessageSent(42), Recorded::PhaseEntered(Task::Sum)],
+        );
+
+        // the observer is not part of the persistent state and must be set again after
+        // the participant is restored, which notifies the phase it resumes in
+        let mut participant =
+            Participant::restore(&participant.save(), "http://localhost:1").unwrap();
+        participant.tick();
+        assert!(progress.lock().unwrap().is_empty());
+        participant.set_progress_observer(Some(Box::new(ProgressRecorder(progress.clone()))));
+        participant.notifier.notify(Event::Idle);
+        participant.tick();
+        assert_eq!(
+            *progress.lock().unwrap(),
+            vec![Recorded::PhaseEntered(Task::None)],
+        );
+
+        // a removed observer is not notified anymore
+        participant.set_progress_observer(None);
+        participant.notifier.notify(Event::MessageSent(1));
+        participant.tick();
+        assert_eq!(progress.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_state_observer() {
+        let mut participant = participant();
+        let changes = Arc::new(StdMutex::new(Vec::new()));
+        participant.set_state_observer(Some(Box::new(Recorder(changes.clone()))));
+
+        // the coordinator is unreachable: the participant doesn't make progress until
+        // the circuit breaker opens
+        while !matches!(participant.circuit_state(), CircuitState::Open { .. }) {
+            participant.tick();
+        }
+        participant.tick();
+        assert_eq!(*changes.lock().unwrap(), vec![StateChange::CircuitBreaker]);
+    }
+
+    #[test]
+    fn test_events_overflow() {
+        let (mut events, mut notifier) = Events::new(2);
+        notifier.notify(Event::NewRound);
+        notifier.notify(Event::Sum);
+        notifier.notify(Event::MessageSent(1));
+        notifier.notify(Event::MessageSent(2));
+
+        // the task events are kept, the informational events are dropped and the overflow
+        // is reported once, first
+        assert!(matches!(
+            events.next(),
+            Some(Event::EventsDropped { count: 2 })
+        ));
+        assert!(matches!(events.next(), Some(Event::NewRound)));
+        assert!(matches!(events.next(), Some(Event::Sum)));
+        assert!(events.next().is_none());
+
+        // a new overflow is reported again
+        for bytes in 0..3 {
+            notifier.notify(Event::MessageSent(bytes));
+        }
+        assert!(matches!(
+            events.next(),
+            Some(Event::EventsDropped { count: 1 })
+        ));
+        assert!(matches!(events.next(), Some(Event::MessageSent(1))));
+        assert!(matches!(events.next(), Some(Event::MessageSent(2))));
+        assert!(events.next().is_none());
+        assert_eq!(events.dropped(), 3);
+    }
+
+    #[test]
+    fn test_task_events_overflow() {
+        let (mut events, mut notifier) = Events::new(2);
+        notifier.notify(Event::MessageSent(1));
+        notifier.notify(Event::Sum2MaskReady);
+        notifier.notify(Event::LoadModel);
+        notifier.notify(Event::Idle);
+
+        // the oldest informational event is dropped, then the task events are kept beyond
+        // the capacity of the queue
+        assert!(matches!(
+            events.next(),
+            Some(Event::EventsDropped { count: 1 })
+        ));
+        assert!(matches!(events.next(), Some(Event::Sum2MaskReady)));
+        assert!(matches!(events.next(), Some(Event::LoadModel)));
+        assert!(matches!(events.next(), Some(Event::Idle)));
+        assert!(events.next().is_none());
+        assert_eq!(events.dropped(), 1);
+    }
+
+    #[test]
+    fn test_restore_max_pending_events() {
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url("http://localhost:1".to_string());
+        settings.set_max_pending_events(1);
+        let state = Participant::new(settings).unwrap().save();
+
+        // the restored participant keeps the configured maximum number of pending events
+        let participant = Participant::restore(&state, "http://localhost:1").unwrap();
+        assert_eq!(participant.events.capacity(), 1);
+        assert_same_state(&participant.save(), &state);
+    }
+
+    #[test]
+    fn test_dropped_events() {
+        let mut settings = Settings::new();
+        settings.set_keys(SigningKeyPair::generate());
+        settings.set_url("http://localhost:1".to_string());
+        settings.set_max_pending_events(1);
+        let mut participant = Participant::new(settings).unwrap();
+        let progress = Arc::new(StdMutex::new(Vec::new()));
+        participant.set_progress_observer(Some(Box::new(ProgressRecorder(progress.clone()))));
+        participant.tick();
+        progress.lock().unwrap().clear();
+
+        participant.notifier.notify(Event::MessageSent(1));
+        participant.notifier.notify(Event::Sum);
+        participant.tick();
+        assert_eq!(participant.dropped_events(), 1);
+        assert_eq!(participant.task(), Task::Sum);
+        assert_eq!(
+            *progress.lock().unwrap(),
+            vec![Recorded::PhaseEntered(Task::Sum)],
+        );
+    }
+
+    #[test]
+    fn test_save_with_full_event_queue() {
+        sodiumoxide::init().unwrap();
+        let keys = SigningKeyPair::generate();
+        let new_participant = || {
+            let mut settings = Settings::new();
+            settings.set_keys(keys.clone());
+            settings.set_url("http://localhost:1".to_string());
+            Participant::new(settings).unwrap()
+        };
+        let state = new_participant().save();
+
+        // the pending events are not part of the persistent state
+        let mut participant = new_participant();
+        for bytes in 0..2 * DEFAULT_MAX_PENDING_EVENTS {
+            participant.notifier.notify(Event::MessageSent(bytes));
+        }
+        assert_same_state(&participant.save(), &state);
+    }
+}
@@ -2,13 +2,18 @@
 //!
 //! [`Participant`]: crate::Participant
 
-use std::convert::TryInto;
+use std::{convert::TryInto, time::Duration};
 use thiserror::Error;
 use xaynet_core::{
     crypto::SigningKeyPair,
-    mask::{FromPrimitive, PrimitiveCastError, Scalar},
+    mask::{Scalar, ScalarCastError},
 };
-use xaynet_sdk::settings::{MaxMessageSize, PetSettings};
+use xaynet_sdk::{
+    client::DEFAULT_POOL_IDLE_TIMEOUT,
+    settings::{CircuitBreakerSettings, MaxMessageSize, PetSettings},
+};
+
+use crate::{history::DEFAULT_MAX_HISTORY_LEN, participant::DEFAULT_MAX_PENDING_EVENTS};
 
 /// A participant settings
 #[derive(Clone, Debug)]
@@ -18,9 +23,31 @@ pub struct Settings {
     /// The participant signing keys.
     keys: Option<SigningKeyPair>,
     /// The scalar used for masking.
-    scalar: Result<Scalar, PrimitiveCastError<f64>>,
+    scalar: Result<Scalar, ScalarCastError>,
     /// The maximum possible size of a message.
     max_message_size: MaxMessageSize,
+    /// The maximum number of bytes the participant may consume per day.
+    daily_data_budget_bytes: Option<u64>,
+    /// The settings of the circuit breaker for the requests to the coordinator.
+    circuit_breaker: CircuitBreakerSettings,
+    /// Whether the participant pauses before sending the sum2 message, until the
+    /// global mask is confirmed.
+    confirm_sum2: bool,
+    /// Whether the participant asks for the consent of the user before taking part in a
+    /// task.
+    require_consent: bool,
+    /// How long the participant waits for the consent of the user before abandoning the
+    /// task.
+    consent_timeout: Option<Duration>,
+    /// The safety margin the participant keeps before the end of a phase when checking
+    /// whether it can complete its task in time.
+    deadline_margin: Option<Duration>,
+    /// How long an idle connection to the coordinator is kept open for the next request.
+    pool_idle_timeout: Duration,
+    /// The maximum number of events the participant keeps until it processes them.
+    max_pending_events: usize,
+    /// The maximum number of rounds the participant keeps in its history.
+    max_history_len: usize,
 }
 
 impl Default for Settings {
@@ -37,6 +64,15 @@ impl Settings {
             keys: None,
             scalar: Ok(Scalar::unit()),
             max_message_size: MaxMessageSize::default(),
+            daily_data_budget_bytes: None,
+            circuit_breaker: CircuitBreakerSettings::default(),
+            confirm_sum2: false,
+            require_consent: false,
+            consent_timeout: None,
+            deadline_margin: None,
+            pool_idle_timeout: DEFAULT_POOL_IDLE_TIMEOUT,
+            max_pending_events: DEFAULT_MAX_PENDING_EVENTS,
+            max_history_len: DEFAULT_MAX_HISTORY_LEN,
         }
     }
 
@@ -45,9 +81,11 @@ impl Settings {
         self.keys = Some(keys);
     }
 
-    /// Set the scalar to use for masking
+    /// Set the scalar to use for masking. The scalar is rounded to [`Scalar::DECIMALS`]
+    /// decimal digits in the [`Scalar::ROUNDING`] mode, which is the scalar the coordinator
+    /// unmasks.
     pub fn set_scalar(&mut self, scalar: f64) {
-        self.scalar = Scalar::from_primitive(scalar)
+        self.scalar = Scalar::from_f64_checked(scalar, Scalar::ROUNDING)
     }
 
     /// Set the Xaynet coordinator address
@@ -60,6 +98,111 @@ impl Settings {
         self.max_message_size = size;
     }
 
+    /// Sets the maximum number of bytes the participant may send and receive per day. If
+    /// `budget` is `None`, the data usage is not limited.
+    pub fn set_daily_data_budget(&mut self, budget: Option<u64>) {
+        self.daily_data_budget_bytes = budget;
+    }
+
+    /// Return the maximum number of bytes the participant may send and receive per day.
+    pub fn daily_data_budget(&self) -> Option<u64> {
+        self.daily_data_budget_bytes
+    }
+
+    /// Sets the circuit breaker that stops the participant from sending requests to the
+    /// coordinator when it repeatedly fails with server errors.
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: CircuitBreakerSettings) {
+        self.circuit_breaker = circuit_breaker;
+    }
+
+    /// Sets whether the participant pauses before sending the sum2 message, until the
+    /// global mask it aggregated is confirmed with [`Participant::confirm_sum2()`].
+    ///
+    /// [`Participant::confirm_sum2()`]: crate::Participant::confirm_sum2
+    pub fn set_confirm_sum2(&mut self, confirm: bool) {
+        self.confirm_sum2 = confirm;
+    }
+
+    /// Sets whether the participant pauses when it is selected for a task, until the user
+    /// consents to take part in it with [`Participant::grant_consent()`] or refuses with
+    /// [`Participant::deny_consent()`]. Nothing is uploaded in the meantime.
+    ///
+    /// [`Participant::grant_consent()`]: crate::Participant::grant_consent
+    /// [`Participant::deny_consent()`]: crate::Participant::deny_consent
+    pub fn set_require_consent(&mut self, require: bool) {
+        self.require_consent = require;
+    }
+
+    /// Sets how long the participant waits for the consent of the user before abandoning
+    /// the task. If `timeout` is `None`, the participant waits until the end of the round,
+    /// which is the default.
+    pub fn set_consent_timeout(&mut self, timeout: Option<Duration>) {
+        self.consent_timeout = timeout;
+    }
+
+    /// Sets the safety margin the participant keeps before the end of the update and sum2
+    /// phases. If set, the participant abandons its task when it can't complete it before
+    /// the deadline of the phase minus `margin`, and emits an [`Event::InsufficientTime`].
+    /// If `margin` is `None`, the participant never abandons its task, which is the
+    /// default.
+    ///
+    /// The margin isn't part of the saved state of the participant, hence it must be set
+    /// again on a restored participant (see [`Participant::set_deadline_margin()`]).
+    ///
+    /// [`Event::InsufficientTime`]: crate::Event::InsufficientTime
+    /// [`Participant::set_deadline_margin()`]: crate::Participant::set_deadline_margin
+    pub fn set_deadline_margin(&mut self, margin: Option<Duration>) {
+        self.deadline_margin = margin;
+    }
+
+    /// Sets how long an idle connection to the coordinator is kept open, such that the
+    /// next request doesn't have to open a new one. Defaults to 90 seconds.
+    pub fn set_pool_idle_timeout(&mut self, timeout: Duration) {
+        self.pool_idle_timeout = timeout;
+    }
+
+    /// Return how long an idle connection to the coordinator is kept open.
+    pub fn pool_idle_timeout(&self) -> Duration {
+        self.pool_idle_timeout
+    }
+
+    /// Sets the maximum number of events that the participant keeps until it processes
+    /// them. If its internal state machine emits more events in a single tick, the oldest
+    /// informational ones are dropped and an [`Event::EventsDropped`] is emitted. The task
+    /// events are never dropped (see [`Event::is_informational()`]). Defaults to
+    /// [`DEFAULT_MAX_PENDING_EVENTS`], and values below `1` are treated as `1`. The
+    /// maximum is part of the saved state of the participant.
+    ///
+    /// [`Event::EventsDropped`]: crate::Event::EventsDropped
+    /// [`Event::is_informational()`]: crate::Event::is_informational
+    pub fn set_max_pending_events(&mut self, max: usize) {
+        self.max_pending_events = max;
+    }
+
+    /// Return the maximum number of events that the participant keeps until it processes
+    /// them.
+    pub fn max_pending_events(&self) -> usize {
+        self.max_pending_events
+    }
+
+    /// Sets the maximum number of completed rounds the participant keeps in its history
+    /// (see [`Participant::history()`]). When the history is full, the oldest round is
+    /// dropped. Defaults to [`DEFAULT_MAX_HISTORY_LEN`], and `0` disables the history.
+    ///
+    /// The history is part of the saved state of the participant, and a restored
+    /// participant keeps the maximum length it was created with.
+    ///
+    /// [`Participant::history()`]: crate::Participant::history
+    pub fn set_max_history_len(&mut self, max: usize) {
+        self.max_history_len = max;
+    }
+
+    /// Return the maximum number of completed rounds the participant keeps in its
+    /// history.
+    pub fn max_history_len(&self) -> usize {
+        self.max_history_len
+    }
+
     /// Check whether the settings are complete and valid
     pub fn check(&self) -> Result<(), SettingsError> {
         if self.url.is_none() {
@@ -82,7 +225,7 @@ pub enum SettingsError {
     #[error("the participant signing key pair must be specified")]
     MissingKeys,
     #[error("float not within range of scalar: {0}")]
-    OutOfScalarRange(#[from] PrimitiveCastError<f64>),
+    OutOfScalarRange(#[from] ScalarCastError),
 }
 
 impl TryInto<(String, PetSettings)> for Settings {
@@ -94,17 +237,26 @@ impl TryInto<(String, PetSettings)> for Settings {
             url,
             scalar,
             max_message_size,
+            circuit_breaker,
+            confirm_sum2,
+            require_consent,
+            consent_timeout,
+            deadline_margin,
+            ..
         } = self;
 
         let url = url.ok_or(SettingsError::MissingUrl)?;
         let keys = keys.ok_or(SettingsError::MissingKeys)?;
         let scalar = scalar.map_err(SettingsError::OutOfScalarRange)?;
 
-        let pet_settings = PetSettings {
-            keys,
-            scalar,
-            max_message_size,
-        };
+        let mut pet_settings = PetSettings::new(keys);
+        pet_settings.scalar = scalar;
+        pet_settings.max_message_size = max_message_size;
+        pet_settings.circuit_breaker = circuit_breaker;
+        pet_settings.confirm_sum2 = confirm_sum2;
+        pet_settings.require_consent = require_consent;
+        pet_settings.consent_timeout = consent_timeout;
+        pet_settings.deadline_margin = deadline_margin;
 
         Ok((url, pet_settings))
     }
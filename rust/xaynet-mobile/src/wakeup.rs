@@ -0,0 +1,219 @@
+//! Scheduling hints for the participant.
+//!
+//! On mobile, the participant is usually driven by a background task scheduler that
+//! wakes the app up from time to time. The [`WakeupRecommendation`] returned by
+//! [`Participant::next_wakeup_recommendation()`] tells the scheduler when the
+//! participant should be ticked next, and what kind of work to budget for.
+//!
+//! The coordinator doesn't publish a polling interval nor the deadlines of the PET
+//! phases, so the recommendation falls back to default intervals whenever no better
+//! signal is available.
+//!
+//! [`Participant::next_wakeup_recommendation()`]: crate::Participant::next_wakeup_recommendation
+use std::time::Duration;
+
+use xaynet_sdk::{CircuitState, WorkClass};
+
+/// Earliest time at which a participant that waits for a new round or for the user
+/// should be ticked again.
+const IDLE_EARLIEST: Duration = Duration::from_secs(60);
+/// Latest time at which a participant that waits for a new round or for the user should
+/// be ticked again.
+const IDLE_LATEST: Duration = Duration::from_secs(15 * 60);
+/// Latest time at which a participant that is busy with a task should be ticked again.
+const BUSY_LATEST: Duration = Duration::from_secs(60);
+
+/// The reason of a [`WakeupRecommendation`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WakeupReason {
+    /// The participant waits to be selected for a task.
+    Idle,
+    /// The participant is taking part in a task and can make progress on its own.
+    Busy,
+    /// The participant waits for the model it is expected to train (see
+    /// [`Participant::should_set_model()`]).
+    ///
+    /// [`Participant::should_set_model()`]: crate::Participant::should_set_model
+    AwaitingModel,
+    /// The participant waits for the consent of the user or for the confirmation of the
+    /// global mask it aggregated.
+    AwaitingUser,
+    /// The coordinator repeatedly failed and the participant backs off.
+    CircuitOpen,
+    /// The daily data budget of the participant is exhausted.
+    DataBudgetExceeded,
+}
+
+/// A recommendation of when the participant should be ticked next, see
+/// [`Participant::next_wakeup_recommendation()`].
+///
+/// [`Participant::next_wakeup_recommendation()`]: crate::Participant::next_wakeup_recommendation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WakeupRecommendation {
+    /// Delay before which ticking the participant is not expected to make progress.
+    pub earliest: Duration,
+    /// Delay after which the participant risks missing its task. It is never shorter
+    /// than `earliest`.
+    pub latest: Duration,
+    /// The kind of work the participant performs once woken up.
+    pub expected_work: WorkClass,
+    /// Why the participant should be woken up in this window.
+    pub reason: WakeupReason,
+}
+
+/// The signals of a participant that a [`WakeupRecommendation`] is synthesized from.
+pub(crate) struct WakeupSignals {
+    /// The work pending in the participant internal state machine.
+    pub work: WorkClass,
+    /// The state of the circuit breaker.
+    pub circuit_state: CircuitState,
+    /// The time until the data usage is reset, if the daily data budget is exhausted.
+    pub data_budget_reset: Option<Duration>,
+    /// Whether the participant waits for a decision of the user.
+    pub awaiting_user: bool,
+}
+
+impl WakeupRecommendation {
+    /// Synthesize a recommendation from the given signals. The signals that prevent the
+    /// participant from making progress take precedence over the pending work.
+    pub(crate) fn new(signals: WakeupSignals) -> Self {
+        let WakeupSignals {
+            work,
+            circuit_state,
+            data_budget_reset,
+            awaiting_user,
+        } = signals;
+
+        let (earliest, latest, reason) = match (data_budget_reset, circuit_state) {
+            (Some(reset), circuit_state) => {
+                let earliest = match circuit_state {
+                    CircuitState::Open { remaining } => reset.max(remaining),
+                    _ => reset,
+                };
+                (
+                    earliest,
+                    earliest + IDLE_LATEST,
+                    WakeupReason::DataBudgetExceeded,
+                )
+            }
+            (None, CircuitState::Open { remaining }) => (
+                remaining,
+                remaining + BUSY_LATEST,
+                WakeupReason::CircuitOpen,
+            ),
+            _ if awaiting_user => (IDLE_EARLIEST, IDLE_LATEST, WakeupReason::AwaitingUser),
+            _ => match work {
+                WorkClass::Poll => (IDLE_EARLIEST, IDLE_LATEST, WakeupReason::Idle),
+                WorkClass::Train => (
+                    Duration::from_secs(0),
+                    BUSY_LATEST,
+                    WakeupReason::AwaitingModel,
+                ),
+                WorkClass::Download | WorkClass::Compute | WorkClass::Upload => {
+                    (Duration::from_secs(0), BUSY_LATEST, WakeupReason::Busy)
+                }
+            },
+        };
+
+        Self {
+            earliest,
+            latest,
+            expected_work: work,
+            reason,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signals(work: WorkClass) -> WakeupSignals {
+        WakeupSignals {
+            work,
+            circuit_state: CircuitState::Closed,
+            data_budget_reset: None,
+            awaiting_user: false,
+        }
+    }
+
+    fn recommend(signals: WakeupSignals) -> WakeupRecommendation {
+        let recommendation = WakeupRecommendation::new(signals);
+        assert!(recommendation.earliest <= recommendation.latest);
+        recommendation
+    }
+
+    #[test]
+    fn test_idle_between_rounds() {
+        let recommendation = recommend(signals(WorkClass::Poll));
+        assert_eq!(recommendation.expected_work, WorkClass::Poll);
+        assert_eq!(recommendation.reason, WakeupReason::Idle);
+        assert!(recommendation.earliest > Duration::from_secs(0));
+    }
+
+    #[test]
+    fn test_awaiting_training() {
+        let recommendation = recommend(signals(WorkClass::Train));
+        assert_eq!(recommendation.expected_work, WorkClass::Train);
+        assert_eq!(recommendation.reason, WakeupReason::AwaitingModel);
+        assert_eq!(recommendation.earliest, Duration::from_secs(0));
+        // a busy participant is woken up sooner than an idle one
+        assert!(recommendation.latest < recommend(signals(WorkClass::Poll)).latest);
+    }
+
+    #[test]
+    fn test_mid_upload() {
+        let recommendation = recommend(signals(WorkClass::Upload));
+        assert_eq!(recommendation.expected_work, WorkClass::Upload);
+        assert_eq!(recommendation.reason, WakeupReason::Busy);
+        assert_eq!(recommendation.earliest, Duration::from_secs(0));
+
+        let recommendation = recommend(signals(WorkClass::Download));
+        assert_eq!(recommendation.expected_work, WorkClass::Download);
+        assert_eq!(recommendation.reason, WakeupReason::Busy);
+    }
+
+    #[test]
+    fn test_offline_backoff() {
+        let remaining = Duration::from_secs(42);
+        let recommendation = recommend(WakeupSignals {
+            circuit_state: CircuitState::Open { remaining },
+            ..signals(WorkClass::Upload)
+        });
+        assert_eq!(recommendation.expected_work, WorkClass::Upload);
+        assert_eq!(recommendation.reason, WakeupReason::CircuitOpen);
+        assert_eq!(recommendation.earliest, remaining);
+
+        // once the backoff elapsed, the next request probes the coordinator
+        let recommendation = recommend(WakeupSignals {
+            circuit_state: CircuitState::HalfOpen,
+            ..signals(WorkClass::Upload)
+        });
+        assert_eq!(recommendation.reason, WakeupReason::Busy);
+    }
+
+    #[test]
+    fn test_data_budget_exceeded() {
+        let reset = Duration::from_secs(3600);
+        let recommendation = recommend(WakeupSignals {
+            circuit_state: CircuitState::Open {
+                remaining: Duration::from_secs(42),
+            },
+            data_budget_reset: Some(reset),
+            ..signals(WorkClass::Download)
+        });
+        assert_eq!(recommendation.expected_work, WorkClass::Download);
+        assert_eq!(recommendation.reason, WakeupReason::DataBudgetExceeded);
+        assert_eq!(recommendation.earliest, reset);
+    }
+
+    #[test]
+    fn test_awaiting_user() {
+        let recommendation = recommend(WakeupSignals {
+            awaiting_user: true,
+            ..signals(WorkClass::Poll)
+        });
+        assert_eq!(recommendation.reason, WakeupReason::AwaitingUser);
+        assert_eq!(recommendation.expected_work, WorkClass::Poll);
+    }
+}
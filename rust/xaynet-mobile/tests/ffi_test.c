@@ -1,4 +1,6 @@
 #include <assert.h>
+#include <limits.h>
+#include <pthread.h>
 #include <stdint.h>
 #include <stdio.h>
 #include <stdlib.h>
@@ -94,6 +96,14 @@ static char *test_global_model() {
   err = xaynet_ffi_participant_global_model(participant, buffer, local_model_config->data_type, local_model_config->len);
   mu_assert("expected io error (cannot connect to coordinator)", err == ERR_GLOBALMODEL_IO);
 
+  unsigned int len = 0;
+  err = xaynet_ffi_participant_global_model_len(NULL, &len);
+  mu_assert("expected participant is null error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_global_model_len(participant, NULL);
+  mu_assert("expected len is null error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_global_model_len(participant, &len);
+  mu_assert("expected io error (cannot connect to coordinator)", err == ERR_GLOBALMODEL_IO);
+
   free(buffer);
   xaynet_ffi_local_model_config_destroy(local_model_config);
   xaynet_ffi_participant_destroy(participant);
@@ -102,6 +112,66 @@ static char *test_global_model() {
   return 0;
 }
 
+static char *test_set_sparse_model() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+
+  unsigned int indices[] = {3, 0};
+  float values[] = {0.5, -1.0};
+  int err = xaynet_ffi_participant_set_sparse_model(NULL, indices, values, MODEL_DATA_TYPE_F32, 2, 4);
+  mu_assert("expected participant is null error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_set_sparse_model(participant, NULL, values, MODEL_DATA_TYPE_F32, 2, 4);
+  mu_assert("expected indices is null error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_set_sparse_model(participant, indices, values, 42, 2, 4);
+  mu_assert("expected data type error", err == ERR_SETMODEL_DATATYPE);
+  err = xaynet_ffi_participant_set_sparse_model(participant, indices, values, MODEL_DATA_TYPE_F32, 2, 4);
+  mu_assert("failed to set sparse model", err == OK);
+
+  unsigned int out_of_range[] = {1, 4};
+  err = xaynet_ffi_participant_set_sparse_model(participant, out_of_range, values, MODEL_DATA_TYPE_F32, 2, 4);
+  mu_assert("expected index out of range error", err == ERR_SETMODEL_INDICES);
+  unsigned int duplicated[] = {1, 1};
+  err = xaynet_ffi_participant_set_sparse_model(participant, duplicated, values, MODEL_DATA_TYPE_F32, 2, 4);
+  mu_assert("expected duplicated index error", err == ERR_SETMODEL_INDICES);
+
+  xaynet_ffi_participant_destroy(participant);
+  xaynet_ffi_settings_destroy(settings);
+
+  return 0;
+}
+
+static char *test_set_model() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+
+  uint8_t quantized[] = {0, 1, 128, 255};
+  int err = xaynet_ffi_participant_set_model(participant, quantized, MODEL_DATA_TYPE_U8, 4);
+  mu_assert("failed to set u8 model", err == OK);
+  int16_t weights[] = {-32768, -1, 0, 32767};
+  err = xaynet_ffi_participant_set_model(participant, weights, MODEL_DATA_TYPE_I16, 4);
+  mu_assert("failed to set i16 model", err == OK);
+  // -2.0, -0.0, 0.5, 65504.0
+  uint16_t halfs[] = {0xc000, 0x8000, 0x3800, 0x7bff};
+  err = xaynet_ffi_participant_set_model(participant, halfs, MODEL_DATA_TYPE_F16, 4);
+  mu_assert("failed to set f16 model", err == OK);
+  float longer[] = {0.0, 0.5, 1.0, -0.5, -1.0};
+  err = xaynet_ffi_participant_set_model(participant, longer, MODEL_DATA_TYPE_F32, 5);
+  mu_assert("expected model shape changed", err == MODEL_SHAPE_CHANGED);
+  err = xaynet_ffi_participant_set_model(participant, longer, MODEL_DATA_TYPE_F32, 5);
+  mu_assert("failed to set f32 model", err == OK);
+
+  xaynet_ffi_participant_destroy(participant);
+  xaynet_ffi_settings_destroy(settings);
+
+  return 0;
+}
+
 static char *test_participant_save_and_restore() {
   Settings *settings = xaynet_ffi_settings_new();
   with_keys(settings);
@@ -136,6 +206,33 @@ static char *test_participant_save_and_restore() {
   mu_assert("failed to read serialized participant", n_read == fsize);
   fclose(f);
 
+  err = xaynet_ffi_check_state(&restore_buf);
+  mu_assert("expected valid state", err == OK);
+
+  // a corrupt state is rejected
+  restore_buf.data[fsize / 2] ^= 1;
+  err = xaynet_ffi_check_state(&restore_buf);
+  mu_assert("expected corrupt state error", err == ERR_STATE_CORRUPT);
+  Participant *corrupt =
+      xaynet_ffi_participant_restore("http://localhost:8081", &restore_buf);
+  mu_assert("unexpected restored corrupt participant", corrupt == NULL);
+  const ByteBuffer *migrated = NULL;
+  err = xaynet_ffi_migrate_state(&restore_buf, &migrated);
+  mu_assert("expected corrupt state error", err == ERR_STATE_CORRUPT);
+  mu_assert("unexpected migrated corrupt state", migrated == NULL);
+  restore_buf.data[fsize / 2] ^= 1;
+
+  // a current state is migrated unchanged
+  err = xaynet_ffi_migrate_state(&restore_buf, &migrated);
+  mu_assert("failed to migrate state", err == OK);
+  mu_assert("unexpected migrated state", migrated->len == restore_buf.len);
+  mu_assert("unexpected migrated state",
+            memcmp(migrated->data, restore_buf.data, restore_buf.len) == 0);
+  err = xaynet_ffi_byte_buffer_destroy(migrated);
+  assert(!err);
+  err = xaynet_ffi_migrate_state(NULL, &migrated);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+
   // restore the participant
   Participant *restored =
       xaynet_ffi_participant_restore("http://localhost:8081", &restore_buf);
@@ -171,14 +268,500 @@ static char *test_participant_tick() {
   return 0;
 }
 
+typedef struct {
+  Participant *participant;
+  int changes;
+  int checkpoints;
+} StateChangedData;
+
+static void on_state_changed(void *user_data, int change) {
+  StateChangedData *data = (StateChangedData *)user_data;
+  if (change == STATE_CHANGE_CIRCUIT_BREAKER) {
+    data->changes++;
+  }
+  // the participant can be used from within the callback
+  const ByteBuffer *buf =
+      xaynet_ffi_participant_prepare_for_shutdown(data->participant);
+  if (buf != NULL) {
+    data->checkpoints++;
+    xaynet_ffi_byte_buffer_destroy(buf);
+  }
+}
+
+static char *test_participant_state_changed_callback() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  StateChangedData data = {.participant = participant};
+  int err = xaynet_ffi_participant_set_state_changed_callback(
+      participant, on_state_changed, &data);
+  mu_assert("failed to set state changed callback", err == OK);
+
+  // the coordinator is unreachable, so the circuit breaker eventually opens
+  int status = 0;
+  while (!(status & PARTICIPANT_CIRCUIT_OPEN)) {
+    status = xaynet_ffi_participant_tick(participant);
+  }
+  mu_assert("expected circuit breaker state change", data.changes == 1);
+  mu_assert("expected checkpoint", data.checkpoints == 1);
+
+  // remove the callback
+  err = xaynet_ffi_participant_set_state_changed_callback(participant, NULL,
+                                                          NULL);
+  mu_assert("failed to remove state changed callback", err == OK);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+typedef struct {
+  int phases;
+  int last_task;
+  uint64_t bytes_sent;
+} ProgressData;
+
+static void on_progress(void *user_data, int progress, uint64_t value) {
+  ProgressData *data = (ProgressData *)user_data;
+  if (progress == PROGRESS_PHASE_ENTERED) {
+    data->phases++;
+    data->last_task = (int)value;
+  } else if (progress == PROGRESS_MESSAGE_SENT) {
+    data->bytes_sent += value;
+  }
+}
+
+static char *test_participant_progress_callback() {
+  int err = xaynet_ffi_participant_set_progress_callback(NULL, on_progress, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  ProgressData data = {.phases = 0, .last_task = -1, .bytes_sent = 0};
+  err = xaynet_ffi_participant_set_progress_callback(participant, on_progress,
+                                                     &data);
+  mu_assert("failed to set progress callback", err == OK);
+
+  // the participant entered the awaiting phase when it was created, and the
+  // coordinator is unreachable so no message is sent
+  xaynet_ffi_participant_tick(participant);
+  xaynet_ffi_participant_tick(participant);
+  mu_assert("expected a single phase", data.phases == 1);
+  mu_assert("expected no task", data.last_task == TASK_NONE);
+  mu_assert("unexpected message sent", data.bytes_sent == 0);
+
+  // remove the callback
+  err = xaynet_ffi_participant_set_progress_callback(participant, NULL, NULL);
+  mu_assert("failed to remove progress callback", err == OK);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_sum2_confirmation() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+  int err = xaynet_ffi_settings_set_confirm_sum2(settings, 1);
+  mu_assert("failed to set confirm sum2", err == OK);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  int status = xaynet_ffi_participant_tick(participant);
+  mu_assert("unexpected sum2 confirmation",
+            !(status & PARTICIPANT_AWAITING_SUM2_CONFIRMATION));
+
+  // the participant is not in the sum2 phase, so there's no mask to confirm
+  unsigned int len = 0;
+  err = xaynet_ffi_participant_sum2_mask(participant, NULL, &len);
+  mu_assert("unexpected sum2 mask", err == SUM2_MASK_NONE);
+  err = xaynet_ffi_participant_sum2_mask(participant, NULL, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_confirm_sum2(participant);
+  mu_assert("unexpected sum2 confirmation", err == SUM2_MASK_NONE);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_consent() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+  int err = xaynet_ffi_settings_set_require_consent(settings, 1);
+  mu_assert("failed to set require consent", err == OK);
+  err = xaynet_ffi_settings_set_consent_timeout(settings, 60);
+  mu_assert("failed to set consent timeout", err == OK);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  int status = xaynet_ffi_participant_tick(participant);
+  mu_assert("unexpected consent request", !(status & PARTICIPANT_AWAITING_CONSENT));
+
+  // the participant is not selected for a task, so there's no consent to give
+  int task = 0;
+  uint64_t upload_bytes = 0;
+  err = xaynet_ffi_participant_consent_request(participant, &task, &upload_bytes);
+  mu_assert("unexpected consent request", err == CONSENT_NONE);
+  err = xaynet_ffi_participant_consent_request(participant, NULL, &upload_bytes);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_grant_consent(participant);
+  mu_assert("unexpected consent", err == CONSENT_NONE);
+  err = xaynet_ffi_participant_deny_consent(participant);
+  mu_assert("unexpected consent", err == CONSENT_NONE);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_rounds_observed_and_task() {
+  mu_assert("expected null rounds observed",
+            xaynet_ffi_participant_rounds_observed(NULL) == UINT_MAX);
+  mu_assert("expected null pointer error",
+            xaynet_ffi_participant_task(NULL) == -ERR_NULLPTR);
+  mu_assert("expected null pointer error",
+            xaynet_ffi_participant_pending_work(NULL) == -ERR_NULLPTR);
+
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  xaynet_ffi_participant_tick(participant);
+  unsigned int rounds = xaynet_ffi_participant_rounds_observed(participant);
+  mu_assert("unexpected rounds observed", rounds == 0);
+  mu_assert("unexpected task",
+            xaynet_ffi_participant_task(participant) == TASK_NONE);
+  mu_assert("unexpected pending work",
+            xaynet_ffi_participant_pending_work(participant) == WORK_POLL);
+
+  // the number of rounds doesn't decrease across save and restore cycles
+  for (int i = 0; i < 3; i++) {
+    const ByteBuffer *save_buf = xaynet_ffi_participant_save(participant);
+    mu_assert("failed to save participant", save_buf != NULL);
+    participant =
+        xaynet_ffi_participant_restore("http://localhost:8081", save_buf);
+    xaynet_ffi_byte_buffer_destroy(save_buf);
+    mu_assert("failed to restore participant", participant != NULL);
+    xaynet_ffi_participant_tick(participant);
+    unsigned int restored_rounds =
+        xaynet_ffi_participant_rounds_observed(participant);
+    mu_assert("rounds observed decreased", restored_rounds >= rounds);
+    rounds = restored_rounds;
+  }
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_public_key() {
+  unsigned char key[PUBLIC_KEY_LENGTH];
+  mu_assert("expected null pointer error",
+            xaynet_ffi_participant_public_key(NULL, key) == ERR_NULLPTR);
+
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  int err = xaynet_ffi_participant_public_key(participant, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_public_key(participant, key);
+  mu_assert("failed to get public key", err == OK);
+
+  // the key is preserved across save and restore cycles
+  const ByteBuffer *save_buf = xaynet_ffi_participant_save(participant);
+  mu_assert("failed to save participant", save_buf != NULL);
+  participant =
+      xaynet_ffi_participant_restore("http://localhost:8081", save_buf);
+  xaynet_ffi_byte_buffer_destroy(save_buf);
+  mu_assert("failed to restore participant", participant != NULL);
+  unsigned char restored_key[PUBLIC_KEY_LENGTH];
+  err = xaynet_ffi_participant_public_key(participant, restored_key);
+  mu_assert("failed to get public key", err == OK);
+  mu_assert("public key changed",
+            memcmp(key, restored_key, PUBLIC_KEY_LENGTH) == 0);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_rotate_keys() {
+  unsigned char key[PUBLIC_KEY_LENGTH];
+  mu_assert("expected null pointer error",
+            xaynet_ffi_participant_rotate_keys(NULL, key) == ERR_NULLPTR);
+
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  int err = xaynet_ffi_participant_rotate_keys(participant, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_public_key(participant, key);
+  mu_assert("failed to get public key", err == OK);
+  unsigned char new_key[PUBLIC_KEY_LENGTH];
+  err = xaynet_ffi_participant_rotate_keys(participant, new_key);
+  mu_assert("failed to rotate keys", err == OK);
+  mu_assert("new public key is the old one",
+            memcmp(key, new_key, PUBLIC_KEY_LENGTH) != 0);
+
+  // the old key is used until the next round starts
+  unsigned char current_key[PUBLIC_KEY_LENGTH];
+  err = xaynet_ffi_participant_public_key(participant, current_key);
+  mu_assert("failed to get public key", err == OK);
+  mu_assert("public key changed before the next round",
+            memcmp(key, current_key, PUBLIC_KEY_LENGTH) == 0);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_update_tls() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  char pem[] = "not a certificate";
+  ByteBuffer buf = {
+      .len = sizeof(pem) - 1,
+      .data = (uint8_t *)pem,
+  };
+  int err = xaynet_ffi_participant_update_tls_identity(participant, &buf);
+  mu_assert("expected invalid identity error", err == ERR_TLS_CERTIFICATE);
+  err = xaynet_ffi_participant_update_tls_identity(participant, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_update_tls_root_certificate(NULL, &buf);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_next_wakeup() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  WakeupRecommendation recommendation;
+  int err = xaynet_ffi_participant_next_wakeup(participant, &recommendation);
+  mu_assert("failed to get the wake-up recommendation", err == OK);
+  mu_assert("unexpected expected work", recommendation.expected_work == WORK_POLL);
+  mu_assert("unexpected reason", recommendation.reason == WAKEUP_IDLE);
+  mu_assert("unexpected wake-up window",
+            recommendation.earliest_ms <= recommendation.latest_ms);
+
+  err = xaynet_ffi_participant_next_wakeup(participant, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_next_wakeup(NULL, &recommendation);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_public_stats() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  PublicStats stats;
+  int err = xaynet_ffi_participant_public_stats(NULL, &stats);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_public_stats(participant, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_public_stats(participant, &stats);
+  mu_assert("expected io error (cannot connect to coordinator)",
+            err == ERR_PUBLIC_STATS_IO);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static char *test_participant_history() {
+  mu_assert("expected null history length",
+            xaynet_ffi_participant_history_len(NULL) == UINT_MAX);
+
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+  int err = xaynet_ffi_settings_set_max_history_len(settings, 10);
+  mu_assert("failed to set the maximum history length", err == OK);
+
+  Participant *participant = xaynet_ffi_participant_new(settings);
+  mu_assert("failed to create participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  // the coordinator is unreachable, no round has been completed
+  xaynet_ffi_participant_tick(participant);
+  mu_assert("unexpected history length",
+            xaynet_ffi_participant_history_len(participant) == 0);
+
+  RoundRecord record;
+  err = xaynet_ffi_participant_history_entry(participant, 0, &record);
+  mu_assert("expected history index error", err == ERR_HISTORY_INDEX);
+  err = xaynet_ffi_participant_history_entry(participant, 0, NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  err = xaynet_ffi_participant_history_entry(NULL, 0, &record);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+
+  xaynet_ffi_participant_destroy(participant);
+
+  return 0;
+}
+
+static void *tick_shared_participant(void *participant) {
+  for (int i = 0; i < 5; i++) {
+    int flags = xaynet_ffi_shared_participant_tick(participant);
+    assert(flags & PARTICIPANT_TASK_NONE);
+  }
+  xaynet_ffi_shared_participant_destroy(participant);
+  return NULL;
+}
+
+static char *test_shared_participant() {
+  Settings *settings = xaynet_ffi_settings_new();
+  with_keys(settings);
+  with_url(settings);
+
+  const SharedParticipant *participant =
+      xaynet_ffi_shared_participant_new(settings);
+  mu_assert("failed to create shared participant", participant != NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  // tick the participant from another thread, which owns a reference
+  pthread_t thread;
+  const SharedParticipant *reference =
+      xaynet_ffi_shared_participant_clone(participant);
+  mu_assert("failed to clone shared participant", reference == participant);
+  int err = pthread_create(&thread, NULL, tick_shared_participant,
+                           (void *)reference);
+  mu_assert("failed to spawn thread", err == 0);
+
+  for (int i = 0; i < 5; i++) {
+    const ByteBuffer *save_buf = xaynet_ffi_shared_participant_save(participant);
+    mu_assert("failed to save shared participant", save_buf != NULL);
+    err = xaynet_ffi_check_state(save_buf);
+    mu_assert("expected valid state", err == OK);
+    xaynet_ffi_byte_buffer_destroy(save_buf);
+  }
+  pthread_join(thread, NULL);
+
+  int task = xaynet_ffi_shared_participant_task(participant);
+  mu_assert("unexpected task", task == TASK_NONE);
+  err = xaynet_ffi_shared_participant_destroy(participant);
+  mu_assert("failed to destroy shared participant", err == OK);
+  err = xaynet_ffi_shared_participant_destroy(NULL);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+
+  return 0;
+}
+
+static char *test_last_error() {
+  Participant *participant = xaynet_ffi_participant_new(NULL);
+  mu_assert("expected participant creation to fail", participant == NULL);
+
+  int len = xaynet_ffi_last_error_length();
+  char *expected = "xaynet_ffi_participant_new failed: `settings` is NULL";
+  mu_assert("unexpected error length", len == (int)strlen(expected) + 1);
+  char *message = malloc(len);
+  int err = xaynet_ffi_last_error_message(message, len);
+  mu_assert("failed to get the error message", err == OK);
+  mu_assert("unexpected error message", strcmp(message, expected) == 0);
+
+  err = xaynet_ffi_last_error_message(message, len - 1);
+  mu_assert("expected buffer length error", err == ERR_LAST_ERROR_LEN);
+  err = xaynet_ffi_last_error_message(NULL, len);
+  mu_assert("expected null pointer error", err == ERR_NULLPTR);
+  free(message);
+
+  // the message names the failing function and the reason of the failure
+  Settings *settings = xaynet_ffi_settings_new();
+  with_url(settings);
+  participant = xaynet_ffi_participant_new(settings);
+  mu_assert("expected participant creation to fail", participant == NULL);
+  xaynet_ffi_settings_destroy(settings);
+
+  len = xaynet_ffi_last_error_length();
+  message = malloc(len);
+  err = xaynet_ffi_last_error_message(message, len);
+  mu_assert("failed to get the error message", err == OK);
+  mu_assert("unexpected error message",
+            strstr(message, "xaynet_ffi_participant_new failed: ") == message);
+  mu_assert("unexpected error message", strstr(message, "MissingKeys") != NULL);
+  free(message);
+
+  return 0;
+}
+
 static char *all_tests() {
   mu_run_test(test_settings_new);
   mu_run_test(test_settings_set_keys);
   mu_run_test(test_settings_set_url);
   mu_run_test(test_settings);
   mu_run_test(test_global_model);
+  mu_run_test(test_set_sparse_model);
+  mu_run_test(test_set_model);
   mu_run_test(test_participant_save_and_restore);
   mu_run_test(test_participant_tick);
+  mu_run_test(test_participant_state_changed_callback);
+  mu_run_test(test_participant_progress_callback);
+  mu_run_test(test_participant_sum2_confirmation);
+  mu_run_test(test_participant_consent);
+  mu_run_test(test_participant_rounds_observed_and_task);
+  mu_run_test(test_participant_public_key);
+  mu_run_test(test_participant_rotate_keys);
+  mu_run_test(test_participant_update_tls);
+  mu_run_test(test_participant_next_wakeup);
+  mu_run_test(test_participant_public_stats);
+  mu_run_test(test_participant_history);
+  mu_run_test(test_shared_participant);
+  mu_run_test(test_last_error);
   return 0;
 }
 
@@ -8,114 +8,174 @@
 #include <stdlib.h>
 
 /**
- * Return value upon success
+ * The default maximum number of rounds a participant keeps in its history.
  */
-#define OK 0
+#define DEFAULT_MAX_HISTORY_LEN 50
 
 /**
- * NULL pointer argument
+ * Default maximum number of events that the participant keeps until it processes them.
+ * See [`Settings::set_max_pending_events()`].
  */
-#define ERR_NULLPTR 1
+#define DEFAULT_MAX_PENDING_EVENTS 256
 
 /**
- * Invalid coordinator URL
+ * The participant is not taking part in the sum or update task
  */
-#define ERR_INVALID_URL 2
+#define PARTICIPANT_TASK_NONE 1
 
 /**
- * Invalid settings: coordinator URL is not set
+ * The participant is not taking part in the sum task
  */
-#define ERR_SETTINGS_URL 3
+#define PARTICIPANT_TASK_SUM (1 << 1)
 
 /**
- * Invalid settings: signing keys are not set
+ * The participant is not taking part in the update task
  */
-#define ERR_SETTINGS_KEYS 4
+#define PARTICIPANT_TASK_UPDATE (1 << 2)
 
 /**
- * Invalid settings: scalar is out of bounds
+ * The participant is expected to set the model it trained
  */
-#define ERR_SETTINGS_SCALAR 5
+#define PARTICIPANT_SHOULD_SET_MODEL (1 << 3)
 
 /**
- * Failed to set the local model: invalid model
+ * The participant is expected to set the model it trained
  */
-#define ERR_SETMODEL_MODEL 6
+#define PARTICIPANT_MADE_PROGRESS (1 << 4)
 
 /**
- * Failed to set the local model: invalid data type
+ * A new global model is available
  */
-#define ERR_SETMODEL_DATATYPE 7
+#define PARTICIPANT_NEW_GLOBALMODEL (1 << 5)
 
 /**
- * Failed to initialized the crypto library
+ * The participant daily data budget is exhausted
  */
-#define ERR_CRYPTO_INIT 8
+#define PARTICIPANT_DATA_BUDGET_EXCEEDED (1 << 6)
 
 /**
- * Invalid secret signing key
+ * The coordinator repeatedly failed and the participant stopped sending requests to it
  */
-#define ERR_CRYPTO_SECRET_KEY 9
+#define PARTICIPANT_CIRCUIT_OPEN (1 << 7)
 
 /**
- * Invalid public signing key
+ * The participant awaits the confirmation of the global mask it aggregated
  */
-#define ERR_CRYPTO_PUBLIC_KEY 10
+#define PARTICIPANT_AWAITING_SUM2_CONFIRMATION (1 << 8)
 
 /**
- * No global model is currently available
+ * The participant has been selected for a task and awaits the consent of the user
  */
-#define GLOBALMODEL_NONE 11
+#define PARTICIPANT_AWAITING_CONSENT (1 << 9)
 
 /**
- * Failed to get the global model: communication with the coordinator failed
+ * The participant is not taking part in the sum or update task, see
+ * [`xaynet_ffi_participant_task()`]
  */
-#define ERR_GLOBALMODEL_IO 12
+#define TASK_NONE 0
 
 /**
- * Failed to get the global model: invalid data type
+ * The participant is taking part in the sum task, see [`xaynet_ffi_participant_task()`]
  */
-#define ERR_GLOBALMODEL_DATATYPE 13
+#define TASK_SUM 1
 
 /**
- * Failed to get the global model: invalid buffer length
+ * The participant is taking part in the update task, see
+ * [`xaynet_ffi_participant_task()`]
  */
-#define ERR_GLOBALMODEL_LEN 14
+#define TASK_UPDATE 2
 
 /**
- * Failed to get the global model: invalid model
+ * The participant state changed because the participant made progress
  */
-#define ERR_GLOBALMODEL_CONVERT 15
+#define STATE_CHANGE_PROGRESS 1
 
 /**
- * The participant is not taking part in the sum or update task
+ * The participant state changed because the circuit breaker opened, became half-open
+ * or closed
  */
-#define PARTICIPANT_TASK_NONE 1
+#define STATE_CHANGE_CIRCUIT_BREAKER 2
 
 /**
- * The participant is not taking part in the sum task
+ * The participant entered the phase of a task, see
+ * [`xaynet_ffi_participant_set_progress_callback()`]
  */
-#define PARTICIPANT_TASK_SUM (1 << 1)
+#define PROGRESS_PHASE_ENTERED 1
 
 /**
- * The participant is not taking part in the update task
+ * The participant sent a message to the coordinator, see
+ * [`xaynet_ffi_participant_set_progress_callback()`]
  */
-#define PARTICIPANT_TASK_UPDATE (1 << 2)
+#define PROGRESS_MESSAGE_SENT 2
 
 /**
- * The participant is expected to set the model it trained
+ * The participant polls the coordinator or waits for a decision of the user, see
+ * [`WakeupRecommendation`]
  */
-#define PARTICIPANT_SHOULD_SET_MODEL (1 << 3)
+#define WORK_POLL 0
 
 /**
- * The participant is expected to set the model it trained
+ * The participant downloads data from the coordinator, see [`WakeupRecommendation`]
  */
-#define PARTICIPANT_MADE_PROGRESS (1 << 4)
+#define WORK_DOWNLOAD 1
 
 /**
- * A new global model is available
+ * The participant waits for the model to train, see [`WakeupRecommendation`]
  */
-#define PARTICIPANT_NEW_GLOBALMODEL (1 << 5)
+#define WORK_TRAIN 2
+
+/**
+ * The participant uploads a message to the coordinator, see [`WakeupRecommendation`]
+ */
+#define WORK_UPLOAD 3
+
+/**
+ * The participant masks its model or aggregates the masks, see [`WakeupRecommendation`]
+ */
+#define WORK_COMPUTE 4
+
+/**
+ * The participant doesn't request the coordinator, see
+ * [`xaynet_ffi_participant_pending_work()`]
+ */
+#define WORK_NONE 5
+
+/**
+ * The participant waits to be selected for a task, see [`WakeupRecommendation`]
+ */
+#define WAKEUP_IDLE 0
+
+/**
+ * The participant is taking part in a task, see [`WakeupRecommendation`]
+ */
+#define WAKEUP_BUSY 1
+
+/**
+ * The participant waits for the model it is expected to train, see
+ * [`WakeupRecommendation`]
+ */
+#define WAKEUP_AWAITING_MODEL 2
+
+/**
+ * The participant waits for a decision of the user, see [`WakeupRecommendation`]
+ */
+#define WAKEUP_AWAITING_USER 3
+
+/**
+ * The participant backs off from a failing coordinator, see [`WakeupRecommendation`]
+ */
+#define WAKEUP_CIRCUIT_OPEN 4
+
+/**
+ * The participant daily data budget is exhausted, see [`WakeupRecommendation`]
+ */
+#define WAKEUP_DATA_BUDGET_EXCEEDED 5
+
+/**
+ * The length in bytes of the public signing key of a participant, see
+ * [`xaynet_ffi_participant_public_key()`]
+ */
+#define PUBLIC_KEY_LENGTH 32
 
 /**
  * The original primitive data type of the numerical values to be masked.
@@ -137,9 +197,152 @@ enum ModelDataType {
    * Numbers of type i64.
    */
   MODEL_DATA_TYPE_I64 = 3,
+  /**
+   * Numbers of type u8.
+   */
+  MODEL_DATA_TYPE_U8 = 4,
+  /**
+   * Numbers of type i16.
+   */
+  MODEL_DATA_TYPE_I16 = 5,
+  /**
+   * Numbers of type f16, passed as the IEEE 754 binary16 bits in an `uint16_t`.
+   */
+  MODEL_DATA_TYPE_F16 = 6,
 };
 typedef uint8_t ModelDataType;
 
+/**
+ * The return codes of the C API, see the module documentation. The C header names the
+ * variants in screaming snake case, e.g. `ERR_NULLPTR` for [`ReturnCode::ErrNullptr`].
+ *
+ * The discriminants are part of the C API: they must never change, and a new code gets
+ * a new variant with the next value.
+ */
+typedef enum ReturnCode {
+  /**
+   * Return value upon success
+   */
+  OK = 0,
+  /**
+   * NULL pointer argument
+   */
+  ERR_NULLPTR = 1,
+  /**
+   * Invalid coordinator URL
+   */
+  ERR_INVALID_URL = 2,
+  /**
+   * Invalid settings: coordinator URL is not set
+   */
+  ERR_SETTINGS_URL = 3,
+  /**
+   * Invalid settings: signing keys are not set
+   */
+  ERR_SETTINGS_KEYS = 4,
+  /**
+   * Invalid settings: scalar is out of bounds
+   */
+  ERR_SETTINGS_SCALAR = 5,
+  /**
+   * Failed to set the local model: invalid model
+   */
+  ERR_SETMODEL_MODEL = 6,
+  /**
+   * Failed to set the local model: invalid data type
+   */
+  ERR_SETMODEL_DATATYPE = 7,
+  /**
+   * Failed to initialized the crypto library
+   */
+  ERR_CRYPTO_INIT = 8,
+  /**
+   * Invalid secret signing key
+   */
+  ERR_CRYPTO_SECRET_KEY = 9,
+  /**
+   * Invalid public signing key
+   */
+  ERR_CRYPTO_PUBLIC_KEY = 10,
+  /**
+   * No global model is currently available
+   */
+  GLOBALMODEL_NONE = 11,
+  /**
+   * Failed to get the global model: communication with the coordinator failed
+   */
+  ERR_GLOBALMODEL_IO = 12,
+  /**
+   * Failed to get the global model: invalid data type
+   */
+  ERR_GLOBALMODEL_DATATYPE = 13,
+  /**
+   * Failed to get the global model: invalid buffer length
+   */
+  ERR_GLOBALMODEL_LEN = 14,
+  /**
+   * Failed to get the global model: invalid model
+   */
+  ERR_GLOBALMODEL_CONVERT = 15,
+  /**
+   * Invalid participant state: the state is truncated or altered
+   */
+  ERR_STATE_CORRUPT = 16,
+  /**
+   * Invalid participant state: the state can't be deserialized
+   */
+  ERR_STATE_DESERIALIZE = 17,
+  /**
+   * The participant doesn't await the confirmation of a global mask
+   */
+  SUM2_MASK_NONE = 18,
+  /**
+   * Failed to get the global mask: the buffer is too small
+   */
+  ERR_SUM2_MASK_LEN = 19,
+  /**
+   * The participant doesn't await the consent of the user to take part in a task
+   */
+  CONSENT_NONE = 20,
+  /**
+   * No FFI function failed on the current thread
+   */
+  LAST_ERROR_NONE = 21,
+  /**
+   * Failed to get the last error message: the buffer is too small
+   */
+  ERR_LAST_ERROR_LEN = 22,
+  /**
+   * Invalid participant state: the state was saved by a newer version of the library
+   */
+  ERR_STATE_VERSION = 23,
+  /**
+   * Failed to set the local model: a weight index is out of range or duplicated
+   */
+  ERR_SETMODEL_INDICES = 24,
+  /**
+   * The local model is set, but it has a different length than the models that were set
+   * before: the cached global model and the local model that was not sent are discarded
+   */
+  MODEL_SHAPE_CHANGED = 25,
+  /**
+   * Failed to set the local model: the coordinator expects another model length
+   */
+  ERR_SETMODEL_LENGTH = 26,
+  /**
+   * Failed to get a round of the participant history: the index is out of range
+   */
+  ERR_HISTORY_INDEX = 27,
+  /**
+   * Failed to get the public statistics: the communication with the coordinator failed
+   */
+  ERR_PUBLIC_STATS_IO = 28,
+  /**
+   * Failed to update the TLS certificates: the certificate or the private key is invalid
+   */
+  ERR_TLS_CERTIFICATE = 29,
+} ReturnCode;
+
 /**
  * A signing key pair
  */
@@ -158,6 +361,27 @@ typedef struct Participant Participant;
  */
 typedef struct Settings Settings;
 
+/**
+ * A participant that can be used from several threads.
+ *
+ * The `xaynet_ffi_participant_*` functions must not be called concurrently on the same
+ * participant. A shared participant is instead used with the `xaynet_ffi_shared_*`
+ * functions, which lock the participant internally, and it is passed to them as a
+ * `const` pointer. Each `xaynet_ffi_shared_participant_*` function behaves like the
+ * `xaynet_ffi_participant_*` function of the same name, and the last error messages
+ * name the latter.
+ *
+ * A shared participant is reference counted: [`xaynet_ffi_shared_participant_clone()`]
+ * creates a new reference, for instance for another thread, and each reference must be
+ * destroyed with [`xaynet_ffi_shared_participant_destroy()`].
+ *
+ * The callbacks of a shared participant are invoked on the thread that ticks the
+ * participant, without holding the lock, so they can use the participant. Hence the
+ * user data of the callbacks must be safe to use from any thread that ticks the
+ * participant.
+ */
+typedef struct SharedParticipant SharedParticipant;
+
 /**
  * ByteBuffer is a struct that represents an array of bytes to be sent over the FFI boundaries.
  * There are several cases when you might want to use this, but the primary one for us
@@ -244,6 +468,27 @@ typedef struct ByteBuffer {
   uint8_t *data;
 } ByteBuffer;
 
+/**
+ * A round the participant observed and completed, see
+ * [`xaynet_ffi_participant_history_entry()`].
+ */
+typedef struct RoundRecord {
+  /**
+   * The SHA-256 hash of the round seed, which identifies the round.
+   */
+  uint8_t round_seed_hash[32];
+  /**
+   * The task the participant was selected for in the round, one of [`TASK_NONE`],
+   * [`TASK_SUM`] and [`TASK_UPDATE`].
+   */
+  int task;
+  /**
+   * Whether the participant successfully sent a message to the coordinator in the
+   * round.
+   */
+  bool message_sent;
+} RoundRecord;
+
 /**
  * `FfiStr<'a>` is a safe (`#[repr(transparent)]`) wrapper around a
  * nul-terminated `*const c_char` (e.g. a C string). Conceptually, it is
@@ -307,6 +552,57 @@ typedef struct LocalModelConfig {
   uint64_t len;
 } LocalModelConfig;
 
+/**
+ * A recommendation of when the participant should be ticked next, see
+ * [`xaynet_ffi_participant_next_wakeup()`].
+ */
+typedef struct WakeupRecommendation {
+  /**
+   * Delay in milliseconds before which ticking the participant is not expected to make
+   * progress.
+   */
+  uint64_t earliest_ms;
+  /**
+   * Delay in milliseconds after which the participant risks missing its task. It is
+   * never shorter than `earliest_ms`.
+   */
+  uint64_t latest_ms;
+  /**
+   * The kind of work the participant performs once woken up, one of [`WORK_POLL`],
+   * [`WORK_DOWNLOAD`], [`WORK_TRAIN`], [`WORK_COMPUTE`] and [`WORK_UPLOAD`].
+   */
+  int expected_work;
+  /**
+   * Why the participant should be woken up, one of the `WAKEUP_*` constants.
+   */
+  int reason;
+} WakeupRecommendation;
+
+/**
+ * The statistics about the training which are safe to show to the user of the app, see
+ * [`xaynet_ffi_participant_public_stats()`].
+ */
+typedef struct PublicStats {
+  /**
+   * The id of the current round.
+   */
+  uint64_t round_id;
+  /**
+   * The number of rounds that completed with a new global model.
+   */
+  uint64_t completed_rounds;
+  /**
+   * The approximate number of participants that contributed to the latest global
+   * model, or `0` if too few participants contributed to publish it.
+   */
+  uint64_t participants;
+  /**
+   * The time at which the latest global model was published (in seconds since the UNIX
+   * epoch), or `0` if no global model was published yet.
+   */
+  uint64_t last_model_update;
+} PublicStats;
+
 /**
  * Destroy the given `ByteBuffer` and free its memory. This function must only be
  * called on `ByteBuffer`s that have been created on the Rust side of the FFI. If you
@@ -315,8 +611,8 @@ typedef struct LocalModelConfig {
  *
  * # Return value
  *
- * - [`OK`] on success
- * - [`ERR_NULLPTR`] if `buf` is NULL
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `buf` is NULL
  *
  * # Safety
  *
@@ -333,7 +629,7 @@ typedef struct LocalModelConfig {
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_byte_buffer_destroy(const struct ByteBuffer *buf);
+enum ReturnCode xaynet_ffi_byte_buffer_destroy(const struct ByteBuffer *buf);
 
 /**
  * Initialize the crypto library. This method must be called before instantiating a
@@ -342,14 +638,14 @@ int xaynet_ffi_byte_buffer_destroy(const struct ByteBuffer *buf);
  *
  * # Return value
  *
- * - [`OK`] if the initialization succeeded
- * - -[`ERR_CRYPTO_INIT`] if the initialization failed
+ * - `OK` if the initialization succeeded
+ * - -`ERR_CRYPTO_INIT` if the initialization failed
  *
  * # Safety
  *
  * This function is safe to call
  */
-int xaynet_ffi_crypto_init(void);
+enum ReturnCode xaynet_ffi_crypto_init(void);
 
 /**
  * Destroy the participant created by [`xaynet_ffi_participant_new()`] or
@@ -357,8 +653,8 @@ int xaynet_ffi_crypto_init(void);
  *
  * # Return value
  *
- * - [`OK`] on success
- * - [`ERR_NULLPTR`] if `participant` is NULL
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` is NULL
  *
  * # Safety
  *
@@ -375,7 +671,7 @@ int xaynet_ffi_crypto_init(void);
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_participant_destroy(struct Participant *participant);
+enum ReturnCode xaynet_ffi_participant_destroy(struct Participant *participant);
 
 /**
  * Instantiate a new participant with the given settings. The participant must be
@@ -383,9 +679,12 @@ int xaynet_ffi_participant_destroy(struct Participant *participant);
  *
  * # Return value
  *
- * - a NULL pointer if `settings` is NULL or if the participant creation failed
+ * - a NULL pointer if `settings` is NULL or if the participant creation failed, the
+ *   reason is given by [`xaynet_ffi_last_error_message()`]
  * - a valid pointer to a [`Participant`] otherwise
  *
+ * [`xaynet_ffi_last_error_message()`]: crate::ffi::xaynet_ffi_last_error_message
+ *
  * # Safety
  *
  * When calling this method, you have to ensure that *either* the pointer is NULL *or*
@@ -409,7 +708,7 @@ struct Participant *xaynet_ffi_participant_new(const struct Settings *settings);
  *
  * # Return value
  *
- * - [`ERR_NULLPTR`] is `participant` is NULL
+ * - `ERR_NULLPTR` is `participant` is NULL
  * - a bitflag otherwise, with the following flags:
  *   - [`PARTICIPANT_MADE_PROGRESS`]: if set, this flag indicates that the participant
  *     internal state machine was able to make some progress, and that the participant
@@ -433,6 +732,33 @@ struct Participant *xaynet_ffi_participant_new(const struct Settings *settings);
  *     model, by calling [`xaynet_ffi_participant_set_model()`]
  *   - [`PARTICIPANT_NEW_GLOBALMODEL`]: if set, the participant can fetch the new global
  *     model, by calling [`xaynet_ffi_participant_global_model()`]
+ *   - [`PARTICIPANT_DATA_BUDGET_EXCEEDED`]: if set, the participant daily data budget
+ *     is exhausted and the participant declines to start new network operations until
+ *     the next day (see [`xaynet_ffi_participant_data_usage()`])
+ *   - [`PARTICIPANT_CIRCUIT_OPEN`]: if set, the coordinator repeatedly failed with
+ *     server errors and the participant backs off: it doesn't send any request to the
+ *     coordinator until the backoff elapsed
+ *   - [`PARTICIPANT_AWAITING_SUM2_CONFIRMATION`]: if set, the participant aggregated the
+ *     global mask and doesn't send it to the coordinator until it is confirmed with
+ *     [`xaynet_ffi_participant_confirm_sum2()`] (see
+ *     [`xaynet_ffi_settings_set_confirm_sum2()`])
+ *   - [`PARTICIPANT_AWAITING_CONSENT`]: if set, the participant has been selected for a
+ *     task and doesn't take part in it until the user consents with
+ *     [`xaynet_ffi_participant_grant_consent()`] (see
+ *     [`xaynet_ffi_participant_consent_request()`] and
+ *     [`xaynet_ffi_settings_set_require_consent()`])
+ *
+ * If the participant made progress through the PET protocol, the callback registered
+ * with [`xaynet_ffi_participant_set_progress_callback()`] is invoked before this
+ * function returns. Then, if the participant state changed, the callback registered
+ * with [`xaynet_ffi_participant_set_state_changed_callback()`] is invoked.
+ *
+ * The events emitted by the participant internal state machine are processed before
+ * this function returns, so they don't accumulate between ticks. If more events than
+ * the maximum set with [`xaynet_ffi_settings_set_max_pending_events()`] are emitted in
+ * a single tick, the oldest informational ones are dropped (see
+ * [`xaynet_ffi_participant_dropped_events()`]). The events that report the progress
+ * through the tasks or request an action are never dropped.
  *
  * # Safety
  *
@@ -448,191 +774,1377 @@ struct Participant *xaynet_ffi_participant_new(const struct Settings *settings);
  *
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_settings_set_confirm_sum2()`]: crate::ffi::xaynet_ffi_settings_set_confirm_sum2
+ * [`xaynet_ffi_settings_set_require_consent()`]: crate::ffi::xaynet_ffi_settings_set_require_consent
+ * [`xaynet_ffi_settings_set_max_pending_events()`]: crate::ffi::xaynet_ffi_settings_set_max_pending_events
  */
 int xaynet_ffi_participant_tick(struct Participant *participant);
 
 /**
- * Serialize the participant state and return a buffer that contains the serialized
- * participant.
+ * Get the number of rounds the participant has observed. It increases every time the
+ * coordinator starts a new round and it is preserved when the participant is saved and
+ * restored, so it can be used to decide whether the model should be trained again. It is
+ * unrelated to the round ID used internally by the coordinator.
+ *
+ * # Return value
+ *
+ * - the number of rounds, saturated at `UINT_MAX - 1`
+ * - `UINT_MAX` if `participant` is NULL
  *
  * # Safety
  *
- * 1. When calling this method, you have to ensure that *either* the pointer is NULL
- *    *or* all of the following is true:
- *    - The pointer must be properly [aligned].
- *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
- *      documentation.
- * 2. the `ByteBuffer` created by this function must be destroyed with
- *    [`xaynet_ffi_participant_destroy`]. Attempting to free the memory from the other
- *    side of the FFI is UB.
- * 3. This function destroys the participant. Therefore, **the pointer becomes invalid
- *    and must not be used anymore**. Instead, a new participant should be created,
- *    either with [`xaynet_ffi_participant_new()`] or
- *    [`xaynet_ffi_participant_restore()`]
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
  *
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
- *
- * # Example
- *
- * To save the participant into a file:
- *
- * ```c
- *  const ByteBuffer *save_buf = xaynet_ffi_participant_save(participant);
- *  assert(save_buf);
- *
- *  char *path = "./participant.bin";
- *  FILE *f = fopen(path, "w");
- *  fwrite(save_buf->data, 1, save_buf->len, f);
- *  fclose(f);
- * ```
  */
-const struct ByteBuffer *xaynet_ffi_participant_save(struct Participant *participant);
+unsigned int xaynet_ffi_participant_rounds_observed(const struct Participant *participant);
 
 /**
- * Restore the participant from a buffer that contained its serialized state.
+ * Copy the public signing key of the participant into `buffer`. The key identifies the
+ * participant towards the coordinator, for instance in its logs, and it is preserved
+ * when the participant is saved and restored.
  *
  * # Return value
  *
- * - a NULL pointer on failure
- * - a pointer to the restored participant on success
+ * - `OK` if the key is copied into `buffer`
+ * - `ERR_NULLPTR` if `participant` or `buffer` is NULL
  *
  * # Safety
  *
- * When calling this method, you have to ensure that *either* the pointers are NULL
- * *or* all of the following is true:
- * - The pointers must be properly [aligned].
- * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
- *   documentation.
+ * 1. When calling this method, you have to ensure that *either* the pointers are NULL
+ *    *or* all of the following is true:
+ *    - The pointers must be properly [aligned].
+ *    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. `buffer` must be valid for writes of [`PUBLIC_KEY_LENGTH`] bytes.
  *
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
- *
- * # Example
- *
- * To restore a participant from a file:
- *
- * ```c
- * f = fopen("./participant.bin", "r");
- * fseek(f, 0L, SEEK_END);
- * int fsize = ftell(f);
- * fseek(f, 0L, SEEK_SET);
- * ByteBuffer buf = {
- *     .len = fsize,
- *     .data = (uint8_t *)malloc(fsize),
- * };
- * int n_read = fread(buf.data, 1, fsize, f);
- * assert(n_read == fsize);
- * fclose(f);
- * Participant *restored =
- *     xaynet_ffi_participant_restore("http://localhost:8081", &buf);
- * free(buf.data);
- * ```
  */
-struct Participant *xaynet_ffi_participant_restore(FfiStr url, const struct ByteBuffer *buffer);
+enum ReturnCode xaynet_ffi_participant_public_key(const struct Participant *participant,
+                                                  unsigned char *buffer);
 
 /**
- * Set the participant's model. Usually this should be called when the value returned
- * by [`xaynet_ffi_participant_tick()`] contains the [`PARTICIPANT_SHOULD_SET_MODEL`]
- * flag, but it can be called anytime. The model just won't be sent to the coordinator
- * until it's time.
+ * Generate new signing keys for the participant and copy their public key into
+ * `buffer`, so that the coordinator cannot link the participant across the rounds.
  *
- * - `buffer` should be a pointer to a buffer that contains the model
- * - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
- *   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
- * - `len` is the number of weights the model has
+ * The new keys are applied when the next round starts: until then,
+ * [`xaynet_ffi_participant_public_key()`] still returns the current key, which signs
+ * the messages of the current round. The new keys are preserved when the participant
+ * is saved and restored.
  *
  * # Return value
  *
- * - [`OK`] if the model is set successfully
- * - [`ERR_NULLPTR`] if `participant` is NULL
- * - [`ERR_SETMODEL_DATATYPE`] if the datatype is invalid
- * - [`ERR_SETMODEL_MODEL`] if the model is invalid
+ * - `OK` if the keys are rotated and the new public key is copied into `buffer`
+ * - `ERR_NULLPTR` if `participant` or `buffer` is NULL
  *
  * # Safety
  *
- * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ * 1. When calling this method, you have to ensure that *either* the pointers are NULL
  *    *or* all of the following is true:
- *    - The pointer must be properly [aligned].
- *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *    - The pointers must be properly [aligned].
+ *    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
  *      documentation.
- * 2. If `len` or `data_type` do not match the model in `buffer`, this method will
- *    result in a buffer over-read.
+ * 2. `buffer` must be valid for writes of [`PUBLIC_KEY_LENGTH`] bytes.
  *
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_participant_set_model(struct Participant *participant,
-                                     const void *buffer,
-                                     unsigned char data_type,
-                                     unsigned int len);
+enum ReturnCode xaynet_ffi_participant_rotate_keys(struct Participant *participant,
+                                                   unsigned char *buffer);
 
 /**
- * Return the latest global model from the coordinator.
+ * Replace the root certificate that the participant uses to authenticate the
+ * coordinator, with the PEM encoded certificate in `buffer`.
  *
- * - `buffer` is the array in which the global model should be copied.
- * - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
- *   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
- * - `len` is the number of weights the model has
+ * The certificate can be rotated at any time: the participant keeps its progress and
+ * uses the new certificate for its next requests. The certificates are not part of the
+ * participant state, so they must be set again after the participant is restored.
  *
- * # Return Value
+ * # Return value
  *
- * - [`OK`] if the model is set successfully
- * - [`ERR_NULLPTR`] if `participant` or the `buffer` is NULL
- * - [`GLOBALMODEL_NONE`] if no model exists
- * - [`ERR_GLOBALMODEL_IO`] if the communication with the coordinator failed
- * - [`ERR_GLOBALMODEL_DATATYPE`] if the datatype is invalid
- * - [`ERR_GLOBALMODEL_LEN`] if the length of the buffer does not match the length of the model
- * - [`ERR_GLOBALMODEL_CONVERT`] if the conversion of the model failed
+ * - `OK` if the certificate is replaced
+ * - `ERR_NULLPTR` if `participant` or `buffer` is NULL
+ * - `ERR_TLS_CERTIFICATE` if the certificate is invalid. The current one is kept.
  *
- * # Note
+ * # Safety
  *
- *   It is **not** guaranteed, that the model configuration returned by
- *   [`xaynet_ffi_participant_local_model_config`] corresponds to the configuration of
- *   the global model. This means that the global model can have a different length / data type
- *   than it is defined in model configuration. That both model configurations are the same is
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_update_tls_root_certificate(struct Participant *participant,
+                                                                   const struct ByteBuffer *buffer);
+
+/**
+ * Replace the identity that the participant uses to authenticate itself to the
+ * coordinator, with the PEM encoded private key and certificate chain in `buffer`.
+ *
+ * Like the root certificate (see
+ * [`xaynet_ffi_participant_update_tls_root_certificate()`]), the identity can be
+ * rotated at any time without losing the participant progress.
+ *
+ * # Return value
+ *
+ * - `OK` if the identity is replaced
+ * - `ERR_NULLPTR` if `participant` or `buffer` is NULL
+ * - `ERR_TLS_CERTIFICATE` if the private key or the certificates are invalid. The
+ *   current identity is kept.
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_participant_update_tls_root_certificate()`].
+ */
+enum ReturnCode xaynet_ffi_participant_update_tls_identity(struct Participant *participant,
+                                                           const struct ByteBuffer *buffer);
+
+/**
+ * Get the task the participant has been selected for, in the current round.
+ *
+ * # Return value
+ *
+ * - [`TASK_NONE`] if the participant is not taking part in the sum or update task
+ * - [`TASK_SUM`] if the participant is taking part in the sum task
+ * - [`TASK_UPDATE`] if the participant is taking part in the update task
+ * - -`ERR_NULLPTR` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+int xaynet_ffi_participant_task(const struct Participant *participant);
+
+/**
+ * Get the work that the next [`xaynet_ffi_participant_tick()`] performs, without
+ * advancing the participant. Every tick that requests the coordinator checks whether a
+ * new round started. A caller that wants to avoid network use can skip ticking while
+ * this returns [`WORK_DOWNLOAD`] or [`WORK_UPLOAD`], and one that wants to avoid heavy
+ * computations while it returns [`WORK_COMPUTE`].
+ *
+ * # Return value
+ *
+ * - [`WORK_POLL`] if the next tick polls the coordinator for the selection for a task
+ * - [`WORK_DOWNLOAD`] if the next tick downloads data from the coordinator
+ * - [`WORK_TRAIN`] if the next tick waits for the local model, which must be set with
+ *   [`xaynet_ffi_participant_set_model()`] first. Until then, ticking only checks
+ *   whether a new round started.
+ * - [`WORK_COMPUTE`] if the next tick masks the local model or aggregates the masks
+ * - [`WORK_UPLOAD`] if the next tick uploads a message to the coordinator
+ * - [`WORK_NONE`] if the next tick doesn't request the coordinator, because the circuit
+ *   breaker is open or the daily data budget is exhausted
+ * - -`ERR_NULLPTR` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+int xaynet_ffi_participant_pending_work(const struct Participant *participant);
+
+/**
+ * Get the number of rounds in the history of the participant, i.e. the number of rounds
+ * the participant observed and completed, up to the maximum set with
+ * [`xaynet_ffi_settings_set_max_history_len()`]. The history is preserved when the
+ * participant is saved and restored.
+ *
+ * # Return value
+ *
+ * - the number of rounds in the history
+ * - `UINT_MAX` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_settings_set_max_history_len()`]: crate::ffi::xaynet_ffi_settings_set_max_history_len
+ */
+unsigned int xaynet_ffi_participant_history_len(const struct Participant *participant);
+
+/**
+ * Copy the round at position `index` in the history of the participant into `record`.
+ * The rounds are ordered from the oldest to the most recent one (see
+ * [`xaynet_ffi_participant_history_len()`]).
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` or `record` is NULL
+ * - `ERR_HISTORY_INDEX` if `index` is not smaller than the number of rounds in the
+ *   history
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_history_entry(const struct Participant *participant,
+                                                     unsigned int index,
+                                                     struct RoundRecord *record);
+
+/**
+ * Register a callback that is invoked whenever the participant state changed, with
+ * the given user data and the reason of the change:
+ * - [`STATE_CHANGE_PROGRESS`] if the participant made progress
+ * - [`STATE_CHANGE_CIRCUIT_BREAKER`] if the circuit breaker opened, became half-open
+ *   or closed
+ *
+ * The callback is invoked by [`xaynet_ffi_participant_tick()`], once per change, and
+ * can be used to save the participant whenever needed, instead of after every tick.
+ * Setting a model with [`xaynet_ffi_participant_set_model()`] doesn't invoke the
+ * callback, even though the model is part of the participant state saved afterwards.
+ *
+ * A previously registered callback is replaced. If `callback` is NULL, the
+ * previously registered callback is removed.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. `user_data` must remain valid until the callback is replaced or removed, or
+ *    until the participant is destroyed.
+ * 3. The callback can use the participant, for instance to checkpoint it with
+ *    [`xaynet_ffi_participant_prepare_for_shutdown()`]. However, it must not destroy
+ *    the participant, so it must not call [`xaynet_ffi_participant_save()`] or
+ *    [`xaynet_ffi_participant_destroy()`].
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ *
+ * # Example
+ *
+ * To checkpoint the participant whenever its state changed:
+ *
+ * ```c
+ * static void on_state_changed(void *user_data, int change) {
+ *   Participant *participant = (Participant *)user_data;
+ *   const ByteBuffer *buf = xaynet_ffi_participant_prepare_for_shutdown(participant);
+ *   // write the buffer to a file
+ *   xaynet_ffi_byte_buffer_destroy(buf);
+ * }
+ *
+ * xaynet_ffi_participant_set_state_changed_callback(participant, on_state_changed,
+ *                                                   participant);
+ * ```
+ */
+enum ReturnCode xaynet_ffi_participant_set_state_changed_callback(struct Participant *participant,
+                                                                  void (*callback)(void *user_data, int change),
+                                                                  void *user_data);
+
+/**
+ * Register a callback that is invoked whenever the participant made progress through
+ * the PET protocol, with the given user data, the kind of progress and its value:
+ * - [`PROGRESS_PHASE_ENTERED`] if the participant entered the phase of a task. The
+ *   value is [`TASK_NONE`], [`TASK_SUM`] or [`TASK_UPDATE`]. [`TASK_NONE`] means that
+ *   the participant is done with its task or has not been selected for any task.
+ * - [`PROGRESS_MESSAGE_SENT`] if the participant sent a message to the coordinator. The
+ *   value is the number of bytes sent. A message that is split in several parts is
+ *   notified once per part.
+ *
+ * The callback is invoked by [`xaynet_ffi_participant_tick()`], once per progress, in
+ * the order the progress was made. The callback is not part of the participant state,
+ * so it must be registered again after the participant is restored with
+ * [`xaynet_ffi_participant_restore()`]. The restored participant then notifies the
+ * phase it resumes in, on the next tick.
+ *
+ * A previously registered callback is replaced. If `callback` is NULL, the
+ * previously registered callback is removed.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. `user_data` must remain valid until the callback is replaced or removed, or
+ *    until the participant is destroyed.
+ * 3. The callback can use the participant. However, it must not destroy the
+ *    participant, so it must not call [`xaynet_ffi_participant_save()`] or
+ *    [`xaynet_ffi_participant_destroy()`].
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_set_progress_callback(struct Participant *participant,
+                                                             void (*callback)(void *user_data, int progress, uint64_t value),
+                                                             void *user_data);
+
+/**
+ * Serialize the participant state and return a buffer that contains the serialized
+ * participant.
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. the `ByteBuffer` created by this function must be destroyed with
+ *    [`xaynet_ffi_participant_destroy`]. Attempting to free the memory from the other
+ *    side of the FFI is UB.
+ * 3. This function destroys the participant. Therefore, **the pointer becomes invalid
+ *    and must not be used anymore**. Instead, a new participant should be created,
+ *    either with [`xaynet_ffi_participant_new()`] or
+ *    [`xaynet_ffi_participant_restore()`]
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ *
+ * # Example
+ *
+ * To save the participant into a file:
+ *
+ * ```c
+ *  const ByteBuffer *save_buf = xaynet_ffi_participant_save(participant);
+ *  assert(save_buf);
+ *
+ *  char *path = "./participant.bin";
+ *  FILE *f = fopen(path, "w");
+ *  fwrite(save_buf->data, 1, save_buf->len, f);
+ *  fclose(f);
+ * ```
+ */
+const struct ByteBuffer *xaynet_ffi_participant_save(struct Participant *participant);
+
+/**
+ * Checkpoint the participant before the app is shut down, and return a buffer that
+ * contains the serialized participant.
+ *
+ * Unlike [`xaynet_ffi_participant_save()`], this function doesn't destroy the
+ * participant, which can keep being used if the app is not shut down after all. A
+ * model that was set with [`xaynet_ffi_participant_set_model()`] but not yet processed
+ * by [`xaynet_ffi_participant_tick()`] is part of the checkpoint: a participant
+ * restored from the checkpoint doesn't ask for the model again.
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. the `ByteBuffer` created by this function must be destroyed with
+ *    [`xaynet_ffi_byte_buffer_destroy`]. Attempting to free the memory from the other
+ *    side of the FFI is UB.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_byte_buffer_destroy`]: crate::ffi::xaynet_ffi_byte_buffer_destroy
+ *
+ * # Example
+ *
+ * To checkpoint the participant into a file:
+ *
+ * ```c
+ *  const ByteBuffer *checkpoint_buf = xaynet_ffi_participant_prepare_for_shutdown(participant);
+ *  assert(checkpoint_buf);
+ *
+ *  char *path = "./participant.bin";
+ *  FILE *f = fopen(path, "w");
+ *  fwrite(checkpoint_buf->data, 1, checkpoint_buf->len, f);
+ *  fclose(f);
+ *  xaynet_ffi_byte_buffer_destroy(checkpoint_buf);
+ * ```
+ */
+const struct ByteBuffer *xaynet_ffi_participant_prepare_for_shutdown(struct Participant *participant);
+
+/**
+ * Restore the participant from a buffer that contained its serialized state.
+ *
+ * # Return value
+ *
+ * - a NULL pointer on failure. [`xaynet_ffi_check_state()`] tells whether the
+ *   failure is due to a corrupt state, and [`xaynet_ffi_last_error_message()`] gives
+ *   the reason of the failure.
+ * - a pointer to the restored participant on success
+ *
+ * [`xaynet_ffi_last_error_message()`]: crate::ffi::xaynet_ffi_last_error_message
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ *
+ * # Example
+ *
+ * To restore a participant from a file:
+ *
+ * ```c
+ * f = fopen("./participant.bin", "r");
+ * fseek(f, 0L, SEEK_END);
+ * int fsize = ftell(f);
+ * fseek(f, 0L, SEEK_SET);
+ * ByteBuffer buf = {
+ *     .len = fsize,
+ *     .data = (uint8_t *)malloc(fsize),
+ * };
+ * int n_read = fread(buf.data, 1, fsize, f);
+ * assert(n_read == fsize);
+ * fclose(f);
+ * Participant *restored =
+ *     xaynet_ffi_participant_restore("http://localhost:8081", &buf);
+ * free(buf.data);
+ * ```
+ */
+struct Participant *xaynet_ffi_participant_restore(FfiStr url, const struct ByteBuffer *buffer);
+
+/**
+ * Check whether a participant can be restored from a buffer that contains its
+ * serialized state (see [`xaynet_ffi_participant_restore()`]).
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `buffer` is NULL
+ * - `ERR_STATE_CORRUPT` if the state is truncated or altered
+ * - `ERR_STATE_DESERIALIZE` if the state can't be deserialized
+ * - `ERR_STATE_VERSION` if the state was saved by a newer version of the library
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_check_state(const struct ByteBuffer *buffer);
+
+/**
+ * Migrate a serialized participant state that was saved by an older version of the
+ * library to the format of the current version, and write a pointer to a buffer that
+ * contains the migrated state into `migrated`.
+ *
+ * [`xaynet_ffi_participant_restore()`] migrates the states it restores, so this is only
+ * needed for upgrading the states that are stored by the app.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `buffer` or `migrated` is NULL
+ * - `ERR_STATE_CORRUPT` if the state is truncated or altered
+ * - `ERR_STATE_DESERIALIZE` if the state can't be deserialized
+ * - `ERR_STATE_VERSION` if the state was saved by a newer version of the library
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointers are NULL
+ *    *or* all of the following is true:
+ *    - The pointers must be properly [aligned].
+ *    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. the `ByteBuffer` created by this function must be destroyed with
+ *    [`xaynet_ffi_byte_buffer_destroy`]. Attempting to free the memory from the other
+ *    side of the FFI is UB.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_byte_buffer_destroy`]: crate::ffi::xaynet_ffi_byte_buffer_destroy
+ *
+ * # Example
+ *
+ * ```c
+ * const ByteBuffer *migrated = NULL;
+ * if (xaynet_ffi_migrate_state(&buf, &migrated) == OK) {
+ *   // store the migrated state in place of the old one
+ *   xaynet_ffi_byte_buffer_destroy(migrated);
+ * }
+ * ```
+ */
+enum ReturnCode xaynet_ffi_migrate_state(const struct ByteBuffer *buffer,
+                                         const struct ByteBuffer **migrated);
+
+/**
+ * Set the participant's model. Usually this should be called when the value returned
+ * by [`xaynet_ffi_participant_tick()`] contains the [`PARTICIPANT_SHOULD_SET_MODEL`]
+ * flag, but it can be called anytime. The model just won't be sent to the coordinator
+ * until it's time.
+ *
+ * - `buffer` should be a pointer to a buffer that contains the model
+ * - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
+ *   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
+ * - `len` is the number of weights the model has
+ *
+ * # Return value
+ *
+ * - `OK` if the model is set successfully
+ * - `MODEL_SHAPE_CHANGED` if the model is set successfully, but it has a different
+ *   length than the models that were set before. The global model that was cached for
+ *   the previous length and the local model that was not sent yet are discarded.
+ * - `ERR_NULLPTR` if `participant` is NULL
+ * - `ERR_SETMODEL_DATATYPE` if the datatype is invalid
+ * - `ERR_SETMODEL_MODEL` if the model is invalid
+ * - `ERR_SETMODEL_LENGTH` if the coordinator expects another model length in the
+ *   current round. The model is not sent to the coordinator.
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. If `len` or `data_type` do not match the model in `buffer`, this method will
+ *    result in a buffer over-read.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_set_model(struct Participant *participant,
+                                                 const void *buffer,
+                                                 unsigned char data_type,
+                                                 unsigned int len);
+
+/**
+ * Set the participant's model from a sparse representation, see
+ * [`xaynet_ffi_participant_set_model()`]. This avoids copying the whole model when only
+ * some of its weights changed.
+ *
+ * - `indices` should be a pointer to an array of `len` weight indices
+ * - `values` should be a pointer to an array of `len` weights: the weight at index
+ *   `indices[i]` is `values[i]`
+ * - `data_type` specifies the type of the weights in `values` (see [`DataType`]). The C
+ *   header file generated by this crate provides an enum corresponding to the parameters:
+ *   `DataType`.
+ * - `len` is the number of weights in the sparse representation
+ * - `total_len` is the number of weights the model has
+ *
+ * The weights that are not part of the sparse representation are the ones of the last
+ * global model fetched with [`xaynet_ffi_participant_global_model()`] in the current
+ * round, or zero if no global model was fetched. The global model is not fetched again.
+ *
+ * # Return value
+ *
+ * - `OK` if the model is set successfully
+ * - `MODEL_SHAPE_CHANGED` if the model is set successfully, but it has a different
+ *   length than the models that were set before (see
+ *   [`xaynet_ffi_participant_set_model()`])
+ * - `ERR_NULLPTR` if `participant`, `indices` or `values` is NULL
+ * - `ERR_SETMODEL_DATATYPE` if the datatype is invalid
+ * - `ERR_SETMODEL_MODEL` if the model is invalid, or if the last global model doesn't
+ *   have `total_len` weights
+ * - `ERR_SETMODEL_INDICES` if an index is out of range or duplicated
+ * - `ERR_SETMODEL_LENGTH` if the coordinator expects another model length in the
+ *   current round
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointers are NULL
+ *    *or* all of the following is true:
+ *    - The pointers must be properly [aligned].
+ *    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. If `len` or `data_type` do not match the arrays `indices` and `values`, this method
+ *    will result in a buffer over-read.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_set_sparse_model(struct Participant *participant,
+                                                        const unsigned int *indices,
+                                                        const void *values,
+                                                        unsigned char data_type,
+                                                        unsigned int len,
+                                                        unsigned int total_len);
+
+/**
+ * Get the number of weights of the latest global model from the coordinator, and
+ * write it into `len`. This is the length of the buffer expected by
+ * [`xaynet_ffi_participant_global_model()`].
+ *
+ * The global model is cached by the participant until a new round starts, so that
+ * a subsequent call to [`xaynet_ffi_participant_global_model()`] doesn't download it
+ * again.
+ *
+ * # Return Value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` or `len` is NULL
+ * - `GLOBALMODEL_NONE` if no model exists
+ * - `ERR_GLOBALMODEL_IO` if the communication with the coordinator failed
+ * - `ERR_GLOBALMODEL_LEN` if the number of weights doesn't fit in `len`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_global_model_len(struct Participant *participant,
+                                                        unsigned int *len);
+
+/**
+ * Return the latest global model from the coordinator.
+ *
+ * - `buffer` is the array in which the global model should be copied.
+ * - `data_type` specifies the type of the model weights (see [`DataType`]). The C header
+ *   file generated by this crate provides an enum corresponding to the parameters: `DataType`.
+ * - `len` is the number of weights the model has (see
+ *   [`xaynet_ffi_participant_global_model_len()`])
+ *
+ * # Return Value
+ *
+ * - `OK` if the model is set successfully
+ * - `ERR_NULLPTR` if `participant` or the `buffer` is NULL
+ * - `GLOBALMODEL_NONE` if no model exists
+ * - `ERR_GLOBALMODEL_IO` if the communication with the coordinator failed
+ * - `ERR_GLOBALMODEL_DATATYPE` if the datatype is invalid
+ * - `ERR_GLOBALMODEL_LEN` if the length of the buffer does not match the length of the model
+ * - `ERR_GLOBALMODEL_CONVERT` if the conversion of the model failed
+ *
+ * # Note
+ *
+ *   It is **not** guaranteed, that the model configuration returned by
+ *   [`xaynet_ffi_participant_local_model_config`] corresponds to the configuration of
+ *   the global model. This means that the global model can have a different length / data type
+ *   than it is defined in model configuration. That both model configurations are the same is
  *   only guaranteed if the model config **never** changes on the coordinator side.
  *
  * # Safety
  *
- * 1. When calling this method, you have to ensure that *either* the pointer is NULL
- *    *or* all of the following is true:
- *    - The pointer must be properly [aligned].
- *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
- *      documentation.
- * 2. If `len` or `data_type` do not match the model in `buffer`, this method will
- *    result in a buffer over-read.
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. If `len` or `data_type` do not match the model in `buffer`, this method will
+ *    result in a buffer over-read.
+ *
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_global_model(struct Participant *participant,
+                                                    void *buffer,
+                                                    unsigned char data_type,
+                                                    unsigned int len);
+
+/**
+ * Return the local model configuration of the model that is expected in the
+ * [`xaynet_ffi_participant_set_model()`] function.
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointer is NULL
+ *    *or* all of the following is true:
+ *    - The pointer must be properly [aligned].
+ *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ *
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+struct LocalModelConfig *xaynet_ffi_participant_local_model_config(const struct Participant *participant);
+
+/**
+ * Recommend when the participant should be ticked next and what kind of work it
+ * performs then, and write the recommendation into `recommendation`. This is meant for
+ * scheduling the participant in the background (see
+ * [`Participant::next_wakeup_recommendation()`]).
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` or `recommendation` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_next_wakeup(const struct Participant *participant,
+                                                   struct WakeupRecommendation *recommendation);
+
+/**
+ * Get the statistics about the training from the coordinator, and write them into
+ * `stats`. They are safe to show to the user of the app, e.g. the approximate number of
+ * participants that contributed to the latest global model.
+ *
+ * The statistics change at most once per round and the coordinator rate limits the
+ * requests, so they don't need to be fetched more often than every few minutes.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` or `stats` is NULL
+ * - `ERR_PUBLIC_STATS_IO` if the communication with the coordinator failed
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_public_stats(struct Participant *participant,
+                                                    struct PublicStats *stats);
+
+/**
+ * Get the number of bytes the participant sent and received in the current day, and
+ * write it into `used`.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` or `used` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_data_usage(const struct Participant *participant,
+                                                  uint64_t *used);
+
+/**
+ * Get the number of events that the participant dropped, and write it into `dropped`.
+ *
+ * The participant processes the events emitted by its internal state machine during
+ * every [`xaynet_ffi_participant_tick()`], and keeps at most the number of events set
+ * with [`xaynet_ffi_settings_set_max_pending_events()`] until then. If more events are
+ * emitted in a single tick, the oldest informational ones are dropped, the events that
+ * report the progress through the tasks or request an action are never dropped. The
+ * pending events are not part of the state saved with [`xaynet_ffi_participant_save()`],
+ * so its size doesn't depend on them, but their maximum is.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` or `dropped` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_settings_set_max_pending_events()`]: crate::ffi::xaynet_ffi_settings_set_max_pending_events
+ */
+enum ReturnCode xaynet_ffi_participant_dropped_events(const struct Participant *participant,
+                                                      uint64_t *dropped);
+
+/**
+ * Set the maximum number of bytes the participant may send and receive per day. If
+ * `budget` is `0`, the data usage is not limited.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_set_daily_data_budget(struct Participant *participant,
+                                                             uint64_t budget);
+
+/**
+ * Copy the serialized global mask that the participant aggregated in the sum2 phase
+ * into `buffer`, while the participant awaits its confirmation (see
+ * [`PARTICIPANT_AWAITING_SUM2_CONFIRMATION`]).
+ *
+ * When calling this function, `len` must point to the length of `buffer`. On return,
+ * it points to the length of the serialized mask, unless the participant doesn't await
+ * the confirmation of a mask. To get the length of the mask without copying it, `len`
+ * can point to `0`, in which case `buffer` may be NULL.
+ *
+ * # Return value
+ *
+ * - `OK` if the mask is copied into `buffer`
+ * - `ERR_NULLPTR` if `participant` or `len` is NULL, or if `buffer` is NULL while
+ *   `len` doesn't point to `0`
+ * - `SUM2_MASK_NONE` if the participant doesn't await the confirmation of a mask
+ * - `ERR_SUM2_MASK_LEN` if `buffer` is too small for the mask
+ *
+ * # Safety
+ *
+ * 1. When calling this method, you have to ensure that *either* the pointers are NULL
+ *    *or* all of the following is true:
+ *    - The pointers must be properly [aligned].
+ *    - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *      documentation.
+ * 2. `buffer` must be valid for writes of `len` bytes.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_sum2_mask(const struct Participant *participant,
+                                                 unsigned char *buffer,
+                                                 unsigned int *len);
+
+/**
+ * Confirm the global mask that the participant aggregated in the sum2 phase, so that
+ * the sum2 message is sent on the next tick.
+ *
+ * # Return value
+ *
+ * - `OK` if the mask is confirmed
+ * - `ERR_NULLPTR` if `participant` is NULL
+ * - `SUM2_MASK_NONE` if the participant doesn't await the confirmation of a mask
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_confirm_sum2(struct Participant *participant);
+
+/**
+ * Get the request for the consent of the user to take part in the task the participant
+ * has been selected for, while the participant awaits the consent (see
+ * [`PARTICIPANT_AWAITING_CONSENT`]).
+ *
+ * On success, `task` points to [`PARTICIPANT_TASK_SUM`] or [`PARTICIPANT_TASK_UPDATE`]
+ * and `upload_bytes` points to an estimate of the number of bytes the participant
+ * uploads for the task. The entries of the local seed dictionary of the update task
+ * are not part of the estimate, because they depend on the number of sum participants.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant`, `task` or `upload_bytes` is NULL
+ * - `CONSENT_NONE` if the participant doesn't await the consent of the user
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointers are NULL
+ * *or* all of the following is true:
+ * - The pointers must be properly [aligned].
+ * - They must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_consent_request(const struct Participant *participant,
+                                                       int *task,
+                                                       uint64_t *upload_bytes);
+
+/**
+ * Grant the consent of the user to take part in the task the participant has been
+ * selected for, so that the task starts on the next tick.
+ *
+ * # Return value
+ *
+ * - `OK` if the consent is granted
+ * - `ERR_NULLPTR` if `participant` is NULL
+ * - `CONSENT_NONE` if the participant doesn't await the consent of the user
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_grant_consent(struct Participant *participant);
+
+/**
+ * Deny the consent of the user to take part in the task the participant has been
+ * selected for, so that the task is abandoned on the next tick.
+ *
+ * # Return value
+ *
+ * - `OK` if the consent is denied
+ * - `ERR_NULLPTR` if `participant` is NULL
+ * - `CONSENT_NONE` if the participant doesn't await the consent of the user
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_participant_deny_consent(struct Participant *participant);
+
+/**
+ * Instantiate a new shared participant with the given settings, see
+ * [`xaynet_ffi_participant_new()`]. The participant must be destroyed with
+ * [`xaynet_ffi_shared_participant_destroy()`].
+ *
+ * # Return value
+ *
+ * - a NULL pointer if `settings` is NULL or if the participant creation failed
+ * - a valid pointer to a [`SharedParticipant`] otherwise
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_participant_new()`].
+ */
+const struct SharedParticipant *xaynet_ffi_shared_participant_new(const struct Settings *settings);
+
+/**
+ * Restore a shared participant from a buffer that contained its serialized state, see
+ * [`xaynet_ffi_participant_restore()`]. The participant must be destroyed with
+ * [`xaynet_ffi_shared_participant_destroy()`].
+ *
+ * # Return value
+ *
+ * - a NULL pointer on failure
+ * - a valid pointer to a [`SharedParticipant`] otherwise
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_participant_restore()`].
+ */
+const struct SharedParticipant *xaynet_ffi_shared_participant_restore(FfiStr url,
+                                                                      const struct ByteBuffer *buffer);
+
+/**
+ * Create a new reference to the given shared participant. The reference must be
+ * destroyed with [`xaynet_ffi_shared_participant_destroy()`].
+ *
+ * # Return value
+ *
+ * - a NULL pointer if `participant` is NULL
+ * - the new reference otherwise, which is the same pointer as `participant`
+ *
+ * # Safety
+ *
+ * `participant` must be NULL or a reference to a shared participant that has not been
+ * destroyed.
+ */
+const struct SharedParticipant *xaynet_ffi_shared_participant_clone(const struct SharedParticipant *participant);
+
+/**
+ * Destroy a reference to the given shared participant. The participant is destroyed
+ * with its last reference.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `participant` is NULL
+ *
+ * # Safety
+ *
+ * 1. `participant` must be NULL or a reference to a shared participant that has not
+ *    been destroyed.
+ * 2. After destroying the reference, the pointer must not be used through this
+ *    reference.
+ */
+enum ReturnCode xaynet_ffi_shared_participant_destroy(const struct SharedParticipant *participant);
+
+/**
+ * Drive the shared participant internal state machine, see
+ * [`xaynet_ffi_participant_tick()`]. The callbacks are invoked on the current thread,
+ * after the participant is unlocked.
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+int xaynet_ffi_shared_participant_tick(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_rounds_observed()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+unsigned int xaynet_ffi_shared_participant_rounds_observed(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_public_key()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_public_key()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_public_key(const struct SharedParticipant *participant,
+                                                         unsigned char *buffer);
+
+/**
+ * See [`xaynet_ffi_participant_rotate_keys()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_rotate_keys()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_rotate_keys(const struct SharedParticipant *participant,
+                                                          unsigned char *buffer);
+
+/**
+ * See [`xaynet_ffi_participant_update_tls_root_certificate()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_update_tls_root_certificate()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_update_tls_root_certificate(const struct SharedParticipant *participant,
+                                                                          const struct ByteBuffer *buffer);
+
+/**
+ * See [`xaynet_ffi_participant_update_tls_identity()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_update_tls_identity()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_update_tls_identity(const struct SharedParticipant *participant,
+                                                                  const struct ByteBuffer *buffer);
+
+/**
+ * See [`xaynet_ffi_participant_history_len()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+unsigned int xaynet_ffi_shared_participant_history_len(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_history_entry()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_history_entry()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_history_entry(const struct SharedParticipant *participant,
+                                                            unsigned int index,
+                                                            struct RoundRecord *record);
+
+/**
+ * See [`xaynet_ffi_participant_task()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+int xaynet_ffi_shared_participant_task(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_pending_work()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+int xaynet_ffi_shared_participant_pending_work(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_set_state_changed_callback()`]. The callback may be
+ * invoked on any thread that ticks the participant.
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_set_state_changed_callback()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_set_state_changed_callback(const struct SharedParticipant *participant,
+                                                                         void (*callback)(void *user_data, int change),
+                                                                         void *user_data);
+
+/**
+ * See [`xaynet_ffi_participant_set_progress_callback()`]. The callback may be invoked
+ * on any thread that ticks the participant.
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_set_progress_callback()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_set_progress_callback(const struct SharedParticipant *participant,
+                                                                    void (*callback)(void *user_data, int progress, uint64_t value),
+                                                                    void *user_data);
+
+/**
+ * Save the shared participant without destroying it, see
+ * [`xaynet_ffi_participant_prepare_for_shutdown()`]. Unlike
+ * [`xaynet_ffi_participant_save()`], the participant stays usable by the other
+ * references.
  *
- * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`]. The `ByteBuffer` created by this
+ * function must be destroyed with [`xaynet_ffi_byte_buffer_destroy`].
+ *
+ * [`xaynet_ffi_byte_buffer_destroy`]: crate::ffi::xaynet_ffi_byte_buffer_destroy
  */
-int xaynet_ffi_participant_global_model(struct Participant *participant,
-                                        void *buffer,
-                                        unsigned char data_type,
-                                        unsigned int len);
+const struct ByteBuffer *xaynet_ffi_shared_participant_save(const struct SharedParticipant *participant);
 
 /**
- * Return the local model configuration of the model that is expected in the
- * [`xaynet_ffi_participant_set_model()`] function.
+ * See [`xaynet_ffi_participant_set_model()`].
  *
  * # Safety
  *
- * 1. When calling this method, you have to ensure that *either* the pointer is NULL
- *    *or* all of the following is true:
- *    - The pointer must be properly [aligned].
- *    - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
- *      documentation.
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_set_model()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_set_model(const struct SharedParticipant *participant,
+                                                        const void *buffer,
+                                                        unsigned char data_type,
+                                                        unsigned int len);
+
+/**
+ * See [`xaynet_ffi_participant_set_sparse_model()`].
  *
- * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_set_sparse_model()`].
  */
-struct LocalModelConfig *xaynet_ffi_participant_local_model_config(const struct Participant *participant);
+enum ReturnCode xaynet_ffi_shared_participant_set_sparse_model(const struct SharedParticipant *participant,
+                                                               const unsigned int *indices,
+                                                               const void *values,
+                                                               unsigned char data_type,
+                                                               unsigned int len,
+                                                               unsigned int total_len);
+
+/**
+ * See [`xaynet_ffi_participant_global_model_len()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_global_model_len()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_global_model_len(const struct SharedParticipant *participant,
+                                                               unsigned int *len);
+
+/**
+ * See [`xaynet_ffi_participant_global_model()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_global_model()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_global_model(const struct SharedParticipant *participant,
+                                                           void *buffer,
+                                                           unsigned char data_type,
+                                                           unsigned int len);
+
+/**
+ * See [`xaynet_ffi_participant_local_model_config()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_local_model_config()`].
+ */
+struct LocalModelConfig *xaynet_ffi_shared_participant_local_model_config(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_next_wakeup()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_next_wakeup()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_next_wakeup(const struct SharedParticipant *participant,
+                                                          struct WakeupRecommendation *recommendation);
+
+/**
+ * See [`xaynet_ffi_participant_public_stats()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_public_stats()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_public_stats(const struct SharedParticipant *participant,
+                                                           struct PublicStats *stats);
+
+/**
+ * See [`xaynet_ffi_participant_data_usage()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_data_usage()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_data_usage(const struct SharedParticipant *participant,
+                                                         uint64_t *used);
+
+/**
+ * See [`xaynet_ffi_participant_dropped_events()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_dropped_events()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_dropped_events(const struct SharedParticipant *participant,
+                                                             uint64_t *dropped);
+
+/**
+ * See [`xaynet_ffi_participant_set_daily_data_budget()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_set_daily_data_budget(const struct SharedParticipant *participant,
+                                                                    uint64_t budget);
+
+/**
+ * See [`xaynet_ffi_participant_sum2_mask()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_sum2_mask()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_sum2_mask(const struct SharedParticipant *participant,
+                                                        unsigned char *buffer,
+                                                        unsigned int *len);
+
+/**
+ * See [`xaynet_ffi_participant_confirm_sum2()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_confirm_sum2(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_consent_request()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`] and
+ * [`xaynet_ffi_participant_consent_request()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_consent_request(const struct SharedParticipant *participant,
+                                                              int *task,
+                                                              uint64_t *upload_bytes);
+
+/**
+ * See [`xaynet_ffi_participant_grant_consent()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_grant_consent(const struct SharedParticipant *participant);
+
+/**
+ * See [`xaynet_ffi_participant_deny_consent()`].
+ *
+ * # Safety
+ *
+ * See [`xaynet_ffi_shared_participant_clone()`].
+ */
+enum ReturnCode xaynet_ffi_shared_participant_deny_consent(const struct SharedParticipant *participant);
 
 /**
  * Destroy the settings created by [`xaynet_ffi_settings_new()`].
  *
  * # Return value
  *
- * - [`OK`] on success
- * - [`ERR_NULLPTR`] if `buf` is NULL
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `buf` is NULL
  *
  * # Safety
  *
@@ -649,7 +2161,7 @@ struct LocalModelConfig *xaynet_ffi_participant_local_model_config(const struct
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_settings_destroy(struct Settings *settings);
+enum ReturnCode xaynet_ffi_settings_destroy(struct Settings *settings);
 
 /**
  * Create new [`Settings`] and return a pointer to it.
@@ -667,8 +2179,8 @@ struct Settings *xaynet_ffi_settings_new(void);
  *
  * # Return value
  *
- * - [`OK`] if successful
- * - [`ERR_NULLPTR`] if `settings` is `NULL`
+ * - `OK` if successful
+ * - `ERR_NULLPTR` if `settings` is `NULL`
  *
  * # Safety
  *
@@ -681,16 +2193,16 @@ struct Settings *xaynet_ffi_settings_new(void);
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_settings_set_scalar(struct Settings *settings, double scalar);
+enum ReturnCode xaynet_ffi_settings_set_scalar(struct Settings *settings, double scalar);
 
 /**
  * Set coordinator URL.
  *
  * # Return value
  *
- * - [`OK`] if successful
- * - [`ERR_INVALID_URL`] if `url` is not a valid string
- * - [`ERR_NULLPTR`] if `settings` is `NULL`
+ * - `OK` if successful
+ * - `ERR_INVALID_URL` if `url` is not a valid string
+ * - `ERR_NULLPTR` if `settings` is `NULL`
  *
  * # Safety
  *
@@ -703,7 +2215,183 @@ int xaynet_ffi_settings_set_scalar(struct Settings *settings, double scalar);
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_settings_set_url(struct Settings *settings, FfiStr url);
+enum ReturnCode xaynet_ffi_settings_set_url(struct Settings *settings, FfiStr url);
+
+/**
+ * Set the maximum number of bytes the participant may send and receive per day. Once
+ * the budget is exhausted, the participant declines to start new network operations
+ * until the next day. If `budget` is `0`, the data usage is not limited.
+ *
+ * # Return value
+ *
+ * - `OK` if successful
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_settings_set_daily_data_budget(struct Settings *settings,
+                                                          uint64_t budget);
+
+/**
+ * Set whether the participant pauses before sending the sum2 message, until the global
+ * mask it aggregated is confirmed with [`xaynet_ffi_participant_confirm_sum2()`]. If
+ * `confirm` is `0`, the participant doesn't pause, which is the default.
+ *
+ * [`xaynet_ffi_participant_confirm_sum2()`]: crate::ffi::xaynet_ffi_participant_confirm_sum2
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_settings_set_confirm_sum2(struct Settings *settings, int confirm);
+
+/**
+ * Set whether the participant pauses when it is selected for a task, until the user
+ * consents to take part in it with [`xaynet_ffi_participant_grant_consent()`] or
+ * refuses with [`xaynet_ffi_participant_deny_consent()`]. If `require` is `0`, the
+ * participant doesn't pause, which is the default.
+ *
+ * [`xaynet_ffi_participant_grant_consent()`]: crate::ffi::xaynet_ffi_participant_grant_consent
+ * [`xaynet_ffi_participant_deny_consent()`]: crate::ffi::xaynet_ffi_participant_deny_consent
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_settings_set_require_consent(struct Settings *settings, int require);
+
+/**
+ * Set for how many seconds the participant waits for the consent of the user before
+ * abandoning the task. If `secs` is `0`, the participant waits until the end of the
+ * round, which is the default.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_settings_set_consent_timeout(struct Settings *settings, uint64_t secs);
+
+/**
+ * Set for how many seconds an idle connection to the coordinator is kept open, such that
+ * the next request doesn't have to open a new one. The default is 90 seconds.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ */
+enum ReturnCode xaynet_ffi_settings_set_pool_idle_timeout(struct Settings *settings, uint64_t secs);
+
+/**
+ * Set the maximum number of events that the participant keeps until it processes them.
+ * The participant processes the events emitted by its internal state machine during
+ * every [`xaynet_ffi_participant_tick()`]. If more events are emitted in a single tick,
+ * the oldest informational ones are dropped (see
+ * [`xaynet_ffi_participant_dropped_events()`]), the events that report the progress
+ * through the tasks or request an action are never dropped. The default is 256, and `0`
+ * is treated as `1`. The maximum is part of the saved state of the participant.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_participant_tick()`]: crate::ffi::xaynet_ffi_participant_tick
+ * [`xaynet_ffi_participant_dropped_events()`]: crate::ffi::xaynet_ffi_participant_dropped_events
+ */
+enum ReturnCode xaynet_ffi_settings_set_max_pending_events(struct Settings *settings,
+                                                           unsigned int max);
+
+/**
+ * Set the maximum number of completed rounds the participant keeps in its history (see
+ * [`xaynet_ffi_participant_history_len()`]). When the history is full, the oldest round
+ * is dropped. The default is 50, and `0` disables the history.
+ *
+ * # Return value
+ *
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `settings` is `NULL`
+ *
+ * # Safety
+ *
+ * When calling this method, you have to ensure that *either* the pointer is NULL *or*
+ * all of the following is true:
+ * - The pointer must be properly [aligned].
+ * - It must be "dereferencable" in the sense defined in the [`std::ptr`] module
+ *   documentation.
+ *
+ * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
+ * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
+ * [`xaynet_ffi_participant_history_len()`]: crate::ffi::xaynet_ffi_participant_history_len
+ */
+enum ReturnCode xaynet_ffi_settings_set_max_history_len(struct Settings *settings,
+                                                        unsigned int max);
 
 /**
  * Generate a new signing key pair that can be used in the [`Settings`]. **Before
@@ -728,8 +2416,8 @@ const struct KeyPair *xaynet_ffi_generate_key_pair(void);
  *
  * # Return value
  *
- * - [`ERR_NULLPTR`] is `key_pair` is NULL
- * - [`OK`] otherwise
+ * - `ERR_NULLPTR` is `key_pair` is NULL
+ * - `OK` otherwise
  *
  * # Safety
  *
@@ -742,17 +2430,17 @@ const struct KeyPair *xaynet_ffi_generate_key_pair(void);
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_forget_key_pair(const struct KeyPair *key_pair);
+enum ReturnCode xaynet_ffi_forget_key_pair(const struct KeyPair *key_pair);
 
 /**
  * Set participant signing keys.
  *
  * # Return value
  *
- * - [`OK`] if successful
- * - [`ERR_NULLPTR`] if `settings` or `key_pair` is `NULL`
- * - [`ERR_CRYPTO_PUBLIC_KEY`] if the given `key_pair` contains an invalid public key
- * - [`ERR_CRYPTO_SECRET_KEY`] if the given `key_pair` contains an invalid secret key
+ * - `OK` if successful
+ * - `ERR_NULLPTR` if `settings` or `key_pair` is `NULL`
+ * - `ERR_CRYPTO_PUBLIC_KEY` if the given `key_pair` contains an invalid public key
+ * - `ERR_CRYPTO_SECRET_KEY` if the given `key_pair` contains an invalid secret key
  *
  * # Safety
  *
@@ -765,7 +2453,8 @@ int xaynet_ffi_forget_key_pair(const struct KeyPair *key_pair);
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_settings_set_keys(struct Settings *settings, const struct KeyPair *key_pair);
+enum ReturnCode xaynet_ffi_settings_set_keys(struct Settings *settings,
+                                             const struct KeyPair *key_pair);
 
 /**
  * Check whether the given settings are valid and can be used to instantiate a
@@ -773,10 +2462,10 @@ int xaynet_ffi_settings_set_keys(struct Settings *settings, const struct KeyPair
  *
  * # Return value
  *
- * - [`OK`] on success
- * - [`ERR_SETTINGS_URL`] if the URL has not been set
- * - [`ERR_SETTINGS_KEYS`] if the signing keys have not been set
- * - [`ERR_SETTINGS_SCALAR`] if the scalar is out of bounds
+ * - `OK` on success
+ * - `ERR_SETTINGS_URL` if the URL has not been set
+ * - `ERR_SETTINGS_KEYS` if the signing keys have not been set
+ * - `ERR_SETTINGS_SCALAR` if the scalar is out of bounds
  *
  * # Safety
  *
@@ -791,15 +2480,15 @@ int xaynet_ffi_settings_set_keys(struct Settings *settings, const struct KeyPair
  * [`std::ptr`]: https://doc.rust-lang.org/std/ptr/index.html#safety
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  */
-int xaynet_ffi_check_settings(const struct Settings *settings);
+enum ReturnCode xaynet_ffi_check_settings(const struct Settings *settings);
 
 /**
  * Destroy the model configuration created by [`xaynet_ffi_participant_local_model_config()`].
  *
  * # Return value
  *
- * - [`OK`] on success
- * - [`ERR_NULLPTR`] if `local_model_config` is NULL
+ * - `OK` on success
+ * - `ERR_NULLPTR` if `local_model_config` is NULL
  *
  * # Safety
  *
@@ -817,4 +2506,58 @@ int xaynet_ffi_check_settings(const struct Settings *settings);
  * [aligned]: https://doc.rust-lang.org/std/ptr/index.html#alignment
  * [`xaynet_ffi_participant_local_model_config()`]: crate::ffi::xaynet_ffi_participant_local_model_config
  */
-int xaynet_ffi_local_model_config_destroy(struct LocalModelConfig *local_model_config);
+enum ReturnCode xaynet_ffi_local_model_config_destroy(struct LocalModelConfig *local_model_config);
+
+/**
+ * Get the length in bytes of the message of the last error of an FFI function on the
+ * current thread, including the terminating NUL byte.
+ *
+ * # Return value
+ *
+ * - the length of the message
+ * - `0` if no FFI function failed on the current thread
+ *
+ * # Safety
+ *
+ * This function is safe to call
+ */
+int xaynet_ffi_last_error_length(void);
+
+/**
+ * Copy the message of the last error of an FFI function on the current thread into
+ * `buffer`, as a NUL-terminated string. The message names the function that failed
+ * and the reason of the failure, for instance:
+ *
+ * ```text
+ * xaynet_ffi_participant_new failed: invalid participant settings MissingKeys
+ * ```
+ *
+ * The length of `buffer` must be at least [`xaynet_ffi_last_error_length()`]. The
+ * message is kept until another FFI function fails on the current thread, this
+ * function and [`xaynet_ffi_last_error_length()`] never fail with a message.
+ *
+ * # Return value
+ *
+ * - `OK` if the message is copied into `buffer`
+ * - `ERR_NULLPTR` if `buffer` is NULL
+ * - `LAST_ERROR_NONE` if no FFI function failed on the current thread
+ * - `ERR_LAST_ERROR_LEN` if `buffer` is too small for the message
+ *
+ * # Safety
+ *
+ * `buffer` must be valid for writes of `len` bytes.
+ *
+ * # Example
+ *
+ * ```c
+ * int len = xaynet_ffi_last_error_length();
+ * if (len > 0) {
+ *   char *message = malloc(len);
+ *   if (xaynet_ffi_last_error_message(message, len) == OK) {
+ *     fprintf(stderr, "%s\n", message);
+ *   }
+ *   free(message);
+ * }
+ * ```
+ */
+enum ReturnCode xaynet_ffi_last_error_message(char *buffer, unsigned int len);
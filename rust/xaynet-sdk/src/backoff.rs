@@ -0,0 +1,164 @@
+//! Polling intervals of an agent.
+//!
+//! An agent polls the coordinator by calling [`StateMachine::transition()`] until the
+//! state machine makes progress. When many participants poll a loaded coordinator at a
+//! fixed interval, they degrade its throughput further. A [`Backoff`] makes the interval
+//! grow while the state machine is pending, and resets it once it makes progress.
+//!
+//! [`StateMachine::transition()`]: crate::StateMachine::transition
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Configuration of a [`Backoff`].
+///
+/// The first interval is [`initial_interval`], and each following interval is
+/// [`multiplier`] times the previous one, up to [`max_interval`]. The default
+/// configuration polls at a fixed interval of one second.
+///
+/// [`initial_interval`]: BackoffConfig::initial_interval
+/// [`multiplier`]: BackoffConfig::multiplier
+/// [`max_interval`]: BackoffConfig::max_interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackoffConfig {
+    /// The interval after the state machine made progress.
+    pub initial_interval: Duration,
+    /// The maximum interval. It is never shorter than the initial interval.
+    pub max_interval: Duration,
+    /// The factor by which the interval grows while the state machine is pending. A
+    /// multiplier smaller than `1` is treated as `1`.
+    pub multiplier: f64,
+    /// Whether each interval is shortened by a random delay of at most half of it, so that
+    /// the participants don't all poll the coordinator at the same time.
+    pub jitter: bool,
+}
+
+impl Default for BackoffConfig {
+    fn default() -> Self {
+        Self::fixed(Duration::from_secs(1))
+    }
+}
+
+impl BackoffConfig {
+    /// Configuration of a backoff that polls at a fixed `interval`.
+    pub fn fixed(interval: Duration) -> Self {
+        Self {
+            initial_interval: interval,
+            max_interval: interval,
+            multiplier: 1.0,
+            jitter: false,
+        }
+    }
+}
+
+/// The polling intervals of an agent.
+///
+/// ```
+/// use tokio::time::sleep;
+/// use xaynet_sdk::{Backoff, StateMachine, TransitionOutcome};
+///
+/// async fn run_agent(mut state_machine: StateMachine, mut backoff: Backoff) {
+///     loop {
+///         state_machine = match state_machine.transition().await {
+///             TransitionOutcome::Pending(state_machine) => {
+///                 sleep(backoff.next_interval()).await;
+///                 state_machine
+///             }
+///             TransitionOutcome::Complete(state_machine) => {
+///                 backoff.reset();
+///                 state_machine
+///             }
+///         };
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    config: BackoffConfig,
+    /// The next interval, without jitter.
+    interval: Duration,
+}
+
+impl Backoff {
+    /// Creates a new backoff.
+    pub fn new(config: BackoffConfig) -> Self {
+        Self {
+            config,
+            interval: config.initial_interval,
+        }
+    }
+
+    /// Returns the interval to wait before polling again, and grows the next one.
+    pub fn next_interval(&mut self) -> Duration {
+        let interval = self.interval;
+        let max_interval = self.config.max_interval.max(self.config.initial_interval);
+        let multiplier = self.config.multiplier.max(1.0);
+        self.interval = if interval.as_secs_f64() * multiplier < max_interval.as_secs_f64() {
+            interval.mul_f64(multiplier)
+        } else {
+            max_interval
+        };
+
+        if self.config.jitter {
+            let max_jitter = interval / 2;
+            interval - rand::thread_rng().gen_range(Duration::from_secs(0)..=max_jitter)
+        } else {
+            interval
+        }
+    }
+
+    /// Resets the interval to the initial one. This should be called when the state
+    /// machine made progress.
+    pub fn reset(&mut self) {
+        self.interval = self.config.initial_interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intervals(backoff: &mut Backoff, n: usize) -> Vec<Duration> {
+        (0..n).map(|_| backoff.next_interval()).collect()
+    }
+
+    #[test]
+    fn test_fixed_interval() {
+        let mut backoff = Backoff::new(BackoffConfig::default());
+        assert_eq!(intervals(&mut backoff, 3), vec![Duration::from_secs(1); 3]);
+    }
+
+    #[test]
+    fn test_converges_to_max_interval() {
+        let mut backoff = Backoff::new(BackoffConfig {
+            initial_interval: Duration::from_millis(100),
+            max_interval: Duration::from_millis(1000),
+            multiplier: 2.0,
+            jitter: false,
+        });
+        let expected: Vec<_> = [100, 200, 400, 800, 1000, 1000, 1000]
+            .iter()
+            .map(|&millis| Duration::from_millis(millis))
+            .collect();
+        assert_eq!(intervals(&mut backoff, 7), expected);
+
+        backoff.reset();
+        assert_eq!(backoff.next_interval(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let mut backoff = Backoff::new(BackoffConfig {
+            initial_interval: Duration::from_millis(100),
+            max_interval: Duration::from_millis(1000),
+            multiplier: 2.0,
+            jitter: true,
+        });
+        for max in &[100, 200, 400, 800, 1000, 1000] {
+            let interval = backoff.next_interval();
+            assert!(interval >= Duration::from_millis(max / 2));
+            assert!(interval <= Duration::from_millis(*max));
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Gzip compression of the request and response bodies, shared by the HTTP backends.
+
+use std::io::{Read, Write};
+
+use bytes::Bytes;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use super::ClientError;
+
+/// The content coding of gzip compressed bodies.
+pub(super) const GZIP: &str = "gzip";
+
+/// The maximum length of a decompressed response body, which protects the participant
+/// against decompression bombs.
+const MAX_DECOMPRESSED_LENGTH: u64 = 1 << 30;
+
+/// Compress the `body` of a request.
+pub(super) fn compress(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // UNWRAP_SAFE: writing to a vector never fails
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Decode the `body` of a response according to its `Content-Encoding` header.
+///
+/// # Errors
+/// Fails if the body is encoded with another coding than gzip or if it isn't valid gzip.
+pub(super) fn decode(content_encoding: Option<&str>, body: Bytes) -> Result<Bytes, ClientError> {
+    match content_encoding.map(str::trim) {
+        None => Ok(body),
+        Some(coding) if coding.is_empty() || coding.eq_ignore_ascii_case("identity") => Ok(body),
+        Some(coding) if coding.eq_ignore_ascii_case(GZIP) => decompress(&body).map(Bytes::from),
+        Some(coding) => Err(ClientError::Deserialize(format!(
+            "unsupported content encoding: {}",
+            coding
+        ))),
+    }
+}
+
+fn decompress(body: &[u8]) -> Result<Vec<u8>, ClientError> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(body)
+        .take(MAX_DECOMPRESSED_LENGTH + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| ClientError::Deserialize(format!("invalid gzip body: {}", e)))?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_LENGTH {
+        return Err(ClientError::Deserialize(
+            "decompressed body too large".to_string(),
+        ));
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let body = vec![7; 10_000];
+        let compressed = compress(&body);
+        assert!(compressed.len() < body.len());
+        assert_eq!(
+            decode(Some("gzip"), compressed.clone().into()).unwrap(),
+            body
+        );
+        assert_eq!(decode(Some(" GZIP "), compressed.into()).unwrap(), body);
+    }
+
+    #[test]
+    fn test_decode_uncompressed() {
+        let body = Bytes::from_static(b"data");
+        assert_eq!(decode(None, body.clone()).unwrap(), body);
+        assert_eq!(decode(Some("identity"), body.clone()).unwrap(), body);
+        assert!(matches!(
+            decode(Some("br"), body.clone()),
+            Err(ClientError::Deserialize(_))
+        ));
+        assert!(matches!(
+            decode(Some("gzip"), body),
+            Err(ClientError::Deserialize(_))
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+//! A lean [`XaynetHttpClient`] implementation based on [`hyper`] and [`rustls`].
+//!
+//! It only speaks HTTP/1.1 and has none of the extras of `reqwest` (redirects, cookies,
+//! proxies), which keeps the libraries that embed the participant small. Bodies are only
+//! compressed with the `gzip` feature.
+
+use std::{io::Cursor, time::Duration};
+
+use async_trait::async_trait;
+#[cfg(feature = "gzip")]
+use hyper::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+use hyper::{body::Bytes, client::HttpConnector, Body, Request};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore};
+use rustls_pemfile::Item;
+use thiserror::Error;
+
+#[cfg(feature = "gzip")]
+use super::gzip;
+use super::{response, ClientError, XaynetHttpClient};
+
+/// Error returned upon failing to build a [`HyperClient`].
+#[derive(Debug, Error)]
+pub enum HyperClientError {
+    #[error("invalid root certificate: {0}")]
+    RootCertificate(String),
+    #[error("invalid client identity: {0}")]
+    Identity(String),
+}
+
+/// Returns a [`HyperClientBuilder`] for talking to the coordinator.
+///
+/// The connections to the coordinator are pooled, such that consecutive requests reuse the
+/// same connection as long as it hasn't been idle for longer than `pool_idle_timeout`.
+pub fn hyper_client_builder(pool_idle_timeout: Duration) -> HyperClientBuilder {
+    HyperClient::builder().pool_idle_timeout(pool_idle_timeout)
+}
+
+/// A builder for a [`HyperClient`].
+///
+/// The server certificates are verified against the Mozilla root certificates, plus the
+/// root certificates added with [`HyperClientBuilder::add_root_certificate()`].
+#[derive(Debug, Default)]
+pub struct HyperClientBuilder {
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    root_certificates: Vec<Certificate>,
+    identity: Option<(Vec<Certificate>, PrivateKey)>,
+}
+
+impl HyperClientBuilder {
+    /// Sets how long an idle connection is kept in the pool.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum number of idle connections per host. `0` disables the pool.
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Trusts the PEM encoded root certificate(s) for the TLS server authentication.
+    ///
+    /// # Errors
+    /// Fails if `pem` contains no certificate.
+    pub fn add_root_certificate(mut self, pem: &[u8]) -> Result<Self, HyperClientError> {
+        let certs = rustls_pemfile::certs(&mut Cursor::new(pem))
+            .map_err(|e| HyperClientError::RootCertificate(e.to_string()))?;
+        if certs.is_empty() {
+            return Err(HyperClientError::RootCertificate(
+                "no certificate found".to_string(),
+            ));
+        }
+        self.root_certificates
+            .extend(certs.into_iter().map(Certificate));
+        Ok(self)
+    }
+
+    /// Sets the identity for the TLS client authentication, from a PEM encoded private key
+    /// and certificate chain.
+    ///
+    /// # Errors
+    /// Fails if `pem` doesn't contain exactly one private key and at least one certificate.
+    pub fn identity(mut self, pem: &[u8]) -> Result<Self, HyperClientError> {
+        let items = rustls_pemfile::read_all(&mut Cursor::new(pem))
+            .map_err(|e| HyperClientError::Identity(e.to_string()))?;
+        let mut certs = Vec::new();
+        let mut keys = Vec::new();
+        for item in items {
+            match item {
+                Item::X509Certificate(cert) => certs.push(Certificate(cert)),
+                Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => {
+                    keys.push(PrivateKey(key))
+                }
+                _ => {}
+            }
+        }
+        if certs.is_empty() || keys.len() != 1 {
+            return Err(HyperClientError::Identity(
+                "expected one private key and at least one certificate".to_string(),
+            ));
+        }
+        // UNWRAP_SAFE: there is one key
+        self.identity = Some((certs, keys.pop().unwrap()));
+        Ok(self)
+    }
+
+    /// Builds the client.
+    ///
+    /// # Errors
+    /// Fails if a root certificate or the identity is rejected by the TLS library.
+    pub fn build(self) -> Result<HyperClient, HyperClientError> {
+        let mut roots = RootCertStore::empty();
+        roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|ta| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+        for cert in &self.root_certificates {
+            roots
+                .add(cert)
+                .map_err(|e| HyperClientError::RootCertificate(e.to_string()))?;
+        }
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let config = match self.identity {
+            Some((certs, key)) => config
+                .with_single_cert(certs, key)
+                .map_err(|e| HyperClientError::Identity(e.to_string()))?,
+            None => config.with_no_client_auth(),
+        };
+        let connector = HttpsConnectorBuilder::new()
+            .with_tls_config(config)
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        let mut builder = hyper::Client::builder();
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max_idle);
+        }
+        Ok(HyperClient(builder.build(connector)))
+    }
+}
+
+/// An HTTP/1.1 client for talking to the coordinator over plain HTTP or over TLS.
+#[derive(Clone, Debug)]
+pub struct HyperClient(hyper::Client<HttpsConnector<HttpConnector>>);
+
+impl HyperClient {
+    /// Creates a client with the default settings.
+    pub fn new() -> Self {
+        // PANIC_SAFE: the default settings have no certificate which could be rejected
+        Self::builder()
+            .build()
+            .expect("failed to build the default client")
+    }
+
+    /// Returns a builder to configure the client.
+    pub fn builder() -> HyperClientBuilder {
+        HyperClientBuilder::default()
+    }
+}
+
+impl Default for HyperClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl XaynetHttpClient for HyperClient {
+    type Error = hyper::Error;
+    type GetResponse = Bytes;
+
+    async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        let uri = url.parse().map_err(ClientError::http_error)?;
+        let resp = self.0.get(uri).await.map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        response::decode_get(status, async move {
+            hyper::body::to_bytes(resp.into_body())
+                .await
+                .map_err(ClientError::http_error)
+        })
+        .await
+    }
+
+    async fn post(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        let request = Request::post(url)
+            .body(Body::from(body))
+            .map_err(ClientError::http_error)?;
+        let resp = self
+            .0
+            .request(request)
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        response::decode_post(status, async move {
+            let body = hyper::body::to_bytes(resp.into_body())
+                .await
+                .map_err(ClientError::http_error)?;
+            Ok(String::from_utf8_lossy(&body).into_owned())
+        })
+        .await
+    }
+
+    #[cfg(feature = "gzip")]
+    async fn get_gzip(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        let request = Request::get(url)
+            .header(ACCEPT_ENCODING, gzip::GZIP)
+            .body(Body::empty())
+            .map_err(ClientError::http_error)?;
+        let resp = self
+            .0
+            .request(request)
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        let encoding = resp
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        response::decode_get(status, async move {
+            let body = hyper::body::to_bytes(resp.into_body())
+                .await
+                .map_err(ClientError::http_error)?;
+            gzip::decode(encoding.as_deref(), body)
+        })
+        .await
+    }
+
+    #[cfg(feature = "gzip")]
+    async fn post_gzip(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        let request = Request::post(url)
+            .header(CONTENT_ENCODING, gzip::GZIP)
+            .body(Body::from(gzip::compress(&body)))
+            .map_err(ClientError::http_error)?;
+        let resp = self
+            .0
+            .request(request)
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        response::decode_post(status, async move {
+            let body = hyper::body::to_bytes(resp.into_body())
+                .await
+                .map_err(ClientError::http_error)?;
+            Ok(String::from_utf8_lossy(&body).into_owned())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_certificates() {
+        assert!(HyperClient::builder()
+            .add_root_certificate(b"not a certificate")
+            .is_err());
+        assert!(HyperClient::builder().identity(b"").is_err());
+
+        // a certificate without a private key is not an identity
+        let pem = b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n";
+        assert!(HyperClient::builder().identity(pem).is_err());
+    }
+}
@@ -0,0 +1,1253 @@
+#[cfg(all(
+    feature = "gzip",
+    any(feature = "reqwest-client", feature = "hyper-client")
+))]
+mod gzip;
+#[cfg(feature = "hyper-client")]
+mod hyper_client;
+#[cfg(feature = "websocket")]
+mod push;
+mod reloadable;
+#[cfg(feature = "reqwest-client")]
+mod reqwest_client;
+#[cfg(any(feature = "reqwest-client", feature = "hyper-client"))]
+mod response;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::time::sleep;
+use tracing::warn;
+use url::Url;
+
+#[cfg(feature = "hyper-client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hyper-client")))]
+pub use self::hyper_client::{
+    hyper_client_builder,
+    HyperClient,
+    HyperClientBuilder,
+    HyperClientError,
+};
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+pub use self::push::{PhaseEvents, PushNotifications};
+pub use self::reloadable::ReloadableClient;
+#[cfg(feature = "reqwest-client")]
+#[cfg_attr(docsrs, doc(cfg(feature = "reqwest-client")))]
+pub use self::reqwest_client::reqwest_client_builder;
+use crate::{Backoff, BackoffConfig, XaynetClient};
+use xaynet_core::{
+    common::{PhaseStats, PublicStats, RoundMetadata, RoundParameters},
+    crypto::{ByteObject, PublicSigningKey},
+    mask::Model,
+    message::Tag,
+    SumDict,
+    UpdateSeedDict,
+};
+
+/// Error returned upon failing to build a new [`Client`]
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("failed to deserialize data: {0}")]
+    Deserialize(String),
+
+    #[error("HTTP request failed: {0}")]
+    Http(String),
+
+    #[error("{0}")]
+    Other(String),
+
+    #[error("Reading from file failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Unexpected response: {0}")]
+    UnexpectedResponse(u16),
+
+    /// The coordinator rejected the message and explained why. This is only returned by
+    /// coordinators with `api.debug_rejections` enabled.
+    #[error("Message rejected ({0}): {1}")]
+    Rejected(u16, String),
+
+    /// The coordinator rejected the message because the participant exceeded its update
+    /// participation quota.
+    #[error("Update participation quota exceeded")]
+    QuotaExceeded,
+
+    #[error("Unexpected certificate extension")]
+    UnexpectedCertificate,
+
+    #[error("No certificate found")]
+    NoCertificate,
+
+    /// None of the coordinator URLs could be reached. The errors are in the order in which
+    /// the URLs were tried.
+    #[error("All coordinator endpoints failed: {}", display_errors(.0))]
+    AllEndpointsFailed(Vec<ClientError>),
+}
+
+fn display_errors(errors: &[ClientError]) -> String {
+    errors
+        .iter()
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+impl ClientError {
+    #[cfg_attr(
+        not(any(feature = "reqwest-client", feature = "hyper-client")),
+        allow(dead_code)
+    )]
+    fn http_error<E: std::error::Error>(e: E) -> Self {
+        Self::Http(format!("{}", e))
+    }
+
+    /// Whether the request may succeed if it is retried, i.e. whether it failed because of
+    /// the connection or because of a server error.
+    pub(crate) fn is_transient(&self) -> bool {
+        match self {
+            Self::Http(_) => true,
+            Self::UnexpectedResponse(status) | Self::Rejected(status, _) => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Whether the coordinator could not be reached at all, in which case the request may be
+    /// sent to another coordinator URL.
+    fn is_connection_error(&self) -> bool {
+        matches!(self, Self::Http(_))
+    }
+
+    /// Aggregate the errors of the coordinator URLs that were tried. A single error is
+    /// returned as is.
+    fn from_endpoint_errors(mut errors: Vec<ClientError>) -> Self {
+        if errors.len() == 1 {
+            // UNWRAP_SAFE: there is one error
+            errors.pop().unwrap()
+        } else {
+            Self::AllEndpointsFailed(errors)
+        }
+    }
+}
+
+impl From<bincode::Error> for ClientError {
+    fn from(e: bincode::Error) -> Self {
+        Self::Deserialize(format!("{}", e))
+    }
+}
+
+impl From<std::num::ParseIntError> for ClientError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        Self::Deserialize(format!("{}", e))
+    }
+}
+
+/// A basic HTTP interface that [`Client`] HTTP backends must implement.
+#[async_trait]
+pub trait XaynetHttpClient {
+    /// Error type for all the trait's methods
+    type Error: std::error::Error;
+    /// Reponse type for `GET` requests
+    type GetResponse: AsRef<[u8]>;
+
+    /// Perform an HTTP `GET` on the given URL.
+    ///
+    /// If the response is `NO_CONTENT`, the implementor must return `Ok(None)`. Otherwise, the
+    /// response body must be returned
+    async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError>;
+
+    /// Perform an HTTP `POST` on the given URL, with the given body.
+    async fn post(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError>;
+
+    /// Perform an HTTP `GET` on the given URL and accept a gzip compressed response.
+    ///
+    /// The response body must be returned decompressed. The default implementation
+    /// doesn't accept compressed responses and falls back to [`get()`].
+    ///
+    /// [`get()`]: XaynetHttpClient::get
+    async fn get_gzip(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        self.get(url).await
+    }
+
+    /// Perform an HTTP `POST` on the given URL, with the given body compressed with gzip.
+    ///
+    /// The body is passed uncompressed. The default implementation doesn't compress it and
+    /// falls back to [`post()`].
+    ///
+    /// [`post()`]: XaynetHttpClient::post
+    async fn post_gzip(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        self.post(url, body).await
+    }
+}
+
+/// Configuration of the retries of the requests to the coordinator.
+///
+/// Requests that fail because of the connection or because of a server error (`5xx`) are
+/// retried at most [`max_retries`] times. The first retry waits [`base_delay`], and each
+/// following delay is twice the previous one, up to [`max_delay`]. Requests that the
+/// coordinator rejects (`4xx`) are never retried. The default configuration doesn't retry.
+///
+/// [`max_retries`]: RetryConfig::max_retries
+/// [`base_delay`]: RetryConfig::base_delay
+/// [`max_delay`]: RetryConfig::max_delay
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryConfig {
+    /// The maximum number of retries of a request.
+    pub max_retries: u32,
+    /// The delay before the first retry.
+    pub base_delay: Duration,
+    /// The maximum delay between two retries.
+    pub max_delay: Duration,
+    /// Whether each delay is shortened by a random delay of at most half of it, so that the
+    /// participants don't all retry at the same time.
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: false,
+        }
+    }
+}
+
+impl RetryConfig {
+    fn backoff(&self) -> Backoff {
+        Backoff::new(self.backoff_config())
+    }
+
+    fn backoff_config(&self) -> BackoffConfig {
+        BackoffConfig {
+            initial_interval: self.base_delay,
+            max_interval: self.max_delay,
+            multiplier: 2.0,
+            jitter: self.jitter,
+        }
+    }
+}
+
+/// When a [`Client`] with several coordinator URLs may send a request to another URL.
+///
+/// A URL is abandoned when the coordinator can't be reached at all, not when it answers
+/// with an error status. The client then keeps sending its requests to the next URL that
+/// answered. The messages of a round are signed for the round parameters of a single
+/// coordinator, hence the client never switches to a coordinator that serves other round
+/// parameters in the middle of a round. When the round parameters are fetched, any URL may
+/// be chosen, and the state machine starts a new round if the parameters changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FailoverPolicy {
+    /// Only switch to another URL when fetching the round parameters. This is the default.
+    #[default]
+    BetweenRounds,
+    /// Also switch to another URL in the middle of a round, if it serves the same round
+    /// parameters, e.g. because both URLs lead to the same coordinator.
+    WithinRound,
+}
+
+#[derive(Debug, Clone)]
+/// A client that communicates with the coordinator's API via HTTP(S).
+pub struct Client<C> {
+    /// HTTP(S) client
+    client: C,
+    /// Coordinator URLs
+    base_urls: Vec<Url>,
+    /// Index of the coordinator URL the requests are sent to
+    current: usize,
+    /// When the requests may be sent to another coordinator URL
+    failover: FailoverPolicy,
+    /// Round parameters last fetched from the current coordinator URL
+    round_params: Option<RoundParameters>,
+    /// Retries of the failed requests
+    retry: RetryConfig,
+    /// Minimum length of the request bodies that are compressed with gzip, or `None` if
+    /// gzip is disabled
+    gzip_threshold: Option<usize>,
+}
+
+/// Error returned when trying to client a [`Client`] with an invalid
+/// address for the Xaynet coordinator.
+#[derive(Debug, Error)]
+#[error("Invalid base URL: {}", .0)]
+pub struct InvalidBaseUrl(String);
+
+impl<C> Client<C>
+where
+    C: XaynetHttpClient,
+{
+    /// Create a new client.
+    ///
+    /// # Args
+    ///
+    /// - `client` is the HTTP client that will be used to perform the HTTP requests. Any HTTP
+    ///   client can be used, as long as it implements the [`XaynetHttpClient`] trait.
+    /// - `base_url` is the URL to the Xaynet coordinator
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `base_url` is not a valid URL
+    pub fn new(http_client: C, base_url: &str) -> Result<Self, InvalidBaseUrl> {
+        Self::with_failover(http_client, &[base_url], FailoverPolicy::default())
+    }
+
+    /// Create a new client for a coordinator that can be reached via several URLs.
+    ///
+    /// # Args
+    ///
+    /// - `client` is the HTTP client that will be used to perform the HTTP requests.
+    /// - `base_urls` are the URLs to the Xaynet coordinator, in the order in which they are
+    ///   tried. The requests are sent to the first URL until it can't be reached.
+    /// - `policy` tells when the requests may be sent to another URL (see
+    ///   [`FailoverPolicy`]).
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `base_urls` is empty or contains an invalid URL
+    pub fn with_failover(
+        http_client: C,
+        base_urls: &[&str],
+        policy: FailoverPolicy,
+    ) -> Result<Self, InvalidBaseUrl> {
+        if base_urls.is_empty() {
+            return Err(InvalidBaseUrl(String::from("no base URL")));
+        }
+        let base_urls = base_urls
+            .iter()
+            .map(|base_url| {
+                let base_url =
+                    Url::parse(base_url).map_err(|e| InvalidBaseUrl(format!("{}", e)))?;
+                if base_url.cannot_be_a_base() {
+                    return Err(InvalidBaseUrl(String::from("cannot be a base URL")));
+                }
+                Ok(base_url)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            client: http_client,
+            base_urls,
+            current: 0,
+            failover: policy,
+            round_params: None,
+            retry: RetryConfig::default(),
+            gzip_threshold: None,
+        })
+    }
+
+    /// Retry the requests that fail with a transient error according to `retry`.
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Compress the messages of at least `threshold` bytes with gzip and accept gzip
+    /// compressed responses.
+    ///
+    /// The coordinator must have gzip enabled (`api.gzip`), otherwise it rejects the
+    /// compressed messages.
+    #[cfg(feature = "gzip")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+    pub fn with_gzip(mut self, threshold: usize) -> Self {
+        self.gzip_threshold = Some(threshold);
+        self
+    }
+
+    /// Return the coordinator URL the requests are currently sent to.
+    pub fn base_url(&self) -> &Url {
+        &self.base_urls[self.current]
+    }
+
+    /// Subscribe to the phases that the coordinator at the current URL pushes, instead of
+    /// polling its round metadata. The subscription reconnects with the retry delays of
+    /// this client.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the coordinator URL is not an HTTP URL
+    #[cfg(feature = "websocket")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+    pub fn subscribe_to_events(&self) -> Result<PhaseEvents, InvalidBaseUrl> {
+        PhaseEvents::new(self.base_url().as_str(), self.retry.backoff_config())
+    }
+
+    /// Append the given segments and query to the base URL of the coordinator URL at
+    /// `index`
+    fn url(&self, index: usize, request: &Request<'_>) -> Url {
+        let mut url = self.base_urls[index].clone();
+        url.path_segments_mut().unwrap().extend(request.segments);
+        if let Some((key, ref value)) = request.query {
+            url.query_pairs_mut().append_pair(key, value);
+        }
+        url
+    }
+}
+
+impl<C> Client<C>
+where
+    C: XaynetHttpClient + Send,
+{
+    /// Retrieve the statistics about the training which are safe to show to the end users
+    /// of an app, like the approximate number of participants of the latest global model.
+    ///
+    /// The coordinator rate limits these requests and allows to cache the statistics for
+    /// several minutes, so they don't need to be fetched more often.
+    pub async fn get_public_stats(&mut self) -> Result<PublicStats, ClientError> {
+        let data = self
+            .request(Request::get(&["stats", "public"]), false)
+            .await?
+            .ok_or_else(|| {
+                ClientError::Other("failed to fetch public stats: empty response".to_string())
+            })?;
+        serde_json::from_slice(data.as_ref()).map_err(|e| ClientError::Deserialize(e.to_string()))
+    }
+
+    /// Send the `request` to the current coordinator URL, or to the next ones that the
+    /// failover policy allows if it can't be reached. `new_round` tells whether the
+    /// request fetches the round parameters, in which case any URL is allowed.
+    async fn request(
+        &mut self,
+        mut request: Request<'_>,
+        new_round: bool,
+    ) -> Result<Option<C::GetResponse>, ClientError> {
+        let count = self.base_urls.len();
+        let mut body = request.body.take();
+        let mut errors = Vec::new();
+        for offset in 0..count {
+            let index = (self.current + offset) % count;
+            if offset > 0 {
+                match self.may_switch_to(index, new_round).await {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(err) => {
+                        errors.push(err);
+                        continue;
+                    }
+                }
+            }
+            let url = self.url(index, &request);
+            let result = match body {
+                // the body is only copied if the request may be sent to another URL
+                Some(ref mut data) if offset + 1 < count => {
+                    self.post(&url, data.clone()).await.map(|_| None)
+                }
+                Some(ref mut data) => self.post(&url, std::mem::take(data)).await.map(|_| None),
+                None => self.get(&url).await,
+            };
+            match result {
+                Err(err) if err.is_connection_error() => {
+                    warn!(
+                        "coordinator at {} can't be reached: {}",
+                        url.origin().ascii_serialization(),
+                        err
+                    );
+                    errors.push(err);
+                }
+                result => {
+                    self.current = index;
+                    return result;
+                }
+            }
+        }
+        Err(ClientError::from_endpoint_errors(errors))
+    }
+
+    /// Check whether the requests may be sent to the coordinator URL at `index` instead of
+    /// the current one.
+    async fn may_switch_to(&mut self, index: usize, new_round: bool) -> Result<bool, ClientError> {
+        if new_round || self.round_params.is_none() {
+            return Ok(true);
+        }
+        if self.failover == FailoverPolicy::BetweenRounds {
+            return Ok(false);
+        }
+        let url = self.url(index, &Request::get(&["params"]));
+        let response = self.get(&url).await?;
+        let params =
+            response.and_then(|data| bincode::deserialize::<RoundParameters>(data.as_ref()).ok());
+        Ok(params.is_some() && params == self.round_params)
+    }
+
+    async fn get_and_deserialize<T>(
+        &mut self,
+        request: Request<'_>,
+        new_round: bool,
+    ) -> Result<Option<T>, ClientError>
+    where
+        T: for<'a> serde::Deserialize<'a>,
+    {
+        Ok(match self.request(request, new_round).await? {
+            Some(data) => Some(bincode::deserialize::<T>(data.as_ref())?),
+            None => None,
+        })
+    }
+
+    async fn get(&mut self, url: &Url) -> Result<Option<C::GetResponse>, ClientError> {
+        let mut backoff = self.retry.backoff();
+        let mut retries = 0;
+        loop {
+            let err = match self.get_once(url).await {
+                Err(err) if retries < self.retry.max_retries && err.is_transient() => err,
+                response => return response,
+            };
+            retries += 1;
+            wait_for_retry(url, err, &mut backoff).await;
+        }
+    }
+
+    /// Perform a single `GET`, accepting a gzip compressed response if gzip is enabled.
+    async fn get_once(&mut self, url: &Url) -> Result<Option<C::GetResponse>, ClientError> {
+        if self.gzip_threshold.is_some() {
+            self.client.get_gzip(url.as_str()).await
+        } else {
+            self.client.get(url.as_str()).await
+        }
+    }
+
+    async fn post(&mut self, url: &Url, mut data: Vec<u8>) -> Result<(), ClientError> {
+        let mut backoff = self.retry.backoff();
+        let mut retries = 0;
+        loop {
+            // the body is only copied if the request may be retried
+            let body = if retries < self.retry.max_retries {
+                data.clone()
+            } else {
+                std::mem::take(&mut data)
+            };
+            let result = match self.gzip_threshold {
+                Some(threshold) if body.len() >= threshold => {
+                    self.client.post_gzip(url.as_str(), body).await
+                }
+                _ => self.client.post(url.as_str(), body).await,
+            };
+            let err = match result {
+                Err(err) if retries < self.retry.max_retries && err.is_transient() => err,
+                result => return result,
+            };
+            retries += 1;
+            wait_for_retry(url, err, &mut backoff).await;
+        }
+    }
+}
+
+/// A request to the coordinator, relative to its base URL.
+struct Request<'a> {
+    /// Path segments appended to the base URL
+    segments: &'a [&'a str],
+    /// Query pair appended to the URL, if any
+    query: Option<(&'a str, String)>,
+    /// Body of a `POST` request, or `None` for a `GET` request
+    body: Option<Vec<u8>>,
+}
+
+impl<'a> Request<'a> {
+    fn get(segments: &'a [&'a str]) -> Self {
+        Self {
+            segments,
+            query: None,
+            body: None,
+        }
+    }
+
+    fn query(mut self, key: &'a str, value: String) -> Self {
+        self.query = Some((key, value));
+        self
+    }
+
+    fn post(segments: &'a [&'a str], body: Vec<u8>) -> Self {
+        Self {
+            segments,
+            query: None,
+            body: Some(body),
+        }
+    }
+}
+
+/// Wait before retrying the request to `url` which failed with `err`.
+async fn wait_for_retry(url: &Url, err: ClientError, backoff: &mut Backoff) {
+    let delay = backoff.next_interval();
+    warn!(
+        "request to {} failed ({}), retrying in {:?}",
+        url.path(),
+        err,
+        delay
+    );
+    sleep(delay).await;
+}
+
+#[async_trait]
+impl<C> XaynetClient for Client<C>
+where
+    C: XaynetHttpClient + Send,
+{
+    type Error = ClientError;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        let round_params: Option<RoundParameters> = self
+            .get_and_deserialize(Request::get(&["params"]), true)
+            .await?;
+        let round_params = round_params.ok_or_else(|| {
+            ClientError::Other("failed to fetch round parameters: empty response".to_string())
+        })?;
+        self.round_params = Some(round_params.clone());
+        Ok(round_params)
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        let metadata: Option<RoundMetadata> = self
+            .get_and_deserialize(Request::get(&["round_metadata"]), false)
+            .await?;
+        metadata.ok_or_else(|| {
+            ClientError::Other("failed to fetch round metadata: empty response".to_string())
+        })
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        let stats: Option<PhaseStats> = self
+            .get_and_deserialize(Request::get(&["phase_stats"]), false)
+            .await?;
+        stats.ok_or_else(|| {
+            ClientError::Other("failed to fetch phase stats: empty response".to_string())
+        })
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        self.get_and_deserialize(Request::get(&["sums"]), false)
+            .await
+    }
+
+    async fn get_seeds(
+        &mut self,
+        pk: PublicSigningKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        let request = Request::get(&["seeds"]).query("pk", base64::encode(pk.as_slice()));
+        self.get_and_deserialize(request, false).await
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        self.get_and_deserialize(Request::get(&["model"]), false)
+            .await
+    }
+
+    async fn get_model_by_id(&mut self, id: &str) -> Result<Option<Model>, Self::Error> {
+        match self
+            .get_and_deserialize(Request::get(&["models", id]), false)
+            .await
+        {
+            Err(ClientError::UnexpectedResponse(404)) => Ok(None),
+            result => result,
+        }
+    }
+
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Self::Error> {
+        let request = Request::post(&["message"], msg).query("tag", u8::from(tag).to_string());
+        self.request(request, false).await.map(|_| ())
+    }
+}
+
+#[cfg(any(feature = "reqwest-client", feature = "hyper-client"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "reqwest-client", feature = "hyper-client")))
+)]
+/// The default time an idle connection to the coordinator is kept in the pool.
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::{HashMap, VecDeque};
+
+    use tokio::time::Instant;
+    use xaynet_core::common::RoundSeed;
+
+    use crate::state_machine::tests::utils::{round_params, SelectFor};
+
+    #[derive(Default)]
+    struct RecordingClient {
+        posted: Vec<String>,
+    }
+
+    #[async_trait]
+    impl XaynetHttpClient for RecordingClient {
+        type Error = ClientError;
+        type GetResponse = Vec<u8>;
+
+        async fn get(&mut self, _url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+            Ok(None)
+        }
+
+        async fn post(&mut self, url: &str, _body: Vec<u8>) -> Result<(), ClientError> {
+            self.posted.push(url.to_string());
+            Ok(())
+        }
+    }
+
+    /// A client for a coordinator that knows no global model by id.
+    struct NotFoundClient {
+        requested: Vec<String>,
+    }
+
+    #[async_trait]
+    impl XaynetHttpClient for NotFoundClient {
+        type Error = ClientError;
+        type GetResponse = Vec<u8>;
+
+        async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+            self.requested.push(url.to_string());
+            Err(ClientError::UnexpectedResponse(404))
+        }
+
+        async fn post(&mut self, _url: &str, _body: Vec<u8>) -> Result<(), ClientError> {
+            Ok(())
+        }
+    }
+
+    /// A client for a coordinator which fails with the given errors before it succeeds.
+    struct FlakyClient {
+        failures: VecDeque<ClientError>,
+        requests: usize,
+    }
+
+    impl FlakyClient {
+        fn new(failures: Vec<ClientError>) -> Self {
+            Self {
+                failures: failures.into(),
+                requests: 0,
+            }
+        }
+
+        fn respond(&mut self) -> Result<(), ClientError> {
+            self.requests += 1;
+            match self.failures.pop_front() {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl XaynetHttpClient for FlakyClient {
+        type Error = ClientError;
+        type GetResponse = Vec<u8>;
+
+        async fn get(&mut self, _url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+            self.respond().map(|_| None)
+        }
+
+        async fn post(&mut self, _url: &str, _body: Vec<u8>) -> Result<(), ClientError> {
+            self.respond()
+        }
+    }
+
+    fn flaky_client(failures: Vec<ClientError>) -> Client<FlakyClient> {
+        Client::new(FlakyClient::new(failures), "http://localhost:8081")
+            .unwrap()
+            .with_retry(RetryConfig {
+                max_retries: 3,
+                base_delay: Duration::from_millis(100),
+                max_delay: Duration::from_millis(300),
+                jitter: false,
+            })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_transient_errors() {
+        let mut client = flaky_client(vec![
+            ClientError::Http("connection reset".to_string()),
+            ClientError::UnexpectedResponse(503),
+            ClientError::Rejected(500, "internal error".to_string()),
+        ]);
+        let start = Instant::now();
+        assert!(client.get_model().await.unwrap().is_none());
+        assert_eq!(client.client.requests, 4);
+        // the delays grow exponentially up to the maximum delay
+        assert_eq!(start.elapsed(), Duration::from_millis(100 + 200 + 300));
+
+        // the delays are reset for every request
+        client.client.failures = vec![ClientError::UnexpectedResponse(502)].into();
+        let start = Instant::now();
+        client.send_message(Tag::Sum, vec![]).await.unwrap();
+        assert_eq!(client.client.requests, 6);
+        assert_eq!(start.elapsed(), Duration::from_millis(100));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_exhausted() {
+        let mut client = flaky_client(
+            (0..4)
+                .map(|_| ClientError::UnexpectedResponse(503))
+                .collect(),
+        );
+        assert!(matches!(
+            client.send_message(Tag::Update, vec![]).await,
+            Err(ClientError::UnexpectedResponse(503))
+        ));
+        assert_eq!(client.client.requests, 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_no_retry_client_errors() {
+        let mut client = flaky_client(vec![ClientError::Rejected(400, "invalid".to_string())]);
+        assert!(client.send_message(Tag::Sum, vec![]).await.is_err());
+        assert_eq!(client.client.requests, 1);
+
+        let mut client = flaky_client(vec![ClientError::QuotaExceeded]);
+        assert!(matches!(
+            client.send_message(Tag::Update, vec![]).await,
+            Err(ClientError::QuotaExceeded)
+        ));
+        assert_eq!(client.client.requests, 1);
+
+        // retries are disabled by default
+        let mut client = Client::new(
+            FlakyClient::new(vec![ClientError::UnexpectedResponse(503)]),
+            "http://localhost:8081",
+        )
+        .unwrap();
+        assert!(client.get_model().await.is_err());
+        assert_eq!(client.client.requests, 1);
+    }
+
+    /// Check that the `http_client` retries the requests to an unavailable coordinator.
+    #[cfg(any(feature = "reqwest-client", feature = "hyper-client"))]
+    async fn check_http_client_recovers<C: XaynetHttpClient + Send>(http_client: C) {
+        use std::{
+            convert::Infallible,
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+        };
+
+        use hyper::{
+            service::{make_service_fn, service_fn},
+            Body,
+            Response,
+            Server,
+            StatusCode,
+        };
+
+        // a coordinator which is unavailable for the first two requests
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let make_service = make_service_fn(move |_| {
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_| {
+                    let status = if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                        StatusCode::SERVICE_UNAVAILABLE
+                    } else {
+                        StatusCode::OK
+                    };
+                    async move {
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let mut client = Client::new(http_client, &url)
+            .unwrap()
+            .with_retry(RetryConfig {
+                max_retries: 3,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_millis(100),
+                jitter: true,
+            });
+        client.send_message(Tag::Sum, vec![1, 2, 3]).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "reqwest-client")]
+    #[tokio::test]
+    async fn test_reqwest_client_recovers() {
+        let http_client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+        check_http_client_recovers(http_client).await;
+    }
+
+    #[cfg(feature = "hyper-client")]
+    #[tokio::test]
+    async fn test_hyper_client_recovers() {
+        let http_client = hyper_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+        check_http_client_recovers(http_client).await;
+    }
+
+    /// Check that the `http_client` compresses the messages and decompresses the responses
+    /// with gzip.
+    #[cfg(all(
+        feature = "gzip",
+        any(feature = "reqwest-client", feature = "hyper-client")
+    ))]
+    async fn check_http_client_gzip<C: XaynetHttpClient + Send>(http_client: C) {
+        use std::{convert::Infallible, io::Read};
+
+        use flate2::read::GzDecoder;
+        use hyper::{
+            header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+            service::{make_service_fn, service_fn},
+            Body,
+            Method,
+            Response,
+            Server,
+            StatusCode,
+        };
+
+        // a coordinator which only accepts and serves gzip compressed bodies
+        let params = round_params(SelectFor::None);
+        let make_service = make_service_fn(move |_| {
+            let params = params.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: hyper::Request<Body>| {
+                    let params = params.clone();
+                    async move {
+                        let gzip = |name| {
+                            req.headers().get(name).map(|value| value.as_bytes()) == Some(b"gzip")
+                        };
+                        let response = if req.method() == Method::GET && gzip(ACCEPT_ENCODING) {
+                            let body = gzip::compress(&bincode::serialize(&params).unwrap());
+                            Response::builder()
+                                .header(CONTENT_ENCODING, "gzip")
+                                .body(Body::from(body))
+                        } else if req.method() == Method::POST && gzip(CONTENT_ENCODING) {
+                            let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                            let mut message = Vec::new();
+                            GzDecoder::new(&body[..]).read_to_end(&mut message).unwrap();
+                            let status = if message == vec![1; 100] {
+                                StatusCode::OK
+                            } else {
+                                StatusCode::BAD_REQUEST
+                            };
+                            Response::builder().status(status).body(Body::empty())
+                        } else {
+                            Response::builder()
+                                .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                                .body(Body::empty())
+                        };
+                        Ok::<_, Infallible>(response.unwrap())
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let url = format!("http://{}", server.local_addr());
+        tokio::spawn(server);
+
+        let mut client = Client::new(http_client, &url).unwrap().with_gzip(10);
+        assert_eq!(
+            client.get_round_params().await.unwrap(),
+            round_params(SelectFor::None)
+        );
+        client.send_message(Tag::Sum, vec![1; 100]).await.unwrap();
+        // messages below the threshold are not compressed
+        assert!(matches!(
+            client.send_message(Tag::Sum, vec![1; 5]).await,
+            Err(ClientError::UnexpectedResponse(415))
+        ));
+    }
+
+    #[cfg(all(feature = "gzip", feature = "reqwest-client"))]
+    #[tokio::test]
+    async fn test_reqwest_client_gzip() {
+        let http_client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+        check_http_client_gzip(http_client).await;
+    }
+
+    #[cfg(all(feature = "gzip", feature = "hyper-client"))]
+    #[tokio::test]
+    async fn test_hyper_client_gzip() {
+        let http_client = hyper_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+        check_http_client_gzip(http_client).await;
+    }
+
+    /// A client for coordinators at several hosts, which can't be reached once they are
+    /// down.
+    struct EndpointsClient {
+        /// The round parameters served by each host, or `None` if it is down
+        coordinators: HashMap<&'static str, Option<RoundParameters>>,
+        requested: Vec<String>,
+    }
+
+    impl EndpointsClient {
+        fn new(coordinators: Vec<(&'static str, RoundParameters)>) -> Self {
+            Self {
+                coordinators: coordinators
+                    .into_iter()
+                    .map(|(host, params)| (host, Some(params)))
+                    .collect(),
+                requested: Vec::new(),
+            }
+        }
+
+        fn respond(&mut self, url: &str) -> Result<Option<Vec<u8>>, ClientError> {
+            self.requested.push(url.to_string());
+            let url = Url::parse(url).unwrap();
+            match self.coordinators[url.host_str().unwrap()] {
+                None => Err(ClientError::Http("connection refused".to_string())),
+                Some(ref params) if url.path() == "/params" => {
+                    Ok(Some(bincode::serialize(params).unwrap()))
+                }
+                Some(_) => Ok(None),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl XaynetHttpClient for EndpointsClient {
+        type Error = ClientError;
+        type GetResponse = Vec<u8>;
+
+        async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+            self.respond(url)
+        }
+
+        async fn post(&mut self, url: &str, _body: Vec<u8>) -> Result<(), ClientError> {
+            self.respond(url).map(|_| ())
+        }
+    }
+
+    fn new_round_params() -> RoundParameters {
+        RoundParameters {
+            seed: RoundSeed::generate(),
+            ..round_params(SelectFor::None)
+        }
+    }
+
+    fn endpoints_client(
+        coordinators: Vec<(&'static str, RoundParameters)>,
+        policy: FailoverPolicy,
+    ) -> Client<EndpointsClient> {
+        Client::with_failover(
+            EndpointsClient::new(coordinators),
+            &["http://a:8081", "http://b:8081"],
+            policy,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failover_between_rounds() {
+        let (params_a, params_b) = (new_round_params(), new_round_params());
+        let mut client = endpoints_client(
+            vec![("a", params_a.clone()), ("b", params_b.clone())],
+            FailoverPolicy::BetweenRounds,
+        );
+        assert_eq!(client.get_round_params().await.unwrap(), params_a);
+
+        // the requests of the round stick to the first coordinator
+        client.client.coordinators.insert("a", None);
+        assert!(matches!(client.get_sums().await, Err(ClientError::Http(_))));
+        assert!(matches!(
+            client.send_message(Tag::Update, vec![]).await,
+            Err(ClientError::Http(_))
+        ));
+        assert_eq!(client.base_url().as_str(), "http://a:8081/");
+
+        // the next round is fetched from the second coordinator, which is then kept
+        assert_eq!(client.get_round_params().await.unwrap(), params_b);
+        client.client.coordinators.insert("a", Some(params_a));
+        assert!(client.get_sums().await.unwrap().is_none());
+        assert_eq!(client.base_url().as_str(), "http://b:8081/");
+        assert_eq!(
+            client.client.requested,
+            vec![
+                "http://a:8081/params",
+                "http://a:8081/sums",
+                "http://a:8081/message?tag=2",
+                "http://a:8081/params",
+                "http://b:8081/params",
+                "http://b:8081/sums",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failover_within_round() {
+        let params = new_round_params();
+        let mut client = endpoints_client(
+            vec![("a", params.clone()), ("b", params.clone())],
+            FailoverPolicy::WithinRound,
+        );
+        assert_eq!(client.get_round_params().await.unwrap(), params);
+
+        // the second coordinator serves the same round
+        client.client.coordinators.insert("a", None);
+        client.send_message(Tag::Update, vec![]).await.unwrap();
+        assert_eq!(client.base_url().as_str(), "http://b:8081/");
+        assert_eq!(
+            client.client.requested,
+            vec![
+                "http://a:8081/params",
+                "http://a:8081/message?tag=2",
+                "http://b:8081/params",
+                "http://b:8081/message?tag=2",
+            ]
+        );
+
+        // the first coordinator serves another round
+        client
+            .client
+            .coordinators
+            .insert("a", Some(new_round_params()));
+        client.client.coordinators.insert("b", None);
+        assert!(matches!(client.get_sums().await, Err(ClientError::Http(_))));
+        assert_eq!(client.base_url().as_str(), "http://b:8081/");
+    }
+
+    #[tokio::test]
+    async fn test_all_endpoints_failed() {
+        let mut client = endpoints_client(
+            vec![("a", new_round_params()), ("b", new_round_params())],
+            FailoverPolicy::BetweenRounds,
+        );
+        client.client.coordinators.insert("a", None);
+        client.client.coordinators.insert("b", None);
+        match client.get_round_params().await {
+            Err(ClientError::AllEndpointsFailed(errors)) => {
+                assert_eq!(errors.len(), 2);
+                assert!(errors.iter().all(|e| matches!(e, ClientError::Http(_))));
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        assert!(Client::with_failover(
+            RecordingClient::default(),
+            &[],
+            FailoverPolicy::BetweenRounds
+        )
+        .is_err());
+    }
+
+    /// Check that the `http_client` fails over to another coordinator when the first one
+    /// is shut down. The client must not keep idle connections.
+    #[cfg(any(feature = "reqwest-client", feature = "hyper-client"))]
+    async fn check_http_client_failover<C: XaynetHttpClient + Send>(http_client: C) {
+        use std::convert::Infallible;
+
+        use hyper::{
+            service::{make_service_fn, service_fn},
+            Body,
+            Response,
+            Server,
+            StatusCode,
+        };
+        use tokio::sync::oneshot;
+
+        /// Start a coordinator which serves the `params`, until it is shut down.
+        fn serve(params: &RoundParameters) -> (String, oneshot::Sender<()>) {
+            let params = bincode::serialize(params).unwrap();
+            let make_service = make_service_fn(move |_| {
+                let params = params.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req: hyper::Request<Body>| {
+                        let response = if req.uri().path() == "/params" {
+                            Response::new(Body::from(params.clone()))
+                        } else {
+                            Response::builder()
+                                .status(StatusCode::NO_CONTENT)
+                                .body(Body::empty())
+                                .unwrap()
+                        };
+                        async move { Ok::<_, Infallible>(response) }
+                    }))
+                }
+            });
+            let (shutdown, rx) = oneshot::channel();
+            let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+            let url = format!("http://{}", server.local_addr());
+            tokio::spawn(server.with_graceful_shutdown(async {
+                rx.await.ok();
+            }));
+            (url, shutdown)
+        }
+
+        let params = new_round_params();
+        let (url_a, shutdown_a) = serve(&params);
+        let (url_b, _shutdown_b) = serve(&params);
+        let mut client =
+            Client::with_failover(http_client, &[&url_a, &url_b], FailoverPolicy::WithinRound)
+                .unwrap();
+        assert_eq!(client.get_round_params().await.unwrap(), params);
+        assert!(client.get_sums().await.unwrap().is_none());
+        assert_eq!(client.base_url(), &Url::parse(&url_a).unwrap());
+
+        // the first coordinator is shut down in the middle of the round
+        shutdown_a.send(()).unwrap();
+        tokio::task::yield_now().await;
+        assert!(client.get_sums().await.unwrap().is_none());
+        client
+            .send_message(Tag::Update, vec![1, 2, 3])
+            .await
+            .unwrap();
+        assert_eq!(client.base_url(), &Url::parse(&url_b).unwrap());
+    }
+
+    #[cfg(feature = "reqwest-client")]
+    #[tokio::test]
+    async fn test_reqwest_client_failover() {
+        let http_client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        check_http_client_failover(http_client).await;
+    }
+
+    #[cfg(feature = "hyper-client")]
+    #[tokio::test]
+    async fn test_hyper_client_failover() {
+        let http_client = hyper_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+        check_http_client_failover(http_client).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_model_by_id_not_found() {
+        let http_client = NotFoundClient { requested: vec![] };
+        let mut client = Client::new(http_client, "http://localhost:8081").unwrap();
+        assert!(client.get_model_by_id("abc").await.unwrap().is_none());
+        assert_eq!(
+            client.client.requested,
+            vec!["http://localhost:8081/models/abc"]
+        );
+
+        // other errors are not hidden
+        assert!(matches!(
+            client.get_model().await,
+            Err(ClientError::UnexpectedResponse(404))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_tag() {
+        let mut client = Client::new(RecordingClient::default(), "http://localhost:8081").unwrap();
+        client.send_message(Tag::Sum, vec![]).await.unwrap();
+        client.send_message(Tag::Sum2, vec![]).await.unwrap();
+        assert_eq!(
+            client.client.posted,
+            vec![
+                "http://localhost:8081/message?tag=1",
+                "http://localhost:8081/message?tag=3",
+            ]
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Round events pushed by the coordinator over a WebSocket.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{stream, Stream, StreamExt};
+use tokio::{
+    net::TcpStream,
+    time::{sleep, timeout},
+};
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, warn};
+use url::Url;
+
+use super::InvalidBaseUrl;
+use crate::{Backoff, BackoffConfig};
+use xaynet_core::common::{PhaseEvent, RoundEvent, RoundEventFrame};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// The longest time [`PushNotifications::wait()`] waits for a pushed event, in case the
+/// socket silently stopped working.
+const MAX_PUSH_WAIT: Duration = Duration::from_secs(300);
+
+/// The round events that the coordinator pushes at `/events`, if it enables them. They tell
+/// an agent when to step its state machine again, instead of polling the coordinator at a
+/// fixed interval.
+///
+/// If the socket can't be opened or if it drops, [`wait()`] falls back to polling: it waits
+/// for the polling interval, and it tries to open the socket again on the next call. The
+/// sockets don't support TLS for now, hence coordinators served over HTTPS are polled.
+///
+/// ```no_run
+/// use xaynet_sdk::{client::PushNotifications, StateMachine};
+///
+/// async fn run_agent(mut state_machine: StateMachine, mut push: PushNotifications) {
+///     loop {
+///         let (pending, _steps) = state_machine.run_until_pending(usize::MAX).await;
+///         // wait for the coordinator to push an event, or poll if it can't
+///         push.wait().await;
+///         state_machine = pending;
+///     }
+/// }
+/// ```
+///
+/// [`wait()`]: PushNotifications::wait
+#[derive(Debug)]
+pub struct PushNotifications {
+    url: Url,
+    polling_interval: Duration,
+    socket: Option<Socket>,
+}
+
+impl PushNotifications {
+    /// Create the push notifications of the coordinator at `base_url`. The socket is opened
+    /// on the first call to [`PushNotifications::wait()`].
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `base_url` is not a valid HTTP URL
+    pub fn new(base_url: &str, polling_interval: Duration) -> Result<Self, InvalidBaseUrl> {
+        Ok(Self {
+            url: events_url(base_url)?,
+            polling_interval,
+            socket: None,
+        })
+    }
+
+    /// Check whether the socket is open, i.e. whether the events are pushed.
+    pub fn is_connected(&self) -> bool {
+        self.socket.is_some()
+    }
+
+    /// Wait until the state machine should be stepped again, and return the event that
+    /// the coordinator pushed, if any.
+    ///
+    /// - If the socket is open, this waits for the next event, but at most for five
+    ///   minutes. Events of an unknown format are not returned, but still end the wait.
+    /// - If the socket is not open, this opens it and returns immediately, so that the
+    ///   events that were missed in the meantime are caught up with.
+    /// - If the socket can't be opened or if it drops, this waits for the polling interval.
+    pub async fn wait(&mut self) -> Option<RoundEvent> {
+        let socket = match self.socket.as_mut() {
+            Some(socket) => socket,
+            None => {
+                match timeout(self.polling_interval, connect_async(self.url.as_str())).await {
+                    Ok(Ok((socket, _))) => {
+                        debug!("receiving round events from {}", self.url);
+                        self.socket = Some(socket);
+                    }
+                    Ok(Err(err)) => {
+                        debug!("failed to open {}, polling instead: {}", self.url, err);
+                        self.poll().await;
+                    }
+                    Err(_) => debug!("timed out opening {}, polling instead", self.url),
+                }
+                return None;
+            }
+        };
+        loop {
+            let message = match timeout(MAX_PUSH_WAIT, socket.next()).await {
+                Ok(message) => message,
+                Err(_) => return None,
+            };
+            match message {
+                Some(Ok(Message::Text(frame))) => {
+                    return serde_json::from_str::<RoundEventFrame>(&frame)
+                        .ok()
+                        .filter(|frame| frame.version == RoundEventFrame::VERSION)
+                        .map(|frame| frame.event);
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    warn!("round events socket closed, polling instead");
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => warn!("round events socket failed, polling instead: {}", err),
+            }
+            self.socket = None;
+            self.poll().await;
+            return None;
+        }
+    }
+
+    async fn poll(&self) {
+        tokio::time::sleep(self.polling_interval).await;
+    }
+}
+
+/// Get the URL of the round events of the coordinator at `base_url`.
+fn events_url(base_url: &str) -> Result<Url, InvalidBaseUrl> {
+    let mut url = Url::parse(base_url).map_err(|e| InvalidBaseUrl(format!("{}", e)))?;
+    let scheme = match url.scheme() {
+        "http" => "ws",
+        "https" => "wss",
+        _ => return Err(InvalidBaseUrl(String::from("not an HTTP URL"))),
+    };
+    // UNWRAP_SAFE: the HTTP and WebSocket schemes are interchangeable, and HTTP URLs can be a
+    // base
+    url.set_scheme(scheme).unwrap();
+    url.path_segments_mut()
+        .unwrap()
+        .pop_if_empty()
+        .push("events");
+    Ok(url)
+}
+
+/// The stream of the phases that the coordinator pushes at `/events`, if it enables the
+/// events. It is created by [`Client::subscribe_to_events()`].
+///
+/// The socket is opened when the stream is first polled. If it can't be opened or if it
+/// drops, it is opened again after a growing delay, hence the stream never ends. The phases
+/// that start while the socket is closed are missed, and so are the phases that start while
+/// the stream is not polled for long: the coordinator disconnects the participants that
+/// don't keep up with its events.
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use xaynet_sdk::client::PhaseEvents;
+///
+/// async fn log_phases(mut phases: PhaseEvents) {
+///     while let Some(event) = phases.next().await {
+///         println!("round {}: {} phase", event.round_id, event.phase_name);
+///     }
+/// }
+/// ```
+///
+/// [`Client::subscribe_to_events()`]: super::Client::subscribe_to_events
+pub struct PhaseEvents(Pin<Box<dyn Stream<Item = PhaseEvent> + Send>>);
+
+impl std::fmt::Debug for PhaseEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PhaseEvents").finish()
+    }
+}
+
+impl PhaseEvents {
+    /// Create the stream of the phases of the coordinator at `base_url`. The delays before
+    /// the socket is opened again follow the `reconnect` backoff, which is reset once an
+    /// event is received.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if `base_url` is not a valid HTTP URL
+    pub fn new(base_url: &str, reconnect: BackoffConfig) -> Result<Self, InvalidBaseUrl> {
+        let subscription = Subscription {
+            url: events_url(base_url)?,
+            reconnect: Backoff::new(reconnect),
+            socket: None,
+        };
+        Ok(Self(Box::pin(stream::unfold(
+            subscription,
+            |subscription| subscription.next_phase(),
+        ))))
+    }
+}
+
+impl Stream for PhaseEvents {
+    type Item = PhaseEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0.as_mut().poll_next(cx)
+    }
+}
+
+/// The state of a [`PhaseEvents`] stream.
+struct Subscription {
+    url: Url,
+    reconnect: Backoff,
+    socket: Option<Socket>,
+}
+
+impl Subscription {
+    /// Wait for the next phase, reopening the socket as often as needed. Events of other
+    /// kinds or of unknown formats are skipped.
+    async fn next_phase(mut self) -> Option<(PhaseEvent, Self)> {
+        loop {
+            let socket = match self.socket.as_mut() {
+                Some(socket) => socket,
+                None => {
+                    match connect_async(self.url.as_str()).await {
+                        Ok((socket, _)) => {
+                            debug!("receiving phase events from {}", self.url);
+                            self.socket = Some(socket);
+                        }
+                        Err(err) => {
+                            debug!("failed to open {}: {}", self.url, err);
+                            sleep(self.reconnect.next_interval()).await;
+                        }
+                    }
+                    continue;
+                }
+            };
+            match socket.next().await {
+                Some(Ok(Message::Text(frame))) => {
+                    if let Ok(RoundEventFrame {
+                        version: RoundEventFrame::VERSION,
+                        event: RoundEvent::Phase(event),
+                    }) = serde_json::from_str(&frame)
+                    {
+                        self.reconnect.reset();
+                        return Some((event, self));
+                    }
+                    continue;
+                }
+                Some(Ok(Message::Close(_))) | None => warn!("phase events socket closed"),
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => warn!("phase events socket failed: {}", err),
+            }
+            self.socket = None;
+            sleep(self.reconnect.next_interval()).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::SinkExt;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    use super::*;
+    use xaynet_core::common::PhaseName;
+
+    const POLLING_INTERVAL: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn test_url() {
+        let push = PushNotifications::new("http://localhost:8081", POLLING_INTERVAL).unwrap();
+        assert_eq!(push.url.as_str(), "ws://localhost:8081/events");
+        let push = PushNotifications::new("https://host/api/", POLLING_INTERVAL).unwrap();
+        assert_eq!(push.url.as_str(), "wss://host/api/events");
+        assert!(PushNotifications::new("ftp://host", POLLING_INTERVAL).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_push_and_fall_back_to_polling() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            let frame = RoundEventFrame::new(RoundEvent::NewRound { round_id: 1 });
+            let frame = serde_json::to_string(&frame).unwrap();
+            socket.send(Message::text(frame)).await.unwrap();
+            let frame = r#"{"version":2,"event":"new_round","round_id":2}"#;
+            socket.send(Message::text(frame)).await.unwrap();
+            socket.close(None).await.unwrap();
+        });
+
+        let url = format!("http://{}", address);
+        let mut push = PushNotifications::new(&url, POLLING_INTERVAL).unwrap();
+        // the state machine is stepped once the socket is opened
+        assert_eq!(push.wait().await, None);
+        assert!(push.is_connected());
+        assert_eq!(
+            push.wait().await,
+            Some(RoundEvent::NewRound { round_id: 1 })
+        );
+        // frames of unknown versions are skipped
+        assert_eq!(push.wait().await, None);
+        assert!(push.is_connected());
+        // the socket drops
+        assert_eq!(push.wait().await, None);
+        assert!(!push.is_connected());
+        server.await.unwrap();
+
+        // the coordinator can't be reached anymore
+        assert_eq!(push.wait().await, None);
+        assert!(!push.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_phase_events_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let phase = |round_id| PhaseEvent {
+            round_id,
+            phase_name: PhaseName::Sum,
+            timestamp: 42,
+        };
+        let server = tokio::spawn(async move {
+            for round_id in 1..=2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = accept_async(stream).await.unwrap();
+                let frame = RoundEventFrame::new(RoundEvent::NewRound { round_id });
+                let frame = serde_json::to_string(&frame).unwrap();
+                socket.send(Message::text(frame)).await.unwrap();
+                let frame = RoundEventFrame::new(RoundEvent::Phase(phase(round_id)));
+                let frame = serde_json::to_string(&frame).unwrap();
+                socket.send(Message::text(frame)).await.unwrap();
+                socket.close(None).await.unwrap();
+            }
+        });
+
+        let url = format!("http://{}", address);
+        let mut phases = PhaseEvents::new(&url, BackoffConfig::fixed(POLLING_INTERVAL)).unwrap();
+        // the other events are skipped
+        assert_eq!(phases.next().await, Some(phase(1)));
+        // the socket is opened again after it dropped
+        assert_eq!(phases.next().await, Some(phase(2)));
+        server.await.unwrap();
+    }
+}
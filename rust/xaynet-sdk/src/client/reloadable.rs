@@ -0,0 +1,111 @@
+//! An HTTP backend that can be replaced while the [`Client`] is in use.
+//!
+//! [`Client`]: super::Client
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use super::{ClientError, XaynetHttpClient};
+
+/// An HTTP backend that can be replaced by another one, for instance to rotate the TLS
+/// certificates used to talk to the coordinator.
+///
+/// The clones of a `ReloadableClient` share the same backend, so reloading one of them
+/// reloads the backend of the [`Client`]s and the state machines that own the other
+/// clones too, without having to rebuild them. The requests that are in flight complete
+/// with the previous backend, the next ones use the new backend.
+///
+/// [`Client`]: super::Client
+#[derive(Debug)]
+pub struct ReloadableClient<C>(Arc<Mutex<C>>);
+
+impl<C> Clone for ReloadableClient<C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<C> ReloadableClient<C> {
+    /// Create a new reloadable HTTP backend.
+    pub fn new(http_client: C) -> Self {
+        Self(Arc::new(Mutex::new(http_client)))
+    }
+
+    /// Replace the backend of this client and of all its clones.
+    pub fn reload(&self, http_client: C) {
+        // UNWRAP_SAFE: the lock is never held across a panic
+        *self.0.lock().unwrap() = http_client;
+    }
+}
+
+impl<C> ReloadableClient<C>
+where
+    C: Clone,
+{
+    /// Get the current backend. The lock is released before the request is sent.
+    fn current(&self) -> C {
+        // UNWRAP_SAFE: the lock is never held across a panic
+        self.0.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl<C> XaynetHttpClient for ReloadableClient<C>
+where
+    C: XaynetHttpClient + Clone + Send + 'static,
+    C::GetResponse: Send,
+{
+    type Error = C::Error;
+    type GetResponse = C::GetResponse;
+
+    async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        self.current().get(url).await
+    }
+
+    async fn post(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        self.current().post(url, body).await
+    }
+
+    async fn get_gzip(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        self.current().get_gzip(url).await
+    }
+
+    async fn post_gzip(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        self.current().post_gzip(url, body).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backend that answers every `GET` with its name.
+    #[derive(Clone)]
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl XaynetHttpClient for Named {
+        type Error = ClientError;
+        type GetResponse = Vec<u8>;
+
+        async fn get(&mut self, _url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+            Ok(Some(self.0.as_bytes().to_vec()))
+        }
+
+        async fn post(&mut self, _url: &str, _body: Vec<u8>) -> Result<(), ClientError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_affects_clones() {
+        let mut client = ReloadableClient::new(Named("old"));
+        let mut clone = client.clone();
+        assert_eq!(clone.get("").await.unwrap().unwrap(), b"old");
+
+        client.reload(Named("new"));
+        assert_eq!(client.get("").await.unwrap().unwrap(), b"new");
+        assert_eq!(clone.get("").await.unwrap().unwrap(), b"new");
+    }
+}
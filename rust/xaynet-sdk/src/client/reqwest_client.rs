@@ -0,0 +1,88 @@
+//! The [`XaynetHttpClient`] implementation for [`reqwest::Client`].
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+#[cfg(feature = "gzip")]
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+
+#[cfg(feature = "gzip")]
+use super::gzip;
+use super::{response, ClientError, XaynetHttpClient};
+
+/// Returns a [`reqwest::ClientBuilder`] for talking to the coordinator.
+///
+/// The connections to the coordinator are pooled, such that consecutive requests reuse the
+/// same connection as long as it hasn't been idle for longer than `pool_idle_timeout`. Over
+/// TLS, HTTP/2 is preferred if the coordinator supports it, which lets all requests share
+/// a single connection.
+pub fn reqwest_client_builder(pool_idle_timeout: Duration) -> reqwest::ClientBuilder {
+    reqwest::ClientBuilder::new().pool_idle_timeout(pool_idle_timeout)
+}
+
+#[cfg_attr(docsrs, doc(cfg(feature = "reqwest-client")))]
+#[async_trait]
+impl XaynetHttpClient for reqwest::Client {
+    type Error = reqwest::Error;
+    type GetResponse = bytes::Bytes;
+
+    async fn get(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        let resp = reqwest::Client::get(self, url)
+            .send()
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        response::decode_get(status, async move {
+            resp.bytes().await.map_err(ClientError::http_error)
+        })
+        .await
+    }
+
+    async fn post(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        let resp = reqwest::Client::post(self, url)
+            .body(body)
+            .send()
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        response::decode_post(status, async move {
+            resp.text().await.map_err(ClientError::http_error)
+        })
+        .await
+    }
+
+    #[cfg(feature = "gzip")]
+    async fn get_gzip(&mut self, url: &str) -> Result<Option<Self::GetResponse>, ClientError> {
+        let resp = reqwest::Client::get(self, url)
+            .header(ACCEPT_ENCODING, gzip::GZIP)
+            .send()
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        let encoding = resp
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        response::decode_get(status, async move {
+            let body = resp.bytes().await.map_err(ClientError::http_error)?;
+            gzip::decode(encoding.as_deref(), body)
+        })
+        .await
+    }
+
+    #[cfg(feature = "gzip")]
+    async fn post_gzip(&mut self, url: &str, body: Vec<u8>) -> Result<(), ClientError> {
+        let resp = reqwest::Client::post(self, url)
+            .header(CONTENT_ENCODING, gzip::GZIP)
+            .body(gzip::compress(&body))
+            .send()
+            .await
+            .map_err(ClientError::http_error)?;
+        let status = resp.status().as_u16();
+        response::decode_post(status, async move {
+            resp.text().await.map_err(ClientError::http_error)
+        })
+        .await
+    }
+}
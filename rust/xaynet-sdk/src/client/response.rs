@@ -0,0 +1,100 @@
+//! Decoding of the responses of the coordinator, shared by the HTTP backends.
+//!
+//! The backends only perform the requests. How the status and the body of a response are
+//! turned into the result of a [`XaynetHttpClient`] method is decided here, so that all
+//! the backends behave the same.
+//!
+//! [`XaynetHttpClient`]: super::XaynetHttpClient
+
+use std::future::Future;
+
+use super::ClientError;
+
+/// Decode the response to a `GET` request with the given `status`.
+///
+/// The `body` is only read if the response has some content.
+pub(super) async fn decode_get<B, F>(status: u16, body: F) -> Result<Option<B>, ClientError>
+where
+    F: Future<Output = Result<B, ClientError>>,
+{
+    match status {
+        200 => body.await.map(Some),
+        204 => Ok(None),
+        status => Err(ClientError::UnexpectedResponse(status)),
+    }
+}
+
+/// Decode the response to a `POST` request with the given `status`.
+///
+/// The `body` is only read if the coordinator rejected the message, in which case it may
+/// explain why.
+pub(super) async fn decode_post<F>(status: u16, body: F) -> Result<(), ClientError>
+where
+    F: Future<Output = Result<String, ClientError>>,
+{
+    if (200..300).contains(&status) {
+        return Ok(());
+    }
+    if status == 429 {
+        return Err(ClientError::QuotaExceeded);
+    }
+    match body.await {
+        Ok(reason) if !reason.is_empty() => Err(ClientError::Rejected(status, reason)),
+        _ => Err(ClientError::UnexpectedResponse(status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(body: &str) -> Result<String, ClientError> {
+        Ok(body.to_string())
+    }
+
+    async fn unread() -> Result<String, ClientError> {
+        panic!("the body must not be read")
+    }
+
+    #[tokio::test]
+    async fn test_decode_get() {
+        assert_eq!(
+            decode_get(200, body("data")).await.unwrap(),
+            Some("data".to_string())
+        );
+        assert_eq!(decode_get(204, unread()).await.unwrap(), None);
+        assert!(matches!(
+            decode_get(404, unread()).await,
+            Err(ClientError::UnexpectedResponse(404))
+        ));
+        assert!(matches!(
+            decode_get(200, async {
+                Err::<String, _>(ClientError::Http("reset".into()))
+            })
+            .await,
+            Err(ClientError::Http(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_decode_post() {
+        assert!(decode_post(200, unread()).await.is_ok());
+        assert!(decode_post(202, unread()).await.is_ok());
+        assert!(matches!(
+            decode_post(429, unread()).await,
+            Err(ClientError::QuotaExceeded)
+        ));
+        assert!(matches!(
+            decode_post(400, body("invalid signature")).await,
+            Err(ClientError::Rejected(400, reason)) if reason == "invalid signature"
+        ));
+        assert!(matches!(
+            decode_post(400, body("")).await,
+            Err(ClientError::UnexpectedResponse(400))
+        ));
+        assert!(matches!(
+            decode_post(500, async { Err(ClientError::Http("reset".into())) }).await,
+            Err(ClientError::UnexpectedResponse(500))
+        ));
+    }
+}
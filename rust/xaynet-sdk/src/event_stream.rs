@@ -0,0 +1,304 @@
+//! A [`Stream`] of notifications, as an alternative to implementing [`Notify`].
+//!
+//! See [`StateMachine::with_event_stream`].
+//!
+//! [`StateMachine::with_event_stream`]: crate::StateMachine::with_event_stream
+
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use futures::Stream;
+
+use crate::{ConsentRequest, Notify};
+
+/// A notification emitted by the [`StateMachine`].
+///
+/// Each variant corresponds to a method of the [`Notify`] trait.
+///
+/// [`StateMachine`]: crate::StateMachine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A new round of federated learning started.
+    NewRound,
+    /// The participant has been selected for the sum task.
+    Sum,
+    /// The participant has been selected for the update task.
+    Update,
+    /// The participant is not selected for any task and is waiting for another round to
+    /// start.
+    Idle,
+    /// The participant should populate the model store (see [`ModelStore`]).
+    ///
+    /// [`ModelStore`]: crate::ModelStore
+    LoadModel,
+    /// The global mask is aggregated and awaits its confirmation (see
+    /// [`PetSettings::confirm_sum2`]).
+    ///
+    /// [`PetSettings::confirm_sum2`]: crate::settings::PetSettings::confirm_sum2
+    Sum2MaskReady,
+    /// The coordinator rejected an update message because the participant exceeded its
+    /// update participation quota.
+    QuotaExceeded,
+    /// The participant has been selected for a task and awaits the consent of the user
+    /// (see [`PetSettings::require_consent`]).
+    ///
+    /// [`PetSettings::require_consent`]: crate::settings::PetSettings::require_consent
+    AwaitingConsent(ConsentRequest),
+    /// The participant abandoned its task because there is not enough time remaining to
+    /// complete it before the end of the phase (see [`PetSettings::deadline_margin`]).
+    ///
+    /// [`PetSettings::deadline_margin`]: crate::settings::PetSettings::deadline_margin
+    InsufficientTime,
+    /// The participant abandoned the sum task because its ephemeral keys don't match the
+    /// ephemeral public key it sent in the sum message.
+    EphemeralKeysMismatch,
+    /// A new round started but the global model didn't change since the previous round.
+    ModelUnchanged,
+    /// A new round started with the given identifier.
+    RoundIdChanged(u64),
+    /// A new round of the training plan with the given id started.
+    TrainingPlan(String),
+}
+
+/// What happens to an [`Event`] emitted while the buffer of the [`EventStream`] is full.
+///
+/// The notifications are emitted synchronously while the state machine makes a
+/// transition, so the state machine cannot wait for the buffer to be drained. Instead,
+/// one of the events is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// The oldest buffered event is dropped to make room for the new one.
+    DropOldest,
+    /// The new event is dropped.
+    DropNewest,
+}
+
+/// Configuration of an [`EventStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventStreamConfig {
+    /// The maximum number of events buffered until they are polled. A capacity of `0`
+    /// is treated as `1`.
+    pub capacity: usize,
+    /// What happens to the events emitted while the buffer is full.
+    pub overflow: Overflow,
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 16,
+            overflow: Overflow::DropOldest,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Buffer {
+    events: VecDeque<Event>,
+    config: EventStreamConfig,
+    dropped: u64,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl Buffer {
+    fn push(&mut self, event: Event) {
+        if self.events.len() >= self.config.capacity.max(1) {
+            self.dropped += 1;
+            match self.config.overflow {
+                Overflow::DropOldest => {
+                    self.events.pop_front();
+                }
+                Overflow::DropNewest => return,
+            }
+        }
+        self.events.push_back(event);
+        self.wake();
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Create a [`Notify`] implementation and the [`EventStream`] of the events it emits.
+pub(crate) fn event_stream(config: EventStreamConfig) -> (EventNotifier, EventStream) {
+    let buffer = Arc::new(Mutex::new(Buffer {
+        events: VecDeque::with_capacity(config.capacity.max(1)),
+        config,
+        dropped: 0,
+        closed: false,
+        waker: None,
+    }));
+    (EventNotifier(buffer.clone()), EventStream(buffer))
+}
+
+/// A [`Notify`] implementation that buffers the notifications for an [`EventStream`].
+///
+/// The stream terminates when the notifier is dropped.
+pub(crate) struct EventNotifier(Arc<Mutex<Buffer>>);
+
+impl EventNotifier {
+    fn push(&mut self, event: Event) {
+        // the lock is never held while panicking
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+impl Notify for EventNotifier {
+    fn new_round(&mut self) {
+        self.push(Event::NewRound)
+    }
+
+    fn sum(&mut self) {
+        self.push(Event::Sum)
+    }
+
+    fn update(&mut self) {
+        self.push(Event::Update)
+    }
+
+    fn idle(&mut self) {
+        self.push(Event::Idle)
+    }
+
+    fn load_model(&mut self) {
+        self.push(Event::LoadModel)
+    }
+
+    fn sum2_mask_ready(&mut self) {
+        self.push(Event::Sum2MaskReady)
+    }
+
+    fn quota_exceeded(&mut self) {
+        self.push(Event::QuotaExceeded)
+    }
+
+    fn awaiting_consent(&mut self, request: ConsentRequest) {
+        self.push(Event::AwaitingConsent(request))
+    }
+
+    fn insufficient_time(&mut self) {
+        self.push(Event::InsufficientTime)
+    }
+
+    fn ephemeral_keys_mismatch(&mut self) {
+        self.push(Event::EphemeralKeysMismatch)
+    }
+
+    fn model_unchanged(&mut self) {
+        self.push(Event::ModelUnchanged)
+    }
+
+    fn round_id_changed(&mut self, round_id: u64) {
+        self.push(Event::RoundIdChanged(round_id))
+    }
+
+    fn training_plan(&mut self, plan_id: String) {
+        self.push(Event::TrainingPlan(plan_id))
+    }
+}
+
+impl Drop for EventNotifier {
+    fn drop(&mut self) {
+        if let Ok(mut buffer) = self.0.lock() {
+            buffer.closed = true;
+            buffer.wake();
+        }
+    }
+}
+
+/// A [`Stream`] of the notifications emitted by a [`StateMachine`].
+///
+/// The events are buffered until they are polled, up to the capacity set in the
+/// [`EventStreamConfig`]. The stream terminates once the buffered events have been
+/// polled and the state machine has been dropped, which includes saving it.
+///
+/// [`StateMachine`]: crate::StateMachine
+#[derive(Debug)]
+pub struct EventStream(Arc<Mutex<Buffer>>);
+
+impl EventStream {
+    /// Get the number of events dropped so far because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.0.lock().unwrap().dropped
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Event;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut buffer = self.0.lock().unwrap();
+        if let Some(event) = buffer.events.pop_front() {
+            Poll::Ready(Some(event))
+        } else if buffer.closed {
+            Poll::Ready(None)
+        } else {
+            buffer.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+    use tokio_test::{assert_pending, assert_ready_eq, task::spawn};
+
+    use super::*;
+
+    fn config(overflow: Overflow) -> EventStreamConfig {
+        EventStreamConfig {
+            capacity: 2,
+            overflow,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (mut notifier, stream) = event_stream(config(Overflow::DropOldest));
+        notifier.new_round();
+        notifier.sum();
+        notifier.idle();
+        assert_eq!(stream.dropped(), 1);
+        drop(notifier);
+        let events: Vec<Event> = stream.collect().await;
+        assert_eq!(events, vec![Event::Sum, Event::Idle]);
+    }
+
+    #[tokio::test]
+    async fn test_drop_newest() {
+        let (mut notifier, stream) = event_stream(config(Overflow::DropNewest));
+        notifier.new_round();
+        notifier.sum();
+        notifier.idle();
+        notifier.load_model();
+        assert_eq!(stream.dropped(), 2);
+        drop(notifier);
+        let events: Vec<Event> = stream.collect().await;
+        assert_eq!(events, vec![Event::NewRound, Event::Sum]);
+    }
+
+    #[test]
+    fn test_termination() {
+        let (mut notifier, stream) = event_stream(EventStreamConfig::default());
+        let mut stream = spawn(stream);
+        assert_pending!(stream.poll_next());
+
+        notifier.update();
+        assert!(stream.is_woken());
+        assert_ready_eq!(stream.poll_next(), Some(Event::Update));
+        assert_pending!(stream.poll_next());
+
+        drop(notifier);
+        assert!(stream.is_woken());
+        assert_ready_eq!(stream.poll_next(), None);
+    }
+}
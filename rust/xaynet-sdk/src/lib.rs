@@ -51,26 +51,25 @@
 //! use std::time::Duration;
 //!
 //! use tokio::time::sleep;
-//! use xaynet_sdk::{StateMachine, TransitionOutcome};
+//! use xaynet_sdk::StateMachine;
 //!
 //! async fn run_agent(mut state_machine: StateMachine, tick: Duration) {
 //!     loop {
-//!         state_machine = match state_machine.transition().await {
-//!             // The state machine is stuck waiting for some data,
-//!             // either from the coordinator or from the
-//!             // participant. Let's wait a little and try again
-//!             TransitionOutcome::Pending(state_machine) => {
-//!                 sleep(tick).await;
-//!                 state_machine
-//!             }
-//!             // The state machine moved forward in the PET protocol.
-//!             // We simply continue looping, trying to make more progress.
-//!             TransitionOutcome::Complete(state_machine) => state_machine,
-//!         };
+//!         // Move forward in the PET protocol as much as possible
+//!         let (pending, _steps) = state_machine.run_until_pending(usize::MAX).await;
+//!         // The state machine is stuck waiting for some data,
+//!         // either from the coordinator or from the
+//!         // participant. Let's wait a little and try again
+//!         sleep(tick).await;
+//!         state_machine = pending;
 //!     }
 //! }
 //! ```
 //!
+//! Rather than polling at a fixed interval, an agent can use a [`Backoff`] to poll less
+//! often while the state machine is pending. With the `websocket` feature, it can instead
+//! wait for the round events that the coordinator pushes with `client::PushNotifications`.
+//!
 //! This agent needs to be fed a [`StateMachine`] in order to run. A
 //! state machine requires found components:
 //!
@@ -85,7 +84,9 @@
 //!   client that is available when compiling with `--features reqwest-client`.
 //! - a notifier that the state machine can use to send
 //!   notifications. This can be any type that implements the
-//!   [`Notify`] trait. We'll use channels for this.
+//!   [`Notify`] trait. We'll use channels for this. Alternatively,
+//!   [`StateMachine::with_event_stream`] creates a state machine along
+//!   with an [`EventStream`] of its notifications.
 //!
 //! [`PetSettings`]: crate::settings::PetSettings
 //! [`Client`]: crate::client::Client
@@ -109,24 +110,13 @@
 //!     crypto::SigningKeyPair,
 //!     mask::{BoundType, DataType, FromPrimitives, GroupType, MaskConfig, Model, ModelType},
 //! };
-//! use xaynet_sdk::{
-//!     client::Client,
-//!     settings::PetSettings,
-//!     ModelStore,
-//!     Notify,
-//!     StateMachine,
-//!     TransitionOutcome,
-//! };
+//! use xaynet_sdk::{client::Client, settings::PetSettings, ModelStore, Notify, StateMachine};
 //!
 //! async fn run_agent(mut state_machine: StateMachine, tick: Duration) {
 //!     loop {
-//!         state_machine = match state_machine.transition().await {
-//!             TransitionOutcome::Pending(state_machine) => {
-//!                 sleep(tick.clone()).await;
-//!                 state_machine
-//!             }
-//!             TransitionOutcome::Complete(state_machine) => state_machine,
-//!         };
+//!         let (pending, _steps) = state_machine.run_until_pending(usize::MAX).await;
+//!         sleep(tick).await;
+//!         state_machine = pending;
 //!     }
 //! }
 //!
@@ -140,6 +130,9 @@
 //!     Sum,
 //!     // event sent by the state machine when a new round starts
 //!     NewRound,
+//!     // event sent by the state machine right after `NewRound`,
+//!     // with the identifier of the new round
+//!     RoundIdChanged(u64),
 //!     // event sent by the state machine when the participant
 //!     // becomes inactive (after finishing a task for instance)
 //!     Idle,
@@ -155,6 +148,9 @@
 //!     fn new_round(&mut self) {
 //!         self.0.send(Event::NewRound).unwrap();
 //!     }
+//!     fn round_id_changed(&mut self, round_id: u64) {
+//!         self.0.send(Event::RoundIdChanged(round_id)).unwrap();
+//!     }
 //!     fn sum(&mut self) {
 //!         self.0.send(Event::Sum).unwrap();
 //!     }
@@ -202,20 +198,42 @@
 //!     });
 //!
 //!     loop {
-//!         println!("{:?}", rx.recv().unwrap());
+//!         match rx.recv().unwrap() {
+//!             Event::RoundIdChanged(round_id) => println!("round {} started", round_id),
+//!             event => println!("{:?}", event),
+//!         }
 //!     }
 //! }
 //! # }
 //! # fn main() {} // don't actually run anything, because the client never terminates
 //! ```
 
+mod backoff;
 pub mod client;
+mod event_stream;
 mod message_encoder;
+pub mod replay;
 pub mod settings;
 mod state_machine;
 mod traits;
 pub(crate) mod utils;
 
 pub(crate) use self::message_encoder::MessageEncoder;
-pub use self::traits::{ModelStore, Notify, XaynetClient};
-pub use state_machine::{LocalModelConfig, SerializableState, StateMachine, TransitionOutcome};
+pub use self::{
+    backoff::{Backoff, BackoffConfig},
+    event_stream::{Event, EventStream, EventStreamConfig, Overflow},
+    traits::{ModelStore, Notify, XaynetClient, MAX_SAMPLE_COUNT},
+};
+pub use state_machine::{
+    legacy,
+    CheckpointError,
+    CircuitState,
+    ConsentRequest,
+    ConsentTask,
+    LocalModelConfig,
+    RestoreError,
+    SerializableState,
+    StateMachine,
+    TransitionOutcome,
+    WorkClass,
+};
@@ -57,7 +57,7 @@ where
 /// # Panic
 ///
 /// This function panic if `d` is 0.
-fn ceiling_div(n: usize, d: usize) -> usize {
+pub(crate) fn ceiling_div(n: usize, d: usize) -> usize {
     (n + d - 1) / d
 }
 
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use super::Chunker;
+#[cfg(feature = "compression")]
+use xaynet_core::message::compression;
 use xaynet_core::{
     crypto::{PublicEncryptKey, SecretSigningKey, SigningKeyPair},
     message::{Chunk, Message, Payload, Tag, ToBytes},
@@ -37,6 +39,14 @@ impl Iterator for MultipartEncoder {
     type Item = Vec<u8>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk(false)
+    }
+}
+
+impl MultipartEncoder {
+    /// Produce the next message part, which has the compression flag set if `self.data` is
+    /// a compressed payload.
+    fn next_chunk(&mut self, is_compressed: bool) -> Option<Vec<u8>> {
         let chunker = Chunker::new(&self.data, self.payload_size - CHUNK_OVERHEAD);
 
         if self.id as usize >= chunker.nb_chunks() {
@@ -56,6 +66,7 @@ impl Iterator for MultipartEncoder {
             signature: None,
             participant_pk: self.keys.public,
             is_multipart: true,
+            is_compressed,
             tag: self.tag,
             payload: Payload::Chunk(chunk),
             coordinator_pk: self.coordinator_pk,
@@ -77,6 +88,9 @@ pub enum MessageEncoder {
     /// Encoder for a large payload that needs to be split in several
     /// parts.
     Multipart(MultipartEncoder),
+    /// Encoder for a compressed payload, which is always sent in one
+    /// or several parts.
+    Compressed(MultipartEncoder),
 }
 
 impl Iterator for MessageEncoder {
@@ -86,6 +100,9 @@ impl Iterator for MessageEncoder {
         match self {
             MessageEncoder::Simple(ref mut data) => data.take(),
             MessageEncoder::Multipart(ref mut multipart_encoder) => multipart_encoder.next(),
+            MessageEncoder::Compressed(ref mut multipart_encoder) => {
+                multipart_encoder.next_chunk(true)
+            }
         }
     }
 }
@@ -150,6 +167,7 @@ impl MessageEncoder {
             signature: None,
             participant_pk: keys.public,
             is_multipart: false,
+            is_compressed: false,
             coordinator_pk,
             tag: Self::get_tag_from_payload(&payload),
             payload,
@@ -178,6 +196,64 @@ impl MessageEncoder {
         })
     }
 
+    /// Create a new encoder which compresses the given payload (see
+    /// [`compression`]). The compressed payload is split in chunks of
+    /// `max_payload_size` like a multipart message, or sent in a
+    /// single chunk if `max_payload_size` is `0`.
+    ///
+    /// # Errors
+    ///
+    /// Fails like [`MessageEncoder::new()`].
+    ///
+    /// [`compression`]: xaynet_core::message::compression
+    #[cfg(feature = "compression")]
+    pub fn new_compressed(
+        keys: SigningKeyPair,
+        payload: Payload,
+        coordinator_pk: PublicEncryptKey,
+        max_payload_size: usize,
+    ) -> Result<Self, InvalidEncodingInput> {
+        if payload.is_chunk() {
+            return Err(InvalidEncodingInput::Payload);
+        }
+
+        if max_payload_size != 0 && max_payload_size <= MIN_PAYLOAD_SIZE {
+            return Err(InvalidEncodingInput::PayloadSize);
+        }
+
+        let tag = Self::get_tag_from_payload(&payload);
+        let mut data = vec![0; payload.buffer_length()];
+        payload.to_bytes(&mut data);
+        let data = compression::compress(&data);
+        let payload_size = if max_payload_size == 0 {
+            data.len() + CHUNK_OVERHEAD
+        } else {
+            max_payload_size
+        };
+        Ok(Self::Compressed(MultipartEncoder {
+            keys,
+            data,
+            id: 0,
+            tag,
+            coordinator_pk,
+            payload_size,
+            message_id: rand::random::<u16>(),
+        }))
+    }
+
+    /// Sets the ID common to all the message chunks, instead of a random one. This has no
+    /// effect if the payload fits in a single message.
+    pub(crate) fn with_message_id(mut self, message_id: u16) -> Self {
+        match self {
+            MessageEncoder::Multipart(ref mut multipart_encoder)
+            | MessageEncoder::Compressed(ref mut multipart_encoder) => {
+                multipart_encoder.message_id = message_id;
+            }
+            MessageEncoder::Simple(_) => {}
+        }
+        self
+    }
+
     fn get_tag_from_payload(payload: &Payload) -> Tag {
         match payload {
             Payload::Sum(_) => Tag::Sum,
@@ -214,6 +290,7 @@ mod tests {
             signature: None,
             participant_pk: participant_keys().public,
             is_multipart: false,
+            is_compressed: false,
             tag: Tag::Update,
             payload,
             coordinator_pk: coordinator_keys().public,
@@ -293,6 +370,62 @@ mod tests {
         assert_eq!(update, extract_update(msg));
     }
 
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_chunks() {
+        let msg = small_message();
+
+        let enc = MessageEncoder::new_compressed(
+            participant_keys(),
+            msg.clone().payload,
+            msg.coordinator_pk,
+            MIN_PAYLOAD_SIZE + 10,
+        )
+        .unwrap();
+
+        let chunks: Vec<Chunk> = enc
+            .map(|data| {
+                let parsed = Message::from_byte_slice(&data.as_slice()).unwrap();
+                assert!(parsed.is_multipart);
+                assert!(parsed.is_compressed);
+                extract_chunk(parsed)
+            })
+            .collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks.last().unwrap().last);
+
+        let compressed: Vec<u8> = chunks.into_iter().flat_map(|chunk| chunk.data).collect();
+        let payload_data = compression::decompress(&compressed, 268).unwrap();
+        let update = Update::from_byte_slice(&payload_data).unwrap();
+        assert_eq!(update, extract_update(msg));
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_single_chunk() {
+        let msg = small_message();
+
+        let mut enc = MessageEncoder::new_compressed(
+            participant_keys(),
+            msg.clone().payload,
+            msg.coordinator_pk,
+            0,
+        )
+        .unwrap();
+
+        let data = enc.next().unwrap();
+        let parsed = Message::from_byte_slice(&data.as_slice()).unwrap();
+        assert!(parsed.is_compressed);
+        let chunk = extract_chunk(parsed);
+        assert!(chunk.last);
+        assert_eq!(chunk.id, 0);
+        assert!(enc.next().is_none());
+
+        let payload_data = compression::decompress(&chunk.data, 268).unwrap();
+        let update = Update::from_byte_slice(&payload_data).unwrap();
+        assert_eq!(update, extract_update(msg));
+    }
+
     fn extract_chunk(message: Message) -> Chunk {
         if let Payload::Chunk(c) = message.payload {
             c
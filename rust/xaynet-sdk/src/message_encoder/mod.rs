@@ -1,5 +1,6 @@
 mod chunker;
 mod encoder;
 
+pub(crate) use chunker::ceiling_div;
 use chunker::Chunker;
-pub use encoder::MessageEncoder;
+pub use encoder::{MessageEncoder, CHUNK_OVERHEAD};
@@ -0,0 +1,326 @@
+//! Deterministic replay of a participant from a saved state and a transcript of the
+//! responses it got from the coordinator.
+//!
+//! A [`TranscriptRecorder`] wraps any [`XaynetClient`] to record a [`Transcript`] of the
+//! requests of a participant and of the responses of the coordinator. Given the
+//! [`SerializableState`] the participant started from, [`replay()`] runs a state machine
+//! against a [`TranscriptClient`] serving the recorded responses and reports every
+//! decision the state machine makes on the way.
+//!
+//! The random choices of a participant (its ephemeral keys, its mask seed and the
+//! encryption of its local seed dictionary) are part of its state once made. Hence a
+//! replay reproduces the messages that are composed from a state in which these choices
+//! were already made, while the choices made during the replay are drawn anew, unless the
+//! participant derives them from a [`DeterministicSeed`].
+//!
+//! [`XaynetClient`]: crate::XaynetClient
+//! [`DeterministicSeed`]: crate::settings::DeterministicSeed
+
+mod recorder;
+mod transcript;
+
+use std::{
+    error::Error,
+    num::NonZeroU64,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+pub use self::{
+    recorder::{TranscriptHandle, TranscriptRecorder},
+    transcript::{
+        Endpoint,
+        Exchange,
+        RecordedError,
+        Request,
+        Response,
+        Transcript,
+        TranscriptClient,
+        TranscriptError,
+    },
+};
+use crate::{
+    settings::CircuitBreakerSettings,
+    state_machine::{CircuitBreaker, StateMachine, TransitionOutcome, IO},
+    ConsentRequest,
+    ConsentTask,
+    Event,
+    LocalModelConfig,
+    SerializableState,
+    XaynetClient,
+};
+use xaynet_core::{
+    common::{RoundMetadata, RoundParameters},
+    crypto::Sha256,
+    mask::Model,
+    message::Tag,
+    SumDict,
+    SumParticipantPublicKey,
+    UpdateSeedDict,
+};
+
+/// The task a participant has been selected for at the start of a round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// The participant has been selected for the sum task.
+    Sum,
+    /// The participant has been selected for the update task.
+    Update,
+    /// The participant has not been selected for any task.
+    None,
+}
+
+/// A decision of the state machine during a replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// The participant has been selected for a task.
+    Task(Task),
+    /// The state machine emitted a notification.
+    Notification(Event),
+    /// The state machine composed a PET message with the given tag. The message is
+    /// identified by the hash of its signed but unencrypted bytes, because the
+    /// encryption is randomized.
+    Message(Tag, Sha256),
+}
+
+/// The report of a [`replay()`].
+#[derive(Debug)]
+pub struct ReplayReport {
+    /// The decisions of the state machine in the order in which they were made.
+    pub decisions: Vec<Decision>,
+    /// The first request that didn't match the transcript, if the participant diverged
+    /// from it.
+    pub divergence: Option<TranscriptError>,
+    /// The number of exchanges of the transcript that have not been replayed.
+    pub remaining: usize,
+    /// The state of the state machine at the end of the replay.
+    pub state: SerializableState,
+}
+
+/// What has been observed so far during a replay.
+#[derive(Debug, Default)]
+struct Observations {
+    decisions: Vec<Decision>,
+    divergence: Option<TranscriptError>,
+    remaining: usize,
+}
+
+/// Replays a participant from the given `state` against the given `transcript`.
+///
+/// The state machine makes transitions until it cannot make progress anymore, ie until
+/// the transcript is exhausted, the participant diverged from the transcript or the
+/// participant waits for something that is not part of the transcript: the replay has
+/// no model store, doesn't confirm the global mask of the sum2 phase and doesn't give
+/// the consent of the user to take part in a task.
+///
+/// The circuit breaker is disabled during the replay, because the recorded responses are
+/// served without delay.
+pub async fn replay(mut state: SerializableState, transcript: Transcript) -> ReplayReport {
+    state.shared_mut().circuit_breaker = CircuitBreaker::new(CircuitBreakerSettings::disabled());
+
+    let client = TranscriptClient::new(transcript);
+    let observations = Arc::new(Mutex::new(Observations {
+        remaining: client.remaining(),
+        ..Observations::default()
+    }));
+    let io = Box::new(ReplayIO {
+        client,
+        observations: observations.clone(),
+    });
+    let mut state_machine = StateMachine::restore_with_io(state, io);
+
+    loop {
+        let remaining = observations.lock().unwrap().remaining;
+        let is_new_round = matches!(state_machine, StateMachine::NewRound(_));
+        let completed = match state_machine.transition().await {
+            TransitionOutcome::Pending(next) => {
+                state_machine = next;
+                false
+            }
+            TransitionOutcome::Complete(next) => {
+                state_machine = next;
+                true
+            }
+        };
+
+        let mut observations = observations.lock().unwrap();
+        if completed && is_new_round {
+            let task = match state_machine {
+                StateMachine::Sum(_) => Some(Task::Sum),
+                StateMachine::Update(_) => Some(Task::Update),
+                StateMachine::AwaitingConsent(_) => {
+                    state_machine
+                        .consent_request()
+                        .map(|request| match request.task {
+                            ConsentTask::Sum => Task::Sum,
+                            ConsentTask::Update => Task::Update,
+                        })
+                }
+                StateMachine::Awaiting(_) => Some(Task::None),
+                _ => None,
+            };
+            if let Some(task) = task {
+                observations.decisions.push(Decision::Task(task));
+            }
+        }
+        let is_stuck = !completed && observations.remaining == remaining;
+        if observations.divergence.is_some() || is_stuck {
+            break;
+        }
+    }
+
+    let state = state_machine.save();
+    let mut observations = observations.lock().unwrap();
+    ReplayReport {
+        decisions: std::mem::take(&mut observations.decisions),
+        divergence: observations.divergence.take(),
+        remaining: observations.remaining,
+        state,
+    }
+}
+
+/// The [`IO`] object of a replay. It serves the responses of a [`TranscriptClient`] and
+/// observes the decisions of the state machine.
+struct ReplayIO {
+    client: TranscriptClient,
+    observations: Arc<Mutex<Observations>>,
+}
+
+impl ReplayIO {
+    /// Observes the outcome of a request.
+    fn observe_request<T>(
+        &mut self,
+        result: Result<T, <TranscriptClient as XaynetClient>::Error>,
+    ) -> Result<T, Box<dyn Error>> {
+        let mut observations = self.observations.lock().unwrap();
+        observations.remaining = self.client.remaining();
+        if observations.divergence.is_none() {
+            observations.divergence = self.client.divergence().cloned();
+        }
+        result.map_err(|e| Box::new(e) as Box<dyn Error>)
+    }
+
+    /// Observes a decision.
+    fn observe(&mut self, decision: Decision) {
+        self.observations.lock().unwrap().decisions.push(decision);
+    }
+}
+
+#[async_trait]
+impl IO for ReplayIO {
+    type Model = Box<dyn AsRef<Model> + Send>;
+
+    async fn load_model(
+        &mut self,
+        _config: &LocalModelConfig,
+    ) -> Result<Option<Self::Model>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    fn model_chunk_size(&self) -> Option<usize> {
+        None
+    }
+
+    fn model_sample_count(&self) -> Option<NonZeroU64> {
+        None
+    }
+
+    async fn load_model_chunk(
+        &mut self,
+        _config: &LocalModelConfig,
+        _range: Range<usize>,
+    ) -> Result<Option<Model>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Box<dyn Error>> {
+        let result = self.client.get_round_params().await;
+        self.observe_request(result)
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Box<dyn Error>> {
+        let result = self.client.get_round_metadata().await;
+        self.observe_request(result)
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Box<dyn Error>> {
+        let result = self.client.get_sums().await;
+        self.observe_request(result)
+    }
+
+    async fn get_seeds(
+        &mut self,
+        pk: SumParticipantPublicKey,
+    ) -> Result<Option<UpdateSeedDict>, Box<dyn Error>> {
+        let result = self.client.get_seeds(pk).await;
+        self.observe_request(result)
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Box<dyn Error>> {
+        let result = self.client.get_model().await;
+        self.observe_request(result)
+    }
+
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        let result = self.client.send_message(tag, msg).await;
+        self.observe_request(result)
+    }
+
+    fn observe_message(&mut self, tag: Tag, msg: &[u8]) {
+        self.observe(Decision::Message(tag, Sha256::hash(msg)));
+    }
+
+    fn notify_new_round(&mut self) {
+        self.observe(Decision::Notification(Event::NewRound));
+    }
+
+    fn notify_sum(&mut self) {
+        self.observe(Decision::Notification(Event::Sum));
+    }
+
+    fn notify_update(&mut self) {
+        self.observe(Decision::Notification(Event::Update));
+    }
+
+    fn notify_idle(&mut self) {
+        self.observe(Decision::Notification(Event::Idle));
+    }
+
+    fn notify_load_model(&mut self) {
+        self.observe(Decision::Notification(Event::LoadModel));
+    }
+
+    fn notify_sum2_mask_ready(&mut self) {
+        self.observe(Decision::Notification(Event::Sum2MaskReady));
+    }
+
+    fn notify_quota_exceeded(&mut self) {
+        self.observe(Decision::Notification(Event::QuotaExceeded));
+    }
+
+    fn notify_awaiting_consent(&mut self, request: ConsentRequest) {
+        self.observe(Decision::Notification(Event::AwaitingConsent(request)));
+    }
+
+    fn notify_insufficient_time(&mut self) {
+        self.observe(Decision::Notification(Event::InsufficientTime));
+    }
+
+    fn notify_ephemeral_keys_mismatch(&mut self) {
+        self.observe(Decision::Notification(Event::EphemeralKeysMismatch));
+    }
+
+    fn notify_model_unchanged(&mut self) {
+        self.observe(Decision::Notification(Event::ModelUnchanged));
+    }
+
+    fn notify_round_id_changed(&mut self, round_id: u64) {
+        self.observe(Decision::Notification(Event::RoundIdChanged(round_id)));
+    }
+
+    fn notify_training_plan(&mut self, plan_id: String) {
+        self.observe(Decision::Notification(Event::TrainingPlan(plan_id)));
+    }
+}
@@ -0,0 +1,161 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use super::{Endpoint, Exchange, RecordedError, Request, Response, Transcript};
+use crate::XaynetClient;
+use xaynet_core::{
+    common::{PhaseStats, RoundMetadata, RoundParameters, RoundSeed},
+    mask::Model,
+    message::Tag,
+    SumDict,
+    SumParticipantPublicKey,
+    UpdateSeedDict,
+};
+
+/// A [`XaynetClient`] that records a [`Transcript`] of the requests it makes with the
+/// wrapped client.
+///
+/// The recorder is moved into the state machine, hence the transcript is accessed via a
+/// [`TranscriptHandle`] (see [`TranscriptRecorder::handle`]).
+#[derive(Debug)]
+pub struct TranscriptRecorder<C> {
+    /// The wrapped client.
+    client: C,
+    /// The seed of the round of the last fetched round parameters.
+    round: Option<RoundSeed>,
+    /// The recorded transcript.
+    transcript: Arc<Mutex<Transcript>>,
+}
+
+/// A handle to the [`Transcript`] of a [`TranscriptRecorder`].
+#[derive(Debug, Clone)]
+pub struct TranscriptHandle(Arc<Mutex<Transcript>>);
+
+impl TranscriptHandle {
+    /// Returns a copy of the transcript recorded so far.
+    pub fn transcript(&self) -> Transcript {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl<C> TranscriptRecorder<C> {
+    /// Creates a new recorder that wraps the given `client`.
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            round: None,
+            transcript: Arc::new(Mutex::new(Transcript::default())),
+        }
+    }
+
+    /// Returns a handle to the recorded transcript.
+    pub fn handle(&self) -> TranscriptHandle {
+        TranscriptHandle(self.transcript.clone())
+    }
+}
+
+impl<C> TranscriptRecorder<C>
+where
+    C: XaynetClient,
+    C::Error: 'static,
+{
+    /// Returns the request to the given `endpoint` in the current round.
+    fn request(&self, endpoint: Endpoint) -> Request {
+        Request {
+            round: self.round.clone(),
+            endpoint,
+        }
+    }
+
+    /// Records the outcome of a request.
+    fn record<T, F>(&mut self, request: Request, result: &Result<T, C::Error>, response: F)
+    where
+        F: FnOnce(&T) -> Response,
+    {
+        let response = result
+            .as_ref()
+            .map(response)
+            .map_err(|e| RecordedError::new(e));
+        if let Ok(Response::RoundParams(ref params)) = response {
+            self.round = Some(params.seed.clone());
+        }
+        self.transcript
+            .lock()
+            .unwrap()
+            .exchanges
+            .push(Exchange { request, response });
+    }
+}
+
+#[async_trait]
+impl<C> XaynetClient for TranscriptRecorder<C>
+where
+    C: XaynetClient + Send,
+    C::Error: 'static,
+{
+    type Error = C::Error;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        let request = self.request(Endpoint::RoundParams);
+        let result = self.client.get_round_params().await;
+        self.record(request, &result, |params| {
+            Response::RoundParams(params.clone())
+        });
+        result
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        let request = self.request(Endpoint::RoundMetadata);
+        let result = self.client.get_round_metadata().await;
+        self.record(request, &result, |metadata| {
+            Response::RoundMetadata(*metadata)
+        });
+        result
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        let request = self.request(Endpoint::PhaseStats);
+        let result = self.client.get_phase_stats().await;
+        self.record(request, &result, |stats| Response::PhaseStats(*stats));
+        result
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        let request = self.request(Endpoint::Sums);
+        let result = self.client.get_sums().await;
+        self.record(request, &result, |sums| Response::Sums(sums.clone()));
+        result
+    }
+
+    async fn get_seeds(
+        &mut self,
+        pk: SumParticipantPublicKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        let request = self.request(Endpoint::Seeds(pk));
+        let result = self.client.get_seeds(pk).await;
+        self.record(request, &result, |seeds| Response::Seeds(seeds.clone()));
+        result
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        let request = self.request(Endpoint::Model);
+        let result = self.client.get_model().await;
+        self.record(request, &result, |model| Response::Model(model.clone()));
+        result
+    }
+
+    async fn get_model_by_id(&mut self, id: &str) -> Result<Option<Model>, Self::Error> {
+        let request = self.request(Endpoint::ModelById(id.to_string()));
+        let result = self.client.get_model_by_id(id).await;
+        self.record(request, &result, |model| Response::Model(model.clone()));
+        result
+    }
+
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Self::Error> {
+        let request = self.request(Endpoint::Message(tag));
+        let result = self.client.send_message(tag, msg).await;
+        self.record(request, &result, |_| Response::Message);
+        result
+    }
+}
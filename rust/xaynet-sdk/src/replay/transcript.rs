@@ -0,0 +1,266 @@
+use std::{collections::VecDeque, error::Error};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{client::ClientError, XaynetClient};
+use xaynet_core::{
+    common::{PhaseStats, RoundMetadata, RoundParameters, RoundSeed},
+    mask::Model,
+    message::Tag,
+    SumDict,
+    SumParticipantPublicKey,
+    UpdateSeedDict,
+};
+
+/// A recorded sequence of requests to the coordinator and of the responses they got.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Transcript {
+    /// The exchanges in the order in which they happened.
+    pub exchanges: Vec<Exchange>,
+}
+
+/// A request to the coordinator and the response it got.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Exchange {
+    /// The request.
+    pub request: Request,
+    /// The response, or the error the request failed with.
+    pub response: Result<Response, RecordedError>,
+}
+
+/// A request to the coordinator.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Request {
+    /// The seed of the round the participant was in when it made the request. It is
+    /// `None` until the participant fetched the round parameters for the first time.
+    pub round: Option<RoundSeed>,
+    /// The requested endpoint.
+    pub endpoint: Endpoint,
+}
+
+/// An endpoint of the coordinator API.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    /// The round parameters.
+    RoundParams,
+    /// The metadata of the current round.
+    RoundMetadata,
+    /// The sum dictionary.
+    Sums,
+    /// The seed dictionary of the given sum participant.
+    Seeds(SumParticipantPublicKey),
+    /// The global model.
+    Model,
+    /// The global model with the given id.
+    ModelById(String),
+    /// A PET message with the given tag.
+    Message(Tag),
+    /// The message counters of the current phase.
+    PhaseStats,
+}
+
+/// A successful response of the coordinator.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Response {
+    /// The round parameters.
+    RoundParams(RoundParameters),
+    /// The metadata of the current round.
+    RoundMetadata(RoundMetadata),
+    /// The sum dictionary, if available.
+    Sums(Option<SumDict>),
+    /// The seed dictionary, if available.
+    Seeds(Option<UpdateSeedDict>),
+    /// The global model, if available.
+    Model(Option<Model>),
+    /// The PET message has been accepted.
+    Message,
+    /// The message counters of the current phase.
+    PhaseStats(PhaseStats),
+}
+
+/// An error a request failed with.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum RecordedError {
+    /// The coordinator rejected the message because the participant exceeded its update
+    /// participation quota.
+    QuotaExceeded,
+    /// The coordinator rejected the message and explained why.
+    Rejected(u16, String),
+    /// Any other error, recorded as its description.
+    Other(String),
+}
+
+impl RecordedError {
+    /// Records an error. The errors that the state machine reacts to are recorded as
+    /// such, the other ones as their description.
+    pub(crate) fn new(error: &(dyn Error + 'static)) -> Self {
+        match error.downcast_ref::<ClientError>() {
+            Some(ClientError::QuotaExceeded) => Self::QuotaExceeded,
+            Some(ClientError::Rejected(status, reason)) => Self::Rejected(*status, reason.clone()),
+            _ => Self::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for ClientError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::QuotaExceeded => Self::QuotaExceeded,
+            RecordedError::Rejected(status, reason) => Self::Rejected(status, reason),
+            RecordedError::Other(description) => Self::Other(description),
+        }
+    }
+}
+
+/// Error returned when a request cannot be served from a [`Transcript`].
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum TranscriptError {
+    #[error("the transcript is exhausted, no response for {0:?}")]
+    Exhausted(Request),
+
+    #[error("the transcript expected {expected:?} but got {found:?}")]
+    Mismatch { expected: Request, found: Request },
+
+    #[error("the recorded response doesn't fit {0:?}")]
+    InvalidResponse(Request),
+}
+
+/// A [`XaynetClient`] that serves the responses of a [`Transcript`].
+///
+/// A request is only served the next recorded response if it matches the next recorded
+/// request, ie if both are made to the same endpoint during the same round. Otherwise,
+/// the request fails and the first mismatch is kept as the point where the participant
+/// diverged from the transcript (see [`TranscriptClient::divergence`]).
+#[derive(Debug)]
+pub struct TranscriptClient {
+    /// The exchanges that have not been served yet.
+    exchanges: VecDeque<Exchange>,
+    /// The seed of the round of the last served round parameters.
+    round: Option<RoundSeed>,
+    /// The first request that didn't match the transcript.
+    divergence: Option<TranscriptError>,
+}
+
+impl TranscriptClient {
+    /// Creates a new client that serves the responses of the given `transcript`.
+    pub fn new(transcript: Transcript) -> Self {
+        Self {
+            exchanges: transcript.exchanges.into(),
+            round: None,
+            divergence: None,
+        }
+    }
+
+    /// Returns the number of exchanges that have not been served yet.
+    pub fn remaining(&self) -> usize {
+        self.exchanges.len()
+    }
+
+    /// Returns the first request that didn't match the transcript, if any.
+    pub fn divergence(&self) -> Option<&TranscriptError> {
+        self.divergence.as_ref()
+    }
+
+    /// Serves the next recorded response for a request to the given `endpoint`.
+    fn respond(&mut self, endpoint: Endpoint) -> Result<Response, ClientError> {
+        let request = Request {
+            round: self.round.clone(),
+            endpoint,
+        };
+        let error = match self.exchanges.front() {
+            None => TranscriptError::Exhausted(request),
+            Some(exchange) if exchange.request != request => TranscriptError::Mismatch {
+                expected: exchange.request.clone(),
+                found: request,
+            },
+            Some(_) => {
+                // UNWRAP_SAFE: the front exchange exists
+                let exchange = self.exchanges.pop_front().unwrap();
+                if let Ok(Response::RoundParams(ref params)) = exchange.response {
+                    self.round = Some(params.seed.clone());
+                }
+                return exchange.response.map_err(ClientError::from);
+            }
+        };
+        if let TranscriptError::Mismatch { .. } = error {
+            self.divergence.get_or_insert_with(|| error.clone());
+        }
+        Err(ClientError::Other(error.to_string()))
+    }
+
+    /// Returns the error for a recorded response that doesn't fit the request.
+    fn invalid_response(&self, endpoint: Endpoint) -> ClientError {
+        let request = Request {
+            round: self.round.clone(),
+            endpoint,
+        };
+        ClientError::Other(TranscriptError::InvalidResponse(request).to_string())
+    }
+}
+
+#[async_trait]
+impl XaynetClient for TranscriptClient {
+    type Error = ClientError;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        match self.respond(Endpoint::RoundParams)? {
+            Response::RoundParams(params) => Ok(params),
+            _ => Err(self.invalid_response(Endpoint::RoundParams)),
+        }
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        match self.respond(Endpoint::RoundMetadata)? {
+            Response::RoundMetadata(metadata) => Ok(metadata),
+            _ => Err(self.invalid_response(Endpoint::RoundMetadata)),
+        }
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        match self.respond(Endpoint::PhaseStats)? {
+            Response::PhaseStats(stats) => Ok(stats),
+            _ => Err(self.invalid_response(Endpoint::PhaseStats)),
+        }
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        match self.respond(Endpoint::Sums)? {
+            Response::Sums(sums) => Ok(sums),
+            _ => Err(self.invalid_response(Endpoint::Sums)),
+        }
+    }
+
+    async fn get_seeds(
+        &mut self,
+        pk: SumParticipantPublicKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        match self.respond(Endpoint::Seeds(pk))? {
+            Response::Seeds(seeds) => Ok(seeds),
+            _ => Err(self.invalid_response(Endpoint::Seeds(pk))),
+        }
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        match self.respond(Endpoint::Model)? {
+            Response::Model(model) => Ok(model),
+            _ => Err(self.invalid_response(Endpoint::Model)),
+        }
+    }
+
+    async fn get_model_by_id(&mut self, id: &str) -> Result<Option<Model>, Self::Error> {
+        let endpoint = Endpoint::ModelById(id.to_string());
+        match self.respond(endpoint.clone())? {
+            Response::Model(model) => Ok(model),
+            _ => Err(self.invalid_response(endpoint)),
+        }
+    }
+
+    async fn send_message(&mut self, tag: Tag, _msg: Vec<u8>) -> Result<(), Self::Error> {
+        match self.respond(Endpoint::Message(tag))? {
+            Response::Message => Ok(()),
+            _ => Err(self.invalid_response(Endpoint::Message(tag))),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings of the circuit breaker that protects the coordinator from being flooded with
+/// requests while it is unavailable.
+///
+/// After [`threshold`] consecutive server errors within [`window`], the circuit breaker
+/// opens and the state machine stops sending requests to the coordinator for
+/// [`backoff`] plus a random delay of at most [`jitter`]. Then a single probing request
+/// is let through: if it succeeds, the circuit breaker closes again, otherwise it
+/// re-opens.
+///
+/// [`threshold`]: CircuitBreakerSettings::threshold
+/// [`window`]: CircuitBreakerSettings::window
+/// [`backoff`]: CircuitBreakerSettings::backoff
+/// [`jitter`]: CircuitBreakerSettings::jitter
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CircuitBreakerSettings {
+    /// Number of consecutive server errors after which the circuit breaker opens. `0`
+    /// disables the circuit breaker.
+    pub threshold: u32,
+    /// Time window within which the consecutive server errors must occur.
+    pub window: Duration,
+    /// Time during which the requests are short-circuited once the circuit breaker
+    /// opened.
+    pub backoff: Duration,
+    /// Maximum random delay added to the backoff, so that the participants don't all
+    /// probe the coordinator at the same time.
+    pub jitter: Duration,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_secs(60),
+            jitter: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CircuitBreakerSettings {
+    /// Settings that disable the circuit breaker.
+    pub fn disabled() -> Self {
+        Self {
+            threshold: 0,
+            ..Self::default()
+        }
+    }
+}
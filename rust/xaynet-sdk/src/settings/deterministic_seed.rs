@@ -0,0 +1,174 @@
+use std::fmt;
+
+#[cfg(not(feature = "deterministic"))]
+use serde::{de::Error as SerdeError, Deserializer};
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+
+use xaynet_core::{
+    common::RoundSeed,
+    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, PublicSigningKey},
+    mask::MaskSeed,
+    message::Tag,
+};
+
+/// A seed from which a participant derives all the randomness it needs for the PET
+/// protocol, i.e. the ephemeral keys of the sum task, the mask seed of the update task
+/// and the ephemeral keys used to encrypt the messages.
+///
+/// **This is meant for reproducible test runs only.** Given the seed and the public round
+/// parameters, anyone can recompute the mask of the participant, hence it must never be
+/// used in production. Each value is derived from the seed, the public key of the
+/// participant, the round seed and a label, hence the values differ from one round to the
+/// next.
+///
+/// The state machine also derives the randomness of each round from a seed that it
+/// generates securely when the round starts and keeps in its saved state.
+///
+/// The participant settings only accept a seed with the `deterministic` feature.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DeterministicSeed(pub [u8; 32]);
+
+impl fmt::Debug for DeterministicSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DeterministicSeed(..)")
+    }
+}
+
+impl DeterministicSeed {
+    /// Generates a random seed.
+    pub(crate) fn generate() -> Self {
+        Self(rand::random())
+    }
+
+    /// Derives 32 bytes for the given `label` and `data`.
+    fn derive(
+        &self,
+        pk: &PublicSigningKey,
+        round_seed: &RoundSeed,
+        label: &[u8],
+        data: &[u8],
+    ) -> sha256::Digest {
+        sha256::hash(
+            &[
+                &self.0[..],
+                pk.as_slice(),
+                round_seed.as_slice(),
+                label,
+                data,
+            ]
+            .concat(),
+        )
+    }
+
+    /// Derives the ephemeral keys of a sum participant.
+    pub(crate) fn ephm_keys(
+        &self,
+        pk: &PublicSigningKey,
+        round_seed: &RoundSeed,
+    ) -> EncryptKeyPair {
+        let digest = self.derive(pk, round_seed, b"ephm_keys", &[]);
+        // UNWRAP_SAFE: the length of the hash is 32 bytes
+        EncryptKeyPair::derive_from_seed(&EncryptKeySeed::from_slice_unchecked(digest.as_ref()))
+    }
+
+    /// Derives the mask seed of an update participant.
+    pub(crate) fn mask_seed(&self, pk: &PublicSigningKey, round_seed: &RoundSeed) -> MaskSeed {
+        let digest = self.derive(pk, round_seed, b"mask_seed", &[]);
+        // UNWRAP_SAFE: the length of the hash is 32 bytes
+        MaskSeed::from_slice_unchecked(digest.as_ref())
+    }
+
+    /// Derives the seed of the differential privacy noise of an update participant.
+    pub(crate) fn dp_seed(&self, pk: &PublicSigningKey, round_seed: &RoundSeed) -> [u8; 32] {
+        self.derive(pk, round_seed, b"dp_seed", &[]).0
+    }
+
+    /// Derives the ID common to the chunks of a multipart message with the given `tag`.
+    pub(crate) fn message_id(
+        &self,
+        pk: &PublicSigningKey,
+        round_seed: &RoundSeed,
+        tag: Tag,
+    ) -> u16 {
+        let digest = self.derive(pk, round_seed, b"message_id", &[tag.into()]);
+        u16::from_le_bytes([digest.0[0], digest.0[1]])
+    }
+
+    /// Derives the seed of the ephemeral keys used to encrypt the given `data`.
+    pub(crate) fn encrypt_key_seed(
+        &self,
+        pk: &PublicSigningKey,
+        round_seed: &RoundSeed,
+        data: &[u8],
+    ) -> EncryptKeySeed {
+        let digest = self.derive(pk, round_seed, b"encrypt", data);
+        // UNWRAP_SAFE: the length of the hash is 32 bytes
+        EncryptKeySeed::from_slice_unchecked(digest.as_ref())
+    }
+}
+
+/// Deserializes the deterministic seed of the participant settings without the
+/// `deterministic` feature: a seed is refused rather than silently ignored.
+#[cfg(not(feature = "deterministic"))]
+pub(super) fn refuse<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    match Option::<DeterministicSeed>::deserialize(deserializer)? {
+        Some(_) => Err(SerdeError::custom(
+            "a deterministic seed requires the `deterministic` feature",
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use xaynet_core::crypto::SigningKeyPair;
+
+    #[test]
+    fn test_derive() {
+        let seed = DeterministicSeed([1; 32]);
+        let pk = SigningKeyPair::generate().public;
+        let round_seed = RoundSeed::generate();
+
+        assert_eq!(
+            seed.ephm_keys(&pk, &round_seed),
+            seed.ephm_keys(&pk, &round_seed)
+        );
+        assert_eq!(
+            seed.mask_seed(&pk, &round_seed),
+            seed.mask_seed(&pk, &round_seed)
+        );
+        assert_ne!(
+            seed.ephm_keys(&pk, &round_seed),
+            seed.ephm_keys(&pk, &RoundSeed::generate())
+        );
+        assert!(
+            seed.encrypt_key_seed(&pk, &round_seed, b"a")
+                != seed.encrypt_key_seed(&pk, &round_seed, b"b")
+        );
+        let other_pk = SigningKeyPair::generate().public;
+        assert_ne!(
+            seed.mask_seed(&pk, &round_seed),
+            seed.mask_seed(&other_pk, &round_seed)
+        );
+    }
+
+    #[cfg(not(feature = "deterministic"))]
+    #[test]
+    fn test_refuse_seed() {
+        use crate::settings::PetSettings;
+
+        let settings = PetSettings::new(SigningKeyPair::generate());
+        let mut settings = serde_json::to_value(&settings).unwrap();
+        assert!(serde_json::from_value::<PetSettings>(settings.clone()).is_ok());
+
+        settings["deterministic_seed"] = serde_json::Value::Null;
+        assert!(serde_json::from_value::<PetSettings>(settings.clone()).is_ok());
+
+        settings["deterministic_seed"] =
+            serde_json::to_value(Some(DeterministicSeed([1; 32]))).unwrap();
+        assert!(serde_json::from_value::<PetSettings>(settings).is_err());
+    }
+}
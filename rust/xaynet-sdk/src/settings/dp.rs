@@ -0,0 +1,48 @@
+use serde::{de::Error as SerdeError, Deserialize, Deserializer};
+use xaynet_core::mask::DpConfig;
+
+/// Deserializes the differential privacy step of the participant settings: an invalid
+/// configuration is refused rather than discovered when the model is masked.
+pub(super) fn validate<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DpConfig>, D::Error> {
+    let dp = Option::<DpConfig>::deserialize(deserializer)?;
+    if let Some(dp) = dp {
+        dp.validate().map_err(SerdeError::custom)?;
+    }
+    Ok(dp)
+}
+
+#[cfg(test)]
+mod tests {
+    use xaynet_core::{crypto::SigningKeyPair, mask::DpMechanism};
+
+    use super::*;
+    use crate::settings::PetSettings;
+
+    #[test]
+    fn test_validate() {
+        let mut settings = PetSettings::new(SigningKeyPair::generate());
+        let dp = DpConfig {
+            epsilon: 0.5,
+            delta: 1e-5,
+            sensitivity: 1.0,
+            mechanism: DpMechanism::Gaussian,
+        };
+        settings.dp = Some(dp);
+        let mut settings = serde_json::to_value(&settings).unwrap();
+        let parsed = serde_json::from_value::<PetSettings>(settings.clone()).unwrap();
+        assert_eq!(parsed.dp, Some(dp));
+
+        settings["dp"] = serde_json::Value::Null;
+        let parsed = serde_json::from_value::<PetSettings>(settings.clone()).unwrap();
+        assert_eq!(parsed.dp, None);
+
+        settings["dp"] = serde_json::to_value(DpConfig {
+            sensitivity: 0.0,
+            ..dp
+        })
+        .unwrap();
+        assert!(serde_json::from_value::<PetSettings>(settings).is_err());
+    }
+}
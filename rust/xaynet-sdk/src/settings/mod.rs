@@ -1,15 +1,86 @@
+mod circuit_breaker;
+mod deterministic_seed;
+mod dp;
 mod max_message_size;
 
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 
+pub use circuit_breaker::CircuitBreakerSettings;
+pub use deterministic_seed::DeterministicSeed;
 pub use max_message_size::{InvalidMaxMessageSize, MaxMessageSize, MIN_MESSAGE_SIZE};
+pub use xaynet_core::mask::{DpConfig, DpMechanism, InvalidDpConfig};
 use xaynet_core::{crypto::SigningKeyPair, mask::Scalar};
 
+/// Default value of [`PetSettings::yield_interval`].
+pub const DEFAULT_YIELD_INTERVAL: usize = 8;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PetSettings {
     pub keys: SigningKeyPair,
     pub scalar: Scalar,
     pub max_message_size: MaxMessageSize,
+    /// Number of elements (e.g. mask seeds to encrypt or decrypt) the state machine
+    /// processes in a CPU heavy section before yielding back to the executor. Masking
+    /// and mask aggregation are offloaded to the blocking thread pool of the tokio
+    /// runtime, if any. If `0`, the CPU heavy sections are executed inline and block
+    /// the executor until they complete.
+    pub yield_interval: usize,
+    /// Settings of the circuit breaker that stops the requests to the coordinator when
+    /// it repeatedly fails with server errors.
+    pub circuit_breaker: CircuitBreakerSettings,
+    /// Whether the state machine pauses in the sum2 phase once the global mask is
+    /// aggregated, until it is confirmed with [`StateMachine::confirm_sum2()`]. While it
+    /// is paused, the mask can be inspected with [`StateMachine::sum2_mask()`].
+    ///
+    /// [`StateMachine::confirm_sum2()`]: crate::StateMachine::confirm_sum2
+    /// [`StateMachine::sum2_mask()`]: crate::StateMachine::sum2_mask
+    pub confirm_sum2: bool,
+    /// Whether the state machine asks for the consent of the user before taking part in
+    /// the task it has been selected for. It then pauses until the consent is given with
+    /// [`StateMachine::grant_consent()`] or refused with
+    /// [`StateMachine::deny_consent()`], and nothing is uploaded in the meantime.
+    ///
+    /// [`StateMachine::grant_consent()`]: crate::StateMachine::grant_consent
+    /// [`StateMachine::deny_consent()`]: crate::StateMachine::deny_consent
+    pub require_consent: bool,
+    /// How long the state machine waits for the consent of the user before abandoning the
+    /// task. `None` means that it waits until the end of the round. This is only relevant
+    /// if [`PetSettings::require_consent`] is set.
+    pub consent_timeout: Option<Duration>,
+    /// Safety margin kept before the end of the update and sum2 phases. Before the
+    /// expensive steps of these tasks, the state machine compares the time it needs to
+    /// complete the task, plus this margin, with the deadline of the phase announced by
+    /// the coordinator, and abandons the task if it can't make it in time. `None`
+    /// disables the check. The task is never abandoned if the deadline is unknown.
+    pub deadline_margin: Option<Duration>,
+    /// Seed from which all the randomness of the participant is derived, making its
+    /// messages reproducible. `None` means that the randomness is generated securely.
+    /// **This must never be set in production** (see [`DeterministicSeed`]). It is only
+    /// available with the `deterministic` feature.
+    #[cfg(feature = "deterministic")]
+    pub deterministic_seed: Option<DeterministicSeed>,
+    /// Without the `deterministic` feature, serialized settings with a deterministic seed
+    /// are refused.
+    #[cfg(not(feature = "deterministic"))]
+    #[serde(
+        rename = "deterministic_seed",
+        default,
+        deserialize_with = "deterministic_seed::refuse",
+        skip_serializing
+    )]
+    #[allow(dead_code)]
+    no_deterministic_seed: (),
+    /// Differential privacy of the local model, which is clipped and noised when it is
+    /// masked (see [`DpConfig`]). `None` disables it. An invalid configuration is refused
+    /// when the settings are deserialized.
+    #[serde(default, deserialize_with = "dp::validate")]
+    pub dp: Option<DpConfig>,
+    /// Whether the update and sum2 messages are compressed, if the coordinator accepts
+    /// compressed messages. This has no effect unless the `compression` feature is
+    /// enabled.
+    pub compression: bool,
 }
 
 impl PetSettings {
@@ -18,6 +89,18 @@ impl PetSettings {
             keys,
             scalar: Scalar::unit(),
             max_message_size: MaxMessageSize::default(),
+            yield_interval: DEFAULT_YIELD_INTERVAL,
+            circuit_breaker: CircuitBreakerSettings::default(),
+            confirm_sum2: false,
+            require_consent: false,
+            consent_timeout: None,
+            deadline_margin: None,
+            #[cfg(feature = "deterministic")]
+            deterministic_seed: None,
+            #[cfg(not(feature = "deterministic"))]
+            no_deterministic_seed: (),
+            dp: None,
+            compression: false,
         }
     }
 }
@@ -0,0 +1,522 @@
+use std::{error::Error, time::Duration};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::clock::{millis, Now};
+use crate::{client::ClientError, settings::CircuitBreakerSettings};
+
+/// State of the circuit breaker of a state machine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// The requests are sent to the coordinator.
+    Closed,
+    /// The requests are short-circuited, without any network I/O, for the `remaining`
+    /// time.
+    Open { remaining: Duration },
+    /// The backoff elapsed. The next request probes whether the coordinator recovered.
+    HalfOpen,
+}
+
+/// Internal state of the [`CircuitBreaker`]. All the timestamps are milliseconds on the
+/// monotonic clock (see [`Now`]), so that they are not affected by jumps of the wall
+/// clock.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    /// `failures` consecutive server errors occurred in the window that ends at
+    /// `window_end`.
+    Closed { failures: u32, window_end: u64 },
+    /// The requests are short-circuited until `until`.
+    Open { until: u64 },
+    /// The next request is a probe.
+    HalfOpen,
+}
+
+impl BreakerState {
+    fn closed() -> Self {
+        Self::Closed {
+            failures: 0,
+            window_end: 0,
+        }
+    }
+}
+
+/// A circuit breaker that stops the state machine from sending requests to the
+/// coordinator when it repeatedly fails with server errors.
+///
+/// Server errors are `5xx` responses and failures to reach the coordinator at all. Any
+/// other outcome of a request means that the coordinator is up and resets the circuit
+/// breaker.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct CircuitBreaker {
+    settings: CircuitBreakerSettings,
+    state: BreakerState,
+    /// The last reading of the clocks. It anchors the monotonic timestamps of the state
+    /// when the circuit breaker is restored in another process.
+    last_seen: Option<Now>,
+}
+
+impl CircuitBreaker {
+    /// Create a new closed circuit breaker.
+    pub fn new(settings: CircuitBreakerSettings) -> Self {
+        Self {
+            settings,
+            state: BreakerState::closed(),
+            last_seen: None,
+        }
+    }
+
+    /// Record a reading of the clocks, and log the jumps of the wall clock since the
+    /// previous reading.
+    pub fn observe(&mut self, now: Now) {
+        if let Some(jump) = self.last_seen.and_then(|last| now.wall_jump_since(last)) {
+            warn!(
+                "the wall clock jumped by {} ms, the circuit breaker is not affected",
+                jump
+            );
+        }
+        self.last_seen = Some(now);
+    }
+
+    /// Check whether a request can be sent at time `now`. If the backoff elapsed, the
+    /// circuit breaker becomes half-open and lets a probing request through.
+    pub fn allows_request(&mut self, now: Now) -> bool {
+        self.observe(now);
+        match self.state {
+            BreakerState::Open { until } if now.monotonic < until => false,
+            BreakerState::Open { .. } => {
+                info!("circuit breaker half-open: probing the coordinator");
+                self.state = BreakerState::HalfOpen;
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// Record the outcome of a request sent at time `now`.
+    pub fn record<T>(&mut self, result: &Result<T, Box<dyn Error>>, now: Now) {
+        self.observe(now);
+        match result {
+            Err(e) if is_server_error(e.as_ref()) => self.record_failure(now.monotonic),
+            _ => self.record_success(),
+        }
+    }
+
+    fn record_success(&mut self) {
+        if self.state == BreakerState::HalfOpen {
+            info!("coordinator recovered: circuit breaker closed");
+        }
+        self.state = BreakerState::closed();
+    }
+
+    fn record_failure(&mut self, now: u64) {
+        if self.settings.threshold == 0 {
+            return;
+        }
+
+        let (failures, window_end) = match self.state {
+            BreakerState::Closed {
+                failures,
+                window_end,
+            } if failures > 0 && now <= window_end => (failures + 1, window_end),
+            BreakerState::Closed { .. } => (1, now.saturating_add(millis(self.settings.window))),
+            BreakerState::HalfOpen => {
+                warn!("coordinator did not recover: circuit breaker re-opened");
+                self.open(now);
+                return;
+            }
+            // requests are short-circuited while the circuit breaker is open
+            BreakerState::Open { .. } => return,
+        };
+
+        if failures >= self.settings.threshold {
+            warn!(
+                "{} consecutive server errors: circuit breaker opened",
+                failures
+            );
+            self.open(now);
+        } else {
+            self.state = BreakerState::Closed {
+                failures,
+                window_end,
+            };
+        }
+    }
+
+    fn open(&mut self, now: u64) {
+        let until = now
+            .saturating_add(millis(self.settings.backoff))
+            .saturating_add(self.jitter());
+        self.state = BreakerState::Open { until };
+    }
+
+    fn jitter(&self) -> u64 {
+        match millis(self.settings.jitter) {
+            0 => 0,
+            max => rand::thread_rng().gen_range(0..=max),
+        }
+    }
+
+    /// Re-anchor the circuit breaker after it has been restored at time `now`,
+    /// possibly in another process.
+    ///
+    /// The time elapsed since the circuit breaker was last used is estimated with the
+    /// wall clock. If the wall clock moved backwards, no time is assumed to have
+    /// elapsed, so the remaining backoff never exceeds the one at the time the state was
+    /// saved. A forward jump of the wall clock can't be told apart from a device that
+    /// was turned off and at worst shortens the backoff.
+    ///
+    /// The jitter of an open circuit breaker is drawn once when it opens, hence the
+    /// backoff doesn't change no matter how often the state is saved and restored. A
+    /// half-open circuit breaker is re-opened with a random jitter instead. This prevents
+    /// the participants that are restored at the same time from all probing the
+    /// coordinator simultaneously.
+    pub fn restored(&mut self, now: Now) {
+        match self.last_seen {
+            Some(saved) => {
+                let elapsed = match now.wall_since(saved) {
+                    elapsed if elapsed < 0 => {
+                        warn!(
+                            "the wall clock is {} ms behind the last use of the circuit breaker: assuming that no time elapsed",
+                            -elapsed
+                        );
+                        0
+                    }
+                    elapsed => elapsed as u64,
+                };
+                // time left until the given timestamp of the saved state
+                let left = |timestamp: u64| {
+                    timestamp
+                        .saturating_sub(saved.monotonic)
+                        .saturating_sub(elapsed)
+                };
+                self.state = match self.state {
+                    BreakerState::Open { until } => BreakerState::Open {
+                        until: now.monotonic + left(until),
+                    },
+                    BreakerState::Closed {
+                        failures,
+                        window_end,
+                    } if left(window_end) > 0 => BreakerState::Closed {
+                        failures,
+                        window_end: now.monotonic + left(window_end),
+                    },
+                    BreakerState::Closed { .. } => BreakerState::closed(),
+                    BreakerState::HalfOpen => BreakerState::HalfOpen,
+                };
+            }
+            // the circuit breaker has never been used
+            None => self.state = BreakerState::closed(),
+        }
+        self.last_seen = Some(now);
+
+        if self.state == BreakerState::HalfOpen {
+            self.state = BreakerState::Open {
+                until: now.monotonic.saturating_add(self.jitter()),
+            }
+        }
+    }
+
+    /// Forget the clock readings of the circuit breaker, so that the states saved at
+    /// different times can be compared.
+    #[cfg(feature = "testutils")]
+    pub fn clear_clock_readings(&mut self) {
+        self.last_seen = None;
+        self.state = match self.state {
+            BreakerState::Closed { failures, .. } => BreakerState::Closed {
+                failures,
+                window_end: 0,
+            },
+            BreakerState::Open { .. } => BreakerState::Open { until: 0 },
+            BreakerState::HalfOpen => BreakerState::HalfOpen,
+        };
+    }
+
+    /// Let the backoff of an open circuit breaker elapse immediately.
+    #[cfg(test)]
+    pub fn elapse_backoff(&mut self) {
+        if let BreakerState::Open { .. } = self.state {
+            self.state = BreakerState::Open { until: 0 };
+        }
+    }
+
+    /// Get the state of the circuit breaker at time `now`.
+    pub fn state(&self, now: Now) -> CircuitState {
+        match self.state {
+            BreakerState::Closed { .. } => CircuitState::Closed,
+            BreakerState::Open { until } if now.monotonic < until => CircuitState::Open {
+                remaining: Duration::from_millis(until - now.monotonic),
+            },
+            BreakerState::Open { .. } | BreakerState::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// Check whether the given error is a server error.
+fn is_server_error(error: &(dyn Error + 'static)) -> bool {
+    match error.downcast_ref::<ClientError>() {
+        Some(ClientError::AllEndpointsFailed(_)) => true,
+        Some(error) => error.is_transient(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> CircuitBreakerSettings {
+        CircuitBreakerSettings {
+            threshold: 3,
+            window: Duration::from_secs(10),
+            backoff: Duration::from_secs(60),
+            jitter: Duration::from_secs(0),
+        }
+    }
+
+    /// Read the clocks at `monotonic`, with a wall clock that doesn't jump.
+    fn at(monotonic: u64) -> Now {
+        Now {
+            monotonic,
+            wall: 1_600_000_000_000 + monotonic,
+        }
+    }
+
+    fn server_error() -> Result<(), Box<dyn Error>> {
+        Err(Box::new(ClientError::UnexpectedResponse(503)))
+    }
+
+    fn client_error() -> Result<(), Box<dyn Error>> {
+        Err(Box::new(ClientError::UnexpectedResponse(404)))
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_server_errors() {
+        let mut breaker = CircuitBreaker::new(settings());
+        breaker.record(&server_error(), at(1_000));
+        breaker.record(&server_error(), at(2_000));
+        assert_eq!(breaker.state(at(2_000)), CircuitState::Closed);
+        breaker.record(&server_error(), at(3_000));
+        assert_eq!(
+            breaker.state(at(3_000)),
+            CircuitState::Open {
+                remaining: Duration::from_secs(60)
+            }
+        );
+        assert!(!breaker.allows_request(at(62_999)));
+        assert!(breaker.allows_request(at(63_000)));
+        assert_eq!(breaker.state(at(63_000)), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn test_streak_is_reset() {
+        let mut breaker = CircuitBreaker::new(settings());
+        breaker.record(&server_error(), at(1_000));
+        breaker.record(&server_error(), at(2_000));
+        // the coordinator responded, it is up
+        breaker.record(&client_error(), at(3_000));
+        breaker.record(&server_error(), at(4_000));
+        breaker.record(&server_error(), at(5_000));
+        assert_eq!(breaker.state(at(5_000)), CircuitState::Closed);
+
+        // the errors are not within the window
+        let mut breaker = CircuitBreaker::new(settings());
+        breaker.record(&server_error(), at(1_000));
+        breaker.record(&server_error(), at(2_000));
+        breaker.record(&server_error(), at(12_000));
+        assert_eq!(breaker.state(at(12_000)), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_rejections() {
+        // a coordinator with `api.debug_rejections` enabled explains its failures
+        let rejected = |status| -> Result<(), Box<dyn Error>> {
+            Err(Box::new(ClientError::Rejected(
+                status,
+                "reason".to_string(),
+            )))
+        };
+        let mut breaker = CircuitBreaker::new(settings());
+        for now in 0..3 {
+            breaker.record(&rejected(500), at(now));
+        }
+        assert!(matches!(breaker.state(at(3)), CircuitState::Open { .. }));
+
+        let mut breaker = CircuitBreaker::new(settings());
+        for now in 0..3 {
+            breaker.record(&rejected(400), at(now));
+        }
+        assert_eq!(breaker.state(at(3)), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let mut breaker = CircuitBreaker::new(settings());
+        for now in 0..3 {
+            breaker.record(&server_error(), at(now));
+        }
+        assert!(breaker.allows_request(at(60_002)));
+        breaker.record(&server_error(), at(60_002));
+        assert_eq!(
+            breaker.state(at(60_002)),
+            CircuitState::Open {
+                remaining: Duration::from_secs(60)
+            }
+        );
+
+        assert!(breaker.allows_request(at(120_002)));
+        breaker.record(&Ok(()), at(120_002));
+        assert_eq!(breaker.state(at(120_002)), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut breaker = CircuitBreaker::new(CircuitBreakerSettings::disabled());
+        for now in 0..100 {
+            breaker.record(&server_error(), at(now));
+            assert!(breaker.allows_request(at(now)));
+        }
+    }
+
+    #[test]
+    fn test_restored_with_jitter() {
+        let mut breaker = CircuitBreaker::new(CircuitBreakerSettings {
+            jitter: Duration::from_secs(30),
+            ..settings()
+        });
+        breaker.state = BreakerState::HalfOpen;
+        breaker.last_seen = Some(at(0));
+        breaker.restored(at(5_000));
+        let until = match breaker.state {
+            BreakerState::Open { until } => until,
+            state => panic!("unexpected state {:?}", state),
+        };
+        assert!((5_000..=35_000).contains(&until));
+
+        // the jitter is drawn once, when the circuit breaker re-opens
+        for _ in 0..10 {
+            breaker.restored(at(5_000));
+            assert_eq!(breaker.state, BreakerState::Open { until });
+        }
+    }
+
+    #[test]
+    fn test_jitter_is_stable_while_open() {
+        let mut breaker = CircuitBreaker::new(CircuitBreakerSettings {
+            jitter: Duration::from_secs(3_600),
+            ..settings()
+        });
+        for &now in &[1_000, 2_000, 3_000] {
+            breaker.record(&server_error(), at(now));
+        }
+        let remaining = match breaker.state(at(3_000)) {
+            CircuitState::Open { remaining } => remaining,
+            state => panic!("unexpected state {:?}", state),
+        };
+        assert!(remaining >= Duration::from_secs(60));
+
+        // neither the polls nor the restored states draw another jitter
+        for now in (3_000..10_000).step_by(1_000) {
+            assert!(!breaker.allows_request(at(now)));
+            breaker.restored(at(now));
+            assert_eq!(
+                breaker.state(at(now)),
+                CircuitState::Open {
+                    remaining: remaining - Duration::from_millis(now - 3_000)
+                }
+            );
+        }
+    }
+
+    /// Open a circuit breaker at `at(3_000)`, with a backoff until `at(63_000)`.
+    fn opened() -> CircuitBreaker {
+        let mut breaker = CircuitBreaker::new(settings());
+        for &now in &[1_000, 2_000, 3_000] {
+            breaker.record(&server_error(), at(now));
+        }
+        breaker
+    }
+
+    #[test]
+    fn test_wall_clock_jump_while_open() {
+        let mut breaker = opened();
+
+        // the wall clock moved 40 minutes backwards: the backoff is unchanged
+        let jumped = |monotonic| Now {
+            monotonic,
+            wall: at(monotonic).wall - 2_400_000,
+        };
+        assert!(!breaker.allows_request(jumped(10_000)));
+        assert_eq!(
+            breaker.state(jumped(10_000)),
+            CircuitState::Open {
+                remaining: Duration::from_secs(53)
+            }
+        );
+        assert!(breaker.allows_request(jumped(63_000)));
+
+        // the wall clock moved 40 minutes forwards: the backoff is unchanged
+        let mut breaker = opened();
+        let jumped = |monotonic| Now {
+            monotonic,
+            wall: at(monotonic).wall + 2_400_000,
+        };
+        assert!(!breaker.allows_request(jumped(10_000)));
+        assert!(!breaker.allows_request(jumped(62_999)));
+        assert!(breaker.allows_request(jumped(63_000)));
+    }
+
+    #[test]
+    fn test_restored_after_wall_clock_jumps() {
+        let saved = at(3_000);
+        // the monotonic clock of the process that restores the state starts over
+        let restored = |wall: i64| {
+            let mut breaker = opened();
+            let now = Now {
+                monotonic: 100,
+                wall: (saved.wall as i64 + wall) as u64,
+            };
+            breaker.restored(now);
+            breaker.state(now)
+        };
+
+        // 20 seconds elapsed
+        assert_eq!(
+            restored(20_000),
+            CircuitState::Open {
+                remaining: Duration::from_secs(40)
+            }
+        );
+        // the wall clock moved 40 minutes backwards: assume that no time elapsed
+        assert_eq!(
+            restored(-2_400_000),
+            CircuitState::Open {
+                remaining: Duration::from_secs(60)
+            }
+        );
+        // the wall clock moved an hour forwards: the backoff elapsed
+        assert_eq!(restored(3_600_000), CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn test_restored_failure_window() {
+        let restored = |elapsed: u64| {
+            let mut breaker = CircuitBreaker::new(settings());
+            breaker.record(&server_error(), at(1_000));
+            breaker.record(&server_error(), at(2_000));
+            let now = Now {
+                monotonic: 100,
+                wall: at(2_000).wall + elapsed,
+            };
+            breaker.restored(now);
+            breaker.record(&server_error(), now);
+            breaker.state(now)
+        };
+
+        // the third error is within the window
+        assert!(matches!(restored(5_000), CircuitState::Open { .. }));
+        // the window elapsed
+        assert_eq!(restored(20_000), CircuitState::Closed);
+    }
+}
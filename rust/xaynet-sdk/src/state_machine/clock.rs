@@ -0,0 +1,89 @@
+//! Clocks of the state machine.
+//!
+//! The durations are measured on a monotonic clock, which never jumps. The wall clock
+//! can jump backwards or forwards (NTP synchronization, manual changes), so it is only
+//! used to estimate the time elapsed while the state machine was saved, during which
+//! the monotonic clock readings are meaningless.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// Minimum drift between the wall clock and the monotonic clock that is considered a
+/// jump of the wall clock, in milliseconds.
+const JUMP_THRESHOLD: i64 = 60_000;
+
+/// Origin of the monotonic clock readings of the process.
+static ORIGIN: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// A reading of the clocks.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Now {
+    /// Milliseconds on the monotonic clock, since an arbitrary origin in the current
+    /// process.
+    pub monotonic: u64,
+    /// Milliseconds since the UNIX epoch on the wall clock.
+    pub wall: u64,
+}
+
+impl Now {
+    /// Get the number of milliseconds on the wall clock since the `earlier` reading,
+    /// which may be negative if the wall clock moved backwards.
+    pub fn wall_since(&self, earlier: Now) -> i64 {
+        (self.wall as i128 - earlier.wall as i128) as i64
+    }
+
+    /// Get the number of milliseconds the wall clock jumped since the `earlier`
+    /// reading of the same process, if it drifted from the monotonic clock by more than
+    /// a threshold.
+    pub fn wall_jump_since(&self, earlier: Now) -> Option<i64> {
+        let monotonic = self.monotonic.saturating_sub(earlier.monotonic) as i64;
+        let drift = self.wall_since(earlier).saturating_sub(monotonic);
+        if drift.abs() >= JUMP_THRESHOLD {
+            Some(drift)
+        } else {
+            None
+        }
+    }
+}
+
+/// Read the clocks.
+pub(crate) fn now() -> Now {
+    let monotonic = millis(ORIGIN.elapsed());
+    let wall = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(millis)
+        .unwrap_or(0);
+    Now { monotonic, wall }
+}
+
+pub(crate) fn millis(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wall_jump() {
+        let earlier = Now {
+            monotonic: 1_000,
+            wall: 10_000_000,
+        };
+        let later = |monotonic, wall| Now { monotonic, wall };
+
+        assert_eq!(later(11_000, 10_010_000).wall_jump_since(earlier), None);
+        assert_eq!(later(11_000, 10_030_000).wall_jump_since(earlier), None);
+        assert_eq!(
+            later(11_000, 10_070_000).wall_jump_since(earlier),
+            Some(60_000)
+        );
+        // the wall clock moved 40 minutes backwards
+        assert_eq!(
+            later(11_000, 7_610_000).wall_jump_since(earlier),
+            Some(-2_400_000)
+        );
+    }
+}
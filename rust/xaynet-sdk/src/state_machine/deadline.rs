@@ -0,0 +1,306 @@
+//! Deadline awareness of the state machine.
+//!
+//! The coordinator ends a phase somewhere between its minimum and maximum duration,
+//! depending on when enough messages arrived. A participant that starts an expensive step
+//! late in the phase (e.g. because it was scheduled late in the background) may not be
+//! able to send its message before the phase ends, in which case the work and the uploaded
+//! data are wasted.
+//!
+//! Before such steps, starting with the download of the dictionaries, the state machine
+//! compares the time it still needs to complete its task, plus a safety margin, with the
+//! end of the phase announced by the coordinator in the round metadata. The time needed is
+//! estimated from the remaining [`Work`]: the number of weights that remain to be masked
+//! resp. unmasked, at the compute throughput observed for the previous tasks, and the
+//! number of bytes that remain to be uploaded, at the upload throughput observed for the
+//! previous messages. Nothing is assumed about a throughput before it has been observed.
+//! The check is conservative: the task is only abandoned if the phase will certainly have
+//! ended, i.e. after its maximum duration. Whenever the deadline is unknown, the
+//! participant proceeds.
+
+use std::time::Duration;
+
+use xaynet_core::common::{PhaseName, RoundMetadata};
+
+use crate::state_machine::clock::{millis, Now};
+
+/// Weight of the latest observation in the throughput estimates.
+const THROUGHPUT_SMOOTHING: f64 = 0.5;
+
+/// The work that remains to complete a task.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Work {
+    /// The number of weights that remain to be masked or unmasked.
+    pub weights: u64,
+    /// The number of bytes that remain to be uploaded.
+    pub bytes: u64,
+}
+
+/// Estimates whether a task can be completed before the end of the phase.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Deadline {
+    /// Safety margin kept before the end of the phase. `None` disables the check.
+    pub margin: Option<Duration>,
+    /// Exponentially weighted average of the observed upload throughput, in bytes per
+    /// millisecond.
+    throughput: Option<f64>,
+    /// Exponentially weighted average of the observed compute throughput, in weights per
+    /// millisecond.
+    compute_throughput: Option<f64>,
+}
+
+/// Updates the exponentially weighted average `average` with the throughput observed for
+/// `amount` units of work done in `elapsed` milliseconds.
+fn record_throughput(average: &mut Option<f64>, amount: u64, elapsed: u64) {
+    // work that is too fast to be measured doesn't tell anything about the throughput
+    if elapsed == 0 {
+        return;
+    }
+    let observed = amount as f64 / elapsed as f64;
+    *average = Some(match *average {
+        Some(average) => THROUGHPUT_SMOOTHING * observed + (1.0 - THROUGHPUT_SMOOTHING) * average,
+        None => observed,
+    });
+}
+
+/// Estimates the number of milliseconds needed for `amount` units of work at the given
+/// throughput. Nothing is assumed as long as no throughput has been observed.
+fn estimate_time(throughput: Option<f64>, amount: u64) -> u64 {
+    match throughput {
+        Some(throughput) if throughput > 0.0 => (amount as f64 / throughput).ceil() as u64,
+        _ => 0,
+    }
+}
+
+impl Deadline {
+    /// Creates a new deadline estimator with the given safety margin.
+    pub fn new(margin: Option<Duration>) -> Self {
+        Self {
+            margin,
+            throughput: None,
+            compute_throughput: None,
+        }
+    }
+
+    /// Records that `bytes` have been uploaded in `elapsed` milliseconds.
+    pub fn record_upload(&mut self, bytes: usize, elapsed: u64) {
+        record_throughput(&mut self.throughput, bytes as u64, elapsed);
+    }
+
+    /// Records that `weights` have been masked or unmasked in `elapsed` milliseconds.
+    pub fn record_compute(&mut self, weights: usize, elapsed: u64) {
+        record_throughput(&mut self.compute_throughput, weights as u64, elapsed);
+    }
+
+    /// Estimates the number of milliseconds needed to complete the given `work`.
+    fn work_time(&self, work: Work) -> u64 {
+        estimate_time(self.compute_throughput, work.weights)
+            .saturating_add(estimate_time(self.throughput, work.bytes))
+    }
+
+    /// Checks whether a task which still has to do the given `work` in the given `phase` of
+    /// the coordinator can be completed in time, according to the coordinator `metadata`
+    /// read at time `now`.
+    ///
+    /// Returns `true` if the check is disabled, if the coordinator is in another phase than
+    /// expected or if the phase has no known maximum duration.
+    pub fn has_time_left(
+        &self,
+        metadata: &RoundMetadata,
+        phase: PhaseName,
+        work: Work,
+        now: Now,
+    ) -> bool {
+        let margin = match self.margin {
+            Some(margin) => margin,
+            None => return true,
+        };
+        if metadata.phase != phase {
+            return true;
+        }
+        let deadline = match metadata.phase_max_deadline() {
+            Some(deadline) => deadline.saturating_mul(1_000),
+            None => return true,
+        };
+        let completion = now
+            .wall
+            .saturating_add(millis(margin))
+            .saturating_add(self.work_time(work));
+        completion <= deadline
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use xaynet_core::common::PhaseDuration;
+
+    /// Metadata of an update phase which started at `1_600_000_000` and lasts at most
+    /// `60` seconds.
+    fn metadata(phase_duration: Option<PhaseDuration>) -> RoundMetadata {
+        RoundMetadata {
+            round_id: 1,
+            phase: PhaseName::Update,
+            phase_start: 1_600_000_000,
+            phase_duration,
+            compression: false,
+            changed_since_previous: true,
+        }
+    }
+
+    fn duration() -> Option<PhaseDuration> {
+        Some(PhaseDuration { min: 10, max: 60 })
+    }
+
+    /// Read the clocks `secs` seconds after the start of the phase.
+    fn at(secs: u64) -> Now {
+        Now {
+            monotonic: secs * 1_000,
+            wall: (1_600_000_000 + secs) * 1_000,
+        }
+    }
+
+    fn deadline(margin: u64) -> Deadline {
+        Deadline::new(Some(Duration::from_secs(margin)))
+    }
+
+    fn upload(bytes: u64) -> Work {
+        Work { weights: 0, bytes }
+    }
+
+    fn compute(weights: u64) -> Work {
+        Work { weights, bytes: 0 }
+    }
+
+    #[test]
+    fn test_proceed() {
+        let deadline = deadline(10);
+        assert!(deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            upload(1_000),
+            at(20)
+        ));
+        assert!(deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            upload(1_000),
+            at(50)
+        ));
+    }
+
+    #[test]
+    fn test_abandon() {
+        let deadline = deadline(10);
+        assert!(!deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            upload(1_000),
+            at(51)
+        ));
+        // the phase already ended
+        assert!(!deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            Work::default(),
+            at(90)
+        ));
+    }
+
+    #[test]
+    fn test_abandon_slow_upload() {
+        let mut deadline = deadline(10);
+        // 1 kB per second
+        deadline.record_upload(5_000, 5_000);
+        assert!(deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            upload(20_000),
+            at(30)
+        ));
+        assert!(!deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            upload(30_000),
+            at(30)
+        ));
+    }
+
+    #[test]
+    fn test_abandon_slow_compute() {
+        let mut deadline = deadline(10);
+        // 1k weights per second
+        deadline.record_compute(5_000, 5_000);
+        assert!(deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            compute(20_000),
+            at(30)
+        ));
+        assert!(!deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            compute(30_000),
+            at(30)
+        ));
+
+        // the compute and the upload time add up
+        deadline.record_upload(5_000, 5_000);
+        let work = Work {
+            weights: 10_000,
+            bytes: 10_000,
+        };
+        assert!(deadline.has_time_left(&metadata(duration()), PhaseName::Update, work, at(30)));
+        assert!(!deadline.has_time_left(&metadata(duration()), PhaseName::Update, work, at(31)));
+    }
+
+    #[test]
+    fn test_unknown_deadline() {
+        // the check is disabled
+        let disabled = Deadline::default();
+        assert!(disabled.has_time_left(
+            &metadata(duration()),
+            PhaseName::Update,
+            Work::default(),
+            at(90)
+        ));
+
+        let deadline = deadline(10);
+        // the phase has no known duration
+        assert!(deadline.has_time_left(
+            &metadata(None),
+            PhaseName::Update,
+            Work::default(),
+            at(90)
+        ));
+        // the coordinator is in another phase than expected
+        assert!(deadline.has_time_left(
+            &metadata(duration()),
+            PhaseName::Sum2,
+            Work::default(),
+            at(90)
+        ));
+    }
+
+    #[test]
+    fn test_throughput() {
+        let mut deadline = Deadline::default();
+        assert_eq!(deadline.work_time(upload(1_000)), 0);
+        deadline.record_upload(1_000, 0);
+        assert_eq!(deadline.work_time(upload(1_000)), 0);
+        deadline.record_upload(1_000, 1_000);
+        assert_eq!(deadline.work_time(upload(1_000)), 1_000);
+        deadline.record_upload(3_000, 1_000);
+        assert_eq!(deadline.work_time(upload(1_000)), 500);
+
+        // the compute throughput is estimated independently
+        assert_eq!(deadline.work_time(compute(1_000)), 0);
+        deadline.record_compute(2_000, 1_000);
+        assert_eq!(deadline.work_time(compute(1_000)), 500);
+        let work = Work {
+            weights: 1_000,
+            bytes: 1_000,
+        };
+        assert_eq!(deadline.work_time(work), 1_000);
+    }
+}
@@ -1,16 +1,17 @@
-use std::error::Error;
+use std::{error::Error, num::NonZeroU64, ops::Range};
 
 use async_trait::async_trait;
 
 use xaynet_core::{
-    common::RoundParameters,
+    common::{RoundMetadata, RoundParameters},
     mask::Model,
+    message::Tag,
     SumDict,
     SumParticipantPublicKey,
     UpdateSeedDict,
 };
 
-use crate::{ModelStore, Notify, XaynetClient};
+use crate::{ConsentRequest, LocalModelConfig, ModelStore, Notify, XaynetClient};
 
 /// Returned a dynamically dispatched [`IO`] object
 pub(crate) fn boxed_io<X, M, N>(
@@ -47,11 +48,28 @@ type DynModel = Box<(dyn std::convert::AsRef<xaynet_core::mask::Model> + Send)>;
 pub(crate) trait IO: Send + 'static {
     type Model;
 
-    /// Attempt to load the model from the store.
-    async fn load_model(&mut self) -> Result<Option<Self::Model>, Box<dyn Error>>;
+    /// Attempt to load the model from the store, given the configuration of the
+    /// model that is expected in the current round.
+    async fn load_model(
+        &mut self,
+        config: &LocalModelConfig,
+    ) -> Result<Option<Self::Model>, Box<dyn Error>>;
+    /// Return the number of weights in the chunks in which the store loads the model,
+    /// if it loads it in chunks
+    fn model_chunk_size(&self) -> Option<usize>;
+    /// Return the number of samples the local model was trained on, if the store knows it
+    fn model_sample_count(&self) -> Option<NonZeroU64>;
+    /// Attempt to load the weights of the model in the given range from the store
+    async fn load_model_chunk(
+        &mut self,
+        config: &LocalModelConfig,
+        range: Range<usize>,
+    ) -> Result<Option<Model>, Box<dyn Error>>;
 
     /// Fetch the round parameters from the coordinator
     async fn get_round_params(&mut self) -> Result<RoundParameters, Box<dyn Error>>;
+    /// Fetch the metadata of the current round from the coordinator
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Box<dyn Error>>;
     /// Fetch the sum dictionary from the coordinator
     async fn get_sums(&mut self) -> Result<Option<SumDict>, Box<dyn Error>>;
     /// Fetch the seed dictionary for the given sum participant from the coordinator
@@ -62,10 +80,19 @@ pub(crate) trait IO: Send + 'static {
     /// Fetch the latest global model from the coordinator
     async fn get_model(&mut self) -> Result<Option<Model>, Box<dyn Error>>;
     /// Send the given signed and encrypted PET message to the coordinator
-    async fn send_message(&mut self, msg: Vec<u8>) -> Result<(), Box<dyn Error>>;
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Box<dyn Error>>;
+    /// Observe the given signed PET message before it gets encrypted. This is only
+    /// relevant for replays (see [`replay()`]).
+    ///
+    /// [`replay()`]: crate::replay::replay
+    fn observe_message(&mut self, tag: Tag, msg: &[u8]);
 
     /// Notify the participant that a new round started
     fn notify_new_round(&mut self);
+    /// Notify the participant of the identifier of the new round
+    fn notify_round_id_changed(&mut self, round_id: u64);
+    /// Notify the participant of the training plan the new round belongs to
+    fn notify_training_plan(&mut self, plan_id: String);
     /// Notify the participant that they have been selected for the sum task for the current
     /// round
     fn notify_sum(&mut self);
@@ -78,6 +105,24 @@ pub(crate) trait IO: Send + 'static {
     /// Notify the participant that is is expected to provide a model to the state
     /// machine by loading it into the store
     fn notify_load_model(&mut self);
+    /// Notify the participant that the global mask is aggregated and awaits its
+    /// confirmation
+    fn notify_sum2_mask_ready(&mut self);
+    /// Notify the participant that it exceeded its update participation quota
+    fn notify_quota_exceeded(&mut self);
+    /// Notify the participant that it has been selected for a task and awaits the
+    /// consent of the user
+    fn notify_awaiting_consent(&mut self, request: ConsentRequest);
+    /// Notify the participant that it abandoned its task because there is not enough time
+    /// remaining to complete it before the end of the phase
+    fn notify_insufficient_time(&mut self);
+    /// Notify the participant that it abandoned the sum task because its ephemeral keys
+    /// don't match the ephemeral public key it sent
+    fn notify_ephemeral_keys_mismatch(&mut self);
+
+    /// Notify the participant that the global model didn't change since the previous
+    /// round
+    fn notify_model_unchanged(&mut self);
 }
 
 /// Internal struct that implements the [`IO`] trait. It is not used as is in the state
@@ -108,14 +153,36 @@ where
 {
     type Model = Box<dyn AsRef<Model> + Send>;
 
-    async fn load_model(&mut self) -> Result<Option<Self::Model>, Box<dyn Error>> {
+    async fn load_model(
+        &mut self,
+        config: &LocalModelConfig,
+    ) -> Result<Option<Self::Model>, Box<dyn Error>> {
         self.model_store
-            .load_model()
+            .load_model_with_config(config)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error>)
             .map(|opt| opt.map(|model| Box::new(model) as Box<dyn AsRef<Model> + Send>))
     }
 
+    fn model_chunk_size(&self) -> Option<usize> {
+        self.model_store.chunk_size()
+    }
+
+    fn model_sample_count(&self) -> Option<NonZeroU64> {
+        self.model_store.sample_count()
+    }
+
+    async fn load_model_chunk(
+        &mut self,
+        config: &LocalModelConfig,
+        range: Range<usize>,
+    ) -> Result<Option<Model>, Box<dyn Error>> {
+        self.model_store
+            .load_model_chunk(config, range)
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error>)
+    }
+
     async fn get_round_params(&mut self) -> Result<RoundParameters, Box<dyn Error>> {
         self.xaynet_client
             .get_round_params()
@@ -123,6 +190,13 @@ where
             .map_err(|e| Box::new(e) as Box<dyn Error>)
     }
 
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Box<dyn Error>> {
+        self.xaynet_client
+            .get_round_metadata()
+            .await
+            .map_err(|e| Box::new(e) as Box<dyn Error>)
+    }
+
     async fn get_sums(&mut self) -> Result<Option<SumDict>, Box<dyn Error>> {
         self.xaynet_client
             .get_sums()
@@ -147,17 +221,27 @@ where
             .map_err(|e| Box::new(e) as Box<dyn Error>)
     }
 
-    async fn send_message(&mut self, msg: Vec<u8>) -> Result<(), Box<dyn Error>> {
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Box<dyn Error>> {
         self.xaynet_client
-            .send_message(msg)
+            .send_message(tag, msg)
             .await
             .map_err(|e| Box::new(e) as Box<dyn Error>)
     }
 
+    fn observe_message(&mut self, _tag: Tag, _msg: &[u8]) {}
+
     fn notify_new_round(&mut self) {
         self.notifier.new_round()
     }
 
+    fn notify_round_id_changed(&mut self, round_id: u64) {
+        self.notifier.round_id_changed(round_id)
+    }
+
+    fn notify_training_plan(&mut self, plan_id: String) {
+        self.notifier.training_plan(plan_id)
+    }
+
     fn notify_sum(&mut self) {
         self.notifier.sum()
     }
@@ -173,20 +257,67 @@ where
     fn notify_load_model(&mut self) {
         self.notifier.load_model()
     }
+
+    fn notify_sum2_mask_ready(&mut self) {
+        self.notifier.sum2_mask_ready()
+    }
+
+    fn notify_quota_exceeded(&mut self) {
+        self.notifier.quota_exceeded()
+    }
+
+    fn notify_awaiting_consent(&mut self, request: ConsentRequest) {
+        self.notifier.awaiting_consent(request)
+    }
+
+    fn notify_insufficient_time(&mut self) {
+        self.notifier.insufficient_time()
+    }
+
+    fn notify_ephemeral_keys_mismatch(&mut self) {
+        self.notifier.ephemeral_keys_mismatch()
+    }
+
+    fn notify_model_unchanged(&mut self) {
+        self.notifier.model_unchanged()
+    }
 }
 
 #[async_trait]
 impl IO for Box<dyn IO<Model = Box<dyn AsRef<Model> + Send>>> {
     type Model = Box<dyn AsRef<Model> + Send>;
 
-    async fn load_model(&mut self) -> Result<Option<Self::Model>, Box<dyn Error>> {
-        self.as_mut().load_model().await
+    async fn load_model(
+        &mut self,
+        config: &LocalModelConfig,
+    ) -> Result<Option<Self::Model>, Box<dyn Error>> {
+        self.as_mut().load_model(config).await
+    }
+
+    fn model_chunk_size(&self) -> Option<usize> {
+        self.as_ref().model_chunk_size()
+    }
+
+    fn model_sample_count(&self) -> Option<NonZeroU64> {
+        self.as_ref().model_sample_count()
+    }
+
+    async fn load_model_chunk(
+        &mut self,
+        config: &LocalModelConfig,
+        range: Range<usize>,
+    ) -> Result<Option<Model>, Box<dyn Error>> {
+        self.as_mut().load_model_chunk(config, range).await
     }
 
     async fn get_round_params(&mut self) -> Result<RoundParameters, Box<dyn Error>> {
         self.as_mut().get_round_params().await
     }
 
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Box<dyn Error>> {
+        self.as_mut().get_round_metadata().await
+    }
+
     async fn get_sums(&mut self) -> Result<Option<SumDict>, Box<dyn Error>> {
         self.as_mut().get_sums().await
     }
@@ -202,14 +333,26 @@ impl IO for Box<dyn IO<Model = Box<dyn AsRef<Model> + Send>>> {
         self.as_mut().get_model().await
     }
 
-    async fn send_message(&mut self, msg: Vec<u8>) -> Result<(), Box<dyn Error>> {
-        self.as_mut().send_message(msg).await
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        self.as_mut().send_message(tag, msg).await
+    }
+
+    fn observe_message(&mut self, tag: Tag, msg: &[u8]) {
+        self.as_mut().observe_message(tag, msg)
     }
 
     fn notify_new_round(&mut self) {
         self.as_mut().notify_new_round()
     }
 
+    fn notify_round_id_changed(&mut self, round_id: u64) {
+        self.as_mut().notify_round_id_changed(round_id)
+    }
+
+    fn notify_training_plan(&mut self, plan_id: String) {
+        self.as_mut().notify_training_plan(plan_id)
+    }
+
     fn notify_sum(&mut self) {
         self.as_mut().notify_sum()
     }
@@ -225,4 +368,28 @@ impl IO for Box<dyn IO<Model = Box<dyn AsRef<Model> + Send>>> {
     fn notify_load_model(&mut self) {
         self.as_mut().notify_load_model()
     }
+
+    fn notify_sum2_mask_ready(&mut self) {
+        self.as_mut().notify_sum2_mask_ready()
+    }
+
+    fn notify_quota_exceeded(&mut self) {
+        self.as_mut().notify_quota_exceeded()
+    }
+
+    fn notify_awaiting_consent(&mut self, request: ConsentRequest) {
+        self.as_mut().notify_awaiting_consent(request)
+    }
+
+    fn notify_insufficient_time(&mut self) {
+        self.as_mut().notify_insufficient_time()
+    }
+
+    fn notify_ephemeral_keys_mismatch(&mut self) {
+        self.as_mut().notify_ephemeral_keys_mismatch()
+    }
+
+    fn notify_model_unchanged(&mut self) {
+        self.as_mut().notify_model_unchanged()
+    }
 }
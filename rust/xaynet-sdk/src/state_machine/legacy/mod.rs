@@ -0,0 +1,18 @@
+//! Frozen layouts of the states serialized by earlier versions of the SDK.
+//!
+//! A [`SerializableState`] is serialized with `bincode`, which is not self-describing:
+//! the fields are decoded by position, so a state can only be deserialized into the
+//! exact layout it was serialized from. Every time a field is added to the state, the
+//! previous layout is frozen here, with a conversion to the next layout. A state saved
+//! by an earlier version is deserialized into its frozen layout and converted step by
+//! step to a current [`SerializableState`].
+//!
+//! The frozen layouts must never be changed.
+//!
+//! [`SerializableState`]: crate::SerializableState
+
+pub mod v0;
+pub mod v1;
+pub mod v2;
+pub mod v3;
+pub mod v4;
@@ -0,0 +1,132 @@
+//! The layout of the states serialized by the first release of the SDK, before the
+//! circuit breaker, the confirmation of the global mask, the consent of the user and the
+//! round ID were part of the state.
+
+use serde::Deserialize;
+use xaynet_core::{
+    crypto::{EncryptKeyPair, Signature, SigningKeyPair},
+    mask::{MaskObject, MaskSeed, Scalar},
+    UpdateSeedDict,
+};
+
+use super::{v1, v2, v3::RoundParameters};
+use crate::{
+    settings::{MaxMessageSize, PetSettings},
+    state_machine::{Awaiting, CircuitBreaker, NewRound, SendingSum2, SendingUpdate, Sum, Update},
+    MessageEncoder,
+};
+
+/// State of the state machine.
+#[derive(Deserialize, Debug)]
+pub struct State<P> {
+    private: Box<P>,
+    shared: Box<SharedState>,
+}
+
+/// Store for all the data that are common to all the phases.
+#[derive(Deserialize, Debug)]
+struct SharedState {
+    keys: SigningKeyPair,
+    scalar: Scalar,
+    message_size: MaxMessageSize,
+    round_params: RoundParameters,
+}
+
+/// The state of the sum2 phase, without the confirmation of the global mask.
+#[derive(Deserialize, Debug)]
+pub struct Sum2 {
+    ephm_keys: EncryptKeyPair,
+    sum_signature: Signature,
+    seed_dict: Option<UpdateSeedDict>,
+    seeds: Option<Vec<MaskSeed>>,
+    mask: Option<MaskObject>,
+}
+
+/// The state of the sum sending phase, which transitions to the sum2 phase.
+#[derive(Deserialize, Debug)]
+pub struct SendingSum {
+    message: MessageEncoder,
+    failed: Option<Vec<u8>>,
+    next: Sum2,
+}
+
+/// A serialized state in this layout.
+#[derive(Deserialize, Debug)]
+pub enum SerializableState {
+    NewRound(State<NewRound>),
+    Awaiting(State<Awaiting>),
+    Sum(State<Sum>),
+    Update(State<Update>),
+    Sum2(State<Sum2>),
+    SendingSum(State<SendingSum>),
+    SendingUpdate(State<SendingUpdate>),
+    SendingSum2(State<SendingSum2>),
+}
+
+impl From<SharedState> for v1::SharedState {
+    fn from(shared: SharedState) -> Self {
+        // the settings that were not part of the state get their default values
+        let settings = PetSettings::new(shared.keys);
+        Self {
+            keys: settings.keys,
+            scalar: shared.scalar,
+            message_size: shared.message_size,
+            round_params: shared.round_params,
+            yield_interval: settings.yield_interval,
+            circuit_breaker: CircuitBreaker::new(settings.circuit_breaker),
+            confirm_sum2: settings.confirm_sum2,
+            require_consent: settings.require_consent,
+            consent_timeout: settings.consent_timeout,
+            round_id: 0,
+        }
+    }
+}
+
+impl From<Sum2> for v2::Sum2 {
+    fn from(sum2: Sum2) -> Self {
+        // the global mask was sent without a confirmation
+        Self {
+            ephm_keys: sum2.ephm_keys,
+            sum_signature: sum2.sum_signature,
+            seed_dict: sum2.seed_dict,
+            seeds: sum2.seeds,
+            mask: sum2.mask,
+            confirmed: false,
+        }
+    }
+}
+
+impl From<SendingSum> for v2::SendingSum {
+    fn from(sending: SendingSum) -> Self {
+        Self {
+            message: sending.message,
+            failed: sending.failed,
+            next: sending.next.into(),
+        }
+    }
+}
+
+impl<P> State<P> {
+    /// Convert the private state of the phase.
+    fn map<Q>(self, f: impl FnOnce(P) -> Q) -> v1::State<Q> {
+        v1::State {
+            private: Box::new(f(*self.private)),
+            shared: Box::new((*self.shared).into()),
+        }
+    }
+}
+
+impl From<SerializableState> for v1::SerializableState {
+    fn from(state: SerializableState) -> Self {
+        match state {
+            SerializableState::NewRound(state) => Self::NewRound(state.map(|p| p)),
+            SerializableState::Awaiting(state) => Self::Awaiting(state.map(|p| p)),
+            SerializableState::Sum(state) => Self::Sum(state.map(|p| p)),
+            SerializableState::Update(state) => Self::Update(state.map(|p| p)),
+            SerializableState::Sum2(state) => Self::Sum2(state.map(Into::into)),
+            SerializableState::SendingSum(state) => Self::SendingSum(state.map(Into::into)),
+            SerializableState::SendingUpdate(state) => Self::SendingUpdate(state.map(|p| p)),
+            SerializableState::SendingSum2(state) => Self::SendingSum2(state.map(|p| p)),
+        }
+    }
+}
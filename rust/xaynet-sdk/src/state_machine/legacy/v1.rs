@@ -0,0 +1,108 @@
+//! The layout of the states serialized before the signing keys of a participant could
+//! be rotated and before the randomness of a round was derived from a saved seed.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use xaynet_core::{crypto::SigningKeyPair, mask::Scalar};
+
+use super::{
+    v2::{self, SendingSum, Sum2},
+    v3::RoundParameters,
+};
+use crate::{
+    settings::MaxMessageSize,
+    state_machine::{
+        Awaiting,
+        AwaitingConsent,
+        CircuitBreaker,
+        NewRound,
+        SendingSum2,
+        SendingUpdate,
+        Sum,
+        Update,
+    },
+};
+
+/// State of the state machine.
+#[derive(Deserialize, Debug)]
+pub struct State<P> {
+    pub(super) private: Box<P>,
+    pub(super) shared: Box<SharedState>,
+}
+
+/// Store for all the data that are common to all the phases, without the keys of the
+/// next round and the seed of the current round.
+#[derive(Deserialize, Debug)]
+pub(super) struct SharedState {
+    pub(super) keys: SigningKeyPair,
+    pub(super) scalar: Scalar,
+    pub(super) message_size: MaxMessageSize,
+    pub(super) round_params: RoundParameters,
+    pub(super) yield_interval: usize,
+    pub(super) circuit_breaker: CircuitBreaker,
+    pub(super) confirm_sum2: bool,
+    pub(super) require_consent: bool,
+    pub(super) consent_timeout: Option<Duration>,
+    pub(super) round_id: u64,
+}
+
+/// A serialized state in this layout.
+#[derive(Deserialize, Debug)]
+pub enum SerializableState {
+    NewRound(State<NewRound>),
+    Awaiting(State<Awaiting>),
+    Sum(State<Sum>),
+    Update(State<Update>),
+    Sum2(State<Sum2>),
+    SendingSum(State<SendingSum>),
+    SendingUpdate(State<SendingUpdate>),
+    SendingSum2(State<SendingSum2>),
+    AwaitingConsent(State<AwaitingConsent>),
+}
+
+impl From<SharedState> for v2::SharedState {
+    fn from(shared: SharedState) -> Self {
+        // the keys were never rotated and there is no seed: the randomness of the current
+        // round is generated securely, like it was before the state was saved
+        Self {
+            keys: shared.keys,
+            scalar: shared.scalar,
+            message_size: shared.message_size,
+            round_params: shared.round_params,
+            yield_interval: shared.yield_interval,
+            circuit_breaker: shared.circuit_breaker,
+            confirm_sum2: shared.confirm_sum2,
+            require_consent: shared.require_consent,
+            consent_timeout: shared.consent_timeout,
+            round_id: shared.round_id,
+            next_keys: None,
+            task_seed: None,
+        }
+    }
+}
+
+impl<P> From<State<P>> for v2::State<P> {
+    fn from(state: State<P>) -> Self {
+        Self {
+            private: state.private,
+            shared: Box::new((*state.shared).into()),
+        }
+    }
+}
+
+impl From<SerializableState> for v2::SerializableState {
+    fn from(state: SerializableState) -> Self {
+        match state {
+            SerializableState::NewRound(state) => Self::NewRound(state.into()),
+            SerializableState::Awaiting(state) => Self::Awaiting(state.into()),
+            SerializableState::Sum(state) => Self::Sum(state.into()),
+            SerializableState::Update(state) => Self::Update(state.into()),
+            SerializableState::Sum2(state) => Self::Sum2(state.into()),
+            SerializableState::SendingSum(state) => Self::SendingSum(state.into()),
+            SerializableState::SendingUpdate(state) => Self::SendingUpdate(state.into()),
+            SerializableState::SendingSum2(state) => Self::SendingSum2(state.into()),
+            SerializableState::AwaitingConsent(state) => Self::AwaitingConsent(state.into()),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! The layout of the states serialized before the sum2 phase recorded the ephemeral
+//! public key that was sent in the sum message.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use xaynet_core::{
+    crypto::{EncryptKeyPair, Signature, SigningKeyPair},
+    mask::{MaskObject, MaskSeed, Scalar},
+    UpdateSeedDict,
+};
+
+use super::v3::{self, RoundParameters};
+use crate::{
+    settings::{DeterministicSeed, MaxMessageSize},
+    state_machine::{
+        phases,
+        Awaiting,
+        AwaitingConsent,
+        CircuitBreaker,
+        NewRound,
+        SendingSum2,
+        SendingUpdate,
+        Sum,
+        Update,
+    },
+    MessageEncoder,
+};
+
+/// State of the state machine.
+#[derive(Deserialize, Debug)]
+pub struct State<P> {
+    pub(super) private: Box<P>,
+    pub(super) shared: Box<SharedState>,
+}
+
+/// Store for all the data that are common to all the phases.
+#[derive(Deserialize, Debug)]
+pub(super) struct SharedState {
+    pub(super) keys: SigningKeyPair,
+    pub(super) scalar: Scalar,
+    pub(super) message_size: MaxMessageSize,
+    pub(super) round_params: RoundParameters,
+    pub(super) yield_interval: usize,
+    pub(super) circuit_breaker: CircuitBreaker,
+    pub(super) confirm_sum2: bool,
+    pub(super) require_consent: bool,
+    pub(super) consent_timeout: Option<Duration>,
+    pub(super) round_id: u64,
+    pub(super) next_keys: Option<SigningKeyPair>,
+    pub(super) task_seed: Option<DeterministicSeed>,
+}
+
+/// The state of the sum2 phase, without the ephemeral public key that was sent.
+#[derive(Deserialize, Debug)]
+pub struct Sum2 {
+    pub(super) ephm_keys: EncryptKeyPair,
+    pub(super) sum_signature: Signature,
+    pub(super) seed_dict: Option<UpdateSeedDict>,
+    pub(super) seeds: Option<Vec<MaskSeed>>,
+    pub(super) mask: Option<MaskObject>,
+    pub(super) confirmed: bool,
+}
+
+/// The state of the sum sending phase, which transitions to the sum2 phase.
+#[derive(Deserialize, Debug)]
+pub struct SendingSum {
+    pub(super) message: MessageEncoder,
+    pub(super) failed: Option<Vec<u8>>,
+    pub(super) next: Sum2,
+}
+
+/// A serialized state in this layout.
+#[derive(Deserialize, Debug)]
+pub enum SerializableState {
+    NewRound(State<NewRound>),
+    Awaiting(State<Awaiting>),
+    Sum(State<Sum>),
+    Update(State<Update>),
+    Sum2(State<Sum2>),
+    SendingSum(State<SendingSum>),
+    SendingUpdate(State<SendingUpdate>),
+    SendingSum2(State<SendingSum2>),
+    AwaitingConsent(State<AwaitingConsent>),
+}
+
+impl From<SharedState> for v3::SharedState {
+    fn from(shared: SharedState) -> Self {
+        Self {
+            keys: shared.keys,
+            scalar: shared.scalar,
+            message_size: shared.message_size,
+            round_params: shared.round_params,
+            yield_interval: shared.yield_interval,
+            circuit_breaker: shared.circuit_breaker,
+            confirm_sum2: shared.confirm_sum2,
+            require_consent: shared.require_consent,
+            consent_timeout: shared.consent_timeout,
+            round_id: shared.round_id,
+            next_keys: shared.next_keys,
+            task_seed: shared.task_seed,
+        }
+    }
+}
+
+impl From<Sum2> for phases::Sum2 {
+    fn from(sum2: Sum2) -> Self {
+        // the ephemeral keys were not replaced since the sum message was sent, so the
+        // ephemeral public key that was sent is the one of the keys
+        Self {
+            ephm_pk: sum2.ephm_keys.public,
+            ephm_keys: sum2.ephm_keys,
+            sum_signature: sum2.sum_signature,
+            seed_dict: sum2.seed_dict,
+            seeds: sum2.seeds,
+            mask: sum2.mask,
+            confirmed: sum2.confirmed,
+        }
+    }
+}
+
+impl From<SendingSum> for phases::SendingSum {
+    fn from(sending: SendingSum) -> Self {
+        Self {
+            message: sending.message,
+            failed: sending.failed,
+            next: sending.next.into(),
+        }
+    }
+}
+
+impl<P> State<P> {
+    /// Convert the private state of the phase.
+    fn map<Q>(self, f: impl FnOnce(P) -> Q) -> v3::State<Q> {
+        v3::State {
+            private: Box::new(f(*self.private)),
+            shared: Box::new((*self.shared).into()),
+        }
+    }
+}
+
+impl From<SerializableState> for v3::SerializableState {
+    fn from(state: SerializableState) -> Self {
+        match state {
+            SerializableState::NewRound(state) => Self::NewRound(state.map(|p| p)),
+            SerializableState::Awaiting(state) => Self::Awaiting(state.map(|p| p)),
+            SerializableState::Sum(state) => Self::Sum(state.map(|p| p)),
+            SerializableState::Update(state) => Self::Update(state.map(|p| p)),
+            SerializableState::Sum2(state) => Self::Sum2(state.map(Into::into)),
+            SerializableState::SendingSum(state) => Self::SendingSum(state.map(Into::into)),
+            SerializableState::SendingUpdate(state) => Self::SendingUpdate(state.map(|p| p)),
+            SerializableState::SendingSum2(state) => Self::SendingSum2(state.map(|p| p)),
+            SerializableState::AwaitingConsent(state) => Self::AwaitingConsent(state.map(|p| p)),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! The layout of the states serialized before the round parameters recorded the id of the
+//! training plan of the round.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use xaynet_core::{
+    common::RoundSeed,
+    crypto::SigningKeyPair,
+    mask::{MaskConfigPair, Scalar},
+    CoordinatorPublicKey,
+};
+
+use super::v4;
+use crate::{
+    settings::{DeterministicSeed, MaxMessageSize},
+    state_machine::{
+        Awaiting,
+        AwaitingConsent,
+        CircuitBreaker,
+        NewRound,
+        SendingSum,
+        SendingSum2,
+        SendingUpdate,
+        Sum,
+        Sum2,
+        Update,
+    },
+};
+
+/// The round parameters, without the id of the training plan.
+#[derive(Deserialize, Debug)]
+pub(super) struct RoundParameters {
+    pub(super) pk: CoordinatorPublicKey,
+    pub(super) sum: f64,
+    pub(super) update: f64,
+    pub(super) seed: RoundSeed,
+    pub(super) mask_config: MaskConfigPair,
+    pub(super) model_length: usize,
+}
+
+/// State of the state machine.
+#[derive(Deserialize, Debug)]
+pub struct State<P> {
+    pub(super) private: Box<P>,
+    pub(super) shared: Box<SharedState>,
+}
+
+/// Store for all the data that are common to all the phases.
+#[derive(Deserialize, Debug)]
+pub(super) struct SharedState {
+    pub(super) keys: SigningKeyPair,
+    pub(super) scalar: Scalar,
+    pub(super) message_size: MaxMessageSize,
+    pub(super) round_params: RoundParameters,
+    pub(super) yield_interval: usize,
+    pub(super) circuit_breaker: CircuitBreaker,
+    pub(super) confirm_sum2: bool,
+    pub(super) require_consent: bool,
+    pub(super) consent_timeout: Option<Duration>,
+    pub(super) round_id: u64,
+    pub(super) next_keys: Option<SigningKeyPair>,
+    pub(super) task_seed: Option<DeterministicSeed>,
+}
+
+/// A serialized state in this layout.
+#[derive(Deserialize, Debug)]
+pub enum SerializableState {
+    NewRound(State<NewRound>),
+    Awaiting(State<Awaiting>),
+    Sum(State<Sum>),
+    Update(State<Update>),
+    Sum2(State<Sum2>),
+    SendingSum(State<SendingSum>),
+    SendingUpdate(State<SendingUpdate>),
+    SendingSum2(State<SendingSum2>),
+    AwaitingConsent(State<AwaitingConsent>),
+}
+
+impl From<RoundParameters> for v4::RoundParameters {
+    fn from(params: RoundParameters) -> Self {
+        // the round was not part of a training plan, as far as the participant knows
+        Self {
+            pk: params.pk,
+            sum: params.sum,
+            update: params.update,
+            seed: params.seed,
+            mask_config: params.mask_config,
+            model_length: params.model_length,
+            plan_id: None,
+        }
+    }
+}
+
+impl From<SharedState> for v4::SharedState {
+    fn from(shared: SharedState) -> Self {
+        Self {
+            keys: shared.keys,
+            scalar: shared.scalar,
+            message_size: shared.message_size,
+            round_params: shared.round_params.into(),
+            yield_interval: shared.yield_interval,
+            circuit_breaker: shared.circuit_breaker,
+            confirm_sum2: shared.confirm_sum2,
+            require_consent: shared.require_consent,
+            consent_timeout: shared.consent_timeout,
+            round_id: shared.round_id,
+            next_keys: shared.next_keys,
+            task_seed: shared.task_seed,
+        }
+    }
+}
+
+impl<P> From<State<P>> for v4::State<P> {
+    fn from(state: State<P>) -> Self {
+        Self {
+            private: state.private,
+            shared: Box::new((*state.shared).into()),
+        }
+    }
+}
+
+impl From<SerializableState> for v4::SerializableState {
+    fn from(state: SerializableState) -> Self {
+        match state {
+            SerializableState::NewRound(state) => Self::NewRound(state.into()),
+            SerializableState::Awaiting(state) => Self::Awaiting(state.into()),
+            SerializableState::Sum(state) => Self::Sum(state.into()),
+            SerializableState::Update(state) => Self::Update(state.into()),
+            SerializableState::Sum2(state) => Self::Sum2(state.into()),
+            SerializableState::SendingSum(state) => Self::SendingSum(state.into()),
+            SerializableState::SendingUpdate(state) => Self::SendingUpdate(state.into()),
+            SerializableState::SendingSum2(state) => Self::SendingSum2(state.into()),
+            SerializableState::AwaitingConsent(state) => Self::AwaitingConsent(state.into()),
+        }
+    }
+}
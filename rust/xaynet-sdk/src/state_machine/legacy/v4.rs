@@ -0,0 +1,143 @@
+//! The layout of the states serialized before the round parameters recorded the sample
+//! count by which the update participants normalize their sample counts.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use xaynet_core::{
+    common::{RoundParameters as CurrentRoundParameters, RoundSeed},
+    crypto::SigningKeyPair,
+    mask::{MaskConfigPair, Scalar},
+    CoordinatorPublicKey,
+};
+
+use crate::{
+    settings::{DeterministicSeed, MaxMessageSize},
+    state_machine::{
+        phase,
+        Awaiting,
+        AwaitingConsent,
+        CircuitBreaker,
+        Deadline,
+        NewRound,
+        SendingSum,
+        SendingSum2,
+        SendingUpdate,
+        Sum,
+        Sum2,
+        Update,
+    },
+    SerializableState as CurrentState,
+};
+
+/// The round parameters, without the maximal sample count.
+#[derive(Deserialize, Debug)]
+pub(super) struct RoundParameters {
+    pub(super) pk: CoordinatorPublicKey,
+    pub(super) sum: f64,
+    pub(super) update: f64,
+    pub(super) seed: RoundSeed,
+    pub(super) mask_config: MaskConfigPair,
+    pub(super) model_length: usize,
+    pub(super) plan_id: Option<String>,
+}
+
+/// State of the state machine.
+#[derive(Deserialize, Debug)]
+pub struct State<P> {
+    pub(super) private: Box<P>,
+    pub(super) shared: Box<SharedState>,
+}
+
+/// Store for all the data that are common to all the phases.
+#[derive(Deserialize, Debug)]
+pub(super) struct SharedState {
+    pub(super) keys: SigningKeyPair,
+    pub(super) scalar: Scalar,
+    pub(super) message_size: MaxMessageSize,
+    pub(super) round_params: RoundParameters,
+    pub(super) yield_interval: usize,
+    pub(super) circuit_breaker: CircuitBreaker,
+    pub(super) confirm_sum2: bool,
+    pub(super) require_consent: bool,
+    pub(super) consent_timeout: Option<Duration>,
+    pub(super) round_id: u64,
+    pub(super) next_keys: Option<SigningKeyPair>,
+    pub(super) task_seed: Option<DeterministicSeed>,
+}
+
+/// A serialized state in this layout.
+#[derive(Deserialize, Debug)]
+pub enum SerializableState {
+    NewRound(State<NewRound>),
+    Awaiting(State<Awaiting>),
+    Sum(State<Sum>),
+    Update(State<Update>),
+    Sum2(State<Sum2>),
+    SendingSum(State<SendingSum>),
+    SendingUpdate(State<SendingUpdate>),
+    SendingSum2(State<SendingSum2>),
+    AwaitingConsent(State<AwaitingConsent>),
+}
+
+impl From<RoundParameters> for CurrentRoundParameters {
+    fn from(params: RoundParameters) -> Self {
+        // the participant normalizes its sample counts by its own maximal sample count
+        Self {
+            pk: params.pk,
+            sum: params.sum,
+            update: params.update,
+            seed: params.seed,
+            mask_config: params.mask_config,
+            model_length: params.model_length,
+            plan_id: params.plan_id,
+            max_sample_count: None,
+        }
+    }
+}
+
+impl From<SharedState> for phase::SharedState {
+    fn from(shared: SharedState) -> Self {
+        Self {
+            keys: shared.keys,
+            scalar: shared.scalar,
+            message_size: shared.message_size,
+            round_params: shared.round_params.into(),
+            yield_interval: shared.yield_interval,
+            circuit_breaker: shared.circuit_breaker,
+            confirm_sum2: shared.confirm_sum2,
+            require_consent: shared.require_consent,
+            consent_timeout: shared.consent_timeout,
+            deadline: Deadline::default(),
+            round_id: shared.round_id,
+            next_keys: shared.next_keys,
+            deterministic_seed: None,
+            task_seed: shared.task_seed,
+            dp: None,
+            compression: false,
+            coordinator_compression: None,
+        }
+    }
+}
+
+impl<P> From<State<P>> for phase::State<P> {
+    fn from(state: State<P>) -> Self {
+        Self::new(Box::new((*state.shared).into()), state.private)
+    }
+}
+
+impl From<SerializableState> for CurrentState {
+    fn from(state: SerializableState) -> Self {
+        match state {
+            SerializableState::NewRound(state) => Self::NewRound(state.into()),
+            SerializableState::Awaiting(state) => Self::Awaiting(state.into()),
+            SerializableState::Sum(state) => Self::Sum(state.into()),
+            SerializableState::Update(state) => Self::Update(state.into()),
+            SerializableState::Sum2(state) => Self::Sum2(state.into()),
+            SerializableState::SendingSum(state) => Self::SendingSum(state.into()),
+            SerializableState::SendingUpdate(state) => Self::SendingUpdate(state.into()),
+            SerializableState::SendingSum2(state) => Self::SendingSum2(state.into()),
+            SerializableState::AwaitingConsent(state) => Self::AwaitingConsent(state.into()),
+        }
+    }
+}
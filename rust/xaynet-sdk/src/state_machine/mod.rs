@@ -2,24 +2,49 @@
 // macro to be used in the other modules (until declarative macros are stable)
 #[macro_use]
 mod phase;
+mod circuit_breaker;
+mod clock;
+mod deadline;
 mod io;
+pub mod legacy;
 mod phases;
 #[allow(clippy::module_inception)]
 mod state_machine;
+#[cfg(feature = "testutils")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testutils")))]
+mod testutils;
 
 // It is useful to re-export everything within this module because
 // there are lot of interdependencies between all the sub-modules
 #[cfg(test)]
 use self::io::MockIO;
 use self::{
-    io::{boxed_io, IO},
-    phase::{IntoPhase, Phase, PhaseIo, Progress, SharedState, State, Step},
-    phases::{Awaiting, NewRound, SendingSum, SendingSum2, SendingUpdate, Sum, Sum2, Update},
+    io::boxed_io,
+    phase::{IntoPhase, Phase, Progress, SharedState, State, Step},
+    phases::{
+        Awaiting,
+        AwaitingConsent,
+        NewRound,
+        SendingSum,
+        SendingSum2,
+        SendingUpdate,
+        Sum,
+        Sum2,
+        Update,
+    },
 };
 
+pub(crate) use self::{
+    circuit_breaker::CircuitBreaker,
+    deadline::{Deadline, Work},
+    io::IO,
+    phase::PhaseIo,
+};
 pub use self::{
-    phase::{LocalModelConfig, SerializableState},
-    state_machine::{StateMachine, TransitionOutcome},
+    circuit_breaker::CircuitState,
+    phase::{CheckpointError, LocalModelConfig, RestoreError, SerializableState},
+    phases::{ConsentRequest, ConsentTask},
+    state_machine::{StateMachine, TransitionOutcome, WorkClass},
 };
 
 #[cfg(test)]
@@ -1,20 +1,39 @@
+use std::time::Duration;
+
 use async_trait::async_trait;
 use derive_more::From;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
-use super::{Awaiting, NewRound, SendingSum, SendingSum2, SendingUpdate, Sum, Sum2, Update, IO};
+use super::{
+    clock,
+    Awaiting,
+    AwaitingConsent,
+    CircuitBreaker,
+    Deadline,
+    NewRound,
+    SendingSum,
+    SendingSum2,
+    SendingUpdate,
+    Sum,
+    Sum2,
+    Update,
+    Work,
+    IO,
+};
 use crate::{
-    settings::{MaxMessageSize, PetSettings},
+    settings::{DeterministicSeed, DpConfig, InvalidDpConfig, MaxMessageSize, PetSettings},
     state_machine::{StateMachine, TransitionOutcome},
+    utils::cooperative::Yielder,
     MessageEncoder,
 };
 use xaynet_core::{
-    common::{RoundParameters, RoundSeed},
-    crypto::{ByteObject, PublicEncryptKey, SigningKeyPair},
-    mask::{self, DataType, MaskConfig, Model, Scalar},
-    message::Payload,
+    common::{PhaseName, RoundParameters, RoundSeed},
+    crypto::{ByteObject, EncryptKeyPair, PublicEncryptKey, SigningKeyPair},
+    mask::{self, DataType, EncryptedMaskSeed, MaskConfig, MaskSeed, Masker, Model, Scalar},
+    message::{Payload, Tag},
+    SumParticipantEphemeralPublicKey,
 };
 
 /// State of the state machine
@@ -33,6 +52,20 @@ impl<P> State<P> {
     }
 }
 
+impl<P> State<P>
+where
+    P: Serialize + DeserializeOwned,
+    State<P>: Into<SerializableState>,
+{
+    /// Copy the state into a serializable state. The private states are not `Clone`,
+    /// hence the copy goes through their serialized representation.
+    pub(crate) fn checkpoint(&self) -> Result<SerializableState, CheckpointError> {
+        let bytes = bincode::serialize(self)?;
+        let state: Self = bincode::deserialize(&bytes)?;
+        Ok(state.into())
+    }
+}
+
 /// A dynamically dispatched [`IO`] object.
 pub(crate) type PhaseIo = Box<dyn IO<Model = Box<dyn AsRef<Model> + Send>>>;
 
@@ -70,6 +103,56 @@ pub struct SharedState {
     pub message_size: MaxMessageSize,
     /// Current round parameters
     pub round_params: RoundParameters,
+    /// Number of elements to process in CPU heavy sections before yielding back to the
+    /// executor. `0` disables cooperative scheduling.
+    pub yield_interval: usize,
+    /// Circuit breaker for the requests to the coordinator.
+    pub(crate) circuit_breaker: CircuitBreaker,
+    /// Whether the global mask must be confirmed before the sum2 message is sent.
+    pub confirm_sum2: bool,
+    /// Whether the consent of the user is required before taking part in a task.
+    pub require_consent: bool,
+    /// How long to wait for the consent of the user before abandoning the task.
+    pub consent_timeout: Option<Duration>,
+    /// Estimator of whether a task can be completed before the end of the phase. It is
+    /// not part of the saved state, hence the safety margin must be set again after a
+    /// restore (see [`StateMachine::set_deadline_margin()`]).
+    #[serde(skip)]
+    pub(crate) deadline: Deadline,
+    /// Number of rounds the participant has observed. It is incremented every time the
+    /// coordinator publishes new round parameters, hence it increases monotonically
+    /// but it is unrelated to the round ID used internally by the coordinator.
+    pub round_id: u64,
+    /// Keys that replace [`SharedState::keys`] when the next round starts, if the keys
+    /// were rotated (see [`StateMachine::rotate_keys()`]). They are not applied right
+    /// away, so that all the messages of the current round are signed with the same keys.
+    pub(crate) next_keys: Option<SigningKeyPair>,
+    /// Seed from which the randomness of the participant is derived, if it runs in
+    /// deterministic mode. It is not part of the saved state, hence a restored participant
+    /// derives its randomness from the task seed instead.
+    #[serde(skip)]
+    pub(crate) deterministic_seed: Option<DeterministicSeed>,
+    /// Seed from which the randomness of the current round is derived, unless the
+    /// participant runs in deterministic mode. It is generated securely when a new round
+    /// is observed and, unlike the deterministic seed, it is part of the saved state:
+    /// two copies of the same saved state derive the same keys, mask and noise, hence
+    /// they compose the same messages and the coordinator never receives two different
+    /// messages for the same task.
+    pub(crate) task_seed: Option<DeterministicSeed>,
+    /// Differential privacy of the local model, which is clipped and noised when it is
+    /// masked. It is not part of the saved state either, hence it must be set again after a restore
+    /// (see [`StateMachine::set_dp_config()`]).
+    #[serde(skip)]
+    pub(crate) dp: Option<DpConfig>,
+    /// Whether the update and sum2 messages are compressed, if the coordinator accepts
+    /// it. It is not part of the saved state either (see
+    /// [`StateMachine::set_compression()`]).
+    #[serde(skip)]
+    pub(crate) compression: bool,
+    /// Whether the coordinator accepts compressed messages in the current round, if it
+    /// is known yet.
+    #[serde(skip)]
+    pub(crate) coordinator_compression: Option<bool>,
 }
 
 /// Get arbitrary round parameters. These round parameters are never used, we just
@@ -90,6 +173,8 @@ fn dummy_round_parameters() -> RoundParameters {
         }
         .into(),
         model_length: 0,
+        plan_id: None,
+        max_sample_count: None,
     }
 }
 
@@ -100,6 +185,92 @@ impl SharedState {
             scalar: settings.scalar,
             message_size: settings.max_message_size,
             round_params: dummy_round_parameters(),
+            yield_interval: settings.yield_interval,
+            circuit_breaker: CircuitBreaker::new(settings.circuit_breaker),
+            confirm_sum2: settings.confirm_sum2,
+            require_consent: settings.require_consent,
+            consent_timeout: settings.consent_timeout,
+            deadline: Deadline::new(settings.deadline_margin),
+            round_id: 0,
+            next_keys: None,
+            #[cfg(feature = "deterministic")]
+            deterministic_seed: settings.deterministic_seed,
+            #[cfg(not(feature = "deterministic"))]
+            deterministic_seed: None,
+            task_seed: None,
+            dp: settings.dp,
+            compression: settings.compression && cfg!(feature = "compression"),
+            coordinator_compression: None,
+        }
+    }
+
+    /// Gets the seed from which the randomness of the current round is derived, i.e. the
+    /// deterministic seed or else the task seed, if any.
+    fn seed(&self) -> Option<&DeterministicSeed> {
+        self.deterministic_seed.as_ref().or(self.task_seed.as_ref())
+    }
+
+    /// Derives the ephemeral keys of a sum participant from the seed, or generates them
+    /// if there is none.
+    pub(crate) fn ephm_keys(&self) -> EncryptKeyPair {
+        match self.seed() {
+            Some(seed) => seed.ephm_keys(&self.keys.public, &self.round_params.seed),
+            None => EncryptKeyPair::generate(),
+        }
+    }
+
+    /// Creates a masker with a mask seed derived from the seed, or with a random mask
+    /// seed if there is none. If the differential privacy step is enabled, the seed of
+    /// its noise is derived in the same way.
+    ///
+    /// # Errors
+    /// Fails if the differential privacy configuration is invalid.
+    pub(crate) fn masker(&self) -> Result<Masker, InvalidDpConfig> {
+        let config = self.round_params.mask_config;
+        match (self.seed(), self.dp) {
+            (Some(seed), Some(dp)) => Masker::with_seed_and_dp(
+                config,
+                seed.mask_seed(&self.keys.public, &self.round_params.seed),
+                dp,
+                seed.dp_seed(&self.keys.public, &self.round_params.seed),
+            ),
+            (Some(seed), None) => Ok(Masker::with_seed(
+                config,
+                seed.mask_seed(&self.keys.public, &self.round_params.seed),
+            )),
+            (None, Some(dp)) => Masker::new_with_dp(config, dp),
+            (None, None) => Ok(Masker::new(config)),
+        }
+    }
+
+    /// Encrypts the `mask_seed` for the sum participant with the ephemeral key `ephm_pk`.
+    pub(crate) fn encrypt_mask_seed(
+        &self,
+        mask_seed: &MaskSeed,
+        ephm_pk: &SumParticipantEphemeralPublicKey,
+    ) -> EncryptedMaskSeed {
+        match self.seed() {
+            Some(seed) => {
+                let key_seed = seed.encrypt_key_seed(
+                    &self.keys.public,
+                    &self.round_params.seed,
+                    ephm_pk.as_slice(),
+                );
+                mask_seed.encrypt_with_seed(ephm_pk, &key_seed)
+            }
+            None => mask_seed.encrypt(ephm_pk),
+        }
+    }
+
+    /// Encrypts a message for the coordinator.
+    pub(crate) fn encrypt_message(&self, data: &[u8]) -> Vec<u8> {
+        match self.seed() {
+            Some(seed) => {
+                let key_seed =
+                    seed.encrypt_key_seed(&self.keys.public, &self.round_params.seed, data);
+                self.round_params.pk.encrypt_with_seed(data, &key_seed)
+            }
+            None => self.round_params.pk.encrypt(data),
         }
     }
 }
@@ -158,11 +329,26 @@ where
     /// wasn't updated. In case `2.` and `3.` the updated state machine is returned
     /// wrapped in [`TransitionOutcome::Complete`].
     pub async fn step(mut self) -> TransitionOutcome {
+        if !self
+            .state
+            .shared
+            .circuit_breaker
+            .allows_request(clock::now())
+        {
+            debug!("circuit breaker is open, not sending any request to the coordinator");
+            return TransitionOutcome::Pending(self.into());
+        }
+
         match self.check_round_freshness().await {
             RoundFreshness::Unknown => TransitionOutcome::Pending(self.into()),
             RoundFreshness::Outdated => {
                 info!("a new round started: updating the round parameters and resetting the state machine");
                 self.io.notify_new_round();
+                self.io.notify_round_id_changed(self.state.shared.round_id);
+                if let Some(plan_id) = self.state.shared.round_params.plan_id.clone() {
+                    self.io.notify_training_plan(plan_id);
+                }
+                self.check_model_changed().await;
                 TransitionOutcome::Complete(
                     Phase::<NewRound>::new(
                         State::new(self.state.shared, Box::new(NewRound)),
@@ -181,7 +367,9 @@ where
     /// Check whether the coordinator has published new round parameters. In other
     /// words, this checks whether a new round has started.
     async fn check_round_freshness(&mut self) -> RoundFreshness {
-        match self.io.get_round_params().await {
+        let round_params = self.io.get_round_params().await;
+        self.record_request(&round_params);
+        match round_params {
             Err(e) => {
                 warn!("failed to fetch round parameters {:?}", e);
                 RoundFreshness::Unknown
@@ -193,6 +381,13 @@ where
                 } else {
                     info!("fetched fresh round parameters");
                     self.state.shared.round_params = params;
+                    self.state.shared.coordinator_compression = None;
+                    self.state.shared.round_id = self.state.shared.round_id.saturating_add(1);
+                    self.state.shared.task_seed = Some(DeterministicSeed::generate());
+                    if let Some(keys) = self.state.shared.next_keys.take() {
+                        info!("applying the rotated keys for the new round");
+                        self.state.shared.keys = keys;
+                    }
                     RoundFreshness::Outdated
                 }
             }
@@ -223,20 +418,135 @@ impl<P> Phase<P> {
     /// The encoder takes care of converting the given `payload` into one or several
     /// signed and encrypted PET messages.
     pub fn message_encoder(&self, payload: Payload) -> MessageEncoder {
-        MessageEncoder::new(
-            self.state.shared.keys.clone(),
+        let shared = &self.state.shared;
+        let message_id = shared.seed().map(|seed| {
+            let tag = match payload {
+                Payload::Sum(_) => Tag::Sum,
+                Payload::Update(_) => Tag::Update,
+                _ => Tag::Sum2,
+            };
+            seed.message_id(&shared.keys.public, &shared.round_params.seed, tag)
+        });
+        let max_payload_size = shared.message_size.max_payload_size().unwrap_or(0);
+        #[cfg(feature = "compression")]
+        let encoder = if shared.coordinator_compression == Some(true) {
+            MessageEncoder::new_compressed(
+                shared.keys.clone(),
+                payload,
+                shared.round_params.pk,
+                max_payload_size,
+            )
+        } else {
+            MessageEncoder::new(
+                shared.keys.clone(),
+                payload,
+                shared.round_params.pk,
+                max_payload_size,
+            )
+        };
+        #[cfg(not(feature = "compression"))]
+        let encoder = MessageEncoder::new(
+            shared.keys.clone(),
             payload,
-            self.state.shared.round_params.pk,
-            self.state
-                .shared
-                .message_size
-                .max_payload_size()
-                .unwrap_or(0),
-        )
+            shared.round_params.pk,
+            max_payload_size,
+        );
         // the encoder rejects Chunk payload, but in the state
         // machine, we never manually create such payloads so
         // unwrapping is fine
-        .unwrap()
+        let encoder = encoder.unwrap();
+        match message_id {
+            Some(message_id) => encoder.with_message_id(message_id),
+            None => encoder,
+        }
+    }
+
+    /// Record the outcome of a request to the coordinator in the circuit breaker.
+    pub(crate) fn record_request<T>(&mut self, result: &Result<T, Box<dyn std::error::Error>>) {
+        self.state
+            .shared
+            .circuit_breaker
+            .record(result, clock::now());
+    }
+
+    /// Check whether the task can be completed before the end of the given `phase` of the
+    /// coordinator, given the `work` that remains to be done (see [`Deadline`]). The
+    /// participant proceeds if the round metadata can't be fetched.
+    pub(crate) async fn has_time_left(&mut self, phase: PhaseName, work: Work) -> bool {
+        if self.state.shared.deadline.margin.is_none() {
+            return true;
+        }
+        let metadata = self.io.get_round_metadata().await;
+        self.record_request(&metadata);
+        match metadata {
+            Ok(metadata) => {
+                self.state
+                    .shared
+                    .deadline
+                    .has_time_left(&metadata, phase, work, clock::now())
+            }
+            Err(e) => {
+                warn!(
+                    "failed to fetch round metadata, assuming that there is time left: {:?}",
+                    e
+                );
+                true
+            }
+        }
+    }
+
+    /// Ask the coordinator whether it accepts compressed messages, unless the participant
+    /// doesn't compress its messages or already knows it for the current round. The
+    /// messages are sent uncompressed if the round metadata can't be fetched.
+    pub(crate) async fn negotiate_compression(&mut self) {
+        let shared = &self.state.shared;
+        if !shared.compression || shared.coordinator_compression.is_some() {
+            return;
+        }
+        let metadata = self.io.get_round_metadata().await;
+        self.record_request(&metadata);
+        match metadata {
+            Ok(metadata) => {
+                debug!(
+                    "coordinator accepts compressed messages: {}",
+                    metadata.compression
+                );
+                self.state.shared.coordinator_compression = Some(metadata.compression);
+            }
+            Err(e) => warn!(
+                "failed to fetch round metadata, sending uncompressed messages: {:?}",
+                e
+            ),
+        }
+    }
+
+    /// Ask the coordinator whether the global model changed since the previous round and
+    /// notify the participant if it didn't. The model is assumed to have changed if the
+    /// round metadata can't be fetched.
+    async fn check_model_changed(&mut self) {
+        let metadata = self.io.get_round_metadata().await;
+        self.record_request(&metadata);
+        match metadata {
+            Ok(metadata) if !metadata.changed_since_previous => {
+                info!("the global model didn't change since the previous round");
+                self.io.notify_model_unchanged();
+            }
+            Ok(_) => debug!("the global model changed since the previous round"),
+            Err(e) => warn!(
+                "failed to fetch round metadata, assuming that the global model changed: {:?}",
+                e
+            ),
+        }
+    }
+
+    /// Whether CPU heavy sections should be executed cooperatively.
+    pub(crate) fn is_cooperative(&self) -> bool {
+        self.state.shared.yield_interval != 0
+    }
+
+    /// Instantiate a [`Yielder`] for a CPU heavy section of the phase.
+    pub(crate) fn yielder(&self) -> Yielder {
+        Yielder::new(self.state.shared.yield_interval)
     }
 
     /// Return the local model configuration of the model that is expected in the update phase.
@@ -244,6 +554,8 @@ impl<P> Phase<P> {
         LocalModelConfig {
             data_type: self.state.shared.round_params.mask_config.vect.data_type,
             len: self.state.shared.round_params.model_length,
+            mask_config: self.state.shared.round_params.mask_config.vect,
+            round_id: self.state.shared.round_id,
         }
     }
 
@@ -266,7 +578,7 @@ impl<P> Phase<P> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 /// The local model configuration of the model that is expected in the update phase.
 pub struct LocalModelConfig {
     /// The expected data type of the local model.
@@ -276,12 +588,30 @@ pub struct LocalModelConfig {
     pub data_type: DataType,
     /// The expected length of the local model.
     pub len: usize,
+    /// The masking configuration of the local model in the current round.
+    pub mask_config: MaskConfig,
+    /// The identifier of the current round.
+    pub round_id: u64,
 }
 
 #[derive(Error, Debug)]
 #[error("failed to send a PET message")]
 pub struct SendMessageError;
 
+/// Error returned when a checkpoint of the state machine cannot be taken.
+#[derive(Error, Debug)]
+#[error("failed to checkpoint the state machine: {0}")]
+pub struct CheckpointError(#[from] bincode::Error);
+
+/// Error returned when the state machine cannot be restored from a checkpoint.
+#[derive(Error, Debug)]
+pub enum RestoreError {
+    #[error("the ephemeral secret key doesn't match the ephemeral public key")]
+    EphemeralKeys,
+    #[error("the global mask doesn't match the round parameters")]
+    Mask,
+}
+
 /// Round freshness indicator
 pub enum RoundFreshness {
     /// A new round started. The current round is outdated
@@ -310,6 +640,86 @@ pub enum SerializableState {
     SendingSum(State<SendingSum>),
     SendingUpdate(State<SendingUpdate>),
     SendingSum2(State<SendingSum2>),
+    AwaitingConsent(State<AwaitingConsent>),
+}
+
+impl SerializableState {
+    /// Get the shared state.
+    pub(crate) fn shared_mut(&mut self) -> &mut SharedState {
+        match self {
+            SerializableState::NewRound(ref mut state) => &mut state.shared,
+            SerializableState::Awaiting(ref mut state) => &mut state.shared,
+            SerializableState::AwaitingConsent(ref mut state) => &mut state.shared,
+            SerializableState::Sum(ref mut state) => &mut state.shared,
+            SerializableState::Update(ref mut state) => &mut state.shared,
+            SerializableState::Sum2(ref mut state) => &mut state.shared,
+            SerializableState::SendingSum(ref mut state) => &mut state.shared,
+            SerializableState::SendingUpdate(ref mut state) => &mut state.shared,
+            SerializableState::SendingSum2(ref mut state) => &mut state.shared,
+        }
+    }
+
+    /// Check that the state is consistent, so that a state machine restored from it can
+    /// continue the PET protocol.
+    pub(crate) fn check(&self) -> Result<(), RestoreError> {
+        match self {
+            SerializableState::Sum(ref state) => check_ephm_keys(&state.private.ephm_keys),
+            SerializableState::SendingSum(ref state) => {
+                state.private.next.check(&state.shared.round_params)
+            }
+            SerializableState::Sum2(ref state) => state.private.check(&state.shared.round_params),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stage a local model in the update phase, as if the state machine had loaded it
+    /// from its model store. A restored state machine then doesn't ask for the model
+    /// again.
+    ///
+    /// Returns `false`, and drops the model, if the state is not in the update phase or
+    /// if the state machine already loaded a model.
+    pub fn stage_local_model(&mut self, model: Model) -> bool {
+        match self {
+            SerializableState::Update(ref mut state) if !state.private.has_loaded_model() => {
+                state.private.model = Some(model.into());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Discard the local model of the update task, e.g. because the model has a different
+    /// length than the one of the restored state.
+    ///
+    /// In the update phase, the model and everything derived from it are dropped, and a
+    /// restored state machine asks for the model again. If the masked model is already
+    /// being sent, the update task is abandoned and the state machine waits for the next
+    /// round. Any other state is left unchanged.
+    pub fn discard_local_model(self) -> Self {
+        match self {
+            SerializableState::Update(mut state) => {
+                state.private.model = None;
+                state.private.mask = None;
+                state.private.seed_dict = None;
+                SerializableState::Update(state)
+            }
+            SerializableState::SendingUpdate(state) => {
+                warn!("abandoning the update task: the local model was discarded");
+                SerializableState::Awaiting(State::new(state.shared, Box::new(Awaiting)))
+            }
+            state => state,
+        }
+    }
+}
+
+/// Check that the ephemeral secret key of a sum participant derives its ephemeral public
+/// key.
+pub(crate) fn check_ephm_keys(keys: &EncryptKeyPair) -> Result<(), RestoreError> {
+    if keys.secret.public_key() == keys.public {
+        Ok(())
+    } else {
+        Err(RestoreError::EphemeralKeys)
+    }
 }
 
 impl<P> From<Phase<P>> for SerializableState
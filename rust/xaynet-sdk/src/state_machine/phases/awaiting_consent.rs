@@ -0,0 +1,247 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use xaynet_core::{
+    crypto::{ByteObject, PublicEncryptKey, Signature, SEALBYTES},
+    mask::MaskObject,
+    message::{
+        Sum as SumMessage,
+        Sum2 as Sum2Message,
+        Tag,
+        ToBytes,
+        Update as UpdateMessage,
+        MESSAGE_HEADER_LENGTH,
+    },
+    LocalSeedDict,
+};
+
+use crate::{
+    message_encoder::{ceiling_div, CHUNK_OVERHEAD},
+    state_machine::{
+        clock::{self, Now},
+        Awaiting,
+        IntoPhase,
+        Phase,
+        PhaseIo,
+        SharedState,
+        State,
+        StateMachine,
+        Step,
+        Sum,
+        TransitionOutcome,
+        Update,
+    },
+};
+
+/// A task that requires the consent of the user.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsentTask {
+    /// The participant has been selected for the sum task.
+    Sum,
+    /// The participant has been selected for the update task.
+    Update,
+}
+
+/// A request for the consent of the user to take part in a task.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsentRequest {
+    /// The task the participant has been selected for.
+    pub task: ConsentTask,
+    /// An estimate of the number of bytes the participant uploads for the task,
+    /// including the message headers and the encryption overhead. The local seed
+    /// dictionary of the update message grows with the number of sum participants, which
+    /// is unknown before the task starts, hence its entries are not part of the estimate.
+    pub upload_bytes: u64,
+}
+
+impl ConsentRequest {
+    /// Creates a consent request for the given task, in the current round.
+    pub(crate) fn new(shared: &SharedState, task: ConsentTask) -> Self {
+        Self {
+            task,
+            upload_bytes: estimate_upload(shared, task) as u64,
+        }
+    }
+}
+
+/// The state of the awaiting consent phase.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AwaitingConsent {
+    /// The pending consent request.
+    pub request: ConsentRequest,
+    /// Signature that proves that the participant has been selected
+    /// for the sum task.
+    pub sum_signature: Signature,
+    /// Signature that proves that the participant has been selected
+    /// for the update task. This is only set for the update task.
+    pub update_signature: Option<Signature>,
+    /// Milliseconds since the UNIX epoch on the wall clock, when the
+    /// consent was requested. The wall clock is used because the
+    /// timeout must survive saving and restoring the state machine.
+    pub requested_at: u64,
+    /// The decision of the user, if any.
+    pub consent: Option<bool>,
+}
+
+impl AwaitingConsent {
+    /// Creates a new awaiting consent state.
+    pub fn new(
+        request: ConsentRequest,
+        sum_signature: Signature,
+        update_signature: Option<Signature>,
+    ) -> Self {
+        Self {
+            request,
+            sum_signature,
+            update_signature,
+            requested_at: clock::now().wall,
+            consent: None,
+        }
+    }
+}
+
+impl IntoPhase<AwaitingConsent> for State<AwaitingConsent> {
+    fn into_phase(self, mut io: PhaseIo) -> Phase<AwaitingConsent> {
+        if self.private.consent.is_none() {
+            io.notify_awaiting_consent(self.private.request);
+        }
+        Phase::<_>::new(self, io)
+    }
+}
+
+#[async_trait]
+impl Step for Phase<AwaitingConsent> {
+    async fn step(mut self) -> TransitionOutcome {
+        info!("awaiting consent task");
+        match self.state.private.consent {
+            Some(true) => {
+                info!("consent granted");
+                TransitionOutcome::Complete(self.into_task())
+            }
+            Some(false) => {
+                info!("consent denied, going to awaiting phase");
+                let awaiting: Phase<Awaiting> = self.into();
+                TransitionOutcome::Complete(awaiting.into())
+            }
+            None if self.has_timed_out(clock::now()) => {
+                info!("consent timed out, going to awaiting phase");
+                let awaiting: Phase<Awaiting> = self.into();
+                TransitionOutcome::Complete(awaiting.into())
+            }
+            None => {
+                debug!("consent not given yet");
+                TransitionOutcome::Pending(self.into())
+            }
+        }
+    }
+}
+
+impl From<Phase<AwaitingConsent>> for Phase<Awaiting> {
+    fn from(awaiting_consent: Phase<AwaitingConsent>) -> Self {
+        State::new(awaiting_consent.state.shared, Box::new(Awaiting))
+            .into_phase(awaiting_consent.io)
+    }
+}
+
+impl Phase<AwaitingConsent> {
+    /// Checks if the consent is still pending.
+    pub(crate) fn is_pending(&self) -> bool {
+        self.state.private.consent.is_none()
+    }
+
+    /// Checks if the consent timeout elapsed at time `now`. If the
+    /// wall clock moved backwards, no time is assumed to have elapsed.
+    fn has_timed_out(&self, now: Now) -> bool {
+        match self.state.shared.consent_timeout {
+            Some(timeout) => {
+                let elapsed = now.wall.saturating_sub(self.state.private.requested_at);
+                elapsed >= clock::millis(timeout)
+            }
+            None => false,
+        }
+    }
+
+    /// Goes to the phase of the task the consent was granted for.
+    fn into_task(self) -> StateMachine {
+        let sum_signature = self.state.private.sum_signature;
+        match self.state.private.update_signature {
+            None => {
+                let ephm_keys = self.state.shared.ephm_keys();
+                let sum = Box::new(Sum::with_ephm_keys(sum_signature, ephm_keys));
+                State::new(self.state.shared, sum)
+                    .into_phase(self.io)
+                    .into()
+            }
+            Some(update_signature) => {
+                let update = Box::new(Update::new(sum_signature, update_signature));
+                State::new(self.state.shared, update)
+                    .into_phase(self.io)
+                    .into()
+            }
+        }
+    }
+}
+
+/// Estimates the number of bytes uploaded for the given task, given the
+/// round parameters and the maximum message size of the participant.
+fn estimate_upload(shared: &SharedState, task: ConsentTask) -> usize {
+    let tags: &[Tag] = match task {
+        ConsentTask::Sum => &[Tag::Sum, Tag::Sum2],
+        ConsentTask::Update => &[Tag::Update],
+    };
+    tags.iter()
+        .map(|tag| estimate_message_upload(shared, *tag))
+        .sum()
+}
+
+/// Estimates the number of bytes uploaded for the message with the given tag, given
+/// the round parameters and the maximum message size of the participant.
+pub(crate) fn estimate_message_upload(shared: &SharedState, tag: Tag) -> usize {
+    let config = shared.round_params.mask_config;
+    // the numbers of the masks are counted separately, to avoid
+    // allocating masks of the model length
+    let numbers = config.vect.bytes_per_number() * shared.round_params.model_length;
+    let payload = match tag {
+        Tag::Sum => SumMessage {
+            sum_signature: Signature::zeroed(),
+            ephm_pk: PublicEncryptKey::zeroed(),
+        }
+        .buffer_length(),
+        Tag::Sum2 => {
+            Sum2Message {
+                sum_signature: Signature::zeroed(),
+                model_mask: MaskObject::empty(config, 0),
+            }
+            .buffer_length()
+                + numbers
+        }
+        Tag::Update => {
+            UpdateMessage {
+                sum_signature: Signature::zeroed(),
+                update_signature: Signature::zeroed(),
+                masked_model: MaskObject::empty(config, 0),
+                local_seed_dict: LocalSeedDict::new(),
+            }
+            .buffer_length()
+                + numbers
+        }
+    };
+    encoded_length(payload, shared.message_size.max_payload_size())
+}
+
+/// Gets the number of bytes sent for a payload of the given length,
+/// which is split in chunks if it exceeds the maximum payload size
+/// (see [`MessageEncoder`]).
+///
+/// [`MessageEncoder`]: crate::MessageEncoder
+fn encoded_length(payload: usize, max_payload_size: Option<usize>) -> usize {
+    let overhead = MESSAGE_HEADER_LENGTH + SEALBYTES;
+    match max_payload_size {
+        Some(max_payload_size) if payload > max_payload_size => {
+            let chunk_size = max_payload_size - CHUNK_OVERHEAD;
+            let chunks = ceiling_div(payload, chunk_size);
+            payload + chunks * (CHUNK_OVERHEAD + overhead)
+        }
+        _ => payload + overhead,
+    }
+}
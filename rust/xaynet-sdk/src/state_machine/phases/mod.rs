@@ -1,12 +1,15 @@
 mod awaiting;
+mod awaiting_consent;
 mod new_round;
 mod sending;
 mod sum;
 mod sum2;
 mod update;
 
+pub(crate) use self::awaiting_consent::estimate_message_upload;
 pub use self::{
     awaiting::Awaiting,
+    awaiting_consent::{AwaitingConsent, ConsentRequest, ConsentTask},
     new_round::NewRound,
     sending::{SendingSum, SendingSum2, SendingUpdate},
     sum::Sum,
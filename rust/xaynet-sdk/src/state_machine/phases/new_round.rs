@@ -5,6 +5,9 @@ use xaynet_core::crypto::{ByteObject, Signature};
 
 use crate::state_machine::{
     Awaiting,
+    AwaitingConsent,
+    ConsentRequest,
+    ConsentTask,
     IntoPhase,
     Phase,
     PhaseIo,
@@ -34,6 +37,12 @@ impl Step for Phase<NewRound> {
         let sum_signature = self.sign(b"sum");
         if sum_signature.is_eligible(self.state.shared.round_params.sum) {
             info!("eligible for sum task");
+            if self.state.shared.require_consent {
+                return TransitionOutcome::Complete(
+                    self.into_awaiting_consent(ConsentTask::Sum, sum_signature, None)
+                        .into(),
+                );
+            }
             return TransitionOutcome::Complete(self.into_sum(sum_signature).into());
         }
 
@@ -41,6 +50,16 @@ impl Step for Phase<NewRound> {
         let update_signature = self.sign(b"update");
         if update_signature.is_eligible(self.state.shared.round_params.update) {
             info!("eligible for update task");
+            if self.state.shared.require_consent {
+                return TransitionOutcome::Complete(
+                    self.into_awaiting_consent(
+                        ConsentTask::Update,
+                        sum_signature,
+                        Some(update_signature),
+                    )
+                    .into(),
+                );
+            }
             return TransitionOutcome::Complete(
                 self.into_update(sum_signature, update_signature).into(),
             );
@@ -66,11 +85,28 @@ impl Phase<NewRound> {
     }
 
     fn into_sum(self, sum_signature: Signature) -> Phase<Sum> {
-        let sum = Box::new(Sum::new(sum_signature));
+        let ephm_keys = self.state.shared.ephm_keys();
+        let sum = Box::new(Sum::with_ephm_keys(sum_signature, ephm_keys));
         let state = State::new(self.state.shared, sum);
         state.into_phase(self.io)
     }
 
+    fn into_awaiting_consent(
+        self,
+        task: ConsentTask,
+        sum_signature: Signature,
+        update_signature: Option<Signature>,
+    ) -> Phase<AwaitingConsent> {
+        let request = ConsentRequest::new(&self.state.shared, task);
+        let awaiting_consent = Box::new(AwaitingConsent::new(
+            request,
+            sum_signature,
+            update_signature,
+        ));
+        let state = State::new(self.state.shared, awaiting_consent);
+        state.into_phase(self.io)
+    }
+
     fn into_update(self, sum_signature: Signature, update_signature: Signature) -> Phase<Update> {
         let update = Box::new(Update::new(sum_signature, update_signature));
         let state = State::new(self.state.shared, update);
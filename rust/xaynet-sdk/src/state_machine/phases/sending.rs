@@ -1,10 +1,13 @@
 use async_trait::async_trait;
 use paste::paste;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+use xaynet_core::message::Tag;
 
 use crate::{
+    client::ClientError,
     state_machine::{
+        clock,
         phases::Sum2,
         Awaiting,
         IntoPhase,
@@ -19,21 +22,30 @@ use crate::{
     MessageEncoder,
 };
 
+/// Checks whether the coordinator rejected a message because the participant exceeded its
+/// update participation quota.
+fn is_quota_exceeded(error: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        error.downcast_ref::<ClientError>(),
+        Some(ClientError::QuotaExceeded)
+    )
+}
+
 /// Implements the `SendingSum`, `SendingUpdate` and `SendingSum2` phases and transitions.
 macro_rules! impl_sending {
-    ($Phase: ty, $Next: ty, $phase: expr, $next: expr) => {
+    ($Phase: ty, $Next: ty, $tag: expr, $phase: expr, $next: expr) => {
         paste! {
             #[doc = "The state of the " $phase " sending phase."]
             #[derive(Serialize, Deserialize, Debug)]
             pub struct [<Sending $Phase>] {
                 /// The message to send.
-                message: MessageEncoder,
+                pub(crate) message: MessageEncoder,
 
                 /// Chunk that couldn't be sent and should be tried again.
-                failed: Option<Vec<u8>>,
+                pub(crate) failed: Option<Vec<u8>>,
 
                 /// State of the phase to transition to, after this one completes.
-                next: $Next,
+                pub(crate) next: $Next,
             }
 
             impl [<Sending $Phase>] {
@@ -76,11 +88,27 @@ macro_rules! impl_sending {
                 #[doc = "Tries to send a " $phase " message and reports back on the progress made."]
                 async fn try_send(mut self, data: Vec<u8>) -> Progress<[<Sending $Phase>]> {
                     info!("sending {} message (size = {})", $phase, data.len());
-                    if let Err(e) = self.io.send_message(data.clone()).await {
+                    let bytes = data.len();
+                    let start = clock::now();
+                    let sent = self.io.send_message($tag, data.clone()).await;
+                    self.record_request(&sent);
+                    if let Err(e) = sent {
+                        if is_quota_exceeded(e.as_ref()) {
+                            warn!(
+                                "{} message rejected: participation quota exceeded, going to awaiting phase",
+                                $phase
+                            );
+                            self.io.notify_quota_exceeded();
+                            let phase: Phase<Awaiting> =
+                                State::new(self.state.shared, Box::new(Awaiting)).into_phase(self.io);
+                            return Progress::Updated(phase.into());
+                        }
                         error!("failed to send {} message: {:?}", $phase, e);
                         self.state.private.failed = Some(data);
                         Progress::Stuck(self)
                     } else {
+                        let elapsed = clock::now().monotonic.saturating_sub(start.monotonic);
+                        self.state.shared.deadline.record_upload(bytes, elapsed);
                         Progress::Updated(self.into())
                     }
                 }
@@ -101,7 +129,8 @@ macro_rules! impl_sending {
                     } else {
                         match self.state.private.message.next() {
                             Some(data) => {
-                                let data = self.state.shared.round_params.pk.encrypt(data.as_slice());
+                                self.io.observe_message($tag, &data);
+                                let data = self.state.shared.encrypt_message(data.as_slice());
                                 self.try_send(data).await
                             }
                             None => {
@@ -116,6 +145,6 @@ macro_rules! impl_sending {
     }
 }
 
-impl_sending!(Sum, Sum2, "sum", "sum2");
-impl_sending!(Update, Awaiting, "update", "awaiting");
-impl_sending!(Sum2, Awaiting, "sum2", "awaiting");
+impl_sending!(Sum, Sum2, Tag::Sum, "sum", "sum2");
+impl_sending!(Update, Awaiting, Tag::Update, "update", "awaiting");
+impl_sending!(Sum2, Awaiting, Tag::Sum2, "sum2", "awaiting");
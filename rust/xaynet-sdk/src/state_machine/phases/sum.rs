@@ -32,6 +32,14 @@ impl Sum {
             sum_signature,
         }
     }
+
+    /// Creates a new sum state with the given ephemeral keys.
+    pub fn with_ephm_keys(sum_signature: Signature, ephm_keys: EncryptKeyPair) -> Self {
+        Sum {
+            ephm_keys,
+            sum_signature,
+        }
+    }
 }
 
 impl IntoPhase<Sum> for State<Sum> {
@@ -2,24 +2,32 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, warn};
 use xaynet_core::{
-    crypto::{EncryptKeyPair, Signature},
-    mask::{Aggregation, MaskObject, MaskSeed},
-    message::Sum2 as Sum2Message,
+    common::{PhaseName, RoundParameters},
+    crypto::{EncryptKeyPair, PublicEncryptKey, Signature},
+    mask::{Aggregation, AggregationError, MaskObject, MaskSeed},
+    message::{Sum2 as Sum2Message, Tag},
     UpdateSeedDict,
 };
 
 use crate::{
     state_machine::{
+        clock,
+        phase::check_ephm_keys,
+        phases::estimate_message_upload,
         IntoPhase,
         Phase,
         PhaseIo,
         Progress,
+        RestoreError,
         SendingSum2,
         State,
         Step,
         TransitionOutcome,
+        Work,
+        WorkClass,
         IO,
     },
+    utils::cooperative::run_blocking,
     MessageEncoder,
 };
 
@@ -31,6 +39,9 @@ pub struct Sum2 {
     /// The sum participant ephemeral keys. They are used to decrypt
     /// the encrypted mask seeds.
     pub ephm_keys: EncryptKeyPair,
+    /// The ephemeral public key that was signed and sent to the coordinator in the sum
+    /// message. The update participants encrypt their mask seeds with it.
+    pub ephm_pk: PublicEncryptKey,
     /// Signature that proves that the participant has been selected
     /// for the sum task.
     pub sum_signature: Signature,
@@ -42,17 +53,25 @@ pub struct Sum2 {
     /// The global mask, obtained by aggregating the masks derived
     /// from the mask seeds.
     pub mask: Option<MaskObject>,
+    /// Whether the global mask has been confirmed. This is only
+    /// relevant if [`SharedState::confirm_sum2`] is set.
+    ///
+    /// [`SharedState::confirm_sum2`]: crate::state_machine::SharedState::confirm_sum2
+    pub confirmed: bool,
 }
 
 impl Sum2 {
-    /// Creates a new sum2 state.
+    /// Creates a new sum2 state, for the ephemeral keys whose public key was sent in the
+    /// sum message.
     pub fn new(ephm_keys: EncryptKeyPair, sum_signature: Signature) -> Self {
         Self {
+            ephm_pk: ephm_keys.public,
             ephm_keys,
             sum_signature,
             seed_dict: None,
             seeds: None,
             mask: None,
+            confirmed: false,
         }
     }
 
@@ -61,6 +80,15 @@ impl Sum2 {
         self.seed_dict.is_some() || self.has_decrypted_seeds()
     }
 
+    /// Gets the number of mask seeds, if they have already been fetched but not aggregated.
+    fn seed_count(&self) -> Option<usize> {
+        match (&self.seed_dict, &self.seeds) {
+            (Some(seed_dict), _) => Some(seed_dict.len()),
+            (None, Some(seeds)) => Some(seeds.len()),
+            (None, None) => None,
+        }
+    }
+
     /// Checks if the seeds have already been decrypted.
     fn has_decrypted_seeds(&self) -> bool {
         self.seeds.is_some() || self.has_aggregated_masks()
@@ -70,11 +98,51 @@ impl Sum2 {
     fn has_aggregated_masks(&self) -> bool {
         self.mask.is_some()
     }
+
+    /// Checks if the ephemeral keys can decrypt the mask seeds encrypted with the
+    /// ephemeral public key that was sent.
+    fn holds_sent_ephm_keys(&self) -> bool {
+        self.ephm_keys.public == self.ephm_pk && self.ephm_keys.secret.public_key() == self.ephm_pk
+    }
+
+    /// Check that the state is consistent with the round parameters.
+    pub(crate) fn check(&self, round_params: &RoundParameters) -> Result<(), RestoreError> {
+        check_ephm_keys(&self.ephm_keys)?;
+        if self.ephm_keys.public != self.ephm_pk {
+            return Err(RestoreError::EphemeralKeys);
+        }
+        match self.mask {
+            Some(ref mask)
+                if mask.vect.config != round_params.mask_config.vect
+                    || mask.unit.config != round_params.mask_config.unit
+                    || mask.vect.data.len() != round_params.model_length
+                    || !mask.is_valid() =>
+            {
+                Err(RestoreError::Mask)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the kind of work that remains to be done in the sum2 phase.
+    pub(crate) fn pending_work(&self) -> WorkClass {
+        if !self.has_fetched_seed_dict() {
+            WorkClass::Download
+        } else if !self.has_aggregated_masks() {
+            WorkClass::Compute
+        } else {
+            WorkClass::Upload
+        }
+    }
 }
 
 impl IntoPhase<Sum2> for State<Sum2> {
     fn into_phase(self, io: PhaseIo) -> Phase<Sum2> {
-        Phase::<_>::new(self, io)
+        let mut phase = Phase::<_>::new(self, io);
+        if phase.is_awaiting_confirmation() {
+            phase.io.notify_sum2_mask_ready();
+        }
+        phase
     }
 }
 
@@ -82,9 +150,13 @@ impl IntoPhase<Sum2> for State<Sum2> {
 impl Step for Phase<Sum2> {
     async fn step(mut self) -> TransitionOutcome {
         info!("sum2 task");
+        self = try_progress!(self.check_ephm_keys());
+        self = try_progress!(self.check_deadline().await);
         self = try_progress!(self.fetch_seed_dict().await);
-        self = try_progress!(self.decrypt_seeds());
-        self = try_progress!(self.aggregate_masks());
+        self = try_progress!(self.decrypt_seeds().await);
+        self = try_progress!(self.aggregate_masks().await);
+        self = try_progress!(self.await_confirmation());
+        self.negotiate_compression().await;
         let sending: Phase<SendingSum2> = self.into();
         TransitionOutcome::Complete(sending.into())
     }
@@ -109,13 +181,57 @@ impl From<Phase<Sum2>> for Phase<Awaiting> {
 }
 
 impl Phase<Sum2> {
+    /// Check that the ephemeral keys still match the ephemeral public key that was sent to
+    /// the coordinator in the sum message.
+    ///
+    /// The update participants encrypt their mask seeds with that public key, so if the
+    /// keys got out of sync, for instance because the state was not properly restored,
+    /// none of the seeds could be decrypted. Instead of occupying a sum slot without
+    /// contributing anything, the participant gives up on the task.
+    pub(crate) fn check_ephm_keys(mut self) -> Progress<Sum2> {
+        if self.state.private.has_decrypted_seeds() || self.state.private.holds_sent_ephm_keys() {
+            return Progress::Continue(self);
+        }
+
+        error!("sum2 phase failed: ephemeral keys don't match the sent public key");
+        error!("going to awaiting phase");
+        self.io.notify_ephemeral_keys_mismatch();
+        let awaiting: Phase<Awaiting> = self.into();
+        Progress::Updated(awaiting.into())
+    }
+
+    /// Abandon the task if the sum2 message can't be sent before the end of the sum2
+    /// phase. The check runs before each step until the masks are aggregated, starting with
+    /// the download of the seeds. As long as the number of seeds is unknown, the mask of a
+    /// single update participant is assumed to be derived.
+    pub(crate) async fn check_deadline(mut self) -> Progress<Sum2> {
+        if self.state.private.has_aggregated_masks() {
+            return Progress::Continue(self);
+        }
+        let seeds = self.state.private.seed_count().unwrap_or(1);
+        let model_length = self.state.shared.round_params.model_length;
+        let work = Work {
+            weights: seeds.saturating_mul(model_length) as u64,
+            bytes: estimate_message_upload(&self.state.shared, Tag::Sum2) as u64,
+        };
+        if self.has_time_left(PhaseName::Sum2, work).await {
+            return Progress::Continue(self);
+        }
+        warn!("insufficient time remaining in the sum2 phase, going to awaiting phase");
+        self.io.notify_insufficient_time();
+        let awaiting: Phase<Awaiting> = self.into();
+        Progress::Updated(awaiting.into())
+    }
+
     /// Retrieve the encrypted mask seeds.
     pub(crate) async fn fetch_seed_dict(mut self) -> Progress<Sum2> {
         if self.state.private.has_fetched_seed_dict() {
             return Progress::Continue(self);
         }
         debug!("polling for update seeds");
-        match self.io.get_seeds(self.state.shared.keys.public).await {
+        let seed_dict = self.io.get_seeds(self.state.shared.keys.public).await;
+        self.record_request(&seed_dict);
+        match seed_dict {
             Err(e) => {
                 warn!("failed to fetch seeds: {}", e);
                 Progress::Stuck(self)
@@ -132,23 +248,28 @@ impl Phase<Sum2> {
     }
 
     /// Decrypt the mask seeds that the update participants generated.
-    pub(crate) fn decrypt_seeds(mut self) -> Progress<Sum2> {
+    pub(crate) async fn decrypt_seeds(mut self) -> Progress<Sum2> {
         if self.state.private.has_decrypted_seeds() {
             return Progress::Continue(self);
         }
 
-        let keys = &self.state.private.ephm_keys;
+        let mut yielder = self.yielder();
         // UNWRAP_SAFE: the seed dict is set in
         // `self.fetch_seed_dict()` which is called before this method
-        let seeds: Result<Vec<MaskSeed>, ()> = self
-            .state
-            .private
-            .seed_dict
-            .take()
-            .unwrap()
-            .into_iter()
-            .map(|(_, seed)| seed.decrypt(&keys.public, &keys.secret).map_err(|_| ()))
-            .collect();
+        let seed_dict = self.state.private.seed_dict.take().unwrap();
+        let mut seeds: Result<Vec<MaskSeed>, ()> = Ok(Vec::with_capacity(seed_dict.len()));
+        for (_, seed) in seed_dict.into_iter() {
+            let keys = &self.state.private.ephm_keys;
+            match seed.decrypt(&keys.public, &keys.secret) {
+                // UNWRAP_SAFE: we stop iterating as soon as a decryption fails
+                Ok(seed) => seeds.as_mut().unwrap().push(seed),
+                Err(_) => {
+                    seeds = Err(());
+                    break;
+                }
+            }
+            yielder.tick().await;
+        }
 
         match seeds {
             Ok(seeds) => {
@@ -167,7 +288,7 @@ impl Phase<Sum2> {
     /// Derive the masks from the decrypted mask seeds, and aggregate
     /// them. The resulting mask will later be added to the sum2
     /// message to be sent to the coordinator.
-    pub(crate) fn aggregate_masks(mut self) -> Progress<Sum2> {
+    pub(crate) async fn aggregate_masks(mut self) -> Progress<Sum2> {
         if self.state.private.has_aggregated_masks() {
             return Progress::Continue(self);
         }
@@ -175,21 +296,57 @@ impl Phase<Sum2> {
         info!("aggregating masks");
         let config = self.state.shared.round_params.mask_config;
         let mask_len = self.state.shared.round_params.model_length;
-        let mut mask_agg = Aggregation::new(config, mask_len as usize);
         // UNWRAP_SAFE: the seeds are set in `decrypt_seeds()` which is called before this method
-        for seed in self.state.private.seeds.take().unwrap().into_iter() {
-            let mask = seed.derive_mask(mask_len as usize, config);
-            if let Err(e) = mask_agg.validate_aggregation(&mask) {
+        let seeds = self.state.private.seeds.take().unwrap();
+        let weights = seeds.len().saturating_mul(mask_len);
+        let start = clock::now();
+        let aggregation = run_blocking(self.is_cooperative(), move || {
+            let mut mask_agg = Aggregation::new(config, mask_len);
+            for seed in seeds.into_iter() {
+                let mask = seed.derive_mask(mask_len, config);
+                mask_agg.validate_aggregation(&mask)?;
+                mask_agg.aggregate(mask);
+            }
+            Ok::<_, AggregationError>(mask_agg)
+        })
+        .await;
+
+        match aggregation {
+            Ok(mask_agg) => {
+                let elapsed = clock::now().monotonic.saturating_sub(start.monotonic);
+                self.state.shared.deadline.record_compute(weights, elapsed);
+                self.state.private.mask = Some(mask_agg.into());
+                if self.is_awaiting_confirmation() {
+                    self.io.notify_sum2_mask_ready();
+                }
+                Progress::Updated(self.into())
+            }
+            Err(e) => {
                 error!("sum2 phase failed: cannot aggregate masks: {}", e);
                 error!("going to awaiting phase");
                 let awaiting: Phase<Awaiting> = self.into();
-                return Progress::Updated(awaiting.into());
-            } else {
-                mask_agg.aggregate(mask);
+                Progress::Updated(awaiting.into())
             }
         }
-        self.state.private.mask = Some(mask_agg.into());
-        Progress::Updated(self.into())
+    }
+
+    /// Wait for the global mask to be confirmed, if the participant
+    /// is configured to do so.
+    pub(crate) fn await_confirmation(self) -> Progress<Sum2> {
+        if self.is_awaiting_confirmation() {
+            debug!("global mask not confirmed yet");
+            Progress::Stuck(self)
+        } else {
+            Progress::Continue(self)
+        }
+    }
+
+    /// Checks if the global mask is aggregated but not confirmed
+    /// yet.
+    pub(crate) fn is_awaiting_confirmation(&self) -> bool {
+        self.state.shared.confirm_sum2
+            && self.state.private.has_aggregated_masks()
+            && !self.state.private.confirmed
     }
 
     /// Creates and encodes the sum2 message from the sum2 state.
@@ -1,4 +1,4 @@
-use std::ops::Deref;
+use std::{num::NonZeroU64, ops::Deref};
 
 use async_trait::async_trait;
 use derive_more::From;
@@ -6,9 +6,10 @@ use serde::{Deserialize, Serialize};
 use tracing::{debug, info, warn};
 
 use xaynet_core::{
+    common::PhaseName,
     crypto::Signature,
-    mask::{MaskObject, MaskSeed, Masker, Model},
-    message::Update as UpdateMessage,
+    mask::{MaskObject, MaskSeed, Model, Scalar},
+    message::{Tag, Update as UpdateMessage},
     LocalSeedDict,
     ParticipantTaskSignature,
     SumDict,
@@ -16,6 +17,8 @@ use xaynet_core::{
 
 use crate::{
     state_machine::{
+        clock,
+        phases::estimate_message_upload,
         Awaiting,
         IntoPhase,
         Phase,
@@ -25,9 +28,13 @@ use crate::{
         State,
         Step,
         TransitionOutcome,
+        Work,
+        WorkClass,
         IO,
     },
+    utils::cooperative::run_blocking,
     MessageEncoder,
+    MAX_SAMPLE_COUNT,
 };
 
 #[derive(From)]
@@ -106,7 +113,7 @@ impl Update {
         self.sum_dict.is_some() || self.has_loaded_model()
     }
 
-    fn has_loaded_model(&self) -> bool {
+    pub(crate) fn has_loaded_model(&self) -> bool {
         self.model.is_some() || self.has_masked_model()
     }
 
@@ -117,6 +124,19 @@ impl Update {
     fn has_built_seed_dict(&self) -> bool {
         self.seed_dict.is_some()
     }
+
+    /// Return the kind of work that remains to be done in the update phase.
+    pub(crate) fn pending_work(&self) -> WorkClass {
+        if !self.has_fetched_sum_dict() {
+            WorkClass::Download
+        } else if !self.has_loaded_model() {
+            WorkClass::Train
+        } else if !self.has_built_seed_dict() {
+            WorkClass::Compute
+        } else {
+            WorkClass::Upload
+        }
+    }
 }
 
 impl IntoPhase<Update> for State<Update> {
@@ -132,10 +152,12 @@ impl IntoPhase<Update> for State<Update> {
 #[async_trait]
 impl Step for Phase<Update> {
     async fn step(mut self) -> TransitionOutcome {
+        self = try_progress!(self.check_deadline().await);
         self = try_progress!(self.fetch_sum_dict().await);
         self = try_progress!(self.load_model().await);
-        self = try_progress!(self.mask_model());
-        self = try_progress!(self.build_seed_dict());
+        self = try_progress!(self.mask_model().await);
+        self = try_progress!(self.build_seed_dict().await);
+        self.negotiate_compression().await;
         let sending: Phase<SendingUpdate> = self.into();
         TransitionOutcome::Complete(sending.into())
     }
@@ -166,7 +188,9 @@ impl Phase<Update> {
             return Progress::Continue(self);
         }
         debug!("fetching sum dictionary");
-        match self.io.get_sums().await {
+        let sum_dict = self.io.get_sums().await;
+        self.record_request(&sum_dict);
+        match sum_dict {
             Ok(Some(dict)) => {
                 self.state.private.sum_dict = Some(dict);
                 Progress::Updated(self.into())
@@ -187,9 +211,14 @@ impl Phase<Update> {
             debug!("already loaded the model, continuing");
             return Progress::Continue(self);
         }
+        if self.io.model_chunk_size().is_some() {
+            debug!("the model is loaded in chunks while it is masked, continuing");
+            return Progress::Continue(self);
+        }
 
         debug!("loading local model");
-        match self.io.load_model().await {
+        let config = self.local_model_config();
+        match self.io.load_model(&config).await {
             Ok(Some(model)) => {
                 self.state.private.model = Some(model.into());
                 Progress::Updated(self.into())
@@ -205,40 +234,165 @@ impl Phase<Update> {
         }
     }
 
+    /// Abandon the task if the update message can't be sent before the end of the update
+    /// phase. The check runs before each step until the seed dictionary is built, starting
+    /// with the download of the sum dictionary, hence also while the local model is trained.
+    pub(crate) async fn check_deadline(mut self) -> Progress<Update> {
+        if self.state.private.has_built_seed_dict() {
+            return Progress::Continue(self);
+        }
+        let weights = if self.state.private.has_masked_model() {
+            0
+        } else {
+            self.state.shared.round_params.model_length as u64
+        };
+        let work = Work {
+            weights,
+            bytes: estimate_message_upload(&self.state.shared, Tag::Update) as u64,
+        };
+        if self.has_time_left(PhaseName::Update, work).await {
+            return Progress::Continue(self);
+        }
+        warn!("insufficient time remaining in the update phase, going to awaiting phase");
+        self.io.notify_insufficient_time();
+        let awaiting: Phase<Awaiting> = self.into();
+        Progress::Updated(awaiting.into())
+    }
+
     /// Generate a mask seed and mask a local model.
-    pub(crate) fn mask_model(mut self) -> Progress<Update> {
+    pub(crate) async fn mask_model(mut self) -> Progress<Update> {
         if self.state.private.has_masked_model() {
             debug!("already computed the masked model, continuing");
             return Progress::Continue(self);
         }
+        if self.state.private.model.is_none() {
+            // the model is loaded in chunks, per the `load_model()` check, unless the
+            // store stopped doing so since then
+            return match self.io.model_chunk_size() {
+                Some(chunk_size) => self.mask_model_chunks(chunk_size).await,
+                None => Progress::Stuck(self),
+            };
+        }
         info!("computing masked model");
-        let config = self.state.shared.round_params.mask_config;
-        let masker = Masker::new(config);
-        // UNWRAP_SAFE: the model is set, per the `has_masked_model()` check above
+        let masker = match self.state.shared.masker() {
+            Ok(masker) => masker,
+            Err(e) => {
+                warn!("invalid differential privacy settings: {}", e);
+                return Progress::Stuck(self);
+            }
+        };
+        // UNWRAP_SAFE: the model is set, per the check above
         let model = self.state.private.model.take().unwrap();
-        let scalar = self.state.shared.scalar.clone();
-        self.state.private.mask = Some(masker.mask(scalar, model.as_ref()));
+        let weights = model.as_ref().len();
+        let scalar = self.scalar();
+        let start = clock::now();
+        let mask = run_blocking(self.is_cooperative(), move || {
+            masker.mask(scalar, model.as_ref())
+        })
+        .await;
+        let elapsed = clock::now().monotonic.saturating_sub(start.monotonic);
+        self.state.shared.deadline.record_compute(weights, elapsed);
+        self.state.private.mask = Some(mask);
+        Progress::Updated(self.into())
+    }
+
+    /// Return the scalar which weights the local model in the global model: its number of
+    /// samples normalized by the maximal sample count of the round parameters, or by
+    /// [`MAX_SAMPLE_COUNT`] if the coordinator doesn't set one, if the store knows it, the
+    /// scalar of the settings otherwise.
+    fn scalar(&self) -> Scalar {
+        match self.io.model_sample_count() {
+            Some(count) => {
+                let max_count = self
+                    .state
+                    .shared
+                    .round_params
+                    .max_sample_count
+                    .map_or(MAX_SAMPLE_COUNT, NonZeroU64::get);
+                if count.get() > max_count {
+                    warn!(
+                        "the sample count {} exceeds the maximal sample count {}",
+                        count, max_count
+                    );
+                }
+                Scalar::new(count.get().min(max_count), max_count)
+            }
+            None => self.state.shared.scalar.clone(),
+        }
+    }
+
+    /// Generate a mask seed and mask a local model that is loaded chunk by chunk, such
+    /// that only one chunk is loaded at a time. If a chunk can't be loaded, the chunks
+    /// masked so far are discarded and the masking starts over on the next step.
+    async fn mask_model_chunks(mut self, chunk_size: usize) -> Progress<Update> {
+        info!("computing masked model in chunks of {} weights", chunk_size);
+        let config = self.local_model_config();
+        let chunk_size = chunk_size.max(1);
+        let mut masker = match self.state.shared.masker() {
+            Ok(masker) => masker.into_chunked(self.scalar(), config.len),
+            Err(e) => {
+                warn!("invalid differential privacy settings: {}", e);
+                return Progress::Stuck(self);
+            }
+        };
+        let start = clock::now();
+        while masker.len() < config.len {
+            let range = masker.len()..config.len.min(masker.len().saturating_add(chunk_size));
+            let chunk = match self.io.load_model_chunk(&config, range.clone()).await {
+                Ok(Some(chunk)) if chunk.len() == range.len() => chunk,
+                Ok(Some(chunk)) => {
+                    warn!(
+                        "the model chunk {:?} has {} weights instead of {}",
+                        range,
+                        chunk.len(),
+                        range.len()
+                    );
+                    return Progress::Stuck(self);
+                }
+                Ok(None) => {
+                    debug!("model chunk {:?} is not ready", range);
+                    return Progress::Stuck(self);
+                }
+                Err(e) => {
+                    warn!("failed to load model chunk {:?}: {:?}", range, e);
+                    return Progress::Stuck(self);
+                }
+            };
+            masker = run_blocking(self.is_cooperative(), move || {
+                masker.mask_chunk(&chunk);
+                masker
+            })
+            .await;
+        }
+        // the time to load the chunks is included, since it is part of the masking
+        let elapsed = clock::now().monotonic.saturating_sub(start.monotonic);
+        self.state
+            .shared
+            .deadline
+            .record_compute(config.len, elapsed);
+        self.state.private.mask = Some(masker.finish());
         Progress::Updated(self.into())
     }
 
     // Create a local seed dictionary from a sum dictionary.
-    pub(crate) fn build_seed_dict(mut self) -> Progress<Update> {
+    pub(crate) async fn build_seed_dict(mut self) -> Progress<Update> {
         if self.state.private.has_built_seed_dict() {
             debug!("already built the seed dictionary, continuing");
             return Progress::Continue(self);
         }
-        // UNWRAP_SAFE: the mask is set in `mask_model()` which is called before this method
-        let mask_seed = &self.state.private.mask.as_ref().unwrap().0;
         info!("building local seed dictionary");
-        let seeds = self
-            .state
-            .private
-            .sum_dict
-            .take()
-            .unwrap()
-            .into_iter()
-            .map(|(pk, ephm_pk)| (pk, mask_seed.encrypt(&ephm_pk)))
-            .collect();
+        let mut yielder = self.yielder();
+        // UNWRAP_SAFE: the sum dict is set in `fetch_sum_dict()` which is called before
+        // this method
+        let sum_dict = self.state.private.sum_dict.take().unwrap();
+        let mut seeds = LocalSeedDict::new();
+        for (pk, ephm_pk) in sum_dict.into_iter() {
+            // UNWRAP_SAFE: the mask is set in `mask_model()` which is called before this
+            // method
+            let mask_seed = &self.state.private.mask.as_ref().unwrap().0;
+            seeds.insert(pk, self.state.shared.encrypt_mask_seed(mask_seed, &ephm_pk));
+            yielder.tick().await;
+        }
         self.state.private.seed_dict = Some(seeds);
         Progress::Updated(self.into())
     }
@@ -1,12 +1,27 @@
+use std::time::Duration;
+
 use derive_more::From;
+use xaynet_core::{
+    common::RoundParameters,
+    crypto::SigningKeyPair,
+    mask::MaskObject,
+    ParticipantPublicKey,
+};
 
 use super::{
     boxed_io,
+    clock,
     Awaiting,
+    AwaitingConsent,
+    CheckpointError,
+    CircuitState,
+    ConsentRequest,
     IntoPhase,
     LocalModelConfig,
     NewRound,
     Phase,
+    PhaseIo,
+    RestoreError,
     SendingSum,
     SendingSum2,
     SendingUpdate,
@@ -17,7 +32,13 @@ use super::{
     Sum2,
     Update,
 };
-use crate::{settings::PetSettings, ModelStore, Notify, XaynetClient};
+use crate::{
+    event_stream::{event_stream, EventStream, EventStreamConfig},
+    settings::{DpConfig, InvalidDpConfig, PetSettings},
+    ModelStore,
+    Notify,
+    XaynetClient,
+};
 
 /// Outcome of a state machine transition attempt.
 #[derive(Debug)]
@@ -29,6 +50,24 @@ pub enum TransitionOutcome {
     Complete(StateMachine),
 }
 
+/// Kind of work the state machine performs on its next transitions (see
+/// [`StateMachine::pending_work()`]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkClass {
+    /// The state machine polls the coordinator until it is selected for a task, or
+    /// waits for a decision of the user.
+    Poll,
+    /// The state machine downloads the dictionaries published by the coordinator.
+    Download,
+    /// The state machine waits for the model trained by the participant.
+    Train,
+    /// The state machine masks the local model or aggregates the masks, which is CPU
+    /// bound and doesn't transfer data apart from checking whether a new round started.
+    Compute,
+    /// The state machine composes a message and uploads it to the coordinator.
+    Upload,
+}
+
 /// PET state machine.
 #[derive(From, Debug)]
 pub enum StateMachine {
@@ -36,6 +75,8 @@ pub enum StateMachine {
     NewRound(Phase<NewRound>),
     /// PET state machine in the "awaiting" phase
     Awaiting(Phase<Awaiting>),
+    /// PET state machine in the "awaiting consent" phase
+    AwaitingConsent(Phase<AwaitingConsent>),
     /// PET state machine in the "sum" phase
     Sum(Phase<Sum>),
     /// PET state machine in the "update" phase
@@ -56,6 +97,7 @@ impl StateMachine {
         match self {
             StateMachine::NewRound(phase) => phase.step().await,
             StateMachine::Awaiting(phase) => phase.step().await,
+            StateMachine::AwaitingConsent(phase) => phase.step().await,
             StateMachine::Sum(phase) => phase.step().await,
             StateMachine::Update(phase) => phase.step().await,
             StateMachine::Sum2(phase) => phase.step().await,
@@ -65,19 +107,59 @@ impl StateMachine {
         }
     }
 
+    /// Try to make as much progress as possible in the PET protocol: transition until
+    /// the state machine is pending, or until `max_steps` transitions completed.
+    ///
+    /// Return the state machine and the number of completed transitions.
+    pub async fn run_until_pending(self, max_steps: usize) -> (StateMachine, usize) {
+        let mut state_machine = self;
+        for steps in 0..max_steps {
+            state_machine = match state_machine.transition().await {
+                TransitionOutcome::Pending(state_machine) => return (state_machine, steps),
+                TransitionOutcome::Complete(state_machine) => state_machine,
+            };
+        }
+        (state_machine, max_steps)
+    }
+
     /// Convert the state machine into a serializable data structure so
     /// that it can be saved.
     pub fn save(self) -> SerializableState {
-        match self {
+        let mut state: SerializableState = match self {
             StateMachine::NewRound(phase) => phase.state.into(),
             StateMachine::Awaiting(phase) => phase.state.into(),
+            StateMachine::AwaitingConsent(phase) => phase.state.into(),
             StateMachine::Sum(phase) => phase.state.into(),
             StateMachine::Update(phase) => phase.state.into(),
             StateMachine::Sum2(phase) => phase.state.into(),
             StateMachine::SendingSum(phase) => phase.state.into(),
             StateMachine::SendingUpdate(phase) => phase.state.into(),
             StateMachine::SendingSum2(phase) => phase.state.into(),
-        }
+        };
+        // anchor the timestamps of the state in case it is restored in another process
+        state.shared_mut().circuit_breaker.observe(clock::now());
+        state
+    }
+
+    /// Take a checkpoint of the state machine, from which it can be restored with
+    /// [`StateMachine::restore_from_checkpoint()`], for instance if the process dies in
+    /// the middle of a round. Unlike [`StateMachine::save()`], this doesn't consume the
+    /// state machine.
+    pub fn checkpoint(&self) -> Result<SerializableState, CheckpointError> {
+        let mut state = match self {
+            StateMachine::NewRound(ref phase) => phase.state.checkpoint(),
+            StateMachine::Awaiting(ref phase) => phase.state.checkpoint(),
+            StateMachine::AwaitingConsent(ref phase) => phase.state.checkpoint(),
+            StateMachine::Sum(ref phase) => phase.state.checkpoint(),
+            StateMachine::Update(ref phase) => phase.state.checkpoint(),
+            StateMachine::Sum2(ref phase) => phase.state.checkpoint(),
+            StateMachine::SendingSum(ref phase) => phase.state.checkpoint(),
+            StateMachine::SendingUpdate(ref phase) => phase.state.checkpoint(),
+            StateMachine::SendingSum2(ref phase) => phase.state.checkpoint(),
+        }?;
+        // anchor the timestamps of the state, as in `save()`
+        state.shared_mut().circuit_breaker.observe(clock::now());
+        Ok(state)
     }
 
     /// Return the local model configuration of the model that is expected in the update phase.
@@ -85,6 +167,7 @@ impl StateMachine {
         match self {
             StateMachine::NewRound(ref phase) => phase.local_model_config(),
             StateMachine::Awaiting(ref phase) => phase.local_model_config(),
+            StateMachine::AwaitingConsent(ref phase) => phase.local_model_config(),
             StateMachine::Sum(ref phase) => phase.local_model_config(),
             StateMachine::Update(ref phase) => phase.local_model_config(),
             StateMachine::Sum2(ref phase) => phase.local_model_config(),
@@ -93,6 +176,206 @@ impl StateMachine {
             StateMachine::SendingSum2(ref phase) => phase.local_model_config(),
         }
     }
+
+    /// Return the state of the circuit breaker that protects the coordinator from being
+    /// flooded with requests while it fails.
+    pub fn circuit_state(&self) -> CircuitState {
+        self.shared().circuit_breaker.state(clock::now())
+    }
+
+    /// Return the kind of work the state machine performs on its next transitions. It
+    /// only depends on the state of the state machine, so it is preserved when the state
+    /// machine is saved and restored.
+    pub fn pending_work(&self) -> WorkClass {
+        match self {
+            StateMachine::NewRound(_)
+            | StateMachine::Awaiting(_)
+            | StateMachine::AwaitingConsent(_) => WorkClass::Poll,
+            StateMachine::Update(ref phase) => phase.state.private.pending_work(),
+            StateMachine::Sum2(ref phase) => phase.state.private.pending_work(),
+            StateMachine::Sum(_)
+            | StateMachine::SendingSum(_)
+            | StateMachine::SendingUpdate(_)
+            | StateMachine::SendingSum2(_) => WorkClass::Upload,
+        }
+    }
+
+    /// Return the public signing key of the participant, which identifies it towards the
+    /// coordinator.
+    pub fn public_key(&self) -> &ParticipantPublicKey {
+        &self.shared().keys.public
+    }
+
+    /// Rotate the signing keys of the participant, which otherwise identify it towards
+    /// the coordinator across all the rounds.
+    ///
+    /// The new `keys` are applied when the next round starts: the messages of the
+    /// current round are still signed with the current keys, which keeps them valid
+    /// until the round completes. Rotating the keys again before that replaces the keys
+    /// that wait to be applied. The new keys are part of the saved state.
+    pub fn rotate_keys(&mut self, keys: SigningKeyPair) {
+        self.shared_mut().next_keys = Some(keys);
+    }
+
+    /// Return the public signing key that will identify the participant from the next
+    /// round on, if the keys were rotated (see [`StateMachine::rotate_keys()`]).
+    pub fn next_public_key(&self) -> Option<&ParticipantPublicKey> {
+        self.shared().next_keys.as_ref().map(|keys| &keys.public)
+    }
+
+    /// Return the number of rounds the participant has observed. The round ID starts at
+    /// 0 and increases monotonically, also across saves and restores.
+    pub fn round_id(&self) -> u64 {
+        self.shared().round_id
+    }
+
+    /// Return the parameters of the current round, i.e. the fractions of participants
+    /// selected for the sum and update tasks, the round seed and the coordinator public
+    /// key. They are updated every time a new round is observed, and `None` is returned
+    /// as long as no round has been observed.
+    pub fn round_params(&self) -> Option<&RoundParameters> {
+        let shared = self.shared();
+        if shared.round_id == 0 {
+            None
+        } else {
+            Some(&shared.round_params)
+        }
+    }
+
+    /// Return the global mask that the participant aggregated in the sum2 phase, if it
+    /// awaits confirmation (see [`PetSettings::confirm_sum2`]).
+    pub fn sum2_mask(&self) -> Option<&MaskObject> {
+        match self {
+            StateMachine::Sum2(ref phase) if phase.is_awaiting_confirmation() => {
+                phase.state.private.mask.as_ref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Confirm the global mask that the participant aggregated in the sum2 phase, so
+    /// that the sum2 message can be sent on the next transition. Return `false` if the
+    /// participant doesn't await confirmation.
+    pub fn confirm_sum2(&mut self) -> bool {
+        match self {
+            StateMachine::Sum2(ref mut phase) if phase.is_awaiting_confirmation() => {
+                phase.state.private.confirmed = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the request for the consent of the user to take part in the task the
+    /// participant has been selected for, if it awaits the consent (see
+    /// [`PetSettings::require_consent`]).
+    pub fn consent_request(&self) -> Option<ConsentRequest> {
+        match self {
+            StateMachine::AwaitingConsent(ref phase) if phase.is_pending() => {
+                Some(phase.state.private.request)
+            }
+            _ => None,
+        }
+    }
+
+    /// Grant the consent of the user to take part in the task the participant has been
+    /// selected for, so that it starts on the next transition. Return `false` if the
+    /// participant doesn't await the consent.
+    pub fn grant_consent(&mut self) -> bool {
+        self.decide_consent(true)
+    }
+
+    /// Deny the consent of the user to take part in the task the participant has been
+    /// selected for, so that the task is abandoned on the next transition. Return
+    /// `false` if the participant doesn't await the consent.
+    pub fn deny_consent(&mut self) -> bool {
+        self.decide_consent(false)
+    }
+
+    fn decide_consent(&mut self, consent: bool) -> bool {
+        match self {
+            StateMachine::AwaitingConsent(ref mut phase) if phase.is_pending() => {
+                phase.state.private.consent = Some(consent);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Return the safety margin kept before the end of the update and sum2 phases (see
+    /// [`PetSettings::deadline_margin`]).
+    pub fn deadline_margin(&self) -> Option<Duration> {
+        self.shared().deadline.margin
+    }
+
+    /// Set the safety margin kept before the end of the update and sum2 phases (see
+    /// [`PetSettings::deadline_margin`]). The margin is not part of the saved state, so
+    /// it must be set again after the state machine is restored.
+    pub fn set_deadline_margin(&mut self, margin: Option<Duration>) {
+        self.shared_mut().deadline.margin = margin;
+    }
+
+    /// Return the differential privacy of the local model (see [`PetSettings::dp`]).
+    pub fn dp_config(&self) -> Option<DpConfig> {
+        self.shared().dp
+    }
+
+    /// Set the differential privacy of the local model (see [`PetSettings::dp`]). The
+    /// setting is not part of the saved state, so it must be set again after the state
+    /// machine is restored.
+    ///
+    /// # Errors
+    /// Fails if the configuration is invalid (see [`DpConfig::validate()`]), in which
+    /// case the setting is unchanged.
+    pub fn set_dp_config(&mut self, dp: Option<DpConfig>) -> Result<(), InvalidDpConfig> {
+        if let Some(dp) = dp {
+            dp.validate()?;
+        }
+        self.shared_mut().dp = dp;
+        Ok(())
+    }
+
+    /// Return whether the update and sum2 messages are compressed, if the coordinator
+    /// accepts it (see [`PetSettings::compression`]).
+    pub fn compression(&self) -> bool {
+        self.shared().compression
+    }
+
+    /// Set whether the update and sum2 messages are compressed (see
+    /// [`PetSettings::compression`]). This has no effect unless the `compression` feature
+    /// is enabled. The setting is not part of the saved state, so it must be set again
+    /// after the state machine is restored.
+    pub fn set_compression(&mut self, compression: bool) {
+        self.shared_mut().compression = compression && cfg!(feature = "compression");
+    }
+
+    fn shared(&self) -> &SharedState {
+        match self {
+            StateMachine::NewRound(ref phase) => &phase.state.shared,
+            StateMachine::Awaiting(ref phase) => &phase.state.shared,
+            StateMachine::AwaitingConsent(ref phase) => &phase.state.shared,
+            StateMachine::Sum(ref phase) => &phase.state.shared,
+            StateMachine::Update(ref phase) => &phase.state.shared,
+            StateMachine::Sum2(ref phase) => &phase.state.shared,
+            StateMachine::SendingSum(ref phase) => &phase.state.shared,
+            StateMachine::SendingUpdate(ref phase) => &phase.state.shared,
+            StateMachine::SendingSum2(ref phase) => &phase.state.shared,
+        }
+    }
+
+    fn shared_mut(&mut self) -> &mut SharedState {
+        match self {
+            StateMachine::NewRound(ref mut phase) => &mut phase.state.shared,
+            StateMachine::Awaiting(ref mut phase) => &mut phase.state.shared,
+            StateMachine::AwaitingConsent(ref mut phase) => &mut phase.state.shared,
+            StateMachine::Sum(ref mut phase) => &mut phase.state.shared,
+            StateMachine::Update(ref mut phase) => &mut phase.state.shared,
+            StateMachine::Sum2(ref mut phase) => &mut phase.state.shared,
+            StateMachine::SendingSum(ref mut phase) => &mut phase.state.shared,
+            StateMachine::SendingUpdate(ref mut phase) => &mut phase.state.shared,
+            StateMachine::SendingSum2(ref mut phase) => &mut phase.state.shared,
+        }
+    }
 }
 
 impl StateMachine {
@@ -121,7 +404,31 @@ impl StateMachine {
         state.into_phase(io).into()
     }
 
+    /// Instantiate a new PET state machine that emits its notifications on an
+    /// [`EventStream`] instead of a [`Notify`] implementation.
+    ///
+    /// The stream terminates when the state machine is dropped.
+    pub fn with_event_stream<X, M>(
+        settings: PetSettings,
+        xaynet_client: X,
+        model_store: M,
+        config: EventStreamConfig,
+    ) -> (Self, EventStream)
+    where
+        X: XaynetClient + Send + 'static,
+        M: ModelStore + Send + 'static,
+    {
+        let (notifier, events) = event_stream(config);
+        let state_machine = Self::new(settings, xaynet_client, model_store, notifier);
+        (state_machine, events)
+    }
+
     /// Restore the PET state machine from the given `state`.
+    ///
+    /// The timestamps of the state are re-anchored to the current time, which is
+    /// robust against jumps of the wall clock since the state was saved. If the circuit
+    /// breaker was open when the state was saved, its backoff is kept. If it was
+    /// half-open, the next request to the coordinator is delayed by a random jitter.
     pub fn restore<X, M, N>(
         state: SerializableState,
         xaynet_client: X,
@@ -134,9 +441,39 @@ impl StateMachine {
         N: Notify + Send + 'static,
     {
         let io = boxed_io(xaynet_client, model_store, notifier);
+        Self::restore_with_io(state, io)
+    }
+
+    /// Restore the PET state machine from a checkpoint taken with
+    /// [`StateMachine::checkpoint()`] or [`StateMachine::save()`] (see
+    /// [`StateMachine::restore`]).
+    ///
+    /// Unlike [`StateMachine::restore`], the checkpoint is checked first: a sum
+    /// participant whose ephemeral keys or global mask got corrupted couldn't take part
+    /// in the sum2 phase anymore.
+    pub fn restore_from_checkpoint<X, M, N>(
+        state: SerializableState,
+        xaynet_client: X,
+        model_store: M,
+        notifier: N,
+    ) -> Result<Self, RestoreError>
+    where
+        X: XaynetClient + Send + 'static,
+        M: ModelStore + Send + 'static,
+        N: Notify + Send + 'static,
+    {
+        state.check()?;
+        Ok(Self::restore(state, xaynet_client, model_store, notifier))
+    }
+
+    /// Restore the PET state machine from the given `state` with the given `io` object
+    /// (see [`StateMachine::restore`]).
+    pub(crate) fn restore_with_io(mut state: SerializableState, io: PhaseIo) -> Self {
+        state.shared_mut().circuit_breaker.restored(clock::now());
         match state {
             SerializableState::NewRound(state) => state.into_phase(io).into(),
             SerializableState::Awaiting(state) => state.into_phase(io).into(),
+            SerializableState::AwaitingConsent(state) => state.into_phase(io).into(),
             SerializableState::Sum(state) => state.into_phase(io).into(),
             SerializableState::Sum2(state) => state.into_phase(io).into(),
             SerializableState::Update(state) => state.into_phase(io).into(),
@@ -145,4 +482,21 @@ impl StateMachine {
             SerializableState::SendingSum2(state) => state.into_phase(io).into(),
         }
     }
+
+    /// Restore the PET state machine from the given `state`, emitting its
+    /// notifications on an [`EventStream`] (see [`StateMachine::with_event_stream`]).
+    pub fn restore_with_event_stream<X, M>(
+        state: SerializableState,
+        xaynet_client: X,
+        model_store: M,
+        config: EventStreamConfig,
+    ) -> (Self, EventStream)
+    where
+        X: XaynetClient + Send + 'static,
+        M: ModelStore + Send + 'static,
+    {
+        let (notifier, events) = event_stream(config);
+        let state_machine = Self::restore(state, xaynet_client, model_store, notifier);
+        (state_machine, events)
+    }
 }
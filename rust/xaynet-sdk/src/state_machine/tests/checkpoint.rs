@@ -0,0 +1,183 @@
+use xaynet_core::{
+    crypto::{ByteObject, EncryptKeyPair, SigningKeyPair},
+    mask::{Aggregation, MaskObject, MaskSeed},
+    message::Payload,
+    SumDict,
+};
+
+use crate::{
+    settings::{MaxMessageSize, PetSettings},
+    state_machine::{
+        tests::{
+            coordinator::{model, Coordinator, Store, MODEL_LENGTH},
+            utils::{shared_state, SelectFor},
+        },
+        RestoreError,
+        SerializableState,
+        State,
+        StateMachine,
+        Sum,
+        Sum2,
+        TransitionOutcome,
+    },
+    Notify,
+};
+
+struct Notifier;
+
+impl Notify for Notifier {}
+
+fn phase_name(state_machine: &StateMachine) -> &'static str {
+    match state_machine {
+        StateMachine::NewRound(_) => "new_round",
+        StateMachine::Awaiting(_) => "awaiting",
+        StateMachine::AwaitingConsent(_) => "awaiting_consent",
+        StateMachine::Sum(_) => "sum",
+        StateMachine::Update(_) => "update",
+        StateMachine::Sum2(_) => "sum2",
+        StateMachine::SendingSum(_) => "sending_sum",
+        StateMachine::SendingUpdate(_) => "sending_update",
+        StateMachine::SendingSum2(_) => "sending_sum2",
+    }
+}
+
+/// Runs a new state machine until it sent `nb_messages` messages and went back to the
+/// awaiting phase. The state machine is checkpointed after every transition, and it is
+/// dropped and restored from its serialized checkpoint, as if the process died. Returns
+/// the phases the state machine went through.
+async fn run_with_checkpoints(
+    coordinator: &Coordinator,
+    store: &Store,
+    nb_messages: usize,
+) -> Vec<&'static str> {
+    let mut settings = PetSettings::new(shared_state(SelectFor::None).keys);
+    settings.max_message_size = MaxMessageSize::unlimited();
+    let mut state_machine =
+        StateMachine::new(settings, coordinator.clone(), store.clone(), Notifier);
+
+    let mut phases = vec![phase_name(&state_machine)];
+    for _ in 0..20 {
+        state_machine = match state_machine.transition().await {
+            TransitionOutcome::Pending(state_machine) => state_machine,
+            TransitionOutcome::Complete(state_machine) => state_machine,
+        };
+        if phases.last() != Some(&phase_name(&state_machine)) {
+            phases.push(phase_name(&state_machine));
+        }
+
+        let checkpoint = bincode::serialize(&state_machine.checkpoint().unwrap()).unwrap();
+        drop(state_machine);
+        state_machine = StateMachine::restore_from_checkpoint(
+            bincode::deserialize(&checkpoint).unwrap(),
+            coordinator.clone(),
+            store.clone(),
+            Notifier,
+        )
+        .unwrap();
+
+        if coordinator.payloads().len() == nb_messages {
+            if let StateMachine::Awaiting(_) = state_machine {
+                break;
+            }
+        }
+    }
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+    phases
+}
+
+#[tokio::test]
+async fn test_checkpoint_sum_task() {
+    sodiumoxide::init().unwrap();
+    let mut coordinator = Coordinator::new(SelectFor::Sum);
+    coordinator.seeds = vec![MaskSeed::generate(), MaskSeed::generate()];
+
+    let phases = run_with_checkpoints(&coordinator, &Store(model()), 2).await;
+    assert_eq!(
+        phases,
+        vec![
+            "awaiting",
+            "new_round",
+            "sum",
+            "sending_sum",
+            "sum2",
+            "sending_sum2",
+            "awaiting"
+        ]
+    );
+
+    // the restored participant aggregated the masks of the seeds it received
+    let mask_config = coordinator.round_params.mask_config;
+    let mut aggregation = Aggregation::new(mask_config, MODEL_LENGTH);
+    for seed in &coordinator.seeds {
+        aggregation.aggregate(seed.derive_mask(MODEL_LENGTH, mask_config));
+    }
+    match coordinator.payloads().as_slice() {
+        [Payload::Sum(_), Payload::Sum2(sum2)] => {
+            assert_eq!(sum2.model_mask, MaskObject::from(aggregation))
+        }
+        payloads => panic!("unexpected messages: {:?}", payloads),
+    }
+}
+
+#[tokio::test]
+async fn test_checkpoint_update_task() {
+    sodiumoxide::init().unwrap();
+    let mut coordinator = Coordinator::new(SelectFor::Update);
+    let sum_pk = SigningKeyPair::generate().public;
+    let ephm_keys = EncryptKeyPair::generate();
+    let mut sum_dict = SumDict::new();
+    sum_dict.insert(sum_pk, ephm_keys.public);
+    coordinator.sum_dict = Some(sum_dict);
+
+    let phases = run_with_checkpoints(&coordinator, &Store(model()), 1).await;
+    assert_eq!(
+        phases,
+        vec![
+            "awaiting",
+            "new_round",
+            "update",
+            "sending_update",
+            "awaiting"
+        ]
+    );
+
+    // the masked model of the restored participant can be unmasked by the sum participant
+    match coordinator.payloads().as_slice() {
+        [Payload::Update(update)] => {
+            let seed = update.local_seed_dict[&sum_pk]
+                .decrypt(&ephm_keys.public, &ephm_keys.secret)
+                .unwrap();
+            let mask = seed.derive_mask(MODEL_LENGTH, coordinator.round_params.mask_config);
+            let unmasked = Aggregation::from(update.masked_model.clone()).unmask(mask);
+            assert_eq!(unmasked, model());
+        }
+        payloads => panic!("unexpected messages: {:?}", payloads),
+    }
+}
+
+#[test]
+fn test_restore_inconsistent_checkpoint() {
+    sodiumoxide::init().unwrap();
+    let restore = |state: SerializableState| {
+        let coordinator = Coordinator::new(SelectFor::Sum);
+        StateMachine::restore_from_checkpoint(state, coordinator, Store(model()), Notifier)
+    };
+
+    // the ephemeral public key of the sum participant doesn't match its secret key
+    let shared = shared_state(SelectFor::Sum);
+    let mut sum = Sum::new(shared.keys.secret.sign_detached(b"sum"));
+    sum.ephm_keys.public = EncryptKeyPair::generate().public;
+    let state = State::new(shared, Box::new(sum)).into();
+    assert!(matches!(restore(state), Err(RestoreError::EphemeralKeys)));
+
+    // the global mask doesn't have the length of the model
+    let shared = shared_state(SelectFor::Sum);
+    let mut sum2 = Sum2::new(
+        EncryptKeyPair::generate(),
+        shared.keys.secret.sign_detached(b"sum"),
+    );
+    let mask_len = shared.round_params.model_length + 1;
+    sum2.mask = Some(MaskSeed::generate().derive_mask(mask_len, shared.round_params.mask_config));
+    let state = State::new(shared, Box::new(sum2)).into();
+    assert!(matches!(restore(state), Err(RestoreError::Mask)));
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use mockall::Sequence;
+
+use crate::{
+    client::ClientError,
+    settings::CircuitBreakerSettings,
+    state_machine::{
+        tests::utils::{round_params, shared_state, SelectFor},
+        Awaiting,
+        CircuitBreaker,
+        CircuitState,
+        IntoPhase,
+        MockIO,
+        Phase,
+        State,
+        StateMachine,
+        TransitionOutcome,
+    },
+    unwrap_as,
+};
+
+/// Instantiate an awaiting phase with a circuit breaker that opens after three server
+/// errors.
+fn make_phase() -> Phase<Awaiting> {
+    let mut shared = shared_state(SelectFor::None);
+    shared.circuit_breaker = CircuitBreaker::new(CircuitBreakerSettings {
+        threshold: 3,
+        window: Duration::from_secs(60),
+        backoff: Duration::from_secs(60),
+        jitter: Duration::from_secs(0),
+    });
+
+    let mut mock = MockIO::new();
+    mock.expect_notify_idle().times(1).return_const(());
+    let mut phase = State::new(shared, Box::new(Awaiting)).into_phase(Box::new(mock));
+    phase.check_io_mock();
+    phase
+}
+
+async fn step_pending(phase: Phase<Awaiting>) -> Phase<Awaiting> {
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Pending);
+    unwrap_as!(state_machine, StateMachine::Awaiting)
+}
+
+#[tokio::test]
+async fn test_circuit_breaker_short_circuits_and_recovers() {
+    let mut phase = make_phase();
+    phase.with_io_mock(|mock| {
+        // the coordinator fails three times in a row, then the circuit breaker opens and
+        // no more requests are sent
+        mock.expect_get_round_params()
+            .times(3)
+            .returning(|| Err(Box::new(ClientError::UnexpectedResponse(503))));
+    });
+    for _ in 0..10 {
+        phase = step_pending(phase).await;
+    }
+    let state_machine = StateMachine::from(phase);
+    assert!(matches!(
+        state_machine.circuit_state(),
+        CircuitState::Open { .. }
+    ));
+    let mut phase = unwrap_as!(state_machine, StateMachine::Awaiting);
+    phase.check_io_mock();
+
+    // once the backoff elapsed, a single probe is let through and the coordinator
+    // recovered
+    phase.state.shared.circuit_breaker.elapse_backoff();
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        mock.expect_get_round_params()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(round_params(SelectFor::None)));
+        mock.expect_get_round_params()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|| Ok(round_params(SelectFor::None)));
+    });
+    let phase = step_pending(phase).await;
+    let state_machine = StateMachine::from(phase);
+    assert_eq!(state_machine.circuit_state(), CircuitState::Closed);
+    let phase = unwrap_as!(state_machine, StateMachine::Awaiting);
+    let mut phase = step_pending(phase).await;
+    phase.check_io_mock();
+}
@@ -0,0 +1,150 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use xaynet_core::{
+    common::{PhaseName, PhaseStats, RoundMetadata, RoundParameters},
+    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, PublicSigningKey},
+    mask::{FromPrimitives, MaskSeed, Model},
+    message::{Message, Payload, Tag},
+    SumDict,
+    UpdateSeedDict,
+};
+
+use crate::{
+    client::ClientError,
+    state_machine::tests::utils::{round_params, SelectFor, SigningKeyGenerator},
+    ModelStore,
+    XaynetClient,
+};
+
+pub const MODEL_LENGTH: usize = 4;
+
+/// A coordinator that serves the same round parameters over and over, and keeps the
+/// payloads of the messages it receives. Its clones share the received payloads, so that
+/// a restored participant talks to the same coordinator.
+#[derive(Clone)]
+pub struct Coordinator {
+    pub round_params: RoundParameters,
+    /// The sum dictionary served to the update participants.
+    pub sum_dict: Option<SumDict>,
+    /// The mask seeds of the update participants. They are encrypted for the sum
+    /// participant once its sum message is received.
+    pub seeds: Vec<MaskSeed>,
+    payloads: Arc<Mutex<Vec<Payload>>>,
+}
+
+impl Coordinator {
+    pub fn new(task: SelectFor) -> Self {
+        Self {
+            round_params: RoundParameters {
+                model_length: MODEL_LENGTH,
+                ..round_params(task)
+            },
+            sum_dict: None,
+            seeds: Vec::new(),
+            payloads: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Return the payloads of the messages received so far.
+    pub fn payloads(&self) -> Vec<Payload> {
+        self.payloads.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl XaynetClient for Coordinator {
+    type Error = ClientError;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        Ok(self.round_params.clone())
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        Ok(RoundMetadata {
+            round_id: 0,
+            phase: PhaseName::Idle,
+            phase_start: 0,
+            phase_duration: None,
+            compression: false,
+            changed_since_previous: true,
+        })
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        Ok(PhaseStats {
+            round_id: 0,
+            phase: PhaseName::Idle,
+            count: None,
+            accepted: 0,
+            rejected: 0,
+            discarded: 0,
+        })
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        Ok(self.sum_dict.clone())
+    }
+
+    async fn get_seeds(
+        &mut self,
+        _pk: PublicSigningKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        let ephm_pk = self
+            .payloads()
+            .into_iter()
+            .find_map(|payload| match payload {
+                Payload::Sum(sum) => Some(sum.ephm_pk),
+                _ => None,
+            });
+        let ephm_pk = match ephm_pk {
+            Some(ephm_pk) => ephm_pk,
+            None => return Ok(None),
+        };
+        let mut key_gen = SigningKeyGenerator::new();
+        let seed_dict = self
+            .seeds
+            .iter()
+            .map(|seed| (key_gen.next().public, seed.encrypt(&ephm_pk)))
+            .collect();
+        Ok(Some(seed_dict))
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_model_by_id(&mut self, _id: &str) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn send_message(&mut self, _tag: Tag, msg: Vec<u8>) -> Result<(), Self::Error> {
+        let keys = EncryptKeyPair::derive_from_seed(&EncryptKeySeed::zeroed());
+        let msg = keys.secret.decrypt(&msg, &keys.public).unwrap();
+        let message = Message::from_byte_slice(&msg).unwrap();
+        self.payloads.lock().unwrap().push(message.payload);
+        Ok(())
+    }
+}
+
+/// A store that always loads the same model.
+#[derive(Clone)]
+pub struct Store(pub Model);
+
+#[async_trait]
+impl ModelStore for Store {
+    type Error = Infallible;
+    type Model = Box<Model>;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(Some(Box::new(self.0.clone())))
+    }
+}
+
+/// The model of the update participants.
+pub fn model() -> Model {
+    Model::from_primitives(vec![0.5_f32, -0.25, 0.125, 1.0].into_iter()).unwrap()
+}
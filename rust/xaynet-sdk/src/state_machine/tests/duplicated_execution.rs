@@ -0,0 +1,93 @@
+use xaynet_core::{
+    crypto::{ByteObject, EncryptKeyPair, SigningKeyPair},
+    mask::MaskSeed,
+    message::Payload,
+    SumDict,
+};
+
+use crate::{
+    settings::{MaxMessageSize, PetSettings},
+    state_machine::{
+        tests::{
+            coordinator::{model, Coordinator, Store},
+            utils::{shared_state, SelectFor},
+        },
+        StateMachine,
+        TransitionOutcome,
+    },
+    Notify,
+};
+
+struct Notifier;
+
+impl Notify for Notifier {}
+
+/// Runs a new state machine until it observes a new round, and returns its serialized
+/// state, from which it is about to compose the message of its task.
+async fn state_before_task(coordinator: &Coordinator) -> Vec<u8> {
+    let mut settings = PetSettings::new(shared_state(SelectFor::None).keys);
+    settings.max_message_size = MaxMessageSize::unlimited();
+    let state_machine = StateMachine::new(settings, coordinator.clone(), Store(model()), Notifier);
+    let state_machine = match state_machine.transition().await {
+        TransitionOutcome::Complete(state_machine) => state_machine,
+        TransitionOutcome::Pending(_) => panic!("expected a transition"),
+    };
+    assert!(matches!(state_machine, StateMachine::NewRound(_)));
+    bincode::serialize(&state_machine.save()).unwrap()
+}
+
+/// Runs a state machine restored from the serialized `state` until it is pending, as if
+/// an embedder accidentally drove several copies of the same state machine.
+async fn run_copy(coordinator: &Coordinator, state: &[u8]) {
+    let state_machine = StateMachine::restore(
+        bincode::deserialize(state).unwrap(),
+        coordinator.clone(),
+        Store(model()),
+        Notifier,
+    );
+    let (state_machine, _) = state_machine.run_until_pending(usize::MAX).await;
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+}
+
+#[tokio::test]
+async fn test_duplicated_sum_task() {
+    sodiumoxide::init().unwrap();
+    let mut coordinator = Coordinator::new(SelectFor::Sum);
+    coordinator.seeds = vec![MaskSeed::generate()];
+
+    let state = state_before_task(&coordinator).await;
+    run_copy(&coordinator, &state).await;
+    run_copy(&coordinator, &state).await;
+
+    // both copies sent the same sum message, with the same ephemeral keys, hence the seeds
+    // encrypted for the first one are aggregated by the second one too
+    match coordinator.payloads().as_slice() {
+        [sum @ Payload::Sum(_), sum2 @ Payload::Sum2(_), sum_copy, sum2_copy] => {
+            assert_eq!(sum_copy, sum);
+            assert_eq!(sum2_copy, sum2);
+        }
+        payloads => panic!("unexpected messages: {:?}", payloads),
+    }
+}
+
+#[tokio::test]
+async fn test_duplicated_update_task() {
+    sodiumoxide::init().unwrap();
+    let mut coordinator = Coordinator::new(SelectFor::Update);
+    let mut sum_dict = SumDict::new();
+    sum_dict.insert(
+        SigningKeyPair::generate().public,
+        EncryptKeyPair::generate().public,
+    );
+    coordinator.sum_dict = Some(sum_dict);
+
+    let state = state_before_task(&coordinator).await;
+    run_copy(&coordinator, &state).await;
+    run_copy(&coordinator, &state).await;
+
+    // both copies sent the same update message, with the same mask
+    match coordinator.payloads().as_slice() {
+        [update @ Payload::Update(_), update_copy] => assert_eq!(update_copy, update),
+        payloads => panic!("unexpected messages: {:?}", payloads),
+    }
+}
@@ -0,0 +1,117 @@
+use std::convert::Infallible;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::{
+    client::ClientError,
+    settings::PetSettings,
+    state_machine::{
+        tests::utils::{round_params, SelectFor},
+        StateMachine,
+        TransitionOutcome,
+    },
+    Event,
+    EventStreamConfig,
+    ModelStore,
+    XaynetClient,
+};
+use xaynet_core::{
+    common::{PhaseName, PhaseStats, RoundMetadata, RoundParameters},
+    crypto::{PublicSigningKey, SigningKeyPair},
+    mask::Model,
+    message::Tag,
+    SumDict,
+    UpdateSeedDict,
+};
+
+struct IdleClient;
+
+#[async_trait]
+impl XaynetClient for IdleClient {
+    type Error = ClientError;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        Ok(round_params(SelectFor::None))
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        Ok(RoundMetadata {
+            round_id: 0,
+            phase: PhaseName::Idle,
+            phase_start: 0,
+            phase_duration: None,
+            compression: false,
+            changed_since_previous: true,
+        })
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        Ok(PhaseStats {
+            round_id: 0,
+            phase: PhaseName::Idle,
+            count: None,
+            accepted: 0,
+            rejected: 0,
+            discarded: 0,
+        })
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_seeds(
+        &mut self,
+        _pk: PublicSigningKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_model_by_id(&mut self, _id: &str) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn send_message(&mut self, _tag: Tag, _msg: Vec<u8>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+struct EmptyStore;
+
+#[async_trait]
+impl ModelStore for EmptyStore {
+    type Error = Infallible;
+    type Model = Box<Model>;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(None)
+    }
+}
+
+#[tokio::test]
+async fn test_event_stream_terminates_when_dropped() {
+    sodiumoxide::init().unwrap();
+    let settings = PetSettings::new(SigningKeyPair::generate());
+    let (state_machine, events) = StateMachine::with_event_stream(
+        settings,
+        IdleClient,
+        EmptyStore,
+        EventStreamConfig::default(),
+    );
+    // awaiting -> new round
+    match state_machine.transition().await {
+        TransitionOutcome::Complete(state_machine) => drop(state_machine),
+        TransitionOutcome::Pending(_) => panic!("expected a transition"),
+    }
+
+    let events: Vec<Event> = events.collect().await;
+    assert_eq!(
+        events,
+        vec![Event::Idle, Event::NewRound, Event::RoundIdChanged(1)]
+    );
+}
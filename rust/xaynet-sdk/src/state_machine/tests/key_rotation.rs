@@ -0,0 +1,96 @@
+use xaynet_core::{
+    common::{RoundParameters, RoundSeed},
+    crypto::{ByteObject, SigningKeyPair, SigningKeySeed},
+};
+
+use crate::{
+    state_machine::{
+        tests::utils::{round_metadata, round_params, shared_state, SelectFor},
+        Awaiting,
+        IntoPhase,
+        MockIO,
+        Phase,
+        State,
+        StateMachine,
+        TransitionOutcome,
+    },
+    unwrap_as,
+};
+
+/// Instantiate an awaiting phase that observed no round yet.
+fn make_phase() -> Phase<Awaiting> {
+    let mut mock = MockIO::new();
+    mock.expect_notify_idle().times(1).return_const(());
+    let mut phase =
+        State::new(shared_state(SelectFor::None), Box::new(Awaiting)).into_phase(Box::new(mock));
+    phase.check_io_mock();
+    phase
+}
+
+/// The parameters of a new round, which differ from the current ones by their seed.
+fn new_round_params(seed: u8) -> RoundParameters {
+    let mut params = round_params(SelectFor::None);
+    params.seed = RoundSeed::from_slice_unchecked(&[seed; RoundSeed::LENGTH]);
+    params
+}
+
+/// Make the coordinator publish the parameters of a new round.
+fn expect_new_round(mock: &mut MockIO, seed: u8) {
+    let params = new_round_params(seed);
+    mock.expect_get_round_params()
+        .times(1)
+        .returning(move || Ok(params.clone()));
+    mock.expect_notify_new_round().times(1).return_const(());
+    mock.expect_notify_round_id_changed()
+        .times(1)
+        .return_const(());
+    mock.expect_get_round_metadata()
+        .times(1)
+        .returning(|| Ok(round_metadata(true)));
+}
+
+#[tokio::test]
+async fn test_rotate_keys_mid_round() {
+    let mut phase = make_phase();
+    let old_keys = phase.state.shared.keys.clone();
+    let new_keys = SigningKeyPair::derive_from_seed(&SigningKeySeed::from_slice_unchecked(
+        &[1; SigningKeySeed::LENGTH],
+    ));
+
+    // a round started
+    phase.with_io_mock(|mock| expect_new_round(mock, 1));
+    let mut state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+
+    // the keys are rotated in the middle of the round
+    state_machine.rotate_keys(new_keys.clone());
+    assert_eq!(state_machine.public_key(), &old_keys.public);
+    assert_eq!(state_machine.next_public_key(), Some(&new_keys.public));
+
+    // the old keys are used until the round completes
+    let mut phase = unwrap_as!(state_machine, StateMachine::NewRound);
+    phase.with_io_mock(|mock| {
+        mock.expect_get_round_params()
+            .times(1)
+            .returning(|| Ok(new_round_params(1)));
+        mock.expect_notify_idle().times(1).return_const(());
+    });
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    assert_eq!(state_machine.public_key(), &old_keys.public);
+
+    // the new keys survive saving and restoring the state machine
+    let mut phase = unwrap_as!(state_machine, StateMachine::Awaiting);
+    phase.check_io_mock();
+    let state = StateMachine::from(phase).save();
+    let mut mock = MockIO::new();
+    mock.expect_notify_idle().times(1).return_const(());
+    let state_machine = StateMachine::restore_with_io(state, Box::new(mock));
+    assert_eq!(state_machine.public_key(), &old_keys.public);
+    assert_eq!(state_machine.next_public_key(), Some(&new_keys.public));
+
+    // the new keys are applied when the next round starts
+    let mut phase = unwrap_as!(state_machine, StateMachine::Awaiting);
+    phase.with_io_mock(|mock| expect_new_round(mock, 2));
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    assert_eq!(state_machine.public_key(), &new_keys.public);
+    assert_eq!(state_machine.next_public_key(), None);
+}
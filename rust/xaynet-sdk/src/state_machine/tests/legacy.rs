@@ -0,0 +1,124 @@
+use xaynet_core::crypto::{ByteObject, SigningKeyPair, SigningKeySeed};
+
+use crate::state_machine::{
+    legacy::{v1, v2, v3, v4},
+    SerializableState,
+};
+
+/// A sum state of the third round observed by a participant whose signing keys are
+/// derived from the seed `[7; 32]`, saved in the [`v1`] layout.
+const SUM_V1: &[u8] = include_bytes!("data/sum_v1.bin");
+
+/// A sum sending state of a participant that composed its sum message, saved in the [`v2`]
+/// layout.
+const SENDING_SUM_V2: &[u8] = include_bytes!("data/sending_sum_v2.bin");
+
+/// The same sum sending state, saved in the [`v3`] layout.
+const SENDING_SUM_V3: &[u8] = include_bytes!("data/sending_sum_v3.bin");
+
+/// The same sum sending state in a round of the training plan `first`, saved in the [`v4`]
+/// layout.
+const SENDING_SUM_V4: &[u8] = include_bytes!("data/sending_sum_v4.bin");
+
+#[test]
+fn test_v1_sum_state() {
+    sodiumoxide::init().unwrap();
+    let keys = SigningKeyPair::derive_from_seed(&SigningKeySeed::from_slice(&[7; 32]).unwrap());
+    // the state was saved before the keys of the next round and the seed of the current
+    // round were part of the state
+    assert!(bincode::deserialize::<SerializableState>(SUM_V1).is_err());
+
+    let state = bincode::deserialize::<v1::SerializableState>(SUM_V1).unwrap();
+    let state = v3::SerializableState::from(v2::SerializableState::from(state));
+    let state: SerializableState = v4::SerializableState::from(state).into();
+    assert!(state.check().is_ok());
+    let state = match state {
+        SerializableState::Sum(state) => state,
+        state => panic!("unexpected state {:?}", state),
+    };
+    assert_eq!(state.shared.keys.public, keys.public);
+    assert_eq!(state.shared.round_id, 3);
+    assert!(state.shared.next_keys.is_none());
+    // the randomness of the round is generated securely, like before the state was saved
+    assert!(state.shared.task_seed.is_none());
+    assert!(state.shared.deterministic_seed.is_none());
+
+    // the converted state is saved in the current layout, where the round is not part of
+    // a training plan and has no maximal sample count
+    let bytes = bincode::serialize(&SerializableState::Sum(state)).unwrap();
+    assert_eq!(bytes.len(), SUM_V1.len() + 4);
+    assert!(bincode::deserialize::<SerializableState>(&bytes).is_ok());
+}
+
+#[test]
+fn test_v2_sending_sum_state() {
+    sodiumoxide::init().unwrap();
+    // the state was saved before the ephemeral public key that was sent in the sum message
+    // was part of the state
+    assert!(bincode::deserialize::<SerializableState>(SENDING_SUM_V2).is_err());
+
+    let state = bincode::deserialize::<v2::SerializableState>(SENDING_SUM_V2).unwrap();
+    let state = v3::SerializableState::from(state);
+    let state: SerializableState = v4::SerializableState::from(state).into();
+    assert!(state.check().is_ok());
+    let state = match state {
+        SerializableState::SendingSum(state) => state,
+        state => panic!("unexpected state {:?}", state),
+    };
+    // the ephemeral public key that was sent is the one of the ephemeral keys
+    let sum2 = &state.private.next;
+    assert_eq!(sum2.ephm_pk, sum2.ephm_keys.public);
+    let ephm_pk_len = bincode::serialized_size(&sum2.ephm_pk).unwrap() as usize;
+
+    // the converted state is saved in the current layout, where the round is not part of
+    // a training plan and has no maximal sample count
+    let bytes = bincode::serialize(&SerializableState::SendingSum(state)).unwrap();
+    assert_eq!(bytes.len(), SENDING_SUM_V2.len() + ephm_pk_len + 2);
+    assert!(bincode::deserialize::<SerializableState>(&bytes).is_ok());
+}
+
+#[test]
+fn test_v3_sending_sum_state() {
+    sodiumoxide::init().unwrap();
+    // the state was saved before the id of the training plan was part of the round
+    // parameters
+    assert!(bincode::deserialize::<SerializableState>(SENDING_SUM_V3).is_err());
+
+    let state = bincode::deserialize::<v3::SerializableState>(SENDING_SUM_V3).unwrap();
+    let state: SerializableState = v4::SerializableState::from(state).into();
+    assert!(state.check().is_ok());
+    let state = match state {
+        SerializableState::SendingSum(state) => state,
+        state => panic!("unexpected state {:?}", state),
+    };
+    assert!(state.shared.round_params.plan_id.is_none());
+
+    // the converted state is saved in the current layout, where the round has no maximal
+    // sample count
+    let bytes = bincode::serialize(&SerializableState::SendingSum(state)).unwrap();
+    assert_eq!(bytes.len(), SENDING_SUM_V3.len() + 2);
+    assert!(bincode::deserialize::<SerializableState>(&bytes).is_ok());
+}
+
+#[test]
+fn test_v4_sending_sum_state() {
+    sodiumoxide::init().unwrap();
+    // the state was saved before the maximal sample count was part of the round parameters
+    assert!(bincode::deserialize::<SerializableState>(SENDING_SUM_V4).is_err());
+
+    let state: SerializableState = bincode::deserialize::<v4::SerializableState>(SENDING_SUM_V4)
+        .unwrap()
+        .into();
+    assert!(state.check().is_ok());
+    let state = match state {
+        SerializableState::SendingSum(state) => state,
+        state => panic!("unexpected state {:?}", state),
+    };
+    assert_eq!(state.shared.round_params.plan_id.as_deref(), Some("first"));
+    assert!(state.shared.round_params.max_sample_count.is_none());
+
+    // the converted state is saved in the current layout
+    let bytes = bincode::serialize(&SerializableState::SendingSum(state)).unwrap();
+    assert_eq!(bytes.len(), SENDING_SUM_V4.len() + 1);
+    assert!(bincode::deserialize::<SerializableState>(&bytes).is_ok());
+}
@@ -1,2 +1,14 @@
+mod checkpoint;
+mod circuit_breaker;
+mod coordinator;
+mod duplicated_execution;
+mod event_stream;
+mod key_rotation;
+mod legacy;
+mod model_unchanged;
 mod phases;
+mod replay;
+mod round_id;
+mod run_until_pending;
+mod sample_count;
 pub mod utils;
@@ -0,0 +1,92 @@
+use xaynet_core::{
+    common::{RoundParameters, RoundSeed},
+    crypto::ByteObject,
+};
+
+use crate::{
+    client::ClientError,
+    state_machine::{
+        tests::utils::{round_metadata, round_params, shared_state, SelectFor},
+        Awaiting,
+        IntoPhase,
+        MockIO,
+        Phase,
+        State,
+        StateMachine,
+        TransitionOutcome,
+    },
+    unwrap_as,
+};
+
+/// Instantiate an awaiting phase that observed no round yet.
+fn make_phase() -> Phase<Awaiting> {
+    let mut mock = MockIO::new();
+    mock.expect_notify_idle().times(1).return_const(());
+    let mut phase =
+        State::new(shared_state(SelectFor::None), Box::new(Awaiting)).into_phase(Box::new(mock));
+    phase.check_io_mock();
+    phase
+}
+
+/// Make the coordinator publish the parameters of a new round. The global model is never
+/// downloaded by the state machine.
+fn expect_new_round(mock: &mut MockIO) {
+    let mut params: RoundParameters = round_params(SelectFor::None);
+    params.seed = RoundSeed::from_slice_unchecked(&[1; RoundSeed::LENGTH]);
+    mock.expect_get_round_params()
+        .times(1)
+        .returning(move || Ok(params.clone()));
+    mock.expect_notify_new_round().times(1).return_const(());
+    mock.expect_notify_round_id_changed()
+        .times(1)
+        .return_const(());
+    mock.expect_get_model().times(0);
+}
+
+#[tokio::test]
+async fn test_model_unchanged() {
+    let mut phase = make_phase();
+    phase.with_io_mock(|mock| {
+        expect_new_round(mock);
+        mock.expect_get_round_metadata()
+            .times(1)
+            .returning(|| Ok(round_metadata(false)));
+        mock.expect_notify_model_unchanged()
+            .times(1)
+            .return_const(());
+    });
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    let mut phase = unwrap_as!(state_machine, StateMachine::NewRound);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_model_changed() {
+    let mut phase = make_phase();
+    phase.with_io_mock(|mock| {
+        expect_new_round(mock);
+        mock.expect_get_round_metadata()
+            .times(1)
+            .returning(|| Ok(round_metadata(true)));
+        mock.expect_notify_model_unchanged().times(0);
+    });
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    let mut phase = unwrap_as!(state_machine, StateMachine::NewRound);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_model_changed_without_metadata() {
+    // the model is assumed to have changed if the coordinator doesn't answer
+    let mut phase = make_phase();
+    phase.with_io_mock(|mock| {
+        expect_new_round(mock);
+        mock.expect_get_round_metadata()
+            .times(1)
+            .returning(|| Err(Box::new(ClientError::Http("no metadata".to_string()))));
+        mock.expect_notify_model_unchanged().times(0);
+    });
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    let mut phase = unwrap_as!(state_machine, StateMachine::NewRound);
+    phase.check_io_mock();
+}
@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use xaynet_core::{
+    crypto::{ByteObject, EncryptKeyPair, Signature, SEALBYTES},
+    mask::MaskSeed,
+    message::{
+        Payload,
+        Sum as SumMessage,
+        Sum2 as Sum2Message,
+        Update as UpdateMessage,
+        MESSAGE_HEADER_LENGTH,
+    },
+    LocalSeedDict,
+};
+
+use crate::{
+    settings::MaxMessageSize,
+    state_machine::{
+        clock,
+        tests::utils::{shared_state, SelectFor},
+        AwaitingConsent,
+        ConsentRequest,
+        ConsentTask,
+        IntoPhase,
+        MockIO,
+        NewRound,
+        Phase,
+        SerializableState,
+        State,
+        StateMachine,
+    },
+    unwrap_as,
+    unwrap_step,
+};
+
+/// Instantiate an awaiting consent phase, by stepping a new round phase in which the
+/// participant is selected for the given `task` and requires consent.
+async fn make_phase(task: SelectFor) -> Phase<AwaitingConsent> {
+    let mut shared = shared_state(task);
+    shared.require_consent = true;
+    shared.round_params.model_length = 4;
+
+    let mut mock = MockIO::new();
+    mock.expect_notify_new_round().times(1).return_const(());
+    let mut phase: Phase<NewRound> =
+        State::new(shared, Box::new(NewRound)).into_phase(Box::new(mock));
+
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_awaiting_consent()
+            .times(1)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting_consent);
+    phase.check_io_mock();
+    phase
+}
+
+#[tokio::test]
+async fn test_grant_sum() {
+    let phase = make_phase(SelectFor::Sum).await;
+    assert_eq!(phase.state.private.request.task, ConsentTask::Sum);
+
+    // nothing happens until the consent is given
+    let mut state_machine: StateMachine = unwrap_step!(phase, pending, awaiting_consent).into();
+    assert!(state_machine.consent_request().is_some());
+    assert!(state_machine.grant_consent());
+    assert!(state_machine.consent_request().is_none());
+    assert!(!state_machine.grant_consent());
+    assert!(!state_machine.deny_consent());
+
+    let mut phase = unwrap_as!(state_machine, StateMachine::AwaitingConsent);
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_sum().times(1).return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, sum);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_grant_update() {
+    let phase = make_phase(SelectFor::Update).await;
+    assert_eq!(phase.state.private.request.task, ConsentTask::Update);
+
+    let mut state_machine: StateMachine = phase.into();
+    assert!(state_machine.grant_consent());
+
+    let mut phase = unwrap_as!(state_machine, StateMachine::AwaitingConsent);
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_update().times(1).return_const(());
+        mock.expect_notify_load_model().times(1).return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, update);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_deny() {
+    let phase = make_phase(SelectFor::Update).await;
+
+    let mut state_machine: StateMachine = phase.into();
+    assert!(state_machine.deny_consent());
+    assert!(state_machine.consent_request().is_none());
+    assert!(!state_machine.grant_consent());
+
+    let mut phase = unwrap_as!(state_machine, StateMachine::AwaitingConsent);
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_idle().times(1).return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_timeout() {
+    let mut phase = make_phase(SelectFor::Sum).await;
+
+    // without a timeout, the state machine waits until the end of the round
+    phase.state.private.requested_at -= 3_600_000;
+    let mut phase = unwrap_step!(phase, pending, awaiting_consent);
+
+    // the timeout didn't elapse yet
+    phase.state.shared.consent_timeout = Some(Duration::from_secs(7_200));
+    let mut phase = unwrap_step!(phase, pending, awaiting_consent);
+
+    // a wall clock that moved backwards doesn't make the timeout elapse
+    phase.state.shared.consent_timeout = Some(Duration::from_secs(60));
+    phase.state.private.requested_at = clock::now().wall + 3_600_000;
+    let mut phase = unwrap_step!(phase, pending, awaiting_consent);
+
+    phase.state.private.requested_at = clock::now().wall - 60_000;
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_idle().times(1).return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_restore_while_awaiting_consent() {
+    let phase = make_phase(SelectFor::Sum).await;
+    let request = phase.state.private.request;
+
+    let state: SerializableState = phase.into();
+    let state = bincode::serialize(&state).unwrap();
+    let state = bincode::deserialize(&state).unwrap();
+
+    // a restored state machine notifies the participant again
+    let mut io = MockIO::new();
+    io.expect_notify_awaiting_consent()
+        .withf(move |restored| *restored == request)
+        .times(1)
+        .return_const(());
+    let mut state_machine = StateMachine::restore_with_io(state, Box::new(io));
+    assert_eq!(state_machine.consent_request(), Some(request));
+
+    // the consent is part of the state
+    assert!(state_machine.grant_consent());
+    let state = bincode::serialize(&state_machine.save()).unwrap();
+    let state = bincode::deserialize(&state).unwrap();
+    let state_machine = StateMachine::restore_with_io(state, Box::new(MockIO::new()));
+    assert!(state_machine.consent_request().is_none());
+
+    let mut phase = unwrap_as!(state_machine, StateMachine::AwaitingConsent);
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_sum().times(1).return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, sum);
+    phase.check_io_mock();
+}
+
+/// Get the number of bytes uploaded for the given payload, once it is encoded and
+/// encrypted.
+fn uploaded_bytes<P>(phase: &Phase<P>, payload: Payload) -> u64 {
+    phase
+        .message_encoder(payload)
+        .map(|message| (message.len() + SEALBYTES) as u64)
+        .sum()
+}
+
+#[tokio::test]
+async fn test_upload_estimate() {
+    for message_size in &[
+        MaxMessageSize::unlimited(),
+        MaxMessageSize::capped(MESSAGE_HEADER_LENGTH + SEALBYTES + 32).unwrap(),
+    ] {
+        let mut phase = make_phase(SelectFor::Sum).await;
+        phase.state.shared.message_size = *message_size;
+        let config = phase.state.shared.round_params.mask_config;
+        let model_length = phase.state.shared.round_params.model_length;
+        let mask = MaskSeed::generate().derive_mask(model_length, config);
+
+        let sum = SumMessage {
+            sum_signature: Signature::zeroed(),
+            ephm_pk: EncryptKeyPair::generate().public,
+        };
+        let sum2 = Sum2Message {
+            sum_signature: Signature::zeroed(),
+            model_mask: mask.clone(),
+        };
+        let upload = uploaded_bytes(&phase, sum.into()) + uploaded_bytes(&phase, sum2.into());
+        let shared = &phase.state.shared;
+        let request = ConsentRequest::new(shared, ConsentTask::Sum);
+        assert_eq!(request.upload_bytes, upload);
+
+        let update = UpdateMessage {
+            sum_signature: Signature::zeroed(),
+            update_signature: Signature::zeroed(),
+            masked_model: mask,
+            local_seed_dict: LocalSeedDict::new(),
+        };
+        let upload = uploaded_bytes(&phase, update.into());
+        let request = ConsentRequest::new(shared, ConsentTask::Update);
+        assert_eq!(request.upload_bytes, upload);
+    }
+}
@@ -1,3 +1,4 @@
+mod awaiting_consent;
 mod new_round;
 mod sum;
 mod sum2;
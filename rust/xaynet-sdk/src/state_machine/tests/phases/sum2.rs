@@ -1,21 +1,29 @@
 use mockall::Sequence;
 use xaynet_core::{
-    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, PublicEncryptKey},
+    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, PublicEncryptKey, Signature},
     mask::{FromPrimitives, MaskConfigPair, MaskObject, MaskSeed, Masker, Model, Scalar},
     UpdateSeedDict,
 };
 
 use crate::{
+    settings::DEFAULT_YIELD_INTERVAL,
     state_machine::{
         tests::utils::{shared_state, SelectFor, SigningKeyGenerator},
         IntoPhase,
         MockIO,
         Phase,
+        Progress,
+        RestoreError,
         SendingSum2,
+        SerializableState,
         SharedState,
         State,
+        StateMachine,
+        Sum,
         Sum2,
+        WorkClass,
     },
+    unwrap_as,
     unwrap_progress_continue,
     unwrap_step,
 };
@@ -35,18 +43,23 @@ fn make_phase() -> Phase<Sum2> {
 
 fn make_sum2(shared: &SharedState) -> Box<Sum2> {
     let ephm_keys = EncryptKeyPair::derive_from_seed(&EncryptKeySeed::zeroed());
-    let sk = &shared.keys.secret;
-    let seed = shared.round_params.seed.as_slice();
-    let signature = sk.sign_detached(&[seed, b"sum"].concat());
     Box::new(Sum2 {
+        ephm_pk: ephm_keys.public,
         ephm_keys,
-        sum_signature: signature,
+        sum_signature: make_sum_signature(shared),
         seed_dict: None,
         seeds: None,
         mask: None,
+        confirmed: false,
     })
 }
 
+fn make_sum_signature(shared: &SharedState) -> Signature {
+    let sk = &shared.keys.secret;
+    let seed = shared.round_params.seed.as_slice();
+    sk.sign_detached(&[seed, b"sum"].concat())
+}
+
 fn make_seed_dict(mask_config: MaskConfigPair, ephm_pk: PublicEncryptKey) -> UpdateSeedDict {
     let (seed, _mask) = make_masked_model(mask_config);
     let mut key_gen = SigningKeyGenerator::new();
@@ -113,6 +126,8 @@ async fn step3_aggregate_masks(phase: Phase<Sum2>) -> Phase<Sum2> {
     assert!(phase.state.private.mask.is_some());
     // Make sure this steps consumes the seeds.
     assert!(phase.state.private.seeds.is_none());
+    // By default, the mask doesn't need to be confirmed
+    assert!(!phase.is_awaiting_confirmation());
     phase
 }
 
@@ -129,3 +144,150 @@ async fn test_phase() {
     let phase = step3_aggregate_masks(phase).await;
     let _phase = step4_into_sending_phase(phase).await;
 }
+
+#[tokio::test]
+async fn test_pending_work() {
+    let phase = make_phase();
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Download);
+    let phase = step1_fetch_seed_dict(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Compute);
+    let phase = step2_decrypt_seeds(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Compute);
+    let phase = step3_aggregate_masks(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Upload);
+}
+
+#[tokio::test]
+async fn test_phase_with_confirmation() {
+    let mut phase = make_phase();
+    phase.state.shared.confirm_sum2 = true;
+    phase.state.shared.round_params.model_length = 4;
+    let phase = step1_fetch_seed_dict(phase).await;
+    let mut phase = step2_decrypt_seeds(phase).await;
+
+    phase.with_io_mock(|mock| {
+        mock.expect_notify_sum2_mask_ready()
+            .times(1)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, sum2);
+    phase.check_io_mock();
+    assert!(phase.is_awaiting_confirmation());
+
+    // The state machine waits until the mask is confirmed
+    let mask_config = phase.state.shared.round_params.mask_config;
+    let model_length = phase.state.shared.round_params.model_length;
+    let state_machine: StateMachine = unwrap_step!(phase, pending, sum2).into();
+    let mask = state_machine.sum2_mask().unwrap();
+    assert_eq!(mask.vect.config, mask_config.vect);
+    assert_eq!(mask.vect.data.len(), model_length);
+
+    // A restored state machine notifies the participant again
+    let phase = unwrap_as!(state_machine, StateMachine::Sum2);
+    let mut io = MockIO::new();
+    io.expect_notify_sum2_mask_ready().times(1).return_const(());
+    let mut phase = phase.state.into_phase(Box::new(io));
+    phase.check_io_mock();
+
+    let mut state_machine: StateMachine = phase.into();
+    assert!(state_machine.confirm_sum2());
+    assert!(state_machine.sum2_mask().is_none());
+    assert!(!state_machine.confirm_sum2());
+
+    let phase = unwrap_as!(state_machine, StateMachine::Sum2);
+    let _phase = step4_into_sending_phase(phase).await;
+}
+
+#[tokio::test]
+async fn test_cooperative_aggregation_is_identical() {
+    let mask_config = shared_state(SelectFor::Sum).round_params.mask_config;
+    let seeds: Vec<MaskSeed> = (0..10).map(|_| make_masked_model(mask_config).0).collect();
+
+    let mut masks = Vec::new();
+    for yield_interval in &[0, 1, DEFAULT_YIELD_INTERVAL] {
+        let mut phase = make_phase();
+        phase.state.shared.yield_interval = *yield_interval;
+        phase.state.shared.round_params.model_length = 100;
+        phase.state.private.seeds = Some(seeds.clone());
+        let state_machine = unwrap_as!(phase.aggregate_masks().await, Progress::Updated);
+        let mut phase = unwrap_as!(state_machine, StateMachine::Sum2);
+        masks.push(phase.state.private.mask.take().unwrap());
+    }
+    assert_eq!(masks[0], masks[1]);
+    assert_eq!(masks[0], masks[2]);
+}
+
+#[tokio::test]
+async fn test_save_and_restore_after_sending_sum() {
+    // Start from the sum phase, with freshly generated ephemeral keys
+    let shared = shared_state(SelectFor::Sum);
+    let mask_config = shared.round_params.mask_config;
+    let sum = Box::new(Sum::new(make_sum_signature(&shared)));
+    let ephm_pk = sum.ephm_keys.public;
+    let mut mock = MockIO::new();
+    mock.expect_notify_sum().times(1).return_const(());
+    let mut phase: Phase<Sum> = State::new(shared, sum).into_phase(Box::new(mock));
+    phase.check_io_mock();
+    let mut phase = unwrap_step!(phase, complete, sending_sum);
+
+    // Send the sum message, which announces the ephemeral public key to the coordinator
+    phase.with_io_mock(|mock| {
+        mock.expect_observe_message().times(1).return_const(());
+        mock.expect_send_message().times(1).returning(|_, _| Ok(()));
+    });
+    let mut phase = unwrap_step!(phase, complete, sending_sum);
+    phase.check_io_mock();
+
+    // Save and restore the state machine right after the sum message was sent
+    let state = StateMachine::from(phase).save();
+    let state: SerializableState =
+        bincode::deserialize(&bincode::serialize(&state).unwrap()).unwrap();
+    let state_machine = StateMachine::restore_with_io(state, Box::new(MockIO::new()));
+    let phase = unwrap_as!(state_machine, StateMachine::SendingSum);
+    let mut phase = unwrap_step!(phase, complete, sum2);
+    assert_eq!(phase.state.private.ephm_keys.public, ephm_pk);
+
+    // The restored participant can decrypt the seeds encrypted with the public key it sent
+    phase.with_io_mock(move |mock| {
+        mock.expect_get_seeds()
+            .times(1)
+            .returning(move |_| Ok(Some(make_seed_dict(mask_config, ephm_pk))));
+    });
+    let mut phase = unwrap_step!(phase, complete, sum2);
+    phase.check_io_mock();
+    let phase = step2_decrypt_seeds(phase).await;
+    let phase = step3_aggregate_masks(phase).await;
+    let _phase = step4_into_sending_phase(phase).await;
+}
+
+#[tokio::test]
+async fn test_ephm_keys_mismatch() {
+    // The ephemeral keys were replaced by a consistent key pair that is not the one whose
+    // public key was sent in the sum message
+    let mut phase = make_phase();
+    phase.state.private.ephm_keys = EncryptKeyPair::generate();
+
+    // The participant gives up on the task before fetching any seeds
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        mock.expect_notify_ephemeral_keys_mismatch()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        mock.expect_notify_idle()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[test]
+fn test_restore_ephm_keys_mismatch() {
+    let shared = shared_state(SelectFor::Sum);
+    let mut sum2 = make_sum2(&shared);
+    sum2.ephm_keys = EncryptKeyPair::generate();
+    let state: SerializableState = State::new(shared, sum2).into();
+    assert!(matches!(state.check(), Err(RestoreError::EphemeralKeys)));
+}
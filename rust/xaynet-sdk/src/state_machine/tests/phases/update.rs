@@ -1,22 +1,45 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
 use mockall::Sequence;
+use num::traits::ToPrimitive;
 use xaynet_core::{
+    common::{PhaseDuration, PhaseName, RoundMetadata},
     crypto::ByteObject,
-    mask::{FromPrimitives, Model},
+    mask::{Aggregation, FromPrimitives, Model},
     SumDict,
 };
 
 use crate::{
+    client::ClientError,
     save_and_restore,
+    settings::{DpConfig, DpMechanism, DEFAULT_YIELD_INTERVAL},
     state_machine::{
-        tests::utils::{shared_state, EncryptKeyGenerator, SelectFor, SigningKeyGenerator},
+        tests::utils::{
+            mask_config,
+            shared_state,
+            EncryptKeyGenerator,
+            SelectFor,
+            SigningKeyGenerator,
+        },
         IntoPhase,
         MockIO,
         Phase,
+        Progress,
         SendingUpdate,
+        SerializableState,
         SharedState,
         State,
+        StateMachine,
         Update,
+        WorkClass,
     },
+    unwrap_as,
     unwrap_progress_continue,
     unwrap_step,
 };
@@ -100,17 +123,19 @@ async fn step1_fetch_sum_dict(mut phase: Phase<Update>) -> Phase<Update> {
 async fn step2_load_model(mut phase: Phase<Update>) -> Phase<Update> {
     phase.with_io_mock(|mock| {
         let mut seq = Sequence::new();
+        mock.expect_model_chunk_size().return_const(None);
         // The first time the state machine fetches the sum dict,
         // pretend it's not published yet
         mock.expect_load_model()
             .times(1)
             .in_sequence(&mut seq)
-            .returning(|| Ok(None));
+            .returning(|_| Ok(None));
         // The second time, return a sum dictionary.
         mock.expect_load_model()
             .times(1)
             .in_sequence(&mut seq)
-            .returning(|| Ok(Some(Box::new(make_model()))));
+            .withf(|config| config.round_id == 0 && config.mask_config == mask_config())
+            .returning(|_| Ok(Some(Box::new(make_model()))));
     });
 
     // First time: no progress should be made, since we didn't
@@ -126,16 +151,23 @@ async fn step2_load_model(mut phase: Phase<Update>) -> Phase<Update> {
     phase
 }
 
-async fn step3_mask_model(phase: Phase<Update>) -> Phase<Update> {
+/// Let the store know no sample count, such that the model is weighted by the scalar of the
+/// settings.
+fn expect_no_sample_count(mock: &mut MockIO) {
+    mock.expect_model_sample_count().return_const(None);
+}
+
+async fn step3_mask_model(mut phase: Phase<Update>) -> Phase<Update> {
+    phase.with_io_mock(expect_no_sample_count);
     let phase = unwrap_step!(phase, complete, update);
-    let mut phase = unwrap_progress_continue!(phase, mask_model);
+    let mut phase = unwrap_progress_continue!(phase, mask_model, async);
     phase.check_io_mock();
     phase
 }
 
 async fn step4_build_seed_dict(phase: Phase<Update>) -> Phase<Update> {
     let phase = unwrap_step!(phase, complete, update);
-    let mut phase = unwrap_progress_continue!(phase, build_seed_dict);
+    let mut phase = unwrap_progress_continue!(phase, build_seed_dict, async);
     phase.check_io_mock();
     phase
 }
@@ -155,6 +187,54 @@ async fn test_update_phase() {
     let _phase = step5_into_sending_phase(phase).await;
 }
 
+#[tokio::test]
+async fn test_update_quota_exceeded() {
+    let phase = make_phase();
+    let phase = step1_fetch_sum_dict(phase).await;
+    let phase = step2_load_model(phase).await;
+    let phase = step3_mask_model(phase).await;
+    let phase = step4_build_seed_dict(phase).await;
+    let mut phase = step5_into_sending_phase(phase).await;
+
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        mock.expect_observe_message()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        mock.expect_send_message()
+            .times(1)
+            .in_sequence(&mut seq)
+            .returning(|_, _| Err(Box::new(ClientError::QuotaExceeded)));
+        mock.expect_notify_quota_exceeded()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        mock.expect_notify_idle()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_pending_work() {
+    let phase = make_phase();
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Download);
+    let phase = step1_fetch_sum_dict(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Train);
+    let phase = step2_load_model(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Compute);
+    let phase = step3_mask_model(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Compute);
+    let phase = step4_build_seed_dict(phase).await;
+    assert_eq!(phase.state.private.pending_work(), WorkClass::Upload);
+    let phase = step5_into_sending_phase(phase).await;
+    assert_eq!(StateMachine::from(phase).pending_work(), WorkClass::Upload);
+}
+
 #[tokio::test]
 async fn test_save_and_restore() {
     let phase = make_phase();
@@ -191,3 +271,371 @@ async fn test_save_and_restore() {
     });
     let _phase = save_and_restore!(phase, Update);
 }
+
+#[tokio::test]
+async fn test_save_and_restore_staged_model() {
+    let phase = make_phase();
+    let phase = step1_fetch_sum_dict(phase).await;
+
+    // the model is staged in the saved state instead of being loaded from the store
+    let mut state: SerializableState = phase.into();
+    assert!(state.stage_local_model(make_model()));
+    assert!(!state.stage_local_model(make_model()));
+    let bytes = bincode::serialize(&state).unwrap();
+    let state: SerializableState = bincode::deserialize(&bytes).unwrap();
+    let state = unwrap_as!(state, SerializableState::Update);
+    assert_eq!(
+        state.private.model.as_ref().unwrap().as_ref(),
+        &make_model()
+    );
+
+    // the restored phase doesn't ask for the model again
+    let mut mock = MockIO::new();
+    mock.expect_notify_update().times(1).return_const(());
+    let mut phase = state.into_phase(Box::new(mock));
+    phase.check_io_mock();
+    let phase = step3_mask_model(phase).await;
+    let phase = step4_build_seed_dict(phase).await;
+    let _phase = step5_into_sending_phase(phase).await;
+}
+
+#[tokio::test]
+async fn test_discard_local_model() {
+    let phase = make_phase();
+    let phase = step1_fetch_sum_dict(phase).await;
+    let phase = step2_load_model(phase).await;
+    let phase = step3_mask_model(phase).await;
+
+    // the discarded model is asked for again once the state is restored
+    let state: SerializableState = phase.into();
+    let state = unwrap_as!(state.discard_local_model(), SerializableState::Update);
+    assert!(!state.private.has_loaded_model());
+    let mut mock = MockIO::new();
+    let mut seq = Sequence::new();
+    mock.expect_notify_update()
+        .times(1)
+        .in_sequence(&mut seq)
+        .return_const(());
+    mock.expect_notify_load_model()
+        .times(1)
+        .in_sequence(&mut seq)
+        .return_const(());
+    let mut phase = state.into_phase(Box::new(mock));
+    phase.check_io_mock();
+    let phase = step2_load_model(phase).await;
+    let phase = step3_mask_model(phase).await;
+    let phase = step4_build_seed_dict(phase).await;
+    let phase = step5_into_sending_phase(phase).await;
+
+    // the update task is abandoned once the masked model is being sent
+    let state: SerializableState = phase.into();
+    unwrap_as!(state.discard_local_model(), SerializableState::Awaiting);
+}
+
+/// Mask a large model while another task is running, and return how many times the
+/// other task got polled in the meantime.
+async fn mask_large_model(yield_interval: usize) -> usize {
+    let mut phase = make_phase();
+    phase.state.shared.yield_interval = yield_interval;
+    phase.with_io_mock(expect_no_sample_count);
+    let weights = vec![0.5_f32; 10_000];
+    phase.state.private.model = Some(Model::from_primitives(weights.into_iter()).unwrap().into());
+
+    let polled = Arc::new(AtomicUsize::new(0));
+    let counter = {
+        let polled = polled.clone();
+        tokio::spawn(async move {
+            loop {
+                polled.fetch_add(1, Ordering::SeqCst);
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    let state_machine = unwrap_as!(phase.mask_model().await, Progress::Updated);
+    counter.abort();
+    let phase = unwrap_as!(state_machine, StateMachine::Update);
+    assert!(phase.state.private.mask.is_some());
+    polled.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+async fn test_mask_model_is_cooperative() {
+    assert!(mask_large_model(DEFAULT_YIELD_INTERVAL).await > 0);
+    // with cooperative scheduling disabled, masking blocks the executor
+    assert_eq!(mask_large_model(0).await, 0);
+}
+
+#[tokio::test]
+async fn test_mask_model_with_dp() {
+    let mut phase = make_phase();
+    // the budget is so large that the noise is negligible
+    phase.state.shared.dp = Some(DpConfig {
+        epsilon: 1e12,
+        delta: 0.,
+        sensitivity: 1.,
+        mechanism: DpMechanism::Laplace,
+    });
+    phase.with_io_mock(expect_no_sample_count);
+    let weights = vec![3_f32, -4., 0.5, 0.];
+    phase.state.private.model = Some(Model::from_primitives(weights.into_iter()).unwrap().into());
+    let state_machine = unwrap_as!(phase.mask_model().await, Progress::Updated);
+    let phase = unwrap_as!(state_machine, StateMachine::Update);
+
+    // the weights of the participant are clipped to the sensitivity
+    let (seed, masked_model) = phase.state.private.mask.clone().unwrap();
+    let mask = seed.derive_mask(4, phase.state.shared.round_params.mask_config);
+    let model = Aggregation::from(masked_model).unmask(mask);
+    let weights = model.iter().map(|weight| weight.to_f64().unwrap());
+    for (weight, expected) in weights.zip(vec![1., -1., 0.5, 0.]) {
+        assert!((weight - expected).abs() < 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn test_mask_model_with_invalid_dp() {
+    let mut phase = make_phase();
+    phase.state.shared.dp = Some(DpConfig {
+        epsilon: 1.,
+        delta: 0.,
+        sensitivity: 0.,
+        mechanism: DpMechanism::Laplace,
+    });
+    phase.state.private.model = Some(make_model().into());
+    let phase = unwrap_as!(phase.mask_model().await, Progress::Stuck);
+    assert!(phase.state.private.mask.is_none());
+}
+
+#[tokio::test]
+async fn test_mask_model_chunks() {
+    let phase = make_phase();
+    let mut phase = step1_fetch_sum_dict(phase).await;
+    phase.state.shared.round_params.model_length = 4;
+    let chunk = |range: std::ops::Range<usize>| {
+        let weights = make_model().iter().cloned().collect::<Vec<_>>();
+        Ok(Some(weights[range].iter().cloned().collect::<Model>()))
+    };
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        mock.expect_model_chunk_size().return_const(Some(3));
+        expect_no_sample_count(mock);
+        // The first time, pretend the last chunk is not ready yet
+        mock.expect_load_model_chunk()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|config, range| config.len == 4 && range == &(0..3))
+            .returning(move |_, range| chunk(range));
+        mock.expect_load_model_chunk()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|_, range| range == &(3..4))
+            .returning(|_, _| Ok(None));
+        // The second time, the masking starts over and all the chunks are ready
+        mock.expect_load_model_chunk()
+            .times(2)
+            .in_sequence(&mut seq)
+            .returning(move |_, range| chunk(range));
+    });
+
+    // the whole model is never loaded
+    let phase = unwrap_step!(phase, pending, update);
+    assert!(phase.state.private.mask.is_none());
+    let mut phase = unwrap_step!(phase, complete, update);
+    let (_, masked_model) = phase.state.private.mask.as_ref().unwrap();
+    assert_eq!(masked_model.vect.data.len(), 4);
+    phase.check_io_mock();
+}
+
+/// Round metadata of an update phase that started `elapsed` seconds ago and lasts at
+/// most `max` seconds, if known.
+fn update_metadata(elapsed: u64, max: Option<u64>) -> RoundMetadata {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    RoundMetadata {
+        round_id: 1,
+        phase: PhaseName::Update,
+        phase_start: now - elapsed,
+        phase_duration: max.map(|max| PhaseDuration { min: 0, max }),
+        compression: false,
+        changed_since_previous: true,
+    }
+}
+
+/// Load the model with a deadline margin of one minute.
+async fn make_phase_with_deadline() -> Phase<Update> {
+    let phase = make_phase();
+    let phase = step1_fetch_sum_dict(phase).await;
+    let mut phase = step2_load_model(phase).await;
+    phase.state.shared.deadline.margin = Some(Duration::from_secs(60));
+    phase
+}
+
+/// Let the coordinator answer the next round metadata request with `metadata`.
+fn expect_round_metadata(mock: &mut MockIO, metadata: Result<RoundMetadata, ClientError>) {
+    let mut metadata = Some(metadata);
+    mock.expect_get_round_metadata()
+        .times(1)
+        .returning(move || metadata.take().unwrap().map_err(|e| Box::new(e) as _));
+}
+
+#[tokio::test]
+async fn test_deadline_proceed() {
+    let mut phase = make_phase_with_deadline().await;
+    phase.with_io_mock(|mock| {
+        expect_round_metadata(mock, Ok(update_metadata(60, Some(600))));
+        expect_no_sample_count(mock);
+    });
+    let mut phase = unwrap_step!(phase, complete, update);
+    assert!(phase.state.private.mask.is_some());
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_deadline_abandon() {
+    let mut phase = make_phase_with_deadline().await;
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        expect_round_metadata(mock, Ok(update_metadata(560, Some(600))));
+        mock.expect_notify_insufficient_time()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        mock.expect_notify_idle()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_deadline_abandon_before_download() {
+    // the sum dictionary is never fetched
+    let mut phase = make_phase();
+    phase.state.shared.deadline.margin = Some(Duration::from_secs(60));
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        expect_round_metadata(mock, Ok(update_metadata(560, Some(600))));
+        mock.expect_notify_insufficient_time()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        mock.expect_notify_idle()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_deadline_abandon_slow_compute() {
+    // masking the model takes longer than the remaining time, although nothing was uploaded
+    // yet
+    let mut phase = make_phase_with_deadline().await;
+    phase.state.shared.round_params.model_length = 4;
+    phase.state.shared.deadline.record_compute(1, 1_000_000);
+    phase.with_io_mock(|mock| {
+        let mut seq = Sequence::new();
+        expect_round_metadata(mock, Ok(update_metadata(60, Some(600))));
+        mock.expect_notify_insufficient_time()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+        mock.expect_notify_idle()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(());
+    });
+    let mut phase = unwrap_step!(phase, complete, awaiting);
+    phase.check_io_mock();
+}
+
+#[tokio::test]
+async fn test_deadline_unknown() {
+    // the phase has no known duration
+    let mut phase = make_phase_with_deadline().await;
+    phase.with_io_mock(|mock| {
+        expect_round_metadata(mock, Ok(update_metadata(560, None)));
+        expect_no_sample_count(mock);
+    });
+    let mut phase = unwrap_step!(phase, complete, update);
+    assert!(phase.state.private.mask.is_some());
+    phase.check_io_mock();
+
+    // the round metadata can't be fetched
+    let mut phase = make_phase_with_deadline().await;
+    phase.with_io_mock(|mock| {
+        expect_round_metadata(mock, Err(ClientError::UnexpectedResponse(404)));
+        expect_no_sample_count(mock);
+    });
+    let mut phase = unwrap_step!(phase, complete, update);
+    assert!(phase.state.private.mask.is_some());
+    phase.check_io_mock();
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_negotiate_compression() {
+    use crate::MessageEncoder;
+
+    let phase = make_phase();
+    let phase = step1_fetch_sum_dict(phase).await;
+    let phase = step2_load_model(phase).await;
+    let phase = step3_mask_model(phase).await;
+    let mut phase = step4_build_seed_dict(phase).await;
+
+    // the coordinator isn't asked unless the participant compresses its messages
+    phase.negotiate_compression().await;
+    assert!(phase.state.shared.coordinator_compression.is_none());
+
+    phase.state.shared.compression = true;
+    phase.with_io_mock(|mock| {
+        let metadata = RoundMetadata {
+            compression: true,
+            ..update_metadata(0, None)
+        };
+        expect_round_metadata(mock, Ok(metadata))
+    });
+    phase.negotiate_compression().await;
+    // the answer is kept for the rest of the round
+    phase.negotiate_compression().await;
+    phase.check_io_mock();
+    assert_eq!(phase.state.shared.coordinator_compression, Some(true));
+    assert!(matches!(
+        phase.compose_message(),
+        MessageEncoder::Compressed(_)
+    ));
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_negotiate_compression_unsupported() {
+    use crate::MessageEncoder;
+
+    let phase = make_phase();
+    let phase = step1_fetch_sum_dict(phase).await;
+    let phase = step2_load_model(phase).await;
+    let phase = step3_mask_model(phase).await;
+    let mut phase = step4_build_seed_dict(phase).await;
+
+    phase.state.shared.compression = true;
+    phase.with_io_mock(|mock| expect_round_metadata(mock, Ok(update_metadata(0, None))));
+    phase.negotiate_compression().await;
+    phase.check_io_mock();
+    assert_eq!(phase.state.shared.coordinator_compression, Some(false));
+
+    // the round metadata can't be fetched, e.g. from an older coordinator
+    phase.state.shared.coordinator_compression = None;
+    phase.with_io_mock(|mock| {
+        expect_round_metadata(mock, Err(ClientError::UnexpectedResponse(404)))
+    });
+    phase.negotiate_compression().await;
+    phase.check_io_mock();
+    assert!(phase.state.shared.coordinator_compression.is_none());
+    assert!(matches!(phase.compose_message(), MessageEncoder::Simple(_)));
+}
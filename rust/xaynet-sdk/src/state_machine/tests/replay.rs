@@ -0,0 +1,248 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures::StreamExt;
+
+use crate::{
+    client::ClientError,
+    replay::{replay, Decision, Endpoint, Transcript, TranscriptError, TranscriptRecorder},
+    state_machine::{
+        tests::utils::{shared_state, SelectFor, SigningKeyGenerator},
+        Awaiting,
+        SerializableState,
+        State,
+        StateMachine,
+        Sum,
+        TransitionOutcome,
+    },
+    Event,
+    EventStreamConfig,
+    ModelStore,
+    XaynetClient,
+};
+use xaynet_core::{
+    common::{PhaseName, PhaseStats, RoundMetadata, RoundParameters},
+    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, PublicSigningKey, Sha256},
+    mask::{MaskSeed, Model},
+    message::Tag,
+    SumDict,
+    UpdateSeedDict,
+};
+
+/// A coordinator in the sum2 phase, which fails to serve the round parameters and the
+/// seed dictionary at first. It keeps the hashes of the decrypted messages it receives.
+struct Coordinator {
+    round_params: RoundParameters,
+    coordinator_keys: EncryptKeyPair,
+    seed_dict: UpdateSeedDict,
+    failed: bool,
+    polled: bool,
+    messages: Arc<Mutex<Vec<(Tag, Sha256)>>>,
+}
+
+#[async_trait]
+impl XaynetClient for Coordinator {
+    type Error = ClientError;
+
+    async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error> {
+        if !self.failed {
+            self.failed = true;
+            return Err(ClientError::Http("service unavailable".to_string()));
+        }
+        Ok(self.round_params.clone())
+    }
+
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error> {
+        Ok(RoundMetadata {
+            round_id: 0,
+            phase: PhaseName::Idle,
+            phase_start: 0,
+            phase_duration: None,
+            compression: false,
+            changed_since_previous: true,
+        })
+    }
+
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error> {
+        Ok(PhaseStats {
+            round_id: 0,
+            phase: PhaseName::Idle,
+            count: None,
+            accepted: 0,
+            rejected: 0,
+            discarded: 0,
+        })
+    }
+
+    async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_seeds(
+        &mut self,
+        _pk: PublicSigningKey,
+    ) -> Result<Option<UpdateSeedDict>, Self::Error> {
+        if !self.polled {
+            self.polled = true;
+            return Ok(None);
+        }
+        Ok(Some(self.seed_dict.clone()))
+    }
+
+    async fn get_model(&mut self) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn get_model_by_id(&mut self, _id: &str) -> Result<Option<Model>, Self::Error> {
+        Ok(None)
+    }
+
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Self::Error> {
+        let keys = &self.coordinator_keys;
+        let msg = keys.secret.decrypt(&msg, &keys.public).unwrap();
+        self.messages
+            .lock()
+            .unwrap()
+            .push((tag, Sha256::hash(&msg)));
+        Ok(())
+    }
+}
+
+struct EmptyStore;
+
+#[async_trait]
+impl ModelStore for EmptyStore {
+    type Error = Infallible;
+    type Model = Box<Model>;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(None)
+    }
+}
+
+/// Serializes the state of a participant that has been selected for the sum task.
+fn sum_state() -> Vec<u8> {
+    let shared = shared_state(SelectFor::Sum);
+    let sk = &shared.keys.secret;
+    let seed = shared.round_params.seed.as_slice();
+    let sum = Sum {
+        ephm_keys: EncryptKeyPair::derive_from_seed(&EncryptKeySeed::zeroed()),
+        sum_signature: sk.sign_detached(&[seed, b"sum"].concat()),
+    };
+    let state: SerializableState = State::new(shared, Box::new(sum)).into();
+    bincode::serialize(&state).unwrap()
+}
+
+/// Records the sum and sum2 tasks of a participant. Returns the transcript, the emitted
+/// events and the messages received by the coordinator.
+async fn record(state: &[u8]) -> (Transcript, Vec<Event>, Vec<(Tag, Sha256)>) {
+    let shared = shared_state(SelectFor::Sum);
+    let ephm_pk = EncryptKeyPair::derive_from_seed(&EncryptKeySeed::zeroed()).public;
+    let mut key_gen = SigningKeyGenerator::new();
+    let mut seed_dict = UpdateSeedDict::new();
+    for _ in 0..4 {
+        seed_dict.insert(
+            key_gen.next().public,
+            MaskSeed::generate().encrypt(&ephm_pk),
+        );
+    }
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let coordinator = Coordinator {
+        round_params: shared.round_params.clone(),
+        coordinator_keys: EncryptKeyPair::derive_from_seed(&EncryptKeySeed::zeroed()),
+        seed_dict,
+        failed: false,
+        polled: false,
+        messages: messages.clone(),
+    };
+
+    let recorder = TranscriptRecorder::new(coordinator);
+    let transcript = recorder.handle();
+    let (mut state_machine, events) = StateMachine::restore_with_event_stream(
+        bincode::deserialize(state).unwrap(),
+        recorder,
+        EmptyStore,
+        EventStreamConfig::default(),
+    );
+    for _ in 0..20 {
+        state_machine = match state_machine.transition().await {
+            TransitionOutcome::Pending(state_machine) => state_machine,
+            TransitionOutcome::Complete(state_machine) => state_machine,
+        };
+        if let StateMachine::Awaiting(_) = state_machine {
+            break;
+        }
+    }
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+    drop(state_machine);
+
+    let messages = messages.lock().unwrap().clone();
+    (transcript.transcript(), events.collect().await, messages)
+}
+
+#[tokio::test]
+async fn test_replay_reproduces_recorded_round() {
+    sodiumoxide::init().unwrap();
+    let state = sum_state();
+    let (transcript, events, messages) = record(&state).await;
+    assert_eq!(events, vec![Event::Sum, Event::Idle]);
+    assert_eq!(
+        messages.iter().map(|(tag, _)| *tag).collect::<Vec<_>>(),
+        vec![Tag::Sum, Tag::Sum2]
+    );
+
+    // the transcript is replayed as it would be from the logs
+    let transcript = bincode::serialize(&transcript).unwrap();
+    let transcript = bincode::deserialize(&transcript).unwrap();
+    let report = replay(bincode::deserialize(&state).unwrap(), transcript).await;
+
+    assert!(report.divergence.is_none());
+    assert_eq!(report.remaining, 0);
+    assert!(matches!(report.state, SerializableState::Awaiting(_)));
+    let replayed_messages: Vec<(Tag, Sha256)> = report
+        .decisions
+        .iter()
+        .filter_map(|decision| match decision {
+            Decision::Message(tag, hash) => Some((*tag, *hash)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(replayed_messages, messages);
+    let replayed_events: Vec<Event> = report
+        .decisions
+        .iter()
+        .filter_map(|decision| match decision {
+            Decision::Notification(event) => Some(event.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(replayed_events, events);
+}
+
+#[tokio::test]
+async fn test_replay_reports_divergence() {
+    sodiumoxide::init().unwrap();
+    let state = sum_state();
+    let (transcript, _, _) = record(&state).await;
+
+    // an idle participant only fetches the round parameters, hence it diverges when the
+    // transcript expects the sum message
+    let state = match bincode::deserialize(&state).unwrap() {
+        SerializableState::Sum(sum) => State::new(sum.shared, Box::new(Awaiting)).into(),
+        state => panic!("unexpected state: {:?}", state),
+    };
+    let report = replay(state, transcript).await;
+
+    match report.divergence {
+        Some(TranscriptError::Mismatch { expected, found }) => {
+            assert_eq!(expected.endpoint, Endpoint::Message(Tag::Sum));
+            assert_eq!(found.endpoint, Endpoint::RoundParams);
+        }
+        divergence => panic!("unexpected divergence: {:?}", divergence),
+    }
+    assert_eq!(report.decisions, vec![Decision::Notification(Event::Idle)]);
+    assert!(report.remaining > 0);
+}
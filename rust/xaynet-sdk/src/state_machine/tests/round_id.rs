@@ -0,0 +1,120 @@
+use xaynet_core::{
+    common::{RoundParameters, RoundSeed},
+    crypto::ByteObject,
+};
+
+use crate::{
+    state_machine::{
+        tests::utils::{round_metadata, round_params, shared_state, SelectFor},
+        Awaiting,
+        IntoPhase,
+        MockIO,
+        Phase,
+        State,
+        StateMachine,
+        TransitionOutcome,
+    },
+    unwrap_as,
+};
+
+/// Instantiate an awaiting phase that observed no round yet.
+fn make_phase() -> Phase<Awaiting> {
+    let mut mock = MockIO::new();
+    mock.expect_notify_idle().times(1).return_const(());
+    let mut phase =
+        State::new(shared_state(SelectFor::None), Box::new(Awaiting)).into_phase(Box::new(mock));
+    phase.check_io_mock();
+    phase
+}
+
+/// The parameters of a new round, which differ from the current ones by their seed.
+fn new_round_params(seed: u8) -> RoundParameters {
+    let mut params = round_params(SelectFor::None);
+    params.seed = RoundSeed::from_slice_unchecked(&[seed; RoundSeed::LENGTH]);
+    params
+}
+
+/// Make the coordinator publish the parameters of a new round, which is expected to get
+/// the given `round_id`.
+fn expect_new_round(mock: &mut MockIO, seed: u8, round_id: u64) {
+    let params = new_round_params(seed);
+    mock.expect_get_round_params()
+        .times(1)
+        .returning(move || Ok(params.clone()));
+    mock.expect_notify_new_round().times(1).return_const(());
+    mock.expect_notify_round_id_changed()
+        .withf(move |id| *id == round_id)
+        .times(1)
+        .return_const(());
+    mock.expect_get_round_metadata()
+        .times(1)
+        .returning(|| Ok(round_metadata(true)));
+}
+
+#[tokio::test]
+async fn test_round_id_increases_monotonically() {
+    let mut phase = make_phase();
+    assert_eq!(phase.state.shared.round_id, 0);
+
+    // the round parameters didn't change
+    phase.with_io_mock(|mock| {
+        mock.expect_get_round_params()
+            .times(1)
+            .returning(|| Ok(round_params(SelectFor::None)));
+    });
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Pending);
+    assert_eq!(state_machine.round_id(), 0);
+    assert!(state_machine.round_params().is_none());
+
+    // a new round started
+    let mut phase = unwrap_as!(state_machine, StateMachine::Awaiting);
+    phase.with_io_mock(|mock| expect_new_round(mock, 1, 1));
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    assert_eq!(state_machine.round_id(), 1);
+    assert_eq!(state_machine.round_params(), Some(&new_round_params(1)));
+
+    // the round ID survives saving and restoring the state machine
+    let mut phase = unwrap_as!(state_machine, StateMachine::NewRound);
+    phase.check_io_mock();
+    let state = StateMachine::from(phase).save();
+    let mut mock = MockIO::new();
+    mock.expect_notify_new_round().times(1).return_const(());
+    let state_machine = StateMachine::restore_with_io(state, Box::new(mock));
+    assert_eq!(state_machine.round_id(), 1);
+    assert_eq!(state_machine.round_params(), Some(&new_round_params(1)));
+
+    // another round started
+    let mut phase = unwrap_as!(state_machine, StateMachine::NewRound);
+    phase.with_io_mock(|mock| expect_new_round(mock, 2, 2));
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    assert_eq!(state_machine.round_id(), 2);
+    assert_eq!(state_machine.round_params(), Some(&new_round_params(2)));
+}
+
+#[tokio::test]
+async fn test_new_round_of_training_plan() {
+    let mut phase = make_phase();
+
+    // a new round of a training plan started
+    let mut params = new_round_params(1);
+    params.plan_id = Some("first".to_string());
+    let expected = params.clone();
+    phase.with_io_mock(move |mock| {
+        mock.expect_get_round_params()
+            .times(1)
+            .returning(move || Ok(params.clone()));
+        mock.expect_notify_new_round().times(1).return_const(());
+        mock.expect_notify_round_id_changed()
+            .times(1)
+            .return_const(());
+        mock.expect_notify_training_plan()
+            .withf(|plan_id| plan_id == "first")
+            .times(1)
+            .return_const(());
+        mock.expect_get_round_metadata()
+            .times(1)
+            .returning(|| Ok(round_metadata(true)));
+    });
+    let state_machine = unwrap_as!(phase.step().await, TransitionOutcome::Complete);
+    assert_eq!(state_machine.round_params(), Some(&expected));
+}
@@ -0,0 +1,127 @@
+use futures::StreamExt;
+use xaynet_core::{
+    crypto::{ByteObject, EncryptKeyPair, SigningKeyPair},
+    mask::MaskSeed,
+    message::Payload,
+    SumDict,
+};
+
+use crate::{
+    settings::{MaxMessageSize, PetSettings},
+    state_machine::{
+        tests::{
+            coordinator::{model, Coordinator, Store},
+            utils::{shared_state, SelectFor},
+        },
+        StateMachine,
+    },
+    Event,
+    EventStream,
+    EventStreamConfig,
+};
+
+fn new_state_machine(coordinator: &Coordinator) -> (StateMachine, EventStream) {
+    let mut settings = PetSettings::new(shared_state(SelectFor::None).keys);
+    settings.max_message_size = MaxMessageSize::unlimited();
+    StateMachine::with_event_stream(
+        settings,
+        coordinator.clone(),
+        Store(model()),
+        EventStreamConfig::default(),
+    )
+}
+
+#[tokio::test]
+async fn test_run_until_pending_sum_round() {
+    sodiumoxide::init().unwrap();
+    let mut coordinator = Coordinator::new(SelectFor::Sum);
+    coordinator.seeds = vec![MaskSeed::generate()];
+    let (state_machine, events) = new_state_machine(&coordinator);
+
+    let (state_machine, steps) = state_machine.run_until_pending(usize::MAX).await;
+    assert!(steps > 0);
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+    assert!(matches!(
+        coordinator.payloads().as_slice(),
+        [Payload::Sum(_), Payload::Sum2(_)]
+    ));
+
+    // the round is over, the state machine is pending until the next one
+    let (state_machine, steps) = state_machine.run_until_pending(usize::MAX).await;
+    assert_eq!(steps, 0);
+    drop(state_machine);
+    assert_eq!(
+        events.collect::<Vec<_>>().await,
+        vec![
+            Event::Idle,
+            Event::NewRound,
+            Event::RoundIdChanged(1),
+            Event::Sum,
+            Event::Idle
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_run_until_pending_update_round() {
+    sodiumoxide::init().unwrap();
+    let mut coordinator = Coordinator::new(SelectFor::Update);
+    let mut sum_dict = SumDict::new();
+    sum_dict.insert(
+        SigningKeyPair::generate().public,
+        EncryptKeyPair::generate().public,
+    );
+    coordinator.sum_dict = Some(sum_dict);
+    let (state_machine, events) = new_state_machine(&coordinator);
+
+    let (state_machine, steps) = state_machine.run_until_pending(usize::MAX).await;
+    assert!(steps > 0);
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+    assert!(matches!(
+        coordinator.payloads().as_slice(),
+        [Payload::Update(_)]
+    ));
+    drop(state_machine);
+    assert_eq!(
+        events.collect::<Vec<_>>().await,
+        vec![
+            Event::Idle,
+            Event::NewRound,
+            Event::RoundIdChanged(1),
+            Event::Update,
+            Event::LoadModel,
+            Event::Idle
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_run_until_pending_max_steps() {
+    sodiumoxide::init().unwrap();
+    let coordinator = || {
+        let mut coordinator = Coordinator::new(SelectFor::Sum);
+        coordinator.seeds = vec![MaskSeed::generate()];
+        coordinator
+    };
+    let (state_machine, _events) = new_state_machine(&coordinator());
+    let (_, round_steps) = state_machine.run_until_pending(usize::MAX).await;
+
+    let coordinator = coordinator();
+    let (state_machine, _events) = new_state_machine(&coordinator);
+
+    let (state_machine, steps) = state_machine.run_until_pending(0).await;
+    assert_eq!(steps, 0);
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+
+    // the new round and the sum phases
+    let (state_machine, steps) = state_machine.run_until_pending(2).await;
+    assert_eq!(steps, 2);
+    assert!(matches!(state_machine, StateMachine::Sum(_)));
+    assert!(coordinator.payloads().is_empty());
+
+    // the rest of the round
+    let (state_machine, steps) = state_machine.run_until_pending(usize::MAX).await;
+    assert_eq!(steps, round_steps - 2);
+    assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+    assert_eq!(coordinator.payloads().len(), 2);
+}
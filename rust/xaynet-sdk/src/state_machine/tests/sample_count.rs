@@ -0,0 +1,170 @@
+use std::{convert::Infallible, num::NonZeroU64};
+
+use async_trait::async_trait;
+use num::traits::ToPrimitive;
+use xaynet_core::{
+    crypto::{EncryptKeyPair, SigningKeyPair},
+    mask::{Aggregation, BoundType, FromPrimitives, Model},
+    message::Payload,
+    SumDict,
+};
+
+use crate::{
+    settings::{MaxMessageSize, PetSettings},
+    state_machine::{
+        tests::{
+            coordinator::{Coordinator, MODEL_LENGTH},
+            utils::{mask_config, SelectFor},
+        },
+        StateMachine,
+    },
+    ModelStore,
+    Notify,
+    MAX_SAMPLE_COUNT,
+};
+
+struct Notifier;
+
+impl Notify for Notifier {}
+
+/// A store that knows the number of samples its model was trained on.
+#[derive(Clone)]
+struct WeightedStore {
+    model: Model,
+    sample_count: Option<NonZeroU64>,
+}
+
+#[async_trait]
+impl ModelStore for WeightedStore {
+    type Error = Infallible;
+    type Model = Box<Model>;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(Some(Box::new(self.model.clone())))
+    }
+
+    fn sample_count(&self) -> Option<NonZeroU64> {
+        self.sample_count
+    }
+}
+
+/// Runs the update tasks of participants with the given constant models and sample counts,
+/// and returns the global model.
+async fn weighted_global_model(participants: Vec<(f32, Option<u64>)>) -> Vec<f64> {
+    normalized_global_model(None, participants).await
+}
+
+/// Like [`weighted_global_model()`], but the coordinator sets the given maximal sample
+/// count in the round parameters.
+async fn normalized_global_model(
+    max_sample_count: Option<u64>,
+    participants: Vec<(f32, Option<u64>)>,
+) -> Vec<f64> {
+    sodiumoxide::init().unwrap();
+    // the default masking configuration of the coordinator, whose bound type is `B0`
+    let mut coordinator = Coordinator::new(SelectFor::Update);
+    assert_eq!(mask_config().bound_type, BoundType::B0);
+    coordinator.round_params.max_sample_count = max_sample_count.and_then(NonZeroU64::new);
+    let sum_pk = SigningKeyPair::generate().public;
+    let ephm_keys = EncryptKeyPair::generate();
+    let mut sum_dict = SumDict::new();
+    sum_dict.insert(sum_pk, ephm_keys.public);
+    coordinator.sum_dict = Some(sum_dict);
+
+    for (weight, sample_count) in participants {
+        let mut settings = PetSettings::new(SigningKeyPair::generate());
+        settings.max_message_size = MaxMessageSize::unlimited();
+        let store = WeightedStore {
+            model: Model::from_primitives(vec![weight; MODEL_LENGTH].into_iter()).unwrap(),
+            sample_count: sample_count.and_then(NonZeroU64::new),
+        };
+        let state_machine = StateMachine::new(settings, coordinator.clone(), store, Notifier);
+        let (state_machine, _) = state_machine.run_until_pending(usize::MAX).await;
+        assert!(matches!(state_machine, StateMachine::Awaiting(_)));
+    }
+
+    // aggregate the masked models and their masks like the coordinator and the sum
+    // participant
+    let mask_config = coordinator.round_params.mask_config;
+    let mut models = Aggregation::new(mask_config, MODEL_LENGTH);
+    let mut masks = Aggregation::new(mask_config, MODEL_LENGTH);
+    for payload in coordinator.payloads() {
+        let update = match payload {
+            Payload::Update(update) => update,
+            payload => panic!("unexpected message: {:?}", payload),
+        };
+        let seed = update.local_seed_dict[&sum_pk]
+            .decrypt(&ephm_keys.public, &ephm_keys.secret)
+            .unwrap();
+        models.aggregate(update.masked_model);
+        masks.aggregate(seed.derive_mask(MODEL_LENGTH, mask_config));
+    }
+    models
+        .unmask(masks.into())
+        .iter()
+        .map(|weight| weight.to_f64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_weighted_by_sample_count() {
+    // the second participant trained on 90% of the samples
+    let global_model = weighted_global_model(vec![(0.0, Some(1)), (1.0, Some(9))]).await;
+    for weight in global_model {
+        assert!((weight - 0.9).abs() < 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn test_weighted_by_large_sample_count() {
+    // the counts exceed the bound of the masking configuration, but not their normalization
+    let global_model = weighted_global_model(vec![(0.0, Some(1_000)), (1.0, Some(9_000))]).await;
+    for weight in global_model {
+        assert!((weight - 0.9).abs() < 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn test_weighted_by_capped_sample_count() {
+    // the counts are capped to the maximal sample count
+    let global_model = weighted_global_model(vec![
+        (0.0, Some(MAX_SAMPLE_COUNT)),
+        (1.0, Some(MAX_SAMPLE_COUNT + 1)),
+    ])
+    .await;
+    for weight in global_model {
+        assert!((weight - 0.5).abs() < 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn test_weighted_by_scalar() {
+    // without sample counts, the models are weighted by the scalar of the settings
+    let global_model = weighted_global_model(vec![(0.0, None), (1.0, None)]).await;
+    for weight in global_model {
+        assert!((weight - 0.5).abs() < 1e-6);
+    }
+}
+
+#[tokio::test]
+async fn test_weighted_by_small_sample_count() {
+    // the counts are normalized by the maximal sample count of the coordinator, such that
+    // the scaled weights keep their precision, unlike when they are normalized by
+    // `MAX_SAMPLE_COUNT`
+    let (first, second) = (0.123_456_79, 0.987_654_3);
+    let expected = (f64::from(first) + 2.0 * f64::from(second)) / 3.0;
+    let global_model =
+        normalized_global_model(Some(3), vec![(first, Some(1)), (second, Some(2))]).await;
+    for weight in global_model {
+        assert!((weight - expected).abs() < 1e-9);
+    }
+}
+
+#[tokio::test]
+async fn test_weighted_by_capped_small_sample_count() {
+    // the counts are capped to the maximal sample count of the coordinator
+    let global_model = normalized_global_model(Some(1), vec![(0.0, Some(1)), (1.0, Some(2))]).await;
+    for weight in global_model {
+        assert!((weight - 0.5).abs() < 1e-6);
+    }
+}
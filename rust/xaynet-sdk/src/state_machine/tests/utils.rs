@@ -1,10 +1,13 @@
 use xaynet_core::{
-    common::{RoundParameters, RoundSeed},
+    common::{PhaseName, RoundMetadata, RoundParameters, RoundSeed},
     crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, SigningKeyPair, SigningKeySeed},
     mask::{self, MaskConfig, Scalar},
 };
 
-use crate::{settings::MaxMessageSize, state_machine::SharedState};
+use crate::{
+    settings::{CircuitBreakerSettings, MaxMessageSize, DEFAULT_YIELD_INTERVAL},
+    state_machine::{CircuitBreaker, Deadline, SharedState},
+};
 
 #[macro_export]
 macro_rules! unwrap_as {
@@ -39,6 +42,13 @@ macro_rules! unwrap_step {
             $crate::state_machine::StateMachine::Awaiting
         )
     };
+    ($phase:expr, $transition_outcome:path, awaiting_consent) => {
+        unwrap_step!(
+            $phase,
+            $transition_outcome,
+            $crate::state_machine::StateMachine::AwaitingConsent
+        )
+    };
     ($phase:expr, $transition_outcome:path, sum) => {
         unwrap_step!(
             $phase,
@@ -109,7 +119,8 @@ macro_rules! save_and_restore {
         let mut phase = $phase;
         let io_mock = std::mem::replace(&mut phase.io, Box::new(MockIO::new()));
         let serializable_state = Into::<$crate::state_machine::SerializableState>::into(phase);
-        // TODO: actually serialize the state here
+        let serializable_state =
+            bincode::deserialize(&bincode::serialize(&serializable_state).unwrap()).unwrap();
         let state = $crate::unwrap_as!(
             serializable_state,
             $crate::state_machine::SerializableState::$state
@@ -148,6 +159,21 @@ pub fn round_params(task: SelectFor) -> RoundParameters {
         seed: RoundSeed::zeroed(),
         mask_config: mask_config().into(),
         model_length: 0,
+        plan_id: None,
+        max_sample_count: None,
+    }
+}
+
+/// Round metadata of an idle phase, telling whether the global model changed since the
+/// previous round.
+pub fn round_metadata(changed_since_previous: bool) -> RoundMetadata {
+    RoundMetadata {
+        round_id: 1,
+        phase: PhaseName::Idle,
+        phase_start: 0,
+        phase_duration: None,
+        compression: false,
+        changed_since_previous,
     }
 }
 
@@ -157,6 +183,19 @@ pub fn shared_state(task: SelectFor) -> Box<SharedState> {
         scalar: Scalar::unit(),
         message_size: MaxMessageSize::unlimited(),
         round_params: round_params(task),
+        yield_interval: DEFAULT_YIELD_INTERVAL,
+        circuit_breaker: CircuitBreaker::new(CircuitBreakerSettings::default()),
+        confirm_sum2: false,
+        require_consent: false,
+        consent_timeout: None,
+        deadline: Deadline::default(),
+        round_id: 0,
+        next_keys: None,
+        deterministic_seed: None,
+        task_seed: None,
+        dp: None,
+        compression: false,
+        coordinator_compression: None,
     })
 }
 
@@ -0,0 +1,71 @@
+//! Constructors of states in a given phase, for testing the crates that save and restore
+//! the state machine. They are only available with the `testutils` feature.
+
+use super::{
+    AwaitingConsent,
+    ConsentRequest,
+    SerializableState,
+    SharedState,
+    State,
+    Sum2,
+    Update,
+};
+use xaynet_core::{
+    crypto::{ByteObject, EncryptKeyPair, Signature},
+    mask::MaskObject,
+    SumDict,
+};
+
+impl SerializableState {
+    /// Take the shared state out of the state.
+    fn into_shared(self) -> Box<SharedState> {
+        match self {
+            SerializableState::NewRound(state) => state.shared,
+            SerializableState::Awaiting(state) => state.shared,
+            SerializableState::AwaitingConsent(state) => state.shared,
+            SerializableState::Sum(state) => state.shared,
+            SerializableState::Update(state) => state.shared,
+            SerializableState::Sum2(state) => state.shared,
+            SerializableState::SendingSum(state) => state.shared,
+            SerializableState::SendingUpdate(state) => state.shared,
+            SerializableState::SendingSum2(state) => state.shared,
+        }
+    }
+
+    /// Build the state of a participant that fetched the given sum dictionary in the
+    /// update phase, with the shared state of this state.
+    pub fn into_update(self, sum_dict: SumDict) -> Self {
+        let mut update = Update::new(Signature::zeroed(), Signature::zeroed());
+        update.sum_dict = Some(sum_dict);
+        State::new(self.into_shared(), Box::new(update)).into()
+    }
+
+    /// Build the state of a participant that aggregated the given global mask in the
+    /// sum2 phase, with the shared state of this state.
+    pub fn into_sum2(self, mask: MaskObject) -> Self {
+        let mut sum2 = Sum2::new(EncryptKeyPair::generate(), Signature::zeroed());
+        sum2.mask = Some(mask);
+        State::new(self.into_shared(), Box::new(sum2)).into()
+    }
+
+    /// Build the state of a participant that awaits the consent of the user for the
+    /// given request, with the shared state of this state.
+    pub fn into_awaiting_consent(self, request: ConsentRequest) -> Self {
+        let awaiting_consent = AwaitingConsent::new(request, Signature::zeroed(), None);
+        State::new(self.into_shared(), Box::new(awaiting_consent)).into()
+    }
+
+    /// Set the number of rounds the participant observed (see
+    /// [`StateMachine::round_id()`]).
+    ///
+    /// [`StateMachine::round_id()`]: crate::StateMachine::round_id
+    pub fn set_round_id(&mut self, round_id: u64) {
+        self.shared_mut().round_id = round_id;
+    }
+
+    /// Forget the clock readings the state is anchored to, so that the states saved at
+    /// different times can be compared.
+    pub fn clear_clock_readings(&mut self) {
+        self.shared_mut().circuit_breaker.clear_clock_readings();
+    }
+}
@@ -1,13 +1,28 @@
+use std::{num::NonZeroU64, ops::Range};
+
 use async_trait::async_trait;
 
+use crate::{ConsentRequest, LocalModelConfig};
+
 use xaynet_core::{
-    common::RoundParameters,
+    common::{PhaseStats, RoundMetadata, RoundParameters},
     mask::Model,
+    message::Tag,
     SumDict,
     SumParticipantPublicKey,
     UpdateSeedDict,
 };
 
+/// The largest number of samples by which a local model is weighted (see
+/// [`ModelStore::sample_count()`]), unless the coordinator sets another one in the round
+/// parameters. Larger counts are capped to it.
+///
+/// The masked scalar must not exceed one, otherwise the weights scaled by it are clamped.
+/// Hence the local models are masked with their number of samples divided by the maximal
+/// sample count, which is the same for all participants and cancels out in the global
+/// model.
+pub const MAX_SAMPLE_COUNT: u64 = 1_000_000;
+
 /// A trait used by the [`StateMachine`] to emit notifications upon
 /// certain events.
 ///
@@ -16,6 +31,16 @@ pub trait Notify {
     /// Emit a notification when a new round of federated learning
     /// starts
     fn new_round(&mut self) {}
+    /// Emit a notification right after [`Notify::new_round()`] with the identifier of
+    /// the new round, i.e. the number of rounds the participant has seen. Apps can
+    /// persist it to avoid training again for the same round after a restart.
+    ///
+    /// The same identifier is passed to the [`ModelStore`] in [`LocalModelConfig`].
+    fn round_id_changed(&mut self, _round_id: u64) {}
+    /// Emit a notification right after [`Notify::round_id_changed()`] with the id of the
+    /// training plan the new round belongs to. It is only emitted if the coordinator runs
+    /// training plans.
+    fn training_plan(&mut self, _plan_id: String) {}
     /// Emit a notification when the participant has been selected for
     /// the sum task
     fn sum(&mut self) {}
@@ -28,6 +53,34 @@ pub trait Notify {
     /// Emit a notification when the participant should populate the
     /// model store (see [`ModelStore`]).
     fn load_model(&mut self) {}
+    /// Emit a notification when the participant aggregated the global mask in the sum2
+    /// phase and awaits its confirmation (see [`PetSettings::confirm_sum2`]).
+    ///
+    /// [`PetSettings::confirm_sum2`]: crate::settings::PetSettings::confirm_sum2
+    fn sum2_mask_ready(&mut self) {}
+    /// Emit a notification when the coordinator rejected an update message because the
+    /// participant exceeded its update participation quota. The app should back off from
+    /// the update task for a while.
+    fn quota_exceeded(&mut self) {}
+    /// Emit a notification when the participant has been selected for a task and awaits
+    /// the consent of the user to take part in it (see [`PetSettings::require_consent`]).
+    ///
+    /// [`PetSettings::require_consent`]: crate::settings::PetSettings::require_consent
+    fn awaiting_consent(&mut self, _request: ConsentRequest) {}
+    /// Emit a notification when the participant abandoned its task because there is not
+    /// enough time remaining to complete it before the end of the phase (see
+    /// [`PetSettings::deadline_margin`]).
+    ///
+    /// [`PetSettings::deadline_margin`]: crate::settings::PetSettings::deadline_margin
+    fn insufficient_time(&mut self) {}
+    /// Emit a notification when the participant abandoned the sum task because its
+    /// ephemeral keys don't match the ephemeral public key it sent in the sum message, so
+    /// it could not decrypt the mask seeds of the update participants.
+    fn ephemeral_keys_mismatch(&mut self) {}
+    /// Emit a notification after [`Notify::round_id_changed()`] when the coordinator
+    /// reports that the global model didn't change since the previous round. A global
+    /// model that was already downloaded doesn't need to be downloaded again.
+    fn model_unchanged(&mut self) {}
 }
 
 /// A trait used by the [`StateMachine`] to load the model trained by
@@ -42,6 +95,72 @@ pub trait ModelStore {
     /// Attempt to load the model. If the model is not yet available,
     /// `Ok(None)` should be returned.
     async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error>;
+
+    /// Attempt to load the model, given the configuration of the model that is
+    /// expected in the current round. This is the method called by the
+    /// [`StateMachine`], so stores that need the masking parameters or the round
+    /// identifier to prepare the model should override it.
+    ///
+    /// The default implementation ignores the configuration and delegates to
+    /// [`ModelStore::load_model()`].
+    ///
+    /// [`StateMachine`]: crate::StateMachine
+    async fn load_model_with_config(
+        &mut self,
+        _config: &LocalModelConfig,
+    ) -> Result<Option<Self::Model>, Self::Error> {
+        self.load_model().await
+    }
+
+    /// Return the number of samples the local model was trained on, if the store knows
+    /// it. The local models are weighted by their number of samples in the global model,
+    /// such that the participants with more data contribute more to it. The count is
+    /// masked like the model, hence the coordinator only learns the total number of
+    /// samples of the round.
+    ///
+    /// The count is normalized by the maximal sample count of the round parameters, or by
+    /// [`MAX_SAMPLE_COUNT`] if the coordinator doesn't set one, such that the weighting
+    /// works with every masking configuration of the coordinator.
+    ///
+    /// The default implementation returns `None`: the model is weighted by
+    /// [`PetSettings::scalar`].
+    ///
+    /// [`PetSettings::scalar`]: crate::settings::PetSettings::scalar
+    fn sample_count(&self) -> Option<NonZeroU64> {
+        None
+    }
+
+    /// Return the number of weights in the chunks in which the model is loaded, if the
+    /// store loads it in chunks. In that case, the [`StateMachine`] calls
+    /// [`ModelStore::load_model_chunk()`] for each chunk in turn and masks it right away,
+    /// so that the whole model is never loaded at once.
+    ///
+    /// The default implementation returns `None`: the model is loaded at once.
+    ///
+    /// [`StateMachine`]: crate::StateMachine
+    fn chunk_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Attempt to load the weights of the model in the given `range`, given the
+    /// configuration of the model that is expected in the current round. If the model
+    /// is not yet available, `Ok(None)` should be returned. This is only called if
+    /// [`ModelStore::chunk_size()`] returns a chunk size.
+    ///
+    /// The default implementation loads the whole model with
+    /// [`ModelStore::load_model_with_config()`] and copies the range, so stores that
+    /// load the model in chunks should override it.
+    async fn load_model_chunk(
+        &mut self,
+        config: &LocalModelConfig,
+        range: Range<usize>,
+    ) -> Result<Option<Model>, Self::Error> {
+        let model = self.load_model_with_config(config).await?;
+        Ok(model.map(|model| {
+            let weights = model.as_ref().iter().skip(range.start).take(range.len());
+            weights.cloned().collect()
+        }))
+    }
 }
 
 /// A trait used by the [`StateMachine`] to communicate with the
@@ -55,6 +174,14 @@ pub trait XaynetClient {
     /// Retrieve the current round parameters
     async fn get_round_params(&mut self) -> Result<RoundParameters, Self::Error>;
 
+    /// Retrieve the metadata of the current round, i.e. the round id, the current phase
+    /// and its deadlines.
+    async fn get_round_metadata(&mut self) -> Result<RoundMetadata, Self::Error>;
+
+    /// Retrieve the number of messages the coordinator handled so far in the current
+    /// phase.
+    async fn get_phase_stats(&mut self) -> Result<PhaseStats, Self::Error>;
+
     /// Retrieve the current sum dictionary, if available.
     async fn get_sums(&mut self) -> Result<Option<SumDict>, Self::Error>;
 
@@ -68,6 +195,16 @@ pub trait XaynetClient {
     /// Retrieve the current global model, if available.
     async fn get_model(&mut self) -> Result<Option<Model>, Self::Error>;
 
+    /// Retrieve the global model with the given id, if it exists.
+    ///
+    /// This requires a coordinator with persisted global models. Unknown ids are
+    /// returned as `Ok(None)`.
+    async fn get_model_by_id(&mut self, id: &str) -> Result<Option<Model>, Self::Error>;
+
     /// Send an encrypted and signed PET message to the coordinator.
-    async fn send_message(&mut self, msg: Vec<u8>) -> Result<(), Self::Error>;
+    ///
+    /// The `tag` of the message should be sent unencrypted along with the message, so
+    /// that the coordinator can discard the messages that are not expected in the
+    /// current phase without decrypting them.
+    async fn send_message(&mut self, tag: Tag, msg: Vec<u8>) -> Result<(), Self::Error>;
 }
@@ -0,0 +1,69 @@
+//! Helpers for keeping the CPU heavy sections of the state machine cooperative.
+//!
+//! Masking a model, aggregating masks or encrypting thousands of seeds can take a long
+//! time. Executing this work inline inside [`StateMachine::transition()`] would block the
+//! executor thread and starve the other tasks running on it. These helpers either
+//! offload the work onto a blocking thread pool, or periodically yield back to the
+//! executor.
+//!
+//! [`StateMachine::transition()`]: crate::StateMachine::transition
+
+use std::panic;
+
+use tokio::{runtime::Handle, task};
+
+/// Run the given CPU heavy closure.
+///
+/// If `offload` is `true` and the caller runs within a tokio runtime, the closure is
+/// executed on the runtime's blocking thread pool so that the executor thread can make
+/// progress on other tasks in the meantime. Otherwise, the closure is executed inline.
+pub(crate) async fn run_blocking<F, T>(offload: bool, f: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    if offload {
+        if let Ok(handle) = Handle::try_current() {
+            return match handle.spawn_blocking(f).await {
+                Ok(output) => output,
+                Err(e) => match e.try_into_panic() {
+                    // propagate the panic, as if the closure had been executed inline
+                    Ok(reason) => panic::resume_unwind(reason),
+                    Err(e) => panic!("blocking task failed: {}", e),
+                },
+            };
+        }
+    }
+    f()
+}
+
+/// Counter that yields back to the executor every `interval` processed elements.
+pub(crate) struct Yielder {
+    /// Number of elements to process between two yield points. If `0`, it never yields.
+    interval: usize,
+    /// Number of elements processed since the last yield point.
+    processed: usize,
+}
+
+impl Yielder {
+    /// Create a new yielder. If `interval` is `0`, it never yields.
+    pub(crate) fn new(interval: usize) -> Self {
+        Self {
+            interval,
+            processed: 0,
+        }
+    }
+
+    /// Record that an element has been processed, and yield back to the executor if
+    /// `interval` elements have been processed since the last yield point.
+    pub(crate) async fn tick(&mut self) {
+        if self.interval == 0 {
+            return;
+        }
+        self.processed += 1;
+        if self.processed >= self.interval {
+            self.processed = 0;
+            task::yield_now().await;
+        }
+    }
+}
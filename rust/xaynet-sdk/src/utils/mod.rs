@@ -1,2 +1,3 @@
 // TODO: move to the e2e package
 pub mod concurrent_futures;
+pub(crate) mod cooperative;
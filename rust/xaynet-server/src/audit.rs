@@ -0,0 +1,739 @@
+//! An append-only audit log of the administrative actions.
+//!
+//! An administrative action is recorded by [`AuditLog::begin()`] before it takes effect
+//! and its outcome is recorded by [`AuditLog::finish()`] afterwards. If the audit log
+//! can't be written, the administrative action must fail.
+//!
+//! The audit log is a file of line-oriented JSON records. Each record contains a
+//! sequence number and the hash of the previous record, which allows [`verify()`] to
+//! detect removed, reordered, truncated or tampered records.
+//!
+//! The coordinator records the actions of its administrative routes in the audit log of the
+//! `[admin]` settings, if any (see [`Admin`]).
+//!
+//! The oldest records can be purged once they are exported (see [`retention`]). The log then
+//! starts at a later record, which chains to the last exported record, and the newest record
+//! is never purged such that the chain goes on.
+//!
+//! [`Admin`]: crate::rest::Admin
+//! [`retention`]: crate::retention
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use chrono::{DateTime, Utc};
+use displaydoc::Display;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::crypto::hash::sha256;
+use thiserror::Error;
+
+use crate::{metric, metrics::Measurement};
+
+/// The value of the redacted parameters.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The parameters whose name contains one of these words are redacted.
+const SECRET_PARAMETERS: [&str; 5] = ["credential", "key", "password", "secret", "token"];
+
+/// The previous hash of the first record.
+const GENESIS_HASH: [u8; sha256::DIGESTBYTES] = [0; sha256::DIGESTBYTES];
+
+/// Errors related to writing the audit log.
+#[derive(Debug, Display, Error)]
+pub enum AuditError {
+    /// Failed to write the audit log: {0}.
+    Io(#[from] io::Error),
+    /// Failed to serialize the audit record: {0}.
+    Serialization(#[from] serde_json::Error),
+    /// The existing audit log is invalid: {0}.
+    Invalid(#[from] VerifyError),
+    /// The audit log is poisoned by a previous failure.
+    Poisoned,
+    /// The record {0} has no valid timestamp.
+    Timestamp(u64),
+}
+
+/// Errors related to the verification of the audit log.
+#[derive(Debug, Display, Error)]
+pub enum VerifyError {
+    /// Failed to read the audit log: {0}.
+    Io(#[from] io::Error),
+    /// Malformed record at line {0}.
+    Malformed(u64),
+    /// The audit log is truncated at line {0}.
+    Truncated(u64),
+    /// Expected the record {expected} but found the record {found}.
+    Sequence { expected: u64, found: u64 },
+    /// The record {0} doesn't follow the previous record.
+    BrokenChain(u64),
+    /// The record {0} has been tampered with.
+    HashMismatch(u64),
+}
+
+/// The outcome of an administrative action.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The action is about to take effect.
+    Pending,
+    /// The action of the record `begin` succeeded.
+    Succeeded { begin: u64 },
+    /// The action of the record `begin` failed.
+    Failed { begin: u64, error: String },
+}
+
+/// A record of the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The sequence number of the record, starting at `0`.
+    pub seq: u64,
+    /// The time of the record, in RFC 3339 format.
+    pub timestamp: String,
+    /// The name of the administrative action.
+    pub action: String,
+    /// The parameters of the action, with the secrets redacted.
+    pub parameters: BTreeMap<String, String>,
+    /// The fingerprint of the token that authenticated the action, if any.
+    pub token_fingerprint: Option<String>,
+    /// The outcome of the action.
+    pub outcome: Outcome,
+    /// The hex encoded hash of the previous record.
+    pub prev_hash: String,
+    /// The hex encoded hash of this record.
+    pub hash: String,
+}
+
+impl AuditRecord {
+    /// Compute the hash of the record, which covers all the fields but the hash itself.
+    fn compute_hash(&self) -> Result<String, serde_json::Error> {
+        let unhashed = AuditRecord {
+            hash: String::new(),
+            ..self.clone()
+        };
+        let bytes = serde_json::to_vec(&unhashed)?;
+        Ok(hex::encode(sha256::hash(&bytes)))
+    }
+}
+
+/// The head of a valid audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainHead {
+    /// The number of records.
+    pub len: u64,
+    /// The hex encoded hash of the last record.
+    pub last_hash: String,
+}
+
+impl Default for ChainHead {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            last_hash: hex::encode(GENESIS_HASH),
+        }
+    }
+}
+
+/// A pending administrative action, returned by [`AuditLog::begin()`].
+#[derive(Debug)]
+#[must_use = "the outcome of the action must be recorded with `AuditLog::finish()`"]
+pub struct AuditEntry {
+    seq: u64,
+    action: String,
+    token_fingerprint: Option<String>,
+}
+
+/// An append-only audit log file.
+#[derive(Debug)]
+pub struct AuditLog {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    path: PathBuf,
+    file: File,
+    head: ChainHead,
+}
+
+impl AuditLog {
+    /// Open the audit log at the given path, creating it if necessary.
+    ///
+    /// # Errors
+    /// Fails if the file can't be opened or if the existing records are invalid.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AuditError> {
+        let path = path.as_ref();
+        let head = match File::open(path) {
+            Ok(file) => verify(BufReader::new(file))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => ChainHead::default(),
+            Err(err) => return Err(err.into()),
+        };
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            inner: Mutex::new(Inner {
+                path: path.to_path_buf(),
+                file,
+                head,
+            }),
+        })
+    }
+
+    /// Open an existing audit log read-only, so that writing any record fails.
+    #[cfg(test)]
+    pub(crate) fn read_only(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let file = File::open(&path).unwrap();
+        let head = verify(BufReader::new(file.try_clone().unwrap())).unwrap();
+        Self {
+            inner: Mutex::new(Inner { path, file, head }),
+        }
+    }
+
+    /// Record an administrative action before it takes effect.
+    ///
+    /// The secret parameters are redacted and only a fingerprint of the authentication
+    /// token is recorded.
+    ///
+    /// # Errors
+    /// Fails if the record can't be written, in which case the action must not be
+    /// executed.
+    pub fn begin<I, K, V>(
+        &self,
+        action: &str,
+        parameters: I,
+        token: Option<&str>,
+    ) -> Result<AuditEntry, AuditError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let parameters = redact(parameters);
+        let token_fingerprint = token.map(token_fingerprint);
+        let seq = self.append(
+            action,
+            parameters,
+            token_fingerprint.clone(),
+            Outcome::Pending,
+        )?;
+        Ok(AuditEntry {
+            seq,
+            action: action.to_string(),
+            token_fingerprint,
+        })
+    }
+
+    /// Record the outcome of an administrative action.
+    ///
+    /// # Errors
+    /// Fails if the record can't be written.
+    pub fn finish<T, E: fmt::Display>(
+        &self,
+        entry: AuditEntry,
+        result: &Result<T, E>,
+    ) -> Result<(), AuditError> {
+        let (outcome, tag) = match result {
+            Ok(_) => (Outcome::Succeeded { begin: entry.seq }, "succeeded"),
+            Err(err) => (
+                Outcome::Failed {
+                    begin: entry.seq,
+                    error: err.to_string(),
+                },
+                "failed",
+            ),
+        };
+        self.append(
+            &entry.action,
+            BTreeMap::new(),
+            entry.token_fingerprint,
+            outcome,
+        )?;
+        metric!(
+            Measurement::AdminAction,
+            1,
+            ("action", entry.action),
+            ("outcome", tag),
+        );
+        Ok(())
+    }
+
+    /// Return the oldest records which were written before the `cutoff`, at most `limit` of
+    /// them. The newest record is never returned, since it can't be purged.
+    ///
+    /// # Errors
+    /// Fails if the audit log can't be read or if a record has no valid timestamp.
+    pub fn expiring(
+        &self,
+        cutoff: DateTime<Utc>,
+        limit: usize,
+    ) -> Result<Vec<AuditRecord>, AuditError> {
+        let inner = self.inner.lock().map_err(|_| AuditError::Poisoned)?;
+        let mut expiring = Vec::new();
+        for line in BufReader::new(File::open(&inner.path)?).lines() {
+            let record: AuditRecord = serde_json::from_str(&line?)?;
+            if expiring.len() == limit || record.seq + 1 >= inner.head.len {
+                break;
+            }
+            let timestamp = DateTime::parse_from_rfc3339(&record.timestamp)
+                .map_err(|_| AuditError::Timestamp(record.seq))?;
+            if timestamp >= cutoff {
+                break;
+            }
+            expiring.push(record);
+        }
+        Ok(expiring)
+    }
+
+    /// Remove the records up to the record `seq` included, once they are exported. The newest
+    /// record is never removed. Returns the number of removed records.
+    ///
+    /// The retained records are written to a new file, which then replaces the audit log, so
+    /// that a failure never leaves a partially purged audit log behind.
+    ///
+    /// # Errors
+    /// Fails if the audit log can't be rewritten, in which case it is unchanged.
+    pub fn remove_through(&self, seq: u64) -> Result<usize, AuditError> {
+        let mut inner = self.inner.lock().map_err(|_| AuditError::Poisoned)?;
+        let mut retained = Vec::new();
+        let mut removed = 0;
+        for line in BufReader::new(File::open(&inner.path)?).lines() {
+            let line = line?;
+            let record: AuditRecord = serde_json::from_str(&line)?;
+            if record.seq <= seq && record.seq + 1 < inner.head.len {
+                removed += 1;
+            } else {
+                retained.extend_from_slice(line.as_bytes());
+                retained.push(b'\n');
+            }
+        }
+        if removed == 0 {
+            return Ok(0);
+        }
+
+        let mut purged = inner.path.clone().into_os_string();
+        purged.push(".purge");
+        let purged = PathBuf::from(purged);
+        let mut file = File::create(&purged)?;
+        file.write_all(&retained)?;
+        file.sync_all()?;
+        fs::rename(&purged, &inner.path)?;
+        inner.file = OpenOptions::new().append(true).open(&inner.path)?;
+        Ok(removed)
+    }
+
+    /// Append a record and flush it to the disk. Returns the sequence number of the
+    /// record.
+    fn append(
+        &self,
+        action: &str,
+        parameters: BTreeMap<String, String>,
+        token_fingerprint: Option<String>,
+        outcome: Outcome,
+    ) -> Result<u64, AuditError> {
+        self.append_at(Utc::now(), action, parameters, token_fingerprint, outcome)
+    }
+
+    /// Append a record with the given timestamp and flush it to the disk. Returns the
+    /// sequence number of the record.
+    fn append_at(
+        &self,
+        timestamp: DateTime<Utc>,
+        action: &str,
+        parameters: BTreeMap<String, String>,
+        token_fingerprint: Option<String>,
+        outcome: Outcome,
+    ) -> Result<u64, AuditError> {
+        let mut inner = self.inner.lock().map_err(|_| AuditError::Poisoned)?;
+        let mut record = AuditRecord {
+            seq: inner.head.len,
+            timestamp: timestamp.to_rfc3339(),
+            action: action.to_string(),
+            parameters,
+            token_fingerprint,
+            outcome,
+            prev_hash: inner.head.last_hash.clone(),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash()?;
+
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        inner.file.write_all(&line)?;
+        inner.file.sync_data()?;
+
+        inner.head = ChainHead {
+            len: record.seq + 1,
+            last_hash: record.hash,
+        };
+        Ok(record.seq)
+    }
+}
+
+/// Verify the records of an audit log and return the head of the chain.
+///
+/// Since the head of the chain is only known from the records themselves, a truncation
+/// at a record boundary can only be detected by comparing the returned head to a
+/// previously known one.
+///
+/// The records may start at a later record than the first one, if the oldest records were
+/// purged. The purged records are verified in the same way, from their exports: the first
+/// retained record must follow the last exported one.
+///
+/// # Errors
+/// Fails at the first record that is malformed, truncated, out of sequence, that doesn't
+/// chain to the previous record or whose hash doesn't match.
+pub fn verify(mut reader: impl BufRead) -> Result<ChainHead, VerifyError> {
+    let mut head = ChainHead::default();
+    let mut line = String::new();
+    let mut lineno = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(head);
+        }
+        lineno += 1;
+        if !line.ends_with('\n') {
+            return Err(VerifyError::Truncated(lineno));
+        }
+
+        let record: AuditRecord =
+            serde_json::from_str(&line).map_err(|_| VerifyError::Malformed(lineno))?;
+        if lineno == 1 && record.seq > 0 {
+            // the preceding records were purged
+            head = ChainHead {
+                len: record.seq,
+                last_hash: record.prev_hash.clone(),
+            };
+        }
+        if record.seq != head.len {
+            return Err(VerifyError::Sequence {
+                expected: head.len,
+                found: record.seq,
+            });
+        }
+        if record.prev_hash != head.last_hash {
+            return Err(VerifyError::BrokenChain(record.seq));
+        }
+        if record.compute_hash().ok().as_ref() != Some(&record.hash) {
+            return Err(VerifyError::HashMismatch(record.seq));
+        }
+
+        head = ChainHead {
+            len: record.seq + 1,
+            last_hash: record.hash,
+        };
+    }
+}
+
+/// Compute the fingerprint of an authentication token.
+pub fn token_fingerprint(token: &str) -> String {
+    hex::encode(&sha256::hash(token.as_bytes()).as_ref()[..8])
+}
+
+/// Redact the secret parameters.
+fn redact<I, K, V>(parameters: I) -> BTreeMap<String, String>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: Into<String>,
+    V: Into<String>,
+{
+    parameters
+        .into_iter()
+        .map(|(name, value)| {
+            let name = name.into();
+            let lowercase = name.to_lowercase();
+            if SECRET_PARAMETERS
+                .iter()
+                .any(|secret| lowercase.contains(secret))
+            {
+                (name, REDACTED.to_string())
+            } else {
+                (name, value.into())
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+
+    /// A temporary audit log file, removed on drop.
+    pub(crate) struct TempLog(pub(crate) PathBuf);
+
+    impl TempLog {
+        pub(crate) fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let path = std::env::temp_dir().join(format!(
+                "xaynet-audit-{}-{}.log",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst),
+            ));
+            let _ = fs::remove_file(&path);
+            Self(path)
+        }
+
+        pub(crate) fn records(&self) -> Vec<AuditRecord> {
+            fs::read_to_string(&self.0)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+
+        pub(crate) fn verify(&self) -> Result<ChainHead, VerifyError> {
+            verify(BufReader::new(File::open(&self.0).unwrap()))
+        }
+
+        fn rewrite(&self, f: impl FnOnce(&mut Vec<String>)) {
+            let content = fs::read_to_string(&self.0).unwrap();
+            let mut lines = content.lines().map(String::from).collect();
+            f(&mut lines);
+            fs::write(&self.0, lines.join("\n") + "\n").unwrap();
+        }
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn record_actions(log: &AuditLog) {
+        let entry = log
+            .begin("phase.abort", vec![("reason", "test")], Some("token"))
+            .unwrap();
+        log.finish(entry, &Ok::<_, String>(())).unwrap();
+        let entry = log
+            .begin("model.seed", vec![("bucket", "models")], Some("token"))
+            .unwrap();
+        log.finish(entry, &Err::<(), _>("no such model")).unwrap();
+    }
+
+    /// Record `count` actions at the given `timestamp`.
+    pub(crate) fn record_actions_at(log: &AuditLog, timestamp: DateTime<Utc>, count: usize) {
+        for _ in 0..count {
+            log.append_at(
+                timestamp,
+                "canary.set",
+                BTreeMap::new(),
+                None,
+                Outcome::Pending,
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_write_ahead() {
+        let file = TempLog::new();
+        let log = AuditLog::open(&file.0).unwrap();
+
+        let entry = log
+            .begin("phase.abort", vec![("reason", "test")], None)
+            .unwrap();
+        // the action is recorded before it takes effect
+        let records = file.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].action, "phase.abort");
+        assert_eq!(records[0].outcome, Outcome::Pending);
+
+        log.finish(entry, &Ok::<_, String>(())).unwrap();
+        let records = file.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].outcome, Outcome::Succeeded { begin: 0 });
+    }
+
+    #[test]
+    fn test_write_failure() {
+        let dir = TempLog::new();
+        fs::create_dir(&dir.0).unwrap();
+        // a directory can't be opened as the audit log
+        assert!(AuditLog::open(&dir.0).is_err());
+        fs::remove_dir(&dir.0).unwrap();
+    }
+
+    #[test]
+    fn test_redaction() {
+        let file = TempLog::new();
+        let log = AuditLog::open(&file.0).unwrap();
+        let entry = log
+            .begin(
+                "settings.patch",
+                vec![
+                    ("s3.secret_access_key", "very secret"),
+                    ("redis.password", "hunter2"),
+                    ("pet.sum.prob", "0.5"),
+                ],
+                Some("admin token"),
+            )
+            .unwrap();
+        log.finish(entry, &Ok::<_, String>(())).unwrap();
+
+        let content = fs::read_to_string(&file.0).unwrap();
+        assert!(!content.contains("very secret"));
+        assert!(!content.contains("hunter2"));
+        assert!(!content.contains("admin token"));
+
+        let record = &file.records()[0];
+        assert_eq!(record.parameters["s3.secret_access_key"], REDACTED);
+        assert_eq!(record.parameters["redis.password"], REDACTED);
+        assert_eq!(record.parameters["pet.sum.prob"], "0.5");
+        assert_eq!(
+            record.token_fingerprint,
+            Some(token_fingerprint("admin token"))
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let file = TempLog::new();
+        let log = AuditLog::open(&file.0).unwrap();
+        record_actions(&log);
+        drop(log);
+
+        let head = file.verify().unwrap();
+        assert_eq!(head.len, 4);
+
+        // the chain is resumed after re-opening the audit log
+        let log = AuditLog::open(&file.0).unwrap();
+        record_actions(&log);
+        let head = file.verify().unwrap();
+        assert_eq!(head.len, 8);
+        assert_eq!(head.last_hash, file.records()[7].hash);
+    }
+
+    #[test]
+    fn test_verify_tampering() {
+        let file = TempLog::new();
+        record_actions(&AuditLog::open(&file.0).unwrap());
+
+        // a modified record
+        file.rewrite(|lines| lines[1] = lines[1].replace("succeeded", "failed"));
+        assert!(matches!(file.verify(), Err(VerifyError::Malformed(2))));
+        file.rewrite(|lines| lines[0] = lines[0].replace("test", "tset"));
+        assert!(matches!(file.verify(), Err(VerifyError::HashMismatch(0))));
+        // AuditLog::open() refuses to append to an invalid audit log
+        assert!(matches!(
+            AuditLog::open(&file.0),
+            Err(AuditError::Invalid(VerifyError::HashMismatch(0)))
+        ));
+    }
+
+    #[test]
+    fn test_verify_removal_and_truncation() {
+        let file = TempLog::new();
+        record_actions(&AuditLog::open(&file.0).unwrap());
+        let records = file.records();
+
+        // a removed record
+        file.rewrite(|lines| {
+            lines.remove(1);
+        });
+        assert!(matches!(
+            file.verify(),
+            Err(VerifyError::Sequence {
+                expected: 1,
+                found: 2
+            })
+        ));
+
+        // a removed record with renumbered sequence numbers
+        file.rewrite(|lines| {
+            let mut record: AuditRecord = serde_json::from_str(&lines[1]).unwrap();
+            record.seq = 1;
+            lines[1] = serde_json::to_string(&record).unwrap();
+        });
+        assert!(matches!(file.verify(), Err(VerifyError::BrokenChain(1))));
+
+        // a partially written record
+        let content = fs::read_to_string(&file.0).unwrap();
+        let mut lines: Vec<_> = content.lines().collect();
+        lines.truncate(1);
+        let truncated = format!("{}\n{{\"seq\":1", lines[0]);
+        fs::write(&file.0, truncated).unwrap();
+        assert!(matches!(file.verify(), Err(VerifyError::Truncated(2))));
+
+        // a truncation at a record boundary is detected by comparing the heads
+        fs::write(&file.0, format!("{}\n", lines[0])).unwrap();
+        let head = file.verify().unwrap();
+        assert_ne!(
+            head,
+            ChainHead {
+                len: 4,
+                last_hash: records[3].hash.clone()
+            }
+        );
+    }
+
+    #[test]
+    fn test_purge() {
+        let file = TempLog::new();
+        let log = AuditLog::open(&file.0).unwrap();
+        let old = Utc::now() - chrono::Duration::days(2);
+        let cutoff = Utc::now() - chrono::Duration::days(1);
+        record_actions_at(&log, old, 3);
+        record_actions_at(&log, Utc::now(), 2);
+        let records = file.records();
+
+        // the expiring records are the oldest ones, at most the limit
+        let expiring = log.expiring(cutoff, 2).unwrap();
+        assert_eq!(expiring, records[..2]);
+        let expiring = log.expiring(cutoff, 10).unwrap();
+        assert_eq!(expiring, records[..3]);
+
+        assert_eq!(log.remove_through(2).unwrap(), 3);
+        assert_eq!(file.records(), records[3..]);
+        assert!(log.expiring(cutoff, 10).unwrap().is_empty());
+        assert_eq!(log.remove_through(2).unwrap(), 0);
+
+        // the retained records chain to the removed ones
+        let head = file.verify().unwrap();
+        assert_eq!(head.len, 5);
+        let mut exported = String::new();
+        for record in &records[..3] {
+            exported.push_str(&serde_json::to_string(record).unwrap());
+            exported.push('\n');
+        }
+        exported.push_str(&fs::read_to_string(&file.0).unwrap());
+        assert_eq!(verify(exported.as_bytes()).unwrap(), head);
+
+        // the chain goes on, also after re-opening the audit log
+        record_actions(&log);
+        drop(log);
+        let log = AuditLog::open(&file.0).unwrap();
+        record_actions(&log);
+        let head = file.verify().unwrap();
+        assert_eq!(head.len, 13);
+        assert_eq!(file.records()[0].seq, 3);
+    }
+
+    #[test]
+    fn test_purge_keeps_newest_record() {
+        let file = TempLog::new();
+        let log = AuditLog::open(&file.0).unwrap();
+        record_actions_at(&log, Utc::now() - chrono::Duration::days(2), 3);
+
+        let expiring = log.expiring(Utc::now(), 10).unwrap();
+        assert_eq!(expiring.len(), 2);
+        assert_eq!(log.remove_through(2).unwrap(), 2);
+        let records = file.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].seq, 2);
+
+        // the sequence goes on after the newest record
+        record_actions(&log);
+        assert_eq!(file.verify().unwrap().len, 7);
+    }
+}
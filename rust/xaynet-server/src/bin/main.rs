@@ -1,19 +1,35 @@
-use std::{path::PathBuf, process};
+use std::{fs, path::PathBuf, process};
 
-use structopt::StructOpt;
+use structopt::{
+    clap::{Error as ClapError, ErrorKind},
+    StructOpt,
+};
 use tokio::signal;
 use tracing::warn;
 use tracing_subscriber::*;
 
-#[cfg(feature = "metrics")]
-use xaynet_server::{metrics, settings::InfluxSettings};
+#[cfg(feature = "dev")]
+use xaynet_server::dev;
+#[cfg(feature = "prometheus")]
+use xaynet_server::rest::serve_metrics;
+#[cfg(any(feature = "metrics", feature = "prometheus"))]
+use xaynet_server::{metrics, settings::MetricsSettings};
 
+#[cfg(feature = "in-memory-storage")]
+use xaynet_server::storage::coordinator_storage::in_memory;
 use xaynet_server::{
-    rest::{serve, RestError},
+    rest::{serve, Admin, RestError},
+    retention::Retention,
+    round_archive,
     services,
-    settings::{LoggingSettings, RedisSettings, Settings},
-    state_machine::initializer::StateMachineInitializer,
-    storage::{coordinator_storage::redis, Storage, Store},
+    settings::{LoggingSettings, Settings},
+    state_machine::{canary::CanarySwitch, initializer::StateMachineInitializer},
+    storage::{
+        coordinator_storage::{postgres, redis},
+        CoordinatorStorage,
+        Storage,
+        Store,
+    },
 };
 #[cfg(feature = "model-persistence")]
 use xaynet_server::{settings::S3Settings, storage::model_storage::s3};
@@ -23,24 +39,120 @@ use xaynet_server::{settings::S3Settings, storage::model_storage::s3};
 struct Opt {
     /// Path of the configuration file
     #[structopt(short, parse(from_os_str))]
-    config_path: PathBuf,
+    config_path: Option<PathBuf>,
+    /// Checks that the configured storage works, without starting the coordinator. Exits with
+    /// 0 if all checks passed, and with 1 otherwise.
+    #[structopt(long)]
+    check: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Reproduces the global model of a round from its archive and checks that it is the
+    /// published global model. Exits with 0 if it is, and with 1 otherwise.
+    VerifyRound {
+        /// Path of the round archive
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+    },
+    /// Runs a coordinator with in-memory storage together with simulated participants, and
+    /// prints a summary of each round. For demos only, never use it in production.
+    #[cfg(feature = "dev")]
+    Dev {
+        /// Number of simulated participants
+        #[structopt(long, default_value = "5")]
+        participants: usize,
+        /// Number of rounds after which to stop, runs until interrupted if not set
+        #[structopt(long)]
+        rounds: Option<u64>,
+        /// Length of the synthetic model
+        #[structopt(long, default_value = "4")]
+        model_length: usize,
+        /// Local port of the REST API, any free port if 0
+        #[structopt(long, default_value = "0")]
+        port: u16,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let opt = Opt::from_args();
+    match opt.command {
+        Some(Command::VerifyRound { archive }) => process::exit(verify_round(archive)),
+        #[cfg(feature = "dev")]
+        Some(Command::Dev {
+            participants,
+            rounds,
+            model_length,
+            port,
+        }) => {
+            if opt.config_path.is_some() {
+                ClapError::with_description(
+                    "The development mode uses an in-memory storage and takes no configuration file",
+                    ErrorKind::ArgumentConflict,
+                )
+                .exit()
+            }
+            let settings = dev::DevSettings {
+                participants,
+                rounds,
+                model_length,
+                port,
+            };
+            process::exit(run_dev(settings).await);
+        }
+        None => {}
+    }
+    let config_path = opt.config_path.unwrap_or_else(|| {
+        ClapError::with_description(
+            "The configuration file is required to run the coordinator",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit()
+    });
 
-    let settings = Settings::new(opt.config_path).unwrap_or_else(|err| {
+    let mut settings = Settings::new(config_path).unwrap_or_else(|err| {
         eprintln!("{}", err);
         process::exit(1);
     });
+
+    // the settings validation ensures that exactly one coordinator storage is configured
+    #[cfg(feature = "in-memory-storage")]
+    if settings.in_memory.take().is_some() {
+        return run(settings, in_memory::Client::new(), opt.check).await;
+    }
+    match (settings.redis.take(), settings.postgres.take()) {
+        (Some(redis_settings), _) => {
+            let coordinator_store = redis::Client::new(redis_settings.url)
+                .await
+                .expect("failed to establish a connection to Redis");
+            run(settings, coordinator_store, opt.check).await
+        }
+        (None, Some(postgres_settings)) => {
+            let coordinator_store = postgres::Client::new(postgres_settings.url)
+                .await
+                .expect("failed to establish a connection to PostgreSQL");
+            run(settings, coordinator_store, opt.check).await
+        }
+        (None, None) => unreachable!("no coordinator storage configured"),
+    }
+}
+
+/// Runs the coordinator with the given coordinator storage backend, or only its preflight checks
+/// if `check` is set.
+async fn run(settings: Settings, coordinator_store: impl CoordinatorStorage, check: bool) {
     let Settings {
         pet: pet_settings,
         mask: mask_settings,
         api: api_settings,
+        admin: admin_settings,
+        retention: retention_settings,
         log: log_settings,
         model: model_settings,
-        redis: redis_settings,
+        shadow: shadow_settings,
+        training_plans,
         ..
     } = settings;
 
@@ -51,29 +163,58 @@ async fn main() {
     // is correctly initialized
     sodiumoxide::init().unwrap();
 
-    #[cfg(feature = "metrics")]
-    init_metrics(settings.metrics.influxdb);
+    #[cfg(any(feature = "metrics", feature = "prometheus"))]
+    init_metrics(settings.metrics);
 
     let store = init_store(
-        redis_settings,
+        coordinator_store,
         #[cfg(feature = "model-persistence")]
         settings.s3,
     )
     .await;
 
-    let (state_machine, requests_tx, event_subscriber) = StateMachineInitializer::new(
+    // the canary rounds are scheduled through the administrative routes
+    let canary = CanarySwitch::new();
+    let mut admin = admin_settings
+        .map(|settings| Admin::new(settings, canary.clone()))
+        .transpose()
+        .expect("failed to open the audit log");
+    let retention = retention_settings.map(|settings| {
+        let audit_log = admin.as_ref().and_then(Admin::audit_log);
+        let (retention, trigger) = Retention::new(settings, audit_log, store.clone());
+        admin = admin.take().map(|admin| admin.with_retention(trigger));
+        retention
+    });
+    let mut initializer = StateMachineInitializer::new(
         pet_settings,
         mask_settings,
         model_settings,
+        shadow_settings,
         #[cfg(feature = "model-persistence")]
         settings.restore,
-        store,
+        store.clone(),
     )
-    .init()
-    .await
-    .expect("failed to initialize state machine");
+    .with_training_plans(training_plans)
+    .with_canary_switch(canary);
+
+    if check {
+        let report = initializer
+            .preflight()
+            .await
+            .expect("failed to run preflight checks");
+        print!("{}", report);
+        process::exit(if report.passed() { 0 } else { 1 });
+    }
 
-    let fetcher = services::fetchers::fetcher(&event_subscriber);
+    let (state_machine, requests_tx, event_subscriber) = initializer
+        .init()
+        .await
+        .expect("failed to initialize state machine");
+    if let Some(retention) = retention {
+        tokio::spawn(retention.run());
+    }
+
+    let fetcher = services::fetchers::fetcher(&event_subscriber, store);
     let message_handler =
         services::messages::PetMessageHandler::new(&event_subscriber, requests_tx);
 
@@ -84,17 +225,59 @@ async fn main() {
         _ = state_machine.run() => {
             warn!("shutting down: Service terminated");
         }
-        result = serve(api_settings, fetcher, message_handler) => {
+        result = serve(api_settings, fetcher, message_handler, event_subscriber, admin) => {
             match result {
                 Ok(()) => warn!("shutting down: REST server terminated"),
                 Err(RestError::InvalidTlsConfig) => {
                     warn!("shutting down: invalid TLS settings for REST server");
                 },
+                Err(err) => warn!("shutting down: {}", err),
             }
         }
     }
 }
 
+/// Runs the development mode until it completes or is interrupted, and returns the exit code.
+#[cfg(feature = "dev")]
+async fn run_dev(settings: dev::DevSettings) -> i32 {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"));
+    init_tracing(LoggingSettings { filter });
+    eprintln!("running in development mode, do not use in production");
+
+    let mut stdout = std::io::stdout();
+    tokio::select! {
+        _ = signal::ctrl_c() => 0,
+        result = dev::run(settings, &mut stdout) => match result {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("{}", err);
+                1
+            }
+        },
+    }
+}
+
+/// Verifies the round archive at the given path, and returns the exit code.
+fn verify_round(path: PathBuf) -> i32 {
+    sodiumoxide::init().unwrap();
+    let verified = fs::read(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|archive| round_archive::verify(&archive).map_err(|err| err.to_string()));
+    match verified {
+        Ok((manifest, _)) => {
+            println!(
+                "round {}: the archive reproduces the global model {}",
+                manifest.round_id, manifest.global_model_id
+            );
+            0
+        }
+        Err(err) => {
+            eprintln!("{}: verification failed: {}", path.display(), err);
+            1
+        }
+    }
+}
+
 fn init_tracing(settings: LoggingSettings) {
     let _fmt_subscriber = FmtSubscriber::builder()
         .with_env_filter(settings.filter)
@@ -102,22 +285,38 @@ fn init_tracing(settings: LoggingSettings) {
         .init();
 }
 
-#[cfg(feature = "metrics")]
-fn init_metrics(settings: InfluxSettings) {
-    let recorder = metrics::Recorder::new(settings);
-    if metrics::GlobalRecorder::install(recorder).is_err() {
-        warn!("failed to install metrics recorder");
-    };
+/// Installs the Prometheus recorder if it is configured, and the InfluxDB recorder otherwise.
+#[cfg(any(feature = "metrics", feature = "prometheus"))]
+fn init_metrics(settings: MetricsSettings) {
+    #[cfg(feature = "prometheus")]
+    if let Some(prometheus_settings) = settings.prometheus {
+        let recorder = metrics::PrometheusRecorder::new();
+        let registry = recorder.registry();
+        if metrics::GlobalRecorder::install(recorder).is_err() {
+            warn!("failed to install metrics recorder");
+            return;
+        }
+        tokio::spawn(async move {
+            if let Err(err) = serve_metrics(prometheus_settings.bind_address, registry).await {
+                warn!("metrics server terminated: {}", err);
+            }
+        });
+        return;
+    }
+
+    #[cfg(feature = "metrics")]
+    {
+        let recorder = metrics::InfluxDbRecorder::new(settings.influxdb);
+        if metrics::GlobalRecorder::install(recorder).is_err() {
+            warn!("failed to install metrics recorder");
+        };
+    }
 }
 
-async fn init_store(
-    redis_settings: RedisSettings,
+async fn init_store<C: CoordinatorStorage>(
+    coordinator_store: C,
     #[cfg(feature = "model-persistence")] s3_settings: S3Settings,
 ) -> impl Storage {
-    let coordinator_store = redis::Client::new(redis_settings.url)
-        .await
-        .expect("failed to establish a connection to Redis");
-
     let model_store = {
         #[cfg(not(feature = "model-persistence"))]
         {
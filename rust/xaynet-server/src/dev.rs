@@ -0,0 +1,494 @@
+//! A self-contained development mode for demos.
+//!
+//! **This mode is not meant for production.** It runs a coordinator with the in-memory
+//! coordinator storage and relaxed PET settings in the current process, together with a number
+//! of simulated participants that connect to its REST API via the [`xaynet_sdk`]. Every
+//! participant contributes a constant synthetic model, hence the rounds only demonstrate the
+//! protocol and not any actual training. A summary of each completed round is written as a
+//! single line, e.g.:
+//!
+//! ```text
+//! round 1: 1 sum / 4 update participants, global model [4.000, 4.000, 4.000, 4.000]
+//! ```
+//!
+//! The development mode refuses to start if a persistent coordinator storage is configured via
+//! the environment, to prevent it from being mistaken for a real deployment.
+
+use std::{
+    env,
+    io::Write,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info, warn};
+
+use xaynet_core::{
+    crypto::SigningKeyPair,
+    mask::{BoundType, DataType, FromPrimitives, GroupType, IntoPrimitives, Model, ModelType},
+    SeedDict,
+    SumDict,
+};
+use xaynet_sdk::{
+    client::{reqwest_client_builder, Client, DEFAULT_POOL_IDLE_TIMEOUT},
+    settings::PetSettings as ParticipantSettings,
+    ModelStore,
+    Notify,
+    StateMachine as Participant,
+    TransitionOutcome,
+};
+
+#[cfg(feature = "model-persistence")]
+use crate::settings::RestoreSettings;
+use crate::{
+    rest::{serve, RestError},
+    services,
+    settings::{
+        ApiSettings,
+        MaskSettings,
+        ModelSettings,
+        PetSettings,
+        PetSettingsCount,
+        PetSettingsSum,
+        PetSettingsSum2,
+        PetSettingsTime,
+        PetSettingsUpdate,
+    },
+    state_machine::{
+        events::{DictionaryUpdate, Event, ModelUpdate},
+        initializer::{StateMachineInitializationError, StateMachineInitializer},
+        phases::PhaseName,
+    },
+    storage::{coordinator_storage::in_memory, model_storage::noop::NoOp, Store},
+};
+
+/// The environment variables which configure a persistent coordinator storage.
+const STORAGE_ENV_VARS: [&str; 2] = ["XAYNET__REDIS__URL", "XAYNET__POSTGRES__URL"];
+
+/// The minimal number of participants: one sum participant and three update participants.
+pub const MIN_PARTICIPANTS: usize = 4;
+
+/// The number of weights of the global model shown in a round summary.
+const SUMMARY_WEIGHTS: usize = 4;
+
+/// The interval at which the simulated participants poll the coordinator.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Settings of the development mode.
+#[derive(Debug, Clone, Copy)]
+pub struct DevSettings {
+    /// The number of simulated participants.
+    pub participants: usize,
+    /// The number of rounds after which the development mode stops. It runs until it is
+    /// interrupted if `None`.
+    pub rounds: Option<u64>,
+    /// The length of the synthetic model.
+    pub model_length: usize,
+    /// The local port of the REST API. Any free port is used if `0`.
+    pub port: u16,
+}
+
+impl Default for DevSettings {
+    fn default() -> Self {
+        Self {
+            participants: 5,
+            rounds: None,
+            model_length: 4,
+            port: 0,
+        }
+    }
+}
+
+/// Errors of the development mode.
+#[derive(Debug, Error)]
+pub enum DevError {
+    #[error("the development mode can't be used with a persistent storage ({0} is set)")]
+    StorageConfigured(&'static str),
+    #[error(
+        "the development mode requires at least {} participants, got {}",
+        MIN_PARTICIPANTS,
+        .0
+    )]
+    TooFewParticipants(usize),
+    #[error("the model length must be positive")]
+    EmptyModel,
+    #[error("failed to initialize the coordinator: {0}")]
+    Init(#[from] StateMachineInitializationError),
+    #[error("the REST server terminated: {0}")]
+    Rest(#[from] RestError),
+    #[error("the coordinator terminated")]
+    Terminated,
+    #[error("failed to write the round summary: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl DevSettings {
+    /// Checks that the development mode can be started with these settings.
+    fn validate(&self) -> Result<(), DevError> {
+        if let Some(var) = STORAGE_ENV_VARS
+            .iter()
+            .find(|var| env::var_os(var).is_some())
+        {
+            return Err(DevError::StorageConfigured(var));
+        }
+        if self.participants < MIN_PARTICIPANTS {
+            return Err(DevError::TooFewParticipants(self.participants));
+        }
+        if self.model_length == 0 {
+            return Err(DevError::EmptyModel);
+        }
+        Ok(())
+    }
+
+    /// The PET settings of the coordinator.
+    ///
+    /// About one and a half participants are selected for the sum task and all the others for
+    /// the update task, and the phases last only a few seconds.
+    fn pet_settings(&self) -> PetSettings {
+        let participants = self.participants as u64;
+        let time = |max| PetSettingsTime { min: 1, max };
+        PetSettings {
+            sum: PetSettingsSum {
+                prob: (1.5 / self.participants as f64).min(0.5),
+                count: PetSettingsCount {
+                    min: 1,
+                    max: participants,
+                },
+                time: time(3),
+            },
+            update: PetSettingsUpdate {
+                prob: 1.,
+                count: PetSettingsCount {
+                    min: 3,
+                    max: participants,
+                },
+                time: time(5),
+                quota: None,
+                max_sample_count: None,
+            },
+            sum2: PetSettingsSum2 {
+                count: PetSettingsCount {
+                    min: 1,
+                    max: participants,
+                },
+                time: time(5),
+                mask_tolerance: None,
+            },
+        }
+    }
+
+    /// The API settings of the coordinator, bound to the given local address.
+    fn api_settings(&self, bind_address: SocketAddr) -> ApiSettings {
+        ApiSettings {
+            bind_address,
+            #[cfg(feature = "tls")]
+            tls_certificate: None,
+            #[cfg(feature = "tls")]
+            tls_key: None,
+            #[cfg(feature = "tls")]
+            tls_client_auth: None,
+            debug_rejections: true,
+            non_production: true,
+            http2: false,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
+        }
+    }
+}
+
+/// Runs the coordinator and the simulated participants, and writes a summary of each completed
+/// round to `out`.
+///
+/// # Errors
+/// Fails if a persistent coordinator storage is configured, if the settings are invalid or if the
+/// coordinator terminates.
+pub async fn run<W: Write>(settings: DevSettings, out: &mut W) -> Result<(), DevError> {
+    settings.validate()?;
+    let bind_address = local_address(settings.port)?;
+
+    let store = Store::new(in_memory::Client::new(), NoOp);
+    let (state_machine, requests_tx, event_subscriber) = StateMachineInitializer::new(
+        settings.pet_settings(),
+        MaskSettings {
+            group_type: GroupType::Prime,
+            data_type: DataType::F32,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        },
+        ModelSettings {
+            length: settings.model_length,
+            update_statistics: false,
+            export_round_archive: false,
+        },
+        None,
+        #[cfg(feature = "model-persistence")]
+        RestoreSettings { enable: false },
+        store.clone(),
+    )
+    .init()
+    .await?;
+
+    let fetcher = services::fetchers::fetcher(&event_subscriber, store);
+    let message_handler =
+        services::messages::PetMessageHandler::new(&event_subscriber, requests_tx);
+    let api_settings = settings.api_settings(bind_address);
+    let mut server = tokio::spawn(serve(
+        api_settings,
+        fetcher,
+        message_handler,
+        event_subscriber.clone(),
+        None,
+    ));
+    let mut coordinator = tokio::spawn(state_machine.run());
+    info!("development coordinator listening on {}", bind_address);
+
+    let url = format!("http://{}", bind_address);
+    let participants = (0..settings.participants)
+        .map(|id| spawn_participant(&url, synthetic_model(id, &settings)))
+        .collect::<Vec<_>>();
+
+    let mut summary = Summary::new(settings.rounds);
+    let mut sum_dict = event_subscriber.sum_dict_listener();
+    let mut seed_dict = event_subscriber.seed_dict_listener();
+    let mut model = event_subscriber.model_listener();
+    let mut phase = event_subscriber.phase_listener();
+    let result = loop {
+        if summary.is_done() {
+            break Ok(());
+        }
+        tokio::select! {
+            Ok(()) = sum_dict.changed() => summary.sum_dict(sum_dict.get_latest()),
+            Ok(()) = seed_dict.changed() => summary.seed_dict(seed_dict.get_latest()),
+            Ok(()) = model.changed() => {
+                if let Err(err) = summary.model(model.get_latest(), out) {
+                    break Err(err.into());
+                }
+            }
+            Ok(()) = phase.changed() => {
+                let Event { round_id, event } = phase.get_latest();
+                if event == PhaseName::Failure {
+                    warn!("round {} failed, the coordinator starts a new round", round_id);
+                }
+            }
+            result = &mut server => {
+                break Err(match result {
+                    Ok(Err(err)) => err.into(),
+                    _ => DevError::Terminated,
+                });
+            }
+            _ = &mut coordinator => break Err(DevError::Terminated),
+        }
+    };
+
+    for participant in participants {
+        participant.abort();
+    }
+    server.abort();
+    coordinator.abort();
+    result
+}
+
+/// Returns the local address of the REST API. A free port is chosen if `port` is `0`.
+fn local_address(port: u16) -> Result<SocketAddr, DevError> {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    if port != 0 {
+        return Ok(address);
+    }
+    // the port may be taken by someone else before the REST API is bound, which is acceptable
+    // for a development mode
+    Ok(TcpListener::bind(address)?.local_addr()?)
+}
+
+/// Creates the synthetic model of a participant. The weights of the `id`-th participant are all
+/// `(id + 1) / participants`.
+fn synthetic_model(id: usize, settings: &DevSettings) -> Arc<Model> {
+    let weight = (id + 1) as f32 / settings.participants as f32;
+    let model = Model::from_primitives(vec![weight; settings.model_length].into_iter())
+        // safe unwrap: the weights are finite
+        .unwrap();
+    Arc::new(model)
+}
+
+/// Spawns a simulated participant which takes part in the rounds of the coordinator at `url`.
+fn spawn_participant(url: &str, model: Arc<Model>) -> JoinHandle<()> {
+    // safe unwraps: the client has a default configuration and the URL is a local address
+    let http_client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+        .build()
+        .unwrap();
+    let client = Client::new(http_client, url).unwrap();
+    let settings = ParticipantSettings::new(SigningKeyPair::generate());
+    let mut participant = Participant::new(settings, client, SyntheticModel(model), Silent);
+    tokio::spawn(async move {
+        loop {
+            participant = match participant.transition().await {
+                TransitionOutcome::Pending(participant) => {
+                    sleep(POLL_INTERVAL).await;
+                    participant
+                }
+                TransitionOutcome::Complete(participant) => participant,
+            };
+        }
+    })
+}
+
+/// The constant model of a simulated participant.
+struct SyntheticModel(Arc<Model>);
+
+#[async_trait]
+impl ModelStore for SyntheticModel {
+    type Model = Arc<Model>;
+    type Error = std::convert::Infallible;
+
+    async fn load_model(&mut self) -> Result<Option<Self::Model>, Self::Error> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// The notifications of the simulated participants are ignored.
+struct Silent;
+
+impl Notify for Silent {}
+
+/// Collects the participants of the rounds and writes a summary of each completed round.
+struct Summary {
+    /// The number of rounds to complete.
+    rounds: Option<u64>,
+    /// The number of completed rounds.
+    completed: u64,
+    /// The round and number of the sum participants.
+    sum: Option<(u64, usize)>,
+    /// The round and number of the update participants.
+    update: Option<(u64, usize)>,
+}
+
+impl Summary {
+    fn new(rounds: Option<u64>) -> Self {
+        Self {
+            rounds,
+            completed: 0,
+            sum: None,
+            update: None,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        matches!(self.rounds, Some(rounds) if self.completed >= rounds)
+    }
+
+    fn sum_dict(&mut self, Event { round_id, event }: Event<DictionaryUpdate<SumDict>>) {
+        if let DictionaryUpdate::New(sum_dict) = event {
+            self.sum = Some((round_id, sum_dict.len()));
+        }
+    }
+
+    fn seed_dict(&mut self, Event { round_id, event }: Event<DictionaryUpdate<SeedDict>>) {
+        if let DictionaryUpdate::New(seed_dict) = event {
+            // every update participant sends a seed to each sum participant
+            let updates = seed_dict.values().next().map_or(0, |seeds| seeds.len());
+            self.update = Some((round_id, updates));
+        }
+    }
+
+    fn model<W: Write>(
+        &mut self,
+        Event { round_id, event }: Event<ModelUpdate>,
+        out: &mut W,
+    ) -> std::io::Result<()> {
+        let model = match event {
+            ModelUpdate::New(model) => model,
+            ModelUpdate::Invalidate => return Ok(()),
+        };
+        let count = |participants: Option<(u64, usize)>| match participants {
+            Some((round, count)) if round == round_id => count,
+            _ => 0,
+        };
+        let weights = model
+            .to_primitives()
+            .take(SUMMARY_WEIGHTS)
+            .map(|weight: Result<f64, _>| {
+                weight.map_or_else(|_| "?".to_string(), |weight| format!("{:.3}", weight))
+            })
+            .collect::<Vec<_>>();
+        let ellipsis = if model.len() > SUMMARY_WEIGHTS {
+            ", ..."
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "round {}: {} sum / {} update participants, global model [{}{}]",
+            round_id,
+            count(self.sum),
+            count(self.update),
+            weights.join(", "),
+            ellipsis,
+        )?;
+        out.flush()?;
+        self.completed += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serial_test::serial;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    #[serial]
+    async fn test_too_few_participants() {
+        let settings = DevSettings {
+            participants: 3,
+            ..DevSettings::default()
+        };
+        assert!(matches!(
+            run(settings, &mut Vec::new()).await,
+            Err(DevError::TooFewParticipants(3))
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_refuse_persistent_storage() {
+        env::set_var("XAYNET__REDIS__URL", "redis://127.0.0.1/");
+        let result = run(DevSettings::default(), &mut Vec::new()).await;
+        env::remove_var("XAYNET__REDIS__URL");
+        assert!(matches!(
+            result,
+            Err(DevError::StorageConfigured("XAYNET__REDIS__URL"))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    #[serial]
+    async fn test_dev_rounds() {
+        let settings = DevSettings {
+            participants: 5,
+            rounds: Some(2),
+            ..DevSettings::default()
+        };
+        let mut out = Vec::new();
+        timeout(Duration::from_secs(120), run(settings, &mut out))
+            .await
+            .expect("the rounds didn't complete in time")
+            .unwrap();
+
+        let summary = String::from_utf8(out).unwrap();
+        let rounds = summary.lines().collect::<Vec<_>>();
+        assert_eq!(rounds.len(), 2);
+        for round in rounds {
+            assert!(round.starts_with("round "));
+            assert!(round.contains("global model ["));
+        }
+    }
+}
@@ -17,8 +17,14 @@
 
 pub mod examples;
 
+pub mod audit;
+#[cfg(feature = "dev")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dev")))]
+pub mod dev;
 pub mod metrics;
 pub mod rest;
+pub mod retention;
+pub mod round_archive;
 pub mod services;
 pub mod settings;
 pub mod state_machine;
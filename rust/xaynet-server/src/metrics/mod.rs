@@ -4,7 +4,12 @@ pub mod recorders;
 
 use once_cell::sync::OnceCell;
 
-pub use self::recorders::influxdb::{Measurement, Recorder, Tags};
+#[cfg(feature = "prometheus")]
+pub use self::recorders::prometheus::Recorder as PrometheusRecorder;
+pub use self::recorders::{
+    influxdb::{Measurement, Recorder as InfluxDbRecorder, Tags},
+    Recorder,
+};
 
 static RECORDER: OnceCell<Recorder> = OnceCell::new();
 
@@ -20,11 +25,11 @@ impl GlobalRecorder {
         RECORDER.get()
     }
 
-    /// Installs a new global recorder.
+    /// Installs a new global recorder of any of the supported backends.
     ///
     /// Returns Err(Recorder) if a recorder has already been set.
-    pub fn install(recorder: Recorder) -> Result<(), Recorder> {
-        RECORDER.set(recorder)
+    pub fn install(recorder: impl Into<Recorder>) -> Result<(), Recorder> {
+        RECORDER.set(recorder.into())
     }
 }
 
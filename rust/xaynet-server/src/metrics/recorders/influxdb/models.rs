@@ -13,6 +13,23 @@ pub enum Measurement {
     MessageAccepted,
     MessageDiscarded,
     MessageRejected,
+    MessageUnexpected,
+    ModelWeightQuantile,
+    ModelUpdateNorm,
+    ModelUpdateSignChanges,
+    AdminAction,
+    UpdateQuotaExceeded,
+    MasksTolerated,
+    AggregationPanicked,
+    ConnectionsAccepted,
+    ConnectionsActive,
+    ConnectionRequests,
+    TlsHandshakes,
+    ShadowTaskSelection,
+    ShadowMaskedModelBytes,
+    ShadowAggregationCapacity,
+    RoundCanary,
+    RetentionPurged,
 }
 
 impl From<Measurement> for &'static str {
@@ -26,6 +43,23 @@ impl From<Measurement> for &'static str {
             Measurement::MessageAccepted => "message_accepted",
             Measurement::MessageDiscarded => "message_discarded",
             Measurement::MessageRejected => "message_rejected",
+            Measurement::MessageUnexpected => "message_unexpected",
+            Measurement::ModelWeightQuantile => "model_weight_quantile",
+            Measurement::ModelUpdateNorm => "model_update_norm",
+            Measurement::ModelUpdateSignChanges => "model_update_sign_changes",
+            Measurement::AdminAction => "admin_action",
+            Measurement::UpdateQuotaExceeded => "update_quota_exceeded",
+            Measurement::MasksTolerated => "masks_tolerated",
+            Measurement::AggregationPanicked => "aggregation_panicked",
+            Measurement::ConnectionsAccepted => "connections_accepted",
+            Measurement::ConnectionsActive => "connections_active",
+            Measurement::ConnectionRequests => "connection_requests",
+            Measurement::TlsHandshakes => "tls_handshakes",
+            Measurement::ShadowTaskSelection => "shadow_task_selection",
+            Measurement::ShadowMaskedModelBytes => "shadow_masked_model_bytes",
+            Measurement::ShadowAggregationCapacity => "shadow_aggregation_capacity",
+            Measurement::RoundCanary => "round_canary",
+            Measurement::RetentionPurged => "retention_purged",
         }
     }
 }
@@ -1 +1,64 @@
 pub mod influxdb;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+
+use std::borrow::Borrow;
+
+use ::influxdb::Type;
+
+use self::influxdb::{Measurement, Tags};
+
+/// A metrics / events recorder of one of the supported backends.
+pub enum Recorder {
+    /// A recorder which dispatches to InfluxDB.
+    InfluxDb(self::influxdb::Recorder),
+    /// A recorder which is scraped by Prometheus.
+    #[cfg(feature = "prometheus")]
+    Prometheus(self::prometheus::Recorder),
+}
+
+impl Recorder {
+    /// Records a new metric.
+    pub fn metric<V, T, I>(&self, measurement: Measurement, value: V, tags: T)
+    where
+        V: Into<Type>,
+        T: Into<Option<I>>,
+        I: Into<Tags>,
+    {
+        match self {
+            Self::InfluxDb(recorder) => recorder.metric(measurement, value, tags),
+            #[cfg(feature = "prometheus")]
+            Self::Prometheus(recorder) => recorder.metric(measurement, value, tags),
+        }
+    }
+
+    /// Records a new event.
+    pub fn event<H, D, S, T, A, B>(&self, title: H, description: D, tags: T)
+    where
+        H: Into<String>,
+        D: Into<Option<S>>,
+        S: Into<String>,
+        T: Into<Option<A>>,
+        A: AsRef<[B]>,
+        B: Borrow<str>,
+    {
+        match self {
+            Self::InfluxDb(recorder) => recorder.event(title, description, tags),
+            #[cfg(feature = "prometheus")]
+            Self::Prometheus(recorder) => recorder.event(title, description, tags),
+        }
+    }
+}
+
+impl From<self::influxdb::Recorder> for Recorder {
+    fn from(recorder: self::influxdb::Recorder) -> Self {
+        Self::InfluxDb(recorder)
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl From<self::prometheus::Recorder> for Recorder {
+    fn from(recorder: self::prometheus::Recorder) -> Self {
+        Self::Prometheus(recorder)
+    }
+}
@@ -0,0 +1,353 @@
+//! A metrics recorder which is scraped by Prometheus.
+//!
+//! The measurements are mapped to Prometheus metrics as follows:
+//!
+//! - the number of messages, events, connections and quota violations are counters,
+//! - the number of requests per connection is a histogram,
+//! - all other measurements (round parameters, phase, masks, model statistics, ...) are gauges.
+//!
+//! The tags of a measurement become the labels of the metric, except for the `round_id` tag: its
+//! cardinality grows with every round, hence the current round is exported by the
+//! `xaynet_round_total_number` gauge instead.
+
+use std::{borrow::Borrow, collections::HashMap, sync::Mutex};
+
+use ::prometheus::{
+    exponential_buckets,
+    CounterVec,
+    Encoder,
+    GaugeVec,
+    HistogramOpts,
+    HistogramVec,
+    Opts,
+    Registry,
+    TextEncoder,
+};
+use influxdb::Type;
+use tracing::warn;
+
+use super::influxdb::{Measurement, Tags};
+
+/// The prefix of the names of the exported metrics.
+const NAMESPACE: &str = "xaynet";
+
+/// The tag which is not exported as a label.
+const ROUND_ID_TAG: &str = "round_id";
+
+/// The Prometheus metric type of a measurement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn of(measurement: &Measurement) -> Self {
+        match measurement {
+            Measurement::MessageAccepted
+            | Measurement::MessageDiscarded
+            | Measurement::MessageRejected
+            | Measurement::MessageUnexpected
+            | Measurement::AdminAction
+            | Measurement::UpdateQuotaExceeded
+            | Measurement::MasksTolerated
+            | Measurement::AggregationPanicked
+            | Measurement::ConnectionsAccepted
+            | Measurement::TlsHandshakes
+            | Measurement::RoundCanary
+            | Measurement::RetentionPurged => Self::Counter,
+            Measurement::ConnectionRequests => Self::Histogram,
+            Measurement::RoundParamSum
+            | Measurement::RoundParamUpdate
+            | Measurement::Phase
+            | Measurement::MasksTotalNumber
+            | Measurement::RoundTotalNumber
+            | Measurement::ModelWeightQuantile
+            | Measurement::ModelUpdateNorm
+            | Measurement::ModelUpdateSignChanges
+            | Measurement::ConnectionsActive
+            | Measurement::ShadowTaskSelection
+            | Measurement::ShadowMaskedModelBytes
+            | Measurement::ShadowAggregationCapacity => Self::Gauge,
+        }
+    }
+}
+
+/// The metric vector of a measurement.
+enum Family {
+    Counter(CounterVec),
+    Gauge(GaugeVec),
+    Histogram(HistogramVec),
+}
+
+/// A registered metric and the names of its labels.
+struct LabeledFamily {
+    labels: Vec<String>,
+    family: Family,
+}
+
+/// A Prometheus metrics / events recorder.
+///
+/// The metrics are registered lazily when they are recorded for the first time, with the labels
+/// of that first record. Later records with other labels are dropped.
+pub struct Recorder {
+    /// The registry of the recorded metrics.
+    registry: Registry,
+    /// The registered metrics by name.
+    families: Mutex<HashMap<String, LabeledFamily>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// Creates a new Prometheus recorder.
+    pub fn new() -> Self {
+        Self {
+            registry: Registry::new(),
+            families: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the registry of the recorded metrics.
+    pub fn registry(&self) -> Registry {
+        self.registry.clone()
+    }
+
+    /// Encodes the recorded metrics in the Prometheus text format.
+    pub fn gather(&self) -> String {
+        encode(&self.registry)
+    }
+
+    /// Records a new metric.
+    pub fn metric<V, T, I>(&self, measurement: Measurement, value: V, tags: T)
+    where
+        V: Into<Type>,
+        T: Into<Option<I>>,
+        I: Into<Tags>,
+    {
+        let kind = Kind::of(&measurement);
+        let name = <&str>::from(measurement);
+        let value = match value.into() {
+            Type::Boolean(value) => value as u8 as f64,
+            Type::Float(value) => value,
+            Type::SignedInteger(value) => value as f64,
+            Type::UnsignedInteger(value) => value as f64,
+            Type::Text(value) => {
+                warn!(
+                    "dropping metric {} with a non numeric value {}",
+                    name, value
+                );
+                return;
+            }
+        };
+        let labels = tags
+            .into()
+            .map(|tags| {
+                let tags: Tags = tags.into();
+                tags.into_iter()
+                    .filter(|(tag, _)| tag != ROUND_ID_TAG)
+                    .map(|(tag, value)| (tag, value.to_string()))
+                    .collect()
+            })
+            .unwrap_or_else(Vec::new);
+        let name = match kind {
+            Kind::Counter => format!("{}_total", name),
+            Kind::Gauge | Kind::Histogram => name.to_string(),
+        };
+        self.record(kind, name, labels, value);
+    }
+
+    /// Records a new event.
+    ///
+    /// Prometheus has no events, hence the events are counted by title.
+    pub fn event<H, D, S, T, A, B>(&self, title: H, _description: D, _tags: T)
+    where
+        H: Into<String>,
+        D: Into<Option<S>>,
+        S: Into<String>,
+        T: Into<Option<A>>,
+        A: AsRef<[B]>,
+        B: Borrow<str>,
+    {
+        let labels = vec![("title".to_string(), title.into())];
+        self.record(Kind::Counter, "events_total".to_string(), labels, 1.0);
+    }
+
+    fn record(&self, kind: Kind, name: String, mut labels: Vec<(String, String)>, value: f64) {
+        if kind == Kind::Counter && value < 0.0 {
+            warn!("dropping negative increment {} of counter {}", value, name);
+            return;
+        }
+        labels.sort();
+
+        let mut families = self.families.lock().unwrap();
+        if !families.contains_key(&name) {
+            let label_names = labels
+                .iter()
+                .map(|(label, _)| label.clone())
+                .collect::<Vec<_>>();
+            match register(&self.registry, kind, &name, &label_names) {
+                Ok(family) => {
+                    families.insert(
+                        name.clone(),
+                        LabeledFamily {
+                            labels: label_names,
+                            family,
+                        },
+                    );
+                }
+                Err(err) => {
+                    warn!("failed to register metric {}: {}", name, err);
+                    return;
+                }
+            }
+        }
+        // safe unwrap: the family is registered above
+        let LabeledFamily {
+            labels: names,
+            family,
+        } = families.get(&name).unwrap();
+        if names.len() != labels.len() || names.iter().zip(&labels).any(|(n, (l, _))| n != l) {
+            warn!(
+                "dropping metric {} with unexpected labels {:?}",
+                name, labels
+            );
+            return;
+        }
+
+        let values = labels
+            .iter()
+            .map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>();
+        match family {
+            Family::Counter(counter) => counter.with_label_values(&values).inc_by(value),
+            Family::Gauge(gauge) => gauge.with_label_values(&values).set(value),
+            Family::Histogram(histogram) => histogram.with_label_values(&values).observe(value),
+        }
+    }
+}
+
+/// Creates and registers the metric vector of a measurement.
+fn register(
+    registry: &Registry,
+    kind: Kind,
+    name: &str,
+    labels: &[String],
+) -> ::prometheus::Result<Family> {
+    let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+    let help = format!("The {} measurement of the coordinator.", name);
+    let family = match kind {
+        Kind::Counter => {
+            let counter = CounterVec::new(Opts::new(name, help).namespace(NAMESPACE), &labels)?;
+            registry.register(Box::new(counter.clone()))?;
+            Family::Counter(counter)
+        }
+        Kind::Gauge => {
+            let gauge = GaugeVec::new(Opts::new(name, help).namespace(NAMESPACE), &labels)?;
+            registry.register(Box::new(gauge.clone()))?;
+            Family::Gauge(gauge)
+        }
+        Kind::Histogram => {
+            let opts = HistogramOpts::new(name, help)
+                .namespace(NAMESPACE)
+                .buckets(exponential_buckets(1.0, 2.0, 12)?);
+            let histogram = HistogramVec::new(opts, &labels)?;
+            registry.register(Box::new(histogram.clone()))?;
+            Family::Histogram(histogram)
+        }
+    };
+    Ok(family)
+}
+
+/// Encodes the metrics of a registry in the Prometheus text format.
+pub fn encode(registry: &Registry) -> String {
+    let mut buffer = Vec::new();
+    if let Err(err) = TextEncoder::new().encode(&registry.gather(), &mut buffer) {
+        warn!("failed to encode the metrics: {}", err);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates key-value tags for metrics.
+    macro_rules! tags {
+        ($(($tag: expr, $val: expr)),+ $(,)?) => {
+            {
+                let mut tags = Tags::new();
+                $(
+                    tags.add($tag, $val);
+                )+
+                tags
+            }
+        };
+    }
+
+    #[test]
+    fn test_counter() {
+        let recorder = Recorder::new();
+        for _ in 0..3 {
+            recorder.metric(
+                Measurement::MessageAccepted,
+                1,
+                tags![("round_id", 1), ("phase", 2)],
+            );
+        }
+        recorder.metric(
+            Measurement::MessageAccepted,
+            1,
+            tags![("round_id", 2), ("phase", 3)],
+        );
+        let metrics = recorder.gather();
+        assert!(metrics.contains("# TYPE xaynet_message_accepted_total counter"));
+        assert!(metrics.contains("xaynet_message_accepted_total{phase=\"2\"} 3"));
+        assert!(metrics.contains("xaynet_message_accepted_total{phase=\"3\"} 1"));
+        assert!(!metrics.contains("round_id"));
+    }
+
+    #[test]
+    fn test_gauge() {
+        let recorder = Recorder::new();
+        recorder.metric::<_, _, Tags>(Measurement::RoundTotalNumber, 1, None);
+        recorder.metric::<_, _, Tags>(Measurement::RoundTotalNumber, 2, None);
+        let metrics = recorder.gather();
+        assert!(metrics.contains("# TYPE xaynet_round_total_number gauge"));
+        assert!(metrics.contains("xaynet_round_total_number 2"));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let recorder = Recorder::new();
+        recorder.metric::<_, _, Tags>(Measurement::ConnectionRequests, 3, None);
+        let metrics = recorder.gather();
+        assert!(metrics.contains("# TYPE xaynet_connection_requests histogram"));
+        assert!(metrics.contains("xaynet_connection_requests_bucket{le=\"4\"} 1"));
+        assert!(metrics.contains("xaynet_connection_requests_count 1"));
+    }
+
+    #[test]
+    fn test_unexpected_labels() {
+        let recorder = Recorder::new();
+        recorder.metric(Measurement::Phase, 1, tags![("phase", 1)]);
+        recorder.metric(Measurement::Phase, 2, tags![("stage", "other")]);
+        recorder.metric(Measurement::Phase, "text", tags![("phase", 1)]);
+        let metrics = recorder.gather();
+        assert!(metrics.contains("xaynet_phase{phase=\"1\"} 1"));
+        assert!(!metrics.contains("stage"));
+    }
+
+    #[test]
+    fn test_event() {
+        let recorder = Recorder::new();
+        recorder.event::<_, _, &str, _, &[_], &str>("Phase error", None, None);
+        let metrics = recorder.gather();
+        assert!(metrics.contains("xaynet_events_total{title=\"Phase error\"} 1"));
+    }
+}
@@ -0,0 +1,403 @@
+//! The administrative routes under `/admin`.
+//!
+//! The routes are only served if the `[admin]` settings are present (see [`AdminSettings`]),
+//! otherwise they are not found. The requests must be authenticated with the token of the
+//! settings as a bearer token, otherwise they are rejected with `401 Unauthorized`.
+//!
+//! - `GET /admin/canary` tells whether the upcoming round is a canary round, as JSON like
+//!   `{"upcoming":false}`.
+//! - `PUT /admin/canary` schedules the upcoming round as a canary round, or cancels it, with a
+//!   JSON body like `{"upcoming":true}` (see [`canary`]).
+//! - `POST /admin/retention/purge` runs a purge cycle right away and answers with the number of
+//!   purged records per data class, as JSON like `{"audit_log":12}` (see [`retention`]). The
+//!   route is not found unless the `[retention]` settings are present.
+//!
+//! If the audit log is enabled in the settings, the administrative actions are recorded in it
+//! before they take effect (see [`audit`]). An action that can't be recorded is not executed and
+//! the request is answered with `500 Internal Server Error`. The requests which don't change
+//! anything are not recorded.
+//!
+//! [`canary`]: crate::state_machine::canary
+//! [`retention`]: crate::retention
+//! [`audit`]: crate::audit
+
+use std::{convert::Infallible, sync::Arc};
+
+use displaydoc::Display;
+use serde::{Deserialize, Serialize};
+use sodiumoxide::utils::memcmp;
+use thiserror::Error;
+use tracing::{error, info};
+use warp::{
+    http::{Response, StatusCode},
+    Filter,
+};
+
+use crate::{
+    audit::{AuditError, AuditLog},
+    retention::{PurgeReport, RetentionError, RetentionTrigger},
+    settings::AdminSettings,
+    state_machine::canary::CanarySwitch,
+};
+
+/// The maximum size of the body of an administrative request.
+const MAX_BODY_LENGTH: u64 = 1024;
+
+/// The request is not authenticated with the token of the admin settings.
+#[derive(Debug)]
+pub(super) struct Unauthorized;
+
+impl warp::reject::Reject for Unauthorized {}
+
+/// The state of the administrative routes.
+#[derive(Clone, Debug)]
+pub struct Admin {
+    token: Arc<String>,
+    audit: Option<Arc<AuditLog>>,
+    canary: CanarySwitch,
+    retention: Option<RetentionTrigger>,
+}
+
+impl Admin {
+    /// Creates the state of the administrative routes, which control the canary rounds of the
+    /// state machine with the given `canary` switch.
+    ///
+    /// # Errors
+    /// Fails if the audit log of the settings can't be opened or if its records are invalid.
+    pub fn new(settings: AdminSettings, canary: CanarySwitch) -> Result<Self, AuditError> {
+        let audit = settings
+            .audit_log
+            .map(AuditLog::open)
+            .transpose()?
+            .map(Arc::new);
+        Ok(Self {
+            token: Arc::new(settings.token),
+            audit,
+            canary,
+            retention: None,
+        })
+    }
+
+    /// Serves the route which triggers an immediate purge cycle with the given `retention`
+    /// trigger.
+    pub fn with_retention(mut self, retention: RetentionTrigger) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    /// Returns the audit log of the administrative actions, if it is enabled.
+    pub fn audit_log(&self) -> Option<Arc<AuditLog>> {
+        self.audit.clone()
+    }
+
+    /// Checks whether the `Authorization` header carries the admin token as a bearer token.
+    fn authenticate(&self, authorization: Option<&str>) -> bool {
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| memcmp(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    /// Schedules the upcoming round as a canary round, or cancels it, once the action is
+    /// recorded in the audit log.
+    fn set_canary(&self, upcoming: bool) -> Result<(), AuditError> {
+        let entry = match self.audit {
+            Some(ref audit) => Some(audit.begin(
+                "canary.set",
+                vec![("upcoming", upcoming.to_string())],
+                Some(self.token.as_str()),
+            )?),
+            None => None,
+        };
+        info!("admin: the upcoming round is a canary round: {}", upcoming);
+        self.canary.set_upcoming(upcoming);
+        match (&self.audit, entry) {
+            (Some(audit), Some(entry)) => audit.finish(entry, &Ok::<_, Infallible>(())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs a purge cycle with the `retention` trigger right away, once the action is recorded
+    /// in the audit log.
+    async fn purge(&self, retention: &RetentionTrigger) -> Result<PurgeReport, PurgeError> {
+        let entry = match self.audit {
+            Some(ref audit) => Some(audit.begin(
+                "retention.purge",
+                Vec::<(String, String)>::new(),
+                Some(self.token.as_str()),
+            )?),
+            None => None,
+        };
+        info!("admin: running a purge cycle");
+        let report = retention.purge().await;
+        if let (Some(audit), Some(entry)) = (&self.audit, entry) {
+            audit.finish(entry, &report)?;
+        }
+        Ok(report?)
+    }
+}
+
+/// Errors of the purge route.
+#[derive(Debug, Display, Error)]
+enum PurgeError {
+    /// Failed to audit the admin action: {0}.
+    Audit(#[from] AuditError),
+    /// Failed to purge the expired records: {0}.
+    Retention(#[from] RetentionError),
+}
+
+/// Whether the upcoming round is a canary round.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+struct CanaryState {
+    upcoming: bool,
+}
+
+/// The administrative routes, which are not found if `admin` is `None`.
+pub(super) fn admin_routes(
+    admin: Option<Admin>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let set_canary = warp::put()
+        .and(warp::body::content_length_limit(MAX_BODY_LENGTH))
+        .and(warp::body::json())
+        .map(Some);
+    let canary = warp::path!("admin" / "canary")
+        .and(authenticated(admin.clone()))
+        .and(warp::get().map(|| None).or(set_canary).unify())
+        .map(|admin: Admin, request: Option<CanaryState>| {
+            if let Some(CanaryState { upcoming }) = request {
+                if let Err(err) = admin.set_canary(upcoming) {
+                    error!("failed to audit the admin action: {}", err);
+                    return Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(Vec::new())
+                        .unwrap();
+                }
+            }
+            let state = CanaryState {
+                upcoming: admin.canary.is_upcoming(),
+            };
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .status(StatusCode::OK)
+                .body(serde_json::to_vec(&state).unwrap())
+                .unwrap()
+        });
+    let purge = warp::path!("admin" / "retention" / "purge")
+        .and(authenticated(admin))
+        .and(warp::post())
+        .and_then(|admin: Admin| async move {
+            let retention = match admin.retention {
+                Some(ref retention) => retention.clone(),
+                None => return Err(warp::reject::not_found()),
+            };
+            let response = match admin.purge(&retention).await {
+                Ok(report) => Response::builder()
+                    .header("Content-Type", "application/json")
+                    .status(StatusCode::OK)
+                    .body(serde_json::to_vec(&report).unwrap())
+                    .unwrap(),
+                Err(err) => {
+                    error!("{}", err);
+                    Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(Vec::new())
+                        .unwrap()
+                }
+            };
+            Ok(response)
+        });
+    canary.or(purge).unify()
+}
+
+/// Extracts the state of the administrative routes if the request is authenticated.
+fn authenticated(
+    admin: Option<Admin>,
+) -> impl Filter<Extract = (Admin,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(
+        move |authorization: Option<String>| {
+            let admin = admin.clone();
+            async move {
+                match admin {
+                    Some(admin) if admin.authenticate(authorization.as_deref()) => Ok(admin),
+                    Some(_) => Err(warp::reject::custom(Unauthorized)),
+                    None => Err(warp::reject::not_found()),
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        audit::{tests::TempLog, token_fingerprint, Outcome},
+        retention::Retention,
+        settings::RetentionSettings,
+        storage::tests::MockModelStore,
+    };
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn admin(canary: CanarySwitch) -> Admin {
+        let settings = AdminSettings {
+            token: TOKEN.to_string(),
+            audit_log: None,
+        };
+        Admin::new(settings, canary).unwrap()
+    }
+
+    async fn set_canary<F>(routes: &F, upcoming: bool) -> Response<bytes::Bytes>
+    where
+        F: Filter + 'static,
+        F::Extract: warp::Reply + Send,
+    {
+        warp::test::request()
+            .method("PUT")
+            .path("/admin/canary")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .json(&CanaryState { upcoming })
+            .reply(routes)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_canary() {
+        let canary = CanarySwitch::new();
+        let routes = admin_routes(Some(admin(canary.clone())));
+
+        let response = set_canary(&routes, true).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), br#"{"upcoming":true}"#);
+        assert!(canary.is_upcoming());
+
+        let response = warp::test::request()
+            .path("/admin/canary")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), br#"{"upcoming":true}"#);
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() {
+        let canary = CanarySwitch::new();
+        let routes = admin_routes(Some(admin(canary.clone())));
+
+        for authorization in &[None, Some("Bearer 0123"), Some(TOKEN)] {
+            let mut request = warp::test::request()
+                .method("PUT")
+                .path("/admin/canary")
+                .json(&CanaryState { upcoming: true });
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", *authorization);
+            }
+            let rejection = request.filter(&routes).await.err().unwrap();
+            assert!(rejection.find::<Unauthorized>().is_some());
+        }
+        assert!(!canary.is_upcoming());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        let file = TempLog::new();
+        let canary = CanarySwitch::new();
+        let settings = AdminSettings {
+            token: TOKEN.to_string(),
+            audit_log: Some(file.0.clone()),
+        };
+        let routes = admin_routes(Some(Admin::new(settings, canary.clone()).unwrap()));
+
+        assert_eq!(set_canary(&routes, true).await.status(), StatusCode::OK);
+        assert!(canary.is_upcoming());
+        // reading the state is not an action
+        let response = warp::test::request()
+            .path("/admin/canary")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let records = file.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].action, "canary.set");
+        assert_eq!(records[0].parameters["upcoming"], "true");
+        assert_eq!(records[0].token_fingerprint, Some(token_fingerprint(TOKEN)));
+        assert_eq!(records[0].outcome, Outcome::Pending);
+        assert_eq!(records[1].outcome, Outcome::Succeeded { begin: 0 });
+        assert_eq!(file.verify().unwrap().len, 2);
+        assert!(!std::fs::read_to_string(&file.0).unwrap().contains(TOKEN));
+    }
+
+    #[tokio::test]
+    async fn test_audit_log_failure() {
+        let file = TempLog::new();
+        std::fs::write(&file.0, "").unwrap();
+        let canary = CanarySwitch::new();
+        let mut admin = admin(canary.clone());
+        admin.audit = Some(Arc::new(AuditLog::read_only(&file.0)));
+        let routes = admin_routes(Some(admin));
+
+        // the action is not executed if it can't be recorded
+        let response = set_canary(&routes, true).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!canary.is_upcoming());
+    }
+
+    #[tokio::test]
+    async fn test_retention_purge() {
+        let file = TempLog::new();
+        let settings = AdminSettings {
+            token: TOKEN.to_string(),
+            audit_log: Some(file.0.clone()),
+        };
+        let admin = Admin::new(settings, CanarySwitch::new()).unwrap();
+
+        // the route is not found without the retention settings
+        let rejection = warp::test::request()
+            .method("POST")
+            .path("/admin/retention/purge")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .filter(&admin_routes(Some(admin.clone())))
+            .await
+            .err()
+            .unwrap();
+        assert!(rejection.is_not_found());
+
+        let settings = RetentionSettings {
+            audit_log: Some(86400),
+            interval: 86400,
+            max_records: 1000,
+        };
+        let (retention, trigger) =
+            Retention::new(settings, admin.audit_log(), MockModelStore::new());
+        let task = tokio::spawn(retention.run());
+        let routes = admin_routes(Some(admin.with_retention(trigger)));
+        let response = warp::test::request()
+            .method("POST")
+            .path("/admin/retention/purge")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), br#"{"audit_log":0}"#);
+
+        let records = file.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].action, "retention.purge");
+        assert_eq!(records[1].outcome, Outcome::Succeeded { begin: 0 });
+        task.abort();
+    }
+
+    #[tokio::test]
+    async fn test_admin_disabled() {
+        let rejection = warp::test::request()
+            .path("/admin/canary")
+            .header("authorization", format!("Bearer {}", TOKEN))
+            .filter(&admin_routes(None))
+            .await
+            .err()
+            .unwrap();
+        assert!(rejection.is_not_found());
+    }
+}
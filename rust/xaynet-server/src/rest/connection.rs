@@ -0,0 +1,379 @@
+//! Tuning and tracking of the connections to the REST API.
+
+use std::{
+    convert::Infallible,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::future::{self, Ready};
+use hyper::server::{
+    accept::Accept,
+    conn::{AddrIncoming, AddrStream},
+    Builder,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{self, Instant, Sleep},
+};
+use tower::Service;
+
+#[cfg(feature = "tls")]
+use super::tls::{TlsAcceptor, TlsStream};
+use super::RestError;
+use crate::{metric, metrics::Measurement, settings::ApiSettings};
+
+/// Counters of the connections to the REST API.
+///
+/// The counters are kept alongside the connection metrics, which are only recorded if a
+/// metrics recorder is installed. The TLS handshakes are only recorded as metrics.
+#[derive(Debug, Default)]
+pub(crate) struct ConnectionStats {
+    accepted: AtomicU64,
+    active: AtomicU64,
+    requests: AtomicU64,
+}
+
+impl ConnectionStats {
+    /// Returns the number of accepted connections.
+    #[cfg(test)]
+    #[cfg_attr(feature = "tls", allow(dead_code))]
+    pub fn accepted(&self) -> u64 {
+        self.accepted.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of open connections.
+    #[cfg(test)]
+    #[cfg_attr(feature = "tls", allow(dead_code))]
+    pub fn active(&self) -> u64 {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests served over all connections.
+    #[cfg(test)]
+    #[cfg_attr(feature = "tls", allow(dead_code))]
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    fn on_accept(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+        let active = self.active.fetch_add(1, Ordering::Relaxed) + 1;
+        metric!(Measurement::ConnectionsAccepted, 1);
+        metric!(Measurement::ConnectionsActive, active);
+    }
+
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn on_handshake(&self) {
+        metric!(Measurement::TlsHandshakes, 1);
+    }
+
+    fn on_close(&self, requests: u64) {
+        let active = self.active.fetch_sub(1, Ordering::Relaxed) - 1;
+        metric!(Measurement::ConnectionsActive, active);
+        metric!(Measurement::ConnectionRequests, requests);
+    }
+}
+
+/// Applies the connection settings of the REST API to a server.
+pub(crate) fn configure<I>(builder: Builder<I>, api_settings: &ApiSettings) -> Builder<I> {
+    builder
+        .http1_only(!api_settings.http2)
+        .http2_max_concurrent_streams(api_settings.max_concurrent_streams)
+}
+
+#[cfg(not(feature = "tls"))]
+type Stream = AddrStream;
+#[cfg(feature = "tls")]
+type Stream = TlsStream<AddrStream>;
+
+/// The incoming connections of the REST API.
+///
+/// Connections are secured with TLS if the `tls` feature is enabled and are closed once
+/// they have been idle for longer than the keep alive timeout.
+pub(crate) struct Incoming {
+    incoming: AddrIncoming,
+    keep_alive_timeout: Option<Duration>,
+    #[cfg(feature = "tls")]
+    tls: TlsAcceptor,
+}
+
+impl Incoming {
+    /// Binds the incoming connections to the address of the REST API.
+    ///
+    /// # Errors
+    /// Fails if the address cannot be bound or if the TLS settings are invalid.
+    pub fn bind(
+        api_settings: &ApiSettings,
+        #[cfg_attr(not(feature = "tls"), allow(unused_variables))] stats: Arc<ConnectionStats>,
+    ) -> Result<Self, RestError> {
+        Ok(Self {
+            incoming: AddrIncoming::bind(&api_settings.bind_address).map_err(RestError::Bind)?,
+            keep_alive_timeout: api_settings.keep_alive_timeout.map(Duration::from_secs),
+            #[cfg(feature = "tls")]
+            tls: TlsAcceptor::new(api_settings, stats)?,
+        })
+    }
+
+    /// Returns the address the incoming connections are bound to.
+    #[cfg(test)]
+    #[cfg_attr(feature = "tls", allow(dead_code))]
+    pub fn local_addr(&self) -> std::net::SocketAddr {
+        self.incoming.local_addr()
+    }
+}
+
+impl Accept for Incoming {
+    type Conn = IdleTimeout<Stream>;
+    type Error = io::Error;
+
+    fn poll_accept(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        let stream = match futures::ready!(Pin::new(&mut self.incoming).poll_accept(cx)) {
+            Some(Ok(stream)) => stream,
+            Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+            None => return Poll::Ready(None),
+        };
+        #[cfg(feature = "tls")]
+        let stream = self.tls.accept(stream);
+        Poll::Ready(Some(Ok(IdleTimeout::new(stream, self.keep_alive_timeout))))
+    }
+}
+
+/// A connection that fails once nothing has been read from or written to it for longer
+/// than its timeout.
+pub(crate) struct IdleTimeout<S> {
+    stream: S,
+    timeout: Option<Duration>,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> IdleTimeout<S> {
+    fn new(stream: S, timeout: Option<Duration>) -> Self {
+        Self {
+            stream,
+            timeout,
+            sleep: timeout.map(|timeout| Box::pin(time::sleep(timeout))),
+        }
+    }
+
+    /// Restarts the timeout after some traffic.
+    fn reset(&mut self) {
+        if let (Some(timeout), Some(sleep)) = (self.timeout, self.sleep.as_mut()) {
+            sleep.as_mut().reset(Instant::now() + timeout);
+        }
+    }
+
+    /// Checks whether the connection has been idle for too long while waiting for traffic.
+    fn poll_timeout(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(sleep) = self.sleep.as_mut() {
+            futures::ready!(sleep.as_mut().poll(cx));
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "the connection has been idle for too long",
+            )));
+        }
+        Poll::Pending
+    }
+}
+
+impl<S> AsyncRead for IdleTimeout<S>
+where
+    S: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.stream).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                this.reset();
+                Poll::Ready(result)
+            }
+            Poll::Pending => this.poll_timeout(cx),
+        }
+    }
+}
+
+impl<S> AsyncWrite for IdleTimeout<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let written = futures::ready!(Pin::new(&mut this.stream).poll_write(cx, buf));
+        this.reset();
+        Poll::Ready(written)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+    }
+}
+
+/// Makes a [`TrackedService`] for each accepted connection.
+#[derive(Clone)]
+pub(crate) struct MakeTrackedService<S> {
+    service: S,
+    stats: Arc<ConnectionStats>,
+}
+
+impl<S> MakeTrackedService<S> {
+    pub fn new(service: S, stats: Arc<ConnectionStats>) -> Self {
+        Self { service, stats }
+    }
+}
+
+impl<'a, S, T> Service<&'a T> for MakeTrackedService<S>
+where
+    S: Clone,
+{
+    type Response = TrackedService<S>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _conn: &'a T) -> Self::Future {
+        self.stats.on_accept();
+        future::ok(TrackedService {
+            service: self.service.clone(),
+            stats: self.stats.clone(),
+            requests: 0,
+        })
+    }
+}
+
+/// The service of a connection, which counts the requests served over the connection
+/// until it is closed.
+pub(crate) struct TrackedService<S> {
+    service: S,
+    stats: Arc<ConnectionStats>,
+    requests: u64,
+}
+
+impl<S, R> Service<R> for TrackedService<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        self.requests += 1;
+        self.stats.requests.fetch_add(1, Ordering::Relaxed);
+        self.service.call(req)
+    }
+}
+
+impl<S> Drop for TrackedService<S> {
+    fn drop(&mut self) {
+        self.stats.on_close(self.requests);
+    }
+}
+
+#[cfg(all(test, not(feature = "tls")))]
+mod tests {
+    use warp::Filter;
+
+    use super::*;
+    use xaynet_sdk::client::{reqwest_client_builder, XaynetHttpClient, DEFAULT_POOL_IDLE_TIMEOUT};
+
+    fn api_settings(http2: bool, keep_alive_timeout: Option<u64>) -> ApiSettings {
+        ApiSettings {
+            bind_address: ([127, 0, 0, 1], 0).into(),
+            debug_rejections: false,
+            non_production: false,
+            http2,
+            keep_alive_timeout,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
+        }
+    }
+
+    /// Starts a server that answers all requests with `ok`. Returns its URL and the
+    /// counters of its connections.
+    fn start_server(api_settings: ApiSettings) -> (String, Arc<ConnectionStats>) {
+        let stats = Arc::new(ConnectionStats::default());
+        let incoming = Incoming::bind(&api_settings, stats.clone()).unwrap();
+        let url = format!("http://{}", incoming.local_addr());
+        let service = warp::service(warp::any().map(|| "ok"));
+        let server = configure(hyper::Server::builder(incoming), &api_settings)
+            .serve(MakeTrackedService::new(service, stats.clone()));
+        tokio::spawn(server);
+        (url, stats)
+    }
+
+    #[tokio::test]
+    async fn test_sequential_requests_reuse_connection() {
+        let (url, stats) = start_server(api_settings(true, None));
+        let mut client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+
+        for _ in 0..2 {
+            let body = XaynetHttpClient::get(&mut client, &url).await.unwrap();
+            assert_eq!(body.unwrap().as_ref(), b"ok");
+        }
+        assert_eq!(stats.accepted(), 1);
+        assert_eq!(stats.active(), 1);
+        assert_eq!(stats.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_http2_setting() {
+        for &http2 in &[true, false] {
+            let (url, _) = start_server(api_settings(http2, None));
+            let client = reqwest::ClientBuilder::new()
+                .http2_prior_knowledge()
+                .build()
+                .unwrap();
+            assert_eq!(client.get(&url).send().await.is_ok(), http2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_timeout() {
+        let (url, stats) = start_server(api_settings(true, Some(1)));
+        let mut client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+
+        XaynetHttpClient::get(&mut client, &url).await.unwrap();
+        assert_eq!(stats.active(), 1);
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(stats.active(), 0);
+
+        XaynetHttpClient::get(&mut client, &url).await.unwrap();
+        assert_eq!(stats.accepted(), 2);
+    }
+}
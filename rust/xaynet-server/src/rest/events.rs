@@ -0,0 +1,279 @@
+//! A WebSocket endpoint which pushes the events of the rounds to the participants.
+//!
+//! Participants otherwise poll the round parameters and dictionaries at a fixed interval,
+//! which doesn't scale to many participants. The events are pushed as JSON encoded
+//! [`RoundEventFrame`]s, and they only tell what is available: the participants still fetch
+//! the data from the other routes.
+//!
+//! Slow participants don't hold back the coordinator: only the latest value of each event is
+//! kept, so that events which a participant couldn't keep up with are coalesced, and a
+//! participant that doesn't read its socket for [`PUSH_TIMEOUT`] is disconnected.
+
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use tokio::time::timeout;
+use tracing::{debug, warn};
+use warp::{
+    ws::{Message, WebSocket, Ws},
+    Filter,
+};
+
+use crate::state_machine::events::{DictionaryUpdate, EventListener, EventSubscriber, ModelUpdate};
+use xaynet_core::{
+    common::{PhaseEvent, RoundEvent, RoundEventFrame, RoundMetadata, RoundParameters},
+    SeedDict,
+    SumDict,
+};
+
+/// The longest time that pushing an event to a participant may take, before the
+/// participant is disconnected.
+const PUSH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The listeners of the events which are pushed to a participant.
+#[derive(Clone)]
+pub(super) struct RoundEventListeners {
+    params: EventListener<RoundParameters>,
+    round_metadata: EventListener<RoundMetadata>,
+    sum_dict: EventListener<DictionaryUpdate<SumDict>>,
+    seed_dict: EventListener<DictionaryUpdate<SeedDict>>,
+    model: EventListener<ModelUpdate>,
+}
+
+impl RoundEventListeners {
+    pub(super) fn new(events: &EventSubscriber) -> Self {
+        Self {
+            params: events.params_listener(),
+            round_metadata: events.round_metadata_listener(),
+            sum_dict: events.sum_dict_listener(),
+            seed_dict: events.seed_dict_listener(),
+            model: events.model_listener(),
+        }
+    }
+
+    /// Marks the current events as seen.
+    fn mark_seen(&mut self) {
+        self.params.mark_seen();
+        self.round_metadata.mark_seen();
+        self.sum_dict.mark_seen();
+        self.seed_dict.mark_seen();
+        self.model.mark_seen();
+    }
+
+    /// Waits for the next event. Invalidated dictionaries and models are skipped.
+    ///
+    /// Returns `None` if the coordinator state machine shut down.
+    async fn next(&mut self) -> Option<RoundEvent> {
+        loop {
+            let event = tokio::select! {
+                changed = self.params.changed() => changed.ok().map(|_| {
+                    let round_id = self.params.get_latest().round_id;
+                    Some(RoundEvent::NewRound { round_id })
+                }),
+                changed = self.round_metadata.changed() => changed.ok().map(|_| {
+                    let metadata = self.round_metadata.get_latest().event;
+                    Some(RoundEvent::Phase(PhaseEvent::from(metadata)))
+                }),
+                changed = self.sum_dict.changed() => changed.ok().map(|_| {
+                    let latest = self.sum_dict.get_latest();
+                    matches!(latest.event, DictionaryUpdate::New(_))
+                        .then(|| RoundEvent::SumDict { round_id: latest.round_id })
+                }),
+                changed = self.seed_dict.changed() => changed.ok().map(|_| {
+                    let latest = self.seed_dict.get_latest();
+                    matches!(latest.event, DictionaryUpdate::New(_))
+                        .then(|| RoundEvent::SeedDict { round_id: latest.round_id })
+                }),
+                changed = self.model.changed() => changed.ok().map(|_| {
+                    let latest = self.model.get_latest();
+                    matches!(latest.event, ModelUpdate::New(_))
+                        .then(|| RoundEvent::GlobalModel { round_id: latest.round_id })
+                }),
+            };
+            match event {
+                Some(Some(event)) => return Some(event),
+                Some(None) => continue,
+                None => return None,
+            }
+        }
+    }
+}
+
+/// The route that pushes the events of the rounds at `/events`, or that is not found if the
+/// events are disabled.
+pub(super) fn events_route(
+    listeners: Option<RoundEventListeners>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("events")
+        .and(warp::get())
+        .and_then(move || {
+            let listeners = listeners.clone();
+            async move { listeners.ok_or_else(warp::reject::not_found) }
+        })
+        .and(warp::ws())
+        .map(|mut listeners: RoundEventListeners, ws: Ws| {
+            // a new participant is only pushed the events that are emitted after it connected
+            listeners.mark_seen();
+            ws.on_upgrade(move |socket| push_events(socket, listeners))
+        })
+}
+
+/// Pushes the events to the `socket` until the participant or the coordinator closes it.
+async fn push_events(socket: WebSocket, mut listeners: RoundEventListeners) {
+    let (mut tx, mut rx) = socket.split();
+    loop {
+        tokio::select! {
+            event = listeners.next() => {
+                let event = match event {
+                    Some(event) => event,
+                    None => break,
+                };
+                // UNWRAP_SAFE: the frame is always serializable
+                let frame = serde_json::to_string(&RoundEventFrame::new(event)).unwrap();
+                match timeout(PUSH_TIMEOUT, tx.send(Message::text(frame))).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        debug!("failed to push round event: {}", err);
+                        break;
+                    }
+                    Err(_) => {
+                        debug!("timed out pushing round event, disconnecting the participant");
+                        break;
+                    }
+                }
+            }
+            message = rx.next() => match message {
+                // the participant doesn't send anything but pings, which are answered by warp
+                Some(Ok(message)) if !message.is_close() => {}
+                Some(Err(err)) => {
+                    warn!("round events socket error: {}", err);
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+    let _ = tx.close().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::services::tests::utils::new_event_channels;
+    use xaynet_core::{
+        common::PhaseName,
+        mask::{FromPrimitives, Model},
+    };
+
+    async fn next_frame(client: &mut warp::test::WsClient) -> RoundEventFrame {
+        let message = client.recv().await.unwrap();
+        serde_json::from_str(message.to_str().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_push_events() {
+        let (mut publisher, subscriber) = new_event_channels();
+        let route = events_route(Some(RoundEventListeners::new(&subscriber)));
+        let mut client = warp::test::ws()
+            .path("/events")
+            .handshake(route)
+            .await
+            .unwrap();
+
+        publisher.set_round_id(1);
+        let params = subscriber.params_listener().get_latest().event;
+        publisher.broadcast_params(params);
+        assert_eq!(
+            next_frame(&mut client).await,
+            RoundEventFrame::new(RoundEvent::NewRound { round_id: 1 })
+        );
+
+        // invalidated dictionaries are not pushed
+        publisher.broadcast_sum_dict(DictionaryUpdate::Invalidate);
+        publisher.broadcast_sum_dict(DictionaryUpdate::New(Arc::new(SumDict::new())));
+        assert_eq!(
+            next_frame(&mut client).await,
+            RoundEventFrame::new(RoundEvent::SumDict { round_id: 1 })
+        );
+
+        publisher.broadcast_seed_dict(DictionaryUpdate::New(Arc::new(SeedDict::new())));
+        assert_eq!(
+            next_frame(&mut client).await,
+            RoundEventFrame::new(RoundEvent::SeedDict { round_id: 1 })
+        );
+
+        let model = Model::from_primitives(vec![0_i32; 2].into_iter()).unwrap();
+        publisher.broadcast_model(ModelUpdate::New(Arc::new(model)));
+        assert_eq!(
+            next_frame(&mut client).await,
+            RoundEventFrame::new(RoundEvent::GlobalModel { round_id: 1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_push_phase_events() {
+        let (mut publisher, subscriber) = new_event_channels();
+        let route = events_route(Some(RoundEventListeners::new(&subscriber)));
+        let mut client = warp::test::ws()
+            .path("/events")
+            .handshake(route)
+            .await
+            .unwrap();
+
+        let metadata = RoundMetadata {
+            round_id: 2,
+            phase: PhaseName::Update,
+            phase_start: 42,
+            phase_duration: None,
+            compression: false,
+            changed_since_previous: false,
+        };
+        publisher.broadcast_round_metadata(metadata);
+        assert_eq!(
+            next_frame(&mut client).await,
+            RoundEventFrame::new(RoundEvent::Phase(PhaseEvent {
+                round_id: 2,
+                phase_name: PhaseName::Update,
+                timestamp: 42,
+            }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_events_disabled() {
+        let route = events_route(None);
+        assert!(warp::test::ws()
+            .path("/events")
+            .handshake(route)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_frame_format() {
+        let frame = RoundEventFrame::new(RoundEvent::NewRound { round_id: 3 });
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(json, r#"{"version":1,"event":"new_round","round_id":3}"#);
+        assert_eq!(
+            serde_json::from_str::<RoundEventFrame>(&json).unwrap(),
+            frame
+        );
+
+        let frame = RoundEventFrame::new(RoundEvent::Phase(PhaseEvent {
+            round_id: 3,
+            phase_name: PhaseName::Sum2,
+            timestamp: 1600000000,
+        }));
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(
+            json,
+            r#"{"version":1,"event":"phase","round_id":3,"phase_name":"Sum2","timestamp":1600000000}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<RoundEventFrame>(&json).unwrap(),
+            frame
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Gzip compression of the PET messages and of the responses of the REST API.
+//!
+//! If enabled, PET messages may be sent compressed with a `Content-Encoding: gzip` header
+//! and are decompressed before they are handled. Responses of at least the configured
+//! threshold are compressed for clients that send an `Accept-Encoding` header which accepts
+//! gzip. Only complete `200 OK` responses are compressed, hence the ranges of the global
+//! models always refer to the uncompressed bytes. The entity tags of compressed responses are
+//! weakened, such that they never match an `If-Range` condition. Clients that don't use gzip
+//! are served as if it was disabled.
+
+use std::{
+    convert::Infallible,
+    io::{Read, Write},
+};
+
+use bytes::Bytes;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use hyper::Body;
+use tokio::task::spawn_blocking;
+use tracing::warn;
+use warp::{
+    http::{header, HeaderValue, Response, StatusCode},
+    reply::Reply,
+    Filter,
+};
+
+/// The maximum length of a decompressed PET message, which protects the coordinator against
+/// decompression bombs.
+const MAX_DECOMPRESSED_LENGTH: u64 = 1 << 30;
+
+/// The content coding of the message is not supported.
+#[derive(Debug)]
+pub(super) struct UnsupportedEncoding;
+
+impl warp::reject::Reject for UnsupportedEncoding {}
+
+/// The message is not valid gzip.
+#[derive(Debug)]
+pub(super) struct InvalidGzip;
+
+impl warp::reject::Reject for InvalidGzip {}
+
+/// Extracts the body of a request, decompressed according to its `Content-Encoding` header.
+///
+/// Bodies without a content coding are always accepted. Gzip compressed bodies are only
+/// accepted if gzip is `enabled`, other codings are rejected with [`UnsupportedEncoding`].
+pub(super) fn body(
+    enabled: bool,
+) -> impl Filter<Extract = (Bytes,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("content-encoding")
+        .and(warp::body::bytes())
+        .and_then(move |encoding: Option<String>, body: Bytes| async move {
+            match encoding.as_deref().map(str::trim) {
+                None | Some("") => Ok(body),
+                Some(coding) if coding.eq_ignore_ascii_case("identity") => Ok(body),
+                Some(coding) if enabled && coding.eq_ignore_ascii_case("gzip") => {
+                    spawn_blocking(move || decompress(&body))
+                        .await
+                        .map_err(|_| warp::reject::custom(InvalidGzip))?
+                        .map(Bytes::from)
+                        .map_err(|_| warp::reject::custom(InvalidGzip))
+                }
+                Some(_) => Err(warp::reject::custom(UnsupportedEncoding)),
+            }
+        })
+}
+
+/// Compresses the replies of the `routes` with gzip if the client accepts it and they are at
+/// least `threshold` bytes long. Replies are never compressed if the `threshold` is `None`.
+pub(super) fn compress<F, R>(
+    routes: F,
+    threshold: Option<usize>,
+) -> impl Filter<Extract = (Response<Body>,), Error = warp::Rejection> + Clone
+where
+    F: Filter<Extract = (R,), Error = warp::Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    warp::header::optional::<String>("accept-encoding")
+        .and(routes)
+        .and_then(move |accept_encoding: Option<String>, reply: R| {
+            let accepted = matches!(accept_encoding.as_deref().map(accepts_gzip), Some(true));
+            let threshold = threshold.filter(|_| accepted);
+            compress_reply(reply.into_response(), threshold)
+        })
+}
+
+/// Compresses the `response` if it is a complete, uncompressed response of at least
+/// `threshold` bytes.
+async fn compress_reply(
+    response: Response<Body>,
+    threshold: Option<usize>,
+) -> Result<Response<Body>, Infallible> {
+    let threshold = match threshold {
+        Some(threshold)
+            if response.status() == StatusCode::OK
+                && !response.headers().contains_key(header::CONTENT_ENCODING) =>
+        {
+            threshold
+        }
+        _ => return Ok(response),
+    };
+
+    let (mut parts, body) = response.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("failed to read the response body: {}", e);
+            return Ok(Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap());
+        }
+    };
+    // the encoding depends on the request, so caches must keep them apart
+    parts
+        .headers
+        .append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    if body.len() < threshold {
+        return Ok(Response::from_parts(parts, Body::from(body)));
+    }
+
+    // UNWRAP_SAFE: the compression doesn't panic
+    let compressed = spawn_blocking(move || compress_body(&body)).await.unwrap();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    // the compressed bytes differ from the ones that the ranges refer to
+    if let Some(etag) = parts.headers.get(header::ETAG) {
+        let weak = format!("W/{}", etag.to_str().unwrap_or_default());
+        // UNWRAP_SAFE: the weakened tag consists of the visible characters of the tag
+        parts
+            .headers
+            .insert(header::ETAG, HeaderValue::from_str(&weak).unwrap());
+    }
+    parts
+        .headers
+        .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+    Ok(Response::from_parts(parts, Body::from(compressed)))
+}
+
+/// Checks whether the value of an `Accept-Encoding` header accepts gzip, i.e. whether it
+/// lists `gzip` or `*` without a zero quality value.
+fn accepts_gzip(accept_encoding: &str) -> bool {
+    let mut wildcard = false;
+    for item in accept_encoding.split(',') {
+        let mut params = item.split(';');
+        // UNWRAP_SAFE: split yields at least one item
+        let coding = params.next().unwrap().trim();
+        let accepted = match params.find_map(|param| param.trim().strip_prefix("q=")) {
+            Some(quality) => matches!(quality.trim().parse::<f32>(), Ok(q) if q > 0.0),
+            None => true,
+        };
+        if coding.eq_ignore_ascii_case("gzip") {
+            return accepted;
+        }
+        if coding == "*" {
+            wildcard = accepted;
+        }
+    }
+    wildcard
+}
+
+fn compress_body(body: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    // UNWRAP_SAFE: writing to a vector never fails
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn decompress(body: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(body)
+        .take(MAX_DECOMPRESSED_LENGTH + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() as u64 > MAX_DECOMPRESSED_LENGTH {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "decompressed message too large",
+        ));
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accepts_gzip() {
+        assert!(accepts_gzip("gzip"));
+        assert!(accepts_gzip("deflate, GZIP;q=0.5"));
+        assert!(accepts_gzip("*"));
+        assert!(accepts_gzip("br, *;q=0.1"));
+        assert!(!accepts_gzip("identity"));
+        assert!(!accepts_gzip("gzip;q=0"));
+        assert!(!accepts_gzip("gzip;q=0, *"));
+        assert!(!accepts_gzip("*;q=0"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let body = vec![3; 10_000];
+        let compressed = compress_body(&body);
+        assert!(compressed.len() < body.len());
+        assert_eq!(decompress(&compressed).unwrap(), body);
+        assert!(decompress(&body).is_err());
+    }
+}
@@ -0,0 +1,1257 @@
+//! A HTTP API for the PET protocol interactions.
+
+mod admin;
+mod connection;
+mod events;
+mod gzip;
+mod range;
+mod rate_limit;
+#[cfg(feature = "tls")]
+mod tls;
+
+use std::{
+    convert::{Infallible, TryFrom},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use hyper::{Body, Server};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, warn};
+use warp::{
+    http::{
+        header::{HeaderName, HeaderValue},
+        Response,
+        StatusCode,
+    },
+    reply::Reply,
+    Filter,
+};
+
+pub use self::admin::Admin;
+
+use self::{
+    admin::admin_routes,
+    connection::{ConnectionStats, Incoming, MakeTrackedService},
+    events::{events_route, RoundEventListeners},
+};
+use crate::{
+    services::{
+        fetchers::Fetcher,
+        messages::{PetMessageHandler, ServiceError},
+    },
+    settings::ApiSettings,
+    state_machine::{events::EventSubscriber, requests::RequestError},
+};
+use xaynet_core::{
+    common::Canonical,
+    crypto::ByteObject,
+    mask::ModelShape,
+    message::Tag,
+    ParticipantPublicKey,
+};
+
+#[derive(Deserialize, Serialize)]
+struct SeedDictQuery {
+    pk: String,
+}
+
+/// The unencrypted envelope of a PET message. Legacy participants don't send it.
+#[derive(Deserialize, Serialize)]
+struct MessageQuery {
+    /// The tag of the message.
+    tag: Option<u8>,
+    /// The shape of the model of an update message, like `[[3,2],[2]]` (see [`ModelShape`]).
+    shape: Option<String>,
+}
+
+/// The header of a global model response which carries the shape of the model, if known.
+const MODEL_SHAPE_HEADER: &str = "x-model-shape";
+
+/// The `Cache-Control` header of the public statistics. They change at most once per round, so
+/// they may be cached for several minutes.
+const PUBLIC_STATS_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// Starts a HTTP server at the given address, listening to GET requests for
+/// data and POST requests containing PET messages.
+///
+/// * `api_settings`: address of the server, tuning of its connections and optional certificate
+///   and key for TLS server authentication as well as trusted anchors for TLS client
+///   authentication.
+/// * `fetcher`: fetcher for responding to data requests.
+/// * `pet_message_handler`: handler for responding to PET messages.
+/// * `event_subscriber`: subscriber to the events which are pushed at `/events`, if enabled
+///   in the settings.
+/// * `admin`: state of the administrative routes under `/admin`, which are not found if it is
+///   `None` (see [`Admin`]).
+///
+/// # Errors
+/// Fails if the server cannot be bound or if the TLS settings are invalid.
+pub async fn serve<F>(
+    api_settings: ApiSettings,
+    fetcher: F,
+    pet_message_handler: PetMessageHandler,
+    event_subscriber: EventSubscriber,
+    admin: Option<Admin>,
+) -> Result<(), RestError>
+where
+    F: Fetcher + Sync + Send + 'static + Clone,
+{
+    let message = message_route(
+        pet_message_handler,
+        api_settings.debug_rejections,
+        api_settings.gzip,
+    );
+
+    let sum_dict = warp::path!("sums")
+        .and(warp::get())
+        .and(with_fetcher(fetcher.clone()))
+        .and_then(handle_sums);
+
+    let seed_dict = warp::path!("seeds")
+        .and(warp::get())
+        .and(warp::query::<SeedDictQuery>())
+        .and_then(part_pk)
+        .and(with_fetcher(fetcher.clone()))
+        .and_then(handle_seeds);
+
+    let round_params = warp::path!("params")
+        .and(warp::get())
+        .and(with_fetcher(fetcher.clone()))
+        .and_then(handle_params);
+
+    let round_metadata = round_metadata_route(fetcher.clone());
+
+    let phase_stats = phase_stats_route(fetcher.clone());
+
+    let public_stats = public_stats_route(fetcher.clone(), api_settings.public_stats_rate_limit);
+
+    let model = model_route(fetcher.clone());
+
+    let model_by_id = model_by_id_route(fetcher.clone());
+
+    let training_plan = warp::path!("plan")
+        .and(warp::get())
+        .and(with_fetcher(fetcher.clone()))
+        .and_then(handle_training_plan);
+
+    let routes = message
+        .or(round_params)
+        .or(round_metadata)
+        .or(phase_stats)
+        .or(public_stats)
+        .or(sum_dict)
+        .or(seed_dict)
+        .or(model)
+        .or(model_by_id)
+        .or(training_plan)
+        .or(admin_routes(admin));
+    let gzip_threshold = Some(api_settings.gzip_threshold).filter(|_| api_settings.gzip);
+    // the WebSocket upgrade is not compressed
+    let events = events_route(
+        Some(&event_subscriber)
+            .filter(|_| api_settings.events)
+            .map(RoundEventListeners::new),
+    );
+    let routes = gzip::compress(routes, gzip_threshold)
+        .or(events)
+        .recover(handle_reject)
+        .with(warp::log("http"));
+
+    run(routes, api_settings).await
+}
+
+/// Starts a HTTP server at the given address, serving the metrics of the `registry` to
+/// Prometheus at `/metrics`.
+///
+/// # Errors
+/// Fails if the server cannot be bound.
+#[cfg(feature = "prometheus")]
+pub async fn serve_metrics(
+    bind_address: std::net::SocketAddr,
+    registry: prometheus::Registry,
+) -> Result<(), RestError> {
+    let routes = metrics_route(registry).with(warp::log("http"));
+    let service = warp::service(routes);
+    let server = Server::try_bind(&bind_address)
+        .map_err(RestError::Bind)?
+        .serve(hyper::service::make_service_fn(move |_| {
+            let service = service.clone();
+            async move { Ok::<_, Infallible>(service) }
+        }));
+    if let Err(err) = server.await {
+        error!("metrics server error: {}", err);
+    }
+    Ok(())
+}
+
+/// The route that serves the metrics to Prometheus.
+#[cfg(feature = "prometheus")]
+fn metrics_route(
+    registry: prometheus::Registry,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("metrics").and(warp::get()).map(move || {
+        Response::builder()
+            .header("Content-Type", prometheus::TEXT_FORMAT)
+            .status(StatusCode::OK)
+            .body(crate::metrics::recorders::prometheus::encode(&registry))
+            .unwrap()
+    })
+}
+
+/// The route that handles PET messages.
+///
+/// The tag of a message and, for an update message, the shape of the model can be sent along
+/// with the message as `tag` and `shape` query parameters (see [`MessageQuery`]).
+///
+/// If `debug_rejections` is enabled, rejected messages are answered with `400 Bad
+/// Request`, or `500 Internal Server Error` if the coordinator failed to handle them, and
+/// the detailed reason of the rejection. Otherwise, all messages are answered with an
+/// empty `200 OK`, except the update messages of participants that exceeded their
+/// participation quota, which are always answered with `429 Too Many Requests`.
+///
+/// If `gzip` is enabled, the message may be compressed (see [`gzip`]).
+fn message_route(
+    handler: PetMessageHandler,
+    debug_rejections: bool,
+    gzip: bool,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("message")
+        .and(warp::post())
+        .and(warp::query::<MessageQuery>())
+        .and(gzip::body(gzip))
+        .and(with_message_handler(handler))
+        .and(warp::any().map(move || debug_rejections))
+        .and_then(handle_message)
+}
+
+/// The route that serves the latest global model.
+///
+/// The model is streamed in chunks, and a single range of it can be requested with a `Range`
+/// header, which is answered with `206 Partial Content`. The range is only served if an
+/// `If-Range` header matches the `ETag` of the model (see [`range`]). The shape of the model,
+/// if known, is sent in the `X-Model-Shape` header, like `[[3,2],[2]]` (see [`ModelShape`]).
+fn model_route<F>(
+    fetcher: F,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+where
+    F: Fetcher + Sync + Send + 'static + Clone,
+{
+    warp::path!("model")
+        .and(warp::get())
+        .and(warp::header::optional::<String>("range"))
+        .and(warp::header::optional::<String>("if-range"))
+        .and(with_fetcher(fetcher))
+        .and_then(handle_model)
+}
+
+/// The route that serves the persisted global models by id. Like the latest global model, they
+/// can be downloaded in ranges.
+fn model_by_id_route<F>(
+    fetcher: F,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+where
+    F: Fetcher + Sync + Send + 'static + Clone,
+{
+    warp::path!("models" / String)
+        .and(warp::get())
+        .and(warp::header::optional::<String>("range"))
+        .and(warp::header::optional::<String>("if-range"))
+        .and(with_fetcher(fetcher))
+        .and_then(handle_model_by_id)
+}
+
+/// The route that serves the metadata of the current round.
+fn round_metadata_route<F>(
+    fetcher: F,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+where
+    F: Fetcher + Sync + Send + 'static + Clone,
+{
+    warp::path!("round_metadata")
+        .and(warp::get())
+        .and(with_fetcher(fetcher))
+        .and_then(handle_round_metadata)
+}
+
+/// The route that serves the number of messages handled in the current phase.
+fn phase_stats_route<F>(
+    fetcher: F,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+where
+    F: Fetcher + Sync + Send + 'static + Clone,
+{
+    warp::path!("phase_stats")
+        .and(warp::get())
+        .and(with_fetcher(fetcher))
+        .and_then(handle_phase_stats)
+}
+
+/// The route that serves the statistics about the training which are safe to show to the end
+/// users of the apps, as JSON.
+///
+/// The route doesn't require any authentication, hence it serves at most `rate_limit` requests
+/// per second (see [`rate_limit`]) and the responses may be cached by the clients and proxies.
+fn public_stats_route<F>(
+    fetcher: F,
+    rate_limit: u32,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone
+where
+    F: Fetcher + Sync + Send + 'static + Clone,
+{
+    warp::path!("stats" / "public")
+        .and(warp::get())
+        .and(rate_limit::rate_limit(rate_limit))
+        .and(with_fetcher(fetcher))
+        .and_then(handle_public_stats)
+}
+
+/// The detailed reason of a rejected PET message.
+#[derive(Deserialize, Serialize)]
+struct RejectionFeedback {
+    /// A short code that identifies the kind of rejection.
+    code: String,
+    /// A description of the rejection, including the expected and actual values where
+    /// available.
+    detail: String,
+}
+
+/// Handles and responds to a PET message.
+async fn handle_message(
+    query: MessageQuery,
+    body: Bytes,
+    mut handler: PetMessageHandler,
+    debug_rejections: bool,
+) -> Result<impl warp::Reply, Infallible> {
+    let envelope = query
+        .tag
+        .map(Tag::try_from)
+        .transpose()
+        .map_err(|e| ("invalid_tag", format!("{:#}", e)))
+        .and_then(|tag| {
+            query
+                .shape
+                .as_deref()
+                .map(str::parse::<ModelShape>)
+                .transpose()
+                .map(|shape| (tag, shape))
+                .map_err(|e| ("invalid_shape", e.to_string()))
+        });
+    let rejection = match envelope {
+        Ok((tag, shape)) => handler
+            .handle_message(tag, shape, body.to_vec())
+            .await
+            .err()
+            .map(|e| {
+                warn!("failed to handle message: {:?}", e);
+                let feedback = RejectionFeedback {
+                    code: e.code().to_string(),
+                    detail: e.detail(),
+                };
+                (rejection_status(&e), feedback)
+            }),
+        Err((code, detail)) => {
+            warn!("failed to handle message: {}: {}", code, detail);
+            let feedback = RejectionFeedback {
+                code: code.to_string(),
+                detail,
+            };
+            Some((StatusCode::BAD_REQUEST, feedback))
+        }
+    };
+
+    Ok(match rejection {
+        // participants must back off when they exceed their quota, so they are always told
+        Some((status, feedback)) if status == StatusCode::TOO_MANY_REQUESTS || debug_rejections => {
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .status(status)
+                .body(serde_json::to_vec(&feedback).unwrap())
+                .unwrap()
+        }
+        _ => Response::builder()
+            .status(StatusCode::OK)
+            .body(Vec::new())
+            .unwrap(),
+    })
+}
+
+/// Gets the status of the response to a message that was rejected with the given error.
+fn rejection_status(error: &ServiceError) -> StatusCode {
+    match error {
+        ServiceError::StateMachine(RequestError::QuotaExceeded) => StatusCode::TOO_MANY_REQUESTS,
+        ServiceError::InternalError(_)
+        | ServiceError::StateMachine(RequestError::InternalError(_))
+        | ServiceError::StateMachine(RequestError::CoordinatorStorage(_)) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        _ => StatusCode::BAD_REQUEST,
+    }
+}
+
+/// Handles and responds to a request for the sum dictionary.
+async fn handle_sums<F: Fetcher>(mut fetcher: F) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.sum_dict().await {
+        Err(e) => {
+            warn!("failed to handle sum dict request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+        Ok(None) => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Vec::new())
+            .unwrap(),
+        Ok(Some(dict)) => {
+            let bytes = bincode::serialize(&Canonical(dict.as_ref())).unwrap();
+            Response::builder()
+                .header("Content-Type", "application/octet-stream")
+                .status(StatusCode::OK)
+                .body(bytes)
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for the seed dictionary.
+async fn handle_seeds<F: Fetcher>(
+    pk: ParticipantPublicKey,
+    mut fetcher: F,
+) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.seed_dict().await {
+        Err(e) => {
+            warn!("failed to handle seed dict request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+        Ok(Some(dict)) if dict.get(&pk).is_some() => {
+            let bytes = bincode::serialize(&Canonical(dict.as_ref().get(&pk).unwrap())).unwrap();
+            Response::builder()
+                .header("Content-Type", "application/octet-stream")
+                .status(StatusCode::OK)
+                .body(bytes)
+                .unwrap()
+        }
+        _ => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Vec::new())
+            .unwrap(),
+    })
+}
+
+/// Handles and responds to a request for the global model, or for the given `range` of it.
+async fn handle_model<F: Fetcher>(
+    range: Option<String>,
+    if_range: Option<String>,
+    mut fetcher: F,
+) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.model().await {
+        Ok(Some(model)) => {
+            let mut response =
+                range::model_response(bincode::serialize(model.as_ref()).unwrap(), range, if_range);
+            if let Some(shape) = model.shape() {
+                // UNWRAP_SAFE: the textual representation of a shape is a valid header value
+                let shape = HeaderValue::from_str(&shape.to_string()).unwrap();
+                response
+                    .headers_mut()
+                    .insert(HeaderName::from_static(MODEL_SHAPE_HEADER), shape);
+            }
+            response
+        }
+        Ok(None) => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle model request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for a persisted global model, or for the given `range`
+/// of it.
+///
+/// Unknown ids are answered with `404 Not Found`.
+async fn handle_model_by_id<F: Fetcher>(
+    id: String,
+    range: Option<String>,
+    if_range: Option<String>,
+    mut fetcher: F,
+) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.model_by_id(id).await {
+        Ok(Some(model)) => {
+            range::model_response(bincode::serialize(&model).unwrap(), range, if_range)
+        }
+        Ok(None) => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle model by id request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for the round parameters.
+async fn handle_params<F: Fetcher>(mut fetcher: F) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.round_params().await {
+        Ok(params) => Response::builder()
+            .status(StatusCode::OK)
+            .body(bincode::serialize(&params).unwrap())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle round parameters request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for the metadata of the current round.
+async fn handle_round_metadata<F: Fetcher>(mut fetcher: F) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.round_metadata().await {
+        Ok(metadata) => Response::builder()
+            .status(StatusCode::OK)
+            .body(bincode::serialize(&metadata).unwrap())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle round metadata request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for the number of messages handled in the current phase.
+async fn handle_phase_stats<F: Fetcher>(mut fetcher: F) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.phase_stats().await {
+        Ok(stats) => Response::builder()
+            .status(StatusCode::OK)
+            .body(bincode::serialize(&stats).unwrap())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle phase stats request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for the public statistics.
+async fn handle_public_stats<F: Fetcher>(mut fetcher: F) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.public_stats().await {
+        Ok(stats) => Response::builder()
+            .header("Content-Type", "application/json")
+            .header("Cache-Control", PUBLIC_STATS_CACHE_CONTROL)
+            .status(StatusCode::OK)
+            .body(serde_json::to_vec(&stats).unwrap())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle public stats request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+    })
+}
+
+/// Handles and responds to a request for the status of the current training plan.
+async fn handle_training_plan<F: Fetcher>(mut fetcher: F) -> Result<impl warp::Reply, Infallible> {
+    Ok(match fetcher.training_plan().await {
+        Ok(Some(status)) => Response::builder()
+            .status(StatusCode::OK)
+            .body(bincode::serialize(&status).unwrap())
+            .unwrap(),
+        Ok(None) => Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Vec::new())
+            .unwrap(),
+        Err(e) => {
+            warn!("failed to handle training plan request: {:?}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Vec::new())
+                .unwrap()
+        }
+    })
+}
+
+/// Converts a PET message handler into a `warp` filter.
+fn with_message_handler(
+    handler: PetMessageHandler,
+) -> impl Filter<Extract = (PetMessageHandler,), Error = Infallible> + Clone {
+    warp::any().map(move || handler.clone())
+}
+
+/// Converts a data fetcher into a `warp` filter.
+fn with_fetcher<F: Fetcher + Sync + Send + 'static + Clone>(
+    fetcher: F,
+) -> impl Filter<Extract = (F,), Error = Infallible> + Clone {
+    warp::any().map(move || fetcher.clone())
+}
+
+/// Extracts a participant public key from the url query string
+async fn part_pk(query: SeedDictQuery) -> Result<ParticipantPublicKey, warp::Rejection> {
+    match base64::decode(query.pk.as_bytes()) {
+        Ok(bytes) => {
+            if let Some(pk) = ParticipantPublicKey::from_slice(&bytes[..]) {
+                Ok(pk)
+            } else {
+                Err(warp::reject::custom(InvalidPublicKey))
+            }
+        }
+        Err(_) => Err(warp::reject::custom(InvalidPublicKey)),
+    }
+}
+
+#[derive(Debug)]
+struct InvalidPublicKey;
+
+impl warp::reject::Reject for InvalidPublicKey {}
+
+/// Handles `warp` rejections of bad requests.
+async fn handle_reject(err: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
+    let code = if err.is_not_found() {
+        StatusCode::NOT_FOUND
+    } else if let Some(InvalidPublicKey) = err.find() {
+        StatusCode::BAD_REQUEST
+    } else if let Some(gzip::InvalidGzip) = err.find() {
+        StatusCode::BAD_REQUEST
+    } else if let Some(gzip::UnsupportedEncoding) = err.find() {
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    } else if let Some(rate_limit::RateLimited) = err.find() {
+        StatusCode::TOO_MANY_REQUESTS
+    } else if let Some(admin::Unauthorized) = err.find() {
+        StatusCode::UNAUTHORIZED
+    } else {
+        error!("unhandled rejection: {:?}", err);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    // reply with empty body; the status code is the interesting part
+    Ok(warp::reply::with_status(Vec::new(), code))
+}
+
+#[derive(Debug, Error)]
+/// Errors of the rest server.
+pub enum RestError {
+    #[error("invalid TLS configuration was provided")]
+    InvalidTlsConfig,
+    #[error("failed to load the TLS configuration: {0}")]
+    Tls(String),
+    #[error("failed to bind the server: {0}")]
+    Bind(hyper::Error),
+}
+
+/// Runs a server with the provided filter routes.
+///
+/// # Errors
+/// Fails if the server cannot be bound or if the TLS settings are invalid.
+async fn run<F>(filter: F, api_settings: ApiSettings) -> Result<(), RestError>
+where
+    F: Filter + Clone + Send + Sync + 'static,
+    F::Extract: Reply,
+{
+    let stats = Arc::new(ConnectionStats::default());
+    let incoming = Incoming::bind(&api_settings, stats.clone())?;
+    let server = connection::configure(Server::builder(incoming), &api_settings)
+        .serve(MakeTrackedService::new(warp::service(filter), stats));
+    if let Err(err) = server.await {
+        error!("server error: {}", err);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::anyhow;
+    use xaynet_core::{
+        common::{PhaseCount, PhaseDuration, PhaseStats, PublicStats, RoundMetadata},
+        mask::{FromPrimitives, Model},
+    };
+
+    use crate::{
+        services::{
+            fetchers::fetcher,
+            tests::utils::{new_event_channels, new_sum_message, serialize_message},
+        },
+        state_machine::{
+            coordinator::RoundHistory,
+            events::{EventPublisher, EventSubscriber, ModelUpdate, PhaseCounters},
+            phases::PhaseName,
+            requests::RequestReceiver,
+        },
+        storage::tests::MockModelStore,
+    };
+
+    fn route(
+        debug_rejections: bool,
+    ) -> (
+        EventPublisher,
+        EventSubscriber,
+        RequestReceiver,
+        impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone,
+    ) {
+        let (mut publisher, subscriber) = new_event_channels();
+        publisher.broadcast_phase(PhaseName::Sum);
+        let (receiver, requests_tx) = RequestReceiver::new();
+        let handler = PetMessageHandler::new(&subscriber, requests_tx);
+        let route = message_route(handler, debug_rejections, true);
+        (publisher, subscriber, receiver, route)
+    }
+
+    async fn post<F>(route: &F, path: &str, body: Vec<u8>) -> Response<Bytes>
+    where
+        F: Filter + 'static,
+        F::Extract: Reply + Send,
+    {
+        warp::test::request()
+            .method("POST")
+            .path(path)
+            .body(body)
+            .reply(route)
+            .await
+    }
+
+    fn feedback(response: &Response<Bytes>) -> RejectionFeedback {
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[test]
+    fn test_rejection_status() {
+        assert_eq!(
+            rejection_status(&ServiceError::StateMachine(RequestError::QuotaExceeded)),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(
+            rejection_status(&ServiceError::InternalError("oops".to_string())),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            rejection_status(&ServiceError::StateMachine(RequestError::InternalError(
+                "oops"
+            ))),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            rejection_status(&ServiceError::StateMachine(RequestError::MessageRejected)),
+            StatusCode::BAD_REQUEST
+        );
+        assert_eq!(
+            rejection_status(&ServiceError::UnexpectedMessage),
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejections_without_debug() {
+        let (_publisher, _subscriber, _receiver, route) = route(false);
+
+        for &path in &["/message", "/message?tag=2", "/message?tag=42"] {
+            let response = post(&route, path, vec![0, 1, 2, 3]).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(response.body().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rejections_with_debug() {
+        let (_publisher, subscriber, _receiver, route) = route(true);
+        let round_params = subscriber.params_listener().get_latest().event;
+
+        let response = post(&route, "/message", vec![0, 1, 2, 3]).await;
+        assert_eq!(feedback(&response).code, "decrypt");
+
+        let response = post(&route, "/message?tag=2", vec![0, 1, 2, 3]).await;
+        assert_eq!(feedback(&response).code, "unexpected_message");
+
+        let response = post(&route, "/message?tag=42", vec![0, 1, 2, 3]).await;
+        assert_eq!(feedback(&response).code, "invalid_tag");
+
+        let response = post(&route, "/message?tag=2&shape=%5B2%5D", vec![0, 1, 2, 3]).await;
+        assert_eq!(feedback(&response).code, "invalid_shape");
+
+        // the message is too short to contain a header
+        let truncated = round_params.pk.encrypt(&[0; 5]);
+        let feedback_ = feedback(&post(&route, "/message?tag=1", truncated).await);
+        assert_eq!(feedback_.code, "parsing");
+        assert!(feedback_.detail.contains("invalid buffer length: 5 < "));
+
+        // the signature doesn't match the message
+        let (message, participant_signing_keys) = new_sum_message(&round_params);
+        let mut serialized = serialize_message(&message, &participant_signing_keys);
+        serialized[0] ^= 1;
+        let forged = round_params.pk.encrypt(&serialized);
+        let response = post(&route, "/message?tag=1", forged).await;
+        assert_eq!(feedback(&response).code, "invalid_message_signature");
+    }
+
+    #[tokio::test]
+    async fn test_model_by_id() {
+        let model = Model::from(vec![]);
+        let mut store = MockModelStore::new();
+        let model_ = model.clone();
+        store.expect_clone().returning(move || {
+            let mut store = MockModelStore::new();
+            let model = model_.clone();
+            store.expect_global_model().returning(move |id| match id {
+                "known" => Ok(Some(model.clone())),
+                "unknown" => Ok(None),
+                _ => Err(anyhow!("storage failure")),
+            });
+            store
+        });
+        let (_publisher, subscriber) = new_event_channels();
+        let route = model_by_id_route(fetcher(&subscriber, store));
+
+        let get = |path: &'static str| warp::test::request().path(path).reply(&route);
+        let response = get("/models/known").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let fetched: Model = bincode::deserialize(response.body()).unwrap();
+        assert_eq!(fetched, model);
+
+        assert_eq!(get("/models/unknown").await.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            get("/models/broken").await.status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_model_ranges() {
+        let model = Model::from_primitives(vec![0_f32; 100].into_iter()).unwrap();
+        let serialized = bincode::serialize(&model).unwrap();
+        let len = serialized.len();
+        let (mut publisher, subscriber) = new_event_channels();
+        publisher.broadcast_model(ModelUpdate::New(Arc::new(model)));
+        let route = model_route(fetcher(&subscriber, MockModelStore::new()));
+
+        let get = |range: Option<&str>| {
+            let request = warp::test::request().path("/model");
+            match range {
+                Some(range) => request.header("range", range),
+                None => request,
+            }
+            .reply(&route)
+        };
+
+        let response = get(None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["accept-ranges"], "bytes");
+        assert!(response.headers().get(MODEL_SHAPE_HEADER).is_none());
+        assert_eq!(response.body().as_ref(), serialized.as_slice());
+
+        let response = get(Some("bytes=10-19")).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(
+            response.headers()["content-range"],
+            format!("bytes 10-19/{}", len).as_str()
+        );
+        assert_eq!(response.body().as_ref(), &serialized[10..20]);
+
+        // resume an interrupted download
+        let response = get(Some("bytes=20-")).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.body().as_ref(), &serialized[20..]);
+
+        let response = get(Some(&format!("bytes={}-", len))).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(
+            response.headers()["content-range"],
+            format!("bytes */{}", len).as_str()
+        );
+
+        // unsupported ranges are ignored
+        let response = get(Some("bytes=0-9,20-29")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body().as_ref(), serialized.as_slice());
+
+        // resume a download only if the model didn't change
+        let etag = response.headers()["etag"].to_str().unwrap().to_string();
+        assert_eq!(etag, range::entity_tag(&serialized));
+        let get_if = |if_range: &str| {
+            warp::test::request()
+                .path("/model")
+                .header("range", "bytes=20-")
+                .header("if-range", if_range)
+                .reply(&route)
+        };
+        let response = get_if(&etag).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        assert_eq!(response.body().as_ref(), &serialized[20..]);
+
+        let other = Model::from_primitives(vec![1_f32; 100].into_iter()).unwrap();
+        publisher.broadcast_model(ModelUpdate::New(Arc::new(other.clone())));
+        let other = bincode::serialize(&other).unwrap();
+        let response = get_if(&etag).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["etag"],
+            range::entity_tag(&other).as_str()
+        );
+        assert_eq!(response.body().as_ref(), other.as_slice());
+    }
+
+    #[tokio::test]
+    async fn test_model_shape() {
+        let model = Model::from_primitives(vec![0_f32; 8].into_iter())
+            .unwrap()
+            .with_shape(vec![vec![3, 2], vec![2]].into())
+            .unwrap();
+        let (mut publisher, subscriber) = new_event_channels();
+        publisher.broadcast_model(ModelUpdate::New(Arc::new(model.clone())));
+        let route = model_route(fetcher(&subscriber, MockModelStore::new()));
+
+        let response = warp::test::request().path("/model").reply(&route).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[MODEL_SHAPE_HEADER], "[[3,2],[2]]");
+        // the shape is not serialized along with the model
+        let fetched: Model = bincode::deserialize(response.body()).unwrap();
+        assert!(fetched.shape().is_none());
+        assert!(fetched.iter().eq(model.iter()));
+    }
+
+    #[tokio::test]
+    async fn test_round_metadata() {
+        let (mut publisher, subscriber) = new_event_channels();
+        let metadata = RoundMetadata {
+            round_id: 0,
+            phase: PhaseName::Sum,
+            phase_start: 42,
+            phase_duration: Some(PhaseDuration { min: 10, max: 60 }),
+            compression: false,
+            changed_since_previous: false,
+        };
+        publisher.broadcast_round_metadata(metadata);
+        let route = round_metadata_route(fetcher(&subscriber, MockModelStore::new()));
+
+        let response = warp::test::request()
+            .path("/round_metadata")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let fetched: RoundMetadata = bincode::deserialize(response.body()).unwrap();
+        assert_eq!(fetched, metadata);
+    }
+
+    #[tokio::test]
+    async fn test_phase_stats() {
+        use xaynet_sdk::{
+            client::{reqwest_client_builder, Client, DEFAULT_POOL_IDLE_TIMEOUT},
+            XaynetClient,
+        };
+
+        let (mut publisher, subscriber) = new_event_channels();
+        let count = PhaseCount { min: 40, max: 100 };
+        let counters = Arc::new(PhaseCounters::new(PhaseName::Update, Some(count)));
+        publisher.broadcast_phase_counters(counters.clone());
+        for _ in 0..3 {
+            counters.increment_accepted();
+        }
+        counters.increment_rejected();
+        let route = phase_stats_route(fetcher(&subscriber, MockModelStore::new()));
+
+        let response = warp::test::request()
+            .path("/phase_stats")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let fetched: PhaseStats = bincode::deserialize(response.body()).unwrap();
+        let expected = PhaseStats {
+            round_id: 0,
+            phase: PhaseName::Update,
+            count: Some(count),
+            accepted: 3,
+            rejected: 1,
+            discarded: 0,
+        };
+        assert_eq!(fetched, expected);
+        assert_eq!(fetched.missing(), Some(37));
+
+        // the counters of the next phase start from zero
+        publisher.broadcast_phase_counters(Arc::new(PhaseCounters::new(PhaseName::Sum2, None)));
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        let http_client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+        let mut client = Client::new(http_client, &format!("http://{}", addr)).unwrap();
+        let fetched = client.get_phase_stats().await.unwrap();
+        assert_eq!(fetched.phase, PhaseName::Sum2);
+        assert_eq!((fetched.accepted, fetched.rejected), (0, 0));
+        assert_eq!(fetched.missing(), None);
+    }
+
+    #[tokio::test]
+    async fn test_public_stats() {
+        use xaynet_sdk::client::{reqwest_client_builder, Client, DEFAULT_POOL_IDLE_TIMEOUT};
+
+        let (mut publisher, subscriber) = new_event_channels();
+        let route = public_stats_route(fetcher(&subscriber, MockModelStore::new()), 2)
+            .recover(handle_reject);
+        let get = || warp::test::request().path("/stats/public").reply(&route);
+
+        let response = get().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["cache-control"],
+            PUBLIC_STATS_CACHE_CONTROL
+        );
+        let fetched: PublicStats = serde_json::from_slice(response.body()).unwrap();
+        let expected = PublicStats {
+            round_id: 0,
+            completed_rounds: 0,
+            participants: None,
+            last_model_update: None,
+        };
+        assert_eq!(fetched, expected);
+
+        // the exact number of participants is never published
+        publisher.set_round_id(8);
+        publisher.broadcast_phase(PhaseName::Sum);
+        publisher.broadcast_round_history(RoundHistory {
+            completed_rounds: 7,
+            participants: 1_234,
+            last_model_update: Some(1_600_000_000),
+            last_mask_disagreement: None,
+            last_shadow_evaluation: None,
+            last_update_statistics: None,
+        });
+        let response = get().await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let fetched: PublicStats = serde_json::from_slice(response.body()).unwrap();
+        let expected = PublicStats {
+            round_id: 8,
+            completed_rounds: 7,
+            participants: Some(1_200),
+            last_model_update: Some(1_600_000_000),
+        };
+        assert_eq!(fetched, expected);
+
+        // the rate limit is exceeded
+        assert_eq!(get().await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        let route = public_stats_route(fetcher(&subscriber, MockModelStore::new()), 2);
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        let http_client = reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+            .build()
+            .unwrap();
+        let mut client = Client::new(http_client, &format!("http://{}", addr)).unwrap();
+        assert_eq!(client.get_public_stats().await.unwrap(), expected);
+    }
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gzip_message() {
+        let (_publisher, _subscriber, _receiver, route) = route(true);
+        let route = route.recover(handle_reject);
+        let post = |encoding: &str, body: Vec<u8>| {
+            warp::test::request()
+                .method("POST")
+                .path("/message?tag=2")
+                .header("content-encoding", encoding)
+                .body(body)
+                .reply(&route)
+        };
+
+        // the message is decompressed before it is handled
+        let response = post("gzip", gzip(&[0, 1, 2, 3])).await;
+        assert_eq!(feedback(&response).code, "unexpected_message");
+        let response = post("identity", vec![0, 1, 2, 3]).await;
+        assert_eq!(feedback(&response).code, "unexpected_message");
+
+        let response = post("gzip", vec![0, 1, 2, 3]).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.body().is_empty());
+        let response = post("br", vec![0, 1, 2, 3]).await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_message_disabled() {
+        let (_publisher, subscriber) = new_event_channels();
+        let (_receiver, requests_tx) = RequestReceiver::new();
+        let handler = PetMessageHandler::new(&subscriber, requests_tx);
+        let route = message_route(handler, false, false).recover(handle_reject);
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/message")
+            .header("content-encoding", "gzip")
+            .body(gzip(&[0, 1, 2, 3]))
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_replies() {
+        use std::io::Read;
+
+        let model = Model::from_primitives(vec![0_f32; 100].into_iter()).unwrap();
+        let serialized = bincode::serialize(&model).unwrap();
+        let (mut publisher, subscriber) = new_event_channels();
+        publisher.broadcast_model(ModelUpdate::New(Arc::new(model)));
+        let model = || model_route(fetcher(&subscriber, MockModelStore::new()));
+
+        let get = |route, accept_encoding: Option<&str>, range: Option<&str>| {
+            let mut request = warp::test::request().path("/model");
+            if let Some(accept_encoding) = accept_encoding {
+                request = request.header("accept-encoding", accept_encoding);
+            }
+            if let Some(range) = range {
+                request = request.header("range", range);
+            }
+            async move { request.reply(&route).await }
+        };
+
+        let route = gzip::compress(model(), Some(10));
+        let response = get(route.clone(), Some("gzip"), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+        assert_eq!(
+            response.headers()["etag"],
+            format!("W/{}", range::entity_tag(&serialized)).as_str()
+        );
+        assert!(response.body().len() < serialized.len());
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(response.body().as_ref())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, serialized);
+
+        // clients that don't accept gzip get the uncompressed model
+        for &accept_encoding in &[None, Some("identity"), Some("gzip;q=0")] {
+            let response = get(route.clone(), accept_encoding, None).await;
+            assert!(response.headers().get("content-encoding").is_none());
+            assert_eq!(response.body().as_ref(), serialized.as_slice());
+        }
+
+        // ranges refer to the uncompressed model
+        let response = get(route, Some("gzip"), Some("bytes=10-19")).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.body().as_ref(), &serialized[10..20]);
+
+        // small replies and disabled gzip are not compressed
+        for &threshold in &[Some(serialized.len() + 1), None] {
+            let response = get(gzip::compress(model(), threshold), Some("gzip"), None).await;
+            assert!(response.headers().get("content-encoding").is_none());
+            assert_eq!(response.body().as_ref(), serialized.as_slice());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_admin_rejections() {
+        use crate::{settings::AdminSettings, state_machine::canary::CanarySwitch};
+
+        let (_publisher, _subscriber, _receiver, message) = route(false);
+        let settings = AdminSettings {
+            token: "0123456789abcdef".to_string(),
+            audit_log: None,
+        };
+        let admin = Admin::new(settings, CanarySwitch::new()).unwrap();
+        let routes = message.or(admin_routes(Some(admin))).recover(handle_reject);
+
+        let response = warp::test::request()
+            .method("PUT")
+            .path("/admin/canary")
+            .header("authorization", "Bearer fedcba9876543210")
+            .json(&serde_json::json!({ "upcoming": true }))
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = warp::test::request()
+            .path("/admin/unknown")
+            .header("authorization", "Bearer 0123456789abcdef")
+            .reply(&routes)
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_gzip_round_trip() {
+        use xaynet_sdk::{
+            client::{reqwest_client_builder, Client, ClientError, DEFAULT_POOL_IDLE_TIMEOUT},
+            XaynetClient,
+        };
+
+        let (_publisher, subscriber, _receiver, message) = route(true);
+        let metadata = subscriber.round_metadata_listener().get_latest().event;
+        let routes = message.or(round_metadata_route(fetcher(
+            &subscriber,
+            MockModelStore::new(),
+        )));
+        let routes = gzip::compress(routes, Some(0)).recover(handle_reject);
+        let (addr, server) = warp::serve(routes).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        let url = format!("http://{}", addr);
+
+        let http_client = || {
+            reqwest_client_builder(DEFAULT_POOL_IDLE_TIMEOUT)
+                .build()
+                .unwrap()
+        };
+        // the coordinator serves participants with and without gzip alike
+        let clients = vec![
+            Client::new(http_client(), &url).unwrap().with_gzip(0),
+            Client::new(http_client(), &url).unwrap(),
+        ];
+        for mut client in clients {
+            assert_eq!(client.get_round_metadata().await.unwrap(), metadata);
+            // the message reaches the handler, which rejects it in the sum phase
+            assert!(matches!(
+                client.send_message(Tag::Update, vec![0; 100]).await,
+                Err(ClientError::Rejected(400, reason)) if reason.contains("unexpected_message")
+            ));
+        }
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[tokio::test]
+    async fn test_metrics() {
+        use crate::metrics::{Measurement, PrometheusRecorder, Tags};
+
+        let recorder = PrometheusRecorder::new();
+        recorder.metric::<_, _, Tags>(Measurement::RoundTotalNumber, 7, None);
+        let route = metrics_route(recorder.registry());
+
+        let response = warp::test::request().path("/metrics").reply(&route).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = String::from_utf8(response.body().to_vec()).unwrap();
+        assert!(body.contains("xaynet_round_total_number 7"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Range requests for the downloads of the global models.
+//!
+//! A serialized global model can be large, hence it is streamed in chunks and clients may
+//! download it in pieces or resume an interrupted download with a `Range` header (see [RFC
+//! 7233]). Only a single range of bytes is supported. Any other range, like several ranges or
+//! another unit, is ignored and the whole model is served, as permitted by the RFC.
+//!
+//! The global model changes from round to round, hence each model is served with a strong
+//! `ETag`, which is the hash of its serialized bytes. A client that resumes a download sends
+//! it back in an `If-Range` header, and the range is only served if the model is still the
+//! same. Otherwise, the whole new model is served, such that the bytes of two different models
+//! are never spliced together.
+//!
+//! [RFC 7233]: https://tools.ietf.org/html/rfc7233
+
+use std::{convert::Infallible, ops::Range};
+
+use bytes::Bytes;
+use hyper::Body;
+use sodiumoxide::crypto::hash::sha256;
+use warp::http::{header, Response, StatusCode};
+
+/// The size of the chunks in which a whole model is streamed.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The requested range can't be served, because it starts after the end of the model.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Unsatisfiable;
+
+/// Parses the value of a `Range` header for a body of `len` bytes.
+///
+/// Returns the requested bytes, or `None` if the range is not supported or malformed and must
+/// be ignored.
+pub(super) fn parse(range: &str, len: usize) -> Result<Option<Range<usize>>, Unsatisfiable> {
+    let spec = match range.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return Ok(None),
+    };
+    let (first, last) = match spec.find('-') {
+        Some(dash) => (&spec[..dash], &spec[dash + 1..]),
+        None => return Ok(None),
+    };
+    let position = |position: &str| position.parse::<usize>().ok();
+    match (first, last) {
+        // the last `suffix` bytes
+        ("", suffix) => match position(suffix) {
+            Some(0) => Err(Unsatisfiable),
+            Some(suffix) => Ok(Some(len.saturating_sub(suffix)..len)),
+            None => Ok(None),
+        },
+        // all bytes from `first` on
+        (first, "") => match position(first) {
+            Some(first) if first >= len => Err(Unsatisfiable),
+            Some(first) => Ok(Some(first..len)),
+            None => Ok(None),
+        },
+        (first, last) => match (position(first), position(last)) {
+            (Some(first), Some(last)) if first <= last => {
+                if first >= len {
+                    Err(Unsatisfiable)
+                } else {
+                    Ok(Some(first..last.saturating_add(1).min(len)))
+                }
+            }
+            _ => Ok(None),
+        },
+    }
+}
+
+/// Computes the strong entity tag of a serialized `model`, which is the hash of its bytes.
+pub(super) fn entity_tag(model: &[u8]) -> String {
+    format!("\"{}\"", hex::encode(sha256::hash(model)))
+}
+
+/// Checks whether the value of an `If-Range` header matches the entity tag `etag`.
+///
+/// Only the same strong entity tag matches. Weak entity tags and dates never match, since the
+/// models are served without a `Last-Modified` header.
+pub(super) fn if_range_matches(if_range: &str, etag: &str) -> bool {
+    if_range.trim() == etag
+}
+
+/// Responds with the serialized `model`, or with the requested `range` of it.
+///
+/// The `range` is ignored and the whole model is served if the `if_range` condition doesn't
+/// match the model.
+pub(super) fn model_response(
+    model: Vec<u8>,
+    range: Option<String>,
+    if_range: Option<String>,
+) -> Response<Body> {
+    let etag = entity_tag(&model);
+    let model = Bytes::from(model);
+    let len = model.len();
+    let range = match if_range {
+        Some(if_range) if !if_range_matches(&if_range, &etag) => None,
+        _ => range,
+    };
+    let builder = Response::builder()
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, etag);
+    match range.map(|range| parse(&range, len)).transpose() {
+        Ok(Some(Some(range))) => builder
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(
+                header::CONTENT_RANGE,
+                format!("bytes {}-{}/{}", range.start, range.end - 1, len),
+            )
+            .body(Body::from(model.slice(range)))
+            .unwrap(),
+        Ok(_) => builder.status(StatusCode::OK).body(chunked(model)).unwrap(),
+        Err(Unsatisfiable) => builder
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+            .body(Body::empty())
+            .unwrap(),
+    }
+}
+
+/// Streams the `bytes` in chunks, without copying them.
+fn chunked(bytes: Bytes) -> Body {
+    let len = bytes.len();
+    let chunks = (0..len)
+        .step_by(CHUNK_SIZE)
+        .map(move |start| Ok::<_, Infallible>(bytes.slice(start..(start + CHUNK_SIZE).min(len))));
+    Body::wrap_stream(futures::stream::iter(chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("bytes=0-9", 100), Ok(Some(0..10)));
+        assert_eq!(parse("bytes=10-10", 100), Ok(Some(10..11)));
+        assert_eq!(parse("bytes=90-200", 100), Ok(Some(90..100)));
+        assert_eq!(parse("bytes=42-", 100), Ok(Some(42..100)));
+        assert_eq!(parse("bytes=-10", 100), Ok(Some(90..100)));
+        assert_eq!(parse("bytes=-200", 100), Ok(Some(0..100)));
+        assert_eq!(parse(" bytes= 0-9 ", 100), Ok(Some(0..10)));
+    }
+
+    #[tokio::test]
+    async fn test_chunked() {
+        let bytes = (0..3 * CHUNK_SIZE + 1).map(|i| i as u8).collect::<Vec<_>>();
+        let body = chunked(Bytes::from(bytes.clone()));
+        assert_eq!(hyper::body::to_bytes(body).await.unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_parse_unsatisfiable() {
+        assert_eq!(parse("bytes=100-", 100), Err(Unsatisfiable));
+        assert_eq!(parse("bytes=100-200", 100), Err(Unsatisfiable));
+        assert_eq!(parse("bytes=-0", 100), Err(Unsatisfiable));
+    }
+
+    #[test]
+    fn test_if_range_matches() {
+        let etag = entity_tag(b"model");
+        assert_eq!(etag.len(), 2 * sha256::DIGESTBYTES + 2);
+        assert_ne!(etag, entity_tag(b"other model"));
+        assert!(if_range_matches(&etag, &etag));
+        assert!(if_range_matches(&format!(" {} ", etag), &etag));
+        assert!(!if_range_matches(&format!("W/{}", etag), &etag));
+        assert!(!if_range_matches(&entity_tag(b"other model"), &etag));
+        assert!(!if_range_matches("Wed, 21 Oct 2015 07:28:00 GMT", &etag));
+    }
+
+    #[tokio::test]
+    async fn test_model_response_if_range() {
+        let model = (0..100).collect::<Vec<u8>>();
+        let etag = entity_tag(&model);
+        let range = || Some("bytes=10-19".to_string());
+
+        let response = model_response(model.clone(), range(), Some(etag.clone()));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, model[10..20]);
+
+        // the model changed since the download started
+        let response = model_response(model.clone(), range(), Some(entity_tag(b"old model")));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, model);
+    }
+
+    #[test]
+    fn test_parse_ignored() {
+        for range in &[
+            "",
+            "bytes",
+            "bytes=",
+            "bytes=-",
+            "bytes=9-0",
+            "bytes=a-9",
+            "bytes=0-9,20-29",
+            "items=0-9",
+        ] {
+            assert_eq!(parse(range, 100), Ok(None), "{}", range);
+        }
+    }
+}
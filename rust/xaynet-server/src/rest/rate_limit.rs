@@ -0,0 +1,86 @@
+//! A rate limit for the routes of the REST API which are open to the public.
+//!
+//! The limit is global rather than per client: the public routes serve small documents that
+//! don't change within a round, hence clients are expected to cache them and a global limit
+//! is enough to protect the coordinator against floods of requests.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use warp::Filter;
+
+/// The length of the window in which the requests are counted.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// The request exceeded the rate limit.
+#[derive(Debug)]
+pub(super) struct RateLimited;
+
+impl warp::reject::Reject for RateLimited {}
+
+/// Counts the requests of the current window.
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    requests: u32,
+}
+
+/// Rejects the requests with [`RateLimited`] once `max_requests` requests were made within the
+/// current second.
+pub(super) fn rate_limit(
+    max_requests: u32,
+) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    let window = Arc::new(Mutex::new(Window {
+        start: Instant::now(),
+        requests: 0,
+    }));
+    warp::any()
+        .and_then(move || {
+            let allowed = acquire(&window, max_requests, Instant::now());
+            async move {
+                if allowed {
+                    Ok(())
+                } else {
+                    Err(warp::reject::custom(RateLimited))
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Counts a request made at time `now` and checks whether it is within the limit.
+fn acquire(window: &Mutex<Window>, max_requests: u32, now: Instant) -> bool {
+    // UNWRAP_SAFE: the lock is never held across a panic
+    let mut window = window.lock().unwrap();
+    if now.saturating_duration_since(window.start) >= WINDOW {
+        window.start = now;
+        window.requests = 0;
+    }
+    if window.requests < max_requests {
+        window.requests += 1;
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire() {
+        let start = Instant::now();
+        let window = Mutex::new(Window { start, requests: 0 });
+
+        assert!(acquire(&window, 2, start));
+        assert!(acquire(&window, 2, start + Duration::from_millis(500)));
+        assert!(!acquire(&window, 2, start + Duration::from_millis(999)));
+        // a new window starts
+        assert!(acquire(&window, 2, start + WINDOW));
+        assert!(acquire(&window, 2, start + WINDOW));
+        assert!(!acquire(&window, 2, start + WINDOW));
+    }
+}
@@ -0,0 +1,199 @@
+//! TLS server and client authentication for the REST API.
+
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufReader, Read},
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_rustls::{
+    rustls::{
+        internal::pemfile,
+        AllowAnyAuthenticatedClient,
+        Certificate,
+        NoClientAuth,
+        PrivateKey,
+        RootCertStore,
+        ServerConfig,
+    },
+    server,
+    Accept,
+};
+
+use super::{connection::ConnectionStats, RestError};
+use crate::settings::ApiSettings;
+
+/// Secures the incoming connections of the REST API.
+pub(crate) struct TlsAcceptor {
+    acceptor: tokio_rustls::TlsAcceptor,
+    stats: Arc<ConnectionStats>,
+}
+
+impl TlsAcceptor {
+    /// Creates an acceptor for TLS server and client authentication.
+    ///
+    /// # Errors
+    /// Fails if the TLS settings are invalid.
+    pub fn new(api_settings: &ApiSettings, stats: Arc<ConnectionStats>) -> Result<Self, RestError> {
+        let config = configure_tls(api_settings)?;
+        Ok(Self {
+            acceptor: Arc::new(config).into(),
+            stats,
+        })
+    }
+
+    /// Starts the TLS handshake of an accepted connection.
+    pub fn accept<S>(&self, stream: S) -> TlsStream<S>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        TlsStream {
+            state: State::Handshaking(self.acceptor.accept(stream)),
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Configures TLS server and client authentication. HTTP/2 is only offered to the clients
+/// if it is enabled in the settings.
+///
+/// # Errors
+/// Fails if the TLS settings are invalid.
+fn configure_tls(api_settings: &ApiSettings) -> Result<ServerConfig, RestError> {
+    let client_auth = match api_settings.tls_client_auth {
+        Some(ref trust_anchor) => {
+            AllowAnyAuthenticatedClient::new(read_trust_anchor(trust_anchor)?)
+        }
+        None => NoClientAuth::new(),
+    };
+    let mut config = ServerConfig::new(client_auth);
+    match (&api_settings.tls_certificate, &api_settings.tls_key) {
+        (Some(cert), Some(key)) => config
+            .set_single_cert(read_certificates(cert)?, read_private_key(key)?)
+            .map_err(|err| RestError::Tls(err.to_string()))?,
+        (None, None) if api_settings.tls_client_auth.is_some() => {}
+        _ => return Err(RestError::InvalidTlsConfig),
+    }
+    if api_settings.http2 {
+        config.set_protocols(&[b"h2".to_vec(), b"http/1.1".to_vec()]);
+    } else {
+        config.set_protocols(&[b"http/1.1".to_vec()]);
+    }
+    Ok(config)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, RestError> {
+    let mut buf = Vec::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_end(&mut buf))
+        .map_err(|err| RestError::Tls(format!("failed to read {}: {}", path.display(), err)))?;
+    Ok(buf)
+}
+
+fn read_certificates(path: &Path) -> Result<Vec<Certificate>, RestError> {
+    let buf = read_file(path)?;
+    pemfile::certs(&mut BufReader::new(buf.as_slice()))
+        .map_err(|_| RestError::Tls(format!("invalid certificate {}", path.display())))
+}
+
+/// Reads the first PKCS8 or RSA private key of a file.
+fn read_private_key(path: &Path) -> Result<PrivateKey, RestError> {
+    let buf = read_file(path)?;
+    let invalid = || RestError::Tls(format!("invalid private key {}", path.display()));
+    let mut keys = pemfile::pkcs8_private_keys(&mut buf.as_slice()).map_err(|_| invalid())?;
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut buf.as_slice()).map_err(|_| invalid())?;
+    }
+    keys.into_iter().next().ok_or_else(invalid)
+}
+
+fn read_trust_anchor(path: &Path) -> Result<RootCertStore, RestError> {
+    let buf = read_file(path)?;
+    let mut store = RootCertStore::empty();
+    match store.add_pem_file(&mut BufReader::new(buf.as_slice())) {
+        Ok((0, _)) | Err(()) => Err(RestError::Tls(format!(
+            "invalid trust anchor {}",
+            path.display()
+        ))),
+        Ok(_) => Ok(store),
+    }
+}
+
+enum State<S> {
+    Handshaking(Accept<S>),
+    Streaming(server::TlsStream<S>),
+}
+
+/// A connection secured with TLS. The handshake is made on the first read or write.
+pub(crate) struct TlsStream<S> {
+    state: State<S>,
+    stats: Arc<ConnectionStats>,
+}
+
+impl<S> TlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Completes the handshake, if it is still ongoing.
+    fn poll_handshake(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<io::Result<&mut server::TlsStream<S>>> {
+        if let State::Handshaking(ref mut accept) = self.state {
+            let stream = futures::ready!(Pin::new(accept).poll(cx))?;
+            self.stats.on_handshake();
+            self.state = State::Streaming(stream);
+        }
+        match self.state {
+            State::Streaming(ref mut stream) => Poll::Ready(Ok(stream)),
+            State::Handshaking(_) => unreachable!("the handshake is completed"),
+        }
+    }
+}
+
+impl<S> AsyncRead for TlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let stream = futures::ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_read(cx, buf)
+    }
+}
+
+impl<S> AsyncWrite for TlsStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let stream = futures::ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut().state {
+            State::Handshaking(_) => Poll::Ready(Ok(())),
+            State::Streaming(ref mut stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut().state {
+            State::Handshaking(_) => Poll::Ready(Ok(())),
+            State::Streaming(ref mut stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Retention of the records which the coordinator accumulates.
+//!
+//! The records of a data class are purged once they are older than the retention duration of
+//! the data class in the `[retention]` settings (see [`RetentionSettings`]). Before the
+//! expiring records are purged, they are exported to the model storage (see
+//! [`ModelStorage::set_retention_archive()`]), and they are kept if the export fails.
+//!
+//! A purge cycle purges at most [`RetentionSettings::max_records`] records of each data class,
+//! such that its work is bounded. The remaining expired records are purged by the following
+//! cycles. The cycles run periodically in their own task (see [`Retention::run()`]), and an
+//! immediate cycle can be triggered with a [`RetentionTrigger`].
+//!
+//! The audit log (see [`audit`]) is the only data class which accumulates records. Its
+//! expiring records are exported in the format of the audit log, such that the export can be
+//! verified with [`audit::verify()`], and the first retained record chains to the last
+//! exported one.
+//!
+//! [`audit`]: crate::audit
+//! [`audit::verify()`]: crate::audit::verify
+//! [`RetentionSettings`]: crate::settings::RetentionSettings
+//! [`RetentionSettings::max_records`]: crate::settings::RetentionSettings::max_records
+//! [`ModelStorage::set_retention_archive()`]: crate::storage::ModelStorage::set_retention_archive
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use displaydoc::Display;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time,
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    audit::{AuditError, AuditLog},
+    metric,
+    metrics::Measurement,
+    settings::RetentionSettings,
+    storage::ModelStorage,
+};
+
+/// The data class of the records of the audit log.
+pub const AUDIT_LOG: &str = "audit_log";
+
+/// Errors related to a purge cycle.
+#[derive(Debug, Display, Error)]
+pub enum RetentionError {
+    /// Failed to read or purge the audit log: {0}.
+    Audit(#[from] AuditError),
+    /// Failed to serialize the expiring records: {0}.
+    Serialization(#[from] serde_json::Error),
+    /// Failed to export the expiring records: {0}.
+    Export(anyhow::Error),
+    /// The retention task is not running.
+    Stopped,
+}
+
+/// The number of records purged by a purge cycle, per data class.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PurgeReport {
+    /// The number of purged records of the audit log.
+    pub audit_log: usize,
+}
+
+/// A request for an immediate purge cycle, which is answered with the outcome of the cycle.
+type PurgeRequest = oneshot::Sender<Result<PurgeReport, RetentionError>>;
+
+/// A handle to trigger an immediate purge cycle of a running [`Retention`] task.
+#[derive(Debug, Clone)]
+pub struct RetentionTrigger(mpsc::Sender<PurgeRequest>);
+
+impl RetentionTrigger {
+    /// Runs a purge cycle right away and waits until it completes.
+    ///
+    /// # Errors
+    /// Fails if the purge cycle fails or if the retention task is not running.
+    pub async fn purge(&self) -> Result<PurgeReport, RetentionError> {
+        let (tx, rx) = oneshot::channel();
+        self.0.send(tx).await.map_err(|_| RetentionError::Stopped)?;
+        rx.await.map_err(|_| RetentionError::Stopped)?
+    }
+}
+
+/// The purge cycles of the records of the coordinator.
+pub struct Retention<M> {
+    settings: RetentionSettings,
+    audit_log: Option<Arc<AuditLog>>,
+    store: M,
+    requests: mpsc::Receiver<PurgeRequest>,
+}
+
+impl<M> Retention<M>
+where
+    M: ModelStorage,
+{
+    /// Creates the purge cycles of the given `audit_log`, whose expiring records are exported to
+    /// the given `store`. The returned trigger runs an immediate purge cycle while the cycles
+    /// run (see [`Retention::run()`]).
+    pub fn new(
+        settings: RetentionSettings,
+        audit_log: Option<Arc<AuditLog>>,
+        store: M,
+    ) -> (Self, RetentionTrigger) {
+        let (tx, requests) = mpsc::channel(1);
+        let retention = Self {
+            settings,
+            audit_log,
+            store,
+            requests,
+        };
+        (retention, RetentionTrigger(tx))
+    }
+
+    /// Runs a purge cycle at the interval of the settings, or when it is triggered. A failed
+    /// purge cycle is retried by the next one.
+    pub async fn run(mut self) {
+        let mut interval = time::interval(Duration::from_secs(self.settings.interval));
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+        let mut triggered = true;
+        loop {
+            tokio::select! {
+                // the triggers never starve the periodic cycles
+                biased;
+
+                _ = interval.tick() => {
+                    if let Err(err) = self.purge().await {
+                        warn!("failed to purge the expired records: {}", err);
+                    }
+                }
+                request = self.requests.recv(), if triggered => match request {
+                    Some(tx) => {
+                        let _ = tx.send(self.purge().await);
+                    }
+                    None => {
+                        debug!("the retention triggers are dropped");
+                        triggered = false;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Runs a purge cycle: the expiring records of each data class are exported and then
+    /// removed, at most [`RetentionSettings::max_records`] of them.
+    ///
+    /// # Errors
+    /// Fails if the expiring records can't be read, exported or removed. The records which
+    /// are not exported are kept.
+    ///
+    /// [`RetentionSettings::max_records`]: crate::settings::RetentionSettings::max_records
+    pub async fn purge(&mut self) -> Result<PurgeReport, RetentionError> {
+        let mut report = PurgeReport::default();
+        if let (Some(audit_log), Some(retention)) =
+            (self.audit_log.clone(), self.settings.audit_log)
+        {
+            report.audit_log = self.purge_audit_log(&audit_log, retention).await?;
+        }
+        Ok(report)
+    }
+
+    /// Exports and removes the records of the audit log which are older than `retention`
+    /// seconds. Returns the number of removed records.
+    async fn purge_audit_log(
+        &mut self,
+        audit_log: &AuditLog,
+        retention: u64,
+    ) -> Result<usize, RetentionError> {
+        let cutoff = chrono::Duration::from_std(Duration::from_secs(retention))
+            .ok()
+            .and_then(|retention| Utc::now().checked_sub_signed(retention));
+        let cutoff = match cutoff {
+            Some(cutoff) => cutoff,
+            // nothing is that old
+            None => return Ok(0),
+        };
+        let expiring = audit_log.expiring(cutoff, self.settings.max_records)?;
+        let (first, last) = match (expiring.first(), expiring.last()) {
+            (Some(first), Some(last)) => (first.seq, last.seq),
+            _ => return Ok(0),
+        };
+
+        let name = format!("{}-{}.jsonl", first, last);
+        let mut archive = Vec::new();
+        for record in &expiring {
+            serde_json::to_writer(&mut archive, record)?;
+            archive.push(b'\n');
+        }
+        self.store
+            .set_retention_archive(AUDIT_LOG, &name, archive)
+            .await
+            .map_err(RetentionError::Export)?;
+
+        let removed = audit_log.remove_through(last)?;
+        info!(
+            "purged {} records of the audit log, exported as {}",
+            removed, name
+        );
+        metric!(
+            Measurement::RetentionPurged,
+            removed as u64,
+            ("data_class", AUDIT_LOG),
+        );
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::{
+        audit::{
+            tests::{record_actions_at, TempLog},
+            verify,
+            AuditRecord,
+        },
+        storage::tests::MockModelStore,
+    };
+
+    /// The archives set in the model store, by name.
+    type Archives = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+    fn settings(max_records: usize) -> RetentionSettings {
+        RetentionSettings {
+            audit_log: Some(86400),
+            interval: 3600,
+            max_records,
+        }
+    }
+
+    fn store(archives: &Archives) -> MockModelStore {
+        let archives = archives.clone();
+        let mut store = MockModelStore::new();
+        store
+            .expect_set_retention_archive()
+            .returning(move |data_class, name, archive| {
+                assert_eq!(data_class, AUDIT_LOG);
+                archives.lock().unwrap().push((name.to_string(), archive));
+                Ok(())
+            });
+        store
+    }
+
+    /// An audit log with 5 records older than the retention and 3 newer ones.
+    fn audit_log(file: &TempLog) -> Arc<AuditLog> {
+        let log = AuditLog::open(&file.0).unwrap();
+        record_actions_at(&log, Utc::now() - chrono::Duration::days(2), 5);
+        record_actions_at(&log, Utc::now(), 3);
+        Arc::new(log)
+    }
+
+    fn exported_records(archive: &[u8]) -> Vec<AuditRecord> {
+        archive
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_purge() {
+        let file = TempLog::new();
+        let log = audit_log(&file);
+        let records = file.records();
+        let archives = Archives::default();
+        let (mut retention, _) = Retention::new(settings(1000), Some(log), store(&archives));
+
+        let report = retention.purge().await.unwrap();
+        assert_eq!(report, PurgeReport { audit_log: 5 });
+
+        // the old records are exported and removed, the new ones remain
+        let exported = archives.lock().unwrap().clone();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].0, "0-4.jsonl");
+        assert_eq!(exported_records(&exported[0].1), records[..5]);
+        assert_eq!(file.records(), records[5..]);
+
+        // the export and the retained records form the whole chain
+        let mut chain = exported[0].1.clone();
+        chain.extend(std::fs::read(&file.0).unwrap());
+        assert_eq!(verify(chain.as_slice()).unwrap(), file.verify().unwrap());
+
+        // a second cycle is a no-op
+        let report = retention.purge().await.unwrap();
+        assert_eq!(report, PurgeReport::default());
+        assert_eq!(archives.lock().unwrap().len(), 1);
+        assert_eq!(file.records(), records[5..]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_is_bounded() {
+        let file = TempLog::new();
+        let log = audit_log(&file);
+        let records = file.records();
+        let archives = Archives::default();
+        let (mut retention, _) = Retention::new(settings(2), Some(log), store(&archives));
+
+        for (purged, retained) in &[(2, 2), (2, 4), (1, 5), (0, 5)] {
+            let report = retention.purge().await.unwrap();
+            assert_eq!(report.audit_log, *purged);
+            assert_eq!(file.records(), records[*retained..]);
+        }
+        let names = archives
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["0-1.jsonl", "2-3.jsonl", "4-4.jsonl"]);
+    }
+
+    #[tokio::test]
+    async fn test_purge_export_failure() {
+        let file = TempLog::new();
+        let log = audit_log(&file);
+        let records = file.records();
+        let mut store = MockModelStore::new();
+        store
+            .expect_set_retention_archive()
+            .returning(|_, _, _| Err(anyhow!("storage failure")));
+        let (mut retention, _) = Retention::new(settings(1000), Some(log), store);
+
+        // the records are kept if they can't be exported
+        assert!(matches!(
+            retention.purge().await,
+            Err(RetentionError::Export(_))
+        ));
+        assert_eq!(file.records(), records);
+    }
+
+    #[tokio::test]
+    async fn test_trigger() {
+        let file = TempLog::new();
+        let log = audit_log(&file);
+        let archives = Archives::default();
+        let settings = RetentionSettings {
+            // the periodic cycles never come after the first one
+            interval: 86400,
+            ..settings(1000)
+        };
+        let (retention, trigger) = Retention::new(settings, Some(log), store(&archives));
+        let task = tokio::spawn(retention.run());
+
+        // the old records are purged by the first periodic cycle or by the triggered one,
+        // whichever comes first
+        let report = trigger.purge().await.unwrap();
+        assert!(report.audit_log == 0 || report.audit_log == 5);
+        assert_eq!(trigger.purge().await.unwrap(), PurgeReport::default());
+        assert_eq!(archives.lock().unwrap().len(), 1);
+        assert_eq!(file.records().len(), 3);
+
+        task.abort();
+        assert!(matches!(task.await, Err(err) if err.is_cancelled()));
+        assert!(matches!(
+            trigger.purge().await,
+            Err(RetentionError::Stopped)
+        ));
+    }
+}
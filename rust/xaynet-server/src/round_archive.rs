@@ -0,0 +1,300 @@
+//! Archives of the aggregation inputs of a round, for offline reproducibility audits.
+//!
+//! At the end of a round, the coordinator can export a [`RoundArchive`] to the model storage
+//! alongside the global model (see [`ModelSettings::export_round_archive`]). The archive
+//! contains the aggregated masked model, the aggregated mask chosen in the sum2 phase and the
+//! metadata of the round. The masking configuration and the aggregated scalar are part of the
+//! aggregated masked model. The archive never contains the masked models or the seeds of
+//! individual participants.
+//!
+//! [`verify()`] unmasks the aggregated masked model offline and checks that the result is the
+//! published global model, i.e. that its id (see [`ModelStorage::create_global_model_id()`])
+//! is the one recorded in the manifest of the archive. The coordinator binary exposes it as the
+//! `verify-round <archive>` subcommand.
+//!
+//! An archive is a single file. Its first line is the [`RoundManifest`] encoded in JSON, which
+//! can be inspected with standard tools, and the rest of the file is the bincode encoded
+//! aggregated masked model and aggregated mask.
+//!
+//! [`ModelSettings::export_round_archive`]: crate::settings::ModelSettings::export_round_archive
+//! [`ModelStorage::create_global_model_id()`]: crate::storage::ModelStorage::create_global_model_id
+
+use displaydoc::Display;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use xaynet_core::{
+    common::RoundSeed,
+    crypto::ByteObject,
+    mask::{Aggregation, MaskConfigPair, MaskObject, Model, UnmaskingError},
+};
+
+use crate::storage::{model_storage::noop::NoOp, ModelStorage};
+
+/// The version of the archive format written by this coordinator.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Errors related to the encoding and the verification of round archives.
+#[derive(Debug, Display, Error)]
+pub enum RoundArchiveError {
+    /// The archive has no manifest.
+    MissingManifest,
+    /// Failed to encode or decode the manifest: {0}.
+    Manifest(#[from] serde_json::Error),
+    /// Unsupported archive format version {0}.
+    UnsupportedVersion(u32),
+    /// Failed to encode or decode the aggregation inputs: {0}.
+    Payload(#[from] bincode::Error),
+    /// The manifest doesn't match the aggregation inputs.
+    Inconsistent,
+    /// Unmasking the global model failed: {0}.
+    Unmasking(#[from] UnmaskingError),
+    /// The unmasked global model {found} is not the published global model {expected}.
+    ModelMismatch { expected: String, found: String },
+}
+
+/// The metadata of a round archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundManifest {
+    /// The version of the archive format.
+    pub version: u32,
+    /// The id of the round.
+    pub round_id: u64,
+    /// The hex encoded seed of the round.
+    pub round_seed: String,
+    /// The id of the published global model.
+    pub global_model_id: String,
+    /// The length of the global model.
+    pub model_length: usize,
+    /// The number of aggregated masked models.
+    pub nb_models: usize,
+    /// The masking configuration of the round.
+    pub mask_config: MaskConfigPair,
+    /// Whether the round was a canary round, whose global model was not published.
+    #[serde(default)]
+    pub canary: bool,
+}
+
+/// The aggregation inputs of a round, from which its global model can be reproduced.
+#[derive(Debug, Clone)]
+pub struct RoundArchive {
+    /// The metadata of the round.
+    pub manifest: RoundManifest,
+    /// The aggregated masked model, including the aggregated scalar.
+    pub aggregation: Aggregation,
+    /// The aggregated mask chosen in the sum2 phase.
+    pub mask: MaskObject,
+}
+
+impl RoundArchive {
+    /// Creates the archive of a round which unmasked the `aggregation` with the `mask` into
+    /// the `global_model`.
+    pub fn new(
+        round_id: u64,
+        round_seed: &RoundSeed,
+        global_model: &Model,
+        aggregation: Aggregation,
+        mask: MaskObject,
+    ) -> Result<Self, RoundArchiveError> {
+        let manifest = RoundManifest {
+            version: FORMAT_VERSION,
+            round_id,
+            round_seed: hex::encode(round_seed.as_slice()),
+            global_model_id: global_model_id(global_model)?,
+            model_length: aggregation.len(),
+            nb_models: aggregation.nb_models(),
+            mask_config: aggregation.config(),
+            canary: false,
+        };
+        Ok(Self {
+            manifest,
+            aggregation,
+            mask,
+        })
+    }
+
+    /// Marks the archive as the archive of a canary round.
+    pub fn with_canary(mut self, canary: bool) -> Self {
+        self.manifest.canary = canary;
+        self
+    }
+
+    /// Encodes the archive.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, RoundArchiveError> {
+        let mut bytes = serde_json::to_vec(&self.manifest)?;
+        bytes.push(b'\n');
+        bincode::serialize_into(&mut bytes, &(&self.aggregation, &self.mask))?;
+        Ok(bytes)
+    }
+
+    /// Decodes an archive.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, RoundArchiveError> {
+        let newline = bytes
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or(RoundArchiveError::MissingManifest)?;
+        let manifest: RoundManifest = serde_json::from_slice(&bytes[..newline])?;
+        if manifest.version != FORMAT_VERSION {
+            return Err(RoundArchiveError::UnsupportedVersion(manifest.version));
+        }
+        let (aggregation, mask) = bincode::deserialize(&bytes[newline + 1..])?;
+        Ok(Self {
+            manifest,
+            aggregation,
+            mask,
+        })
+    }
+
+    /// Reproduces the global model of the round and checks that it is the published global
+    /// model.
+    ///
+    /// Returns the reproduced global model.
+    pub fn verify(self) -> Result<Model, RoundArchiveError> {
+        let Self {
+            manifest,
+            aggregation,
+            mask,
+        } = self;
+        if manifest.model_length != aggregation.len()
+            || manifest.nb_models != aggregation.nb_models()
+            || manifest.mask_config != aggregation.config()
+        {
+            return Err(RoundArchiveError::Inconsistent);
+        }
+
+        aggregation.validate_unmasking(&mask)?;
+        let global_model = aggregation.unmask(mask);
+        let found = global_model_id(&global_model)?;
+        if found != manifest.global_model_id {
+            return Err(RoundArchiveError::ModelMismatch {
+                expected: manifest.global_model_id,
+                found,
+            });
+        }
+        Ok(global_model)
+    }
+}
+
+/// Decodes an archive, reproduces the global model of the round and checks that it is the
+/// published global model (see [`RoundArchive::verify()`]).
+pub fn verify(archive: &[u8]) -> Result<(RoundManifest, Model), RoundArchiveError> {
+    let archive = RoundArchive::from_bytes(archive)?;
+    let manifest = archive.manifest.clone();
+    let global_model = archive.verify()?;
+    Ok((manifest, global_model))
+}
+
+/// Creates the id of a global model, as the model storage does.
+fn global_model_id(global_model: &Model) -> Result<String, bincode::Error> {
+    let data = bincode::serialize(global_model)?;
+    Ok(NoOp::create_global_model_id(&data))
+}
+
+#[cfg(test)]
+mod tests {
+    use num::{bigint::BigUint, One};
+    use xaynet_core::mask::{
+        BoundType,
+        DataType,
+        FromPrimitives,
+        GroupType,
+        MaskConfig,
+        Masker,
+        ModelType,
+        Scalar,
+    };
+
+    use super::*;
+
+    /// Simulates a round with two update participants, and returns its archive.
+    fn simulate_round() -> (RoundArchive, Model) {
+        let config = MaskConfig {
+            group_type: GroupType::Prime,
+            data_type: DataType::F32,
+            bound_type: BoundType::B0,
+            model_type: ModelType::M3,
+        }
+        .into();
+        let models = vec![
+            Model::from_primitives(vec![0.5_f32, -0.25, 0.125, 1.0].into_iter()).unwrap(),
+            Model::from_primitives(vec![-0.5_f32, 0.75, 0.375, 0.0].into_iter()).unwrap(),
+        ];
+        let model_length = models[0].len();
+
+        let mut aggregation = Aggregation::new(config, model_length);
+        let mut mask_aggregation = Aggregation::new(config, model_length);
+        for model in &models {
+            let (seed, masked_model) = Masker::new(config).mask(Scalar::new(1_u32, 2_u32), model);
+            aggregation.validate_aggregation(&masked_model).unwrap();
+            aggregation.aggregate(masked_model);
+            let mask = seed.derive_mask(model_length, config);
+            mask_aggregation.validate_aggregation(&mask).unwrap();
+            mask_aggregation.aggregate(mask);
+        }
+        let mask = MaskObject::from(mask_aggregation);
+        aggregation.validate_unmasking(&mask).unwrap();
+        let global_model = aggregation.clone().unmask(mask.clone());
+
+        let archive =
+            RoundArchive::new(42, &RoundSeed::generate(), &global_model, aggregation, mask)
+                .unwrap();
+        (archive, global_model)
+    }
+
+    #[test]
+    fn test_verify() {
+        let (archive, global_model) = simulate_round();
+        let bytes = archive.to_bytes().unwrap();
+
+        let (manifest, verified_model) = verify(&bytes).unwrap();
+        assert_eq!(verified_model, global_model);
+        assert_eq!(manifest, archive.manifest);
+        assert_eq!(manifest.round_id, 42);
+        assert_eq!(manifest.nb_models, 2);
+
+        // the manifest can be read on its own
+        let line = bytes.split(|&byte| byte == b'\n').next().unwrap();
+        let manifest: RoundManifest = serde_json::from_slice(line).unwrap();
+        assert_eq!(manifest, archive.manifest);
+    }
+
+    #[test]
+    fn test_verify_corrupt_mask() {
+        let (mut archive, _) = simulate_round();
+        archive.mask.vect.data[0] ^= BigUint::one();
+        let bytes = archive.to_bytes().unwrap();
+
+        assert!(matches!(
+            verify(&bytes),
+            Err(RoundArchiveError::ModelMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_invalid_archive() {
+        let (mut archive, _) = simulate_round();
+        archive.manifest.nb_models = 3;
+        let bytes = archive.to_bytes().unwrap();
+        assert!(matches!(
+            verify(&bytes),
+            Err(RoundArchiveError::Inconsistent)
+        ));
+
+        archive.manifest.version = FORMAT_VERSION + 1;
+        let bytes = archive.to_bytes().unwrap();
+        assert!(matches!(
+            verify(&bytes),
+            Err(RoundArchiveError::UnsupportedVersion(_))
+        ));
+
+        assert!(matches!(
+            verify(b"no manifest"),
+            Err(RoundArchiveError::MissingManifest)
+        ));
+        let (archive, _) = simulate_round();
+        let bytes = archive.to_bytes().unwrap();
+        assert!(matches!(
+            verify(&bytes[..bytes.len() - 1]),
+            Err(RoundArchiveError::Payload(_))
+        ));
+    }
+}
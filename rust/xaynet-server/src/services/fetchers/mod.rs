@@ -4,9 +4,14 @@
 //! provides a single unifying interface for all of these.
 
 mod model;
+mod model_by_id;
+mod phase_stats;
+mod public_stats;
+mod round_metadata;
 mod round_parameters;
 mod seed_dict;
 mod sum_dict;
+mod training_plan;
 
 use std::task::{Context, Poll};
 
@@ -16,11 +21,21 @@ use tower::{layer::Layer, Service, ServiceBuilder};
 
 pub use self::{
     model::{ModelRequest, ModelResponse, ModelService},
+    model_by_id::{ModelByIdRequest, ModelByIdResponse, ModelByIdService},
+    phase_stats::{PhaseStatsRequest, PhaseStatsResponse, PhaseStatsService},
+    public_stats::{
+        PublicStatsRequest,
+        PublicStatsResponse,
+        PublicStatsService,
+        PUBLIC_PARTICIPANTS_THRESHOLD,
+    },
+    round_metadata::{RoundMetadataRequest, RoundMetadataResponse, RoundMetadataService},
     round_parameters::{RoundParamsRequest, RoundParamsResponse, RoundParamsService},
     seed_dict::{SeedDictRequest, SeedDictResponse, SeedDictService},
     sum_dict::{SumDictRequest, SumDictResponse, SumDictService},
+    training_plan::{TrainingPlanRequest, TrainingPlanResponse, TrainingPlanService},
 };
-use crate::state_machine::events::EventSubscriber;
+use crate::{state_machine::events::EventSubscriber, storage::ModelStorage};
 
 /// A single interface for retrieving data from the coordinator.
 #[async_trait]
@@ -28,9 +43,15 @@ pub trait Fetcher {
     /// Fetch the parameters for the current round
     async fn round_params(&mut self) -> Result<RoundParamsResponse, FetchError>;
 
+    /// Fetch the metadata of the current round.
+    async fn round_metadata(&mut self) -> Result<RoundMetadataResponse, FetchError>;
+
     /// Fetch the latest global model.
     async fn model(&mut self) -> Result<ModelResponse, FetchError>;
 
+    /// Fetch the persisted global model with the given id.
+    async fn model_by_id(&mut self, id: String) -> Result<ModelByIdResponse, FetchError>;
+
     /// Fetch the global seed dictionary. Each sum2 participant needs a
     /// different portion of that dictionary.
     async fn seed_dict(&mut self) -> Result<SeedDictResponse, FetchError>;
@@ -39,6 +60,15 @@ pub trait Fetcher {
     /// dictionary to encrypt their masking seed for each sum
     /// participant.
     async fn sum_dict(&mut self) -> Result<SumDictResponse, FetchError>;
+
+    /// Fetch the status of the current training plan.
+    async fn training_plan(&mut self) -> Result<TrainingPlanResponse, FetchError>;
+
+    /// Fetch the number of messages handled in the current phase.
+    async fn phase_stats(&mut self) -> Result<PhaseStatsResponse, FetchError>;
+
+    /// Fetch the statistics about the training which are safe to publish.
+    async fn public_stats(&mut self) -> Result<PublicStatsResponse, FetchError>;
 }
 
 /// An error returned by the [`Fetcher`]'s method.
@@ -51,8 +81,28 @@ fn into_fetch_error<E: Into<Box<dyn std::error::Error + 'static + Sync + Send>>>
 }
 
 #[async_trait]
-impl<RoundParams, SumDict, SeedDict, Model> Fetcher
-    for Fetchers<RoundParams, SumDict, SeedDict, Model>
+impl<
+        RoundParams,
+        RoundMetadata,
+        SumDict,
+        SeedDict,
+        Model,
+        ModelById,
+        TrainingPlan,
+        PhaseStats,
+        PublicStats,
+    > Fetcher
+    for Fetchers<
+        RoundParams,
+        RoundMetadata,
+        SumDict,
+        SeedDict,
+        Model,
+        ModelById,
+        TrainingPlan,
+        PhaseStats,
+        PublicStats,
+    >
 where
     Self: Send + Sync + 'static,
 
@@ -61,11 +111,21 @@ where
     <RoundParams as Service<RoundParamsRequest>>::Error:
         Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
 
+    RoundMetadata: Service<RoundMetadataRequest, Response = RoundMetadataResponse> + Send + 'static,
+    <RoundMetadata as Service<RoundMetadataRequest>>::Future: Send + Sync + 'static,
+    <RoundMetadata as Service<RoundMetadataRequest>>::Error:
+        Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
+
     Model: Service<ModelRequest, Response = ModelResponse> + Send + 'static,
     <Model as Service<ModelRequest>>::Future: Send + Sync + 'static,
     <Model as Service<ModelRequest>>::Error:
         Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
 
+    ModelById: Service<ModelByIdRequest, Response = ModelByIdResponse> + Send + 'static,
+    <ModelById as Service<ModelByIdRequest>>::Future: Send + Sync + 'static,
+    <ModelById as Service<ModelByIdRequest>>::Error:
+        Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
+
     SeedDict: Service<SeedDictRequest, Response = SeedDictResponse> + Send + 'static,
     <SeedDict as Service<SeedDictRequest>>::Future: Send + Sync + 'static,
     <SeedDict as Service<SeedDictRequest>>::Error:
@@ -75,6 +135,21 @@ where
     <SumDict as Service<SumDictRequest>>::Future: Send + Sync + 'static,
     <SumDict as Service<SumDictRequest>>::Error:
         Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
+
+    TrainingPlan: Service<TrainingPlanRequest, Response = TrainingPlanResponse> + Send + 'static,
+    <TrainingPlan as Service<TrainingPlanRequest>>::Future: Send + Sync + 'static,
+    <TrainingPlan as Service<TrainingPlanRequest>>::Error:
+        Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
+
+    PhaseStats: Service<PhaseStatsRequest, Response = PhaseStatsResponse> + Send + 'static,
+    <PhaseStats as Service<PhaseStatsRequest>>::Future: Send + Sync + 'static,
+    <PhaseStats as Service<PhaseStatsRequest>>::Error:
+        Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
+
+    PublicStats: Service<PublicStatsRequest, Response = PublicStatsResponse> + Send + 'static,
+    <PublicStats as Service<PublicStatsRequest>>::Future: Send + Sync + 'static,
+    <PublicStats as Service<PublicStatsRequest>>::Error:
+        Into<Box<dyn std::error::Error + 'static + Sync + Send>>,
 {
     async fn round_params(&mut self) -> Result<RoundParamsResponse, FetchError> {
         poll_fn(|cx| {
@@ -90,6 +165,23 @@ where
         .map_err(into_fetch_error)?)
     }
 
+    async fn round_metadata(&mut self) -> Result<RoundMetadataResponse, FetchError> {
+        poll_fn(|cx| {
+            <RoundMetadata as Service<RoundMetadataRequest>>::poll_ready(
+                &mut self.round_metadata,
+                cx,
+            )
+        })
+        .await
+        .map_err(into_fetch_error)?;
+        Ok(<RoundMetadata as Service<RoundMetadataRequest>>::call(
+            &mut self.round_metadata,
+            RoundMetadataRequest,
+        )
+        .await
+        .map_err(into_fetch_error)?)
+    }
+
     async fn model(&mut self) -> Result<ModelResponse, FetchError> {
         poll_fn(|cx| <Model as Service<ModelRequest>>::poll_ready(&mut self.model, cx))
             .await
@@ -101,6 +193,20 @@ where
         )
     }
 
+    async fn model_by_id(&mut self, id: String) -> Result<ModelByIdResponse, FetchError> {
+        poll_fn(|cx| {
+            <ModelById as Service<ModelByIdRequest>>::poll_ready(&mut self.model_by_id, cx)
+        })
+        .await
+        .map_err(into_fetch_error)?;
+        Ok(<ModelById as Service<ModelByIdRequest>>::call(
+            &mut self.model_by_id,
+            ModelByIdRequest(id),
+        )
+        .await
+        .map_err(into_fetch_error)?)
+    }
+
     async fn seed_dict(&mut self) -> Result<SeedDictResponse, FetchError> {
         poll_fn(|cx| <SeedDict as Service<SeedDictRequest>>::poll_ready(&mut self.seed_dict, cx))
             .await
@@ -122,6 +228,48 @@ where
                 .map_err(into_fetch_error)?,
         )
     }
+
+    async fn training_plan(&mut self) -> Result<TrainingPlanResponse, FetchError> {
+        poll_fn(|cx| {
+            <TrainingPlan as Service<TrainingPlanRequest>>::poll_ready(&mut self.training_plan, cx)
+        })
+        .await
+        .map_err(into_fetch_error)?;
+        Ok(<TrainingPlan as Service<TrainingPlanRequest>>::call(
+            &mut self.training_plan,
+            TrainingPlanRequest,
+        )
+        .await
+        .map_err(into_fetch_error)?)
+    }
+
+    async fn phase_stats(&mut self) -> Result<PhaseStatsResponse, FetchError> {
+        poll_fn(|cx| {
+            <PhaseStats as Service<PhaseStatsRequest>>::poll_ready(&mut self.phase_stats, cx)
+        })
+        .await
+        .map_err(into_fetch_error)?;
+        Ok(<PhaseStats as Service<PhaseStatsRequest>>::call(
+            &mut self.phase_stats,
+            PhaseStatsRequest,
+        )
+        .await
+        .map_err(into_fetch_error)?)
+    }
+
+    async fn public_stats(&mut self) -> Result<PublicStatsResponse, FetchError> {
+        poll_fn(|cx| {
+            <PublicStats as Service<PublicStatsRequest>>::poll_ready(&mut self.public_stats, cx)
+        })
+        .await
+        .map_err(into_fetch_error)?;
+        Ok(<PublicStats as Service<PublicStatsRequest>>::call(
+            &mut self.public_stats,
+            PublicStatsRequest,
+        )
+        .await
+        .map_err(into_fetch_error)?)
+    }
 }
 
 pub(in crate::services) struct FetcherService<S>(S);
@@ -153,44 +301,73 @@ impl<S> Layer<S> for FetcherLayer {
     }
 }
 
+/// The set of services backing a [`Fetcher`], one for each kind of data.
 #[derive(Debug, Clone)]
-pub struct Fetchers<RoundParams, SumDict, SeedDict, Model> {
-    round_params: RoundParams,
-    sum_dict: SumDict,
-    seed_dict: SeedDict,
-    model: Model,
-}
-
-impl<RoundParams, SumDict, SeedDict, Model> Fetchers<RoundParams, SumDict, SeedDict, Model> {
-    pub fn new(
-        round_params: RoundParams,
-        sum_dict: SumDict,
-        seed_dict: SeedDict,
-        model: Model,
-    ) -> Self {
-        Self {
-            round_params,
-            sum_dict,
-            seed_dict,
-            model,
-        }
-    }
+pub struct Fetchers<
+    RoundParams,
+    RoundMetadata,
+    SumDict,
+    SeedDict,
+    Model,
+    ModelById,
+    TrainingPlan,
+    PhaseStats,
+    PublicStats,
+> {
+    /// Service fetching the parameters of the current round.
+    pub round_params: RoundParams,
+    /// Service fetching the metadata of the current round.
+    pub round_metadata: RoundMetadata,
+    /// Service fetching the sum dictionary.
+    pub sum_dict: SumDict,
+    /// Service fetching the global seed dictionary.
+    pub seed_dict: SeedDict,
+    /// Service fetching the latest global model.
+    pub model: Model,
+    /// Service fetching the persisted global models by id.
+    pub model_by_id: ModelById,
+    /// Service fetching the status of the current training plan.
+    pub training_plan: TrainingPlan,
+    /// Service fetching the number of messages handled in the current phase.
+    pub phase_stats: PhaseStats,
+    /// Service fetching the statistics about the training which are safe to publish.
+    pub public_stats: PublicStats,
 }
 
 /// Construct a [`Fetcher`] service
-pub fn fetcher(event_subscriber: &EventSubscriber) -> impl Fetcher + Sync + Send + Clone + 'static {
+///
+/// The persisted global models are fetched from the `model_store`.
+pub fn fetcher<M>(
+    event_subscriber: &EventSubscriber,
+    model_store: M,
+) -> impl Fetcher + Sync + Send + Clone + 'static
+where
+    M: ModelStorage,
+{
     let round_params = ServiceBuilder::new()
         .buffer(100)
         .concurrency_limit(100)
         .layer(FetcherLayer)
         .service(RoundParamsService::new(event_subscriber));
 
+    let round_metadata = ServiceBuilder::new()
+        .buffer(100)
+        .concurrency_limit(100)
+        .layer(FetcherLayer)
+        .service(RoundMetadataService::new(event_subscriber));
+
     let model = ServiceBuilder::new()
         .buffer(100)
         .concurrency_limit(100)
         .layer(FetcherLayer)
         .service(ModelService::new(event_subscriber));
 
+    let model_by_id = ServiceBuilder::new()
+        .buffer(100)
+        .concurrency_limit(100)
+        .layer(FetcherLayer)
+        .service(ModelByIdService::new(model_store));
+
     let sum_dict = ServiceBuilder::new()
         .buffer(100)
         .concurrency_limit(100)
@@ -203,5 +380,33 @@ pub fn fetcher(event_subscriber: &EventSubscriber) -> impl Fetcher + Sync + Send
         .layer(FetcherLayer)
         .service(SeedDictService::new(event_subscriber));
 
-    Fetchers::new(round_params, sum_dict, seed_dict, model)
+    let training_plan = ServiceBuilder::new()
+        .buffer(100)
+        .concurrency_limit(100)
+        .layer(FetcherLayer)
+        .service(TrainingPlanService::new(event_subscriber));
+
+    let phase_stats = ServiceBuilder::new()
+        .buffer(100)
+        .concurrency_limit(100)
+        .layer(FetcherLayer)
+        .service(PhaseStatsService::new(event_subscriber));
+
+    let public_stats = ServiceBuilder::new()
+        .buffer(100)
+        .concurrency_limit(100)
+        .layer(FetcherLayer)
+        .service(PublicStatsService::new(event_subscriber));
+
+    Fetchers {
+        round_params,
+        round_metadata,
+        sum_dict,
+        seed_dict,
+        model,
+        model_by_id,
+        training_plan,
+        phase_stats,
+        public_stats,
+    }
 }
@@ -0,0 +1,58 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tower::Service;
+use tracing::error_span;
+use tracing_futures::Instrument;
+
+use crate::storage::{ModelStorage, StorageError};
+use xaynet_core::mask::Model;
+
+/// [`ModelByIdService`]'s request type: the id of the requested global model.
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+pub struct ModelByIdRequest(pub String);
+
+/// [`ModelByIdService`]'s response type.
+///
+/// The response is `None` when no global model exists for the requested id.
+pub type ModelByIdResponse = Option<Model>;
+
+/// A service that serves the global models persisted in the model storage.
+pub struct ModelByIdService<M>(M);
+
+impl<M> ModelByIdService<M>
+where
+    M: ModelStorage,
+{
+    pub fn new(store: M) -> Self {
+        Self(store)
+    }
+}
+
+impl<M> Service<ModelByIdRequest> for ModelByIdService<M>
+where
+    M: ModelStorage,
+{
+    type Response = ModelByIdResponse;
+    type Error = StorageError;
+    #[allow(clippy::type_complexity)]
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + 'static + Send + Sync>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: ModelByIdRequest) -> Self::Future {
+        let mut store = self.0.clone();
+        // the storage futures are not `Sync`, hence the model is fetched in a separate task
+        let fetch = tokio::spawn(
+            async move { store.global_model(&req.0).await }
+                .instrument(error_span!("model_by_id_fetch_request")),
+        );
+        Box::pin(async move { fetch.await? })
+    }
+}
@@ -0,0 +1,44 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::{self, Ready};
+use tower::Service;
+use tracing::error_span;
+use tracing_futures::{Instrument, Instrumented};
+
+use crate::state_machine::events::{Event, EventListener, EventSubscriber, PhaseCounters};
+use xaynet_core::common::PhaseStats;
+
+/// [`PhaseStatsService`]'s request type
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+pub struct PhaseStatsRequest;
+
+/// [`PhaseStatsService`]'s response type
+pub type PhaseStatsResponse = PhaseStats;
+
+/// A service that serves the number of messages handled in the current phase.
+pub struct PhaseStatsService(EventListener<Arc<PhaseCounters>>);
+
+impl PhaseStatsService {
+    pub fn new(events: &EventSubscriber) -> Self {
+        Self(events.phase_counters_listener())
+    }
+}
+
+impl Service<PhaseStatsRequest> for PhaseStatsService {
+    type Response = PhaseStats;
+    type Error = std::convert::Infallible;
+    type Future = Instrumented<Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: PhaseStatsRequest) -> Self::Future {
+        let Event { round_id, event } = self.0.get_latest();
+        future::ready(Ok(event.snapshot(round_id)))
+            .instrument(error_span!("phase_stats_fetch_request"))
+    }
+}
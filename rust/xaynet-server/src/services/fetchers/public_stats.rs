@@ -0,0 +1,106 @@
+use std::task::{Context, Poll};
+
+use futures::future::{self, Ready};
+use tower::Service;
+use tracing::error_span;
+use tracing_futures::{Instrument, Instrumented};
+
+use crate::state_machine::{
+    coordinator::RoundHistory,
+    events::{EventListener, EventSubscriber},
+    phases::PhaseName,
+};
+use xaynet_core::common::PublicStats;
+
+/// The minimal number of participants of a round which is published. Smaller numbers could
+/// single out the participants of a round.
+pub const PUBLIC_PARTICIPANTS_THRESHOLD: u64 = 10;
+
+/// [`PublicStatsService`]'s request type
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+pub struct PublicStatsRequest;
+
+/// [`PublicStatsService`]'s response type
+pub type PublicStatsResponse = PublicStats;
+
+/// A service that serves the statistics about the training which are safe to publish.
+pub struct PublicStatsService {
+    phase: EventListener<PhaseName>,
+    round_history: EventListener<RoundHistory>,
+}
+
+impl PublicStatsService {
+    pub fn new(events: &EventSubscriber) -> Self {
+        Self {
+            phase: events.phase_listener(),
+            round_history: events.round_history_listener(),
+        }
+    }
+}
+
+impl Service<PublicStatsRequest> for PublicStatsService {
+    type Response = PublicStats;
+    type Error = std::convert::Infallible;
+    type Future = Instrumented<Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: PublicStatsRequest) -> Self::Future {
+        let history = self.round_history.get_latest().event;
+        let stats = PublicStats {
+            round_id: self.phase.get_latest().round_id,
+            completed_rounds: history.completed_rounds,
+            participants: bucket_participants(history.participants),
+            last_model_update: history.last_model_update,
+        };
+        future::ready(Ok(stats)).instrument(error_span!("public_stats_fetch_request"))
+    }
+}
+
+/// Rounds the number of `participants` down to two significant digits, but at least to a
+/// multiple of ten, so that the exact number is never published.
+///
+/// Returns `None` if there are fewer participants than [`PUBLIC_PARTICIPANTS_THRESHOLD`].
+fn bucket_participants(participants: u64) -> Option<u64> {
+    if participants < PUBLIC_PARTICIPANTS_THRESHOLD {
+        return None;
+    }
+    let mut bucket = 10;
+    while participants / bucket >= 100 {
+        bucket *= 10;
+    }
+    Some(participants - participants % bucket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_participants() {
+        for participants in 0..PUBLIC_PARTICIPANTS_THRESHOLD {
+            assert_eq!(bucket_participants(participants), None);
+        }
+        assert_eq!(bucket_participants(10), Some(10));
+        assert_eq!(bucket_participants(19), Some(10));
+        assert_eq!(bucket_participants(57), Some(50));
+        assert_eq!(bucket_participants(999), Some(990));
+        assert_eq!(bucket_participants(1_234), Some(1_200));
+        assert_eq!(bucket_participants(98_765), Some(98_000));
+        assert_eq!(
+            bucket_participants(u64::MAX),
+            Some(18_000_000_000_000_000_000)
+        );
+    }
+
+    #[test]
+    fn test_bucket_participants_hides_exact_counts() {
+        for participants in PUBLIC_PARTICIPANTS_THRESHOLD..10_000 {
+            let bucket = bucket_participants(participants).unwrap();
+            assert!(bucket <= participants);
+            assert_eq!(bucket % 10, 0);
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::task::{Context, Poll};
+
+use futures::future::{self, Ready};
+use tower::Service;
+use tracing::error_span;
+use tracing_futures::{Instrument, Instrumented};
+
+use crate::state_machine::events::{EventListener, EventSubscriber};
+use xaynet_core::common::RoundMetadata;
+
+/// [`RoundMetadataService`]'s request type
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+pub struct RoundMetadataRequest;
+
+/// [`RoundMetadataService`]'s response type
+pub type RoundMetadataResponse = RoundMetadata;
+
+/// A service that serves the metadata of the current round.
+pub struct RoundMetadataService(EventListener<RoundMetadata>);
+
+impl RoundMetadataService {
+    pub fn new(events: &EventSubscriber) -> Self {
+        Self(events.round_metadata_listener())
+    }
+}
+
+impl Service<RoundMetadataRequest> for RoundMetadataService {
+    type Response = RoundMetadata;
+    type Error = std::convert::Infallible;
+    type Future = Instrumented<Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: RoundMetadataRequest) -> Self::Future {
+        future::ready(Ok(self.0.get_latest().event))
+            .instrument(error_span!("round_metadata_fetch_request"))
+    }
+}
@@ -0,0 +1,45 @@
+use std::task::{Context, Poll};
+
+use futures::future::{self, Ready};
+use tower::Service;
+use tracing::error_span;
+use tracing_futures::{Instrument, Instrumented};
+
+use crate::state_machine::{
+    coordinator::TrainingPlanStatus,
+    events::{EventListener, EventSubscriber},
+};
+
+/// [`TrainingPlanService`]'s request type
+#[derive(Default, Clone, Eq, PartialEq, Debug)]
+pub struct TrainingPlanRequest;
+
+/// [`TrainingPlanService`]'s response type.
+///
+/// The response is `None` when the coordinator has no training plans
+/// or when all of them completed.
+pub type TrainingPlanResponse = Option<TrainingPlanStatus>;
+
+/// A service that serves the status of the current training plan.
+pub struct TrainingPlanService(EventListener<Option<TrainingPlanStatus>>);
+
+impl TrainingPlanService {
+    pub fn new(events: &EventSubscriber) -> Self {
+        Self(events.training_plan_listener())
+    }
+}
+
+impl Service<TrainingPlanRequest> for TrainingPlanService {
+    type Response = TrainingPlanResponse;
+    type Error = std::convert::Infallible;
+    type Future = Instrumented<Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, _req: TrainingPlanRequest) -> Self::Future {
+        future::ready(Ok(self.0.get_latest().event))
+            .instrument(error_span!("training_plan_fetch_request"))
+    }
+}
@@ -25,10 +25,40 @@ pub enum ServiceError {
     NotSumEligible,
     /// Participant is not eligible for update task.
     NotUpdateEligible,
+    /// Invalid model length: expected {expected} weights, got {actual}.
+    InvalidModelLength { expected: usize, actual: usize },
     /// Internal error: {0}.
     InternalError(String),
 }
 
+impl ServiceError {
+    /// Gets a short code that identifies the kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Decrypt => "decrypt",
+            Self::Parsing(_) => "parsing",
+            Self::InvalidMessageSignature => "invalid_message_signature",
+            Self::InvalidCoordinatorPublicKey => "invalid_coordinator_public_key",
+            Self::UnexpectedMessage => "unexpected_message",
+            Self::StateMachine(RequestError::QuotaExceeded) => "quota_exceeded",
+            Self::StateMachine(_) => "state_machine",
+            Self::NotSumEligible => "not_sum_eligible",
+            Self::NotUpdateEligible => "not_update_eligible",
+            Self::InvalidModelLength { .. } => "invalid_model_length",
+            Self::InternalError(_) => "internal_error",
+        }
+    }
+
+    /// Gets a detailed description of the error, including the whole context of the
+    /// parsing errors.
+    pub fn detail(&self) -> String {
+        match self {
+            Self::Parsing(e) => format!("Failed to parse the message: {:#}.", e),
+            e => e.to_string(),
+        }
+    }
+}
+
 impl From<Box<dyn std::error::Error>> for ServiceError {
     fn from(e: Box<dyn std::error::Error>) -> Self {
         match e.downcast::<ServiceError>() {
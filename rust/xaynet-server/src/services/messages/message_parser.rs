@@ -7,6 +7,8 @@ use tower::{layer::Layer, limit::concurrency::ConcurrencyLimit, Service, Service
 use tracing::{debug, info, trace, warn};
 
 use crate::{
+    metric,
+    metrics::Measurement,
     services::messages::{BoxedServiceFuture, ServiceError},
     state_machine::{
         events::{EventListener, EventSubscriber},
@@ -84,6 +86,31 @@ impl<S> Layer<S> for BufferWrapperLayer {
     }
 }
 
+/// Checks whether a message with the given tag is expected in the given phase.
+///
+/// Records a metric for the unexpected messages. The `stage` tells whether the tag was
+/// read from the unencrypted envelope of the message (`"envelope"`) or from the
+/// decrypted message (`"payload"`).
+pub(crate) fn is_expected(phase: PhaseName, tag: Tag, stage: &'static str) -> bool {
+    let expected = matches!(
+        (phase, tag),
+        (PhaseName::Sum, Tag::Sum)
+            | (PhaseName::Update, Tag::Update)
+            | (PhaseName::Sum2, Tag::Sum2)
+    );
+    if !expected {
+        debug!("discarding {:?} message in {:?} phase", tag, phase);
+        metric!(
+            Measurement::MessageUnexpected,
+            1,
+            ("phase", phase as u8),
+            ("tag", u8::from(tag)),
+            ("stage", stage),
+        );
+    }
+    expected
+}
+
 /// A service that discards messages that are not expected in the current phase
 #[derive(Debug, Clone)]
 struct PhaseFilter<S> {
@@ -111,15 +138,11 @@ where
         debug!("retrieving the current phase");
         let phase = self.phase.get_latest().event;
         match req.buffer.tag().try_into() {
-            Ok(tag) => match (phase, tag) {
-                (PhaseName::Sum, Tag::Sum)
-                | (PhaseName::Update, Tag::Update)
-                | (PhaseName::Sum2, Tag::Sum2) => {
-                    let fut = self.next_svc.call(req);
-                    Box::pin(async move { fut.await })
-                }
-                _ => Box::pin(future::ready(Err(ServiceError::UnexpectedMessage))),
-            },
+            Ok(tag) if is_expected(phase, tag, "payload") => {
+                let fut = self.next_svc.call(req);
+                Box::pin(async move { fut.await })
+            }
+            Ok(_) => Box::pin(future::ready(Err(ServiceError::UnexpectedMessage))),
             Err(e) => Box::pin(future::ready(Err(ServiceError::Parsing(e)))),
         }
     }
@@ -407,4 +430,37 @@ mod tests {
             _ => panic!("expected ServiceError::UnexpectedMessage got {:?}", err),
         }
     }
+
+    #[tokio::test]
+    async fn test_concurrent_messages_with_invalid_signature() {
+        let (mut publisher, subscriber) = utils::new_event_channels();
+        let thread_pool = Arc::new(ThreadPoolBuilder::new().build().unwrap());
+        let parser = MessageParser::new(&subscriber, thread_pool);
+        publisher.broadcast_phase(PhaseName::Sum);
+
+        // Send several messages at once and corrupt the signature of one
+        // of them, which must be the only one to be rejected
+        let round_params = subscriber.params_listener().get_latest().event;
+        let requests = (0..8).map(|i| {
+            let (message, signing_keys) = utils::new_sum_message(&round_params);
+            let mut serialized_message = utils::serialize_message(&message, &signing_keys);
+            if i == 3 {
+                serialized_message[0] ^= 0xff;
+            }
+            let mut parser = parser.clone();
+            async move {
+                future::poll_fn(|cx| Service::<Vec<u8>>::poll_ready(&mut parser, cx)).await?;
+                parser.call(serialized_message).await
+            }
+        });
+        let responses = future::join_all(requests).await;
+
+        for (i, resp) in responses.into_iter().enumerate() {
+            match resp {
+                Ok(_) if i != 3 => {}
+                Err(ServiceError::InvalidMessageSignature) if i == 3 => {}
+                _ => panic!("unexpected response for message {}: {:?}", i, resp),
+            }
+        }
+    }
 }
@@ -15,17 +15,24 @@ use std::sync::Arc;
 use futures::future::poll_fn;
 use rayon::ThreadPoolBuilder;
 use tower::Service;
-use xaynet_core::message::Message;
+use xaynet_core::{
+    mask::ModelShape,
+    message::{Message, Tag},
+};
 
 pub use self::error::ServiceError;
 use self::{
     decryptor::Decryptor,
-    message_parser::MessageParser,
+    message_parser::{is_expected, MessageParser},
     multipart::MultipartHandler,
     state_machine::StateMachine,
     task_validator::TaskValidator,
 };
-use crate::state_machine::{events::EventSubscriber, requests::RequestSender};
+use crate::state_machine::{
+    events::{EventListener, EventSubscriber},
+    phases::PhaseName,
+    requests::{RequestSender, StateMachineRequest},
+};
 
 impl PetMessageHandler {
     pub fn new(event_subscriber: &EventSubscriber, requests_tx: RequestSender) -> Self {
@@ -41,6 +48,7 @@ impl PetMessageHandler {
         let state_machine = StateMachine::new(requests_tx);
 
         Self {
+            phase: event_subscriber.phase_listener(),
             decryptor,
             multipart_handler,
             message_parser,
@@ -72,18 +80,49 @@ impl PetMessageHandler {
         self.task_validator.call(message).await
     }
 
-    async fn process(&mut self, message: Message) -> Result<(), ServiceError> {
+    async fn process(
+        &mut self,
+        message: Message,
+        model_shape: Option<ModelShape>,
+    ) -> Result<(), ServiceError> {
+        let mut request = StateMachineRequest::from(message);
+        if let StateMachineRequest::Update(ref mut update) = request {
+            update.model_shape = model_shape;
+        }
         poll_fn(|cx| self.state_machine.poll_ready(cx)).await?;
-        self.state_machine.call(message).await
+        self.state_machine.call(request).await
     }
 
-    pub async fn handle_message(&mut self, enc_data: Vec<u8>) -> Result<(), ServiceError> {
+    /// Handles an encrypted PET message.
+    ///
+    /// If the `tag` of the message is sent unencrypted along with the message, the
+    /// messages that are not expected in the current phase are discarded before they
+    /// are decrypted. The unencrypted tag is not authenticated, but a message with a
+    /// wrong tag is still discarded once decrypted if its actual tag is not expected
+    /// either. The messages of legacy participants come without a tag and are only
+    /// filtered once decrypted.
+    ///
+    /// The `model_shape` of an update message is sent unencrypted along with the message as
+    /// well. It is ignored for any other message. A multipart message is processed once all its
+    /// chunks have been received and only the shape sent along with the chunk which completes the
+    /// message is kept, hence the shape should be sent along with every chunk.
+    pub async fn handle_message(
+        &mut self,
+        tag: Option<Tag>,
+        model_shape: Option<ModelShape>,
+        enc_data: Vec<u8>,
+    ) -> Result<(), ServiceError> {
+        if let Some(tag) = tag {
+            if !is_expected(self.phase.get_latest().event, tag, "envelope") {
+                return Err(ServiceError::UnexpectedMessage);
+            }
+        }
         let raw_message = self.decrypt(enc_data).await?;
         let message = self.parse(raw_message).await?;
         match self.handle_multipart(message).await? {
             Some(message) => {
                 let message = self.validate_task(message).await?;
-                self.process(message).await
+                self.process(message, model_shape).await
             }
             None => Ok(()),
         }
@@ -106,6 +145,7 @@ impl PetMessageHandler {
 /// 3. Finally, the message is handled by the `StateMachine` service.
 #[derive(Clone)]
 pub struct PetMessageHandler {
+    phase: EventListener<PhaseName>,
     decryptor: Decryptor,
     multipart_handler: MultipartHandler,
     message_parser: MessageParser,
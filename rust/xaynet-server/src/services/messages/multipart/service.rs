@@ -11,11 +11,18 @@ use tower::Service;
 use tracing::{debug, trace, warn};
 
 use crate::services::messages::{multipart::buffer::MultipartMessageBuffer, ServiceError};
+#[cfg(feature = "compression")]
+use xaynet_core::message::compression;
 use xaynet_core::{
     crypto::{PublicEncryptKey, PublicSigningKey},
     message::{Chunk, DecodeError, FromBytes, Message, Payload, Sum, Sum2, Tag, Update},
 };
 
+/// The maximum length of a decompressed message payload, which protects the coordinator
+/// against decompression bombs.
+#[cfg(feature = "compression")]
+const MAX_DECOMPRESSED_LENGTH: usize = 1 << 30;
+
 /// A `MessageBuilder` stores chunks of a multipart message. Once it
 /// has all the chunks, it can be consumed and turned into a
 /// full-blown [`Message`] (see [`into_message()`]).
@@ -30,6 +37,8 @@ pub struct MessageBuilder {
     coordinator_pk: PublicEncryptKey,
     /// Message type
     tag: Tag,
+    /// Whether the chunks carry a compressed payload
+    is_compressed: bool,
     /// The ID of the last chunk is actually the total number of
     /// chunks this message is made of.
     last_chunk_id: Option<u16>,
@@ -39,9 +48,15 @@ pub struct MessageBuilder {
 
 impl MessageBuilder {
     /// Create a new [`MessageBuilder`] that contains no chunk.
-    fn new(tag: Tag, participant_pk: PublicSigningKey, coordinator_pk: PublicEncryptKey) -> Self {
+    fn new(
+        tag: Tag,
+        is_compressed: bool,
+        participant_pk: PublicSigningKey,
+        coordinator_pk: PublicEncryptKey,
+    ) -> Self {
         MessageBuilder {
             tag,
+            is_compressed,
             participant_pk,
             coordinator_pk,
             data: BTreeMap::new(),
@@ -73,11 +88,15 @@ impl MessageBuilder {
     /// when all the chunks are here, otherwise the aggregated message
     /// will be invalid.
     fn into_message(self) -> Result<Message, DecodeError> {
-        let mut bytes = MultipartMessageBuffer::from(self.data);
-        let payload = match self.tag {
-            Tag::Sum => Sum::from_byte_stream(&mut bytes).map(Into::into)?,
-            Tag::Update => Update::from_byte_stream(&mut bytes).map(Into::into)?,
-            Tag::Sum2 => Sum2::from_byte_stream(&mut bytes).map(Into::into)?,
+        let payload = if self.is_compressed {
+            decompress_payload(self.tag, self.data)?
+        } else {
+            let mut bytes = MultipartMessageBuffer::from(self.data);
+            match self.tag {
+                Tag::Sum => Sum::from_byte_stream(&mut bytes).map(Into::into)?,
+                Tag::Update => Update::from_byte_stream(&mut bytes).map(Into::into)?,
+                Tag::Sum2 => Sum2::from_byte_stream(&mut bytes).map(Into::into)?,
+            }
         };
         let message = Message {
             signature: None,
@@ -85,12 +104,32 @@ impl MessageBuilder {
             coordinator_pk: self.coordinator_pk,
             tag: self.tag,
             is_multipart: false,
+            is_compressed: false,
             payload,
         };
         Ok(message)
     }
 }
 
+/// Decompress and parse the payload carried by the chunks of a compressed message.
+#[cfg(feature = "compression")]
+fn decompress_payload(tag: Tag, data: BTreeMap<u16, Vec<u8>>) -> Result<Payload, DecodeError> {
+    let compressed = data.values().flatten().copied().collect::<Vec<u8>>();
+    let bytes = compression::decompress(&compressed, MAX_DECOMPRESSED_LENGTH)?;
+    let payload = match tag {
+        Tag::Sum => Sum::from_byte_slice(&bytes)?.into(),
+        Tag::Update => Update::from_byte_slice(&bytes)?.into(),
+        Tag::Sum2 => Sum2::from_byte_slice(&bytes)?.into(),
+    };
+    Ok(payload)
+}
+
+/// Reject a compressed message, since the coordinator was built without compression.
+#[cfg(not(feature = "compression"))]
+fn decompress_payload(_tag: Tag, _data: BTreeMap<u16, Vec<u8>>) -> Result<Payload, DecodeError> {
+    Err(anyhow::anyhow!("compressed messages are not supported"))
+}
+
 /// [`MessageId`] uniquely identifies a multipart message by its ID
 /// (which uniquely identify a message _for a given participant_), and
 /// the participant public key.
@@ -134,6 +173,7 @@ impl Service<Message> for MultipartHandler {
         debug!("handling multipart message");
         if let Message {
             tag,
+            is_compressed,
             participant_pk,
             coordinator_pk,
             payload: Payload::Chunk(chunk),
@@ -148,7 +188,7 @@ impl Service<Message> for MultipartHandler {
             // an empty one.
             let mp_message = self.message_builders.entry(id.clone()).or_insert_with(|| {
                 debug!("new multipart message (id = {})", id.message_id);
-                MessageBuilder::new(tag, participant_pk, coordinator_pk)
+                MessageBuilder::new(tag, is_compressed, participant_pk, coordinator_pk)
             });
             // Add the chunk to the partial message
             mp_message.add_chunk(chunk);
@@ -222,7 +262,7 @@ mod tests {
         let participant_pk = PublicSigningKey::zeroed();
         let coordinator_pk = PublicEncryptKey::zeroed();
         let tag = Tag::Sum;
-        MessageBuilder::new(tag, participant_pk, coordinator_pk)
+        MessageBuilder::new(tag, false, participant_pk, coordinator_pk)
     }
 
     fn chunks(mut data: Vec<u8>) -> (Chunk, Chunk, Chunk, Chunk, Chunk) {
@@ -369,6 +409,49 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_message_builder_compressed() {
+        let (data, sum) = sum();
+        let mut compressed = compression::compress(&data);
+        let data2 = compressed.split_off(compressed.len() / 2);
+        let participant_pk = PublicSigningKey::zeroed();
+        let coordinator_pk = PublicEncryptKey::zeroed();
+        let mut msg = MessageBuilder::new(Tag::Sum, true, participant_pk, coordinator_pk);
+
+        msg.add_chunk(Chunk {
+            id: 1,
+            message_id: 1234,
+            last: true,
+            data: data2,
+        });
+        msg.add_chunk(Chunk {
+            id: 0,
+            message_id: 1234,
+            last: false,
+            data: compressed,
+        });
+        assert!(msg.has_all_chunks());
+
+        let actual = msg.into_message().unwrap();
+        let expected = Message::new_sum(participant_pk, coordinator_pk, sum);
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn test_message_builder_compressed_unsupported() {
+        let (data, _) = sum();
+        let (c1, c2, c3, c4, c5) = chunks(data);
+        let participant_pk = PublicSigningKey::zeroed();
+        let coordinator_pk = PublicEncryptKey::zeroed();
+        let mut msg = MessageBuilder::new(Tag::Sum, true, participant_pk, coordinator_pk);
+        for chunk in [c1, c2, c3, c4, c5] {
+            msg.add_chunk(chunk);
+        }
+        assert!(msg.into_message().is_err());
+    }
+
     #[tokio::test]
     async fn message_handler() {
         let mut task = spawn_svc();
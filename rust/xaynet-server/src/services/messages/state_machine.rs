@@ -2,11 +2,10 @@ use std::task::Poll;
 
 use futures::task::Context;
 use tower::Service;
-use xaynet_core::message::Message;
 
 use crate::{
     services::messages::{BoxedServiceFuture, ServiceError},
-    state_machine::requests::RequestSender,
+    state_machine::requests::{RequestSender, StateMachineRequest},
 };
 
 /// A service that hands the requests to the [`StateMachine`] that runs in the background.
@@ -28,7 +27,7 @@ impl StateMachine {
     }
 }
 
-impl Service<Message> for StateMachine {
+impl Service<StateMachineRequest> for StateMachine {
     type Response = ();
     type Error = ServiceError;
     type Future = BoxedServiceFuture<Self::Response, Self::Error>;
@@ -37,11 +36,11 @@ impl Service<Message> for StateMachine {
         Poll::Ready(Ok(()))
     }
 
-    fn call(&mut self, req: Message) -> Self::Future {
+    fn call(&mut self, req: StateMachineRequest) -> Self::Future {
         let handle = self.handle.clone();
         Box::pin(async move {
             handle
-                .request(req.into(), tracing::Span::none())
+                .request(req, tracing::Span::none())
                 .await
                 .map_err(ServiceError::StateMachine)
         })
@@ -4,8 +4,12 @@ use futures::{future, task::Context};
 use tower::Service;
 
 use crate::{
+    rejected,
     services::messages::ServiceError,
-    state_machine::events::{EventListener, EventSubscriber},
+    state_machine::{
+        events::{Event, EventListener, EventSubscriber},
+        phases::PhaseName,
+    },
 };
 use xaynet_core::{
     common::RoundParameters,
@@ -15,6 +19,10 @@ use xaynet_core::{
 
 /// A service for performing sanity checks and preparing incoming
 /// requests to be handled by the state machine.
+///
+/// Besides the task eligibility of the participants, it checks that the
+/// masked models of the update messages have the length of the current
+/// round, so that they are rejected before they reach the aggregation.
 #[derive(Clone, Debug)]
 pub struct TaskValidator {
     params_listener: EventListener<RoundParameters>,
@@ -44,7 +52,10 @@ impl Service<Message> for TaskValidator {
             Payload::Sum2(ref sum2) => (sum2.sum_signature, None),
             _ => return future::ready(Err(ServiceError::UnexpectedMessage)),
         };
-        let params = self.params_listener.get_latest().event;
+        let Event {
+            round_id,
+            event: params,
+        } = self.params_listener.get_latest();
         let seed = params.seed.as_slice();
 
         // Check whether the participant is eligible for the sum task
@@ -75,12 +86,19 @@ impl Service<Message> for TaskValidator {
                     future::ready(Err(ServiceError::NotSumEligible))
                 }
             }
-            Payload::Update(_) => {
-                if is_updater {
-                    future::ready(Ok(message))
-                } else {
-                    future::ready(Err(ServiceError::NotUpdateEligible))
+            Payload::Update(ref update) => {
+                if !is_updater {
+                    return future::ready(Err(ServiceError::NotUpdateEligible));
+                }
+                let actual = update.masked_model.vect.data.len();
+                if actual != params.model_length {
+                    rejected!(round_id, PhaseName::Update);
+                    return future::ready(Err(ServiceError::InvalidModelLength {
+                        expected: params.model_length,
+                        actual,
+                    }));
                 }
+                future::ready(Ok(message))
             }
             _ => future::ready(Err(ServiceError::UnexpectedMessage)),
         }
@@ -148,4 +166,51 @@ mod tests {
             _ => panic!("expected ServiceError::NotSumEligible got {:?}", err),
         }
     }
+
+    /// Broadcasts update round parameters for which everyone is eligible for the
+    /// update task and no-one for the sum task.
+    fn update_round_params(
+        publisher: &mut EventPublisher,
+        subscriber: &EventSubscriber,
+    ) -> RoundParameters {
+        let mut round_params = subscriber.params_listener().get_latest().event;
+        round_params.sum = 0.0;
+        round_params.update = 1.0;
+        round_params.model_length = 4;
+        publisher.broadcast_params(round_params.clone());
+        publisher.broadcast_phase(PhaseName::Update);
+        round_params
+    }
+
+    #[tokio::test]
+    async fn test_update_ok() {
+        let (mut publisher, subscriber, mut task) = spawn_svc();
+        let round_params = update_round_params(&mut publisher, &subscriber);
+
+        let (message, _) = utils::new_update_message(&round_params, 4);
+
+        assert_ready!(task.poll_ready()).unwrap();
+        let resp = task.call(message.clone()).await.unwrap();
+        assert_eq!(resp, message);
+    }
+
+    #[tokio::test]
+    async fn test_update_invalid_model_length() {
+        let (mut publisher, subscriber, mut task) = spawn_svc();
+        let round_params = update_round_params(&mut publisher, &subscriber);
+
+        for &actual in &[3, 5] {
+            let (message, _) = utils::new_update_message(&round_params, actual);
+
+            assert_ready!(task.poll_ready()).unwrap();
+            let err = task.call(message).await.unwrap_err();
+            match err {
+                ServiceError::InvalidModelLength {
+                    expected: 4,
+                    actual: length,
+                } if length == actual => {}
+                _ => panic!("expected ServiceError::InvalidModelLength got {:?}", err),
+            }
+        }
+    }
 }
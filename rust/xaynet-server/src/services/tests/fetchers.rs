@@ -68,6 +68,8 @@ async fn test_round_params_svc() {
         seed: RoundSeed::fill_with(0x11),
         mask_config: mask_config().into(),
         model_length: 42,
+        plan_id: None,
+        max_sample_count: None,
     };
     publisher.broadcast_params(params.clone());
     assert_ready!(task.poll_ready()).unwrap();
@@ -0,0 +1,59 @@
+use crate::{
+    services::{
+        messages::{PetMessageHandler, ServiceError},
+        tests::utils::{encrypt_message, new_event_channels, new_sum_message},
+    },
+    state_machine::{phases::PhaseName, requests::RequestReceiver},
+};
+use xaynet_core::message::Tag;
+
+#[tokio::test]
+async fn test_unexpected_tag_is_not_decrypted() {
+    let (mut publisher, subscriber) = new_event_channels();
+    let (_receiver, requests_tx) = RequestReceiver::new();
+    let mut handler = PetMessageHandler::new(&subscriber, requests_tx);
+    publisher.broadcast_phase(PhaseName::Update);
+
+    // the message is not even a valid ciphertext: it is discarded before decryption
+    let garbage = vec![0, 1, 2, 3, 4, 5, 6];
+    match handler
+        .handle_message(Some(Tag::Sum), None, garbage.clone())
+        .await
+    {
+        Err(ServiceError::UnexpectedMessage) => {}
+        res => panic!("expected unexpected message error, got {:?}", res),
+    }
+
+    // legacy messages without tag are decrypted
+    match handler.handle_message(None, None, garbage).await {
+        Err(ServiceError::Decrypt) => {}
+        res => panic!("expected decrypt error, got {:?}", res),
+    }
+}
+
+#[tokio::test]
+async fn test_unexpected_legacy_message() {
+    let (mut publisher, subscriber) = new_event_channels();
+    let (_receiver, requests_tx) = RequestReceiver::new();
+    let mut handler = PetMessageHandler::new(&subscriber, requests_tx);
+    publisher.broadcast_phase(PhaseName::Update);
+
+    let round_params = subscriber.params_listener().get_latest().event;
+    let (message, participant_signing_keys) = new_sum_message(&round_params);
+    let encrypted = encrypt_message(&message, &round_params, &participant_signing_keys);
+
+    // a legacy message is still discarded once decrypted
+    match handler.handle_message(None, None, encrypted.clone()).await {
+        Err(ServiceError::UnexpectedMessage) => {}
+        res => panic!("expected unexpected message error, got {:?}", res),
+    }
+
+    // a sum message sent with a forged tag is discarded once decrypted
+    match handler
+        .handle_message(Some(Tag::Update), None, encrypted)
+        .await
+    {
+        Err(ServiceError::UnexpectedMessage) => {}
+        res => panic!("expected unexpected message error, got {:?}", res),
+    }
+}
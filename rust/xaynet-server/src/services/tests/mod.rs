@@ -1,2 +1,3 @@
 mod fetchers;
+mod messages;
 pub mod utils;
@@ -1,12 +1,15 @@
 use crate::state_machine::{
+    coordinator::RoundHistory,
     events::{EventPublisher, EventSubscriber, ModelUpdate},
     phases::PhaseName,
 };
+use num::{bigint::BigUint, traits::Zero};
 use xaynet_core::{
     common::{RoundParameters, RoundSeed},
     crypto::{ByteObject, EncryptKeyPair, PublicEncryptKey, SigningKeyPair},
-    mask::{self, MaskConfig},
-    message::{Message, Sum},
+    mask::{self, MaskConfig, MaskObject},
+    message::{Message, Sum, Update},
+    LocalSeedDict,
 };
 
 pub fn mask_config() -> MaskConfig {
@@ -30,11 +33,21 @@ pub fn new_event_channels() -> (EventPublisher, EventSubscriber) {
         seed: RoundSeed::generate(),
         mask_config: mask_config().into(),
         model_length: 0,
+        plan_id: None,
+        max_sample_count: None,
     };
     let phase = PhaseName::Idle;
     let round_id = 0;
     let model = ModelUpdate::Invalidate;
-    EventPublisher::init(round_id, keys, params, phase, model)
+    EventPublisher::init(
+        round_id,
+        keys,
+        params,
+        phase,
+        model,
+        None,
+        RoundHistory::default(),
+    )
 }
 
 /// Simulate a participant generating keys and crafting a valid sum
@@ -52,6 +65,33 @@ pub fn new_sum_message(round_params: &RoundParameters) -> (Message, SigningKeyPa
     (message, signing_keys)
 }
 
+/// Simulate a participant generating keys and crafting a valid update
+/// message with a masked model of `model_length` weights for the given
+/// round parameters. The keys generated by the participants are
+/// returned along with the message.
+pub fn new_update_message(
+    round_params: &RoundParameters,
+    model_length: usize,
+) -> (Message, SigningKeyPair) {
+    let signing_keys = SigningKeyPair::generate();
+    let seed = round_params.seed.as_slice();
+    let update = Update {
+        sum_signature: signing_keys.secret.sign_detached(&[seed, b"sum"].concat()),
+        update_signature: signing_keys
+            .secret
+            .sign_detached(&[seed, b"update"].concat()),
+        masked_model: MaskObject::new(
+            round_params.mask_config,
+            vec![BigUint::zero(); model_length],
+            BigUint::zero(),
+        )
+        .unwrap(),
+        local_seed_dict: LocalSeedDict::new(),
+    };
+    let message = Message::new_update(signing_keys.public, round_params.pk, update);
+    (message, signing_keys)
+}
+
 /// Sign and encrypt the given message using the given round
 /// parameters and particpant keys.
 pub fn encrypt_message(
@@ -3,9 +3,12 @@
 //! Values defined in the configuration file can be overridden by environment variables. Examples of
 //! configuration files can be found in the `configs/` directory located in the repository root.
 
-#[cfg(feature = "tls")]
-use std::path::PathBuf;
-use std::{fmt, path::Path};
+use std::{
+    fmt,
+    iter,
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+};
 
 use config::{Config, ConfigError, Environment, File};
 use displaydoc::Display;
@@ -15,11 +18,12 @@ use serde::{
     Deserialize,
 };
 use thiserror::Error;
+use tokio_postgres::Config as PostgresConfig;
 use tracing_subscriber::filter::EnvFilter;
 use validator::{Validate, ValidationError, ValidationErrors};
 
 use xaynet_core::{
-    mask::{BoundType, DataType, GroupType, MaskConfig, ModelType},
+    mask::{BoundType, DataType, GroupType, MaskConfig, ModelType, Scalar},
     message::{SUM_COUNT_MIN, UPDATE_COUNT_MIN},
 };
 
@@ -39,11 +43,23 @@ pub enum SettingsError {
 }
 
 #[derive(Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_coordinator_storage"))]
+#[validate(schema(function = "validate_audit_log_retention"))]
+#[validate(schema(function = "validate_sample_count_precision"))]
 /// The combined settings.
 ///
 /// Each section in the configuration file corresponds to the identically named settings field.
+/// Exactly one of the `[redis]` and `[postgres]` sections, or the `[in_memory]` section with the
+/// `in-memory-storage` feature, must be present, it selects the backend of the coordinator storage.
 pub struct Settings {
+    #[validate]
     pub api: ApiSettings,
+    #[serde(default)]
+    #[validate]
+    pub admin: Option<AdminSettings>,
+    #[serde(default)]
+    #[validate]
+    pub retention: Option<RetentionSettings>,
     #[validate]
     pub pet: PetSettings,
     pub mask: MaskSettings,
@@ -51,7 +67,13 @@ pub struct Settings {
     pub model: ModelSettings,
     #[validate]
     pub metrics: MetricsSettings,
-    pub redis: RedisSettings,
+    #[serde(default)]
+    pub redis: Option<RedisSettings>,
+    #[serde(default)]
+    pub postgres: Option<PostgresSettings>,
+    #[cfg(feature = "in-memory-storage")]
+    #[serde(default)]
+    pub in_memory: Option<InMemorySettings>,
     #[cfg(feature = "model-persistence")]
     #[validate]
     pub s3: S3Settings,
@@ -60,6 +82,12 @@ pub struct Settings {
     pub restore: RestoreSettings,
     #[serde(default)]
     pub trust_anchor: TrustAnchorSettings,
+    #[serde(default)]
+    #[validate]
+    pub shadow: Option<ShadowSettings>,
+    #[serde(default)]
+    #[validate]
+    pub training_plans: Vec<TrainingPlanSettings>,
 }
 
 impl Settings {
@@ -82,6 +110,59 @@ impl Settings {
     }
 }
 
+/// A wrapper for validate derive.
+fn validate_coordinator_storage(s: &Settings) -> Result<(), ValidationError> {
+    #[cfg(feature = "in-memory-storage")]
+    let in_memory = s.in_memory.is_some();
+    #[cfg(not(feature = "in-memory-storage"))]
+    let in_memory = false;
+
+    let configured = [s.redis.is_some(), s.postgres.is_some(), in_memory];
+    if configured.iter().filter(|configured| **configured).count() == 1 {
+        Ok(())
+    } else {
+        Err(ValidationError::new(
+            "exactly one coordinator storage is required",
+        ))
+    }
+}
+
+/// A wrapper for validate derive.
+fn validate_audit_log_retention(s: &Settings) -> Result<(), ValidationError> {
+    let retained = s
+        .retention
+        .is_some_and(|retention| retention.audit_log.is_some());
+    let audited = s
+        .admin
+        .as_ref()
+        .is_some_and(|admin| admin.audit_log.is_some());
+    if retained && !audited {
+        return Err(ValidationError::new(
+            "audit log retention requires an audit log",
+        ));
+    }
+    Ok(())
+}
+
+/// A wrapper for validate derive.
+fn validate_sample_count_precision(s: &Settings) -> Result<(), ValidationError> {
+    let max_sample_count = match s.pet.update.max_sample_count {
+        Some(max_sample_count) => max_sample_count.get(),
+        None => return Ok(()),
+    };
+    // the participants weight a single sample by this scalar, which must survive the masking
+    let single_sample = Scalar::new(1, max_sample_count);
+    let masks = iter::once(s.mask).chain(s.training_plans.iter().map(|plan| plan.mask));
+    for mask in masks {
+        if single_sample.masked(&mask.into()).is_zero() {
+            return Err(ValidationError::new(
+                "the maximal sample count exceeds the precision of the masking",
+            ));
+        }
+    }
+    Ok(())
+}
+
 /// The PET protocol count settings.
 #[derive(Debug, Deserialize, Clone, Copy)]
 #[cfg_attr(test, derive(PartialEq))]
@@ -243,6 +324,64 @@ pub struct PetSettingsUpdate {
     /// XAYNET__PET__UPDATE__TIME__MAX=10
     /// ```
     pub time: PetSettingsTime,
+
+    /// The maximal number of rounds a participant may take part in the `update` phase within a
+    /// sliding window, in seconds. The update messages of a participant that exceeded its quota
+    /// are rejected until its oldest participation leaves the window. If not set, the
+    /// participation of the participants is not limited.
+    ///
+    /// The participations are counted per participant public key in the coordinator storage,
+    /// under a hash of the public key, and expire with the window.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [pet.update.quota]
+    /// max = 10
+    /// window = 604800
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__PET__UPDATE__QUOTA__MAX=10
+    /// XAYNET__PET__UPDATE__QUOTA__WINDOW=604800
+    /// ```
+    #[serde(default)]
+    pub quota: Option<PetSettingsQuota>,
+
+    /// The sample count by which the update participants normalize the number of samples
+    /// their local models were trained on. The participants weight their local models by
+    /// their normalized sample count in the global model, and the larger sample counts are
+    /// capped to it. Set it close to the largest expected sample count, such that the small
+    /// sample counts keep their precision in the masked models. It must not exceed the precision
+    /// of the masking settings, i.e. a single sample must not be weighted by zero. If not set,
+    /// the participants normalize by their own default maximal sample count.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [pet.update]
+    /// max_sample_count = 10000
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__PET__UPDATE__MAX_SAMPLE_COUNT=10000
+    /// ```
+    #[serde(default)]
+    pub max_sample_count: Option<NonZeroU64>,
+}
+
+/// The PET protocol quota settings.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct PetSettingsQuota {
+    /// The maximal number of participations within the window.
+    pub max: u64,
+    /// The length of the sliding window, in seconds.
+    pub window: u64,
 }
 
 /// The PET protocol `sum2` phase settings.
@@ -299,6 +438,38 @@ pub struct PetSettingsSum2 {
     /// XAYNET__PET__SUM2__TIME__MAX=3600
     /// ```
     pub time: PetSettingsTime,
+
+    /// The tolerance for masks that differ from the best mask in a few elements. If not set,
+    /// the `unmask` phase fails if the best mask is not unique.
+    ///
+    /// The sum participants are expected to compute the exact same mask. If they don't agree on
+    /// a unique best mask or if its score is below `sum2.count.min`, the best mask is still
+    /// selected if the masks that differ from it in at most `max_distance` elements increase its
+    /// score to at least `sum2.count.min`. The round then completes, but the disagreement is
+    /// recorded in the round history and as a metric.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [pet.sum2.mask_tolerance]
+    /// max_distance = 1
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__PET__SUM2__MASK_TOLERANCE__MAX_DISTANCE=1
+    /// ```
+    #[serde(default)]
+    pub mask_tolerance: Option<PetSettingsMaskTolerance>,
+}
+
+/// The PET protocol mask tolerance settings.
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct PetSettingsMaskTolerance {
+    /// The maximal number of elements in which a mask may differ from the best mask.
+    pub max_distance: u64,
 }
 
 /// The PET protocol settings.
@@ -319,7 +490,9 @@ impl PetSettings {
     fn validate_pet(&self) -> Result<(), ValidationError> {
         self.validate_counts()?;
         self.validate_times()?;
-        self.validate_probabilities()
+        self.validate_probabilities()?;
+        self.validate_quota()?;
+        self.validate_mask_tolerance()
     }
 
     /// Checks the validity of phase count ranges.
@@ -354,16 +527,26 @@ impl PetSettings {
 
     /// Checks the validity of fraction ranges including pathological cases of deadlocks.
     fn validate_probabilities(&self) -> Result<(), ValidationError> {
-        if 0. < self.sum.prob
-            && self.sum.prob < 1.
-            && 0. < self.update.prob
-            && self.update.prob <= 1.
-            && 0. < self.sum.prob + self.update.prob - self.sum.prob * self.update.prob
-            && self.sum.prob + self.update.prob - self.sum.prob * self.update.prob <= 1.
-        {
-            Ok(())
-        } else {
-            Err(ValidationError::new("starvation"))
+        validate_probabilities(self.sum.prob, self.update.prob)
+    }
+
+    /// Checks the validity of the update quota.
+    fn validate_quota(&self) -> Result<(), ValidationError> {
+        match self.update.quota {
+            Some(PetSettingsQuota { max, window }) if max == 0 || window == 0 => {
+                Err(ValidationError::new("invalid update quota"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the validity of the mask tolerance.
+    fn validate_mask_tolerance(&self) -> Result<(), ValidationError> {
+        match self.sum2.mask_tolerance {
+            Some(PetSettingsMaskTolerance { max_distance: 0 }) => {
+                Err(ValidationError::new("invalid mask tolerance"))
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -373,12 +556,24 @@ fn validate_pet(s: &PetSettings) -> Result<(), ValidationError> {
     s.validate_pet()
 }
 
-#[derive(Debug, Deserialize, Clone)]
-#[cfg_attr(
-    feature = "tls",
-    derive(Validate),
-    validate(schema(function = "validate_api"))
-)]
+/// Checks the validity of the sum and update probabilities including pathological cases of
+/// deadlocks.
+fn validate_probabilities(sum: f64, update: f64) -> Result<(), ValidationError> {
+    if 0. < sum
+        && sum < 1.
+        && 0. < update
+        && update <= 1.
+        && 0. < sum + update - sum * update
+        && sum + update - sum * update <= 1.
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("starvation"))
+    }
+}
+
+#[derive(Debug, Validate, Deserialize, Clone)]
+#[validate(schema(function = "validate_api"))]
 /// REST API settings.
 ///
 /// Requires at least one of the following arguments if the `tls` feature is enabled:
@@ -466,25 +661,352 @@ pub struct ApiSettings {
     /// XAYNET__API__TLS_CLIENT_AUTH=path/to/tls/files/trust_anchor.pem
     /// ```
     pub tls_client_auth: Option<PathBuf>,
+
+    #[serde(default)]
+    /// Whether the responses to rejected PET messages include the detailed reason of the
+    /// rejection. This helps to debug participants, but leaks information about the
+    /// coordinator, so it can only be enabled together with `non_production`. Defaults
+    /// to `false`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// debug_rejections = true
+    /// non_production = true
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__DEBUG_REJECTIONS=true
+    /// ```
+    pub debug_rejections: bool,
+
+    #[serde(default)]
+    /// Marks the coordinator as a non-production deployment, which is required to
+    /// enable `debug_rejections`. Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// non_production = true
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__NON_PRODUCTION=true
+    /// ```
+    pub non_production: bool,
+
+    #[serde(default = "default_http2")]
+    /// Whether the REST API accepts HTTP/2 connections in addition to HTTP/1.1 ones.
+    /// Participants reuse a HTTP/2 connection for all their requests, which saves
+    /// connection setups and TLS handshakes. Defaults to `true`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// http2 = false
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__HTTP2=false
+    /// ```
+    pub http2: bool,
+
+    #[serde(default)]
+    /// The number of seconds after which a connection without any traffic is closed. Leave
+    /// this out to keep idle connections open until the participant closes them.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// keep_alive_timeout = 90
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__KEEP_ALIVE_TIMEOUT=90
+    /// ```
+    pub keep_alive_timeout: Option<u64>,
+
+    #[serde(default)]
+    /// The maximum number of concurrent requests on a single HTTP/2 connection. Leave this
+    /// out to use the default of the HTTP/2 server.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// max_concurrent_streams = 16
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__MAX_CONCURRENT_STREAMS=16
+    /// ```
+    pub max_concurrent_streams: Option<u32>,
+
+    #[serde(default)]
+    /// Whether the REST API accepts gzip compressed PET messages and compresses its
+    /// responses with gzip for the participants that accept it. Participants that don't
+    /// use gzip are served as before. Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// gzip = true
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__GZIP=true
+    /// ```
+    pub gzip: bool,
+
+    #[serde(default = "default_gzip_threshold")]
+    /// The minimum length in bytes of the responses which are compressed if `gzip` is
+    /// enabled. Compressing smaller responses isn't worth the CPU time. Defaults to `1024`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// gzip_threshold = 4096
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__GZIP_THRESHOLD=4096
+    /// ```
+    pub gzip_threshold: usize,
+
+    #[serde(default = "default_public_stats_rate_limit")]
+    /// The maximum number of requests per second which are served by the public statistics
+    /// endpoint `/stats/public`. Further requests are answered with `429 Too Many Requests`.
+    /// Defaults to `100`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// public_stats_rate_limit = 20
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__PUBLIC_STATS_RATE_LIMIT=20
+    /// ```
+    pub public_stats_rate_limit: u32,
+
+    #[serde(default)]
+    /// Whether the REST API pushes the events of the rounds to the participants over a
+    /// WebSocket at `/events`, so that they don't need to poll for the round parameters
+    /// and dictionaries. Defaults to `false`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [api]
+    /// events = true
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__API__EVENTS=true
+    /// ```
+    pub events: bool,
+}
+
+fn default_http2() -> bool {
+    true
+}
+
+fn default_gzip_threshold() -> usize {
+    1024
+}
+
+fn default_public_stats_rate_limit() -> u32 {
+    100
 }
 
-#[cfg(feature = "tls")]
 impl ApiSettings {
     /// Checks API settings.
     fn validate_api(&self) -> Result<(), ValidationError> {
+        #[cfg(feature = "tls")]
         match (&self.tls_certificate, &self.tls_key, &self.tls_client_auth) {
-            (Some(_), Some(_), _) | (None, None, Some(_)) => Ok(()),
-            _ => Err(ValidationError::new("invalid tls settings")),
+            (Some(_), Some(_), _) | (None, None, Some(_)) => {}
+            _ => return Err(ValidationError::new("invalid tls settings")),
         }
+        if self.debug_rejections && !self.non_production {
+            return Err(ValidationError::new(
+                "debug rejections require a non-production deployment",
+            ));
+        }
+        if self.keep_alive_timeout == Some(0) {
+            return Err(ValidationError::new("keep alive timeout must be positive"));
+        }
+        if self.max_concurrent_streams == Some(0) {
+            return Err(ValidationError::new(
+                "max concurrent streams must be positive",
+            ));
+        }
+        if self.public_stats_rate_limit == 0 {
+            return Err(ValidationError::new(
+                "public stats rate limit must be positive",
+            ));
+        }
+        Ok(())
     }
 }
 
 /// A wrapper for validate derive.
-#[cfg(feature = "tls")]
 fn validate_api(s: &ApiSettings) -> Result<(), ValidationError> {
     s.validate_api()
 }
 
+#[derive(Debug, Validate, Deserialize)]
+/// Administrative API settings.
+///
+/// If the `[admin]` section is present, the REST API serves the administrative routes under
+/// `/admin` (see [`Admin`]), otherwise they are not found.
+///
+/// [`Admin`]: crate::rest::Admin
+pub struct AdminSettings {
+    #[validate(length(min = 1))]
+    /// The token which authenticates the requests to the administrative routes. It must be sent
+    /// as a bearer token in the `Authorization` header.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [admin]
+    /// token = "0123456789abcdef"
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__ADMIN__TOKEN=0123456789abcdef
+    /// ```
+    pub token: String,
+
+    #[serde(default)]
+    /// The path of the append-only audit log of the administrative actions (see [`audit`]). If
+    /// it is set, an action is only executed once it is recorded in the audit log.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [admin]
+    /// audit_log = "/var/log/xaynet/audit.log"
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__ADMIN__AUDIT_LOG=/var/log/xaynet/audit.log
+    /// ```
+    ///
+    /// [`audit`]: crate::audit
+    pub audit_log: Option<PathBuf>,
+}
+
+#[derive(Debug, Validate, Deserialize, Clone, Copy)]
+/// Retention settings.
+///
+/// If the `[retention]` section is present, the coordinator periodically purges the records
+/// which are older than the retention duration of their data class, once they are exported to
+/// the model storage (see [`retention`]). A purge cycle can also be triggered with the
+/// administrative route `POST /admin/retention/purge` (see [`Admin`]).
+///
+/// The audit log of the `[admin]` settings is the only data class which accumulates records.
+/// The coordinator data of a round is deleted at the start of the next round, and the global
+/// models and round archives are the archival output itself.
+///
+/// [`retention`]: crate::retention
+/// [`Admin`]: crate::rest::Admin
+pub struct RetentionSettings {
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    /// The retention duration of the records of the audit log, in seconds. If it is not set, the
+    /// records are kept forever.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [retention]
+    /// audit_log = 31536000
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__RETENTION__AUDIT_LOG=31536000
+    /// ```
+    pub audit_log: Option<u64>,
+
+    #[serde(default = "default_retention_interval")]
+    #[validate(range(min = 1))]
+    /// The interval between two purge cycles, in seconds. Defaults to an hour.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [retention]
+    /// interval = 3600
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__RETENTION__INTERVAL=3600
+    /// ```
+    pub interval: u64,
+
+    #[serde(default = "default_retention_max_records")]
+    #[validate(range(min = 1))]
+    /// The maximum number of records of a data class which are purged in a single purge cycle,
+    /// which bounds the work of a cycle. The remaining expired records are purged in the
+    /// following cycles. Defaults to `1000`.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [retention]
+    /// max_records = 1000
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__RETENTION__MAX_RECORDS=1000
+    /// ```
+    pub max_records: usize,
+}
+
+fn default_retention_interval() -> u64 {
+    3600
+}
+
+fn default_retention_max_records() -> usize {
+    1000
+}
+
 #[derive(Debug, Validate, Deserialize, Clone, Copy)]
 #[cfg_attr(test, derive(PartialEq, Eq))]
 /// Masking settings.
@@ -523,6 +1045,10 @@ pub struct MaskSettings {
 
     /// The bounds of the numbers to be masked.
     ///
+    /// This also bounds the scalars which weight the local models of the update participants.
+    /// The scalars are masked like the models, hence the coordinator can't validate the scalar of
+    /// an individual update against tighter bounds before the aggregation.
+    ///
     /// # Examples
     ///
     /// **TOML**
@@ -592,6 +1118,150 @@ pub struct ModelSettings {
     /// XAYNET__MODEL__LENGTH=100
     /// ```
     pub length: usize,
+
+    /// Whether to compute statistics about the change of the global model at the end of each
+    /// round, i.e. the L2 norm of the difference between two consecutive global models and the
+    /// fraction of weights which changed their sign. The statistics are emitted as metrics and
+    /// recorded in the round history. They require a full pass over the model and are therefore
+    /// disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [model]
+    /// update_statistics = true
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__MODEL__UPDATE_STATISTICS=true
+    /// ```
+    #[serde(default)]
+    pub update_statistics: bool,
+
+    /// Whether to export an archive of the aggregation inputs at the end of each round, which
+    /// allows auditors to reproduce the unmasking of the global model offline (see
+    /// [`round_archive`]). The archive contains the aggregated masked model and the aggregated
+    /// mask, but not the masked models or the seeds of individual participants. It is written to
+    /// the model storage alongside the global model. Disabled by default.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [model]
+    /// export_round_archive = true
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__MODEL__EXPORT_ROUND_ARCHIVE=true
+    /// ```
+    ///
+    /// [`round_archive`]: crate::round_archive
+    #[serde(default)]
+    pub export_round_archive: bool,
+}
+
+#[derive(Debug, Validate, Deserialize, Clone, Copy)]
+#[cfg_attr(test, derive(PartialEq))]
+/// Shadow mode settings.
+///
+/// A candidate PET and masking configuration which is evaluated alongside the live one. The
+/// coordinator counts the participants the candidate probabilities would have selected among the
+/// participants who sent messages in a round and computes the size of a masked model and the
+/// aggregation capacity under the candidate masking configuration. The evaluation is recorded in
+/// the metrics and the logs at the end of each round and never affects the live round.
+///
+/// # Examples
+///
+/// **TOML**
+/// ```text
+/// [shadow.pet.sum]
+/// prob = 0.02
+/// # ...
+///
+/// [shadow.mask]
+/// group_type = "Prime"
+/// data_type = "F32"
+/// bound_type = "B0"
+/// model_type = "M6"
+/// ```
+///
+/// **Environment variable**
+/// ```text
+/// XAYNET__SHADOW__PET__SUM__PROB=0.02
+/// XAYNET__SHADOW__MASK__MODEL_TYPE=M6
+/// ```
+pub struct ShadowSettings {
+    /// The candidate PET settings. Only the probabilities take part in the evaluation, the other
+    /// settings are validated like the live ones so that the candidate can be adopted as is.
+    #[validate]
+    pub pet: PetSettings,
+    /// The candidate masking settings.
+    pub mask: MaskSettings,
+}
+
+#[derive(Debug, Validate, Deserialize, Clone)]
+#[cfg_attr(test, derive(PartialEq))]
+#[validate(schema(function = "validate_training_plan"))]
+/// Training plan settings.
+///
+/// The coordinator runs the training plans one after another: each plan runs for a fixed number
+/// of rounds with its own model length, masking configuration and selection probabilities. Once
+/// the last round of a plan completed, the coordinator switches to the next plan with fresh keys
+/// and without a global model, and it shuts down after the last plan. The id of the current plan
+/// is served to the participants under the `/plan` route.
+///
+/// The first plan replaces the model length, the masking settings and the selection
+/// probabilities of the `[model]`, `[mask]` and `[pet]` sections, which still provide the other
+/// settings. Without training plans, the coordinator runs rounds with the settings of these
+/// sections until it is stopped.
+///
+/// # Examples
+///
+/// **TOML**
+/// ```text
+/// [[training_plans]]
+/// id = "model-a"
+/// rounds = 200
+/// model_length = 100
+/// sum_prob = 0.01
+/// update_prob = 0.1
+/// mask = { group_type = "Prime", data_type = "F32", bound_type = "B0", model_type = "M3" }
+///
+/// [[training_plans]]
+/// id = "model-b"
+/// rounds = 50
+/// model_length = 1000
+/// sum_prob = 0.02
+/// update_prob = 0.1
+/// mask = { group_type = "Prime", data_type = "F64", bound_type = "B0", model_type = "M3" }
+/// ```
+///
+/// The training plans can't be set with environment variables.
+pub struct TrainingPlanSettings {
+    /// The id of the plan.
+    pub id: String,
+    /// The number of rounds of the plan. Must be at least `1`.
+    pub rounds: u64,
+    /// The length of the model.
+    pub model_length: usize,
+    /// The probability of participants to be selected for the sum task.
+    pub sum_prob: f64,
+    /// The probability of participants to be selected for the update task.
+    pub update_prob: f64,
+    /// The masking settings.
+    pub mask: MaskSettings,
+}
+
+/// A wrapper for validate derive.
+fn validate_training_plan(s: &TrainingPlanSettings) -> Result<(), ValidationError> {
+    if s.rounds == 0 {
+        return Err(ValidationError::new("invalid training plan round count"));
+    }
+    validate_probabilities(s.sum_prob, s.update_prob)
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -600,6 +1270,32 @@ pub struct MetricsSettings {
     #[validate]
     /// Settings for the InfluxDB backend.
     pub influxdb: InfluxSettings,
+    #[cfg(feature = "prometheus")]
+    #[serde(default)]
+    /// Settings for the Prometheus backend. If set, the metrics are exported to Prometheus
+    /// instead of InfluxDB.
+    pub prometheus: Option<PrometheusSettings>,
+}
+
+#[cfg(feature = "prometheus")]
+#[derive(Debug, Deserialize)]
+/// Prometheus settings.
+pub struct PrometheusSettings {
+    /// The address to which the Prometheus scrape endpoint `/metrics` should be bound.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [metrics.prometheus]
+    /// bind_address = "127.0.0.1:9090"
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__METRICS__PROMETHEUS__BIND_ADDRESS=127.0.0.1:9090
+    /// ```
+    pub bind_address: std::net::SocketAddr,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -691,6 +1387,75 @@ where
     deserializer.deserialize_str(ConnectionInfoVisitor)
 }
 
+#[derive(Debug, Deserialize)]
+/// PostgreSQL settings.
+pub struct PostgresSettings {
+    /// The URL where PostgreSQL is running.
+    ///
+    /// The format of the URL is `postgres://[<username>][:<passwd>@]<hostname>[:port][/<db>]`.
+    /// The connection is not encrypted.
+    ///
+    /// # Examples
+    ///
+    /// **TOML**
+    /// ```text
+    /// [postgres]
+    /// url = "postgres://xaynet@127.0.0.1/xaynet"
+    /// ```
+    ///
+    /// **Environment variable**
+    /// ```text
+    /// XAYNET__POSTGRES__URL=postgres://xaynet@127.0.0.1/xaynet
+    /// ```
+    #[serde(deserialize_with = "deserialize_postgres_url")]
+    pub url: PostgresConfig,
+}
+
+#[cfg(feature = "in-memory-storage")]
+#[derive(Debug, Deserialize)]
+/// In-memory storage settings.
+///
+/// The coordinator data is kept in the memory of the coordinator process, hence it is lost when
+/// the coordinator stops. This is meant for tests and single-node deployments. The section has
+/// no settings, its presence selects the backend.
+///
+/// # Examples
+///
+/// **TOML**
+/// ```text
+/// [in_memory]
+/// ```
+pub struct InMemorySettings {}
+
+fn deserialize_postgres_url<'de, D>(deserializer: D) -> Result<PostgresConfig, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct PostgresConfigVisitor;
+
+    impl<'de> Visitor<'de> for PostgresConfigVisitor {
+        type Value = PostgresConfig;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            write!(
+                formatter,
+                "postgres://[<username>][:<passwd>@]<hostname>[:port][/<db>]"
+            )
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            value
+                .parse()
+                .map_err(|_| de::Error::invalid_value(serde::de::Unexpected::Str(value), &self))
+        }
+    }
+
+    deserializer.deserialize_str(PostgresConfigVisitor)
+}
+
 #[derive(Debug, Deserialize, Validate)]
 /// Trust anchor settings.
 pub struct TrustAnchorSettings {}
@@ -776,6 +1541,8 @@ mod tests {
                         min: 0,
                         max: 604800,
                     },
+                    quota: None,
+                    max_sample_count: None,
                 },
                 sum2: PetSettingsSum2 {
                     count: PetSettingsCount { min: 10, max: 100 },
@@ -783,6 +1550,7 @@ mod tests {
                         min: 0,
                         max: 604800,
                     },
+                    mask_tolerance: None,
                 },
             }
         }
@@ -805,6 +1573,50 @@ mod tests {
         assert!(Settings::new("").is_err());
     }
 
+    #[test]
+    fn test_validate_coordinator_storage() {
+        let mut settings = Settings::load("../../configs/config.toml").unwrap();
+        assert!(settings.validate().is_ok());
+
+        let postgres = PostgresSettings {
+            url: "postgres://xaynet@127.0.0.1/xaynet".parse().unwrap(),
+        };
+        settings.postgres = Some(postgres);
+        assert!(settings.validate().is_err());
+
+        settings.redis = None;
+        assert!(settings.validate().is_ok());
+
+        settings.postgres = None;
+        assert!(settings.validate().is_err());
+    }
+
+    #[cfg(feature = "in-memory-storage")]
+    #[test]
+    fn test_validate_coordinator_storage_in_memory() {
+        let mut settings = Settings::load("../../configs/config.toml").unwrap();
+        settings.in_memory = Some(InMemorySettings {});
+        assert!(settings.validate().is_err());
+
+        settings.redis = None;
+        assert!(settings.validate().is_ok());
+    }
+
+    #[cfg(feature = "in-memory-storage")]
+    #[test]
+    fn test_in_memory_settings_from_config() {
+        let config = r#"
+            [in_memory]
+        "#;
+        let settings = Config::builder()
+            .add_source(File::from_str(config, config::FileFormat::Toml))
+            .build()
+            .unwrap()
+            .get::<Option<InMemorySettings>>("in_memory")
+            .unwrap();
+        assert!(settings.is_some());
+    }
+
     #[test]
     fn test_validate_pet() {
         assert!(PetSettings::default().validate_pet().is_ok());
@@ -890,6 +1702,59 @@ mod tests {
         assert!(pet.validate().is_err());
     }
 
+    #[test]
+    fn test_validate_pet_quota() {
+        let quota = |max, window| {
+            let mut pet = PetSettings::default();
+            pet.update.quota = Some(PetSettingsQuota { max, window });
+            pet
+        };
+        assert!(quota(1, 1).validate().is_ok());
+        assert!(quota(0, 604800).validate().is_err());
+        assert!(quota(10, 0).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_pet_mask_tolerance() {
+        let mut pet = PetSettings::default();
+        pet.sum2.mask_tolerance = Some(PetSettingsMaskTolerance { max_distance: 1 });
+        assert!(pet.validate().is_ok());
+        pet.sum2.mask_tolerance = Some(PetSettingsMaskTolerance { max_distance: 0 });
+        assert!(pet.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_shadow() {
+        let mut shadow = ShadowSettings {
+            pet: PetSettings::default(),
+            mask: MaskSettings::default(),
+        };
+        assert!(shadow.validate().is_ok());
+
+        shadow.pet.sum.prob = 1.;
+        assert!(shadow.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_training_plan() {
+        let mut plan = TrainingPlanSettings {
+            id: "model-a".into(),
+            rounds: 1,
+            model_length: 4,
+            sum_prob: 0.5,
+            update_prob: 0.5,
+            mask: MaskSettings::default(),
+        };
+        assert!(plan.validate().is_ok());
+
+        plan.sum_prob = 1.;
+        assert!(plan.validate().is_err());
+
+        plan.sum_prob = 0.5;
+        plan.rounds = 0;
+        assert!(plan.validate().is_err());
+    }
+
     #[cfg(feature = "tls")]
     #[test]
     fn test_validate_api() {
@@ -901,6 +1766,15 @@ mod tests {
             tls_certificate: some_path.clone(),
             tls_key: some_path.clone(),
             tls_client_auth: some_path.clone(),
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_ok());
@@ -909,6 +1783,15 @@ mod tests {
             tls_certificate: some_path.clone(),
             tls_key: some_path.clone(),
             tls_client_auth: None,
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_ok());
@@ -917,6 +1800,15 @@ mod tests {
             tls_certificate: None,
             tls_key: None,
             tls_client_auth: some_path.clone(),
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_ok());
@@ -926,6 +1818,15 @@ mod tests {
             tls_certificate: some_path.clone(),
             tls_key: None,
             tls_client_auth: some_path.clone(),
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_err());
@@ -934,6 +1835,15 @@ mod tests {
             tls_certificate: None,
             tls_key: some_path.clone(),
             tls_client_auth: some_path.clone(),
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_err());
@@ -942,6 +1852,15 @@ mod tests {
             tls_certificate: some_path.clone(),
             tls_key: None,
             tls_client_auth: None,
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_err());
@@ -950,6 +1869,15 @@ mod tests {
             tls_certificate: None,
             tls_key: some_path,
             tls_client_auth: None,
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_err());
@@ -958,8 +1886,181 @@ mod tests {
             tls_certificate: None,
             tls_key: None,
             tls_client_auth: None,
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
         }
         .validate()
         .is_err());
     }
+
+    #[test]
+    fn test_validate_api_debug_rejections() {
+        let api = |debug_rejections, non_production| ApiSettings {
+            bind_address: ([0, 0, 0, 0], 0).into(),
+            #[cfg(feature = "tls")]
+            tls_certificate: Some(std::path::PathBuf::new()),
+            #[cfg(feature = "tls")]
+            tls_key: Some(std::path::PathBuf::new()),
+            #[cfg(feature = "tls")]
+            tls_client_auth: None,
+            debug_rejections,
+            non_production,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
+        };
+
+        assert!(api(false, false).validate().is_ok());
+        assert!(api(false, true).validate().is_ok());
+        assert!(api(true, true).validate().is_ok());
+        assert!(api(true, false).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_api_connections() {
+        let api = |keep_alive_timeout, max_concurrent_streams| ApiSettings {
+            bind_address: ([0, 0, 0, 0], 0).into(),
+            #[cfg(feature = "tls")]
+            tls_certificate: Some(std::path::PathBuf::new()),
+            #[cfg(feature = "tls")]
+            tls_key: Some(std::path::PathBuf::new()),
+            #[cfg(feature = "tls")]
+            tls_client_auth: None,
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout,
+            max_concurrent_streams,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit: 100,
+            events: false,
+        };
+
+        assert!(api(None, None).validate().is_ok());
+        assert!(api(Some(90), Some(16)).validate().is_ok());
+        assert!(api(Some(0), None).validate().is_err());
+        assert!(api(None, Some(0)).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_api_public_stats_rate_limit() {
+        let api = |public_stats_rate_limit| ApiSettings {
+            bind_address: ([0, 0, 0, 0], 0).into(),
+            #[cfg(feature = "tls")]
+            tls_certificate: Some(std::path::PathBuf::new()),
+            #[cfg(feature = "tls")]
+            tls_key: Some(std::path::PathBuf::new()),
+            #[cfg(feature = "tls")]
+            tls_client_auth: None,
+            debug_rejections: false,
+            non_production: false,
+            http2: true,
+            keep_alive_timeout: None,
+            max_concurrent_streams: None,
+            gzip: false,
+            gzip_threshold: 1024,
+            public_stats_rate_limit,
+            events: false,
+        };
+
+        assert!(api(100).validate().is_ok());
+        assert!(api(0).validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_admin() {
+        let mut settings = Settings::load("../../configs/config.toml").unwrap();
+        assert!(settings.admin.is_none());
+
+        settings.admin = Some(AdminSettings {
+            token: "0123456789abcdef".to_string(),
+            audit_log: None,
+        });
+        assert!(settings.validate().is_ok());
+
+        settings.admin = Some(AdminSettings {
+            token: String::new(),
+            audit_log: None,
+        });
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_retention() {
+        let mut settings = Settings::load("../../configs/config.toml").unwrap();
+        assert!(settings.retention.is_none());
+
+        let retention = RetentionSettings {
+            audit_log: Some(86400),
+            interval: default_retention_interval(),
+            max_records: default_retention_max_records(),
+        };
+        settings.retention = Some(retention);
+        // there is no audit log to apply the retention to
+        assert!(settings.validate().is_err());
+
+        settings.admin = Some(AdminSettings {
+            token: "0123456789abcdef".to_string(),
+            audit_log: Some(PathBuf::from("audit.log")),
+        });
+        assert!(settings.validate().is_ok());
+
+        for invalid in &[
+            RetentionSettings {
+                audit_log: Some(0),
+                ..retention
+            },
+            RetentionSettings {
+                interval: 0,
+                ..retention
+            },
+            RetentionSettings {
+                max_records: 0,
+                ..retention
+            },
+        ] {
+            settings.retention = Some(*invalid);
+            assert!(settings.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_validate_sample_count_precision() {
+        let mut settings = Settings::load("../../configs/config.toml").unwrap();
+        // the scalars are masked with a precision of 10 decimal digits
+        settings.pet.update.max_sample_count = NonZeroU64::new(10_000_000_000);
+        assert!(settings.validate().is_ok());
+        settings.pet.update.max_sample_count = NonZeroU64::new(1_000_000_000_000);
+        assert!(settings.validate().is_err());
+
+        // the scalars of a training plan are masked with a precision of 5 decimal digits
+        settings.pet.update.max_sample_count = NonZeroU64::new(1_000_000);
+        assert!(settings.validate().is_ok());
+        settings.training_plans = vec![TrainingPlanSettings {
+            id: "model-a".to_string(),
+            rounds: 1,
+            model_length: 1,
+            sum_prob: 0.5,
+            update_prob: 0.5,
+            mask: MaskSettings {
+                data_type: DataType::F16,
+                ..settings.mask
+            },
+        }];
+        assert!(settings.validate().is_err());
+        settings.pet.update.max_sample_count = NonZeroU64::new(100_000);
+        assert!(settings.validate().is_ok());
+    }
 }
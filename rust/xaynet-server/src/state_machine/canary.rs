@@ -0,0 +1,288 @@
+//! Canary rounds.
+//!
+//! Before a protocol or configuration change is rolled out, the coordinator can run a canary
+//! round. A canary round executes end to end, i.e. the messages are accepted, the masked models
+//! are aggregated and the global model is unmasked, but the global model is stored for analysis
+//! only and is not published to the participants: they keep receiving the previous global model.
+//!
+//! A canary round is scheduled with a [`CanarySwitch`] (see
+//! [`StateMachineInitializer::with_canary_switch()`]), which the coordinator toggles at
+//! `/admin/canary` if the administrative routes are enabled (see [`Admin`]). The global model of
+//! a canary round is stored with [`ModelStorage::set_canary_global_model()`], the latest global
+//! model id is not updated, the round archive (if exported) is marked as canary and the round is
+//! recorded in the [`Measurement::RoundCanary`] metric.
+//!
+//! [`StateMachineInitializer::with_canary_switch()`]: crate::state_machine::initializer::StateMachineInitializer::with_canary_switch
+//! [`ModelStorage::set_canary_global_model()`]: crate::storage::ModelStorage::set_canary_global_model
+//! [`Measurement::RoundCanary`]: crate::metrics::Measurement::RoundCanary
+//! [`Admin`]: crate::rest::Admin
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A switch to run the upcoming round as a canary round.
+///
+/// Clones of the switch control the same state machine.
+#[derive(Clone, Debug, Default)]
+pub struct CanarySwitch {
+    upcoming: Arc<AtomicBool>,
+}
+
+impl CanarySwitch {
+    /// Creates a new switch which doesn't schedule any canary round.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether the upcoming round is a canary round.
+    ///
+    /// The flag is consumed when the upcoming round starts, the rounds after it are normal
+    /// rounds again unless the flag is set again. The current round is never affected.
+    pub fn set_upcoming(&self, canary: bool) {
+        self.upcoming.store(canary, Ordering::SeqCst);
+    }
+
+    /// Checks whether the upcoming round is a canary round.
+    pub fn is_upcoming(&self) -> bool {
+        self.upcoming.load(Ordering::SeqCst)
+    }
+
+    /// Consumes the flag of the upcoming round when it starts.
+    pub(in crate::state_machine) fn take(&self) -> bool {
+        self.upcoming.swap(false, Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use tokio::task::JoinHandle;
+    use xaynet_core::{message::Message, SeedDict, SumDict};
+
+    use crate::{
+        round_archive::{self, RoundManifest},
+        state_machine::{
+            events::{DictionaryUpdate, EventSubscriber, ModelUpdate},
+            phases::{Idle, PhaseName, PhaseState},
+            requests::{RequestError, RequestSender},
+            tests::{
+                utils::{
+                    compose_sum2_message,
+                    compose_sum_message,
+                    compose_update_message,
+                    enable_logging,
+                    init_shared,
+                },
+                CoordinatorStateBuilder,
+                EventBusBuilder,
+            },
+            StateMachine,
+        },
+        storage::{
+            tests::{utils::create_mask, MockCoordinatorStore, MockModelStore},
+            LocalSeedDictAdd,
+            MaskScoreIncr,
+            Storage,
+            Store,
+            SumPartAdd,
+        },
+    };
+
+    const SUM_MESSAGES: u64 = 3;
+    const UPDATE_MESSAGES: u64 = 4;
+
+    /// The artifacts written to the model storage and the latest global model ids.
+    #[derive(Debug, Default)]
+    struct Artifacts {
+        /// The rounds of the stored global models, with their canary markers.
+        #[cfg_attr(not(feature = "model-persistence"), allow(dead_code))]
+        global_models: Vec<(u64, bool)>,
+        /// The manifests of the exported round archives.
+        archives: Vec<RoundManifest>,
+        /// The latest global model ids that were set.
+        #[cfg_attr(not(feature = "model-persistence"), allow(dead_code))]
+        latest_global_model_ids: Vec<String>,
+    }
+
+    fn store(artifacts: Arc<Mutex<Artifacts>>) -> impl Storage {
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_delete_dicts().returning(|| Ok(()));
+        cs.expect_set_coordinator_state().returning(|_| Ok(()));
+        cs.expect_add_sum_participant()
+            .returning(|_, _| Ok(SumPartAdd(Ok(()))));
+        cs.expect_sum_dict().returning(|| Ok(Some(SumDict::new())));
+        cs.expect_add_local_seed_dict()
+            .returning(|_, _| Ok(LocalSeedDictAdd(Ok(()))));
+        cs.expect_seed_dict()
+            .returning(|| Ok(Some(SeedDict::new())));
+        cs.expect_incr_mask_score()
+            .returning(|_, _| Ok(MaskScoreIncr(Ok(()))));
+        cs.expect_best_masks()
+            .returning(|| Ok(Some(vec![(create_mask(1, 1), SUM_MESSAGES)])));
+        #[cfg(feature = "model-persistence")]
+        {
+            let latest = artifacts.clone();
+            cs.expect_set_latest_global_model_id().returning(move |id| {
+                latest
+                    .lock()
+                    .unwrap()
+                    .latest_global_model_ids
+                    .push(id.to_string());
+                Ok(())
+            });
+        }
+
+        let mut ms = MockModelStore::new();
+        #[cfg(feature = "model-persistence")]
+        {
+            let stored = artifacts.clone();
+            ms.expect_set_global_model()
+                .returning(move |round_id, _, _| {
+                    stored.lock().unwrap().global_models.push((round_id, false));
+                    Ok(format!("model_{}", round_id))
+                });
+            let stored = artifacts.clone();
+            ms.expect_set_canary_global_model()
+                .returning(move |round_id, _, _| {
+                    stored.lock().unwrap().global_models.push((round_id, true));
+                    Ok(format!("canary_model_{}", round_id))
+                });
+        }
+        ms.expect_set_round_archive().returning(move |_, _, bytes| {
+            let (manifest, _) = round_archive::verify(&bytes).unwrap();
+            artifacts.lock().unwrap().archives.push(manifest);
+            Ok(())
+        });
+        Store::new(cs, ms)
+    }
+
+    /// Runs a round from the idle phase to the idle phase of the next round.
+    async fn run_round<T: Storage>(
+        state_machine: StateMachine<T>,
+        request_tx: &RequestSender,
+    ) -> StateMachine<T> {
+        assert!(state_machine.is_idle());
+        let send = |messages: Vec<Message>| -> Vec<JoinHandle<Result<(), RequestError>>> {
+            messages
+                .into_iter()
+                .map(|message| {
+                    let request_tx = request_tx.clone();
+                    tokio::spawn(async move { request_tx.msg(&message).await })
+                })
+                .collect()
+        };
+        let mut pending = Vec::new();
+
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_sum());
+
+        pending.extend(send(
+            (0..SUM_MESSAGES).map(|_| compose_sum_message()).collect(),
+        ));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_update());
+
+        pending.extend(send(
+            (0..UPDATE_MESSAGES)
+                .map(|_| compose_update_message(create_mask(1, 1)))
+                .collect(),
+        ));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_sum2());
+
+        pending.extend(send(
+            (0..SUM_MESSAGES).map(|_| compose_sum2_message()).collect(),
+        ));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_unmask());
+
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_idle());
+        for response in pending {
+            response.await.unwrap().unwrap();
+        }
+        state_machine
+    }
+
+    fn served_model(event_subscriber: &EventSubscriber) -> Option<Arc<xaynet_core::mask::Model>> {
+        match event_subscriber.model_listener().get_latest().event {
+            ModelUpdate::New(model) => Some(model),
+            ModelUpdate::Invalidate => None,
+        }
+    }
+
+    #[test]
+    fn test_switch() {
+        let switch = CanarySwitch::new();
+        assert!(!switch.is_upcoming());
+
+        let clone = switch.clone();
+        clone.set_upcoming(true);
+        assert!(switch.is_upcoming());
+
+        assert!(switch.take());
+        assert!(!switch.is_upcoming());
+        assert!(!switch.take());
+    }
+
+    #[tokio::test]
+    async fn test_canary_round_followed_by_normal_round() {
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(0)
+            .with_sum_count_min(SUM_MESSAGES)
+            .with_sum_count_max(SUM_MESSAGES)
+            .with_sum_time_min(0)
+            .with_update_count_min(UPDATE_MESSAGES)
+            .with_update_count_max(UPDATE_MESSAGES)
+            .with_update_time_min(0)
+            .with_sum2_count_min(SUM_MESSAGES)
+            .with_sum2_count_max(SUM_MESSAGES)
+            .with_sum2_time_min(0)
+            .with_export_round_archive(true)
+            .build();
+        let (event_publisher, event_subscriber) = EventBusBuilder::new(&state)
+            .broadcast_phase(PhaseName::Idle)
+            .broadcast_sum_dict(DictionaryUpdate::Invalidate)
+            .broadcast_seed_dict(DictionaryUpdate::Invalidate)
+            .broadcast_model(ModelUpdate::Invalidate)
+            .build();
+        let artifacts = Arc::new(Mutex::new(Artifacts::default()));
+        let switch = CanarySwitch::new();
+        let (shared, request_tx) = init_shared(state, store(artifacts.clone()), event_publisher);
+        let shared = shared.with_canary_switch(switch.clone());
+
+        // the first round is a canary round: it is not published
+        switch.set_upcoming(true);
+        let state_machine = StateMachine::from(PhaseState::<Idle, _>::new(shared));
+        assert!(!switch.is_upcoming());
+        let state_machine = run_round(state_machine, &request_tx).await;
+        assert!(served_model(&event_subscriber).is_none());
+
+        // the second round is a normal round: it is published
+        let _state_machine = run_round(state_machine, &request_tx).await;
+        assert!(served_model(&event_subscriber).is_some());
+
+        let artifacts = artifacts.lock().unwrap();
+        let archives = artifacts
+            .archives
+            .iter()
+            .map(|manifest| (manifest.round_id, manifest.canary))
+            .collect::<Vec<_>>();
+        assert_eq!(archives, vec![(1, true), (2, false)]);
+        #[cfg(feature = "model-persistence")]
+        {
+            assert_eq!(artifacts.global_models, vec![(1, true), (2, false)]);
+            assert_eq!(
+                artifacts.latest_global_model_ids,
+                vec!["model_2".to_string()]
+            );
+        }
+    }
+}
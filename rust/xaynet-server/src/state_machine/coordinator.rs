@@ -2,20 +2,26 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::settings::{
-    MaskSettings,
-    ModelSettings,
-    PetSettings,
-    PetSettingsCount,
-    PetSettingsSum,
-    PetSettingsSum2,
-    PetSettingsTime,
-    PetSettingsUpdate,
+use crate::{
+    settings::{
+        MaskSettings,
+        ModelSettings,
+        PetSettings,
+        PetSettingsCount,
+        PetSettingsMaskTolerance,
+        PetSettingsQuota,
+        PetSettingsSum,
+        PetSettingsSum2,
+        PetSettingsTime,
+        PetSettingsUpdate,
+        TrainingPlanSettings,
+    },
+    state_machine::{phases::ModelUpdateStatistics, shadow::ShadowReport},
 };
 use xaynet_core::{
     common::{RoundParameters, RoundSeed},
     crypto::{ByteObject, EncryptKeyPair},
-    mask::MaskConfig,
+    mask::{MaskConfig, MaskConfigPair},
 };
 
 /// The phase count parameters.
@@ -81,7 +87,7 @@ impl From<PetSettingsUpdate> for PhaseParameters {
 
 impl From<PetSettingsSum2> for PhaseParameters {
     fn from(sum2: PetSettingsSum2) -> Self {
-        let PetSettingsSum2 { count, time } = sum2;
+        let PetSettingsSum2 { count, time, .. } = sum2;
         Self {
             count: count.into(),
             time: time.into(),
@@ -89,6 +95,150 @@ impl From<PetSettingsSum2> for PhaseParameters {
     }
 }
 
+/// The participation quota parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuotaParameters {
+    /// The maximal number of participations within the window.
+    pub max: u64,
+    /// The length of the sliding window (in seconds).
+    pub window: u64,
+}
+
+impl From<PetSettingsQuota> for QuotaParameters {
+    fn from(quota: PetSettingsQuota) -> Self {
+        let PetSettingsQuota { max, window } = quota;
+        Self { max, window }
+    }
+}
+
+/// The mask tolerance parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaskToleranceParameters {
+    /// The maximal number of elements in which a mask may differ from the best mask.
+    pub max_distance: u64,
+}
+
+impl From<PetSettingsMaskTolerance> for MaskToleranceParameters {
+    fn from(tolerance: PetSettingsMaskTolerance) -> Self {
+        let PetSettingsMaskTolerance { max_distance } = tolerance;
+        Self { max_distance }
+    }
+}
+
+/// A training plan: a fixed number of rounds with their own model length, masking configuration
+/// and selection probabilities.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrainingPlan {
+    /// The id of the plan.
+    pub id: String,
+    /// The number of rounds of the plan.
+    pub rounds: u64,
+    /// The length of the model.
+    pub model_length: usize,
+    /// The masking configuration.
+    pub mask_config: MaskConfigPair,
+    /// The probability of participants to be selected for the sum task.
+    pub sum: f64,
+    /// The probability of participants to be selected for the update task.
+    pub update: f64,
+}
+
+impl From<TrainingPlanSettings> for TrainingPlan {
+    fn from(plan: TrainingPlanSettings) -> Self {
+        let TrainingPlanSettings {
+            id,
+            rounds,
+            model_length,
+            sum_prob,
+            update_prob,
+            mask,
+        } = plan;
+        Self {
+            id,
+            rounds,
+            model_length,
+            mask_config: MaskConfig::from(mask).into(),
+            sum: sum_prob,
+            update: update_prob,
+        }
+    }
+}
+
+/// The progress of the coordinator through its training plans.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrainingPlans {
+    /// The training plans, in the order in which they run.
+    pub plans: Vec<TrainingPlan>,
+    /// The index of the current plan. It is the number of plans once all of them completed.
+    pub current: usize,
+    /// The number of rounds of the current plan that completed.
+    pub completed_rounds: u64,
+}
+
+impl TrainingPlans {
+    /// Gets the current plan, unless all of them completed.
+    pub fn current(&self) -> Option<&TrainingPlan> {
+        self.plans.get(self.current)
+    }
+
+    /// Checks whether all the plans completed.
+    pub fn is_finished(&self) -> bool {
+        self.current >= self.plans.len()
+    }
+}
+
+/// The status of the current training plan, as served to the participants.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrainingPlanStatus {
+    /// The id of the plan.
+    pub id: String,
+    /// The number of the current round within the plan, starting at `1`.
+    pub round: u64,
+    /// The number of rounds of the plan.
+    pub rounds: u64,
+}
+
+/// The history of the rounds that completed with a new global model.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoundHistory {
+    /// The number of completed rounds.
+    pub completed_rounds: u64,
+    /// The number of local models that were aggregated into the latest global model.
+    pub participants: u64,
+    /// The time at which the latest global model was published (in seconds since the UNIX
+    /// epoch), if any.
+    pub last_model_update: Option<u64>,
+    /// The disagreement of the sum participants about the mask of the latest round whose mask
+    /// was only selected thanks to the mask tolerance, if any.
+    pub last_mask_disagreement: Option<MaskDisagreement>,
+    /// The shadow evaluation of a candidate configuration in the latest round, if any. It is
+    /// never published to the participants.
+    pub last_shadow_evaluation: Option<ShadowReport>,
+    /// The statistics of the change from the previous to the latest global model, if they were
+    /// computed (see [`ModelSettings::update_statistics`]).
+    ///
+    /// [`ModelSettings::update_statistics`]: crate::settings::ModelSettings::update_statistics
+    pub last_update_statistics: Option<ModelUpdateStatistics>,
+}
+
+/// The disagreement of the sum participants about the mask of a round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaskDisagreement {
+    /// The id of the round.
+    pub round_id: u64,
+    /// The score of the selected mask.
+    pub score: u64,
+    /// The number of other masks which differ from the selected mask in at most the tolerated
+    /// number of elements.
+    pub tolerated_masks: u64,
+    /// The total score of the tolerated masks.
+    pub tolerated_score: u64,
+    /// The largest number of elements in which a tolerated mask differs from the selected mask.
+    pub max_distance: u64,
+    /// The total score of the other masks with the highest scores which differ in more elements.
+    pub rejected_score: u64,
+}
+
 /// The coordinator state.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CoordinatorState {
@@ -104,6 +254,18 @@ pub struct CoordinatorState {
     pub update: PhaseParameters,
     /// The sum2 phase parameters.
     pub sum2: PhaseParameters,
+    /// Whether the statistics of the global model updates are computed.
+    pub model_update_statistics: bool,
+    /// Whether an archive of the aggregation inputs is exported at the end of each round.
+    pub export_round_archive: bool,
+    /// The participation quota of the update participants, if any.
+    pub update_quota: Option<QuotaParameters>,
+    /// The tolerance for masks that differ from the best mask, if any.
+    pub mask_tolerance: Option<MaskToleranceParameters>,
+    /// The training plans, if any.
+    pub training_plans: Option<TrainingPlans>,
+    /// The history of the completed rounds.
+    pub round_history: RoundHistory,
 }
 
 impl CoordinatorState {
@@ -120,6 +282,8 @@ impl CoordinatorState {
             seed: RoundSeed::zeroed(),
             mask_config: MaskConfig::from(mask_settings).into(),
             model_length: model_settings.length,
+            plan_id: None,
+            max_sample_count: pet_settings.update.max_sample_count,
         };
         let round_id = 0;
         Self {
@@ -129,6 +293,96 @@ impl CoordinatorState {
             sum: pet_settings.sum.into(),
             update: pet_settings.update.into(),
             sum2: pet_settings.sum2.into(),
+            model_update_statistics: model_settings.update_statistics,
+            export_round_archive: model_settings.export_round_archive,
+            update_quota: pet_settings.update.quota.map(Into::into),
+            mask_tolerance: pet_settings.sum2.mask_tolerance.map(Into::into),
+            training_plans: None,
+            round_history: RoundHistory::default(),
+        }
+    }
+
+    /// Sets the training plans and applies the parameters of the first one. Without training
+    /// plans, the coordinator runs rounds with the same parameters until it is stopped.
+    pub fn with_training_plans(mut self, plans: Vec<TrainingPlanSettings>) -> Self {
+        if !plans.is_empty() {
+            self.training_plans = Some(TrainingPlans {
+                plans: plans.into_iter().map(Into::into).collect(),
+                current: 0,
+                completed_rounds: 0,
+            });
+            self.apply_training_plan();
+        }
+        self
+    }
+
+    /// Gets the current training plan, if any.
+    pub fn training_plan(&self) -> Option<&TrainingPlan> {
+        self.training_plans
+            .as_ref()
+            .and_then(TrainingPlans::current)
+    }
+
+    /// Gets the status of the current training plan, if any.
+    pub fn training_plan_status(&self) -> Option<TrainingPlanStatus> {
+        let plans = self.training_plans.as_ref()?;
+        plans.current().map(|plan| TrainingPlanStatus {
+            id: plan.id.clone(),
+            round: plans.completed_rounds + 1,
+            rounds: plan.rounds,
+        })
+    }
+
+    /// Checks whether all the training plans completed.
+    pub fn training_finished(&self) -> bool {
+        matches!(self.training_plans, Some(ref plans) if plans.is_finished())
+    }
+
+    /// Records that a round of the current training plan completed.
+    pub fn complete_training_round(&mut self) {
+        if let Some(plans) = self.training_plans.as_mut() {
+            plans.completed_rounds += 1;
+        }
+    }
+
+    /// Records that a round completed with a new global model which aggregates the local models
+    /// of the given number of `participants` and which was published at `time` (in seconds since
+    /// the UNIX epoch).
+    pub fn complete_round(&mut self, participants: u64, time: u64) {
+        self.round_history.completed_rounds += 1;
+        self.round_history.participants = participants;
+        self.round_history.last_model_update = Some(time);
+    }
+
+    /// Moves to the next training plan once all the rounds of the current one completed, and
+    /// applies its parameters.
+    ///
+    /// Returns whether the training plan changed.
+    pub fn advance_training_plan(&mut self) -> bool {
+        let plans = match self.training_plans.as_mut() {
+            Some(plans) => plans,
+            None => return false,
+        };
+        match plans.current() {
+            Some(plan) if plans.completed_rounds >= plan.rounds => {}
+            _ => return false,
+        }
+        plans.current += 1;
+        plans.completed_rounds = 0;
+        self.apply_training_plan();
+        true
+    }
+
+    /// Applies the parameters of the current training plan to the round parameters.
+    fn apply_training_plan(&mut self) {
+        if let Some(plan) = self.training_plan().cloned() {
+            self.round_params.model_length = plan.model_length;
+            self.round_params.mask_config = plan.mask_config;
+            self.round_params.sum = plan.sum;
+            self.round_params.update = plan.update;
+            self.round_params.plan_id = Some(plan.id);
+        } else {
+            self.round_params.plan_id = None;
         }
     }
 }
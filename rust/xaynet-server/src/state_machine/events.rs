@@ -1,12 +1,21 @@
 //! This module provides the `StateMachine`, `Events`, `EventSubscriber` and `EventPublisher` types.
 
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use tokio::sync::watch;
 
-use crate::state_machine::phases::PhaseName;
+use crate::state_machine::{
+    coordinator::{RoundHistory, TrainingPlanStatus},
+    phases::PhaseName,
+};
 use xaynet_core::{
-    common::RoundParameters,
+    common::{PhaseCount, PhaseStats, RoundMetadata, RoundParameters},
     crypto::EncryptKeyPair,
     mask::Model,
     SeedDict,
@@ -38,6 +47,62 @@ pub enum DictionaryUpdate<D> {
     New(Arc<D>),
 }
 
+/// The number of messages handled in the current phase.
+///
+/// The counters are incremented by the state machine while it processes the messages of
+/// the phase, and they are read by the fetchers without locking. A new instance is
+/// broadcasted at the start of every phase, hence the counters are reset on each phase
+/// transition.
+#[derive(Debug)]
+pub struct PhaseCounters {
+    phase: PhaseName,
+    count: Option<PhaseCount>,
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    discarded: AtomicU64,
+}
+
+impl PhaseCounters {
+    /// Creates new counters for the given phase, which processes at least `count.min` and
+    /// at most `count.max` messages, if any.
+    pub fn new(phase: PhaseName, count: Option<PhaseCount>) -> Self {
+        Self {
+            phase,
+            count,
+            accepted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            discarded: AtomicU64::new(0),
+        }
+    }
+
+    /// Increments the counter for accepted messages.
+    pub fn increment_accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Increments the counter for rejected messages.
+    pub fn increment_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Increments the counter for discarded messages.
+    pub fn increment_discarded(&self) {
+        self.discarded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Takes a snapshot of the counters of the given round.
+    pub fn snapshot(&self, round_id: u64) -> PhaseStats {
+        PhaseStats {
+            round_id,
+            phase: self.phase,
+            count: self.count,
+            accepted: self.accepted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+        }
+    }
+}
+
 /// A convenience type to emit any coordinator event.
 #[derive(Debug)]
 pub struct EventPublisher {
@@ -46,21 +111,29 @@ pub struct EventPublisher {
     keys_tx: EventBroadcaster<EncryptKeyPair>,
     params_tx: EventBroadcaster<RoundParameters>,
     phase_tx: EventBroadcaster<PhaseName>,
+    round_metadata_tx: EventBroadcaster<RoundMetadata>,
     model_tx: EventBroadcaster<ModelUpdate>,
     sum_dict_tx: EventBroadcaster<DictionaryUpdate<SumDict>>,
     seed_dict_tx: EventBroadcaster<DictionaryUpdate<SeedDict>>,
+    training_plan_tx: EventBroadcaster<Option<TrainingPlanStatus>>,
+    phase_counters_tx: EventBroadcaster<Arc<PhaseCounters>>,
+    round_history_tx: EventBroadcaster<RoundHistory>,
 }
 
 /// The `EventSubscriber` hands out `EventListener`s for any
 /// coordinator event.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct EventSubscriber {
     keys_rx: EventListener<EncryptKeyPair>,
     params_rx: EventListener<RoundParameters>,
     phase_rx: EventListener<PhaseName>,
+    round_metadata_rx: EventListener<RoundMetadata>,
     model_rx: EventListener<ModelUpdate>,
     sum_dict_rx: EventListener<DictionaryUpdate<SumDict>>,
     seed_dict_rx: EventListener<DictionaryUpdate<SeedDict>>,
+    training_plan_rx: EventListener<Option<TrainingPlanStatus>>,
+    phase_counters_rx: EventListener<Arc<PhaseCounters>>,
+    round_history_rx: EventListener<RoundHistory>,
 }
 
 impl EventPublisher {
@@ -71,6 +144,8 @@ impl EventPublisher {
         params: RoundParameters,
         phase: PhaseName,
         model: ModelUpdate,
+        training_plan: Option<TrainingPlanStatus>,
+        round_history: RoundHistory,
     ) -> (Self, EventSubscriber) {
         let (keys_tx, keys_rx) = watch::channel::<Event<EncryptKeyPair>>(Event {
             round_id,
@@ -87,6 +162,19 @@ impl EventPublisher {
             event: phase,
         });
 
+        let (round_metadata_tx, round_metadata_rx) =
+            watch::channel::<Event<RoundMetadata>>(Event {
+                round_id,
+                event: RoundMetadata {
+                    round_id,
+                    phase,
+                    phase_start: unix_time(),
+                    phase_duration: None,
+                    compression: cfg!(feature = "compression"),
+                    changed_since_previous: true,
+                },
+            });
+
         let (model_tx, model_rx) = watch::channel::<Event<ModelUpdate>>(Event {
             round_id,
             event: model,
@@ -104,23 +192,48 @@ impl EventPublisher {
                 event: DictionaryUpdate::Invalidate,
             });
 
+        let (training_plan_tx, training_plan_rx) =
+            watch::channel::<Event<Option<TrainingPlanStatus>>>(Event {
+                round_id,
+                event: training_plan,
+            });
+
+        let (phase_counters_tx, phase_counters_rx) =
+            watch::channel::<Event<Arc<PhaseCounters>>>(Event {
+                round_id,
+                event: Arc::new(PhaseCounters::new(phase, None)),
+            });
+
+        let (round_history_tx, round_history_rx) = watch::channel::<Event<RoundHistory>>(Event {
+            round_id,
+            event: round_history,
+        });
+
         let publisher = EventPublisher {
             round_id,
             keys_tx: keys_tx.into(),
             params_tx: params_tx.into(),
             phase_tx: phase_tx.into(),
+            round_metadata_tx: round_metadata_tx.into(),
             model_tx: model_tx.into(),
             sum_dict_tx: sum_dict_tx.into(),
             seed_dict_tx: seed_dict_tx.into(),
+            training_plan_tx: training_plan_tx.into(),
+            phase_counters_tx: phase_counters_tx.into(),
+            round_history_tx: round_history_tx.into(),
         };
 
         let subscriber = EventSubscriber {
             keys_rx: keys_rx.into(),
             params_rx: params_rx.into(),
             phase_rx: phase_rx.into(),
+            round_metadata_rx: round_metadata_rx.into(),
             model_rx: model_rx.into(),
             sum_dict_rx: sum_dict_rx.into(),
             seed_dict_rx: seed_dict_rx.into(),
+            training_plan_rx: training_plan_rx.into(),
+            phase_counters_rx: phase_counters_rx.into(),
+            round_history_rx: round_history_rx.into(),
         };
 
         (publisher, subscriber)
@@ -153,11 +266,21 @@ impl EventPublisher {
         let _ = self.phase_tx.broadcast(self.event(phase));
     }
 
+    /// Emit a round metadata event
+    pub fn broadcast_round_metadata(&mut self, metadata: RoundMetadata) {
+        let _ = self.round_metadata_tx.broadcast(self.event(metadata));
+    }
+
     /// Emit a model event
     pub fn broadcast_model(&mut self, update: ModelUpdate) {
         let _ = self.model_tx.broadcast(self.event(update));
     }
 
+    /// Get the latest model event
+    pub fn latest_model(&self) -> ModelUpdate {
+        self.model_tx.latest().event
+    }
+
     /// Emit a sum dictionary update
     pub fn broadcast_sum_dict(&mut self, update: DictionaryUpdate<SumDict>) {
         let _ = self.sum_dict_tx.broadcast(self.event(update));
@@ -167,6 +290,26 @@ impl EventPublisher {
     pub fn broadcast_seed_dict(&mut self, update: DictionaryUpdate<SeedDict>) {
         let _ = self.seed_dict_tx.broadcast(self.event(update));
     }
+
+    /// Emit a training plan event
+    pub fn broadcast_training_plan(&mut self, status: Option<TrainingPlanStatus>) {
+        let _ = self.training_plan_tx.broadcast(self.event(status));
+    }
+
+    /// Emit the message counters of a new phase
+    pub fn broadcast_phase_counters(&mut self, counters: Arc<PhaseCounters>) {
+        let _ = self.phase_counters_tx.broadcast(self.event(counters));
+    }
+
+    /// Get the message counters of the current phase
+    pub fn latest_phase_counters(&self) -> Arc<PhaseCounters> {
+        self.phase_counters_tx.latest().event
+    }
+
+    /// Emit a round history event
+    pub fn broadcast_round_history(&mut self, history: RoundHistory) {
+        let _ = self.round_history_tx.broadcast(self.event(history));
+    }
 }
 
 impl EventSubscriber {
@@ -186,6 +329,11 @@ impl EventSubscriber {
         self.phase_rx.clone()
     }
 
+    /// Get a listener for round metadata events
+    pub fn round_metadata_listener(&self) -> EventListener<RoundMetadata> {
+        self.round_metadata_rx.clone()
+    }
+
     /// Get a listener for new model events
     pub fn model_listener(&self) -> EventListener<ModelUpdate> {
         self.model_rx.clone()
@@ -200,6 +348,21 @@ impl EventSubscriber {
     pub fn seed_dict_listener(&self) -> EventListener<DictionaryUpdate<SeedDict>> {
         self.seed_dict_rx.clone()
     }
+
+    /// Get a listener for training plan events
+    pub fn training_plan_listener(&self) -> EventListener<Option<TrainingPlanStatus>> {
+        self.training_plan_rx.clone()
+    }
+
+    /// Get a listener for the message counters of the phases
+    pub fn phase_counters_listener(&self) -> EventListener<Arc<PhaseCounters>> {
+        self.phase_counters_rx.clone()
+    }
+
+    /// Get a listener for round history events
+    pub fn round_history_listener(&self) -> EventListener<RoundHistory> {
+        self.round_history_rx.clone()
+    }
 }
 
 /// A listener for coordinator events. It can be used to either
@@ -222,10 +385,16 @@ where
         self.0.borrow().clone()
     }
 
-    #[cfg(test)]
+    /// Waits until a new `Event<E>` is emitted by the coordinator.
     pub async fn changed(&mut self) -> Result<(), watch::error::RecvError> {
         self.0.changed().await
     }
+
+    /// Marks the latest `Event<E>` as seen, so that [`EventListener::changed()`] only waits
+    /// for the events that are emitted afterwards.
+    pub fn mark_seen(&mut self) {
+        self.0.borrow_and_update();
+    }
 }
 
 /// A channel to send `Event<E>` to all the `EventListener<E>`.
@@ -238,6 +407,14 @@ impl<E> EventBroadcaster<E> {
         // We don't care whether there's a listener or not
         let _ = self.0.send(event);
     }
+
+    /// Get the latest `Event<E>` that was sent
+    fn latest(&self) -> Event<E>
+    where
+        E: Clone,
+    {
+        self.0.borrow().clone()
+    }
 }
 
 impl<E> From<watch::Sender<Event<E>>> for EventBroadcaster<E> {
@@ -245,3 +422,11 @@ impl<E> From<watch::Sender<Event<E>>> for EventBroadcaster<E> {
         Self(sender)
     }
 }
+
+/// Gets the current time in seconds since the UNIX epoch.
+pub(in crate::state_machine) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
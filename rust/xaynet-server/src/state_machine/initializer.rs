@@ -3,21 +3,27 @@
 use displaydoc::Display;
 use thiserror::Error;
 #[cfg(feature = "model-persistence")]
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 #[cfg(feature = "model-persistence")]
 use crate::settings::RestoreSettings;
+#[cfg(feature = "model-persistence")]
+use crate::storage::GlobalModelIdFormat;
 use crate::{
-    settings::{MaskSettings, ModelSettings, PetSettings},
+    settings::{MaskSettings, ModelSettings, PetSettings, ShadowSettings, TrainingPlanSettings},
     state_machine::{
+        canary::CanarySwitch,
         coordinator::CoordinatorState,
         events::{EventPublisher, EventSubscriber, ModelUpdate},
         phases::{Idle, PhaseName, PhaseState, Shared},
+        preflight::PreflightReport,
         requests::{RequestReceiver, RequestSender},
+        shadow::Shadow,
         StateMachine,
     },
-    storage::{Storage, StorageError},
+    storage::{CoordinatorStorage, ModelStorage, Storage, StorageError},
 };
+use xaynet_core::crypto::EncryptKeySeed;
 #[cfg(feature = "model-persistence")]
 use xaynet_core::mask::Model;
 
@@ -47,6 +53,10 @@ pub struct StateMachineInitializer<T> {
     pet_settings: PetSettings,
     mask_settings: MaskSettings,
     model_settings: ModelSettings,
+    shadow_settings: Option<ShadowSettings>,
+    training_plans: Vec<TrainingPlanSettings>,
+    canary: CanarySwitch,
+    key_seed: Option<EncryptKeySeed>,
     #[cfg(feature = "model-persistence")]
     restore_settings: RestoreSettings,
     store: T,
@@ -58,6 +68,7 @@ impl<T> StateMachineInitializer<T> {
         pet_settings: PetSettings,
         mask_settings: MaskSettings,
         model_settings: ModelSettings,
+        shadow_settings: Option<ShadowSettings>,
         #[cfg(feature = "model-persistence")] restore_settings: RestoreSettings,
         store: T,
     ) -> Self {
@@ -65,12 +76,45 @@ impl<T> StateMachineInitializer<T> {
             pet_settings,
             mask_settings,
             model_settings,
+            shadow_settings,
+            training_plans: Vec::new(),
+            canary: CanarySwitch::default(),
+            key_seed: None,
             #[cfg(feature = "model-persistence")]
             restore_settings,
             store,
         }
     }
 
+    /// Sets the training plans that the [`StateMachine`] runs one after the other. Once all of
+    /// them completed, the [`StateMachine`] shuts down. The training plans only apply to a fresh
+    /// start: a restored coordinator state keeps its own training plans and their progress.
+    pub fn with_training_plans(mut self, training_plans: Vec<TrainingPlanSettings>) -> Self {
+        self.training_plans = training_plans;
+        self
+    }
+
+    /// Sets the switch to run upcoming rounds as canary rounds (see [`canary`]). The switch can
+    /// be cloned beforehand to schedule canary rounds while the [`StateMachine`] runs.
+    ///
+    /// [`canary`]: crate::state_machine::canary
+    pub fn with_canary_switch(mut self, canary: CanarySwitch) -> Self {
+        self.canary = canary;
+        self
+    }
+
+    /// Derives the round keys deterministically from the given `seed` and the round id instead
+    /// of generating them randomly.
+    ///
+    /// This is meant for reproducible test runs only: anyone who knows the seed can decrypt
+    /// the messages of the participants, hence it must never be used in production. It is only
+    /// available with the `deterministic` feature.
+    #[cfg(feature = "deterministic")]
+    pub fn with_deterministic_keys(mut self, seed: EncryptKeySeed) -> Self {
+        self.key_seed = Some(seed);
+        self
+    }
+
     // Initializes a new [`StateMachine`] with its components.
     fn init_state_machine(
         self,
@@ -83,11 +127,19 @@ impl<T> StateMachineInitializer<T> {
             coordinator_state.round_params.clone(),
             PhaseName::Idle,
             global_model,
+            coordinator_state.training_plan_status(),
+            coordinator_state.round_history,
         );
 
         let (request_rx, request_tx) = RequestReceiver::new();
 
-        let shared = Shared::new(coordinator_state, event_publisher, request_rx, self.store);
+        let shadow = self
+            .shadow_settings
+            .map(|settings| Shadow::new(settings.into()));
+        let shared = Shared::new(coordinator_state, event_publisher, request_rx, self.store)
+            .with_shadow(shadow)
+            .with_canary_switch(self.canary)
+            .with_key_seed(self.key_seed);
 
         let state_machine = StateMachine::from(PhaseState::<Idle, _>::new(shared));
         (state_machine, request_tx, event_subscriber)
@@ -98,6 +150,52 @@ impl<T> StateMachineInitializer<T>
 where
     T: Storage,
 {
+    /// Checks that the storage works without starting the protocol (see [`preflight`]).
+    ///
+    /// The checks are:
+    /// - the coordinator storage is ready, e.g. Redis responds to a ping.
+    /// - a [`CoordinatorState`] can be written to and read back from a scratch location of the
+    ///   coordinator storage.
+    /// - a small object can be written to and deleted from the global models storage, if the
+    ///   `model-persistence` feature is enabled.
+    ///
+    /// All checks are run, even if an earlier one failed. The coordinator data is left untouched.
+    ///
+    /// [`preflight`]: crate::state_machine::preflight
+    pub async fn preflight(&mut self) -> StateMachineInitializationResult<PreflightReport> {
+        // crucial: init must be called before anything else in this module
+        sodiumoxide::init().or(Err(StateMachineInitializationError::CryptoInit))?;
+
+        let mut report = PreflightReport::default();
+        report.record(
+            "coordinator storage is ready",
+            CoordinatorStorage::is_ready(&mut self.store).await,
+        );
+
+        let state = CoordinatorState::new(
+            self.pet_settings,
+            self.mask_settings,
+            self.model_settings.clone(),
+        );
+        let round_trip = match self.store.probe_coordinator_state(&state).await {
+            Ok(Some(probed)) if probed == state => Ok(()),
+            Ok(_) => Err(anyhow::anyhow!(
+                "the state read back differs from the state written"
+            )),
+            Err(err) => Err(err),
+        };
+        report.record("coordinator state round-trip", round_trip);
+
+        if cfg!(feature = "model-persistence") {
+            report.record(
+                "global models storage is writable",
+                ModelStorage::probe_write(&mut self.store).await,
+            );
+        }
+
+        Ok(report)
+    }
+
     #[cfg(not(feature = "model-persistence"))]
     /// Initializes a new [`StateMachine`] with the given settings.
     pub async fn init(
@@ -125,7 +223,8 @@ where
                 self.pet_settings,
                 self.mask_settings,
                 self.model_settings.clone(),
-            ),
+            )
+            .with_training_plans(std::mem::take(&mut self.training_plans)),
             ModelUpdate::Invalidate,
         ))
     }
@@ -217,6 +316,22 @@ where
             }
             Some(global_model_id) => global_model_id,
         };
+        // the latest global model belongs to the previous training plan
+        if matches!(
+            coordinator_state.training_plans,
+            Some(ref plans) if plans.current > 0 && plans.completed_rounds == 0
+        ) {
+            debug!("no round of the current training plan has been completed yet");
+            debug!("restore coordinator without a global model");
+            return Ok((coordinator_state, ModelUpdate::Invalidate));
+        }
+        match GlobalModelIdFormat::detect(&global_model_id) {
+            Some(GlobalModelIdFormat::ContentHash) => {}
+            Some(GlobalModelIdFormat::Legacy) => {
+                debug!("global model id {} has the legacy format", global_model_id)
+            }
+            None => warn!("global model id {} has an unknown format", global_model_id),
+        }
 
         let global_model = self
             .load_global_model(&coordinator_state, &global_model_id)
@@ -96,11 +96,14 @@
 //! [events]: crate::state_machine::events
 //! [`EventSubscriber`]: crate::state_machine::events::EventSubscriber
 
+pub mod canary;
 pub mod coordinator;
 pub mod events;
 pub mod initializer;
 pub mod phases;
+pub mod preflight;
 pub mod requests;
+pub mod shadow;
 
 use derive_more::From;
 
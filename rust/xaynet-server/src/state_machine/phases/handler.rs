@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use tokio::time::{timeout, Duration};
 use tracing::{debug, info, Span};
@@ -8,6 +10,7 @@ use crate::{
     rejected,
     state_machine::{
         coordinator::{CountParameters, PhaseParameters},
+        events::PhaseCounters,
         phases::{Phase, PhaseError, PhaseState},
         requests::{RequestError, ResponseSender, StateMachineRequest},
     },
@@ -22,6 +25,14 @@ pub trait Handler {
     /// # Errors
     /// Fails on PET and storage errors.
     async fn handle_request(&mut self, req: StateMachineRequest) -> Result<(), RequestError>;
+
+    /// Checks whether the state is still consistent after a request has been handled.
+    ///
+    /// # Errors
+    /// Fails if handling a request corrupted the state, in which case the phase must be aborted.
+    fn check_integrity(&self) -> Result<(), PhaseError> {
+        Ok(())
+    }
 }
 
 /// A counter to keep track of handled messages.
@@ -36,6 +47,8 @@ struct Counter {
     rejected: u64,
     /// The number of messages discarded without being processed.
     discarded: u64,
+    /// The counters published to the fetchers.
+    published: Arc<PhaseCounters>,
 }
 
 impl AsMut<Counter> for Counter {
@@ -45,14 +58,15 @@ impl AsMut<Counter> for Counter {
 }
 
 impl Counter {
-    /// Creates a new message counter.
-    fn new(CountParameters { min, max }: CountParameters) -> Self {
+    /// Creates a new message counter, which also increments the `published` counters.
+    fn new(CountParameters { min, max }: CountParameters, published: Arc<PhaseCounters>) -> Self {
         Self {
             min,
             max,
             accepted: 0,
             rejected: 0,
             discarded: 0,
+            published,
         }
     }
 
@@ -69,6 +83,7 @@ impl Counter {
     /// Increments the counter for accepted requests.
     fn increment_accepted(&mut self) {
         self.accepted += 1;
+        self.published.increment_accepted();
         debug!(
             "{} messages accepted (min {} and max {} required)",
             self.accepted, self.min, self.max,
@@ -78,12 +93,14 @@ impl Counter {
     /// Increments the counter for rejected requests.
     fn increment_rejected(&mut self) {
         self.rejected += 1;
+        self.published.increment_rejected();
         debug!("{} messages rejected", self.rejected);
     }
 
     /// Increments the counter for discarded requests.
     fn increment_discarded(&mut self) {
         self.discarded += 1;
+        self.published.increment_discarded();
         debug!("{} messages discarded", self.discarded);
     }
 }
@@ -104,7 +121,7 @@ where
         &mut self,
         PhaseParameters { count, time }: PhaseParameters,
     ) -> Result<(), PhaseError> {
-        let mut counter = Counter::new(count);
+        let mut counter = Counter::new(count, self.shared.events.latest_phase_counters());
 
         info!("processing requests");
         debug!(
@@ -153,7 +170,7 @@ where
                 }
                 next = self.next_request() => {
                     let (req, span, resp_tx) = next?;
-                    self.process_single(req, span, resp_tx, counter).await;
+                    self.process_single(req, span, resp_tx, counter).await?;
                 }
             }
         }
@@ -163,7 +180,7 @@ where
     async fn process_until_enough(&mut self, counter: &mut Counter) -> Result<(), PhaseError> {
         while !counter.has_enough_messages() {
             let (req, span, resp_tx) = self.next_request().await?;
-            self.process_single(req, span, resp_tx, counter).await;
+            self.process_single(req, span, resp_tx, counter).await?;
         }
         Ok(())
     }
@@ -172,13 +189,16 @@ where
     ///
     /// The request is discarded if the maximum message count is reached, accepted if processed
     /// successfully and rejected otherwise.
+    ///
+    /// # Errors
+    /// Fails if the state is corrupted after the request has been handled.
     async fn process_single(
         &mut self,
         req: StateMachineRequest,
         span: Span,
         resp_tx: ResponseSender,
         counter: &mut Counter,
-    ) {
+    ) -> Result<(), PhaseError> {
         let _span_guard = span.enter();
 
         let response = if counter.has_overmuch_messages() {
@@ -199,17 +219,21 @@ where
 
         // This may error out if the receiver has already been dropped but it doesn't matter for us.
         let _ = resp_tx.send(response);
+
+        self.check_integrity()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state_machine::phases::PhaseName;
 
     #[test]
     fn test_counter() {
         // 0 accepted
-        let mut counter = Counter::new(CountParameters { min: 1, max: 3 });
+        let published = Arc::new(PhaseCounters::new(PhaseName::Sum, None));
+        let mut counter = Counter::new(CountParameters { min: 1, max: 3 }, published.clone());
         assert!(!counter.has_enough_messages());
         assert!(!counter.has_overmuch_messages());
 
@@ -227,5 +251,13 @@ mod tests {
         counter.increment_accepted();
         assert!(counter.has_enough_messages());
         assert!(counter.has_overmuch_messages());
+
+        counter.increment_rejected();
+        counter.increment_discarded();
+        counter.increment_discarded();
+        let stats = published.snapshot(7);
+        assert_eq!(stats.round_id, 7);
+        assert_eq!(stats.phase, PhaseName::Sum);
+        assert_eq!((stats.accepted, stats.rejected, stats.discarded), (3, 1, 2));
     }
 }
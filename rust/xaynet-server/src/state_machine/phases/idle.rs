@@ -8,14 +8,15 @@ use crate::{
     metric,
     metrics::Measurement,
     state_machine::{
-        phases::{Phase, PhaseError, PhaseName, PhaseState, Shared, Sum},
+        events::ModelUpdate,
+        phases::{Phase, PhaseError, PhaseName, PhaseState, Shared, Shutdown, Sum},
         StateMachine,
     },
     storage::{Storage, StorageError},
 };
 use xaynet_core::{
     common::RoundSeed,
-    crypto::{ByteObject, EncryptKeyPair, SigningKeySeed},
+    crypto::{ByteObject, EncryptKeyPair, EncryptKeySeed, SigningKeySeed},
 };
 
 /// Errors which can occur during the idle phase.
@@ -41,6 +42,13 @@ where
     async fn process(&mut self) -> Result<(), PhaseError> {
         self.delete_dicts().await?;
 
+        self.advance_training_plan();
+        if self.shared.state.training_finished() {
+            info!("all training plans completed");
+            self.set_coordinator_state().await?;
+            return Ok(());
+        }
+
         self.gen_round_keypair();
         self.update_round_probabilities();
         self.update_round_seed();
@@ -51,13 +59,21 @@ where
     }
 
     fn broadcast(&mut self) {
+        if self.shared.state.training_finished() {
+            return;
+        }
         self.broadcast_keys();
         self.broadcast_params();
+        self.broadcast_training_plan();
         self.broadcast_metrics();
     }
 
     async fn next(self) -> Option<StateMachine<T>> {
-        Some(PhaseState::<Sum, _>::new(self.shared).into())
+        if self.shared.state.training_finished() {
+            Some(PhaseState::<Shutdown, _>::new(self.shared).into())
+        } else {
+            Some(PhaseState::<Sum, _>::new(self.shared).into())
+        }
     }
 }
 
@@ -69,12 +85,34 @@ impl<T> PhaseState<Idle, T> {
         // it here, when instantiating the idle PhaseState.
         shared.set_round_id(shared.round_id() + 1);
         debug!("new round ID = {}", shared.round_id());
+        if let Some(ref mut shadow) = shared.shadow {
+            shadow.reset();
+        }
+        shared.canary_round = shared.canary.take();
+        if shared.canary_round {
+            info!("round {} is a canary round", shared.round_id());
+        }
+        shared.update_model_changed();
+        if !shared.model_changed {
+            info!("the global model didn't change since the previous round");
+        }
         Self {
             private: Idle,
             shared,
         }
     }
 
+    /// Moves to the next training plan once the current one completed. The global model of the
+    /// previous plan is invalidated, since the next plan trains another model.
+    fn advance_training_plan(&mut self) {
+        if self.shared.state.advance_training_plan() {
+            if let Some(plan) = self.shared.state.training_plan() {
+                info!("starting training plan {}", plan.id);
+                self.shared.events.broadcast_model(ModelUpdate::Invalidate);
+            }
+        }
+    }
+
     /// Updates the participant probabilities round parameters.
     fn update_round_probabilities(&mut self) {
         info!("updating round probabilities");
@@ -102,9 +140,27 @@ impl<T> PhaseState<Idle, T> {
     }
 
     /// Generates fresh round credentials.
+    ///
+    /// If the coordinator runs with deterministic keys, they are derived from the key seed and
+    /// the round id instead.
     fn gen_round_keypair(&mut self) {
         info!("updating the keys");
-        self.shared.state.keys = EncryptKeyPair::generate();
+        self.shared.state.keys = match self.shared.key_seed {
+            Some(ref key_seed) => {
+                let round_seed = sha256::hash(
+                    &[
+                        key_seed.as_slice(),
+                        &self.shared.state.round_id.to_le_bytes(),
+                    ]
+                    .concat(),
+                );
+                // Safe unwrap: the length of the hash is 32 bytes
+                EncryptKeyPair::derive_from_seed(&EncryptKeySeed::from_slice_unchecked(
+                    round_seed.as_ref(),
+                ))
+            }
+            None => EncryptKeyPair::generate(),
+        };
         self.shared.state.round_params.pk = self.shared.state.keys.public;
     }
 
@@ -123,6 +179,12 @@ impl<T> PhaseState<Idle, T> {
             .events
             .broadcast_params(self.shared.state.round_params.clone());
     }
+
+    /// Broadcasts the status of the training plan.
+    fn broadcast_training_plan(&mut self) {
+        let status = self.shared.state.training_plan_status();
+        self.shared.events.broadcast_training_plan(status);
+    }
 }
 
 impl<T> PhaseState<Idle, T>
@@ -170,6 +232,14 @@ where
             ("round_id", self.shared.state.round_id),
             ("phase", Self::NAME as u8),
         );
+        if self.shared.canary_round {
+            metric!(
+                Measurement::RoundCanary,
+                1,
+                ("round_id", self.shared.state.round_id),
+                ("phase", Self::NAME as u8),
+            );
+        }
     }
 }
 
@@ -187,7 +257,13 @@ mod tests {
             coordinator::CoordinatorState,
             events::{DictionaryUpdate, EventPublisher, EventSubscriber, ModelUpdate},
             tests::{
-                utils::{assert_event_updated_with_id, enable_logging, init_shared, EventSnapshot},
+                utils::{
+                    assert_event_updated_with_id,
+                    enable_logging,
+                    init_shared,
+                    training_plan_settings,
+                    EventSnapshot,
+                },
                 CoordinatorStateBuilder,
                 EventBusBuilder,
             },
@@ -329,6 +405,30 @@ mod tests {
         assert!(state_machine.is_sum());
     }
 
+    #[tokio::test]
+    async fn test_idle_deterministic_keys() {
+        async fn run_idle(key_seed: Option<EncryptKeySeed>) -> CoordinatorState {
+            let mut cs = MockCoordinatorStore::new();
+            cs.expect_delete_dicts().return_once(move || Ok(()));
+            cs.expect_set_coordinator_state()
+                .return_once(move |_| Ok(()));
+            let store = Store::new(cs, MockModelStore::new());
+
+            let (state, event_publisher, _event_subscriber) = state_and_events_from_unmask_phase();
+            let (shared, _request_tx) = init_shared(state, store, event_publisher);
+            let shared = shared.with_key_seed(key_seed);
+            let state_machine = StateMachine::from(PhaseState::<Idle, _>::new(shared));
+            let state_machine = state_machine.next().await.unwrap();
+            state_machine.as_ref().clone()
+        }
+
+        let key_seed = EncryptKeySeed::from_slice_unchecked(&[7; EncryptKeySeed::LENGTH]);
+        let state = run_idle(Some(key_seed.clone())).await;
+        assert_eq!(state, run_idle(Some(key_seed)).await);
+        assert_eq!(state.keys.public, state.round_params.pk);
+        assert_ne!(state.keys, run_idle(None).await.keys);
+    }
+
     #[tokio::test]
     async fn test_idle_to_sum_delete_dicts_failed() {
         // Storage:
@@ -450,4 +550,162 @@ mod tests {
             PhaseError::Idle(IdleError::SetCoordinatorState(_))
         ))
     }
+
+    #[test]
+    fn test_idle_model_changed_since_previous_round() {
+        let (state, event_publisher, _event_subscriber) = state_and_events_from_unmask_phase();
+        let store = Store::new(MockCoordinatorStore::new(), MockModelStore::new());
+        let (shared, _request_tx) = init_shared(state, store, event_publisher);
+
+        // the first round can't tell whether the global model changed
+        let idle = PhaseState::<Idle, _>::new(shared);
+        assert!(
+            idle.shared
+                .round_metadata(PhaseName::Idle)
+                .changed_since_previous
+        );
+
+        // the round ends with an identical global model
+        let mut shared = idle.shared;
+        shared
+            .events
+            .broadcast_model(ModelUpdate::New(Arc::new(create_global_model(1))));
+        let idle = PhaseState::<Idle, _>::new(shared);
+        assert!(
+            !idle
+                .shared
+                .round_metadata(PhaseName::Idle)
+                .changed_since_previous
+        );
+        assert!(
+            !idle
+                .shared
+                .round_metadata(PhaseName::Sum)
+                .changed_since_previous
+        );
+
+        // the round fails and keeps the global model
+        let idle = PhaseState::<Idle, _>::new(idle.shared);
+        assert!(
+            !idle
+                .shared
+                .round_metadata(PhaseName::Idle)
+                .changed_since_previous
+        );
+
+        // the round ends with a new global model
+        let mut shared = idle.shared;
+        shared
+            .events
+            .broadcast_model(ModelUpdate::New(Arc::new(create_global_model(2))));
+        let idle = PhaseState::<Idle, _>::new(shared);
+        assert!(
+            idle.shared
+                .round_metadata(PhaseName::Idle)
+                .changed_since_previous
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idle_starts_next_training_plan() {
+        // No Storage errors
+        // lets pretend we come from the unmask phase of the last round of the first plan
+        //
+        // What should happen:
+        // 1. move to the second training plan
+        // 2. apply the parameters of the second training plan
+        // 3. invalidate the global model of the first training plan
+        // 4. broadcast the status of the second training plan
+        // 5. move into sum phase
+        enable_logging();
+
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_delete_dicts().return_once(move || Ok(()));
+        cs.expect_set_coordinator_state()
+            .return_once(move |_| Ok(()));
+        let store = Store::new(cs, MockModelStore::new());
+
+        let state = CoordinatorStateBuilder::new()
+            .with_training_plans(vec![
+                training_plan_settings("first", 2, 1),
+                training_plan_settings("second", 3, 4),
+            ])
+            .with_completed_training_rounds(0, 2)
+            .build();
+        let (event_publisher, event_subscriber) = EventBusBuilder::new(&state)
+            .broadcast_phase(PhaseName::Unmask)
+            .broadcast_model(ModelUpdate::New(Arc::new(create_global_model(1))))
+            .build();
+
+        let (shared, _request_tx) = init_shared(state, store, event_publisher);
+        let state_machine = StateMachine::from(PhaseState::<Idle, _>::new(shared));
+        let state_machine = state_machine.next().await.unwrap();
+
+        let state_after_idle = state_machine.as_ref().clone();
+        assert_eq!(state_after_idle.training_plan().unwrap().id, "second");
+        assert_eq!(state_after_idle.round_params.model_length, 4);
+        assert_eq!(
+            state_after_idle.round_params.plan_id.as_deref(),
+            Some("second")
+        );
+        let status = state_after_idle.training_plan_status().unwrap();
+        assert_eq!((status.round, status.rounds), (1, 3));
+
+        assert_eq!(
+            event_subscriber.model_listener().get_latest().event,
+            ModelUpdate::Invalidate
+        );
+        assert_eq!(
+            event_subscriber.params_listener().get_latest().event,
+            state_after_idle.round_params
+        );
+        assert_eq!(
+            event_subscriber.training_plan_listener().get_latest().event,
+            Some(status)
+        );
+
+        assert!(state_machine.is_sum());
+    }
+
+    #[tokio::test]
+    async fn test_idle_to_shutdown_after_training_plans() {
+        // No Storage errors
+        // lets pretend we come from the unmask phase of the last round of the last plan
+        //
+        // What should happen:
+        // 1. save the coordinator state with all training plans completed
+        // 2. move into shutdown phase
+        //
+        // What should not happen:
+        // - new round parameters have been broadcast
+        // - the global model has been invalidated
+        enable_logging();
+
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_delete_dicts().return_once(move || Ok(()));
+        cs.expect_set_coordinator_state()
+            .withf(|state| state.training_finished())
+            .return_once(move |_| Ok(()));
+        let store = Store::new(cs, MockModelStore::new());
+
+        let state = CoordinatorStateBuilder::new()
+            .with_training_plans(vec![training_plan_settings("only", 1, 1)])
+            .with_completed_training_rounds(0, 1)
+            .build();
+        let (event_publisher, event_subscriber) = EventBusBuilder::new(&state)
+            .broadcast_phase(PhaseName::Unmask)
+            .broadcast_model(ModelUpdate::New(Arc::new(create_global_model(1))))
+            .build();
+        let events_before_idle = EventSnapshot::from(&event_subscriber);
+
+        let (shared, _request_tx) = init_shared(state, store, event_publisher);
+        let state_machine = StateMachine::from(PhaseState::<Idle, _>::new(shared));
+        let state_machine = state_machine.next().await.unwrap();
+
+        let events_after_idle = EventSnapshot::from(&event_subscriber);
+        assert_eq!(events_after_idle.params, events_before_idle.params);
+        assert_eq!(events_after_idle.model, events_before_idle.model);
+
+        assert!(state_machine.is_shutdown());
+    }
 }
@@ -20,6 +20,6 @@ pub use self::{
     shutdown::Shutdown,
     sum::{Sum, SumError},
     sum2::Sum2,
-    unmask::{Unmask, UnmaskError},
+    unmask::{ModelUpdateStatistics, Unmask, UnmaskError},
     update::{Update, UpdateError},
 };
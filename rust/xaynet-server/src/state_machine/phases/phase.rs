@@ -1,7 +1,6 @@
-use std::fmt;
+use std::{fmt, sync::Arc};
 
 use async_trait::async_trait;
-use derive_more::Display;
 use futures::StreamExt;
 use tracing::{debug, error, error_span, info, warn, Span};
 use tracing_futures::Instrument;
@@ -11,33 +10,23 @@ use crate::{
     metric,
     metrics::Measurement,
     state_machine::{
+        canary::CanarySwitch,
         coordinator::CoordinatorState,
-        events::EventPublisher,
+        events::{unix_time, EventPublisher, ModelUpdate, PhaseCounters},
         phases::{Failure, PhaseError},
         requests::{RequestError, RequestReceiver, ResponseSender, StateMachineRequest},
+        shadow::Shadow,
         StateMachine,
     },
     storage::Storage,
 };
+use xaynet_core::{
+    common::{PhaseCount, PhaseDuration, RoundMetadata},
+    crypto::EncryptKeySeed,
+};
 
 /// The name of the current phase.
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
-pub enum PhaseName {
-    #[display(fmt = "Idle")]
-    Idle,
-    #[display(fmt = "Sum")]
-    Sum,
-    #[display(fmt = "Update")]
-    Update,
-    #[display(fmt = "Sum2")]
-    Sum2,
-    #[display(fmt = "Unmask")]
-    Unmask,
-    #[display(fmt = "Failure")]
-    Failure,
-    #[display(fmt = "Shutdown")]
-    Shutdown,
-}
+pub use xaynet_core::common::PhaseName;
 
 /// A trait that must be implemented by a state in order to perform its tasks and to move to a next
 /// state.
@@ -80,6 +69,18 @@ pub struct Shared<T> {
     pub(in crate::state_machine) events: EventPublisher,
     /// The store for storing coordinator and model data.
     pub(in crate::state_machine) store: T,
+    /// The shadow evaluation of a candidate configuration, if any.
+    pub(in crate::state_machine) shadow: Option<Shadow>,
+    /// The switch to schedule canary rounds.
+    pub(in crate::state_machine) canary: CanarySwitch,
+    /// Whether the current round is a canary round.
+    pub(in crate::state_machine) canary_round: bool,
+    /// The seed to derive the round keys from, if they are deterministic.
+    pub(in crate::state_machine) key_seed: Option<EncryptKeySeed>,
+    /// The global model when the current round started, if a round started.
+    pub(in crate::state_machine) round_start_model: Option<ModelUpdate>,
+    /// Whether the global model changed since the previous round.
+    pub(in crate::state_machine) model_changed: bool,
 }
 
 impl<T> fmt::Debug for Shared<T> {
@@ -88,6 +89,11 @@ impl<T> fmt::Debug for Shared<T> {
             .field("state", &self.state)
             .field("request_rx", &self.request_rx)
             .field("events", &self.events)
+            .field("shadow", &self.shadow)
+            .field("canary", &self.canary)
+            .field("canary_round", &self.canary_round)
+            .field("deterministic_keys", &self.key_seed.is_some())
+            .field("model_changed", &self.model_changed)
             .finish()
     }
 }
@@ -105,9 +111,33 @@ impl<T> Shared<T> {
             request_rx,
             events: publisher,
             store,
+            shadow: None,
+            canary: CanarySwitch::default(),
+            canary_round: false,
+            key_seed: None,
+            round_start_model: None,
+            model_changed: true,
         }
     }
 
+    /// Sets the shadow evaluation of a candidate configuration.
+    pub fn with_shadow(mut self, shadow: Option<Shadow>) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    /// Sets the switch to schedule canary rounds.
+    pub fn with_canary_switch(mut self, canary: CanarySwitch) -> Self {
+        self.canary = canary;
+        self
+    }
+
+    /// Sets the seed to derive the round keys from.
+    pub fn with_key_seed(mut self, key_seed: Option<EncryptKeySeed>) -> Self {
+        self.key_seed = key_seed;
+        self
+    }
+
     /// Sets the round ID to the given value.
     pub fn set_round_id(&mut self, id: u64) {
         self.state.round_id = id;
@@ -118,6 +148,58 @@ impl<T> Shared<T> {
     pub fn round_id(&self) -> u64 {
         self.state.round_id
     }
+
+    /// Checks whether the global model changed since the start of the previous round. This is
+    /// called when a new round starts.
+    pub(in crate::state_machine) fn update_model_changed(&mut self) {
+        let model = self.events.latest_model();
+        self.model_changed = match (&self.round_start_model, &model) {
+            (Some(ModelUpdate::New(previous)), ModelUpdate::New(current)) => {
+                !Arc::ptr_eq(previous, current) && previous != current
+            }
+            (Some(ModelUpdate::Invalidate), ModelUpdate::Invalidate) => false,
+            _ => true,
+        };
+        self.round_start_model = Some(model);
+    }
+
+    /// Returns the metadata of the current round for the given phase, which starts now.
+    pub fn round_metadata(&self, phase: PhaseName) -> RoundMetadata {
+        let time = match phase {
+            PhaseName::Sum => Some(self.state.sum.time),
+            PhaseName::Update => Some(self.state.update.time),
+            PhaseName::Sum2 => Some(self.state.sum2.time),
+            PhaseName::Idle | PhaseName::Unmask | PhaseName::Failure | PhaseName::Shutdown => None,
+        };
+        RoundMetadata {
+            round_id: self.state.round_id,
+            phase,
+            phase_start: unix_time(),
+            phase_duration: time.map(|time| PhaseDuration {
+                min: time.min,
+                max: time.max,
+            }),
+            compression: cfg!(feature = "compression"),
+            changed_since_previous: self.model_changed,
+        }
+    }
+
+    /// Returns new message counters for the given phase, which starts now.
+    pub fn phase_counters(&self, phase: PhaseName) -> PhaseCounters {
+        let count = match phase {
+            PhaseName::Sum => Some(self.state.sum.count),
+            PhaseName::Update => Some(self.state.update.count),
+            PhaseName::Sum2 => Some(self.state.sum2.count),
+            PhaseName::Idle | PhaseName::Unmask | PhaseName::Failure | PhaseName::Shutdown => None,
+        };
+        PhaseCounters::new(
+            phase,
+            count.map(|count| PhaseCount {
+                min: count.min,
+                max: count.max,
+            }),
+        )
+    }
 }
 
 /// The state corresponding to a phase of the PET protocol.
@@ -150,6 +232,11 @@ where
         async move {
             info!("starting phase");
             self.shared.events.broadcast_phase(phase);
+            self.shared
+                .events
+                .broadcast_round_metadata(self.shared.round_metadata(phase));
+            let counters = Arc::new(self.shared.phase_counters(phase));
+            self.shared.events.broadcast_phase_counters(counters);
             metric!(Measurement::Phase, phase as u8);
 
             if let Err(err) = self.process().await {
@@ -73,9 +73,14 @@ where
         if let StateMachineRequest::Sum(SumRequest {
             participant_pk,
             ephm_pk,
+            sum_signature,
         }) = req
         {
-            self.update_sum_dict(participant_pk, ephm_pk).await
+            self.update_sum_dict(participant_pk, ephm_pk).await?;
+            if let Some(ref mut shadow) = self.shared.shadow {
+                shadow.observe_sum(&sum_signature);
+            }
+            Ok(())
         } else {
             Err(RequestError::MessageRejected)
         }
@@ -255,6 +260,17 @@ mod tests {
         );
 
         assert!(state_machine.is_update());
+
+        let metadata = event_subscriber
+            .round_metadata_listener()
+            .get_latest()
+            .event;
+        assert_eq!(metadata.round_id, 1);
+        assert_eq!(metadata.phase, PhaseName::Sum);
+        assert_eq!(
+            metadata.phase_duration.map(|duration| duration.min),
+            Some(1)
+        );
     }
 
     #[tokio::test]
@@ -1,24 +1,44 @@
-use std::{cmp::Ordering, sync::Arc};
+use std::{
+    cmp::Ordering,
+    sync::{
+        atomic::{self, AtomicU64},
+        Arc,
+    },
+};
 
 use async_trait::async_trait;
 use displaydoc::Display;
+use num::{ToPrimitive, Zero};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-#[cfg(feature = "model-persistence")]
-use tracing::warn;
-use tracing::{error, info};
+use tracing::{debug, error, info, warn};
 
 use crate::{
     metric,
     metrics::{GlobalRecorder, Measurement},
+    round_archive::RoundArchive,
     state_machine::{
-        events::ModelUpdate,
+        coordinator::{MaskDisagreement, MaskToleranceParameters},
+        events::{unix_time, ModelUpdate},
         phases::{Idle, Phase, PhaseError, PhaseName, PhaseState, Shared},
+        shadow::ShadowReport,
         StateMachine,
     },
     storage::{Storage, StorageError},
 };
 use xaynet_core::mask::{Aggregation, MaskObject, Model, UnmaskingError};
 
+/// The quantiles of the global model weights which are emitted as metrics.
+const MODEL_WEIGHT_QUANTILES: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+/// The number of elements of the masks which are compared at once by [`mask_distance()`].
+const MASK_DISTANCE_CHUNK: usize = 4096;
+
+/// The maximal number of masks with the highest scores which are fetched from the store to
+/// tolerate masks that differ from the best mask in a few elements.
+const MASK_TOLERANCE_CANDIDATES: usize = 16;
+
 /// Errors which can occur during the unmask phase.
 #[derive(Debug, Display, Error)]
 pub enum UnmaskError {
@@ -37,6 +57,133 @@ pub enum UnmaskError {
     PublishProof(crate::storage::StorageError),
 }
 
+/// Statistics about the change between two consecutive global models.
+///
+/// The statistics are computed from the unmasked global models only, hence they don't reveal
+/// anything about the local models of the participants.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelUpdateStatistics {
+    /// The L2 norm of the difference between the new and the previous global model.
+    pub l2_norm: f64,
+    /// The fraction of weights which changed their sign, i.e. which are strictly positive in one
+    /// global model and strictly negative in the other one.
+    pub sign_changes: f64,
+}
+
+impl ModelUpdateStatistics {
+    /// Computes the statistics of the change from the previous to the new global model.
+    ///
+    /// Returns `None` if the models are empty or differ in length.
+    pub fn new(previous: &Model, new: &Model) -> Option<Self> {
+        if previous.len() != new.len() || new.len() == 0 {
+            return None;
+        }
+
+        let (squared_norm, sign_changes) = previous.iter().zip(new.iter()).fold(
+            (0_f64, 0_usize),
+            |(squared_norm, sign_changes), (previous, new)| {
+                // `to_f64()` never fails for ratios of big integers, it saturates at infinity
+                let delta = (new - previous).to_f64().unwrap_or(f64::INFINITY);
+                let changed = (previous * new) < Zero::zero();
+                (
+                    squared_norm + delta * delta,
+                    sign_changes + changed as usize,
+                )
+            },
+        );
+
+        Some(Self {
+            l2_norm: squared_norm.sqrt(),
+            sign_changes: sign_changes as f64 / new.len() as f64,
+        })
+    }
+}
+
+/// Gets the unique mask with the highest score together with this score, if any.
+fn unique_best_mask(masks: Vec<(MaskObject, u64)>) -> (Option<MaskObject>, u64) {
+    masks
+        .into_iter()
+        .fold(
+            (None, 0),
+            |(unique_mask, unique_count), (mask, count)| match unique_count.cmp(&count) {
+                Ordering::Less => (Some(mask), count),
+                Ordering::Greater => (unique_mask, unique_count),
+                Ordering::Equal => (None, unique_count),
+            },
+        )
+}
+
+/// Counts the elements in which two masks differ, the masked scalars included, as long as they
+/// differ in at most `max_distance` elements.
+///
+/// Returns `None` if the masks differ in more elements or if their configurations or lengths
+/// differ. The masks are compared chunk by chunk in parallel, and the comparison stops early
+/// once too many elements differ.
+fn mask_distance(first: &MaskObject, second: &MaskObject, max_distance: u64) -> Option<u64> {
+    if first.vect.config != second.vect.config
+        || first.unit.config != second.unit.config
+        || first.vect.data.len() != second.vect.data.len()
+    {
+        return None;
+    }
+
+    let distance = AtomicU64::new((first.unit.data != second.unit.data) as u64);
+    first
+        .vect
+        .data
+        .par_chunks(MASK_DISTANCE_CHUNK)
+        .zip(second.vect.data.par_chunks(MASK_DISTANCE_CHUNK))
+        .try_for_each(|(first, second)| {
+            let differing = first.iter().zip(second).filter(|(a, b)| a != b).count() as u64;
+            if differing == 0 {
+                return Some(());
+            }
+            let total = distance.fetch_add(differing, atomic::Ordering::Relaxed) + differing;
+            if total <= max_distance {
+                Some(())
+            } else {
+                None
+            }
+        })?;
+
+    let distance = distance.into_inner();
+    if distance <= max_distance {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+/// Gets the disagreement of the masks with the mask at `index`, given in descending order of
+/// their scores.
+fn mask_disagreement(
+    round_id: u64,
+    masks: &[(MaskObject, u64)],
+    index: usize,
+    max_distance: u64,
+) -> MaskDisagreement {
+    let (mask, score) = &masks[index];
+    let mut disagreement = MaskDisagreement {
+        round_id,
+        score: *score,
+        tolerated_masks: 0,
+        tolerated_score: 0,
+        max_distance: 0,
+        rejected_score: 0,
+    };
+    for (_, (other, score)) in masks.iter().enumerate().filter(|(i, _)| *i != index) {
+        match mask_distance(mask, other, max_distance) {
+            Some(distance) => {
+                disagreement.tolerated_masks += 1;
+                disagreement.tolerated_score += score;
+                disagreement.max_distance = disagreement.max_distance.max(distance);
+            }
+            None => disagreement.rejected_score += score,
+        }
+    }
+    disagreement
+}
+
 /// The unmask state.
 #[derive(Debug)]
 pub struct Unmask {
@@ -44,6 +191,16 @@ pub struct Unmask {
     model_agg: Option<Aggregation>,
     /// The global model of the current round.
     global_model: Option<Arc<Model>>,
+    /// The aggregated masked model and the aggregated mask of the current round, if the round
+    /// archive is exported.
+    round_archive_inputs: Option<(Aggregation, MaskObject)>,
+    /// The disagreement about the mask of the current round, if the mask was only selected
+    /// thanks to the mask tolerance.
+    mask_disagreement: Option<MaskDisagreement>,
+    /// The shadow evaluation of the current round, if any.
+    shadow_report: Option<ShadowReport>,
+    /// The statistics of the global model update of the current round, if computed.
+    update_statistics: Option<ModelUpdateStatistics>,
 }
 
 #[async_trait]
@@ -55,17 +212,43 @@ where
 
     async fn process(&mut self) -> Result<(), PhaseError> {
         self.emit_number_of_unique_masks_metrics();
+        let participants = self
+            .private
+            .model_agg
+            .as_ref()
+            .map_or(0, |model_agg| model_agg.nb_models() as u64);
         let best_masks = self.best_masks().await?;
         self.end_round(best_masks).await?;
+        self.record_shadow_evaluation();
+        self.emit_model_weight_quantiles_metrics();
+        self.emit_model_update_statistics();
 
         #[cfg(feature = "model-persistence")]
         self.save_global_model().await?;
-        self.publish_proof().await?;
+        self.export_round_archive().await;
+        if self.shared.canary_round {
+            info!("canary round, skipping the proof and the training plan progress");
+        } else {
+            self.publish_proof().await?;
+            self.shared.state.complete_training_round();
+            self.shared.state.complete_round(participants, unix_time());
+            if let Some(disagreement) = self.private.mask_disagreement {
+                self.shared.state.round_history.last_mask_disagreement = Some(disagreement);
+            }
+            if let Some(report) = self.private.shadow_report {
+                self.shared.state.round_history.last_shadow_evaluation = Some(report);
+            }
+            self.shared.state.round_history.last_update_statistics = self.private.update_statistics;
+        }
 
         Ok(())
     }
 
     fn broadcast(&mut self) {
+        if self.shared.canary_round {
+            info!("canary round, keeping the previous global model");
+            return;
+        }
         info!("broadcasting the new global model");
         let global_model =
             self.private.global_model.take().expect(
@@ -74,6 +257,8 @@ where
         self.shared
             .events
             .broadcast_model(ModelUpdate::New(global_model));
+        let history = self.shared.state.round_history;
+        self.shared.events.broadcast_round_history(history);
     }
 
     async fn next(self) -> Option<StateMachine<T>> {
@@ -88,30 +273,108 @@ impl<T> PhaseState<Unmask, T> {
             private: Unmask {
                 model_agg: Some(model_agg),
                 global_model: None,
+                round_archive_inputs: None,
+                mask_disagreement: None,
+                shadow_report: None,
+                update_statistics: None,
             },
             shared,
         }
     }
+}
 
+impl<T> PhaseState<Unmask, T>
+where
+    T: Storage,
+{
     /// Freezes the mask dictionary.
+    ///
+    /// If the mask tolerance is set and the best mask is not unique or its score is below
+    /// `sum2.count.min`, the masks that differ from it in a few elements are tolerated.
     async fn freeze_mask_dict(
         &mut self,
-        mut best_masks: Vec<(MaskObject, u64)>,
+        best_masks: Vec<(MaskObject, u64)>,
     ) -> Result<MaskObject, UnmaskError> {
-        let mask = best_masks
-            .drain(0..)
-            .fold(
-                (None, 0),
-                |(unique_mask, unique_count), (mask, count)| match unique_count.cmp(&count) {
-                    Ordering::Less => (Some(mask), count),
-                    Ordering::Greater => (unique_mask, unique_count),
-                    Ordering::Equal => (None, unique_count),
-                },
-            )
+        let (mask, count) = unique_best_mask(best_masks);
+
+        let tolerance = match self.shared.state.mask_tolerance {
+            Some(tolerance) if mask.is_none() || count < self.shared.state.sum2.count.min => {
+                tolerance
+            }
+            _ => return mask.ok_or(UnmaskError::AmbiguousMasks),
+        };
+        match (self.tolerate_masks(tolerance).await?, mask) {
+            (Some(tolerated), _) => Ok(tolerated),
+            (None, Some(mask)) => Ok(mask),
+            (None, None) => Err(UnmaskError::AmbiguousMasks),
+        }
+    }
+
+    /// Selects the best mask together with the masks that differ from it in a few elements.
+    ///
+    /// Among the masks with the highest score, the one with the highest score together with the
+    /// masks that differ from it in at most the tolerated number of elements is selected, if this
+    /// score is unique and at least `sum2.count.min`. Only the [`MASK_TOLERANCE_CANDIDATES`]
+    /// masks with the highest scores are considered. The disagreement is recorded as a metric
+    /// and in the round history.
+    ///
+    /// Returns `None` if no mask is selected.
+    async fn tolerate_masks(
+        &mut self,
+        tolerance: MaskToleranceParameters,
+    ) -> Result<Option<MaskObject>, UnmaskError> {
+        let mut masks = self
+            .shared
+            .store
+            .top_masks(MASK_TOLERANCE_CANDIDATES)
+            .await
+            .map_err(UnmaskError::FetchBestMasks)?;
+        let best_score = masks.first().ok_or(UnmaskError::NoMask)?.1;
+        let round_id = self.shared.state.round_id;
+
+        let (index, disagreement) = match masks
+            .iter()
+            .take_while(|(_, score)| *score == best_score)
+            .enumerate()
+            .map(|(index, _)| {
+                let disagreement =
+                    mask_disagreement(round_id, &masks, index, tolerance.max_distance);
+                (index, disagreement)
+            })
+            .fold((None, 0), |(best, best_support), (index, disagreement)| {
+                let support = disagreement.score + disagreement.tolerated_score;
+                match best_support.cmp(&support) {
+                    Ordering::Less => (Some((index, disagreement)), support),
+                    Ordering::Greater => (best, best_support),
+                    Ordering::Equal => (None, best_support),
+                }
+            })
             .0
-            .ok_or(UnmaskError::AmbiguousMasks)?;
+        {
+            Some(best) => best,
+            None => return Ok(None),
+        };
+        let support = disagreement.score + disagreement.tolerated_score;
+        if support < self.shared.state.sum2.count.min {
+            return Ok(None);
+        }
+
+        warn!(
+            "the sum participants disagree about the mask: selected a mask with score {} and {} tolerated masks with score {} which differ in at most {} elements, rejected masks with score {}",
+            disagreement.score,
+            disagreement.tolerated_masks,
+            disagreement.tolerated_score,
+            disagreement.max_distance,
+            disagreement.rejected_score,
+        );
+        metric!(
+            Measurement::MasksTolerated,
+            disagreement.tolerated_score,
+            ("round_id", round_id),
+        );
+        self.private.mask_disagreement = Some(disagreement);
 
-        Ok(mask)
+        Ok(Some(masks.swap_remove(index).0))
     }
 
     /// Ends the round by unmasking the global model.
@@ -124,16 +387,23 @@ impl<T> PhaseState<Unmask, T> {
         model_agg
             .validate_unmasking(&mask)
             .map_err(UnmaskError::from)?;
+        if self.shared.state.export_round_archive {
+            self.private.round_archive_inputs = Some((model_agg.clone(), mask.clone()));
+        }
         self.private.global_model = Some(Arc::new(model_agg.unmask(mask)));
 
         Ok(())
     }
-}
 
-impl<T> PhaseState<Unmask, T>
-where
-    T: Storage,
-{
+    /// Records the shadow evaluation of the round, if any. It is kept for the round history.
+    fn record_shadow_evaluation(&mut self) {
+        if let Some(ref shadow) = self.shared.shadow {
+            let report = shadow.report(&self.shared.state);
+            report.record();
+            self.private.shadow_report = Some(report);
+        }
+    }
+
     /// Broadcasts mask metrics.
     fn emit_number_of_unique_masks_metrics(&mut self) {
         if GlobalRecorder::global().is_none() {
@@ -156,6 +426,81 @@ where
         });
     }
 
+    /// Broadcasts the distribution of the global model weights.
+    fn emit_model_weight_quantiles_metrics(&mut self) {
+        if GlobalRecorder::global().is_none() {
+            return;
+        }
+
+        let global_model = match self.private.global_model {
+            Some(ref global_model) => global_model.clone(),
+            None => return,
+        };
+        let round_id = self.shared.state.round_id;
+
+        tokio::task::spawn_blocking(move || {
+            match global_model.quantiles(&MODEL_WEIGHT_QUANTILES) {
+                Ok(quantiles) => {
+                    for (q, value) in MODEL_WEIGHT_QUANTILES.iter().zip(quantiles) {
+                        metric!(
+                            Measurement::ModelWeightQuantile,
+                            value,
+                            ("round_id", round_id),
+                            ("quantile", *q),
+                        );
+                    }
+                }
+                Err(err) => error!("failed to compute the global model quantiles: {}", err),
+            };
+        });
+    }
+
+    /// Computes and broadcasts the statistics of the change from the previous to the new global
+    /// model, if enabled. They are kept for the round history.
+    fn emit_model_update_statistics(&mut self) {
+        if !self.shared.state.model_update_statistics {
+            return;
+        }
+
+        // the new global model is not broadcasted yet, hence the latest one is the previous model
+        let previous_model = match self.shared.events.latest_model() {
+            ModelUpdate::New(model) => model,
+            ModelUpdate::Invalidate => {
+                debug!("no previous global model, skipping the model update statistics");
+                return;
+            }
+        };
+        let global_model = self.private.global_model.as_ref().expect(
+            "unreachable: never fails when `emit_model_update_statistics()` is called after `end_round()`",
+        );
+        let round_id = self.shared.state.round_id;
+
+        match ModelUpdateStatistics::new(&previous_model, global_model) {
+            Some(statistics) => {
+                self.private.update_statistics = Some(statistics);
+                info!(
+                    "global model update: L2 norm {}, sign changes {}",
+                    statistics.l2_norm, statistics.sign_changes,
+                );
+                metric!(
+                    Measurement::ModelUpdateNorm,
+                    statistics.l2_norm,
+                    ("round_id", round_id),
+                );
+                metric!(
+                    Measurement::ModelUpdateSignChanges,
+                    statistics.sign_changes,
+                    ("round_id", round_id),
+                );
+            }
+            None => info!(
+                "cannot compare the previous global model of length {} with the new global model of length {}, skipping the model update statistics",
+                previous_model.len(),
+                global_model.len(),
+            ),
+        }
+    }
+
     /// Gets the two masks with the highest score.
     async fn best_masks(&mut self) -> Result<Vec<(MaskObject, u64)>, UnmaskError> {
         self.shared
@@ -167,6 +512,9 @@ where
     }
 
     /// Persists the global model to the store.
+    ///
+    /// The global model of a canary round is marked as canary and doesn't become the latest
+    /// global model.
     #[cfg(feature = "model-persistence")]
     async fn save_global_model(&mut self) -> Result<(), UnmaskError> {
         info!("saving global model");
@@ -178,14 +526,24 @@ where
                 "unreachable: never fails when `save_global_model()` is called after `end_round()`",
             )
             .as_ref();
+        let (round_id, round_seed) = (
+            self.shared.state.round_id,
+            &self.shared.state.round_params.seed,
+        );
+        if self.shared.canary_round {
+            let global_model_id = self
+                .shared
+                .store
+                .set_canary_global_model(round_id, round_seed, global_model)
+                .await
+                .map_err(UnmaskError::SaveGlobalModel)?;
+            info!("saved canary global model {}", global_model_id);
+            return Ok(());
+        }
         let global_model_id = self
             .shared
             .store
-            .set_global_model(
-                self.shared.state.round_id,
-                &self.shared.state.round_params.seed,
-                global_model,
-            )
+            .set_global_model(round_id, round_seed, global_model)
             .await
             .map_err(UnmaskError::SaveGlobalModel)?;
         if let Err(err) = self
@@ -200,6 +558,54 @@ where
         Ok(())
     }
 
+    /// Exports the archive of the aggregation inputs of the round to the store, if enabled.
+    ///
+    /// The round doesn't fail if the archive can't be exported.
+    async fn export_round_archive(&mut self) {
+        let (aggregation, mask) = match self.private.round_archive_inputs.take() {
+            Some(inputs) => inputs,
+            None => return,
+        };
+        info!("exporting the round archive");
+        let global_model = self
+            .private
+            .global_model
+            .as_ref()
+            .expect(
+                "unreachable: never fails when `export_round_archive()` is called after `end_round()`",
+            )
+            .as_ref();
+        let round_id = self.shared.state.round_id;
+        let archive = match RoundArchive::new(
+            round_id,
+            &self.shared.state.round_params.seed,
+            global_model,
+            aggregation,
+            mask,
+        ) {
+            Ok(archive) => archive.with_canary(self.shared.canary_round),
+            Err(err) => {
+                warn!("failed to create the round archive: {}", err);
+                return;
+            }
+        };
+        let bytes = match archive.to_bytes() {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("failed to encode the round archive: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = self
+            .shared
+            .store
+            .set_round_archive(&archive.manifest.global_model_id, round_id, bytes)
+            .await
+        {
+            warn!("failed to export the round archive: {}", err);
+        }
+    }
+
     /// Publishes proof of the global model.
     async fn publish_proof(&mut self) -> Result<(), UnmaskError> {
         info!("publishing proof of the new global model");
@@ -223,11 +629,14 @@ where
 mod tests {
     use super::*;
 
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     use anyhow::anyhow;
+    use num::BigUint;
+    use xaynet_core::mask::FromPrimitives;
 
     use crate::{
+        round_archive,
         state_machine::{
             coordinator::CoordinatorState,
             events::{DictionaryUpdate, EventPublisher, EventSubscriber, ModelUpdate},
@@ -369,8 +778,81 @@ mod tests {
             &state_after_sum2,
             &events_after_sum2,
         );
+        let history = state_after_sum2.round_history;
+        assert_eq!(history.completed_rounds, 1);
+        assert_eq!(history.participants, 1);
+        assert!(history.last_model_update.is_some());
+        assert_eq!(
+            event_subscriber.round_history_listener().get_latest().event,
+            history
+        );
+
+        assert!(state_machine.is_idle());
+    }
+
+    #[tokio::test]
+    async fn test_unmask_to_idle_phase_export_round_archive() {
+        // No Storage errors
+        // lets pretend we come from the sum2 phase
+        //
+        // What should happen:
+        // 1. broadcast Unmask phase
+        // 2  fetch best masks (return only one)
+        // 3. unmask the masked global model
+        // 4. export the round archive, which reproduces the global model
+        // 5. publish proof
+        // 6. broadcast unmasked global model
+        // 7. move into idle phase
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_export_round_archive(true)
+            .build();
+        let model_length = state.round_params.model_length;
+
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_best_masks()
+            .returning(move || Ok(Some(vec![(create_mask(model_length, 1), 1)])));
+        #[cfg(feature = "model-persistence")]
+        {
+            cs.expect_set_latest_global_model_id()
+                .returning(move |_| Ok(()));
+        }
+        let archive = Arc::new(Mutex::new(None));
+        let mut ms = MockModelStore::new();
+        #[cfg(feature = "model-persistence")]
+        {
+            ms.expect_set_global_model()
+                .returning(move |_, _, _| Ok("id".to_string()));
+        }
+        let exported = archive.clone();
+        ms.expect_set_round_archive()
+            .times(1)
+            .returning(move |id, round_id, bytes| {
+                assert_eq!(round_id, 1);
+                *exported.lock().unwrap() = Some((id.to_string(), bytes));
+                Ok(())
+            });
+
+        let store = Store::new(cs, ms);
 
+        let (event_publisher, event_subscriber) = events_from_sum2_phase(&state);
+        let (shared, _request_tx) = init_shared(state.clone(), store, event_publisher);
+        let aggregator = init_aggregator(&state);
+        let state_machine = StateMachine::from(PhaseState::<Unmask, _>::new(shared, aggregator));
+        let state_machine = state_machine.next().await.unwrap();
         assert!(state_machine.is_idle());
+
+        let global_model = match event_subscriber.model_listener().get_latest().event {
+            ModelUpdate::New(model) => model,
+            ModelUpdate::Invalidate => panic!("no global model"),
+        };
+        let (id, bytes) = archive.lock().unwrap().take().unwrap();
+        let (manifest, verified_model) = round_archive::verify(&bytes).unwrap();
+        assert_eq!(manifest.global_model_id, id);
+        assert_eq!(manifest.round_id, 1);
+        assert_eq!(&verified_model, global_model.as_ref());
     }
 
     #[tokio::test]
@@ -529,6 +1011,294 @@ mod tests {
         ))
     }
 
+    /// Creates a mask like [`create_mask()`], whose first element is replaced by `first`.
+    fn create_near_mask(model_length: usize, number: u32, first: u32) -> MaskObject {
+        let mut mask = create_mask(model_length, number);
+        mask.vect.data[0] = BigUint::from(first);
+        mask
+    }
+
+    /// Creates a store with the given masks in descending order of their scores.
+    fn store_with_masks(masks: Vec<(MaskObject, u64)>) -> impl Storage {
+        let mut cs = MockCoordinatorStore::new();
+        let best_masks = masks.iter().take(2).cloned().collect::<Vec<_>>();
+        cs.expect_best_masks()
+            .returning(move || Ok(Some(best_masks.clone())));
+        cs.expect_top_masks()
+            .withf(|limit| *limit == MASK_TOLERANCE_CANDIDATES)
+            .returning(move |_| Ok(masks.clone()));
+        #[cfg(feature = "model-persistence")]
+        {
+            cs.expect_set_latest_global_model_id()
+                .returning(move |_| Ok(()));
+        }
+        #[allow(unused_mut)]
+        let mut ms = MockModelStore::new();
+        #[cfg(feature = "model-persistence")]
+        {
+            ms.expect_set_global_model()
+                .returning(move |_, _, _| Ok("id".to_string()));
+        }
+        Store::new(cs, ms)
+    }
+
+    /// Creates a store whose best masks are ambiguous and whose masks agree up to one element
+    /// with a total score of five.
+    fn store_with_near_masks(model_length: usize) -> impl Storage {
+        store_with_masks(vec![
+            (create_mask(model_length, 1), 3),
+            (create_mask(model_length, 2), 3),
+            (create_near_mask(model_length, 1, 3), 2),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_unmask_to_idle_phase_update_statistics() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Unmask phase
+        // 2. fetch best masks (return only one)
+        // 3. unmask the masked global model
+        // 4. compare it with the previous global model and record the statistics in the
+        //    round history
+        // 5. move into idle phase
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_model_length(4)
+            .with_model_update_statistics(true)
+            .build();
+        let model_length = state.round_params.model_length;
+        let store = store_with_masks(vec![(create_mask(model_length, 1), 1)]);
+        let previous = Model::from_primitives(vec![1_i32, -1, 0, 2].into_iter()).unwrap();
+
+        let (event_publisher, event_subscriber) = EventBusBuilder::new(&state)
+            .broadcast_phase(PhaseName::Sum2)
+            .broadcast_sum_dict(DictionaryUpdate::Invalidate)
+            .broadcast_seed_dict(DictionaryUpdate::Invalidate)
+            .broadcast_model(ModelUpdate::New(Arc::new(previous.clone())))
+            .build();
+        let (shared, _request_tx) = init_shared(state.clone(), store, event_publisher);
+        let aggregator = init_aggregator(&state);
+        let state_machine = StateMachine::from(PhaseState::<Unmask, _>::new(shared, aggregator));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_idle());
+
+        let global_model = match event_subscriber.model_listener().get_latest().event {
+            ModelUpdate::New(model) => model,
+            ModelUpdate::Invalidate => panic!("the global model was not broadcasted"),
+        };
+        let history = state_machine.as_ref().round_history;
+        assert!(history.last_update_statistics.is_some());
+        assert_eq!(
+            history.last_update_statistics,
+            ModelUpdateStatistics::new(&previous, &global_model)
+        );
+        assert_eq!(
+            event_subscriber.round_history_listener().get_latest().event,
+            history
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmask_to_idle_phase_tolerated_masks() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Unmask phase
+        // 2. fetch best masks (ambiguous)
+        // 3. fetch the top masks, the first one and a mask which differs from it in one
+        //    element reach the minimal sum2 count
+        // 4. unmask the masked global model with the first mask
+        // 5. record the disagreement in the round history
+        // 6. move into idle phase
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_model_length(4)
+            .with_mask_tolerance(1)
+            .with_sum2_count_min(5)
+            .build();
+        let store = store_with_near_masks(state.round_params.model_length);
+
+        let (event_publisher, event_subscriber) = events_from_sum2_phase(&state);
+        let (shared, _request_tx) = init_shared(state.clone(), store, event_publisher);
+        let aggregator = init_aggregator(&state);
+        let state_machine = StateMachine::from(PhaseState::<Unmask, _>::new(shared, aggregator));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_idle());
+
+        let history = state_machine.as_ref().round_history;
+        assert_eq!(history.completed_rounds, 1);
+        assert_eq!(
+            history.last_mask_disagreement,
+            Some(MaskDisagreement {
+                round_id: 1,
+                score: 3,
+                tolerated_masks: 1,
+                tolerated_score: 2,
+                max_distance: 1,
+                rejected_score: 3,
+            })
+        );
+        assert_eq!(
+            event_subscriber.round_history_listener().get_latest().event,
+            history
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmask_to_idle_phase_tolerated_masks_below_count_min() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Unmask phase
+        // 2. fetch best masks (ambiguous)
+        // 3. fetch the top masks, the tolerated masks don't reach the minimal sum2 count
+        // 4. move into error phase
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_model_length(4)
+            .with_mask_tolerance(1)
+            .with_sum2_count_min(6)
+            .build();
+        let store = store_with_near_masks(state.round_params.model_length);
+
+        let (event_publisher, event_subscriber) = events_from_sum2_phase(&state);
+        let events_before_sum2 = EventSnapshot::from(&event_subscriber);
+        let (shared, _request_tx) = init_shared(state.clone(), store, event_publisher);
+        let aggregator = init_aggregator(&state);
+        let state_machine = StateMachine::from(PhaseState::<Unmask, _>::new(shared, aggregator));
+        let state_machine = state_machine.next().await.unwrap();
+
+        let state_after_sum2 = state_machine.as_ref().clone();
+        let events_after_sum2 = EventSnapshot::from(&event_subscriber);
+        assert_after_phase_failure(
+            &state,
+            &events_before_sum2,
+            &state_after_sum2,
+            &events_after_sum2,
+        );
+        assert!(matches!(
+            state_machine.into_failure_phase_state().private.error,
+            PhaseError::Unmask(UnmaskError::AmbiguousMasks)
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_unmask_to_idle_phase_tolerated_unique_mask() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Unmask phase
+        // 2. fetch best masks (unique, but below the minimal sum2 count)
+        // 3. fetch the top masks, the best one and a mask which differs from it in one element
+        //    reach the minimal sum2 count
+        // 4. unmask the masked global model with the best mask
+        // 5. record the disagreement in the round history
+        // 6. move into idle phase
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_model_length(4)
+            .with_mask_tolerance(1)
+            .with_sum2_count_min(5)
+            .build();
+        let model_length = state.round_params.model_length;
+        let store = store_with_masks(vec![
+            (create_mask(model_length, 1), 3),
+            (create_near_mask(model_length, 1, 3), 2),
+            (create_mask(model_length, 2), 1),
+        ]);
+
+        let (event_publisher, _event_subscriber) = events_from_sum2_phase(&state);
+        let (shared, _request_tx) = init_shared(state.clone(), store, event_publisher);
+        let aggregator = init_aggregator(&state);
+        let state_machine = StateMachine::from(PhaseState::<Unmask, _>::new(shared, aggregator));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_idle());
+
+        let history = state_machine.as_ref().round_history;
+        assert_eq!(history.completed_rounds, 1);
+        assert_eq!(
+            history.last_mask_disagreement,
+            Some(MaskDisagreement {
+                round_id: 1,
+                score: 3,
+                tolerated_masks: 1,
+                tolerated_score: 2,
+                max_distance: 1,
+                rejected_score: 1,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unmask_to_idle_phase_unique_mask_below_count_min() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Unmask phase
+        // 2. fetch best masks (unique, but below the minimal sum2 count)
+        // 3. fetch the top masks, the tolerated masks don't reach the minimal sum2 count
+        // 4. unmask the masked global model with the best mask like without mask tolerance
+        // 5. move into idle phase without a disagreement
+        enable_logging();
+
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_model_length(4)
+            .with_mask_tolerance(1)
+            .with_sum2_count_min(5)
+            .build();
+        let model_length = state.round_params.model_length;
+        let store = store_with_masks(vec![
+            (create_mask(model_length, 1), 3),
+            (create_mask(model_length, 2), 2),
+        ]);
+
+        let (event_publisher, _event_subscriber) = events_from_sum2_phase(&state);
+        let (shared, _request_tx) = init_shared(state.clone(), store, event_publisher);
+        let aggregator = init_aggregator(&state);
+        let state_machine = StateMachine::from(PhaseState::<Unmask, _>::new(shared, aggregator));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_idle());
+
+        let history = state_machine.as_ref().round_history;
+        assert_eq!(history.completed_rounds, 1);
+        assert_eq!(history.last_mask_disagreement, None);
+    }
+
+    #[test]
+    fn test_mask_distance() {
+        let mask = create_mask(10_000, 1);
+        assert_eq!(mask_distance(&mask, &mask, 0), Some(0));
+        assert_eq!(
+            mask_distance(&mask, &create_near_mask(10_000, 1, 2), 1),
+            Some(1)
+        );
+
+        // differences in several chunks and in the masked scalar add up
+        let mut other = create_near_mask(10_000, 1, 2);
+        other.vect.data[MASK_DISTANCE_CHUNK + 1] = BigUint::from(2_u32);
+        other.vect.data[9_999] = BigUint::from(2_u32);
+        other.unit.data = BigUint::from(1_u32);
+        assert_eq!(mask_distance(&mask, &other, 4), Some(4));
+        assert_eq!(mask_distance(&mask, &other, 3), None);
+
+        assert_eq!(mask_distance(&mask, &create_mask(10_000, 2), 100), None);
+        assert_eq!(mask_distance(&mask, &create_mask(9_999, 1), 100), None);
+        let mut other = mask.clone();
+        other.unit.data = BigUint::from(1_u32);
+        assert_eq!(mask_distance(&mask, &other, 0), None);
+    }
+
     #[tokio::test]
     async fn test_unmask_to_idle_phase_validate_unmasking_fails() {
         // No Storage errors
@@ -788,4 +1558,37 @@ mod tests {
 
         assert!(state_machine.is_idle());
     }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_model_update_statistics() {
+        let previous = Model::from_primitives(vec![1_i32, -2, 3, 0, 5].into_iter()).unwrap();
+        let new = Model::from_primitives(vec![-1_i32, -2, 6, 4, 5].into_iter()).unwrap();
+
+        // delta: [-2, 0, 3, 4, 0] => norm: sqrt(4 + 9 + 16) = sqrt(29)
+        // sign changes: only the first weight, a weight becoming non-zero doesn't count
+        let statistics = ModelUpdateStatistics::new(&previous, &new).unwrap();
+        assert_eq!(statistics.l2_norm, 29_f64.sqrt());
+        assert_eq!(statistics.sign_changes, 0.2);
+
+        let statistics = ModelUpdateStatistics::new(&new, &new).unwrap();
+        assert_eq!(statistics.l2_norm, 0.0);
+        assert_eq!(statistics.sign_changes, 0.0);
+
+        let previous = Model::from_primitives(vec![0.5_f32, -0.25].into_iter()).unwrap();
+        let new = Model::from_primitives(vec![-0.5_f32, 0.5].into_iter()).unwrap();
+        let statistics = ModelUpdateStatistics::new(&previous, &new).unwrap();
+        assert_eq!(statistics.l2_norm, 1.25);
+        assert_eq!(statistics.sign_changes, 1.0);
+    }
+
+    #[test]
+    fn test_model_update_statistics_length_mismatch() {
+        let previous = Model::from_primitives(vec![1_i32, 2].into_iter()).unwrap();
+        let new = Model::from_primitives(vec![1_i32, 2, 3].into_iter()).unwrap();
+        assert!(ModelUpdateStatistics::new(&previous, &new).is_none());
+
+        let empty = Model::from(Vec::new());
+        assert!(ModelUpdateStatistics::new(&empty, &empty).is_none());
+    }
 }
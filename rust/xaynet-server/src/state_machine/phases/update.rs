@@ -1,13 +1,18 @@
-use std::sync::Arc;
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 use displaydoc::Display;
 use thiserror::Error;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
 use crate::{
+    metric,
+    metrics::Measurement,
     state_machine::{
-        events::DictionaryUpdate,
+        events::{unix_time, DictionaryUpdate},
         phases::{Handler, Phase, PhaseError, PhaseName, PhaseState, Shared, Sum2},
         requests::{RequestError, StateMachineRequest, UpdateRequest},
         StateMachine,
@@ -28,6 +33,8 @@ pub enum UpdateError {
     NoSeedDict,
     /// Fetching seed dictionary failed: {0}.
     FetchSeedDict(StorageError),
+    /// Aggregation of a masked model panicked, the aggregated masked models are corrupted.
+    AggregationPanicked,
 }
 
 /// The update state.
@@ -35,8 +42,24 @@ pub enum UpdateError {
 pub struct Update {
     /// The aggregator for masked models.
     model_agg: Aggregation,
+    /// Whether the aggregator got corrupted by a panic during an aggregation.
+    poisoned: bool,
     /// The seed dictionary which gets assembled during the update phase.
     seed_dict: Option<SeedDict>,
+    /// A hook which is called right before a masked model gets aggregated.
+    #[cfg(test)]
+    before_aggregation: Option<fn(&MaskObject)>,
+}
+
+impl Update {
+    /// Aggregates a masked model.
+    fn aggregate(&mut self, mask_object: MaskObject) {
+        #[cfg(test)]
+        if let Some(hook) = self.before_aggregation {
+            hook(&mask_object);
+        }
+        self.model_agg.aggregate(mask_object);
+    }
 }
 
 #[async_trait]
@@ -81,6 +104,9 @@ where
             participant_pk,
             local_seed_dict,
             masked_model,
+            sum_signature,
+            update_signature,
+            model_shape,
         }) = req
         {
             self.update_seed_dict_and_aggregate_mask(
@@ -88,11 +114,30 @@ where
                 &local_seed_dict,
                 masked_model,
             )
-            .await
+            .await?;
+            if let Some(shape) = model_shape {
+                // the shape is unauthenticated metadata, hence a mismatching shape doesn't
+                // invalidate the aggregated masked model
+                if let Err(e) = self.private.model_agg.set_shape(shape) {
+                    warn!("ignoring the shape of the masked model: {}", e);
+                }
+            }
+            if let Some(ref mut shadow) = self.shared.shadow {
+                shadow.observe_update(&sum_signature, &update_signature);
+            }
+            Ok(())
         } else {
             Err(RequestError::MessageRejected)
         }
     }
+
+    fn check_integrity(&self) -> Result<(), PhaseError> {
+        if self.private.poisoned {
+            Err(UpdateError::AggregationPanicked.into())
+        } else {
+            Ok(())
+        }
+    }
 }
 
 impl<T> PhaseState<Update, T> {
@@ -105,7 +150,10 @@ impl<T> PhaseState<Update, T> {
         Self {
             private: Update {
                 model_agg,
+                poisoned: false,
                 seed_dict: None,
+                #[cfg(test)]
+                before_aggregation: None,
             },
             shared,
         }
@@ -136,6 +184,8 @@ where
                 RequestError::AggregationFailed
             })?;
 
+        self.check_quota(pk).await?;
+
         // Try to update local seed dict first. If this fail, we do
         // not want to aggregate the model.
         info!("updating the global seed dictionary");
@@ -145,9 +195,33 @@ where
                 warn!("invalid local seed dictionary, ignoring update message");
                 err
             })?;
+        self.add_participation(pk).await;
 
         info!("aggregating the masked model and scalar");
-        self.private.model_agg.aggregate(mask_object);
+        self.aggregate_mask(mask_object)
+    }
+
+    /// Aggregates a masked model.
+    ///
+    /// A panic during the aggregation may leave the aggregator with partially aggregated
+    /// elements. The aggregator is then marked as poisoned and the phase fails once the request
+    /// has been answered, because the corrupted aggregate must not be unmasked.
+    ///
+    /// # Error
+    ///
+    /// Fails if the aggregation panicked.
+    fn aggregate_mask(&mut self, mask_object: MaskObject) -> Result<(), RequestError> {
+        let update = &mut self.private;
+        if panic::catch_unwind(AssertUnwindSafe(|| update.aggregate(mask_object))).is_err() {
+            error!("model aggregation panicked, the aggregated masked models are corrupted");
+            metric!(
+                Measurement::AggregationPanicked,
+                1,
+                ("round_id", self.shared.state.round_id),
+            );
+            self.private.poisoned = true;
+            return Err(RequestError::AggregationFailed);
+        }
         Ok(())
     }
 
@@ -169,6 +243,52 @@ where
             .map_err(RequestError::from)
     }
 
+    /// Checks whether the participant exceeded its participation quota, if any.
+    ///
+    /// # Error
+    ///
+    /// Fails if the participant exceeded its quota or due to a [`StorageError`].
+    async fn check_quota(&mut self, pk: &UpdateParticipantPublicKey) -> Result<(), RequestError> {
+        let quota = match self.shared.state.update_quota {
+            Some(quota) => quota,
+            None => return Ok(()),
+        };
+        let participations = self
+            .shared
+            .store
+            .update_participations(pk, unix_time(), quota.window)
+            .await?;
+        if participations >= quota.max {
+            warn!("update participation quota exceeded, ignoring update message");
+            metric!(
+                Measurement::UpdateQuotaExceeded,
+                1,
+                ("round_id", self.shared.state.round_id),
+            );
+            return Err(RequestError::QuotaExceeded);
+        }
+        Ok(())
+    }
+
+    /// Records the participation of an accepted participant, if the participation quota is
+    /// enabled.
+    ///
+    /// The local seed dict of the participant has already been added at this point, hence a
+    /// failure only means that the participation is not counted.
+    async fn add_participation(&mut self, pk: &UpdateParticipantPublicKey) {
+        if let Some(quota) = self.shared.state.update_quota {
+            let round_id = self.shared.state.round_id;
+            if let Err(e) = self
+                .shared
+                .store
+                .add_update_participation(pk, round_id, unix_time(), quota.window)
+                .await
+            {
+                warn!("failed to record the update participation: {}", e);
+            }
+        }
+    }
+
     /// Gets the global seed dict from the store.
     async fn seed_dict(&mut self) -> Result<(), UpdateError> {
         self.private.seed_dict = self
@@ -189,7 +309,7 @@ mod tests {
     use super::*;
 
     use anyhow::anyhow;
-    use xaynet_core::{SeedDict, SumDict};
+    use xaynet_core::{mask::ModelShape, SeedDict, SumDict};
 
     use crate::{
         state_machine::{
@@ -198,6 +318,7 @@ mod tests {
             tests::{
                 utils::{
                     assert_event_updated,
+                    compose_update_message,
                     enable_logging,
                     init_shared,
                     send_update_messages,
@@ -319,6 +440,66 @@ mod tests {
         assert!(state_machine.is_sum2());
     }
 
+    #[tokio::test]
+    async fn test_update_quota_exceeded() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Update phase
+        // 2. reject 1 update message (the participant exceeded its quota)
+        // 3. accept 3 update messages and record their participation
+        // 4. fetch seed dict
+        // 5. broadcast seed dict
+        // 6. move into sum2 phase
+        enable_logging();
+
+        let mut cs = MockCoordinatorStore::new();
+        let mut participations = vec![0, 0, 0, 2].into_iter();
+        cs.expect_update_participations()
+            .times(4)
+            .withf(|_, _, window| *window == 100)
+            .returning(move |_, _, _| Ok(participations.next_back().unwrap()));
+        cs.expect_add_local_seed_dict()
+            .times(3)
+            .returning(move |_, _| Ok(LocalSeedDictAdd(Ok(()))));
+        cs.expect_add_update_participation()
+            .times(3)
+            .withf(|_, round_id, _, window| *round_id == 1 && *window == 100)
+            .returning(move |_, _, _, _| Ok(1));
+        cs.expect_seed_dict()
+            .return_once(move || Ok(Some(SeedDict::new())));
+        let store = Store::new(cs, MockModelStore::new());
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_update_count_min(3)
+            .with_update_count_max(3)
+            .with_update_time_min(1)
+            .with_update_quota(2, 100)
+            .build();
+
+        let (event_publisher, _event_subscriber) = events_from_sum_phase(&state);
+        let (shared, request_tx) = init_shared(state, store, event_publisher);
+        let state_machine = StateMachine::from(PhaseState::<Update, _>::new(shared));
+
+        let request = request_tx.clone();
+        let rejected = tokio::spawn(async move {
+            request
+                .msg(&crate::state_machine::tests::utils::compose_update_message(
+                    create_mask(1, 1),
+                ))
+                .await
+        });
+        tokio::task::yield_now().await;
+        send_update_messages(3, request_tx.clone());
+
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_sum2());
+        assert!(matches!(
+            rejected.await.unwrap(),
+            Err(RequestError::QuotaExceeded)
+        ));
+    }
+
     #[tokio::test]
     async fn test_update_to_sum2_fetch_seed_dict_failed() {
         // Storage errors
@@ -493,6 +674,112 @@ mod tests {
         assert!(state_machine.is_sum2());
     }
 
+    #[tokio::test]
+    async fn test_model_shape() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. accept 4 update messages
+        // 2. ignore the shape of the second message (it doesn't match the model length)
+        // 3. keep the shape of the third message and ignore the shape of the fourth message
+        enable_logging();
+
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_add_local_seed_dict()
+            .times(4)
+            .returning(move |_, _| Ok(LocalSeedDictAdd(Ok(()))));
+        let store = Store::new(cs, MockModelStore::new());
+        let state = CoordinatorStateBuilder::new().with_round_id(1).build();
+
+        let (event_publisher, _event_subscriber) = events_from_sum_phase(&state);
+        let (shared, _request_tx) = init_shared(state, store, event_publisher);
+        let mut update = PhaseState::<Update, _>::new(shared);
+
+        let shapes = vec![None, Some(vec![vec![2]]), Some(vec![vec![1]]), Some(vec![])];
+        for shape in shapes {
+            let mut req = StateMachineRequest::from(compose_update_message(create_mask(1, 1)));
+            if let StateMachineRequest::Update(ref mut update_req) = req {
+                update_req.model_shape = shape.map(ModelShape::from);
+            }
+            update.handle_request(req).await.unwrap();
+        }
+        assert_eq!(
+            update.private.model_agg.shape(),
+            Some(&ModelShape::from(vec![vec![1]]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_aggregation_panicked() {
+        // No Storage errors
+        //
+        // What should happen:
+        // 1. broadcast Update phase
+        // 2. reject 1 update message (the aggregation panics)
+        // 3. move into error phase
+        // 4. move into idle phase
+        // 5. move into sum phase of the next round
+        //
+        // What should not happen:
+        // - the seed dict has been fetched or broadcasted
+        enable_logging();
+
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_add_local_seed_dict()
+            .times(1)
+            .returning(move |_, _| Ok(LocalSeedDictAdd(Ok(()))));
+        cs.expect_is_ready().return_once(move || Ok(()));
+        cs.expect_delete_dicts().return_once(move || Ok(()));
+        cs.expect_set_coordinator_state()
+            .return_once(move |_| Ok(()));
+        let mut ms = MockModelStore::new();
+        ms.expect_is_ready().return_once(move || Ok(()));
+        let store = Store::new(cs, ms);
+        let state = CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_update_count_min(3)
+            .with_update_count_max(3)
+            .with_update_time_min(0)
+            .with_update_time_max(5)
+            .build();
+
+        let (event_publisher, event_subscriber) = events_from_sum_phase(&state);
+        let events_before_update = EventSnapshot::from(&event_subscriber);
+        let state_before_update = state.clone();
+
+        let (shared, request_tx) = init_shared(state, store, event_publisher);
+        let mut update = PhaseState::<Update, _>::new(shared);
+        update.private.before_aggregation = Some(|_| panic!("injected aggregation panic"));
+        let state_machine = StateMachine::from(update);
+        assert!(state_machine.is_update());
+
+        send_update_messages(1, request_tx.clone());
+        let state_machine = state_machine.next().await.unwrap();
+
+        let state_after_update = state_machine.as_ref().clone();
+        let events_after_update = EventSnapshot::from(&event_subscriber);
+        assert_after_phase_failure(
+            &state_before_update,
+            &events_before_update,
+            &state_after_update,
+            &events_after_update,
+        );
+
+        assert!(state_machine.is_failure());
+        let failure = state_machine.into_failure_phase_state();
+        assert!(matches!(
+            failure.private.error,
+            PhaseError::Update(UpdateError::AggregationPanicked)
+        ));
+
+        // the round failed cleanly, hence the next round can start
+        let state_machine = StateMachine::from(failure).next().await.unwrap();
+        assert!(state_machine.is_idle());
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_sum());
+        assert_eq!(state_machine.as_ref().round_id, 2);
+    }
+
     #[tokio::test]
     async fn test_rejected_messages_pet_error() {
         // No Storage errors
@@ -0,0 +1,86 @@
+//! Preflight checks.
+//!
+//! A misconfigured storage, e.g. wrong Redis credentials or a read-only S3 bucket, often only
+//! shows once the coordinator tries to write to it during a round. The preflight (see
+//! [`StateMachineInitializer::preflight()`]) exercises the storage beforehand without starting
+//! the protocol and without touching the coordinator data, and reports the outcome of each check
+//! in a [`PreflightReport`].
+//!
+//! [`StateMachineInitializer::preflight()`]: crate::state_machine::initializer::StateMachineInitializer::preflight
+
+use std::fmt;
+
+use crate::storage::{StorageError, StorageResult};
+
+/// The outcome of a single preflight check.
+#[derive(Debug)]
+pub struct PreflightCheck {
+    /// The name of the check.
+    pub name: &'static str,
+    /// The error, if the check failed.
+    pub error: Option<StorageError>,
+}
+
+impl PreflightCheck {
+    /// Checks whether the check passed.
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The outcomes of the preflight checks, in the order in which they ran.
+#[derive(Debug, Default)]
+pub struct PreflightReport {
+    /// The checks.
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    /// Records the result of a check.
+    pub(crate) fn record(&mut self, name: &'static str, result: StorageResult<()>) {
+        self.checks.push(PreflightCheck {
+            name,
+            error: result.err(),
+        });
+    }
+
+    /// Checks whether all checks passed.
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(PreflightCheck::passed)
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match &check.error {
+                None => writeln!(f, "{}: passed", check.name)?,
+                Some(err) => writeln!(f, "{}: failed: {:#}", check.name, err)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let mut report = PreflightReport::default();
+        assert!(report.passed());
+
+        report.record("ping", Ok(()));
+        assert!(report.passed());
+
+        report.record("write", Err(anyhow::anyhow!("read-only")));
+        assert!(!report.passed());
+        assert!(report.checks[0].passed());
+        assert!(!report.checks[1].passed());
+        assert_eq!(
+            report.to_string(),
+            "ping: passed\nwrite: failed: read-only\n"
+        );
+    }
+}
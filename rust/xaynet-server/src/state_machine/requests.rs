@@ -15,10 +15,11 @@ use tracing::{trace, Span};
 
 use crate::storage::{LocalSeedDictAddError, MaskScoreIncrError, StorageError, SumPartAddError};
 use xaynet_core::{
-    mask::MaskObject,
+    mask::{MaskObject, ModelShape},
     message::{Message, Payload, Update},
     LocalSeedDict,
     ParticipantPublicKey,
+    ParticipantTaskSignature,
     SumParticipantEphemeralPublicKey,
     SumParticipantPublicKey,
     UpdateParticipantPublicKey,
@@ -33,6 +34,8 @@ pub enum RequestError {
     MessageDiscarded,
     /// Invalid update: the model or scalar sent by the participant could not be aggregated.
     AggregationFailed,
+    /// The participant exceeded its update participation quota.
+    QuotaExceeded,
     /// The request could not be processed due to an internal error: {0}.
     InternalError(&'static str),
     /// Storage request failed: {0}.
@@ -52,6 +55,8 @@ pub struct SumRequest {
     pub participant_pk: SumParticipantPublicKey,
     /// The ephemeral public key of the participant.
     pub ephm_pk: SumParticipantEphemeralPublicKey,
+    /// The signature that proves the eligibility of the participant for the sum task.
+    pub sum_signature: ParticipantTaskSignature,
 }
 
 /// An update request.
@@ -63,6 +68,14 @@ pub struct UpdateRequest {
     pub local_seed_dict: LocalSeedDict,
     /// The masked model trained by the participant.
     pub masked_model: MaskObject,
+    /// The signature of the participant for the sum task.
+    pub sum_signature: ParticipantTaskSignature,
+    /// The signature that proves the eligibility of the participant for the update task.
+    pub update_signature: ParticipantTaskSignature,
+    /// The shape of the model trained by the participant, if sent along with the message.
+    ///
+    /// The shape is not part of the PET message, hence it is neither encrypted nor authenticated.
+    pub model_shape: Option<ModelShape>,
 }
 
 /// A sum2 request.
@@ -91,17 +104,22 @@ impl From<Message> for StateMachineRequest {
             Payload::Sum(sum) => StateMachineRequest::Sum(SumRequest {
                 participant_pk,
                 ephm_pk: sum.ephm_pk,
+                sum_signature: sum.sum_signature,
             }),
             Payload::Update(update) => {
                 let Update {
+                    sum_signature,
+                    update_signature,
                     local_seed_dict,
                     masked_model,
-                    ..
                 } = update;
                 StateMachineRequest::Update(UpdateRequest {
                     participant_pk,
                     local_seed_dict,
                     masked_model,
+                    sum_signature,
+                    update_signature,
+                    model_shape: None,
                 })
             }
             Payload::Sum2(sum2) => StateMachineRequest::Sum2(Sum2Request {
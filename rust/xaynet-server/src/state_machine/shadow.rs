@@ -0,0 +1,483 @@
+//! Shadow evaluation of a candidate PET configuration.
+//!
+//! The coordinator can evaluate a candidate configuration alongside the live one, see
+//! [`ShadowSettings`]. The messages of a round carry the task signatures of the participants,
+//! hence the eligibility of the participants who sent messages can be recomputed against the
+//! candidate probabilities. The evaluation only observes the accepted messages and is recorded at
+//! the end of a round in the metrics and in the round history (see [`RoundHistory`]), it is
+//! never published to the participants.
+//!
+//! [`ShadowSettings`]: crate::settings::ShadowSettings
+//! [`RoundHistory`]: crate::state_machine::coordinator::RoundHistory
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    metric,
+    metrics::Measurement,
+    settings::ShadowSettings,
+    state_machine::coordinator::CoordinatorState,
+};
+use xaynet_core::{
+    mask::{MaskConfig, MaskObject},
+    message::ToBytes,
+    ParticipantTaskSignature,
+};
+
+/// The candidate parameters of a shadow evaluation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowParameters {
+    /// The candidate probability of participants selected for the sum task.
+    pub sum: f64,
+    /// The candidate probability of participants selected for the update task.
+    pub update: f64,
+    /// The candidate masking configuration.
+    pub mask_config: MaskConfig,
+}
+
+impl From<ShadowSettings> for ShadowParameters {
+    fn from(shadow: ShadowSettings) -> Self {
+        let ShadowSettings { pet, mask } = shadow;
+        Self {
+            sum: pet.sum.prob,
+            update: pet.update.prob,
+            mask_config: mask.into(),
+        }
+    }
+}
+
+/// The task selection of the participants who sent accepted messages in a round.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskSelection {
+    /// The number of participants selected for the sum task.
+    pub sum: u64,
+    /// The number of participants selected for the update task.
+    pub update: u64,
+    /// The number of participants selected for no task.
+    pub none: u64,
+    /// The number of participants whose selection is unknown. A sum message only carries the sum
+    /// signature, hence a participant who is not selected for the sum task may or may not be
+    /// selected for the update task.
+    pub undetermined: u64,
+}
+
+/// The comparison of the live and the candidate configuration for a round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShadowReport {
+    /// The round of the evaluation.
+    pub round_id: u64,
+    /// The task selection under the live configuration.
+    pub live: TaskSelection,
+    /// The task selection under the candidate configuration.
+    pub candidate: TaskSelection,
+    /// The number of bytes of a masked model under the live masking configuration.
+    pub live_masked_model_bytes: usize,
+    /// The number of bytes of a masked model under the candidate masking configuration.
+    pub candidate_masked_model_bytes: usize,
+    /// The maximal number of models aggregated under the live masking configuration.
+    pub live_aggregation_capacity: usize,
+    /// The maximal number of models aggregated under the candidate masking configuration.
+    pub candidate_aggregation_capacity: usize,
+}
+
+impl ShadowReport {
+    /// Records the report in the logs and the metrics.
+    pub fn record(&self) {
+        info!(
+            "shadow evaluation of round {}: live selection {:?}, candidate selection {:?}",
+            self.round_id, self.live, self.candidate,
+        );
+        info!(
+            "shadow evaluation of round {}: masked model of {} bytes (candidate {} bytes), aggregation capacity of {} models (candidate {} models)",
+            self.round_id,
+            self.live_masked_model_bytes,
+            self.candidate_masked_model_bytes,
+            self.live_aggregation_capacity,
+            self.candidate_aggregation_capacity,
+        );
+
+        for (config, selection) in &[("live", self.live), ("candidate", self.candidate)] {
+            for (task, count) in &[
+                ("sum", selection.sum),
+                ("update", selection.update),
+                ("none", selection.none),
+                ("undetermined", selection.undetermined),
+            ] {
+                metric!(
+                    Measurement::ShadowTaskSelection,
+                    *count,
+                    ("round_id", self.round_id),
+                    ("config", *config),
+                    ("task", *task),
+                );
+            }
+        }
+        for (config, bytes, capacity) in &[
+            (
+                "live",
+                self.live_masked_model_bytes,
+                self.live_aggregation_capacity,
+            ),
+            (
+                "candidate",
+                self.candidate_masked_model_bytes,
+                self.candidate_aggregation_capacity,
+            ),
+        ] {
+            metric!(
+                Measurement::ShadowMaskedModelBytes,
+                *bytes as u64,
+                ("round_id", self.round_id),
+                ("config", *config),
+            );
+            metric!(
+                Measurement::ShadowAggregationCapacity,
+                *capacity as u64,
+                ("round_id", self.round_id),
+                ("config", *config),
+            );
+        }
+    }
+}
+
+/// The shadow evaluation of a candidate configuration.
+#[derive(Debug)]
+pub struct Shadow {
+    /// The candidate parameters.
+    params: ShadowParameters,
+    /// The task selection of the current round under the live configuration.
+    live: TaskSelection,
+    /// The task selection of the current round under the candidate configuration.
+    candidate: TaskSelection,
+}
+
+impl Shadow {
+    /// Creates a new shadow evaluation of the given candidate parameters.
+    pub fn new(params: ShadowParameters) -> Self {
+        Self {
+            params,
+            live: TaskSelection::default(),
+            candidate: TaskSelection::default(),
+        }
+    }
+
+    /// Starts the evaluation of a new round.
+    pub fn reset(&mut self) {
+        self.live = TaskSelection::default();
+        self.candidate = TaskSelection::default();
+    }
+
+    /// Observes an accepted sum message.
+    pub fn observe_sum(&mut self, sum_signature: &ParticipantTaskSignature) {
+        self.live.sum += 1;
+        if sum_signature.is_eligible(self.params.sum) {
+            self.candidate.sum += 1;
+        } else {
+            self.candidate.undetermined += 1;
+        }
+    }
+
+    /// Observes an accepted update message.
+    pub fn observe_update(
+        &mut self,
+        sum_signature: &ParticipantTaskSignature,
+        update_signature: &ParticipantTaskSignature,
+    ) {
+        self.live.update += 1;
+        if sum_signature.is_eligible(self.params.sum) {
+            self.candidate.sum += 1;
+        } else if update_signature.is_eligible(self.params.update) {
+            self.candidate.update += 1;
+        } else {
+            self.candidate.none += 1;
+        }
+    }
+
+    /// Compares the candidate configuration with the live one for the current round.
+    pub fn report(&self, state: &CoordinatorState) -> ShadowReport {
+        let model_length = state.round_params.model_length;
+        let live_config = state.round_params.mask_config.vect;
+        let candidate_config = self.params.mask_config;
+        ShadowReport {
+            round_id: state.round_id,
+            live: self.live,
+            candidate: self.candidate,
+            live_masked_model_bytes: masked_model_bytes(live_config, model_length),
+            candidate_masked_model_bytes: masked_model_bytes(candidate_config, model_length),
+            live_aggregation_capacity: live_config.model_type.max_nb_models(),
+            candidate_aggregation_capacity: candidate_config.model_type.max_nb_models(),
+        }
+    }
+}
+
+/// Gets the number of bytes of a serialized masked model of the given length.
+fn masked_model_bytes(config: MaskConfig, model_length: usize) -> usize {
+    // the numbers are counted separately, to avoid allocating a mask of the model length
+    MaskObject::empty(config.into(), 0).buffer_length() + config.bytes_per_number() * model_length
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::task::JoinHandle;
+    use xaynet_core::{
+        crypto::ByteObject,
+        mask::{BoundType, DataType, GroupType, MaskSeed, ModelType},
+        message::Message,
+        SeedDict,
+        SumDict,
+    };
+
+    use crate::{
+        state_machine::{
+            events::{DictionaryUpdate, ModelUpdate},
+            phases::{PhaseName, PhaseState, Sum},
+            requests::RequestError,
+            tests::{
+                utils::{
+                    compose_sum2_message,
+                    compose_sum_message,
+                    compose_update_message,
+                    enable_logging,
+                    init_shared,
+                    EventSnapshot,
+                },
+                CoordinatorStateBuilder,
+                EventBusBuilder,
+            },
+            StateMachine,
+        },
+        storage::{
+            tests::{utils::create_mask, MockCoordinatorStore, MockModelStore},
+            LocalSeedDictAdd,
+            MaskScoreIncr,
+            Storage,
+            Store,
+            SumPartAdd,
+        },
+    };
+
+    const SUM_MESSAGES: u64 = 3;
+    const UPDATE_MESSAGES: u64 = 4;
+
+    fn candidate(sum: f64, update: f64) -> ShadowParameters {
+        ShadowParameters {
+            sum,
+            update,
+            mask_config: MaskConfig {
+                group_type: GroupType::Integer,
+                data_type: DataType::F64,
+                bound_type: BoundType::B2,
+                model_type: ModelType::M6,
+            },
+        }
+    }
+
+    /// The observable outcome of a round.
+    struct Round {
+        state: CoordinatorState,
+        events: EventSnapshot,
+        responses: Vec<String>,
+        report: Option<ShadowReport>,
+    }
+
+    fn store() -> impl Storage {
+        let mut cs = MockCoordinatorStore::new();
+        cs.expect_add_sum_participant()
+            .times(SUM_MESSAGES as usize)
+            .returning(|_, _| Ok(SumPartAdd(Ok(()))));
+        cs.expect_sum_dict()
+            .return_once(|| Ok(Some(SumDict::new())));
+        cs.expect_add_local_seed_dict()
+            .times(UPDATE_MESSAGES as usize)
+            .returning(|_, _| Ok(LocalSeedDictAdd(Ok(()))));
+        cs.expect_seed_dict()
+            .return_once(|| Ok(Some(SeedDict::new())));
+        cs.expect_incr_mask_score()
+            .times(SUM_MESSAGES as usize)
+            .returning(|_, _| Ok(MaskScoreIncr(Ok(()))));
+        cs.expect_best_masks()
+            .returning(|| Ok(Some(vec![(create_mask(1, 1), SUM_MESSAGES)])));
+        #[cfg(feature = "model-persistence")]
+        cs.expect_set_latest_global_model_id().returning(|_| Ok(()));
+
+        #[allow(unused_mut)]
+        let mut ms = MockModelStore::new();
+        #[cfg(feature = "model-persistence")]
+        ms.expect_set_global_model()
+            .returning(|_, _, _| Ok("id".to_string()));
+        Store::new(cs, ms)
+    }
+
+    /// Runs a round from the sum phase to the end of the unmask phase.
+    async fn run_round(state: CoordinatorState, shadow: Option<Shadow>) -> Round {
+        let (event_publisher, event_subscriber) = EventBusBuilder::new(&state)
+            .broadcast_phase(PhaseName::Idle)
+            .broadcast_sum_dict(DictionaryUpdate::Invalidate)
+            .broadcast_seed_dict(DictionaryUpdate::Invalidate)
+            .broadcast_model(ModelUpdate::Invalidate)
+            .build();
+        let (shared, request_tx) = init_shared(state, store(), event_publisher);
+        let shared = shared.with_shadow(shadow);
+
+        let send = |messages: Vec<Message>| -> Vec<JoinHandle<Result<(), RequestError>>> {
+            messages
+                .into_iter()
+                .map(|message| {
+                    let request_tx = request_tx.clone();
+                    tokio::spawn(async move { request_tx.msg(&message).await })
+                })
+                .collect()
+        };
+        let mut pending = Vec::new();
+
+        let state_machine = StateMachine::from(PhaseState::<Sum, _>::new(shared));
+        pending.extend(send(
+            (0..SUM_MESSAGES).map(|_| compose_sum_message()).collect(),
+        ));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_update());
+
+        pending.extend(send(
+            (0..UPDATE_MESSAGES)
+                .map(|_| compose_update_message(create_mask(1, 1)))
+                .collect(),
+        ));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_sum2());
+
+        pending.extend(send(
+            (0..SUM_MESSAGES).map(|_| compose_sum2_message()).collect(),
+        ));
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_unmask());
+
+        // the evaluation of the round is recorded in the round history
+        let state_machine = state_machine.next().await.unwrap();
+        assert!(state_machine.is_idle());
+        let mut state = state_machine.as_ref().clone();
+        let report = state.round_history.last_shadow_evaluation.take();
+
+        let mut responses = Vec::new();
+        for response in pending {
+            responses.push(format!("{:?}", response.await.unwrap()));
+        }
+        Round {
+            state,
+            events: EventSnapshot::from(&event_subscriber),
+            responses,
+            report,
+        }
+    }
+
+    fn coordinator_state() -> CoordinatorState {
+        CoordinatorStateBuilder::new()
+            .with_round_id(1)
+            .with_sum_count_min(SUM_MESSAGES)
+            .with_sum_count_max(SUM_MESSAGES)
+            .with_sum_time_min(0)
+            .with_update_count_min(UPDATE_MESSAGES)
+            .with_update_count_max(UPDATE_MESSAGES)
+            .with_update_time_min(0)
+            .with_sum2_count_min(SUM_MESSAGES)
+            .with_sum2_count_max(SUM_MESSAGES)
+            .with_sum2_time_min(0)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_shadow_selection() {
+        enable_logging();
+
+        // the eligibility is a threshold on the hash of a task signature, which is never above
+        // `1` and never below `0` for the task signatures of the simulated participants
+        let cases = vec![
+            (
+                candidate(1., 0.),
+                TaskSelection {
+                    sum: SUM_MESSAGES + UPDATE_MESSAGES,
+                    ..TaskSelection::default()
+                },
+            ),
+            (
+                candidate(0., 1.),
+                TaskSelection {
+                    update: UPDATE_MESSAGES,
+                    undetermined: SUM_MESSAGES,
+                    ..TaskSelection::default()
+                },
+            ),
+            (
+                candidate(0., 0.),
+                TaskSelection {
+                    none: UPDATE_MESSAGES,
+                    undetermined: SUM_MESSAGES,
+                    ..TaskSelection::default()
+                },
+            ),
+        ];
+
+        for (params, expected) in cases {
+            let round = run_round(coordinator_state(), Some(Shadow::new(params))).await;
+            let report = round.report.unwrap();
+            assert_eq!(report.round_id, 1);
+            assert_eq!(
+                report.live,
+                TaskSelection {
+                    sum: SUM_MESSAGES,
+                    update: UPDATE_MESSAGES,
+                    ..TaskSelection::default()
+                }
+            );
+            assert_eq!(report.candidate, expected);
+
+            let model_length = round.state.round_params.model_length;
+            let masked_model = |config: MaskConfig| {
+                MaskSeed::generate()
+                    .derive_mask(model_length, config.into())
+                    .buffer_length()
+            };
+            let live_config = round.state.round_params.mask_config.vect;
+            assert_eq!(report.live_masked_model_bytes, masked_model(live_config));
+            assert_eq!(
+                report.candidate_masked_model_bytes,
+                masked_model(params.mask_config)
+            );
+            assert_eq!(report.live_aggregation_capacity, 1_000);
+            assert_eq!(report.candidate_aggregation_capacity, 1_000_000);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shadow_doesnt_affect_live_round() {
+        enable_logging();
+
+        let state = coordinator_state();
+        let live = run_round(state.clone(), None).await;
+        let shadowed = run_round(state, Some(Shadow::new(candidate(0.5, 0.5)))).await;
+
+        // apart from the recorded evaluation, the round is the same
+        assert!(live.report.is_none());
+        assert!(shadowed.report.is_some());
+        assert_eq!(
+            bincode::serialize(&shadowed.state).unwrap(),
+            bincode::serialize(&live.state).unwrap(),
+        );
+        assert_eq!(shadowed.events, live.events);
+        assert_eq!(shadowed.responses, live.responses);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut shadow = Shadow::new(candidate(1., 1.));
+        shadow.observe_sum(&ParticipantTaskSignature::zeroed());
+        assert_eq!(shadow.live.sum, 1);
+        assert_eq!(shadow.candidate.sum, 1);
+
+        shadow.reset();
+        assert_eq!(shadow.live, TaskSelection::default());
+        assert_eq!(shadow.candidate, TaskSelection::default());
+    }
+}
@@ -1,6 +1,9 @@
 use xaynet_core::{common::RoundSeed, crypto::EncryptKeyPair, mask::MaskConfig};
 
-use crate::state_machine::coordinator::CoordinatorState;
+use crate::{
+    settings::TrainingPlanSettings,
+    state_machine::coordinator::{CoordinatorState, MaskToleranceParameters, QuotaParameters},
+};
 
 use super::utils::{mask_settings, model_settings, pet_settings};
 
@@ -115,4 +118,36 @@ impl CoordinatorStateBuilder {
         self.state.sum2.time.max = max;
         self
     }
+
+    pub fn with_model_update_statistics(mut self, enabled: bool) -> Self {
+        self.state.model_update_statistics = enabled;
+        self
+    }
+
+    pub fn with_export_round_archive(mut self, enabled: bool) -> Self {
+        self.state.export_round_archive = enabled;
+        self
+    }
+
+    pub fn with_update_quota(mut self, max: u64, window: u64) -> Self {
+        self.state.update_quota = Some(QuotaParameters { max, window });
+        self
+    }
+
+    pub fn with_mask_tolerance(mut self, max_distance: u64) -> Self {
+        self.state.mask_tolerance = Some(MaskToleranceParameters { max_distance });
+        self
+    }
+
+    pub fn with_training_plans(mut self, plans: Vec<TrainingPlanSettings>) -> Self {
+        self.state = self.state.with_training_plans(plans);
+        self
+    }
+
+    pub fn with_completed_training_rounds(mut self, current: usize, completed_rounds: u64) -> Self {
+        let plans = self.state.training_plans.as_mut().unwrap();
+        plans.current = current;
+        plans.completed_rounds = completed_rounds;
+        self
+    }
 }
@@ -21,6 +21,8 @@ impl EventBusBuilder {
             state.round_params.clone(),
             PhaseName::Idle,
             ModelUpdate::Invalidate,
+            state.training_plan_status(),
+            state.round_history,
         );
 
         Self {
@@ -11,7 +11,6 @@ use crate::{
         phases::PhaseName,
     },
     storage::tests::utils::create_global_model,
-    storage::ModelStorage,
 };
 use crate::{
     state_machine::{
@@ -19,19 +18,20 @@ use crate::{
         initializer::StateMachineInitializer,
         tests::utils::{mask_settings, model_settings, pet_settings},
     },
-    storage::{tests::init_store, CoordinatorStorage},
+    storage::{
+        tests::{init_in_memory_store, init_store, MockCoordinatorStore, MockModelStore},
+        Storage,
+        Store,
+    },
 };
 
 #[cfg(feature = "model-persistence")]
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_no_restore() {
-    let store = init_store().await;
+async fn state_machine_initializer_no_restore(store: impl Storage) {
     let smi = StateMachineInitializer::new(
         pet_settings(),
         mask_settings(),
         model_settings(),
+        None,
         RestoreSettings { enable: false },
         store,
     );
@@ -57,15 +57,12 @@ async fn integration_state_machine_initializer_no_restore() {
 }
 
 #[cfg(feature = "model-persistence")]
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_no_state() {
-    let store = init_store().await;
+async fn state_machine_initializer_no_state(store: impl Storage) {
     let smi = StateMachineInitializer::new(
         pet_settings(),
         mask_settings(),
         model_settings(),
+        None,
         RestoreSettings { enable: true },
         store,
     );
@@ -91,10 +88,7 @@ async fn integration_state_machine_initializer_no_state() {
 }
 
 #[cfg(feature = "model-persistence")]
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_without_global_model() {
+async fn state_machine_initializer_without_global_model(mut store: impl Storage) {
     let pet_settings = pet_settings();
     let mask_settings = mask_settings();
     let model_settings = model_settings();
@@ -103,7 +97,6 @@ async fn integration_state_machine_initializer_without_global_model() {
     // initialized with the coordinator state in the store
     // if we don't update the round_id we can't check if the state in the store was used or if the state was reset
     // because in both cases the round id will be 0
-    let mut store = init_store().await;
     let mut state = CoordinatorState::new(pet_settings, mask_settings, model_settings.clone());
     let new_round_id = 5;
     state.round_id = new_round_id;
@@ -113,6 +106,7 @@ async fn integration_state_machine_initializer_without_global_model() {
         pet_settings,
         mask_settings,
         model_settings,
+        None,
         RestoreSettings { enable: true },
         store,
     );
@@ -138,15 +132,11 @@ async fn integration_state_machine_initializer_without_global_model() {
 }
 
 #[cfg(feature = "model-persistence")]
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_with_global_model() {
+async fn state_machine_initializer_with_global_model(mut store: impl Storage) {
     let pet_settings = pet_settings();
     let mask_settings = mask_settings();
     let model_settings = model_settings();
 
-    let mut store = init_store().await;
     let mut state = CoordinatorState::new(pet_settings, mask_settings, model_settings.clone());
     let new_round_id = 7;
     state.round_id = new_round_id;
@@ -171,6 +161,7 @@ async fn integration_state_machine_initializer_with_global_model() {
         pet_settings,
         mask_settings,
         model_settings,
+        None,
         RestoreSettings { enable: true },
         store,
     );
@@ -198,15 +189,11 @@ async fn integration_state_machine_initializer_with_global_model() {
 }
 
 #[cfg(feature = "model-persistence")]
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_failed_because_of_wrong_size() {
+async fn state_machine_initializer_failed_because_of_wrong_size(mut store: impl Storage) {
     let pet_settings = pet_settings();
     let mask_settings = mask_settings();
     let model_settings = model_settings();
 
-    let mut store = init_store().await;
     let mut state = CoordinatorState::new(pet_settings, mask_settings, model_settings.clone());
     let new_round_id = 9;
     state.round_id = new_round_id;
@@ -231,6 +218,7 @@ async fn integration_state_machine_initializer_failed_because_of_wrong_size() {
         pet_settings,
         mask_settings,
         model_settings,
+        None,
         RestoreSettings { enable: true },
         store,
     );
@@ -244,15 +232,11 @@ async fn integration_state_machine_initializer_failed_because_of_wrong_size() {
 }
 
 #[cfg(feature = "model-persistence")]
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_failed_to_find_global_model() {
+async fn state_machine_initializer_failed_to_find_global_model(mut store: impl Storage) {
     let pet_settings = pet_settings();
     let mask_settings = mask_settings();
     let model_settings = model_settings();
 
-    let mut store = init_store().await;
     let mut state = CoordinatorState::new(pet_settings, mask_settings, model_settings.clone());
     let new_round_id = 11;
     state.round_id = new_round_id;
@@ -269,6 +253,7 @@ async fn integration_state_machine_initializer_failed_to_find_global_model() {
         pet_settings,
         mask_settings,
         model_settings,
+        None,
         RestoreSettings { enable: true },
         store,
     );
@@ -281,15 +266,11 @@ async fn integration_state_machine_initializer_failed_to_find_global_model() {
     ));
 }
 
-#[tokio::test]
-#[serial]
-#[ignore]
-async fn integration_state_machine_initializer_reset_state() {
+async fn state_machine_initializer_reset_state(mut store: impl Storage) {
     let pet_settings = pet_settings();
     let mask_settings = mask_settings();
     let model_settings = model_settings();
 
-    let mut store = init_store().await;
     let state = CoordinatorState::new(pet_settings, mask_settings, model_settings.clone());
     store.set_coordinator_state(&state).await.unwrap();
 
@@ -297,6 +278,7 @@ async fn integration_state_machine_initializer_reset_state() {
         pet_settings,
         mask_settings,
         model_settings,
+        None,
         #[cfg(feature = "model-persistence")]
         RestoreSettings { enable: true },
         store.clone(),
@@ -311,3 +293,90 @@ async fn integration_state_machine_initializer_reset_state() {
     assert!(store.latest_global_model_id().await.unwrap().is_none());
     assert_eq!(store.number_of_unique_masks().await.unwrap(), 0);
 }
+
+async fn state_machine_initializer_preflight(mut store: impl Storage) {
+    let mut smi = StateMachineInitializer::new(
+        pet_settings(),
+        mask_settings(),
+        model_settings(),
+        None,
+        #[cfg(feature = "model-persistence")]
+        RestoreSettings { enable: true },
+        store.clone(),
+    );
+
+    let report = smi.preflight().await.unwrap();
+
+    assert!(report.passed(), "{}", report);
+    assert!(store.coordinator_state().await.unwrap().is_none());
+}
+
+/// Generates an integration test per coordinator storage backend for each of the given test
+/// functions. The Redis tests require a running Redis instance, the in-memory tests only require
+/// the model storage.
+macro_rules! integration_tests {
+    ($($(#[$attr:meta])* $name:ident),+ $(,)?) => {
+        paste::paste! {
+            $(
+                $(#[$attr])*
+                #[tokio::test]
+                #[serial]
+                #[ignore]
+                async fn [<integration_ $name _redis>]() {
+                    $name(init_store().await).await;
+                }
+
+                $(#[$attr])*
+                #[tokio::test]
+                #[serial]
+                #[cfg_attr(feature = "model-persistence", ignore)]
+                async fn [<integration_ $name _in_memory>]() {
+                    $name(init_in_memory_store().await).await;
+                }
+            )+
+        }
+    };
+}
+
+integration_tests!(
+    #[cfg(feature = "model-persistence")]
+    state_machine_initializer_no_restore,
+    #[cfg(feature = "model-persistence")]
+    state_machine_initializer_no_state,
+    #[cfg(feature = "model-persistence")]
+    state_machine_initializer_without_global_model,
+    #[cfg(feature = "model-persistence")]
+    state_machine_initializer_with_global_model,
+    #[cfg(feature = "model-persistence")]
+    state_machine_initializer_failed_because_of_wrong_size,
+    #[cfg(feature = "model-persistence")]
+    state_machine_initializer_failed_to_find_global_model,
+    state_machine_initializer_reset_state,
+    state_machine_initializer_preflight,
+);
+
+#[tokio::test]
+async fn test_state_machine_initializer_preflight_failure() {
+    let mut cs = MockCoordinatorStore::new();
+    cs.expect_is_ready().returning(|| Ok(()));
+    cs.expect_probe_coordinator_state()
+        .returning(|_| Err(anyhow::anyhow!("permission denied")));
+    let mut ms = MockModelStore::new();
+    ms.expect_probe_write().returning(|| Ok(()));
+    let mut smi = StateMachineInitializer::new(
+        pet_settings(),
+        mask_settings(),
+        model_settings(),
+        None,
+        #[cfg(feature = "model-persistence")]
+        RestoreSettings { enable: true },
+        Store::new(cs, ms),
+    );
+
+    let report = smi.preflight().await.unwrap();
+
+    assert!(!report.passed());
+    assert!(report.checks[0].passed());
+    assert_eq!(report.checks[1].name, "coordinator state round-trip");
+    assert!(!report.checks[1].passed());
+}
@@ -25,6 +25,7 @@ use crate::{
         PetSettingsSum2,
         PetSettingsTime,
         PetSettingsUpdate,
+        TrainingPlanSettings,
     },
     state_machine::{
         coordinator::CoordinatorState,
@@ -55,10 +56,13 @@ pub fn pet_settings() -> PetSettings {
             prob: 0.5,
             count: PetSettingsCount { min: 3, max: 1000 },
             time: PetSettingsTime { min: 1, max: 2 },
+            quota: None,
+            max_sample_count: None,
         },
         sum2: PetSettingsSum2 {
             count: PetSettingsCount { min: 1, max: 100 },
             time: PetSettingsTime { min: 1, max: 2 },
+            mask_tolerance: None,
         },
     }
 }
@@ -73,7 +77,22 @@ pub fn mask_settings() -> MaskSettings {
 }
 
 pub fn model_settings() -> ModelSettings {
-    ModelSettings { length: 1 }
+    ModelSettings {
+        length: 1,
+        update_statistics: false,
+        export_round_archive: false,
+    }
+}
+
+pub fn training_plan_settings(id: &str, rounds: u64, model_length: usize) -> TrainingPlanSettings {
+    TrainingPlanSettings {
+        id: id.to_string(),
+        rounds,
+        model_length,
+        sum_prob: 0.2,
+        update_prob: 0.6,
+        mask: mask_settings(),
+    }
 }
 
 pub fn init_shared<T>(
@@ -248,10 +267,13 @@ fn test_initial_settings() {
             prob: 0.5,
             count: PetSettingsCount { min: 3, max: 1000 },
             time: PetSettingsTime { min: 1, max: 2 },
+            quota: None,
+            max_sample_count: None,
         },
         sum2: PetSettingsSum2 {
             count: PetSettingsCount { min: 1, max: 100 },
             time: PetSettingsTime { min: 1, max: 2 },
+            mask_tolerance: None,
         },
     };
 
@@ -276,7 +298,11 @@ fn test_initial_settings() {
         WARNING
     );
 
-    let model = ModelSettings { length: 1 };
+    let model = ModelSettings {
+        length: 1,
+        update_statistics: false,
+        export_round_archive: false,
+    };
 
     assert_eq!(
         model,
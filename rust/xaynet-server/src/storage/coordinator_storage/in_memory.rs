@@ -0,0 +1,1076 @@
+//! An in-memory [`CoordinatorStorage`] backend.
+//!
+//! The coordinator data is kept in process memory and is lost when the coordinator stops.
+//! The backend is meant for tests and single-node deployments in which standing up Redis is
+//! not worth it.
+//!
+//! # Data Model
+//!
+//!```text
+//! Data {
+//!     coordinator_state: Option<CoordinatorState>,
+//!     latest_global_model_id: Option<String>,
+//!     // Sum dict
+//!     sum_dict: { SumParticipantPublicKey: SumParticipantEphemeralPublicKey },
+//!     // Seed dict
+//!     update_participants: { UpdateParticipantPublicKey },
+//!     seed_dict: { SumParticipantPublicKey: { UpdateParticipantPublicKey: EncryptedMaskSeed } },
+//!     // Mask dict
+//!     mask_submitted: { SumParticipantPublicKey },
+//!     mask_dict: { bincode encoded mask: score },
+//!     // Update participation quota
+//!     update_quota: { sha256(UpdateParticipantPublicKey): { round_id: participation time } },
+//! }
+//! ```
+//!
+//! All the operations lock the whole data, hence the checks that the Redis backend runs in
+//! Lua scripts are atomic as well.
+//!
+//! The update participation quota is not part of the coordinator data: it is neither deleted
+//! with the coordinator data, nor stored beyond the quota window. The public keys of the update
+//! participants are only stored hashed.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use sodiumoxide::crypto::hash::sha256;
+use tracing::debug;
+
+use crate::{
+    state_machine::coordinator::CoordinatorState,
+    storage::{
+        CoordinatorStorage,
+        LocalSeedDictAdd,
+        LocalSeedDictAddError,
+        MaskScoreIncr,
+        MaskScoreIncrError,
+        StorageResult,
+        SumPartAdd,
+        SumPartAddError,
+    },
+};
+use xaynet_core::{
+    crypto::ByteObject,
+    mask::MaskObject,
+    LocalSeedDict,
+    SeedDict,
+    SumDict,
+    SumParticipantEphemeralPublicKey,
+    SumParticipantPublicKey,
+    UpdateParticipantPublicKey,
+};
+
+/// In-memory client.
+///
+/// Clones of the client share the same data.
+#[derive(Clone, Default)]
+pub struct Client {
+    data: Arc<Mutex<Data>>,
+}
+
+#[derive(Default)]
+struct Data {
+    coordinator_state: Option<CoordinatorState>,
+    latest_global_model_id: Option<String>,
+    sum_dict: SumDict,
+    update_participants: HashSet<UpdateParticipantPublicKey>,
+    seed_dict: SeedDict,
+    mask_submitted: HashSet<SumParticipantPublicKey>,
+    // the masks are keyed by their serialization, like the members of the Redis sorted set
+    mask_dict: BTreeMap<Vec<u8>, u64>,
+    update_quota: HashMap<sha256::Digest, BTreeMap<u64, u64>>,
+}
+
+impl Data {
+    fn delete_dicts(&mut self) {
+        self.sum_dict.clear();
+        self.update_participants.clear();
+        self.seed_dict.clear();
+        self.mask_submitted.clear();
+        self.mask_dict.clear();
+    }
+}
+
+impl Client {
+    /// Creates a new in-memory client without any data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn data(&self) -> StorageResult<MutexGuard<'_, Data>> {
+        self.data
+            .lock()
+            .map_err(|_| anyhow::anyhow!("the in-memory coordinator storage is poisoned"))
+    }
+}
+
+#[async_trait]
+impl CoordinatorStorage for Client {
+    async fn set_coordinator_state(&mut self, state: &CoordinatorState) -> StorageResult<()> {
+        debug!("set coordinator state");
+        self.data()?.coordinator_state = Some(state.clone());
+        Ok(())
+    }
+
+    async fn coordinator_state(&mut self) -> StorageResult<Option<CoordinatorState>> {
+        Ok(self.data()?.coordinator_state.clone())
+    }
+
+    async fn probe_coordinator_state(
+        &mut self,
+        state: &CoordinatorState,
+    ) -> StorageResult<Option<CoordinatorState>> {
+        debug!("probe coordinator state");
+        // the data is only kept in memory, there is nothing to persist
+        self.data().map(|_| Some(state.clone()))
+    }
+
+    async fn add_sum_participant(
+        &mut self,
+        pk: &SumParticipantPublicKey,
+        ephm_pk: &SumParticipantEphemeralPublicKey,
+    ) -> StorageResult<SumPartAdd> {
+        debug!("add sum participant with pk {:?}", pk);
+        let mut data = self.data()?;
+        if data.sum_dict.contains_key(pk) {
+            return Ok(SumPartAdd(Err(SumPartAddError::AlreadyExists)));
+        }
+        data.sum_dict.insert(*pk, *ephm_pk);
+        Ok(SumPartAdd(Ok(())))
+    }
+
+    async fn sum_dict(&mut self) -> StorageResult<Option<SumDict>> {
+        debug!("get sum dictionary");
+        let data = self.data()?;
+        if data.sum_dict.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(data.sum_dict.clone()))
+    }
+
+    async fn add_local_seed_dict(
+        &mut self,
+        update_pk: &UpdateParticipantPublicKey,
+        local_seed_dict: &LocalSeedDict,
+    ) -> StorageResult<LocalSeedDictAdd> {
+        debug!(
+            "update seed dictionary for update participant with pk {:?}",
+            update_pk
+        );
+        let mut data = self.data()?;
+
+        // check if the local seed dict has the same length as the sum_dict
+        if local_seed_dict.len() != data.sum_dict.len() {
+            return Ok(LocalSeedDictAdd(Err(LocalSeedDictAddError::LengthMisMatch)));
+        }
+
+        // check if all pks of the local seed dict exists in sum_dict
+        if !local_seed_dict
+            .keys()
+            .all(|sum_pk| data.sum_dict.contains_key(sum_pk))
+        {
+            return Ok(LocalSeedDictAdd(Err(
+                LocalSeedDictAddError::UnknownSumParticipant,
+            )));
+        }
+
+        // check if the update pk already exists (i.e. the local seed dict has already been
+        // submitted)
+        if !data.update_participants.insert(*update_pk) {
+            return Ok(LocalSeedDictAdd(Err(
+                LocalSeedDictAddError::UpdatePkAlreadySubmitted,
+            )));
+        }
+
+        if local_seed_dict.keys().any(|sum_pk| {
+            matches!(data.seed_dict.get(sum_pk), Some(seeds) if seeds.contains_key(update_pk))
+        }) {
+            // This condition should never apply.
+            // If this condition is true, it is an indication that the data is corrupted.
+            // Unlike the Redis backend, nothing is written in that case.
+            data.update_participants.remove(update_pk);
+            return Ok(LocalSeedDictAdd(Err(
+                LocalSeedDictAddError::UpdatePkAlreadyExistsInUpdateSeedDict,
+            )));
+        }
+
+        // update the seed dict
+        for (sum_pk, seed) in local_seed_dict {
+            data.seed_dict
+                .entry(*sum_pk)
+                .or_default()
+                .insert(*update_pk, seed.clone());
+        }
+
+        Ok(LocalSeedDictAdd(Ok(())))
+    }
+
+    async fn seed_dict(&mut self) -> StorageResult<Option<SeedDict>> {
+        debug!("get seed dictionary");
+        let data = self.data()?;
+        if data.sum_dict.is_empty() {
+            return Ok(None);
+        }
+
+        // every sum participant has an entry, even if no seeds have been added for it yet
+        let seed_dict = data
+            .sum_dict
+            .keys()
+            .map(|sum_pk| {
+                let seeds = data.seed_dict.get(sum_pk).cloned().unwrap_or_default();
+                (*sum_pk, seeds)
+            })
+            .collect();
+
+        Ok(Some(seed_dict))
+    }
+
+    async fn incr_mask_score(
+        &mut self,
+        sum_pk: &SumParticipantPublicKey,
+        mask: &MaskObject,
+    ) -> StorageResult<MaskScoreIncr> {
+        debug!("increment mask count");
+        let mask = bincode::serialize(mask)?;
+        let mut data = self.data()?;
+
+        // check if the client participated in sum phase
+        //
+        // Note: we cannot delete the sum_pk in the sum_dict because we
+        // need the sum_dict later to delete the seed_dict
+        if !data.sum_dict.contains_key(sum_pk) {
+            return Ok(MaskScoreIncr(Err(MaskScoreIncrError::UnknownSumPk)));
+        }
+
+        // check if sum participant has not already submitted a mask
+        if !data.mask_submitted.insert(*sum_pk) {
+            return Ok(MaskScoreIncr(Err(MaskScoreIncrError::MaskAlreadySubmitted)));
+        }
+
+        *data.mask_dict.entry(mask).or_default() += 1;
+
+        Ok(MaskScoreIncr(Ok(())))
+    }
+
+    async fn best_masks(&mut self) -> StorageResult<Option<Vec<(MaskObject, u64)>>> {
+        debug!("get best masks");
+        let data = self.data()?;
+        if data.mask_dict.is_empty() {
+            return Ok(None);
+        }
+
+        // like the Redis sorted set, masks with the same score are ordered lexicographically
+        // in reverse
+        let mut masks = data.mask_dict.iter().rev().collect::<Vec<_>>();
+        masks.sort_by(|(_, score_1), (_, score_2)| score_2.cmp(score_1));
+        let masks = masks
+            .into_iter()
+            .take(2)
+            .map(|(mask, score)| Ok((bincode::deserialize(mask)?, *score)))
+            .collect::<StorageResult<_>>()?;
+
+        Ok(Some(masks))
+    }
+
+    async fn top_masks(&mut self, limit: usize) -> StorageResult<Vec<(MaskObject, u64)>> {
+        debug!("get top masks");
+        let data = self.data()?;
+        let mut masks = data.mask_dict.iter().rev().collect::<Vec<_>>();
+        masks.sort_by(|(_, score_1), (_, score_2)| score_2.cmp(score_1));
+        masks
+            .into_iter()
+            .take(limit)
+            .map(|(mask, score)| Ok((bincode::deserialize(mask)?, *score)))
+            .collect()
+    }
+
+    async fn number_of_unique_masks(&mut self) -> StorageResult<u64> {
+        debug!("get number of unique masks");
+        Ok(self.data()?.mask_dict.len() as u64)
+    }
+
+    async fn delete_coordinator_data(&mut self) -> StorageResult<()> {
+        debug!("flush coordinator data");
+        let mut data = self.data()?;
+        data.delete_dicts();
+        data.coordinator_state = None;
+        data.latest_global_model_id = None;
+        Ok(())
+    }
+
+    async fn delete_dicts(&mut self) -> StorageResult<()> {
+        debug!("flush all dictionaries");
+        self.data()?.delete_dicts();
+        Ok(())
+    }
+
+    async fn set_latest_global_model_id(&mut self, global_model_id: &str) -> StorageResult<()> {
+        debug!("set latest global model with id {}", global_model_id);
+        self.data()?.latest_global_model_id = Some(global_model_id.to_string());
+        Ok(())
+    }
+
+    async fn latest_global_model_id(&mut self) -> StorageResult<Option<String>> {
+        debug!("get latest global model id");
+        Ok(self.data()?.latest_global_model_id.clone())
+    }
+
+    async fn add_update_participation(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        round_id: u64,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64> {
+        debug!("add update participation in round {}", round_id);
+        let start = now.saturating_sub(window);
+        let mut data = self.data()?;
+
+        // forget the participations of all the participants that left the window, so that none
+        // is stored beyond it
+        data.update_quota.retain(|_, participations| {
+            participations.retain(|_, participated_at| *participated_at > start);
+            !participations.is_empty()
+        });
+
+        // keep the time of the first participation in a round
+        let participations = data
+            .update_quota
+            .entry(sha256::hash(pk.as_slice()))
+            .or_default();
+        participations.entry(round_id).or_insert(now);
+
+        Ok(participations.len() as u64)
+    }
+
+    async fn update_participations(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64> {
+        debug!("get update participations");
+        let start = now.saturating_sub(window);
+        let count = self
+            .data()?
+            .update_quota
+            .get(&sha256::hash(pk.as_slice()))
+            .map_or(0, |participations| {
+                participations
+                    .values()
+                    .filter(|participated_at| **participated_at > start)
+                    .count()
+            });
+        Ok(count as u64)
+    }
+
+    async fn is_ready(&mut self) -> StorageResult<()> {
+        self.data().map(|_| ())
+    }
+}
+
+#[cfg(test)]
+// Functions that are not needed in the state machine but handy for testing.
+impl Client {
+    // Removes an entry in the [`SumDict`].
+    //
+    // Returns the number of removed entries.
+    pub async fn remove_sum_dict_entry(
+        &mut self,
+        pk: &SumParticipantPublicKey,
+    ) -> StorageResult<u64> {
+        Ok(self.data()?.sum_dict.remove(pk).map_or(0, |_| 1))
+    }
+
+    // Returns the length of the [`SumDict`].
+    pub async fn sum_dict_len(&mut self) -> StorageResult<u64> {
+        Ok(self.data()?.sum_dict.len() as u64)
+    }
+
+    // Returns the [`SumParticipantPublicKey`] of the [`SumDict`] or an empty list when the
+    // [`SumDict`] does not exist.
+    pub async fn sum_pks(&mut self) -> StorageResult<HashSet<SumParticipantPublicKey>> {
+        Ok(self.data()?.sum_dict.keys().copied().collect())
+    }
+
+    // Removes an update pk from the the `update_participants` set.
+    pub async fn remove_update_participant(
+        &mut self,
+        update_pk: &UpdateParticipantPublicKey,
+    ) -> StorageResult<u64> {
+        Ok(self.data()?.update_participants.remove(update_pk) as u64)
+    }
+
+    pub async fn mask_submitted_set(&mut self) -> StorageResult<Vec<SumParticipantPublicKey>> {
+        Ok(self.data()?.mask_submitted.iter().copied().collect())
+    }
+
+    // Returns the number of participants for which update participations are stored.
+    pub async fn update_quota_len(&mut self) -> StorageResult<u64> {
+        Ok(self.data()?.update_quota.len() as u64)
+    }
+
+    /// Returns the [`SeedDict`] entry for the given ['SumParticipantPublicKey'] or an empty map
+    /// when a [`SeedDict`] entry does not exist.
+    pub async fn seed_dict_for_sum_pk(
+        &mut self,
+        sum_pk: &SumParticipantPublicKey,
+    ) -> StorageResult<HashMap<UpdateParticipantPublicKey, xaynet_core::mask::EncryptedMaskSeed>>
+    {
+        Ok(self
+            .data()?
+            .seed_dict
+            .get(sum_pk)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    /// Returns `true` if no data is stored at all.
+    pub async fn is_empty(&mut self) -> StorageResult<bool> {
+        let data = self.data()?;
+        Ok(data.coordinator_state.is_none()
+            && data.latest_global_model_id.is_none()
+            && data.sum_dict.is_empty()
+            && data.update_participants.is_empty()
+            && data.seed_dict.is_empty()
+            && data.mask_submitted.is_empty()
+            && data.mask_dict.is_empty()
+            && data.update_quota.is_empty())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        state_machine::tests::utils::{mask_settings, model_settings, pet_settings},
+        storage::tests::utils::*,
+    };
+
+    pub fn init_client() -> Client {
+        Client::new()
+    }
+
+    #[tokio::test]
+    async fn integration_set_and_get_coordinator_state() {
+        // test the writing and reading of the coordinator state
+        let mut client = init_client();
+
+        let set_state = CoordinatorState::new(pet_settings(), mask_settings(), model_settings());
+        client.set_coordinator_state(&set_state).await.unwrap();
+
+        let get_state = client.coordinator_state().await.unwrap().unwrap();
+
+        assert_eq!(set_state, get_state)
+    }
+
+    #[tokio::test]
+    async fn integration_probe_coordinator_state() {
+        // test that probing doesn't touch the coordinator state
+        let mut client = init_client();
+
+        let probe_state = CoordinatorState::new(pet_settings(), mask_settings(), model_settings());
+        let probed = client.probe_coordinator_state(&probe_state).await.unwrap();
+        assert_eq!(probed, Some(probe_state));
+
+        let get_state = client.coordinator_state().await.unwrap();
+        assert_eq!(None, get_state)
+    }
+
+    #[tokio::test]
+    async fn integration_get_coordinator_empty() {
+        // test the reading of a non existing coordinator state
+        let mut client = init_client();
+
+        let get_state = client.coordinator_state().await.unwrap();
+
+        assert_eq!(None, get_state)
+    }
+
+    #[tokio::test]
+    async fn integration_incr_mask_score() {
+        // test the increment of the mask counter
+        let mut client = init_client();
+
+        let should_be_none = client.best_masks().await.unwrap();
+        assert!(should_be_none.is_none());
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 3).await;
+        let mask = create_mask_zeroed(10);
+        for sum_pk in sum_pks {
+            let res = client.incr_mask_score(&sum_pk, &mask).await;
+            assert!(res.is_ok())
+        }
+
+        let best_masks = client.best_masks().await.unwrap().unwrap();
+        assert!(best_masks.len() == 1);
+
+        let (best_mask, count) = best_masks.into_iter().next().unwrap();
+        assert_eq!(best_mask, mask);
+        assert_eq!(count, 3);
+    }
+
+    #[tokio::test]
+    async fn integration_get_incr_mask_count_unknown_sum_pk() {
+        // test the writing and reading of one mask
+        let mut client = init_client();
+
+        let (sum_pk, _) = create_sum_participant_entry();
+        let mask = create_mask_zeroed(10);
+        let unknown_sum_pk = client.incr_mask_score(&sum_pk, &mask).await.unwrap();
+
+        assert!(matches!(
+            unknown_sum_pk.into_inner().unwrap_err(),
+            MaskScoreIncrError::UnknownSumPk
+        ));
+        assert!(client.mask_submitted_set().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn integration_get_incr_mask_score_sum_pk_already_submitted() {
+        // test the writing and reading of one mask
+        let mut client = init_client();
+
+        let mut sum_pks = create_and_add_sum_participant_entries(&mut client, 1).await;
+        let sum_pk = sum_pks.pop().unwrap();
+        let mask = create_mask_zeroed(10);
+        let result = client.incr_mask_score(&sum_pk, &mask).await.unwrap();
+        assert!(result.is_ok());
+
+        let already_submitted = client.incr_mask_score(&sum_pk, &mask).await.unwrap();
+
+        assert!(matches!(
+            already_submitted.into_inner().unwrap_err(),
+            MaskScoreIncrError::MaskAlreadySubmitted
+        ));
+        let (_, count) = client.best_masks().await.unwrap().unwrap().pop().unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn integration_get_best_masks_only_one_mask() {
+        // test the writing and reading of one mask
+        let mut client = init_client();
+
+        let should_be_none = client.best_masks().await.unwrap();
+        assert!(should_be_none.is_none());
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 1).await;
+        let mask = create_mask_zeroed(10);
+        let res = client
+            .incr_mask_score(sum_pks.first().unwrap(), &mask)
+            .await;
+        assert!(res.is_ok());
+
+        let best_masks = client.best_masks().await.unwrap().unwrap();
+        assert!(best_masks.len() == 1);
+
+        let (best_mask, count) = best_masks.into_iter().next().unwrap();
+        assert_eq!(best_mask, mask);
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn integration_get_best_masks_two_masks() {
+        // test the writing and reading of two masks
+        // the first mask is incremented twice
+        let mut client = init_client();
+
+        let should_be_none = client.best_masks().await.unwrap();
+        assert!(should_be_none.is_none());
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+        let mask_1 = create_mask_zeroed(10);
+        for sum_pk in sum_pks {
+            let res = client.incr_mask_score(&sum_pk, &mask_1).await;
+            assert!(res.is_ok())
+        }
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 1).await;
+        let mask_2 = create_mask_zeroed(100);
+        for sum_pk in sum_pks {
+            let res = client.incr_mask_score(&sum_pk, &mask_2).await;
+            assert!(res.is_ok())
+        }
+
+        let best_masks = client.best_masks().await.unwrap().unwrap();
+        assert!(best_masks.len() == 2);
+        let mut best_masks_iter = best_masks.into_iter();
+
+        let (first_mask, count) = best_masks_iter.next().unwrap();
+        assert_eq!(first_mask, mask_1);
+        assert_eq!(count, 2);
+        let (second_mask, count) = best_masks_iter.next().unwrap();
+        assert_eq!(second_mask, mask_2);
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn integration_get_best_masks_no_mask() {
+        // ensure that get_best_masks returns an empty vec if no mask exist
+        let mut client = init_client();
+
+        let best_masks = client.best_masks().await.unwrap();
+        assert!(best_masks.is_none())
+    }
+
+    #[tokio::test]
+    async fn integration_get_top_masks() {
+        // the masks are returned in descending order of their scores
+        let mut client = init_client();
+
+        assert!(client.top_masks(2).await.unwrap().is_empty());
+
+        let masks = vec![
+            create_mask_zeroed(10),
+            create_mask_zeroed(20),
+            create_mask_zeroed(30),
+        ];
+        for (nb_sum_pks, mask) in (1..=3).rev().zip(&masks) {
+            let sum_pks = create_and_add_sum_participant_entries(&mut client, nb_sum_pks).await;
+            for sum_pk in sum_pks {
+                let res = client.incr_mask_score(&sum_pk, mask).await;
+                assert!(res.is_ok())
+            }
+        }
+
+        let top_masks = client.top_masks(2).await.unwrap();
+        assert_eq!(
+            top_masks,
+            masks.iter().cloned().zip(vec![3, 2]).collect::<Vec<_>>()
+        );
+        let top_masks = client.top_masks(5).await.unwrap();
+        assert_eq!(
+            top_masks,
+            masks.into_iter().zip(vec![3, 2, 1]).collect::<Vec<_>>()
+        );
+        assert!(client.top_masks(0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn integration_get_number_of_unique_masks_empty() {
+        // ensure that get_best_masks returns an empty vec if no mask exist
+        let mut client = init_client();
+
+        let number_of_unique_masks = client.number_of_unique_masks().await.unwrap();
+        assert_eq!(number_of_unique_masks, 0)
+    }
+
+    #[tokio::test]
+    async fn integration_get_number_of_unique_masks() {
+        // ensure that get_best_masks returns an empty vec if no mask exist
+        let mut client = init_client();
+
+        let should_be_none = client.best_masks().await.unwrap();
+        assert!(should_be_none.is_none());
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 4).await;
+        for (number, sum_pk) in sum_pks.iter().enumerate() {
+            let mask_1 = create_mask(10, number as u32);
+            let res = client.incr_mask_score(sum_pk, &mask_1).await;
+            assert!(res.is_ok())
+        }
+
+        let number_of_unique_masks = client.number_of_unique_masks().await.unwrap();
+        assert_eq!(number_of_unique_masks, 4)
+    }
+
+    #[tokio::test]
+    async fn integration_sum_dict() {
+        // test multiple sum dict related methods
+        let mut client = init_client();
+
+        // create two entries and write them into the storage
+        let mut entries = vec![];
+        for _ in 0..2 {
+            let (pk, epk) = create_sum_participant_entry();
+            let add_new_key = client.add_sum_participant(&pk, &epk).await.unwrap();
+            assert!(add_new_key.is_ok());
+
+            entries.push((pk, epk));
+        }
+
+        // ensure that add_sum_participant returns SumPartAddError::AlreadyExists if the key already exist
+        let (pk, epk) = entries.first().unwrap();
+        let key_already_exist = client.add_sum_participant(pk, epk).await.unwrap();
+        assert!(matches!(
+            key_already_exist.into_inner().unwrap_err(),
+            SumPartAddError::AlreadyExists
+        ));
+
+        // ensure that get_sum_dict_len returns 2
+        let len_of_sum_dict = client.sum_dict_len().await.unwrap();
+        assert_eq!(len_of_sum_dict, 2);
+
+        // read the written sum keys
+        // ensure they are equal
+        let sum_pks = client.sum_pks().await.unwrap();
+        for (sum_pk, _) in entries.iter() {
+            assert!(sum_pks.contains(sum_pk));
+        }
+
+        // remove both sum entries
+        for (sum_pk, _) in entries.iter() {
+            let removed = client.remove_sum_dict_entry(sum_pk).await.unwrap();
+            assert_eq!(removed, 1);
+        }
+
+        // ensure that removing a non-existent key returns 0
+        let removed = client.remove_sum_dict_entry(pk).await.unwrap();
+        assert_eq!(removed, 0);
+
+        // ensure that get_sum_dict an empty sum dict
+        let sum_dict = client.sum_dict().await.unwrap();
+        assert!(sum_dict.is_none());
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict() {
+        let mut client = init_client();
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.iter().for_each(|res| assert!(res.is_ok()));
+
+        let stored_sum_dict = client.sum_dict().await.unwrap().unwrap();
+        let seed_dict = create_seed_dict(stored_sum_dict, &local_seed_dicts);
+
+        let stored_seed_dict = client.seed_dict().await.unwrap().unwrap();
+        assert_eq!(seed_dict, stored_seed_dict)
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict_len_mis_match() {
+        let mut client = init_client();
+
+        let mut sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        // remove one sum pk to create invalid local seed dicts
+        sum_pks.pop();
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.into_iter().for_each(|res| {
+            assert!(matches!(
+                res.into_inner().unwrap_err(),
+                LocalSeedDictAddError::LengthMisMatch
+            ))
+        });
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict_unknown_sum_participant() {
+        let mut client = init_client();
+
+        let mut sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        // replace a known sum_pk with an unknown one
+        sum_pks.pop();
+        let (pk, _) = create_sum_participant_entry();
+        sum_pks.push(pk);
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.into_iter().for_each(|res| {
+            assert!(matches!(
+                res.into_inner().unwrap_err(),
+                LocalSeedDictAddError::UnknownSumParticipant
+            ))
+        });
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict_update_pk_already_submitted() {
+        let mut client = init_client();
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.iter().for_each(|res| assert!(res.is_ok()));
+
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.into_iter().for_each(|res| {
+            assert!(matches!(
+                res.into_inner().unwrap_err(),
+                LocalSeedDictAddError::UpdatePkAlreadySubmitted
+            ))
+        });
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict_update_pk_already_exists_in_update_seed_dict() {
+        let mut client = init_client();
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.iter().for_each(|res| assert!(res.is_ok()));
+
+        let (update_participant, local_seed_dict) = local_seed_dicts.first().unwrap().clone();
+        let remove_result = client
+            .remove_update_participant(&update_participant)
+            .await
+            .unwrap();
+        assert_eq!(remove_result, 1);
+
+        let update_result =
+            add_local_seed_entries(&mut client, &[(update_participant, local_seed_dict)]).await;
+        update_result.into_iter().for_each(|res| {
+            assert!(matches!(
+                res.into_inner().unwrap_err(),
+                LocalSeedDictAddError::UpdatePkAlreadyExistsInUpdateSeedDict
+            ))
+        });
+        // the rejected local seed dict must not resubmit the update participant
+        let remove_result = client
+            .remove_update_participant(&update_participant)
+            .await
+            .unwrap();
+        assert_eq!(remove_result, 0);
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict_get_seed_dict_for_sum_pk() {
+        let mut client = init_client();
+        let mut sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.iter().for_each(|res| assert!(res.is_ok()));
+
+        let stored_sum_dict = client.sum_dict().await.unwrap().unwrap();
+        let seed_dict = create_seed_dict(stored_sum_dict, &local_seed_dicts);
+
+        let sum_pk = sum_pks.pop().unwrap();
+
+        let stored_sum_seed_dict = client.seed_dict_for_sum_pk(&sum_pk).await.unwrap();
+
+        assert_eq!(&stored_sum_seed_dict, seed_dict.get(&sum_pk).unwrap())
+    }
+
+    #[tokio::test]
+    async fn integration_seed_dict_get_seed_dict_for_sum_pk_empty() {
+        let mut client = init_client();
+        let (sum_pk, _) = create_sum_participant_entry();
+
+        let result = client.seed_dict_for_sum_pk(&sum_pk).await.unwrap();
+        assert!(result.is_empty())
+    }
+
+    #[tokio::test]
+    async fn integration_flush_dicts() {
+        let mut client = init_client();
+
+        // write some data into the storage
+        let set_state = CoordinatorState::new(pet_settings(), mask_settings(), model_settings());
+        let res = client.set_coordinator_state(&set_state).await;
+        assert!(res.is_ok());
+
+        let res = client.set_latest_global_model_id("global_model_id").await;
+        assert!(res.is_ok());
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.iter().for_each(|res| assert!(res.is_ok()));
+
+        let mask = create_mask_zeroed(10);
+        client
+            .incr_mask_score(sum_pks.first().unwrap(), &mask)
+            .await
+            .unwrap();
+
+        // remove dicts
+        let res = client.delete_dicts().await;
+        assert!(res.is_ok());
+
+        // ensure that only the coordinator state and latest global model id exists
+        let res = client.coordinator_state().await;
+        assert!(res.unwrap().is_some());
+
+        let res = client.latest_global_model_id().await;
+        assert!(res.unwrap().is_some());
+
+        let res = client.sum_dict().await;
+        assert!(res.unwrap().is_none());
+
+        let res = client.seed_dict().await;
+        assert!(res.unwrap().is_none());
+
+        let res = client.mask_submitted_set().await;
+        assert!(res.unwrap().is_empty());
+
+        let res = client.best_masks().await;
+        assert!(res.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn integration_flush_coordinator_data() {
+        let mut client = init_client();
+
+        // write some data into the storage
+        let set_state = CoordinatorState::new(pet_settings(), mask_settings(), model_settings());
+        let res = client.set_coordinator_state(&set_state).await;
+        assert!(res.is_ok());
+
+        let res = client.set_latest_global_model_id("global_model_id").await;
+        assert!(res.is_ok());
+
+        let sum_pks = create_and_add_sum_participant_entries(&mut client, 2).await;
+
+        let local_seed_dicts = create_local_seed_entries(&sum_pks);
+        let update_result = add_local_seed_entries(&mut client, &local_seed_dicts).await;
+        update_result.iter().for_each(|res| assert!(res.is_ok()));
+
+        let mask = create_mask_zeroed(10);
+        client
+            .incr_mask_score(sum_pks.first().unwrap(), &mask)
+            .await
+            .unwrap();
+
+        // remove all coordinator data
+        let res = client.delete_coordinator_data().await;
+        assert!(res.is_ok());
+
+        assert!(client.is_empty().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn integration_set_and_get_latest_global_model_id() {
+        // test the writing and reading of the global model id
+        let mut client = init_client();
+
+        let set_id = "global_model_id";
+        client.set_latest_global_model_id(set_id).await.unwrap();
+
+        let get_id = client.latest_global_model_id().await.unwrap().unwrap();
+
+        assert_eq!(set_id, get_id)
+    }
+
+    #[tokio::test]
+    async fn integration_is_ready_ok() {
+        // test is_ready command
+        let mut client = init_client();
+
+        let res = client.is_ready().await;
+        assert!(res.is_ok())
+    }
+
+    #[tokio::test]
+    async fn integration_get_latest_global_model_id_empty() {
+        // test the reading of a non existing global model id
+        let mut client = init_client();
+
+        let get_id = client.latest_global_model_id().await.unwrap();
+
+        assert_eq!(None, get_id)
+    }
+
+    #[tokio::test]
+    async fn integration_update_participations() {
+        // test the counting of the update participations within the window
+        let mut client = init_client();
+        let pk = UpdateParticipantPublicKey::zeroed();
+        let window = 100;
+
+        assert_eq!(
+            client
+                .update_participations(&pk, 1000, window)
+                .await
+                .unwrap(),
+            0
+        );
+        let count = client.add_update_participation(&pk, 1, 1000, window).await;
+        assert_eq!(count.unwrap(), 1);
+        let count = client.add_update_participation(&pk, 2, 1050, window).await;
+        assert_eq!(count.unwrap(), 2);
+        // a participation in the same round is only counted once
+        let count = client.add_update_participation(&pk, 2, 1060, window).await;
+        assert_eq!(count.unwrap(), 2);
+        assert_eq!(
+            client
+                .update_participations(&pk, 1060, window)
+                .await
+                .unwrap(),
+            2
+        );
+
+        // the first participation leaves the window
+        assert_eq!(
+            client
+                .update_participations(&pk, 1100, window)
+                .await
+                .unwrap(),
+            1
+        );
+        let count = client.add_update_participation(&pk, 3, 1100, window).await;
+        assert_eq!(count.unwrap(), 2);
+
+        // all the participations left the window
+        assert_eq!(
+            client
+                .update_participations(&pk, 1200, window)
+                .await
+                .unwrap(),
+            0
+        );
+        let other_pk = UpdateParticipantPublicKey::fill_with(1);
+        assert_eq!(
+            client
+                .update_participations(&other_pk, 1000, window)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn integration_update_participations_expire() {
+        // test that the update participations are not stored beyond the window, and that they
+        // are not part of the coordinator data
+        let mut client = init_client();
+        let pk = UpdateParticipantPublicKey::fill_with(1);
+        let other_pk = UpdateParticipantPublicKey::fill_with(2);
+        client
+            .add_update_participation(&pk, 1, 1000, 100)
+            .await
+            .unwrap();
+        assert_eq!(client.update_quota_len().await.unwrap(), 1);
+
+        client.delete_coordinator_data().await.unwrap();
+        assert_eq!(client.update_quota_len().await.unwrap(), 1);
+
+        // the participation of the first participant is forgotten once it left the window
+        client
+            .add_update_participation(&other_pk, 1, 1100, 100)
+            .await
+            .unwrap();
+        assert_eq!(client.update_quota_len().await.unwrap(), 1);
+        assert_eq!(
+            client.update_participations(&pk, 1050, 100).await.unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn integration_clones_share_data() {
+        // test that the clones of a client operate on the same data
+        let mut client = init_client();
+        let mut clone = client.clone();
+
+        clone
+            .set_latest_global_model_id("global_model_id")
+            .await
+            .unwrap();
+
+        let get_id = client.latest_global_model_id().await.unwrap();
+        assert_eq!(Some("global_model_id".to_string()), get_id)
+    }
+}
@@ -1,3 +1,5 @@
 //! Storage backends to manage the coordinator state.
 
+pub mod in_memory;
+pub mod postgres;
 pub mod redis;
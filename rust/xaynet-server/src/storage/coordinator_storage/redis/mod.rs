@@ -33,17 +33,27 @@
 //!         (mask_object_1, 2), // (mask: bincode encoded string, score/counter: number)
 //!         (mask_object_2, 1)
 //!     ],
-//!     "latest_global_model_id": global_model_id
+//!     "latest_global_model_id": global_model_id,
+//!     // Update participation quota
+//!     "update_quota:sha256(UpdateParticipantPublicKey_1)": [ // sorted set, expires with the window
+//!         (round_id_1, timestamp_1), // (round id: number, score/participation time: number)
+//!         (round_id_2, timestamp_2)
+//!     ]
 //! }
 //! ```
+//!
+//! The update participation quota is not part of the coordinator data: it is neither deleted
+//! with the coordinator data, nor stored beyond the quota window. The public keys of the update
+//! participants are only stored hashed.
 
 pub(in crate::storage) mod impls;
 
-use std::collections::HashMap;
+use std::{collections::HashMap, convert::TryFrom};
 
 use async_trait::async_trait;
 use redis::{aio::ConnectionManager, AsyncCommands, IntoConnectionInfo, Pipeline, Script};
 pub use redis::{RedisError, RedisResult};
+use sodiumoxide::crypto::hash::sha256;
 use tracing::debug;
 
 use self::impls::{
@@ -68,6 +78,7 @@ use crate::{
     },
 };
 use xaynet_core::{
+    crypto::ByteObject,
     mask::MaskObject,
     LocalSeedDict,
     SeedDict,
@@ -87,6 +98,11 @@ fn to_storage_err(e: RedisError) -> StorageError {
     anyhow::anyhow!(e)
 }
 
+/// Gets the key of the update participation quota of the given participant.
+fn update_quota_key(pk: &UpdateParticipantPublicKey) -> String {
+    format!("update_quota:{}", hex::encode(sha256::hash(pk.as_slice())))
+}
+
 impl Client {
     /// Creates a new Redis client.
     ///
@@ -159,6 +175,29 @@ impl CoordinatorStorage for Client {
             .map_err(to_storage_err)
     }
 
+    async fn probe_coordinator_state(
+        &mut self,
+        state: &CoordinatorState,
+    ) -> StorageResult<Option<CoordinatorState>> {
+        debug!("probe coordinator state");
+        self.connection
+            .set::<_, _, ()>("preflight_coordinator_state", state)
+            .await
+            .map_err(to_storage_err)?;
+        let probed = self
+            .connection
+            .get("preflight_coordinator_state")
+            .await
+            .map_err(to_storage_err)?;
+        // https://redis.io/commands/del
+        // > Removes the specified keys. A key is ignored if it does not exist.
+        self.connection
+            .del::<_, ()>("preflight_coordinator_state")
+            .await
+            .map_err(to_storage_err)?;
+        Ok(probed)
+    }
+
     async fn add_sum_participant(
         &mut self,
         pk: &SumParticipantPublicKey,
@@ -367,6 +406,24 @@ impl CoordinatorStorage for Client {
         Ok(result)
     }
 
+    async fn top_masks(&mut self, limit: usize) -> StorageResult<Vec<(MaskObject, u64)>> {
+        debug!("get top masks");
+        // a stop index of -1 would return all the masks
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let stop = isize::try_from(limit).unwrap_or(isize::MAX) - 1;
+        let reply: Vec<(MaskObjectRead, u64)> = self
+            .connection
+            .zrevrange_withscores("mask_dict", 0, stop)
+            .await?;
+
+        Ok(reply
+            .into_iter()
+            .map(|(mask, count)| (mask.into(), count))
+            .collect())
+    }
+
     async fn number_of_unique_masks(&mut self) -> StorageResult<u64> {
         debug!("get number of unique masks");
         // https://redis.io/commands/zcount
@@ -429,6 +486,67 @@ impl CoordinatorStorage for Client {
             .map_err(to_storage_err)
     }
 
+    async fn add_update_participation(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        round_id: u64,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64> {
+        debug!("add update participation in round {}", round_id);
+        let key = update_quota_key(pk);
+        // https://redis.io/commands/zremrangebyscore
+        // > Removes all elements in the sorted set stored at key with a score between min and max
+        //   (inclusive).
+        // https://redis.io/commands/zadd
+        // > If a specified member is already a member of the sorted set, the score is updated.
+        //   We use the NX option to keep the time of the first participation in a round.
+        // https://redis.io/commands/expire
+        // > Set a timeout on key. After the timeout has expired, the key will automatically be
+        //   deleted.
+        // https://redis.io/commands/zcard
+        // > Return value
+        //   Integer reply: the cardinality (number of elements) of the sorted set, or 0 if key
+        //   does not exist.
+        let (count,): (u64,) = redis::pipe()
+            .atomic()
+            .zrembyscore(&key, "-inf", now.saturating_sub(window))
+            .ignore()
+            .cmd("ZADD")
+            .arg(&key)
+            .arg("NX")
+            .arg(now)
+            .arg(round_id)
+            .ignore()
+            .expire(&key, window as usize)
+            .ignore()
+            .zcard(&key)
+            .query_async(&mut self.connection)
+            .await
+            .map_err(to_storage_err)?;
+        Ok(count)
+    }
+
+    async fn update_participations(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64> {
+        debug!("get update participations");
+        // https://redis.io/commands/zcount
+        // > Return value:
+        //   Integer reply: the number of elements in the specified score range.
+        self.connection
+            .zcount(
+                update_quota_key(pk),
+                format!("({}", now.saturating_sub(window)),
+                "+inf",
+            )
+            .await
+            .map_err(to_storage_err)
+    }
+
     async fn is_ready(&mut self) -> StorageResult<()> {
         // https://redis.io/commands/ping
         redis::cmd("PING")
@@ -580,6 +698,21 @@ pub(in crate) mod tests {
         assert_eq!(set_state, get_state)
     }
 
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_probe_coordinator_state() {
+        // test that probing doesn't touch the coordinator state
+        let mut client = init_client().await;
+
+        let probe_state = CoordinatorState::new(pet_settings(), mask_settings(), model_settings());
+        let probed = client.probe_coordinator_state(&probe_state).await.unwrap();
+        assert_eq!(probed, Some(probe_state));
+
+        let get_state = client.coordinator_state().await.unwrap();
+        assert_eq!(None, get_state)
+    }
+
     #[tokio::test]
     #[serial]
     #[ignore]
@@ -732,6 +865,41 @@ pub(in crate) mod tests {
         assert!(best_masks.is_none())
     }
 
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_get_top_masks() {
+        // the masks are returned in descending order of their scores
+        let mut client = init_client().await;
+
+        assert!(client.top_masks(2).await.unwrap().is_empty());
+
+        let masks = vec![
+            create_mask_zeroed(10),
+            create_mask_zeroed(20),
+            create_mask_zeroed(30),
+        ];
+        for (nb_sum_pks, mask) in (1..=3).rev().zip(&masks) {
+            let sum_pks = create_and_add_sum_participant_entries(&mut client, nb_sum_pks).await;
+            for sum_pk in sum_pks {
+                let res = client.incr_mask_score(&sum_pk, mask).await;
+                assert!(res.is_ok())
+            }
+        }
+
+        let top_masks = client.top_masks(2).await.unwrap();
+        assert_eq!(
+            top_masks,
+            masks.iter().cloned().zip(vec![3, 2]).collect::<Vec<_>>()
+        );
+        let top_masks = client.top_masks(5).await.unwrap();
+        assert_eq!(
+            top_masks,
+            masks.into_iter().zip(vec![3, 2, 1]).collect::<Vec<_>>()
+        );
+        assert!(client.top_masks(0).await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     #[serial]
     #[ignore]
@@ -1084,4 +1252,87 @@ pub(in crate) mod tests {
 
         assert_eq!(None, get_id)
     }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_update_participations() {
+        // test the counting of the update participations within the window
+        let mut client = init_client().await;
+        let pk = UpdateParticipantPublicKey::zeroed();
+        let window = 100;
+
+        assert_eq!(
+            client
+                .update_participations(&pk, 1000, window)
+                .await
+                .unwrap(),
+            0
+        );
+        let count = client.add_update_participation(&pk, 1, 1000, window).await;
+        assert_eq!(count.unwrap(), 1);
+        let count = client.add_update_participation(&pk, 2, 1050, window).await;
+        assert_eq!(count.unwrap(), 2);
+        // a participation in the same round is only counted once
+        let count = client.add_update_participation(&pk, 2, 1060, window).await;
+        assert_eq!(count.unwrap(), 2);
+        assert_eq!(
+            client
+                .update_participations(&pk, 1060, window)
+                .await
+                .unwrap(),
+            2
+        );
+
+        // the first participation leaves the window
+        assert_eq!(
+            client
+                .update_participations(&pk, 1100, window)
+                .await
+                .unwrap(),
+            1
+        );
+        let count = client.add_update_participation(&pk, 3, 1100, window).await;
+        assert_eq!(count.unwrap(), 2);
+
+        // all the participations left the window
+        assert_eq!(
+            client
+                .update_participations(&pk, 1200, window)
+                .await
+                .unwrap(),
+            0
+        );
+        let other_pk = UpdateParticipantPublicKey::fill_with(1);
+        assert_eq!(
+            client
+                .update_participations(&other_pk, 1000, window)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_update_participations_expire() {
+        // test that the update participations are neither stored in the clear nor beyond the
+        // window, and that they are not part of the coordinator data
+        let mut client = init_client().await;
+        let pk = UpdateParticipantPublicKey::fill_with(1);
+        client
+            .add_update_participation(&pk, 1, 1000, 100)
+            .await
+            .unwrap();
+
+        let keys = client.keys().await.unwrap();
+        assert_eq!(keys, vec![update_quota_key(&pk)]);
+        assert!(!keys[0].contains(&hex::encode(pk.as_slice())));
+        let ttl: i64 = client.connection.ttl(&keys[0]).await.unwrap();
+        assert!(0 < ttl && ttl <= 100);
+
+        client.delete_coordinator_data().await.unwrap();
+        assert_eq!(client.keys().await.unwrap().len(), 1);
+    }
 }
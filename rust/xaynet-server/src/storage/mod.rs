@@ -12,6 +12,7 @@ pub use self::{
     store::Store,
     traits::{
         CoordinatorStorage,
+        GlobalModelIdFormat,
         LocalSeedDictAdd,
         LocalSeedDictAddError,
         MaskScoreIncr,
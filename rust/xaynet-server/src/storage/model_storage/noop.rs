@@ -11,17 +11,41 @@ pub struct NoOp;
 impl ModelStorage for NoOp {
     async fn set_global_model(
         &mut self,
-        round_id: u64,
-        round_seed: &RoundSeed,
-        _global_model: &Model,
+        _round_id: u64,
+        _round_seed: &RoundSeed,
+        global_model: &Model,
     ) -> StorageResult<String> {
-        Ok(Self::create_global_model_id(round_id, round_seed))
+        let data = bincode::serialize(global_model)?;
+        Ok(Self::create_global_model_id(&data))
     }
 
     async fn global_model(&mut self, _id: &str) -> StorageResult<Option<Model>> {
         Err(anyhow::anyhow!("No-op model store"))
     }
 
+    async fn set_round_archive(
+        &mut self,
+        _global_model_id: &str,
+        _round_id: u64,
+        _archive: Vec<u8>,
+    ) -> StorageResult<()> {
+        Ok(())
+    }
+
+    async fn set_retention_archive(
+        &mut self,
+        _data_class: &str,
+        _name: &str,
+        _archive: Vec<u8>,
+    ) -> StorageResult<()> {
+        // the records must not be purged without an archive
+        Err(anyhow::anyhow!("No-op model store"))
+    }
+
+    async fn probe_write(&mut self) -> StorageResult<()> {
+        Ok(())
+    }
+
     async fn is_ready(&mut self) -> StorageResult<()> {
         Ok(())
     }
@@ -10,6 +10,8 @@ use rusoto_s3::{
     CreateBucketError,
     CreateBucketOutput,
     CreateBucketRequest,
+    DeleteObjectError,
+    DeleteObjectRequest,
     DeleteObjectsError,
     GetObjectError,
     GetObjectOutput,
@@ -30,7 +32,7 @@ use tracing::debug;
 
 use crate::{
     settings::{S3BucketsSettings, S3Settings},
-    storage::{ModelStorage, StorageResult},
+    storage::{GlobalModelIdFormat, ModelStorage, StorageResult},
 };
 use xaynet_core::{common::RoundSeed, mask::Model};
 
@@ -46,6 +48,8 @@ pub enum ClientError {
     PutObject(#[from] RusotoError<PutObjectError>),
     /// Failed to list objects: {0}.
     ListObjects(#[from] RusotoError<ListObjectsV2Error>),
+    /// Failed to delete object: {0}.
+    DeleteObject(#[from] RusotoError<DeleteObjectError>),
     /// Failed to delete objects: {0}.
     DeleteObjects(#[from] RusotoError<DeleteObjectsError>),
     /// Failed to dispatch: {0}.
@@ -58,8 +62,8 @@ pub enum ClientError {
     NoBody,
     /// Failed to download body: {0}.
     DownloadBody(std::io::Error),
-    /// Object {0} already exists.
-    ObjectAlreadyExists(String),
+    /// Object {0} does not match its content hash.
+    HashMismatch(String),
     /// Storage not ready: {0}.
     NotReady(RusotoError<HeadBucketError>),
 }
@@ -120,6 +124,17 @@ impl Client {
         }
     }
 
+    // Creates the key of the round archive of the given global model and round. A global
+    // model can be created in several rounds, so the key includes the round id.
+    fn round_archive_key(global_model_id: &str, round_id: u64) -> String {
+        format!("{}.round_{}", global_model_id, round_id)
+    }
+
+    // Creates the key of the archive of the expiring records of a data class.
+    fn retention_archive_key(data_class: &str, name: &str) -> String {
+        format!("retention/{}/{}", data_class, name)
+    }
+
     // Downloads the content of the given object.
     async fn download_object_body(object: GetObjectOutput) -> ClientResult<Vec<u8>> {
         let mut body = Vec::new();
@@ -148,22 +163,72 @@ impl Client {
         self.client.get_object(req).await
     }
 
-    // Uploads an object with the given key to the given bucket.
+    // Creates the tags of an object of the given round, which retention policies can
+    // filter on. Global models of canary rounds are additionally tagged as canary.
+    fn object_tagging(round_id: u64, canary: bool) -> String {
+        if canary {
+            format!("round_id={}&canary=true", round_id)
+        } else {
+            format!("round_id={}", round_id)
+        }
+    }
+
+    // Uploads an object with the given key and tags to the given bucket.
     async fn upload_object(
         &self,
         bucket: &str,
         key: &str,
+        tagging: String,
         data: Vec<u8>,
     ) -> Result<PutObjectOutput, RusotoError<PutObjectError>> {
         let req = PutObjectRequest {
             bucket: bucket.to_string(),
             key: key.to_string(),
             body: Some(StreamingBody::from(data)),
+            tagging: Some(tagging),
             ..Default::default()
         };
         self.client.put_object(req).await
     }
 
+    // Uploads a global model, unless it already exists, and returns its id.
+    async fn upload_global_model(
+        &self,
+        round_id: u64,
+        global_model: &Model,
+        canary: bool,
+    ) -> StorageResult<String> {
+        let data = bincode::serialize(global_model).map_err(ClientError::Serialization)?;
+        let id = Self::create_global_model_id(&data);
+
+        // the id is derived from the content, so an existing object is the same global
+        // model that was created in an earlier round
+        let output = self
+            .fetch_object_meta(&self.buckets.global_models, &id)
+            .await;
+        if output.is_ok() {
+            debug!("global model {} already exists", id);
+            return Ok(id);
+        };
+
+        debug!("upload global model: {}", id);
+        let tagging = Self::object_tagging(round_id, canary);
+        self.upload_object(&self.buckets.global_models, &id, tagging, data)
+            .await
+            .map(|_| Ok(id))?
+    }
+
+    // Deletes the object with the given key from the given bucket.
+    async fn delete_object(&self, bucket: &str, key: &str) -> ClientResult<()> {
+        let req = DeleteObjectRequest {
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            ..Default::default()
+        };
+        self.client.delete_object(req).await?;
+        Ok(())
+    }
+
     // Creates a new bucket with the given bucket name.
     async fn create_bucket(
         &self,
@@ -182,25 +247,20 @@ impl ModelStorage for Client {
     async fn set_global_model(
         &mut self,
         round_id: u64,
-        round_seed: &RoundSeed,
+        _round_seed: &RoundSeed,
         global_model: &Model,
     ) -> StorageResult<String> {
-        let id = Self::create_global_model_id(round_id, round_seed);
-
-        debug!("upload global model: {}", id);
-        let output = self
-            .fetch_object_meta(&self.buckets.global_models, &id)
-            .await;
-        if output.is_ok() {
-            return Err(anyhow::anyhow!(ClientError::ObjectAlreadyExists(
-                id.to_string()
-            )));
-        };
-
-        let data = bincode::serialize(global_model).map_err(ClientError::Serialization)?;
-        self.upload_object(&self.buckets.global_models, &id, data)
+        self.upload_global_model(round_id, global_model, false)
             .await
-            .map(|_| Ok(id))?
+    }
+
+    async fn set_canary_global_model(
+        &mut self,
+        round_id: u64,
+        _round_seed: &RoundSeed,
+        global_model: &Model,
+    ) -> StorageResult<String> {
+        self.upload_global_model(round_id, global_model, true).await
     }
 
     async fn global_model(&mut self, id: &str) -> StorageResult<Option<Model>> {
@@ -215,10 +275,61 @@ impl ModelStorage for Client {
         };
 
         let body = Self::download_object_body(object_meta).await?;
+        if GlobalModelIdFormat::detect(id) == Some(GlobalModelIdFormat::ContentHash)
+            && Self::create_global_model_id(&body) != id
+        {
+            return Err(anyhow::anyhow!(ClientError::HashMismatch(id.to_string())));
+        }
         let model = bincode::deserialize(&body).map_err(ClientError::Deserialization)?;
         Ok(Some(model))
     }
 
+    async fn set_round_archive(
+        &mut self,
+        global_model_id: &str,
+        round_id: u64,
+        archive: Vec<u8>,
+    ) -> StorageResult<()> {
+        let key = Self::round_archive_key(global_model_id, round_id);
+        debug!("upload round archive: {}", key);
+        let tagging = Self::object_tagging(round_id, false);
+        self.upload_object(&self.buckets.global_models, &key, tagging, archive)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_retention_archive(
+        &mut self,
+        data_class: &str,
+        name: &str,
+        archive: Vec<u8>,
+    ) -> StorageResult<()> {
+        let key = Self::retention_archive_key(data_class, name);
+        debug!("upload retention archive: {}", key);
+        let tagging = format!("data_class={}", data_class);
+        self.upload_object(&self.buckets.global_models, &key, tagging, archive)
+            .await?;
+        Ok(())
+    }
+
+    async fn probe_write(&mut self) -> StorageResult<()> {
+        debug!("probe write to {} bucket", &self.buckets.global_models);
+        // the key is neither a global model id nor a round archive key
+        let key = "preflight";
+        let req = PutObjectRequest {
+            bucket: self.buckets.global_models.clone(),
+            key: key.to_string(),
+            body: Some(StreamingBody::from(b"preflight".to_vec())),
+            ..Default::default()
+        };
+        self.client
+            .put_object(req)
+            .await
+            .map_err(ClientError::PutObject)?;
+        self.delete_object(&self.buckets.global_models, key).await?;
+        Ok(())
+    }
+
     async fn is_ready(&mut self) -> StorageResult<()> {
         let req = HeadBucketRequest {
             // we can't use an empty string because S3/Minio would return BAD_REQUEST
@@ -256,6 +367,7 @@ pub(in crate) mod tests {
         DeleteBucketRequest,
         DeleteObjectsOutput,
         DeleteObjectsRequest,
+        GetObjectTaggingRequest,
         ListObjectsV2Output,
         ListObjectsV2Request,
         ObjectIdentifier,
@@ -364,6 +476,21 @@ pub(in crate) mod tests {
             };
             self.client.delete_bucket(req).await
         }
+
+        // Returns the tags of an object as `key=value` pairs.
+        async fn object_tags(&self, bucket: &str, key: &str) -> Vec<String> {
+            let req = GetObjectTaggingRequest {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+                ..Default::default()
+            };
+            let output = self.client.get_object_tagging(req).await.unwrap();
+            output
+                .tag_set
+                .into_iter()
+                .map(|tag| format!("{}={}", tag.key, tag.value))
+                .collect()
+        }
     }
 
     fn create_minio_setup(url: &str) -> S3Settings {
@@ -415,7 +542,7 @@ pub(in crate) mod tests {
     async fn integration_test_get_global_model_non_existent() {
         let mut client = init_client().await;
 
-        let id = Client::create_global_model_id(1, &RoundSeed::generate());
+        let id = Client::create_global_model_id(b"non-existent");
         let res = client.global_model(&id).await.unwrap();
         assert!(res.is_none())
     }
@@ -426,27 +553,140 @@ pub(in crate) mod tests {
     async fn integration_test_global_model_already_exists() {
         let mut client = init_client().await;
 
+        // an identical global model in a later round shares the id and the object
         let global_model = create_global_model(10);
-        let round_seed = RoundSeed::generate();
         let id = client
-            .set_global_model(1, &round_seed, &global_model)
+            .set_global_model(1, &RoundSeed::generate(), &global_model)
             .await
             .unwrap();
+        let id_2 = client
+            .set_global_model(2, &RoundSeed::generate(), &global_model)
+            .await
+            .unwrap();
+        assert_eq!(id, id_2);
 
-        let global_model_2 = create_global_model(20);
-        let res = client
-            .set_global_model(1, &round_seed, &global_model_2)
+        let downloaded_global_model = client.global_model(&id).await.unwrap().unwrap();
+        assert_eq!(global_model, downloaded_global_model)
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_test_get_legacy_global_model() {
+        let client = init_client().await;
+
+        // global models stored by older coordinators are keyed by their legacy id
+        let global_model = create_global_model(10);
+        let id = format!("1_{}", hex::encode(RoundSeed::generate().as_slice()));
+        let data = bincode::serialize(&global_model).unwrap();
+        client
+            .upload_object(
+                &client.buckets.global_models,
+                &id,
+                "round_id=1".to_string(),
+                data,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            client.object_tags(&client.buckets.global_models, &id).await,
+            vec!["round_id=1"],
+        );
+
+        let downloaded_global_model = client.clone().global_model(&id).await.unwrap().unwrap();
+        assert_eq!(global_model, downloaded_global_model)
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_test_get_global_model_hash_mismatch() {
+        let client = init_client().await;
+
+        let id = Client::create_global_model_id(b"global model");
+        let data = bincode::serialize(&create_global_model(10)).unwrap();
+        client
+            .upload_object(
+                &client.buckets.global_models,
+                &id,
+                "round_id=1".to_string(),
+                data,
+            )
             .await
-            .unwrap_err();
+            .unwrap();
+
+        let res = client.clone().global_model(&id).await.unwrap_err();
         assert!(matches!(
             res.downcast_ref::<ClientError>().unwrap(),
-            ClientError::ObjectAlreadyExists(_)
+            ClientError::HashMismatch(_)
         ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_test_set_round_archive() {
+        let mut client = init_client().await;
 
+        let global_model = create_global_model(10);
+        let id = client
+            .set_global_model(1, &RoundSeed::generate(), &global_model)
+            .await
+            .unwrap();
+        client
+            .set_round_archive(&id, 1, b"round archive".to_vec())
+            .await
+            .unwrap();
+
+        // the archive is stored alongside the global model
+        let key = Client::round_archive_key(&id, 1);
+        let object = client
+            .fetch_object_meta(&client.buckets.global_models, &key)
+            .await
+            .unwrap();
+        let body = Client::download_object_body(object).await.unwrap();
+        assert_eq!(body, b"round archive");
         let downloaded_global_model = client.global_model(&id).await.unwrap().unwrap();
         assert_eq!(global_model, downloaded_global_model)
     }
 
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_test_set_retention_archive() {
+        let mut client = init_client().await;
+
+        client
+            .set_retention_archive("audit_log", "0-9.jsonl", b"records".to_vec())
+            .await
+            .unwrap();
+
+        let key = Client::retention_archive_key("audit_log", "0-9.jsonl");
+        let object = client
+            .fetch_object_meta(&client.buckets.global_models, &key)
+            .await
+            .unwrap();
+        let body = Client::download_object_body(object).await.unwrap();
+        assert_eq!(body, b"records");
+    }
+
+    #[tokio::test]
+    #[serial]
+    #[ignore]
+    async fn integration_test_probe_write() {
+        let mut client = init_client().await;
+
+        let res = client.probe_write().await;
+        assert!(res.is_ok());
+        let res = client
+            .fetch_object_meta(&client.buckets.global_models, "preflight")
+            .await;
+        assert!(matches!(
+            res,
+            Err(RusotoError::Service(GetObjectError::NoSuchKey(_)))
+        ));
+    }
+
     #[tokio::test]
     #[serial]
     #[ignore]
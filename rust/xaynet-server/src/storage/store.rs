@@ -88,6 +88,13 @@ where
         self.coordinator.coordinator_state().await
     }
 
+    async fn probe_coordinator_state(
+        &mut self,
+        state: &CoordinatorState,
+    ) -> StorageResult<Option<CoordinatorState>> {
+        self.coordinator.probe_coordinator_state(state).await
+    }
+
     async fn add_sum_participant(
         &mut self,
         pk: &SumParticipantPublicKey,
@@ -126,6 +133,10 @@ where
         self.coordinator.best_masks().await
     }
 
+    async fn top_masks(&mut self, limit: usize) -> StorageResult<Vec<(MaskObject, u64)>> {
+        self.coordinator.top_masks(limit).await
+    }
+
     async fn number_of_unique_masks(&mut self) -> StorageResult<u64> {
         self.coordinator.number_of_unique_masks().await
     }
@@ -146,6 +157,29 @@ where
         self.coordinator.latest_global_model_id().await
     }
 
+    async fn add_update_participation(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        round_id: u64,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64> {
+        self.coordinator
+            .add_update_participation(pk, round_id, now, window)
+            .await
+    }
+
+    async fn update_participations(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64> {
+        self.coordinator
+            .update_participations(pk, now, window)
+            .await
+    }
+
     async fn is_ready(&mut self) -> StorageResult<()> {
         self.coordinator.is_ready().await
     }
@@ -169,10 +203,47 @@ where
             .await
     }
 
+    async fn set_canary_global_model(
+        &mut self,
+        round_id: u64,
+        round_seed: &RoundSeed,
+        global_model: &Model,
+    ) -> StorageResult<String> {
+        self.model
+            .set_canary_global_model(round_id, round_seed, global_model)
+            .await
+    }
+
     async fn global_model(&mut self, id: &str) -> StorageResult<Option<Model>> {
         self.model.global_model(id).await
     }
 
+    async fn set_round_archive(
+        &mut self,
+        global_model_id: &str,
+        round_id: u64,
+        archive: Vec<u8>,
+    ) -> StorageResult<()> {
+        self.model
+            .set_round_archive(global_model_id, round_id, archive)
+            .await
+    }
+
+    async fn set_retention_archive(
+        &mut self,
+        data_class: &str,
+        name: &str,
+        archive: Vec<u8>,
+    ) -> StorageResult<()> {
+        self.model
+            .set_retention_archive(data_class, name, archive)
+            .await
+    }
+
+    async fn probe_write(&mut self) -> StorageResult<()> {
+        self.model.probe_write().await
+    }
+
     async fn is_ready(&mut self) -> StorageResult<()> {
         self.model.is_ready().await
     }
@@ -1,7 +1,7 @@
 use crate::{
     state_machine::coordinator::CoordinatorState,
     storage::{
-        coordinator_storage::redis,
+        coordinator_storage::{in_memory, redis},
         model_storage,
         CoordinatorStorage,
         LocalSeedDictAdd,
@@ -30,21 +30,23 @@ use xaynet_core::{
 pub mod utils;
 
 pub async fn init_store() -> impl Storage {
-    let coordinator_store = redis::tests::init_client().await;
+    Store::new(redis::tests::init_client().await, init_model_store().await)
+}
 
-    let model_store = {
-        #[cfg(not(feature = "model-persistence"))]
-        {
-            model_storage::noop::NoOp
-        }
+pub async fn init_in_memory_store() -> impl Storage {
+    Store::new(in_memory::Client::new(), init_model_store().await)
+}
 
-        #[cfg(feature = "model-persistence")]
-        {
-            model_storage::s3::tests::init_client().await
-        }
-    };
+async fn init_model_store() -> impl ModelStorage {
+    #[cfg(not(feature = "model-persistence"))]
+    {
+        model_storage::noop::NoOp
+    }
 
-    Store::new(coordinator_store, model_store)
+    #[cfg(feature = "model-persistence")]
+    {
+        model_storage::s3::tests::init_client().await
+    }
 }
 
 mock! {
@@ -54,6 +56,10 @@ mock! {
     impl CoordinatorStorage for CoordinatorStore {
         async fn set_coordinator_state(&mut self, state: &CoordinatorState) -> StorageResult<()>;
         async fn coordinator_state(&mut self) -> StorageResult<Option<CoordinatorState>>;
+        async fn probe_coordinator_state(
+            &mut self,
+            state: &CoordinatorState,
+        ) -> StorageResult<Option<CoordinatorState>>;
         async fn add_sum_participant(
             &mut self,
             pk: &SumParticipantPublicKey,
@@ -72,11 +78,25 @@ mock! {
             mask: &MaskObject,
         ) -> StorageResult<MaskScoreIncr>;
         async fn best_masks(&mut self) -> StorageResult<Option<Vec<(MaskObject, u64)>>>;
+        async fn top_masks(&mut self, limit: usize) -> StorageResult<Vec<(MaskObject, u64)>>;
         async fn number_of_unique_masks(&mut self) -> StorageResult<u64>;
         async fn delete_coordinator_data(&mut self) -> StorageResult<()>;
         async fn delete_dicts(&mut self) -> StorageResult<()>;
         async fn set_latest_global_model_id(&mut self, id: &str) -> StorageResult<()>;
         async fn latest_global_model_id(&mut self) -> StorageResult<Option<String>>;
+        async fn add_update_participation(
+            &mut self,
+            pk: &UpdateParticipantPublicKey,
+            round_id: u64,
+            now: u64,
+            window: u64,
+        ) -> StorageResult<u64>;
+        async fn update_participations(
+            &mut self,
+            pk: &UpdateParticipantPublicKey,
+            now: u64,
+            window: u64,
+        ) -> StorageResult<u64>;
         async fn is_ready(&mut self) -> StorageResult<()>;
     }
 
@@ -96,7 +116,26 @@ mock! {
             round_seed: &RoundSeed,
             global_model: &Model,
         ) -> StorageResult<String>;
+        async fn set_canary_global_model(
+            &mut self,
+            round_id: u64,
+            round_seed: &RoundSeed,
+            global_model: &Model,
+        ) -> StorageResult<String>;
         async fn global_model(&mut self, id: &str) -> StorageResult<Option<Model>>;
+        async fn set_round_archive(
+            &mut self,
+            global_model_id: &str,
+            round_id: u64,
+            archive: Vec<u8>,
+        ) -> StorageResult<()>;
+        async fn set_retention_archive(
+            &mut self,
+            data_class: &str,
+            name: &str,
+            archive: Vec<u8>,
+        ) -> StorageResult<()>;
+        async fn probe_write(&mut self) -> StorageResult<()>;
         async fn is_ready(&mut self) -> StorageResult<()>;
     }
 
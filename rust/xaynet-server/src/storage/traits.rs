@@ -4,6 +4,7 @@ use async_trait::async_trait;
 use derive_more::Deref;
 use displaydoc::Display;
 use num_enum::TryFromPrimitive;
+use sodiumoxide::crypto::hash::sha256;
 use thiserror::Error;
 
 use crate::state_machine::coordinator::CoordinatorState;
@@ -48,6 +49,21 @@ where
     /// - If a state exists, return `StorageResult::Ok(Some(CoordinatorState))`.
     async fn coordinator_state(&mut self) -> StorageResult<Option<CoordinatorState>>;
 
+    /// Writes a [`CoordinatorState`] to a scratch location, reads it back and deletes it again.
+    ///
+    /// This checks that the storage can store and load a [`CoordinatorState`] without
+    /// touching the actual coordinator state.
+    ///
+    /// # Behavior
+    ///
+    /// - If the state could be written and deleted, return `StorageResult::Ok(Option)`
+    ///   containing the state that was read back.
+    /// - If any of the operations failed, return `StorageResult::Err(error)`.
+    async fn probe_coordinator_state(
+        &mut self,
+        state: &CoordinatorState,
+    ) -> StorageResult<Option<CoordinatorState>>;
+
     /// Adds a sum participant entry to the [`SumDict`].
     ///
     /// # Behavior
@@ -122,6 +138,16 @@ where
     ///   both in descending order `StorageResult::Ok(Option::Some(Vec<(MaskObject, u64)>))`.
     async fn best_masks(&mut self) -> StorageResult<Option<Vec<(MaskObject, u64)>>>;
 
+    /// Returns at most `limit` masks with the highest scores, in descending order of the scores.
+    ///
+    /// # Behavior
+    ///
+    /// - If no masks exist, return an empty `StorageResult::Ok(Vec<(MaskObject, u64)>)`.
+    /// - Masks with the same score are ordered like in [`best_masks`].
+    ///
+    /// [`best_masks`]: CoordinatorStorage::best_masks
+    async fn top_masks(&mut self, limit: usize) -> StorageResult<Vec<(MaskObject, u64)>>;
+
     /// Returns the number of unique masks.
     async fn number_of_unique_masks(&mut self) -> StorageResult<u64>;
 
@@ -149,6 +175,40 @@ where
     /// - If the global model id exists, return `StorageResult::Ok(Some(String)))`.
     async fn latest_global_model_id(&mut self) -> StorageResult<Option<String>>;
 
+    /// Records the participation of the given [`UpdateParticipantPublicKey`] in the round
+    /// `round_id`, at the time `now` (in seconds since the UNIX epoch).
+    ///
+    /// The participations are only kept for the length of the `window` (in seconds) and must not
+    /// be stored beyond it.
+    ///
+    /// # Behavior
+    ///
+    /// - Forget the participations that are older than `now - window`.
+    /// - Record the participation, unless a participation in the same round has already been
+    ///   recorded.
+    /// - Return `StorageResult::Ok(u64)` containing the number of participations within the window.
+    async fn add_update_participation(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        round_id: u64,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64>;
+
+    /// Returns the number of participations of the given [`UpdateParticipantPublicKey`] within
+    /// the `window` (in seconds) that ends at the time `now` (in seconds since the UNIX epoch).
+    ///
+    /// # Behavior
+    ///
+    /// - If no participation has been recorded within the window, return `StorageResult::Ok(0)`.
+    /// - Otherwise, return `StorageResult::Ok(u64)` containing the number of participations.
+    async fn update_participations(
+        &mut self,
+        pk: &UpdateParticipantPublicKey,
+        now: u64,
+        window: u64,
+    ) -> StorageResult<u64>;
+
     /// Checks if the [`CoordinatorStorage`] is ready to process requests.
     ///
     /// # Behavior
@@ -159,6 +219,37 @@ where
     async fn is_ready(&mut self) -> StorageResult<()>;
 }
 
+/// The format of a global model id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalModelIdFormat {
+    /// The hex encoded SHA-256 hash of the serialized global model.
+    ContentHash,
+    /// The `roundid_roundseed` format of the global model ids created by older
+    /// coordinators, where the [`RoundSeed`] is encoded in hexadecimal.
+    Legacy,
+}
+
+impl GlobalModelIdFormat {
+    /// Detects the format of the given global model id.
+    ///
+    /// Returns `None` if the id has neither format.
+    pub fn detect(id: &str) -> Option<Self> {
+        let is_hex = |s: &str, len: usize| {
+            s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        match id.find('_') {
+            None if is_hex(id, 2 * sha256::DIGESTBYTES) => Some(Self::ContentHash),
+            Some(i)
+                if id[..i].parse::<u64>().is_ok()
+                    && is_hex(&id[i + 1..], 2 * RoundSeed::LENGTH) =>
+            {
+                Some(Self::Legacy)
+            }
+            _ => None,
+        }
+    }
+}
+
 #[async_trait]
 /// An abstract model storage.
 pub trait ModelStorage
@@ -169,9 +260,11 @@ where
     ///
     /// # Behavior
     ///
-    /// - If the global model already exists (has the same model id), return
-    ///   `StorageResult::Err(StorageError))`.
+    /// - If the global model already exists (has the same model id), keep the existing
+    ///   global model and return `StorageResult::Ok(String)`.
     /// - If the global model does not exist, set the model and return `StorageResult::Ok(String)`
+    ///
+    /// The returned id is created with [`ModelStorage::create_global_model_id`].
     async fn set_global_model(
         &mut self,
         round_id: u64,
@@ -179,22 +272,90 @@ where
         global_model: &Model,
     ) -> StorageResult<String>;
 
+    /// Sets the global model of a canary round (see [`canary`]).
+    ///
+    /// The model is stored like with [`ModelStorage::set_global_model`], but it is marked as
+    /// the global model of a canary round in its metadata, if the storage supports metadata.
+    ///
+    /// # Behavior
+    ///
+    /// - If the global model already exists (has the same model id), keep the existing
+    ///   global model and its metadata and return `StorageResult::Ok(String)`.
+    /// - If the global model does not exist, set the model and return `StorageResult::Ok(String)`
+    ///
+    /// [`canary`]: crate::state_machine::canary
+    async fn set_canary_global_model(
+        &mut self,
+        round_id: u64,
+        round_seed: &RoundSeed,
+        global_model: &Model,
+    ) -> StorageResult<String> {
+        self.set_global_model(round_id, round_seed, global_model)
+            .await
+    }
+
     /// Returns a global model.
     ///
+    /// The `id` is either a content-addressable id or a legacy id (see
+    /// [`GlobalModelIdFormat`]).
+    ///
     /// # Behavior
     ///
     /// - If the global model does not exist, return `StorageResult::Ok(Option::None)`.
     /// - If the global model exists, return `StorageResult::Ok(Option::Some(Model))`.
     async fn global_model(&mut self, id: &str) -> StorageResult<Option<Model>>;
 
-    /// Creates a unique global model id by using the round id and the round seed in which
-    /// the global model was created.
+    /// Sets the round archive of the global model with the given id (see
+    /// [`round_archive`]).
     ///
-    /// The format of the default implementation is `roundid_roundseed`,
-    /// where the [`RoundSeed`] is encoded in hexadecimal.
-    fn create_global_model_id(round_id: u64, round_seed: &RoundSeed) -> String {
-        let round_seed = hex::encode(round_seed.as_slice());
-        format!("{}_{}", round_id, round_seed)
+    /// # Behavior
+    ///
+    /// - If the archive was set successfully, return `StorageResult::Ok(())`.
+    /// - If a round archive already exists for the global model and the round, it is
+    ///   replaced.
+    ///
+    /// [`round_archive`]: crate::round_archive
+    async fn set_round_archive(
+        &mut self,
+        global_model_id: &str,
+        round_id: u64,
+        archive: Vec<u8>,
+    ) -> StorageResult<()>;
+
+    /// Sets the archive of the expiring records of a data class, before they are purged (see
+    /// [`retention`]). The `name` identifies the archived records within the data class.
+    ///
+    /// # Behavior
+    ///
+    /// - If the archive was set successfully, return `StorageResult::Ok(())`.
+    /// - If an archive with the same name already exists for the data class, it is replaced.
+    /// - If the archive can't be kept, return `StorageResult::Err(error)`, such that the
+    ///   records are not purged.
+    ///
+    /// [`retention`]: crate::retention
+    async fn set_retention_archive(
+        &mut self,
+        data_class: &str,
+        name: &str,
+        archive: Vec<u8>,
+    ) -> StorageResult<()>;
+
+    /// Writes a small object to the storage of the global models and deletes it again.
+    ///
+    /// This checks that the storage accepts writes without creating a global model.
+    ///
+    /// # Behavior
+    ///
+    /// If the object could be written and deleted, return `StorageResult::Ok(())`,
+    /// otherwise return `StorageResult::Err(error)`.
+    async fn probe_write(&mut self) -> StorageResult<()>;
+
+    /// Creates a content-addressable global model id from the serialized global model.
+    ///
+    /// The id is the hex encoded SHA-256 hash of the serialized global model, so that
+    /// identical global models have the same id, whichever round they were created in.
+    fn create_global_model_id(serialized_global_model: &[u8]) -> String {
+        hex::encode(sha256::hash(serialized_global_model))
     }
 
     /// Checks if the [`ModelStorage`] is ready to process requests.
@@ -309,3 +470,57 @@ pub enum MaskScoreIncrError {
     /// sum participant submitted a mask already
     MaskAlreadySubmitted = -2,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{model_storage::noop::NoOp, tests::utils::create_global_model};
+
+    #[test]
+    fn test_detect_global_model_id_format() {
+        let hash = hex::encode([0xab; 32]);
+        assert_eq!(
+            GlobalModelIdFormat::detect(&hash),
+            Some(GlobalModelIdFormat::ContentHash)
+        );
+        let legacy = format!("42_{}", hex::encode(RoundSeed::generate().as_slice()));
+        assert_eq!(
+            GlobalModelIdFormat::detect(&legacy),
+            Some(GlobalModelIdFormat::Legacy)
+        );
+
+        assert_eq!(GlobalModelIdFormat::detect(""), None);
+        assert_eq!(GlobalModelIdFormat::detect(&hash[1..]), None);
+        assert_eq!(GlobalModelIdFormat::detect(&hash.to_uppercase()), None);
+        assert_eq!(GlobalModelIdFormat::detect(&format!("x_{}", hash)), None);
+        assert_eq!(
+            GlobalModelIdFormat::detect(&format!("1_{}", &hash[2..])),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_global_model_id_is_content_addressable() {
+        let mut store = NoOp;
+        let model = create_global_model(10);
+        let id_1 = store
+            .set_global_model(1, &RoundSeed::generate(), &model)
+            .await
+            .unwrap();
+        let id_2 = store
+            .set_global_model(2, &RoundSeed::generate(), &model)
+            .await
+            .unwrap();
+        assert_eq!(id_1, id_2);
+        assert_eq!(
+            GlobalModelIdFormat::detect(&id_1),
+            Some(GlobalModelIdFormat::ContentHash)
+        );
+
+        let other_id = store
+            .set_global_model(1, &RoundSeed::generate(), &create_global_model(11))
+            .await
+            .unwrap();
+        assert_ne!(id_1, other_id);
+    }
+}